@@ -3,6 +3,7 @@ use clap::{Parser, Subcommand};
 use log::LevelFilter;
 use simple_logger::SimpleLogger;
 use std::path::PathBuf;
+use std::process::ExitCode;
 
 mod modules;
 
@@ -24,9 +25,49 @@ mod modules;
     long_about = None,
 )]
 struct Cli {
-    /// 日志级别 [trace|debug|info|warn|error|off]
-    #[arg(short, long, default_value = "info")]
-    log_level: String,
+    /// 日志级别 [trace|debug|info|warn|error|off]，指定时优先于 --quiet/--verbose [默认: info]
+    #[arg(short, long)]
+    log_level: Option<String>,
+    
+    /// 只输出结果: 不输出报告标题和进度日志 (日志级别为warn)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    
+    /// 输出每个步骤的详细信息 (日志级别为debug，报告不省略条目)
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    
+    /// 缓存数据包的内存上限 (例如 512M、2G)，超出后写入临时文件
+    #[arg(long, global = true, value_parser = modules::pcap_packet_store::parse_size)]
+    max_memory: Option<u64>,
+    
+    /// 报告不使用颜色 (输出不是终端或设置了 NO_COLOR 环境变量时自动关闭)
+    #[arg(long, global = true)]
+    no_color: bool,
+    
+    /// 写完输出文件后重新读取并校验包数、字节数、时间戳顺序和内容哈希
+    #[arg(long, global = true)]
+    verify: bool,
+    
+    /// 转换命令输出文件的格式 (auto 按扩展名: .pcapng 为pcapng，其他为PCAP)
+    #[arg(long, global = true, value_enum, default_value_t)]
+    output_format: modules::io::OutputFormat,
+    
+    /// 边写入边压缩输出的PCAP/pcapng文件: gzip 或 zstd，可以加级别，例如 gzip:9、zstd:19
+    #[arg(long, global = true, value_name = "METHOD[:LEVEL]", value_parser = modules::pcap_output::parse_compression)]
+    compress: Option<modules::pcap_output::Compression>,
+    
+    /// 持续读取正在写入的输入文件或命名管道，读到末尾时等待新的数据，按Ctrl-C结束
+    #[arg(long, global = true)]
+    follow: bool,
+    
+    /// 报告和导出文件中时间戳的格式 [默认: epoch，list 命令为 relative]
+    #[arg(long, global = true, value_enum)]
+    time_format: Option<modules::pcap_report::TimeFormat>,
+    
+    /// ISO时间格式使用的时区: local、utc 或 +08:00 形式的偏移 [默认: local]
+    #[arg(long, global = true, allow_hyphen_values = true, value_parser = modules::pcap_report::parse_time_zone)]
+    tz: Option<modules::pcap_report::TimeZone>,
     
     /// 要执行的操作
     #[command(subcommand)]
@@ -37,87 +78,1139 @@ struct Cli {
 enum Commands {
     /// 压缩PCAP文件时间轴
     TimeCompress {
-        /// 输入PCAP文件路径
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 压缩因子 (大于1.0)
+        #[arg(short, long)]
+        factor: f64,
+        
+        /// 保持不变的时间点
+        #[arg(long, value_enum, default_value_t = modules::pcap_time_anchor::TimeAnchor::Start)]
+        anchor: modules::pcap_time_anchor::TimeAnchor,
+    },
+    
+    /// 拉伸PCAP文件时间轴
+    TimeStretch {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 拉伸因子 (大于0.0)
+        #[arg(short, long)]
+        factor: f64,
+        
+        /// 保持不变的时间点
+        #[arg(long, value_enum, default_value_t = modules::pcap_time_anchor::TimeAnchor::Start)]
+        anchor: modules::pcap_time_anchor::TimeAnchor,
+    },
+    
+    /// 稀释PCAP文件 (减少数据包数量)
+    Dilute {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 稀释因子 (大于1的整数)
+        #[arg(short, long)]
+        factor: usize,
+    },
+    
+    /// 增强PCAP文件 (复制数据包)
+    Augment {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 复制倍数 (大于1的整数)
+        #[arg(short, long, required_unless_present = "target_duration", conflicts_with = "target_duration")]
+        factor: Option<usize>,
+        
+        /// 循环输入直到填满目标时长 (如 10m、90s)，每一轮改写IP地址以区分流
+        #[arg(long, value_parser = modules::pcap_interval_stats::parse_interval)]
+        target_duration: Option<u64>,
+    },
+    
+    /// 检测PCAP文件中的乱序数据包
+    DisorderDetect {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 把输入复制为pcapng并为乱序包添加注释 (在Wireshark中查看)
+        #[arg(long)]
+        annotate: Option<PathBuf>,
+    },
+    
+    /// 报告时间窗口内内容相同的重复包 (不修改文件)
+    DetectDupes {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 与之前的相同包时间差不超过该窗口时视为重复 (如 1ms、500us)
+        #[arg(long, default_value = "1ms", value_parser = modules::pcap_interval_stats::parse_interval)]
+        window: u64,
+        
+        /// 忽略IPv4的TTL和头校验和、IPv6的跳数限制 (同一个包在路由器两侧被镜像)
+        #[arg(long)]
+        ignore_ttl: bool,
+        
+        /// 把输入复制为pcapng并为重复包添加注释 (在Wireshark中查看)
+        #[arg(long)]
+        annotate: Option<PathBuf>,
+    },
+    
+    /// 检测抓包端网卡卸载 (TSO/GSO/GRO/LRO、校验和卸载) 留下的痕迹
+    DetectOffload {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 链路MTU，IP包长度超过MTU视为卸载合并的巨型帧
+        #[arg(long, default_value_t = 1500)]
+        mtu: usize,
+    },
+    
+    /// 报告IP长度超过MTU的包 (按VLAN统计并列出涉及的流)
+    CheckMtu {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 没有VLAN标签或未单独指定的VLAN使用的MTU
+        #[arg(long, default_value_t = 1500)]
+        mtu: usize,
+        
+        /// 单独指定某个VLAN的MTU，格式为 VLAN=MTU (可多次指定，例如 --vlan-mtu 100=9000)
+        #[arg(long = "vlan-mtu", value_parser = modules::pcap_mtu_check::parse_vlan_mtu)]
+        vlan_mtus: Vec<(u16, usize)>,
+    },
+    
+    /// 报告TCP连接中抓到完整三次握手的比例 (按客户端/服务端统计)
+    CheckHandshake {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+    },
+    
+    /// 按结束方式 (FIN/FIN、RST、超时、未结束) 对TCP连接分类并列出异常结束的连接
+    CheckTermination {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 空闲超时: 没有FIN或RST的连接在抓包结束前空闲超过该时间视为超时
+        #[arg(long, default_value = "300s", value_parser = modules::pcap_interval_stats::parse_interval)]
+        idle_timeout: u64,
+    },
+    
+    /// 按源地址报告初始TTL分组和系统提示，列出TTL不一致 (NAT或伪造) 的源地址
+    CheckTtl {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+    },
+    
+    /// 报告只抓到一个方向的TCP/UDP流 (有SYN无SYN-ACK、有数据无确认)
+    CheckAsymmetry {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+    },
+    
+    /// 检查改写后的抓包是否自洽 (校验和、TCP序列号、两个方向的五元组)
+    ValidateFlows {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+    },
+    
+    /// 按时间戳重新排序乱序的数据包 (时间戳相同的包保持原有顺序)
+    DisorderFix {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        #[arg(required_unless_present = "dry_run")]
+        output: Option<PathBuf>,
+        
+        /// 只统计需要移动的包数，不写输出文件
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// 比较两个PCAP文件的内容差异
+    Compare {
+        /// 基准文件路径 (PCAP或pcapng，--dir 时为基准目录；使用 --baseline 时为对比文件)
+        reference: PathBuf,
+        
+        /// 对比文件路径 (PCAP或pcapng，--dir 时为对比目录)
+        #[arg(required_unless_present = "baseline", conflicts_with = "baseline")]
+        comparison: Option<PathBuf>,
+        
+        /// 与 `baseline save` 保存的基准比较，只需要给出对比文件
+        #[arg(long, conflicts_with_all = ["dir", "annotate_reference"])]
+        baseline: Option<String>,
+
+        #[arg(long)]
+        ignore_timestamp: bool,
+        
+        /// 只比较应用层载荷，忽略二到四层头部 (适用于中间代理改写头部的场景)
+        #[arg(long)]
+        payload_only: bool,
+        
+        /// 忽略IP包之后的以太网填充和尾部 (如一端抓到FCS而另一端没有)
+        #[arg(long)]
+        strip_padding: bool,
+        
+        /// 把基准文件复制为pcapng并为丢失包添加注释 (在Wireshark中查看)
+        #[arg(long)]
+        annotate_reference: Option<PathBuf>,
+        
+        /// 把对比文件复制为pcapng并为多余包添加注释
+        #[arg(long)]
+        annotate_comparison: Option<PathBuf>,
+        
+        /// 比较两个目录中的抓包分段: 逐对并行比较并输出汇总
+        #[arg(long, conflicts_with_all = ["annotate_reference", "annotate_comparison"])]
+        dir: bool,
+        
+        /// 目录中文件的配对方式: 按文件名或按时间范围
+        #[arg(long, value_enum, default_value_t = modules::pcap_dir_compare::PairBy::Name, requires = "dir")]
+        pair_by: modules::pcap_dir_compare::PairBy,
+    },
+    
+    /// 显示抓包文件的概要: 格式、接口、包数、时间范围和pcapng接口统计块中的丢包计数
+    Info {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+    },
+    
+    /// 按固定时间区间统计包数和字节数
+    Stats {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 统计区间 (如 1s、100ms、0.5)，0表示整个文件为一个区间
+        #[arg(long, default_value = "1s", value_parser = modules::pcap_interval_stats::parse_interval)]
+        intervals: u64,
+        
+        /// 每个过滤表达式统计一列 (可多次指定)，默认统计全部包
+        #[arg(long = "column")]
+        columns: Vec<String>,
+        
+        /// 按 tshark -z io,stat 的格式输出
+        #[arg(long)]
+        wireshark_format: bool,
+        
+        /// 不按区间统计，改为列出相邻包之间最大的时间间隔 (位置和起止时间)
+        #[arg(long, conflicts_with_all = ["columns", "wireshark_format"])]
+        gaps: bool,
+        
+        /// 列出的间隔个数
+        #[arg(long, default_value_t = 10, requires = "gaps")]
+        top: usize,
+        
+        /// 与基准抓包对比统计 (`--diff <基准> <输入>`): 包数、字节数、协议分布和 --intervals 区间的速率分位数的变化
+        #[arg(long, value_name = "REFERENCE", conflicts_with_all = ["columns", "wireshark_format", "gaps"])]
+        diff: Option<PathBuf>,
+    },
+    
+    /// 把抓包中的流导出为NetFlow/IPFIX流记录或Zeek conn.log (别名: export)
+    #[command(alias = "export")]
+    ExportFlows {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径
+        #[arg(short, long)]
+        output: PathBuf,
+        
+        /// 输出格式: IPFIX文件、NetFlow v5字段的CSV，或Zeek conn.log
+        #[arg(long, value_enum, default_value_t = modules::pcap_flow_export::FlowFormat::Ipfix)]
+        format: modules::pcap_flow_export::FlowFormat,
+        
+        /// 活动超时 (秒): 流持续超过该时间时拆分为多条记录 (不用于zeek-conn)
+        #[arg(long, default_value_t = 1800)]
+        active_timeout: u64,
+        
+        /// 空闲超时 (秒): 流空闲超过该时间后的包开始新记录
+        /// [默认: 15，zeek-conn与Zeek相同，TCP为300，其他为60]
+        #[arg(long)]
+        idle_timeout: Option<u64>,
+    },
+    
+    /// 提取Suricata/Zeek告警时间前后的数据包
+    Correlate {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 告警文件 (Suricata eve.json，或Zeek的JSON/TSV日志)
+        #[arg(long)]
+        alerts: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        #[arg(short, long)]
+        output: PathBuf,
+        
+        /// 告警时间前后的时间窗口 (如 50ms、1s)
+        #[arg(long, default_value = "50ms", value_parser = modules::pcap_interval_stats::parse_interval)]
+        window: u64,
+        
+        /// 只提取与告警五元组相同的包
+        #[arg(long)]
+        match_flow: bool,
+    },
+    
+    /// 按流水线配置处理一个PCAP文件，定期保存检查点，中断后可继续
+    Pipeline {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng，检查点保存为 <输出>.checkpoint)
+        output: PathBuf,
+        
+        /// 流水线配置文件路径 (YAML)
+        #[arg(short, long)]
+        pipeline: PathBuf,
+        
+        /// 从上次中断处继续 (跳过已完成的步骤)
+        #[arg(long)]
+        resume: bool,
+        
+        /// 流式步骤每处理多少个包保存一次检查点
+        #[arg(long, default_value_t = 100_000)]
+        checkpoint_interval: u64,
+    },
+    
+    /// 从网络接口实时抓包，经过处理流水线后写入PCAP文件 (原始数据不落盘)
+    Live {
+        /// 网络接口名
+        #[arg(short, long)]
+        interface: String,
+        
+        /// 处理流水线，步骤以 | 分隔: filter <表达式>、snap <字节数>、anonymize [keep-oui] [密钥]
+        #[arg(short, long)]
+        pipeline: Option<String>,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        #[arg(short, long)]
+        write: PathBuf,
+        
+        /// 写入指定数量的包后停止 (默认直到按Ctrl-C)
+        #[arg(short, long)]
+        count: Option<u64>,
+    },
+    
+    /// 监控目录并自动处理新完成的PCAP文件
+    Watch {
+        /// 监控的输入目录
+        #[arg(short, long)]
+        dir: PathBuf,
+        
+        /// 流水线配置文件路径 (YAML)
+        #[arg(short, long)]
+        pipeline: PathBuf,
+        
+        /// 处理结果输出目录
+        #[arg(short, long)]
+        output_dir: PathBuf,
+        
+        /// 轮询间隔 (秒)
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+    
+    /// 以HTTP服务方式提供处理作业接口
+    Serve {
+        /// 监听地址
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        
+        /// 作业结果存放目录
+        #[arg(short, long, default_value = "pcap-editor-jobs")]
+        work_dir: PathBuf,
+        
+        /// 作业输入文件所在的目录，只接受该目录下的文件
+        #[arg(long, default_value = ".")]
+        data_root: PathBuf,
+        
+        /// 同时执行的作业数 (默认为CPU核数)，其余作业排队等待
+        #[arg(long)]
+        job_workers: Option<usize>,
+    },
+    
+    /// 使用外部插件逐包处理PCAP文件
+    Transform {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 插件动态库路径 (导出 pcap_editor_transform)
+        #[arg(short, long)]
+        plugin: PathBuf,
+        
+        /// 插件可写入的最大包长
+        #[arg(long, default_value_t = 65535)]
+        max_packet_size: usize,
+    },
+    
+    /// 以现有流为模板生成新的流 (改写客户端地址/端口)
+    TemplateExpand {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 每个流生成的新流数量
+        #[arg(short = 'k', long)]
+        copies: usize,
+        
+        /// 相邻副本之间的时间偏移 (秒)
+        #[arg(long, default_value_t = 0.001)]
+        time_shift: f64,
+        
+        /// 相邻副本之间客户端地址的增量
+        #[arg(long, default_value_t = 65536)]
+        addr_step: u32,
+        
+        /// 相邻副本之间客户端端口的增量
+        #[arg(long, default_value_t = 1)]
+        port_step: u16,
+    },
+    
+    /// 按到达过程和帧长分布生成合成的UDP流量，或生成一个完整的TCP批量传输会话
+    Generate {
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 生成的包数
+        #[arg(short, long, required_unless_present_any = ["duration", "tcp_bulk"])]
+        count: Option<u64>,
+        
+        /// 生成的时长 (例如 10s、500ms)，与包数同时指定时先达到者为准
+        #[arg(long, value_parser = modules::pcap_interval_stats::parse_interval)]
+        duration: Option<u64>,
+        
+        /// 平均发送速率 (包/秒)，开关过程为开启期间的速率
+        #[arg(short, long, default_value_t = 1000.0)]
+        rate: f64,
+        
+        /// 到达过程
+        #[arg(long, value_enum, default_value_t = modules::pcap_generate::ArrivalProcess::Constant)]
+        arrival: modules::pcap_generate::ArrivalProcess,
+        
+        /// 开关过程中开启期的平均时长
+        #[arg(long, default_value = "100ms", value_parser = modules::pcap_interval_stats::parse_interval)]
+        on_time: u64,
+        
+        /// 开关过程中关闭期的平均时长
+        #[arg(long, default_value = "900ms", value_parser = modules::pcap_interval_stats::parse_interval)]
+        off_time: u64,
+        
+        /// 帧长分布: N、fixed:N、uniform:MIN-MAX、normal:MEAN,STDDEV 或 imix
+        #[arg(long, default_value = "fixed:512", value_parser = modules::pcap_generate::parse_size_distribution)]
+        size: modules::pcap_generate::SizeDistribution,
+        
+        /// 流的数量 (源端口从10000起递增)
+        #[arg(long, default_value_t = 1)]
+        flows: u16,
+        
+        /// 随机数种子
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        
+        /// 第一个包的时间 (epoch秒)
+        #[arg(long, default_value_t = 1_700_000_000.0)]
+        start: f64,
+        
+        /// 生成头部故意错误的畸形包 (IP头长度/总长度错误、重叠分片、非法标志组合等)，用于测试解析器和IDS
+        #[arg(long)]
+        malformed: bool,
+        
+        /// 改为生成传输指定字节数 (例如 10M) 的TCP会话 (握手、数据段、挥手)
+        #[arg(long, value_parser = modules::pcap_packet_store::parse_size,
+              conflicts_with_all = ["count", "duration", "arrival", "size", "flows", "malformed"])]
+        tcp_bulk: Option<u64>,
+        
+        /// TCP会话的最大报文段长度 (用于 --tcp-bulk)
+        #[arg(long, default_value_t = 1460)]
+        mss: u16,
+        
+        /// TCP会话的发送速率 (载荷字节/秒，例如 10M，用于 --tcp-bulk)
+        #[arg(long, default_value = "10M", value_parser = modules::pcap_packet_store::parse_size)]
+        bandwidth: u64,
+        
+        /// TCP会话的往返时延 (用于 --tcp-bulk)
+        #[arg(long, default_value = "10ms", value_parser = modules::pcap_interval_stats::parse_interval)]
+        rtt: u64,
+    },
+    
+    /// 测量本机读取/哈希/解析/写入吞吐量
+    Bench {
+        /// 用于测试的抓包文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 多线程测试使用的线程数 (默认为CPU核数)
+        #[arg(short, long)]
+        threads: Option<usize>,
+        
+        /// 最多加载的数据包数量
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    
+    /// 生成索引文件，加速 extract/show 的随机访问
+    Index {
+        /// 输入PCAP文件路径 (索引写入 <文件名>.idx)
+        input: PathBuf,
+    },
+    
+    /// 按序号、时间范围或所属流提取数据包
+    Extract {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 起始包序号 (从1开始)
+        #[arg(short, long)]
+        start: Option<usize>,
+        
+        /// 最多提取的包数
+        #[arg(short, long)]
+        count: Option<usize>,
+        
+        /// 起始时间 (epoch秒)
+        #[arg(long)]
+        from: Option<f64>,
+        
+        /// 结束时间 (epoch秒)
+        #[arg(long)]
+        to: Option<f64>,
+        
+        /// 只提取与该序号的包属于同一个流的包
+        #[arg(long)]
+        flow_of: Option<usize>,
+        
+        /// 只提取该链路层类型的包 (ethernet/raw/linux-sll/ipv4/ipv6 或数字编号)
+        #[arg(long, value_parser = modules::pcap_packet_parser::parse_linktype)]
+        linktype: Option<u32>,
+    },
+    
+    /// 按过滤表达式和L4载荷内容 (正则表达式或字节序列) 筛选数据包
+    Filter {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 过滤表达式 (如 "tcp.dstport == 80")
+        expression: Option<String>,
+        
+        /// 载荷需要匹配的正则表达式 (如 "Host: .*\.example\.com")
+        #[arg(long)]
+        payload_regex: Option<String>,
+        
+        /// 载荷需要包含的字节序列 (十六进制，如 16030100)
+        #[arg(long)]
+        payload_hex: Option<String>,
+        
+        /// 只输出不满足条件的包
+        #[arg(long)]
+        invert: bool,
+    },
+    
+    /// 只保留每个流前N字节的载荷或前N个包，之后的报文段截断或丢弃
+    TrimFlows {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 每个流每个方向保留的载荷字节数 (例如 4096、64K)
+        #[arg(long, value_parser = modules::pcap_packet_store::parse_size)]
+        bytes: Option<u64>,
+        
+        /// 每个会话 (两个方向合计) 保留的包数
+        #[arg(long)]
+        packets: Option<u64>,
+    },
+    
+    /// 删除不带载荷、只有ACK标志的TCP包 (完成握手的ACK保留)
+    StripAcks {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 每个流每个方向保留每第k个纯ACK
+        #[arg(long)]
+        keep_every: Option<u64>,
+    },
+    
+    /// 删除以太网帧尾的FCS (文件头声明了FCS，或帧尾4字节校验正确时)
+    StripFcs {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 文件头没有声明FCS时也认为每个帧都带FCS，不校验直接删除4字节
+        #[arg(long)]
+        assume_fcs: bool,
+    },
+    
+    /// 在以太网帧尾追加正确计算的FCS (部分重放硬件要求帧带FCS)
+    AddFcs {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+    },
+    
+    /// 计算抓包文件规范化内容的哈希，快速判断两个抓包是否等价
+    Hash {
+        /// 输入文件路径 (PCAP或pcapng，可指定多个)
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        
+        /// 忽略的字段 (逗号分隔): timestamps、ip-id、checksums、ttl、padding、order
+        #[arg(long, value_delimiter = ',')]
+        ignore: Vec<modules::pcap_content_hash::HashIgnore>,
+    },
+    
+    /// 应用一组规范化 (校验和、填充、TTL、IP标识清零，排序，时间平移)，输出便于逐字节比较的文件
+    Normalize {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 应用的步骤 (逗号分隔): checksums、padding、ttl、ip-id、sort、rebase，默认全部
+        #[arg(long, value_delimiter = ',', default_value = "checksums,padding,ttl,ip-id,sort,rebase")]
+        steps: Vec<modules::pcap_normalize::NormalizeStep>,
+    },
+    
+    /// 按映射表改写802.1Q/QinQ标签中的VLAN ID
+    Vlan {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// VLAN映射，格式为 原VLAN=新VLAN (逗号分隔或多次指定，例如 --remap 100=200,110=210)
+        #[arg(long, value_delimiter = ',', value_parser = modules::pcap_vlan_remap::parse_vlan_mapping,
+              required_unless_present = "remap_file")]
+        remap: Vec<(u16, u16)>,
+        
+        /// VLAN映射文件 (每行一项 原VLAN=新VLAN，# 开头为注释)
+        #[arg(long)]
+        remap_file: Option<PathBuf>,
+    },
+    
+    /// 用WPA2-PSK口令解密802.11抓包 (从EAPOL握手推导密钥，只支持CCMP)
+    DecryptWifi {
+        /// 输入文件路径 (PCAP或pcapng，802.11或Radiotap)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 网络名称 (SSID)
+        #[arg(long)]
+        ssid: String,
+        
+        /// 口令 (8-63个字符) 或64位十六进制的PSK
+        #[arg(long)]
+        psk: String,
+    },
+    
+    /// 列出HTTP/1.x事务 (方法、主机、路径、状态码、大小、耗时)
+    Http {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 把全部事务导出到CSV文件
+        #[arg(long)]
+        csv: Option<PathBuf>,
+    },
+    
+    /// 识别SIP呼叫和RTP流，报告丢包、抖动和MOS估计值
+    Voip {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 把每个RTP流导出为单独的PCAP文件的目录
+        #[arg(long)]
+        extract_dir: Option<PathBuf>,
+        
+        /// 把每个呼叫的G.711音频解码为WAV文件的目录
+        #[arg(long)]
+        export_audio: Option<PathBuf>,
+    },
+    
+    /// 按时间关联外部事件日志 (CSV/JSON): 标注匹配的数据包，或提取事件前后的数据包
+    CorrelateLog {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 事件文件 (带表头的CSV、每行一个对象的JSON，或对象数组形式的JSON)
+        #[arg(long)]
+        events: PathBuf,
+        
+        /// 事件时间所在的字段名 (epoch秒、epoch毫秒或ISO 8601时间)
+        #[arg(long, default_value = "timestamp")]
+        key: String,
+        
+        /// 作为标注内容的字段名 (默认使用时间以外的全部字段)
+        #[arg(long)]
+        label: Option<String>,
+        
+        /// 输出文件路径 (标注时为pcapng，提取时为PCAP)
+        #[arg(short, long)]
+        output: PathBuf,
+        
+        /// 事件时间前后的时间窗口 (如 50ms、1s)
+        #[arg(long, default_value = "50ms", value_parser = modules::pcap_interval_stats::parse_interval)]
+        window: u64,
+        
+        /// 只匹配与事件五元组相同的包 (事件带有地址和协议字段时)
+        #[arg(long)]
+        match_flow: bool,
+        
+        /// 只提取时间窗口内的包，而不是标注整个抓包
+        #[arg(long)]
+        extract: bool,
+    },
+    
+    /// 列出每个流的流ID (规范化五元组和首包时间的哈希)，可把流ID作为注释写入每个包 (别名: flows)
+    #[command(alias = "flows")]
+    FlowIds {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出pcapng文件路径，为每个IP包添加注释 flow_id=流ID
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// 模拟整形器: 推迟数据包的时间戳，使任意时间窗口内的比特率不超过上限 (不丢包)
+    Shape {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 比特率上限 (如 100M、1.5G、800k，单位为比特每秒)
+        #[arg(long, value_parser = modules::pcap_shaper::parse_bitrate)]
+        max_bps: u64,
+        
+        /// 滑动窗口的长度 (如 10ms、1s)
+        #[arg(long, default_value = "10ms", value_parser = modules::pcap_interval_stats::parse_interval)]
+        window: u64,
+    },
+    
+    /// 按流轮询交错数据包 (公平队列)，避免一个流连续突发，整体的包速率不变
+    FairQueue {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 每个流每一轮可以发出的字节数 (亏空轮询的配额)
+        #[arg(long, default_value_t = 1514)]
+        quantum: u64,
+    },
+    
+    /// 保存和列出比较用的基准 (参考抓包的哈希清单)
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineAction,
+    },
+    
+    /// 按端口和载荷特征识别每个流的应用协议 (HTTP、TLS、DNS、SSH、QUIC、RTP)，统计各协议的包数和字节数
+    Classify {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+    },
+    
+    /// 列出TLS和QUIC连接的握手信息: 版本、服务器名称 (SNI)、ALPN和QUIC连接ID
+    Tls {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+    },
+    
+    /// 拆分抓包文件: 按抓包接口每个接口输出一个pcapng文件
+    Split {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出目录，文件名为 <输入文件名>_<接口名>.pcapng
+        #[arg(short, long)]
+        output: PathBuf,
+        
+        /// 每个接口描述块 (IDB) 输出一个文件 (还原 dumpcap -i eth0 -i eth1 的多接口抓包)
+        #[arg(long, required = true)]
+        by_interface: bool,
+    },
+    
+    /// 以表格列出数据包 (序号、时间、地址、协议、长度和简要信息，与Wireshark的包列表相同)
+    List {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 只列出匹配过滤表达式的包
+        #[arg(long)]
+        filter: Option<String>,
+        
+        /// 最多列出的包数
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    
+    /// 查找包含字节序列的数据包，列出序号、时间和偏移
+    Search {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 要查找的字节序列 (十六进制，如 474554)
+        #[arg(long, required_unless_present = "text", conflicts_with = "text")]
+        hex: Option<String>,
+        
+        /// 要查找的文本 (如 "GET /login")
+        #[arg(long)]
+        text: Option<String>,
+        
+        /// 在整个帧中查找 (默认只查找L4载荷)
+        #[arg(long)]
+        whole_frame: bool,
+        
+        /// 每个匹配的包前后同时列出的包数
+        #[arg(short = 'C', long, default_value_t = 0)]
+        context: usize,
+        
+        /// 找到这么多个匹配的包后停止
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    
+    /// 显示单个数据包的详细信息和十六进制内容
+    Show {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 数据包序号 (从1开始)
+        number: usize,
+    },
+    
+    /// 按外部时间戳文件重新设置每个包的时间，或把时间戳舍入到固定间隔
+    Retime {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 时间戳文件 (每行一个时间戳，或CSV)
+        #[arg(long, required_unless_present = "quantize", conflicts_with = "quantize")]
+        from_file: Option<PathBuf>,
+        
+        /// CSV中时间戳所在的列 (从1开始)
+        #[arg(long, default_value_t = 1)]
+        column: usize,
+        
+        /// 时间戳为相对首包原始时间的偏移 (秒)
+        #[arg(long, conflicts_with = "quantize")]
+        relative: bool,
+        
+        /// 把时间戳舍入到该间隔的整数倍 (如 1ms、10us)
+        #[arg(long, value_parser = modules::pcap_interval_stats::parse_interval)]
+        quantize: Option<u64>,
+    },
+    
+    /// 线性修正抓包时钟漂移
+    Deskew {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 抓包时钟相对参考时钟快了多少ppm (负数表示偏慢)
+        #[arg(long, allow_hyphen_values = true, conflicts_with = "start_offset")]
+        ppm: Option<f64>,
+        
+        /// 首包处抓包时钟领先参考时钟的秒数
+        #[arg(long, allow_hyphen_values = true, requires = "end_offset")]
+        start_offset: Option<f64>,
+        
+        /// 末包处抓包时钟领先参考时钟的秒数
+        #[arg(long, allow_hyphen_values = true, requires = "start_offset")]
+        end_offset: Option<f64>,
+    },
+    
+    /// 估计两个抓包文件之间的时间偏移并对齐对比文件
+    Sync {
+        /// 基准文件路径 (PCAP或pcapng)
+        reference: PathBuf,
+        
+        /// 对比文件路径 (PCAP或pcapng)
+        comparison: PathBuf,
+        
+        /// 对齐后的对比文件输出路径 (省略时只打印估计结果)
+        output: Option<PathBuf>,
+        
+        /// 同时估计时钟漂移
+        #[arg(long)]
+        skew: bool,
+        
+        /// 只按应用层载荷匹配数据包
+        #[arg(long)]
+        payload_only: bool,
+        
+        /// 匹配时忽略以太网填充和尾部
+        #[arg(long)]
+        strip_padding: bool,
+    },
+    
+    /// 两个抓包点之间的单向时延 (分位数、直方图和按流统计)
+    Latency {
+        /// 上游抓包点 (A) 的抓包文件路径 (PCAP或pcapng)
+        reference: PathBuf,
+        
+        /// 下游抓包点 (B) 的抓包文件路径 (PCAP或pcapng)
+        comparison: PathBuf,
+        
+        /// B端时钟比A端快的秒数，从时延中扣除 (可为负数)
+        #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+        offset: f64,
+        
+        /// 只按应用层载荷匹配数据包
+        #[arg(long)]
+        payload_only: bool,
+        
+        /// 匹配时忽略以太网填充和尾部
+        #[arg(long)]
+        strip_padding: bool,
+    },
+    
+    /// 为指定数据包添加注释 (输出pcapng)
+    Annotate {
+        /// 输入文件路径 (PCAP或pcapng)
         input: PathBuf,
         
-        /// 输出PCAP文件路径
+        /// 输出pcapng文件路径
         output: PathBuf,
         
-        /// 压缩因子 (大于1.0)
-        #[arg(short, long)]
-        factor: f64,
+        /// 数据包序号 (从1开始)
+        #[arg(long)]
+        packet: usize,
+        
+        /// 注释内容
+        #[arg(long)]
+        comment: String,
     },
     
-    /// 拉伸PCAP文件时间轴
-    TimeStretch {
-        /// 输入PCAP文件路径
+    /// 按序号修改指定数据包的字段 (同步更新校验和)
+    Edit {
+        /// 输入文件路径 (PCAP或pcapng)
         input: PathBuf,
         
-        /// 输出PCAP文件路径
+        /// 输出文件路径 (PCAP或pcapng)
         output: PathBuf,
         
-        /// 拉伸因子 (大于0.0)
+        /// 要修改的数据包序号 (从1开始，可重复或逗号分隔)
+        #[arg(long, required = true, value_delimiter = ',')]
+        packet: Vec<usize>,
+        
+        /// 字段赋值，如 ip.ttl=64、tcp.flags=SA (可重复，按顺序执行)
+        #[arg(long = "set", required = true, value_parser = modules::pcap_packet_edit::FieldEdit::parse)]
+        edits: Vec<modules::pcap_packet_edit::FieldEdit>,
+    },
+    
+    /// 交互式查看、过滤和修改抓包 (文件只读取一次)
+    Repl {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+    },
+    
+    /// 提取名称解析数据，可从DNS应答生成名称解析块
+    Names {
+        /// 输入文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 输出pcapng文件路径 (写入名称解析块)
         #[arg(short, long)]
-        factor: f64,
+        output: Option<PathBuf>,
+        
+        /// 从DNS应答中收集地址和名称
+        #[arg(long)]
+        from_dns: bool,
     },
     
-    /// 稀释PCAP文件 (减少数据包数量)
-    Dilute {
-        /// 输入PCAP文件路径
+    /// 把数据包载荷导出为单独的文件
+    ExportPayloads {
+        /// 输入文件路径 (PCAP或pcapng)
         input: PathBuf,
         
-        /// 输出PCAP文件路径
-        output: PathBuf,
+        /// 输出目录
+        output_dir: PathBuf,
         
-        /// 稀释因子 (大于1的整数)
-        #[arg(short, long)]
-        factor: usize,
+        /// 按流重组，每个流的每个方向输出一个文件
+        #[arg(long)]
+        by_flow: bool,
     },
     
-    /// 增强PCAP文件 (复制数据包)
-    Augment {
-        /// 输入PCAP文件路径
+    /// 按CSV规则文件批量改写数据包字段，或做TCP选项等规范化
+    Rewrite {
+        /// 输入文件路径 (PCAP或pcapng)
         input: PathBuf,
         
-        /// 输出PCAP文件路径
+        /// 输出文件路径 (PCAP或pcapng)
         output: PathBuf,
         
-        /// 复制倍数 (大于1的整数)
-        #[arg(short, long)]
-        factor: usize,
+        /// 规则文件 (首行为列名: match,src_ip,dst_ip,src_port,dst_port,ttl,vlan,src_mac,dst_mac)
+        #[arg(long)]
+        rules: Option<PathBuf>,
+        
+        /// 替换网络前缀并保留主机位，格式为 原网段=新网段 (逗号分隔或多次指定，例如 --map-subnet 10.12.0.0/16=172.31.0.0/16)
+        #[arg(long = "map-subnet", value_delimiter = ',', value_parser = modules::pcap_subnet_map::SubnetMapping::parse)]
+        subnet_maps: Vec<modules::pcap_subnet_map::SubnetMapping>,
+        
+        /// 删除TCP选项 (逗号分隔: all/mss/wscale/sack/timestamps/tfo 或类型编号)
+        #[arg(long, value_parser = modules::pcap_tcp_normalize::TcpOptionStrip::parse)]
+        strip_tcp_options: Option<modules::pcap_tcp_normalize::TcpOptionStrip>,
+        
+        /// 把每个TCP流两个方向的序列号/确认号平移到该起始值 (例如0)
+        #[arg(long)]
+        tcp_isn: Option<u32>,
+        
+        /// 把IPv4标识改写为每个流 (按方向) 从1开始递增的序列
+        #[arg(long)]
+        renumber_ip_id: bool,
+        
+        /// 删除IP总长度之后的字节 (以太网短帧的填充)
+        #[arg(long)]
+        strip_padding: bool,
+        
+        /// 把短于60字节的以太网帧补零到60字节 (与 --strip-padding 相反)
+        #[arg(long, conflicts_with = "strip_padding")]
+        pad_frames: bool,
     },
     
-    /// 检测PCAP文件中的乱序数据包
-    DisorderDetect {
-        /// 输入PCAP文件路径
+    /// 剥除隧道封装 (GTP-U) 或802.11头，输出内层数据包
+    Decap {
+        /// 输入文件路径 (PCAP或pcapng)
         input: PathBuf,
+        
+        /// 输出文件路径 (PCAP或pcapng)
+        output: PathBuf,
+        
+        /// 把每个解封装包的TEID导出到CSV文件
+        #[arg(long)]
+        teid_csv: Option<PathBuf>,
+        
+        /// 把802.11数据帧 (可带Radiotap头) 转换为以太网帧
+        #[arg(long)]
+        strip_radiotap: bool,
+        
+        /// 输出的链路层类型 (raw/ipv4/ipv6: 去掉链路层头，只输出IP包)
+        #[arg(long, value_parser = modules::pcap_packet_parser::parse_linktype)]
+        output_linktype: Option<u32>,
     },
     
-    /// 比较两个PCAP文件的内容差异
-    Compare {
-        /// 基准PCAP文件路径
-        reference: PathBuf,
+    /// 合并多个PCAP/pcapng文件为pcapng (每个输入接口保留为独立接口)
+    Merge {
+        /// 输入文件路径
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
         
-        /// 对比PCAP文件路径
-        comparison: PathBuf,
+        /// 输出pcapng文件路径
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    
+    /// 用内置样例验证各命令的输出是确定的 (相同输入和参数得到逐字节相同的输出)
+    SelfTest {
+        /// 与该目录中的基准输出比较，缺少的基准输出写入本次的输出
+        #[arg(long)]
+        golden: Option<PathBuf>,
+        
+        /// 用本次的输出覆盖基准输出
+        #[arg(long, requires = "golden")]
+        update: bool,
+    },
+}
 
+/// `baseline` 的子命令
+#[derive(Subcommand)]
+enum BaselineAction {
+    /// 计算参考抓包的哈希并保存为命名的基准 (存放在 $PCAP_EDITOR_HOME/baselines，默认 ~/.pcap-editor/baselines)
+    Save {
+        /// 参考抓包文件路径 (PCAP或pcapng)
+        input: PathBuf,
+        
+        /// 基准名称 (字母、数字和 -_.)，已存在时覆盖
+        #[arg(long)]
+        name: String,
+        
+        /// 忽略时间戳 (与 compare 的选项相同，比较时沿用)
         #[arg(long)]
         ignore_timestamp: bool,
+        
+        /// 只比较应用层载荷
+        #[arg(long)]
+        payload_only: bool,
+        
+        /// 忽略IP包之后的以太网填充和尾部
+        #[arg(long)]
+        strip_padding: bool,
     },
+    
+    /// 列出已保存的基准
+    List,
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
     let cli = Cli::parse();
     
+    let verbosity = if cli.quiet {
+        modules::pcap_report::Verbosity::Quiet
+    } else if cli.verbose {
+        modules::pcap_report::Verbosity::Verbose
+    } else {
+        modules::pcap_report::Verbosity::Normal
+    };
+    
     // 初始化日志
-    let log_level = match cli.log_level.as_str() {
-        "trace" => LevelFilter::Trace,
-        "debug" => LevelFilter::Debug,
-        "info" => LevelFilter::Info,
-        "warn" => LevelFilter::Warn,
-        "error" => LevelFilter::Error,
-        "off" => LevelFilter::Off,
-        _ => LevelFilter::Info,
+    let log_level = match cli.log_level.as_deref() {
+        Some("trace") => LevelFilter::Trace,
+        Some("debug") => LevelFilter::Debug,
+        Some("info") => LevelFilter::Info,
+        Some("warn") => LevelFilter::Warn,
+        Some("error") => LevelFilter::Error,
+        Some("off") => LevelFilter::Off,
+        Some(_) => LevelFilter::Info,
+        None => match verbosity {
+            modules::pcap_report::Verbosity::Quiet => LevelFilter::Warn,
+            modules::pcap_report::Verbosity::Normal => LevelFilter::Info,
+            modules::pcap_report::Verbosity::Verbose => LevelFilter::Debug,
+        },
     };
     
     SimpleLogger::new()
@@ -125,21 +1218,59 @@ fn main() -> anyhow::Result<()> {
         .init()
         .unwrap();
     
-    // 执行命令
-    match cli.command {
-        Commands::TimeCompress { input, output, factor } => {
+    modules::pcap_packet_store::set_memory_limit(cli.max_memory);
+    modules::pcap_report::init_color(cli.no_color);
+    modules::pcap_report::set_verbosity(verbosity);
+    // 包列表与Wireshark相同，默认显示相对第一个包的时间
+    let default_time_format = match cli.command {
+        Commands::List { .. } => modules::pcap_report::TimeFormat::Relative,
+        _ => modules::pcap_report::TimeFormat::Epoch,
+    };
+    modules::pcap_report::set_time_format(
+        cli.time_format.unwrap_or(default_time_format),
+        cli.tz.unwrap_or(modules::pcap_report::TimeZone::Local)
+    );
+    modules::pcap_verify::set_verify(cli.verify);
+    modules::io::set_output_format(cli.output_format);
+    modules::pcap_output::set_compression(cli.compress.unwrap_or_default());
+    modules::pcap_input::set_follow(cli.follow);
+    
+    let result = modules::pcap_verify::check_compression(cli.compress.unwrap_or_default())
+        .and_then(|()| run(cli.command))
+        .and_then(|()| modules::pcap_input::check_inputs());
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(modules::pcap_error::exit_code(&e))
+        }
+    }
+}
+
+/// 执行命令
+fn run(command: Commands) -> anyhow::Result<()> {
+    match command {
+        Commands::TimeCompress { input, output, factor, anchor } => {
             modules::pcap_time_reducer::pcap_time_compressor(
                 input.to_str().unwrap(),
                 output.to_str().unwrap(),
-                factor
+                factor,
+                anchor,
+                modules::io::resolve_output_format(output.to_str().unwrap()),
+                modules::pcap_output::compression(),
+                None
             )
         },
         
-        Commands::TimeStretch { input, output, factor } => {
+        Commands::TimeStretch { input, output, factor, anchor } => {
             modules::pcap_time_dilator::pcap_time_dilator(
                 input.to_str().unwrap(),
                 output.to_str().unwrap(),
-                factor
+                factor,
+                anchor,
+                modules::io::resolve_output_format(output.to_str().unwrap()),
+                modules::pcap_output::compression(),
+                None
             )
         },
         
@@ -147,30 +1278,527 @@ fn main() -> anyhow::Result<()> {
             modules::pcap_dilute_timed::pcap_dilute_timed(
                 input.to_str().unwrap(),
                 output.to_str().unwrap(),
-                factor
+                factor,
+                modules::io::resolve_output_format(output.to_str().unwrap()),
+                modules::pcap_output::compression()
             )
         },
         
-        Commands::Augment { input, output, factor } => {
-            modules::pcap_augment_timed::pcap_augment_timed(
+        Commands::Augment { input, output, factor, target_duration } => match (factor, target_duration) {
+            (_, Some(target_duration)) => modules::pcap_augment_timed::pcap_augment_duration(
                 input.to_str().unwrap(),
                 output.to_str().unwrap(),
-                factor
-            )
+                target_duration
+            ),
+            (factor, None) => modules::pcap_augment_timed::pcap_augment_timed(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                factor.unwrap_or_default(),
+                modules::io::resolve_output_format(output.to_str().unwrap()),
+                modules::pcap_output::compression()
+            ),
         },
         
-        Commands::DisorderDetect { input } => {
+        Commands::DisorderDetect { input, annotate } => {
             modules::pcap_shuffle_tester::detect_pcap_disorder(
-                input.to_str().unwrap()
+                input.to_str().unwrap(),
+                annotate.as_ref().map(|p| p.to_str().unwrap())
+            )
+        },
+        
+        Commands::DetectDupes { input, window, ignore_ttl, annotate } => {
+            modules::pcap_duplicate_report::detect_duplicates(
+                input.to_str().unwrap(),
+                window,
+                ignore_ttl,
+                annotate.as_ref().map(|p| p.to_str().unwrap())
+            )
+        },
+        
+        Commands::DetectOffload { input, mtu } => {
+            modules::pcap_offload_detect::detect_offload(input.to_str().unwrap(), mtu)
+        },
+        
+        Commands::CheckMtu { input, mtu, vlan_mtus } => {
+            modules::pcap_mtu_check::check_mtu(input.to_str().unwrap(), mtu, &vlan_mtus)
+        },
+        
+        Commands::CheckHandshake { input } => {
+            modules::pcap_handshake_report::check_handshake(input.to_str().unwrap())
+        },
+        
+        Commands::CheckTermination { input, idle_timeout } => {
+            modules::pcap_termination_report::check_termination(input.to_str().unwrap(), idle_timeout)
+        },
+        
+        Commands::CheckTtl { input } => {
+            modules::pcap_ttl_report::check_ttl(input.to_str().unwrap())
+        },
+        
+        Commands::CheckAsymmetry { input } => {
+            modules::pcap_asymmetry_report::check_asymmetry(input.to_str().unwrap())
+        },
+        
+        Commands::ValidateFlows { input } => {
+            modules::pcap_flow_validate::validate_flows(input.to_str().unwrap())
+        },
+        
+        Commands::DisorderFix { input, output, dry_run } => {
+            modules::pcap_disorder_fix::pcap_disorder_fix(
+                input.to_str().unwrap(),
+                output.as_ref().map(|p| p.to_str().unwrap()),
+                dry_run
             )
         },
         
-        Commands::Compare { reference, comparison, ignore_timestamp } => {
+        Commands::Compare { reference, comparison, baseline, ignore_timestamp, payload_only, strip_padding, annotate_reference, annotate_comparison, dir, pair_by } => {
+            let options = modules::pcap_comparative_analyzer::CompareOptions {
+                ignore_timestamp,
+                payload_only,
+                strip_padding,
+            };
+            // 有 --baseline 时 clap 保证没有第二个路径
+            let Some(comparison) = comparison else {
+                return modules::pcap_comparative_analyzer::compare_with_baseline(
+                    baseline.as_deref().unwrap(),
+                    reference.to_str().unwrap(),
+                    options,
+                    annotate_comparison.as_ref().map(|p| p.to_str().unwrap())
+                );
+            };
+            if dir {
+                return modules::pcap_dir_compare::compare_directories(
+                    reference.to_str().unwrap(),
+                    comparison.to_str().unwrap(),
+                    options,
+                    pair_by
+                );
+            }
             modules::pcap_comparative_analyzer::compare_ordered_pcaps(
                 reference.to_str().unwrap(),
                 comparison.to_str().unwrap(),
-                ignore_timestamp  // 传递新参数
+                options,
+                [
+                    annotate_reference.as_ref().map(|p| p.to_str().unwrap()),
+                    annotate_comparison.as_ref().map(|p| p.to_str().unwrap()),
+                ]
+            )
+        },
+        
+        Commands::Info { input } => {
+            modules::pcap_info::pcap_info(input.to_str().unwrap())
+        },
+        
+        Commands::Stats { input, diff: Some(reference), intervals, .. } => {
+            modules::pcap_stats_diff::pcap_stats_diff(reference.to_str().unwrap(), input.to_str().unwrap(), intervals)
+        },
+        
+        Commands::Stats { input, gaps: true, top, .. } => {
+            modules::pcap_interval_stats::pcap_gap_report(input.to_str().unwrap(), top)
+        },
+        
+        Commands::Stats { input, intervals, columns, wireshark_format, .. } => {
+            modules::pcap_interval_stats::pcap_interval_stats(
+                input.to_str().unwrap(),
+                intervals,
+                &columns,
+                wireshark_format
+            )
+        },
+        
+        Commands::ExportFlows { input, output, format, active_timeout, idle_timeout } => {
+            let options = modules::pcap_flow_export::FlowExportOptions {
+                format,
+                active_timeout_ms: active_timeout.saturating_mul(1000),
+                idle_timeout_ms: idle_timeout.map(|seconds| seconds.saturating_mul(1000)),
+            };
+            modules::pcap_flow_export::pcap_export_flows(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                &options
+            )
+        },
+        
+        Commands::Correlate { input, alerts, output, window, match_flow } => {
+            modules::pcap_correlate::pcap_correlate(
+                input.to_str().unwrap(),
+                alerts.to_str().unwrap(),
+                output.to_str().unwrap(),
+                window,
+                match_flow
+            )
+        },
+        
+        Commands::Pipeline { input, output, pipeline, resume, checkpoint_interval } => {
+            let steps = modules::pcap_pipeline::load_pipeline(pipeline.to_str().unwrap())?;
+            let options = modules::pcap_checkpoint::CheckpointOptions { interval: checkpoint_interval, resume };
+            modules::pcap_pipeline::run_pipeline(
+                &steps,
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                modules::io::resolve_output_format(output.to_str().unwrap()),
+                Some(&options)
+            )
+        },
+        
+        Commands::Live { interface, pipeline, write, count } => {
+            let stages = match &pipeline {
+                Some(pipeline) => modules::pcap_live::parse_live_pipeline(pipeline)?,
+                None => Vec::new(),
+            };
+            modules::pcap_live::pcap_live(&interface, &stages, write.to_str().unwrap(), count)
+        },
+        
+        Commands::Watch { dir, pipeline, output_dir, interval } => {
+            modules::pcap_dir_watcher::watch_directory(
+                dir.to_str().unwrap(),
+                pipeline.to_str().unwrap(),
+                output_dir.to_str().unwrap(),
+                interval
+            )
+        },
+        
+        Commands::Serve { listen, work_dir, data_root, job_workers } => {
+            let job_workers = job_workers.unwrap_or_else(|| {
+                std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+            });
+            modules::pcap_http_server::serve(
+                &listen,
+                work_dir.to_str().unwrap(),
+                data_root.to_str().unwrap(),
+                job_workers
+            )
+        },
+        
+        Commands::Transform { input, output, plugin, max_packet_size } => {
+            modules::pcap_plugin_transform::pcap_plugin_transform(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                plugin.to_str().unwrap(),
+                max_packet_size
+            )
+        },
+        
+        Commands::TemplateExpand { input, output, copies, time_shift, addr_step, port_step } => {
+            modules::pcap_template_expand::pcap_template_expand(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                copies,
+                time_shift,
+                addr_step,
+                port_step
+            )
+        },
+        
+        Commands::Generate {
+            output, count, duration, rate, arrival, on_time, off_time, size, flows, seed, start, malformed,
+            tcp_bulk, mss, bandwidth, rtt,
+        } => {
+            let start_us = (start * 1_000_000.0).round() as i64;
+            if let Some(transfer_bytes) = tcp_bulk {
+                let options = modules::pcap_generate::TcpBulkOptions {
+                    transfer_bytes,
+                    mss,
+                    bandwidth,
+                    rtt_us: rtt,
+                    seed,
+                    start_us,
+                };
+                modules::pcap_generate::pcap_generate_tcp_bulk(output.to_str().unwrap(), &options)
+            } else {
+                let options = modules::pcap_generate::GenerateOptions {
+                    count,
+                    duration_us: duration,
+                    rate,
+                    arrival,
+                    on_time_us: on_time,
+                    off_time_us: off_time,
+                    size,
+                    flows,
+                    seed,
+                    start_us,
+                    malformed,
+                };
+                modules::pcap_generate::pcap_generate(output.to_str().unwrap(), &options)
+            }
+        },
+        
+        Commands::Bench { input, threads, limit } => {
+            let threads = threads.unwrap_or_else(|| {
+                std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+            });
+            modules::pcap_benchmark::pcap_benchmark(
+                input.to_str().unwrap(),
+                threads,
+                limit
+            )
+        },
+        Commands::Index { input } => {
+            modules::pcap_index::build_index(input.to_str().unwrap())
+        },
+        Commands::Extract { input, output, start, count, from, to, flow_of, linktype } => {
+            let selection = modules::pcap_packet_extractor::PacketSelection {
+                start,
+                count,
+                from_time: from,
+                to_time: to,
+                flow_of,
+                linktype,
+            };
+            modules::pcap_packet_extractor::pcap_extract(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                &selection
+            )
+        },
+        Commands::Filter { input, output, expression, payload_regex, payload_hex, invert } => {
+            let options = modules::pcap_payload_filter::PayloadFilterOptions {
+                expression,
+                payload_regex,
+                payload_hex,
+                invert,
+            };
+            modules::pcap_payload_filter::pcap_filter_payload(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                &options
+            )
+        },
+        Commands::TrimFlows { input, output, bytes, packets } => {
+            let limits = modules::pcap_flow_trim::TrimLimits { max_bytes: bytes, max_packets: packets };
+            modules::pcap_flow_trim::pcap_trim_flows(input.to_str().unwrap(), output.to_str().unwrap(), limits)
+        },
+        Commands::StripAcks { input, output, keep_every } => {
+            modules::pcap_ack_strip::pcap_strip_acks(input.to_str().unwrap(), output.to_str().unwrap(), keep_every)
+        },
+        Commands::StripFcs { input, output, assume_fcs } => {
+            modules::pcap_fcs::pcap_strip_fcs(input.to_str().unwrap(), output.to_str().unwrap(), assume_fcs)
+        },
+        Commands::AddFcs { input, output } => {
+            modules::pcap_fcs::pcap_add_fcs(input.to_str().unwrap(), output.to_str().unwrap())
+        },
+        Commands::Hash { inputs, ignore } => {
+            for input in &inputs {
+                modules::pcap_content_hash::pcap_content_hash(input.to_str().unwrap(), &ignore)?;
+            }
+            Ok(())
+        },
+        
+        Commands::Normalize { input, output, steps } => {
+            modules::pcap_normalize::pcap_normalize(input.to_str().unwrap(), output.to_str().unwrap(), &steps)
+        },
+        
+        Commands::Vlan { input, output, remap, remap_file } => {
+            modules::pcap_vlan_remap::pcap_vlan_remap(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                &remap,
+                remap_file.as_ref().map(|p| p.to_str().unwrap()),
+            )
+        },
+        
+        Commands::DecryptWifi { input, output, ssid, psk } => {
+            modules::pcap_wifi_decrypt::pcap_decrypt_wifi(input.to_str().unwrap(), output.to_str().unwrap(), &ssid, &psk)
+        },
+        
+        Commands::Http { input, csv } => {
+            modules::pcap_http_report::http_report(
+                input.to_str().unwrap(),
+                csv.as_ref().map(|p| p.to_str().unwrap()),
+            )
+        },
+        
+        Commands::Voip { input, extract_dir, export_audio } => {
+            modules::pcap_voip::voip_report(
+                input.to_str().unwrap(),
+                extract_dir.as_ref().map(|p| p.to_str().unwrap()),
+                export_audio.as_ref().map(|p| p.to_str().unwrap()),
+            )
+        },
+        
+        Commands::CorrelateLog { input, events, key, label, output, window, match_flow, extract } => {
+            modules::pcap_correlate::correlate_log(
+                input.to_str().unwrap(),
+                events.to_str().unwrap(),
+                output.to_str().unwrap(),
+                &modules::pcap_correlate::EventLogOptions {
+                    key: &key,
+                    label: label.as_deref(),
+                    window_us: window,
+                    match_flow,
+                    extract,
+                }
+            )
+        },
+        
+        Commands::FlowIds { input, output } => {
+            modules::pcap_flow_id::pcap_flow_ids(
+                input.to_str().unwrap(),
+                output.as_ref().map(|p| p.to_str().unwrap())
+            )
+        },
+        
+        Commands::Shape { input, output, max_bps, window } => {
+            modules::pcap_shaper::pcap_shape(input.to_str().unwrap(), output.to_str().unwrap(), max_bps, window)
+        },
+        
+        Commands::FairQueue { input, output, quantum } => {
+            modules::pcap_fair_queue::pcap_fair_queue(input.to_str().unwrap(), output.to_str().unwrap(), quantum)
+        },
+        
+        Commands::Baseline { action: BaselineAction::Save { input, name, ignore_timestamp, payload_only, strip_padding } } => {
+            let options = modules::pcap_comparative_analyzer::CompareOptions {
+                ignore_timestamp,
+                payload_only,
+                strip_padding,
+            };
+            modules::pcap_baseline::save_baseline(input.to_str().unwrap(), &name, options)
+        },
+        
+        Commands::Baseline { action: BaselineAction::List } => {
+            modules::pcap_baseline::list_baselines()
+        },
+        
+        Commands::Classify { input } => {
+            modules::pcap_classify::classify_flows(input.to_str().unwrap())
+        },
+        
+        Commands::Tls { input } => {
+            modules::pcap_tls_report::tls_report(input.to_str().unwrap())
+        },
+        
+        Commands::Split { input, output, .. } => {
+            modules::pcap_split::pcap_split_by_interface(
+                input.to_str().unwrap(),
+                output.to_str().unwrap()
+            )
+        },
+        
+        Commands::List { input, filter, limit } => {
+            modules::pcap_packet_list::pcap_list(input.to_str().unwrap(), filter.as_deref(), limit)
+        },
+        Commands::Search { input, hex, text, whole_frame, context, limit } => {
+            let options = modules::pcap_search::SearchOptions { hex, text, whole_frame, context, limit };
+            modules::pcap_search::pcap_search(input.to_str().unwrap(), &options)
+        },
+        Commands::Show { input, number } => {
+            modules::pcap_packet_extractor::pcap_show(input.to_str().unwrap(), number)
+        },
+        Commands::Retime { input, output, from_file, column, relative, quantize } => match quantize {
+            Some(grid_us) => {
+                modules::pcap_retime::pcap_quantize(input.to_str().unwrap(), output.to_str().unwrap(), grid_us)
+            }
+            None => modules::pcap_retime::pcap_retime_from_file(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                from_file.unwrap_or_default().to_str().unwrap(),
+                column,
+                relative
+            ),
+        },
+        Commands::Deskew { input, output, ppm, start_offset, end_offset } => {
+            modules::pcap_retime::pcap_deskew(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                ppm,
+                start_offset.zip(end_offset)
+            )
+        },
+        Commands::Sync { reference, comparison, output, skew, payload_only, strip_padding } => {
+            let options = modules::pcap_comparative_analyzer::CompareOptions {
+                ignore_timestamp: false,
+                payload_only,
+                strip_padding,
+            };
+            modules::pcap_time_sync::pcap_time_sync(
+                reference.to_str().unwrap(),
+                comparison.to_str().unwrap(),
+                output.as_ref().map(|p| p.to_str().unwrap()),
+                skew,
+                options
+            )
+        },
+        Commands::Latency { reference, comparison, offset, payload_only, strip_padding } => {
+            let options = modules::pcap_comparative_analyzer::CompareOptions {
+                ignore_timestamp: false,
+                payload_only,
+                strip_padding,
+            };
+            modules::pcap_latency::pcap_latency(
+                reference.to_str().unwrap(),
+                comparison.to_str().unwrap(),
+                (offset * 1_000_000.0).round() as i64,
+                options
+            )
+        },
+        Commands::Annotate { input, output, packet, comment } => {
+            modules::pcap_annotate::pcap_annotate(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                packet,
+                &comment
+            )
+        },
+        Commands::Edit { input, output, packet, edits } => {
+            modules::pcap_packet_edit::pcap_edit(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                &packet,
+                &edits
+            )
+        },
+        Commands::Repl { input } => {
+            modules::pcap_repl::pcap_repl(input.to_str().unwrap())
+        },
+        Commands::Names { input, output, from_dns } => {
+            modules::pcap_name_resolution::pcap_names(
+                input.to_str().unwrap(),
+                output.as_ref().map(|p| p.to_str().unwrap()),
+                from_dns
+            )
+        },
+        Commands::ExportPayloads { input, output_dir, by_flow } => {
+            modules::pcap_payload_export::pcap_export_payloads(
+                input.to_str().unwrap(),
+                output_dir.to_str().unwrap(),
+                by_flow
+            )
+        },
+        Commands::Rewrite { input, output, rules, subnet_maps, strip_tcp_options, tcp_isn, renumber_ip_id, strip_padding, pad_frames } => {
+            let padding = match (strip_padding, pad_frames) {
+                (true, _) => Some(modules::pcap_padding::PaddingAction::Strip),
+                (_, true) => Some(modules::pcap_padding::PaddingAction::Pad),
+                _ => None,
+            };
+            let options = modules::pcap_rule_rewrite::RewriteOptions {
+                subnet_maps,
+                strip_tcp_options,
+                tcp_isn,
+                renumber_ip_id,
+                padding,
+            };
+            modules::pcap_rule_rewrite::pcap_rewrite(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                rules.as_ref().map(|p| p.to_str().unwrap()),
+                &options
+            )
+        },
+        Commands::Decap { input, output, teid_csv, strip_radiotap, output_linktype } => {
+            let options = modules::pcap_decap::DecapOptions { strip_radiotap, output_linktype };
+            modules::pcap_decap::pcap_decap(
+                input.to_str().unwrap(),
+                output.to_str().unwrap(),
+                teid_csv.as_ref().map(|p| p.to_str().unwrap()),
+                &options
             )
         },
+        Commands::Merge { inputs, output } => {
+            let inputs: Vec<&str> = inputs.iter().map(|p| p.to_str().unwrap()).collect();
+            modules::pcap_merge::pcap_merge(output.to_str().unwrap(), &inputs)
+        },
+        Commands::SelfTest { golden, update } => {
+            modules::pcap_self_test::pcap_self_test(golden.as_ref().map(|p| p.to_str().unwrap()), update)
+        },
     }
 }
\ No newline at end of file
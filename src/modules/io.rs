@@ -0,0 +1,307 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use pcap_file::{Packet, PcapReader, PcapWriter};
+use pcap_file::packet::PacketHeader;
+use pcap_file::pcap_header::PcapHeader;
+use anyhow::Result;
+use log::warn;
+
+use crate::modules::pcap_error::{PCAP_HEADER_LEN, PCAP_RECORD_HEADER_LEN, PcapError};
+use crate::modules::pcap_input::{Input, open_input};
+use crate::modules::pcap_pcapng::{
+    BLOCK_SECTION_HEADER, InterfaceDescription, PcapngBlock, PcapngOption, PcapngPacket, PcapngReader, PcapngWriter,
+    SHB_USERAPPL, interface_for_pcap,
+};
+use crate::modules::pcap_time_anchor::ticks_per_second;
+
+/// 输出文件的格式 (`--output-format` 的取值)
+static OUTPUT_FORMAT: AtomicU8 = AtomicU8::new(OutputFormat::Auto as u8);
+
+/// 纳秒精度的PCAP文件的magic (字节序与 `PcapHeader::with_datalink` 相同)
+const PCAP_MAGIC_NANOSECOND: u32 = 0xa1b23c4d;
+
+/// 转换命令输出文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// 按输出文件的扩展名: `.pcapng` 为pcapng，其他为PCAP
+    #[default]
+    Auto,
+    Pcap,
+    Pcapng,
+}
+
+/// 设置输出文件的格式
+pub fn set_output_format(format: OutputFormat) {
+    OUTPUT_FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// `--output-format` 指定的输出格式
+pub fn output_format() -> OutputFormat {
+    match OUTPUT_FORMAT.load(Ordering::Relaxed) {
+        0 => OutputFormat::Auto,
+        1 => OutputFormat::Pcap,
+        _ => OutputFormat::Pcapng,
+    }
+}
+
+/// 输出文件实际使用的格式 (`Auto` 按扩展名确定为 `Pcap` 或 `Pcapng`，忽略最后的 `.gz`、`.zst`)
+pub fn resolve_output_format(path: &str) -> OutputFormat {
+    match output_format() {
+        OutputFormat::Auto => {
+            let mut path = Path::new(path);
+            if let Some(stem) = path.file_stem()
+                && path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("gz") || extension.eq_ignore_ascii_case("zst"))
+            {
+                path = Path::new(stem);
+            }
+            let pcapng = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pcapng"));
+            if pcapng { OutputFormat::Pcapng } else { OutputFormat::Pcap }
+        }
+        format => format,
+    }
+}
+
+/// 按PCAP数据包逐个读取PCAP或pcapng文件 (自动识别格式)
+///
+/// 只处理PCAP数据包的命令都通过它读取输入，pcapng文件表现为一个PCAP文件:
+/// 1. 文件头由第一个接口的链路类型和抓包长度生成；第一个接口的时间戳精度 (if_tsresol) 高于微秒时
+///    表现为纳秒精度的PCAP文件 (magic 0xa1b23c4d)，否则时间戳为微秒
+/// 2. 链路类型与第一个接口不同的接口上的包跳过，并对每个这样的接口给出一次警告
+/// 3. 包的注释等选项、统计块和名称解析块不保留；需要保留时使用 [`crate::modules::pcap_pcapng::CaptureReader`]
+///
+/// 与 `pcap_file::PcapReader` 相同，读取出错时结束迭代 (pcapng给出警告)，
+/// 需要报错的命令在读完之后调用 [`PacketReader::check_complete`]。
+pub struct PacketReader {
+    pub header: PcapHeader,
+    path: String,
+    source: Source,
+    /// 已读取的字节数 (PCAP)
+    read_bytes: u64,
+    /// 读取pcapng时遇到的错误
+    error: Option<String>,
+    /// 输入是否为gzip压缩文件
+    compressed: bool,
+}
+
+enum Source {
+    Pcap(PcapReader<BufReader<Input>>),
+    Pcapng {
+        reader: PcapngReader<BufReader<Input>>,
+        /// 已警告过的不同链路类型的接口 (节序号, 接口ID)
+        skipped: BTreeSet<(usize, u32)>,
+    },
+}
+
+impl PacketReader {
+    /// 打开PCAP或pcapng文件 (也可以是远程地址，只读取一遍)
+    pub fn open(path: &str) -> Result<PacketReader> {
+        let input = open_input(path)?;
+        let compressed = input.decompressed_len().is_some();
+        let mut file = BufReader::new(input);
+        let head = file.fill_buf().map_err(|e| PcapError::open_failed(path, e))?;
+        let pcapng = head.len() >= 4 && u32::from_le_bytes(head[0..4].try_into().unwrap()) == BLOCK_SECTION_HEADER;
+        if !pcapng {
+            let reader = PcapReader::new(file)
+                .map_err(PcapError::invalid_pcap)?;
+            return Ok(PacketReader {
+                header: reader.header,
+                path: path.to_string(),
+                source: Source::Pcap(reader),
+                read_bytes: PCAP_HEADER_LEN,
+                error: None,
+                compressed,
+            });
+        }
+
+        // 读到第一个接口描述块为止，之前只能有节头块
+        let mut reader = PcapngReader::new(file);
+        let interface = loop {
+            match reader.next_block()? {
+                Some(PcapngBlock::Interface(interface)) => break interface,
+                Some(PcapngBlock::Packet(_)) => {
+                    anyhow::bail!(PcapError::InvalidFormat(format!("数据包之前没有接口描述块: {}", path)))
+                }
+                Some(_) => {}
+                None => anyhow::bail!(PcapError::InvalidFormat(format!("pcapng文件中没有接口: {}", path))),
+            }
+        };
+        let mut header = PcapHeader::with_datalink((interface.linktype as u32).into());
+        if interface.snaplen > 0 {
+            header.snaplen = interface.snaplen;
+        }
+        if interface.units_per_second() > 1_000_000 {
+            header.magic_number = PCAP_MAGIC_NANOSECOND;
+        }
+        Ok(PacketReader {
+            header,
+            path: path.to_string(),
+            source: Source::Pcapng { reader, skipped: BTreeSet::new() },
+            read_bytes: 0,
+            error: None,
+            compressed,
+        })
+    }
+
+    /// 输入是否为pcapng文件
+    pub fn is_pcapng(&self) -> bool {
+        matches!(self.source, Source::Pcapng { .. })
+    }
+
+    /// 输入是否为gzip压缩文件 (与pcapng一样只能顺序读取)
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// 检查是否读到了文件末尾 (没有因为文件被截断或损坏而提前结束)
+    pub fn check_complete(&self) -> Result<(), PcapError> {
+        match (&self.source, &self.error) {
+            (Source::Pcap(reader), _) => reader.get_ref().get_ref().check_read(&self.path, self.read_bytes),
+            (Source::Pcapng { .. }, Some(error)) => {
+                Err(PcapError::TruncatedFile(format!("输入文件被截断或损坏: {}: {}", self.path, error)))
+            }
+            (Source::Pcapng { .. }, None) => Ok(()),
+        }
+    }
+}
+
+impl Iterator for PacketReader {
+    type Item = Packet<'static>;
+
+    fn next(&mut self) -> Option<Packet<'static>> {
+        let linktype: u32 = self.header.datalink.into();
+        let ticks_per_second = ticks_per_second(&self.header);
+        let path = &self.path;
+        match &mut self.source {
+            Source::Pcap(reader) => {
+                let packet = reader.next()?;
+                self.read_bytes += PCAP_RECORD_HEADER_LEN + packet.data.len() as u64;
+                Some(packet)
+            }
+            Source::Pcapng { reader, skipped } => loop {
+                let packet = match reader.next_block() {
+                    Ok(Some(PcapngBlock::Packet(packet))) => packet,
+                    Ok(Some(_)) => continue,
+                    Ok(None) => return None,
+                    Err(e) => {
+                        warn!("读取pcapng文件失败，之后的数据包被忽略: {}: {:#}", path, e);
+                        self.error = Some(format!("{:#}", e));
+                        return None;
+                    }
+                };
+                let Some(interface) = reader.interfaces.get(packet.interface_id as usize) else {
+                    warn!("数据包引用了不存在的接口 {}，之后的数据包被忽略: {}", packet.interface_id, path);
+                    self.error = Some(format!("数据包引用了不存在的接口: {}", packet.interface_id));
+                    return None;
+                };
+                if interface.linktype as u32 != linktype {
+                    if skipped.insert((reader.sections, packet.interface_id)) {
+                        warn!(
+                            "接口 {} 的链路类型 {} 与第一个接口 ({}) 不同，跳过该接口的包: {}",
+                            packet.interface_id, interface.linktype, linktype, path
+                        );
+                    }
+                    continue;
+                }
+                return Some(to_pcap_packet(interface, packet.timestamp, packet.orig_len, packet.data, ticks_per_second));
+            },
+        }
+    }
+}
+
+/// pcapng数据包转换为PCAP数据包，时间戳精度为 `ticks_per_second` (微秒或纳秒)；
+/// 早于1970年或晚于2106年的时间戳截断到PCAP的范围
+fn to_pcap_packet(
+    interface: &InterfaceDescription,
+    timestamp: u64,
+    orig_len: u32,
+    data: Vec<u8>,
+    ticks_per_second: i64,
+) -> Packet<'static> {
+    let ticks_per_second = ticks_per_second as i128;
+    let ticks = interface
+        .timestamp_ns(timestamp)
+        .div_euclid(1_000_000_000 / ticks_per_second)
+        .clamp(0, u32::MAX as i128 * ticks_per_second + ticks_per_second - 1);
+    Packet {
+        header: PacketHeader {
+            ts_sec: (ticks / ticks_per_second) as u32,
+            ts_usec: (ticks % ticks_per_second) as u32,
+            incl_len: data.len() as u32,
+            orig_len: orig_len.max(data.len() as u32),
+        },
+        data: Cow::Owned(data),
+    }
+}
+
+/// 按 `--output-format` 把PCAP数据包写为PCAP或pcapng文件
+///
+/// 只产生PCAP数据包的转换命令都通过它写出输出。写为pcapng时:
+/// 1. 节头块带 shb_userappl，唯一的接口描述块由PCAP文件头生成 (纳秒精度的文件带上 if_tsresol)
+/// 2. 数据包写为增强包块，时间戳的精度与PCAP文件头相同
+pub struct PacketWriter<W: Write> {
+    sink: Sink<W>,
+}
+
+enum Sink<W: Write> {
+    Pcap(PcapWriter<W>),
+    Pcapng {
+        writer: PcapngWriter<W>,
+        /// 每秒的时间戳单位数
+        units_per_second: u64,
+    },
+}
+
+impl<W: Write> PacketWriter<W> {
+    /// 创建写入器并写出文件头，`path` 为输出文件路径 (用于按扩展名选择格式)
+    pub fn with_header(header: PcapHeader, writer: W, path: &str) -> Result<PacketWriter<W>> {
+        PacketWriter::with_format(header, writer, resolve_output_format(path))
+    }
+
+    /// 按指定的格式创建写入器并写出文件头 (与输出文件的扩展名无关，`Auto` 写出PCAP)
+    pub fn with_format(header: PcapHeader, writer: W, format: OutputFormat) -> Result<PacketWriter<W>> {
+        if format != OutputFormat::Pcapng {
+            let pcap_writer = PcapWriter::with_header(header, writer)
+                .map_err(|e| PcapError::WriteFailure(e.to_string()))?;
+            return Ok(PacketWriter { sink: Sink::Pcap(pcap_writer) });
+        }
+        let mut pcapng_writer = PcapngWriter::new(writer, &[PcapngOption::string(SHB_USERAPPL, "pcap-editor")])?;
+        pcapng_writer.write_interface(&interface_for_pcap(&header))?;
+        let units_per_second = ticks_per_second(&header) as u64;
+        Ok(PacketWriter { sink: Sink::Pcapng { writer: pcapng_writer, units_per_second } })
+    }
+
+    pub fn write_packet(&mut self, packet: &Packet) -> Result<()> {
+        match &mut self.sink {
+            Sink::Pcap(writer) => {
+                writer.write_packet(packet).map_err(|e| PcapError::WriteFailure(e.to_string()))?;
+                Ok(())
+            }
+            Sink::Pcapng { writer, units_per_second } => writer.write_packet(&PcapngPacket {
+                interface_id: 0,
+                timestamp: packet.header.ts_sec as u64 * *units_per_second + packet.header.ts_usec as u64,
+                orig_len: packet.header.orig_len,
+                data: packet.data.to_vec(),
+                options: Vec::new(),
+            }),
+        }
+    }
+
+    /// 底层的写入目标 (写入器本身不缓冲)
+    pub fn get_mut(&mut self) -> &mut W {
+        match &mut self.sink {
+            Sink::Pcap(writer) => writer.get_mut(),
+            Sink::Pcapng { writer, .. } => writer.get_mut(),
+        }
+    }
+
+    /// 取出底层的写入目标 (例如用于结束压缩的输出)
+    pub fn into_inner(self) -> W {
+        match self.sink {
+            Sink::Pcap(writer) => writer.into_writer(),
+            Sink::Pcapng { writer, .. } => writer.into_inner(),
+        }
+    }
+}
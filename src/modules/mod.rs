@@ -1,6 +1,92 @@
+pub mod io;
+pub mod pcap_ack_strip;
+pub mod pcap_annotate;
+pub mod pcap_anonymize;
+pub mod pcap_asymmetry_report;
 pub mod pcap_augment_timed;
+pub mod pcap_baseline;
+pub mod pcap_benchmark;
+pub mod pcap_byte_regex;
+pub mod pcap_checkpoint;
+pub mod pcap_classify;
 pub mod pcap_comparative_analyzer;
+pub mod pcap_content_hash;
+pub mod pcap_correlate;
+pub mod pcap_decap;
 pub mod pcap_dilute_timed;
+pub mod pcap_dir_compare;
+pub mod pcap_dir_watcher;
+pub mod pcap_disorder_fix;
+pub mod pcap_duplicate_report;
+pub mod pcap_error;
+pub mod pcap_fair_queue;
+pub mod pcap_fcs;
+pub mod pcap_filter;
+pub mod pcap_flow_export;
+pub mod pcap_flow_id;
+pub mod pcap_flow_trim;
+pub mod pcap_flow_validate;
+pub mod pcap_generate;
+pub mod pcap_gzip;
+pub mod pcap_handshake_report;
+pub mod pcap_http_report;
+pub mod pcap_http_server;
+pub mod pcap_index;
+pub mod pcap_info;
+pub mod pcap_input;
+pub mod pcap_interval_stats;
+pub mod pcap_ip_normalize;
+pub mod pcap_json;
+pub mod pcap_latency;
+pub mod pcap_live;
+pub mod pcap_lz77;
+pub mod pcap_malformed;
+pub mod pcap_merge;
+pub mod pcap_mtu_check;
+pub mod pcap_name_resolution;
+pub mod pcap_normalize;
+pub mod pcap_offload_detect;
+pub mod pcap_output;
+pub mod pcap_packet_builder;
+pub mod pcap_packet_edit;
+pub mod pcap_packet_extractor;
+pub mod pcap_packet_list;
+pub mod pcap_packet_parser;
+pub mod pcap_packet_store;
+pub mod pcap_padding;
+pub mod pcap_payload_export;
+pub mod pcap_payload_filter;
+pub mod pcap_pcapng;
+pub mod pcap_pipeline;
+pub mod pcap_plugin_transform;
+pub mod pcap_quic;
+pub mod pcap_repl;
+pub mod pcap_report;
+pub mod pcap_retime;
+pub mod pcap_rule_rewrite;
+pub mod pcap_search;
+pub mod pcap_self_test;
+pub mod pcap_sequence_diff;
+pub mod pcap_shaper;
 pub mod pcap_shuffle_tester;
+pub mod pcap_split;
+pub mod pcap_stats_diff;
+pub mod pcap_subnet_map;
+pub mod pcap_tcp_normalize;
+pub mod pcap_tcp_reassembly;
+pub mod pcap_template_expand;
+pub mod pcap_termination_report;
+pub mod pcap_time_anchor;
 pub mod pcap_time_dilator;
-pub mod pcap_time_reducer;
\ No newline at end of file
+pub mod pcap_time_reducer;
+pub mod pcap_time_sync;
+pub mod pcap_tls;
+pub mod pcap_tls_report;
+pub mod pcap_ttl_report;
+pub mod pcap_verify;
+pub mod pcap_vlan_remap;
+pub mod pcap_voip;
+pub mod pcap_wifi_crypto;
+pub mod pcap_wifi_decrypt;
+pub mod pcap_zeek_conn;
+pub mod pcap_zstd;
\ No newline at end of file
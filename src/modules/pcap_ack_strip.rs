@@ -0,0 +1,82 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::Result;
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{FlowKey, Transport, parse_packet};
+use crate::modules::pcap_verify::OutputDigest;
+
+const TCP_SYN: u8 = 0x02;
+const TCP_ACK: u8 = 0x10;
+
+/// 删除不带载荷、只有ACK标志的TCP包 (纯ACK)
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `keep_every`: 每个流每个方向保留第k、2k、3k...个纯ACK (None表示全部删除)
+///
+/// # 说明
+/// 1. 纯ACK指标志字节恰好为ACK (带PSH、ECE、CWR等其他标志的不算) 且IP头中的长度表明没有载荷的TCP包
+/// 2. 完成三次握手的ACK (SYN-ACK之后反方向的第一个纯ACK) 总是保留，`check-handshake` 等仍能识别完整握手
+/// 3. 重复ACK和窗口更新同样会被删除，需要分析快速重传或零窗口时不宜使用
+pub fn pcap_strip_acks(input_path: &str, output_path: &str, keep_every: Option<u64>) -> Result<()> {
+    if keep_every == Some(0) {
+        anyhow::bail!(PcapError::InvalidArgument("--keep-every 必须大于0".to_string()));
+    }
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    // 等待握手ACK的方向 (SYN-ACK的反方向)
+    let mut pending_handshake: HashSet<FlowKey> = HashSet::new();
+    // 流 (区分方向) -> 已出现的纯ACK数 (不含握手ACK)
+    let mut ack_counts: HashMap<FlowKey, u64> = HashMap::new();
+    let mut packet_count = 0;
+    let mut written = 0;
+    let mut removed = 0;
+    let mut removed_bytes = 0u64;
+    for packet in pcap_reader.by_ref() {
+        packet_count += 1;
+        let data = &packet.data;
+        let parsed = parse_packet(data, linktype);
+        if let (Some(key), Some(ip), Some(Transport::Tcp(tcp))) = (parsed.flow_key(), &parsed.ip, parsed.transport)
+            && let Some(&flags) = data.get(tcp.offset + 13)
+        {
+            if flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK {
+                pending_handshake.insert(key.reversed());
+            }
+            // 总长度为0 (TSO) 时按抓到的长度计算载荷
+            let end = if ip.total_len == 0 { data.len() } else { ip.end() };
+            let bare_ack = flags == TCP_ACK && end <= tcp.offset + tcp.header_len;
+            if bare_ack && !pending_handshake.remove(&key) {
+                let count = ack_counts.entry(key).or_default();
+                *count += 1;
+                if keep_every.is_none_or(|k| !count.is_multiple_of(k)) {
+                    removed += 1;
+                    removed_bytes += data.len() as u64;
+                    continue;
+                }
+            }
+        }
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&packet);
+        written += 1;
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功删除纯ACK: 输入包数={}, 输出包数={}, 删除包数={}, 减少包数据 {} 字节, 输出文件={}",
+        packet_count, written, removed, removed_bytes, output_path
+    );
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{SystemTime, UNIX_EPOCH};
+use seahash::hash_seeded;
+
+use crate::modules::pcap_packet_parser::{LINKTYPE_ETHERNET, ParsedPacket, set_ip_addresses};
+
+/// 地址匿名化: 用带密钥的哈希把以太网头中的MAC地址和IP头中的地址替换为假名地址
+///
+/// 相同的密钥下同一个地址总是映射为同一个假名地址，因此会话关系保持不变；
+/// 不同地址映射到同一个假名地址的概率很低，但不为零。
+/// 未指定的地址、回环地址、广播和组播地址保持不变，IP地址改写后同步更新校验和。
+/// 载荷 (包括ARP、DNS等载荷中的地址) 不修改。
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    key: u64,
+    /// 保留全球唯一MAC地址的OUI (前3字节)，只替换后3字节
+    keep_oui: bool,
+}
+
+impl Anonymizer {
+    pub fn new(key: u64) -> Anonymizer {
+        Anonymizer { key, keep_oui: false }
+    }
+
+    /// 保留MAC地址的厂商前缀 (OUI)，设备数和厂商分布仍可分析
+    ///
+    /// 本地管理的地址 (如手机的随机MAC) 前3字节不代表厂商，仍然整体替换。
+    /// 同一OUI下不同地址的后3字节只有24位，设备很多时冲突的概率不可忽略 (约 n²/2²⁵)。
+    pub fn keep_oui(mut self) -> Anonymizer {
+        self.keep_oui = true;
+        self
+    }
+
+    /// 使用随机密钥 (每次运行的映射不同)
+    pub fn random() -> Anonymizer {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+        Anonymizer::new(hash_seeded(&now.to_le_bytes(), std::process::id() as u64, 0x616e6f6e, 0, 0))
+    }
+
+    fn hash(&self, bytes: &[u8]) -> u64 {
+        hash_seeded(bytes, self.key, self.key.rotate_left(17), 0x70636170, 0x65646974)
+    }
+
+    fn anonymize_mac(&self, mac: &mut [u8]) {
+        // 组播和广播地址的第一个字节最低位为1
+        if mac[0] & 0x01 != 0 || mac.iter().all(|&b| b == 0) {
+            return;
+        }
+        let hash = self.hash(mac).to_be_bytes();
+        if self.keep_oui && mac[0] & 0x02 == 0 {
+            mac[3..].copy_from_slice(&hash[..3]);
+            return;
+        }
+        mac.copy_from_slice(&hash[..6]);
+        // 保持单播，并标记为本地管理的地址
+        mac[0] = (mac[0] & 0xfc) | 0x02;
+    }
+
+    fn anonymize_ip(&self, addr: IpAddr) -> IpAddr {
+        match addr {
+            IpAddr::V4(v4) => {
+                if v4.is_unspecified() || v4.is_broadcast() || v4.is_multicast() || v4.is_loopback() {
+                    return addr;
+                }
+                let mut octets = (self.hash(&v4.octets()) as u32).to_be_bytes();
+                // 避免映射为特殊地址
+                if octets[0] == 0 || octets[0] >= 224 {
+                    octets[0] = 10;
+                }
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            IpAddr::V6(v6) => {
+                if v6.is_unspecified() || v6.is_multicast() || v6.is_loopback() {
+                    return addr;
+                }
+                let high = self.hash(&v6.octets());
+                let low = self.hash(&high.to_le_bytes());
+                let mut anonymized = [0u8; 16];
+                anonymized[..8].copy_from_slice(&high.to_be_bytes());
+                anonymized[8..].copy_from_slice(&low.to_be_bytes());
+                // 保持单播: 第一个字节不为0xff
+                if anonymized[0] == 0xff {
+                    anonymized[0] = 0xfd;
+                }
+                IpAddr::V6(Ipv6Addr::from(anonymized))
+            }
+        }
+    }
+
+    /// 匿名化一个数据包的MAC地址和最内层的IP地址
+    pub fn anonymize(&self, data: &mut [u8], linktype: u32, parsed: &ParsedPacket) {
+        if linktype == LINKTYPE_ETHERNET && data.len() >= 14 {
+            self.anonymize_mac(&mut data[0..6]);
+            self.anonymize_mac(&mut data[6..12]);
+        }
+        if let Some(ip) = &parsed.ip {
+            let (src, dst) = (self.anonymize_ip(ip.src), self.anonymize_ip(ip.dst));
+            set_ip_addresses(data, parsed, src, dst);
+        }
+    }
+}
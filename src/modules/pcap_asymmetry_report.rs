@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{FlowKey, IP_PROTO_TCP, IP_PROTO_UDP, Transport, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, print_fields, print_heading, print_section, print_separator, print_verdict, verbosity,
+};
+
+/// 单向流列表中最多列出的流数 (详细模式不限制)
+const MAX_FLOW_ROWS: usize = 50;
+
+/// 单向的TCP流超过这个比例时，认为抓包点整体缺少一个方向
+const ONE_WAY_MAJORITY: f64 = 0.5;
+
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+/// 流的一个方向
+#[derive(Default)]
+struct Direction {
+    packets: u64,
+    bytes: u64,
+    /// 带载荷的包数
+    data_packets: u64,
+    syn: bool,
+    syn_ack: bool,
+}
+
+/// 一个流 (不区分方向)，`key` 为第一个包的方向
+struct Flow {
+    key: FlowKey,
+    forward: Direction,
+    reverse: Direction,
+}
+
+/// 只有一个方向的流的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OneWay {
+    /// 只有SYN，没有SYN-ACK (也可能是连接被拒绝或丢弃)
+    SynOnly,
+    /// 只有SYN-ACK之后的一方 (看不到客户端的SYN)
+    SynAckOnly,
+    /// 有载荷，另一个方向没有任何确认
+    DataWithoutAck,
+    /// 只有纯ACK、RST等不带载荷的包
+    Other,
+}
+
+impl OneWay {
+    fn name(self) -> &'static str {
+        match self {
+            OneWay::SynOnly => "只有SYN",
+            OneWay::SynAckOnly => "只有SYN-ACK",
+            OneWay::DataWithoutAck => "有数据无确认",
+            OneWay::Other => "其他单向",
+        }
+    }
+}
+
+impl Flow {
+    /// 只有一个方向时，返回单向的类型
+    fn one_way(&self) -> Option<OneWay> {
+        let seen = match (self.forward.packets > 0, self.reverse.packets > 0) {
+            (true, false) => &self.forward,
+            (false, true) => &self.reverse,
+            _ => return None,
+        };
+        Some(if seen.data_packets > 0 {
+            OneWay::DataWithoutAck
+        } else if seen.syn_ack {
+            OneWay::SynAckOnly
+        } else if seen.syn {
+            OneWay::SynOnly
+        } else {
+            OneWay::Other
+        })
+    }
+}
+
+/// 报告只抓到一个方向的流 (有SYN无SYN-ACK、有数据无确认)，判断抓包点是否缺少一个方向
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+///
+/// # 说明
+/// 流按五元组 (不区分方向) 划分。TCP流中只有一个方向的包时视为单向，并按看到的包分类；
+/// 单向的TCP流超过一半时，抓包点很可能整体缺少一个方向 (非对称路由、只镜像了一个方向)，
+/// 这样的文件不适合有状态的重放或双向比较。UDP单向流 (如syslog、流量镜像) 很常见，只统计不告警。
+pub fn check_asymmetry(input_path: &str) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut flows: Vec<Flow> = Vec::new();
+    let mut index: HashMap<FlowKey, usize> = HashMap::new();
+    let mut packet_count = 0u64;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let Some(key) = parsed.flow_key().filter(|key| matches!(key.protocol, IP_PROTO_TCP | IP_PROTO_UDP)) else {
+            continue;
+        };
+        let flow_index = *index.entry(key.canonical()).or_insert_with(|| {
+            flows.push(Flow { key, forward: Direction::default(), reverse: Direction::default() });
+            flows.len() - 1
+        });
+        let flow = &mut flows[flow_index];
+        let direction = if flow.key == key { &mut flow.forward } else { &mut flow.reverse };
+        direction.packets += 1;
+        direction.bytes += packet.orig_len as u64;
+        if !parsed.payload_range(data.len()).is_empty() {
+            direction.data_packets += 1;
+        }
+        if let Some(Transport::Tcp(tcp)) = parsed.transport
+            && let Some(&flags) = data.get(tcp.offset + 13)
+            && flags & (TCP_SYN | TCP_RST) == TCP_SYN
+        {
+            if flags & TCP_ACK != 0 {
+                direction.syn_ack = true;
+            } else {
+                direction.syn = true;
+            }
+        }
+    }
+
+    let tcp: Vec<&Flow> = flows.iter().filter(|flow| flow.key.protocol == IP_PROTO_TCP).collect();
+    let udp_flows = flows.len() - tcp.len();
+    let udp_one_way = flows
+        .iter()
+        .filter(|flow| flow.key.protocol == IP_PROTO_UDP && flow.one_way().is_some())
+        .count();
+    let mut one_way: Vec<(&Flow, OneWay)> = tcp.iter().filter_map(|flow| flow.one_way().map(|kind| (*flow, kind))).collect();
+    one_way.sort_by(|a, b| {
+        let packets = |flow: &Flow| flow.forward.packets + flow.reverse.packets;
+        packets(b.0).cmp(&packets(a.0)).then(a.0.key.cmp(&b.0.key))
+    });
+    let count = |kind: OneWay| one_way.iter().filter(|(_, one_way)| *one_way == kind).count();
+    let percent = |part: usize, total: usize| if total > 0 { part as f64 * 100.0 / total as f64 } else { 0.0 };
+    let warning = |value: usize| (value > 0).then_some(Severity::Warning);
+
+    print_heading(&format!("单向流检测: {}", input_path));
+    let mut fields = vec![
+        ("数据包数", packet_count.to_string(), None),
+        ("TCP流数", tcp.len().to_string(), None),
+        (
+            "单向的TCP流",
+            format!("{} ({:.2}%)", one_way.len(), percent(one_way.len(), tcp.len())),
+            warning(one_way.len()),
+        ),
+    ];
+    for kind in [OneWay::SynOnly, OneWay::SynAckOnly, OneWay::DataWithoutAck, OneWay::Other] {
+        let value = count(kind);
+        fields.push((kind.name(), value.to_string(), warning(value)));
+    }
+    fields.push(("UDP流数", udp_flows.to_string(), None));
+    fields.push(("单向的UDP流", format!("{} ({:.2}%)", udp_one_way, percent(udp_one_way, udp_flows)), None));
+    print_fields(&fields);
+
+    if !one_way.is_empty() {
+        let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_FLOW_ROWS };
+        print_section("单向的TCP流 (按看到的方向):");
+        let mut table = Table::new(&["五元组", "类型", "包数", "字节数", "带载荷的包"]).right_align(&[2, 3, 4]);
+        for (flow, kind) in one_way.iter().take(max_rows) {
+            let (key, seen) = if flow.forward.packets > 0 { (flow.key, &flow.forward) } else { (flow.key.reversed(), &flow.reverse) };
+            table.add_row(vec![
+                key.to_string(),
+                kind.name().to_string(),
+                seen.packets.to_string(),
+                seen.bytes.to_string(),
+                seen.data_packets.to_string(),
+            ]);
+        }
+        table.print();
+        if one_way.len() > max_rows {
+            println!("  ... 还有 {} 个流 (使用 --verbose 查看全部)", one_way.len() - max_rows);
+        }
+    }
+
+    print_separator();
+    if one_way.is_empty() {
+        print_verdict(Severity::Ok, &format!("所有TCP流都抓到了两个方向 (共 {} 个TCP流)", tcp.len()));
+    } else if one_way.len() as f64 > tcp.len() as f64 * ONE_WAY_MAJORITY {
+        print_verdict(
+            Severity::Error,
+            &format!(
+                "{:.2}% 的TCP流只有一个方向，抓包点很可能缺少一个方向 (非对称路由或只镜像了单向)，不适合有状态重放或比较",
+                percent(one_way.len(), tcp.len())
+            ),
+        );
+    } else {
+        print_verdict(
+            Severity::Warning,
+            &format!("{} 个TCP流只有一个方向 (连接被拒绝、抓包开始或结束于连接中途，或部分路径不经过抓包点)", one_way.len()),
+        );
+    }
+    Ok(())
+}
@@ -1,69 +1,80 @@
-use std::path::Path;
-use std::fs::File;
-use pcap_file::{PcapReader, PcapWriter};
-use anyhow::{Context, Result, anyhow};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use pcap_file::Packet;
+use anyhow::Result;
 use log::info;
 
+use crate::modules::io::{OutputFormat, PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{Compression, compression, create_output};
+use crate::modules::pcap_packet_parser::{parse_packet, set_ip_addresses};
+use crate::modules::pcap_time_anchor::{split_timestamp_ticks, ticks_per_second, timestamp_ticks};
+use crate::modules::pcap_packet_store::PacketStore;
+use crate::modules::pcap_verify::OutputDigest;
+
 /// 增强PCAP文件的时间分布
 /// 
 /// # 参数
-/// - `input_path`: 输入PCAP文件路径
-/// - `output_path`: 输出PCAP文件路径
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
 /// - `multiplier`: 数据包复制倍数
+/// - `format`: 输出格式 (PCAP或pcapng)
+/// - `compression`: 输出的压缩方式
 /// 
 /// # 功能
 /// 1. 保持原始时间跨度不变
 /// 2. 复制数据包内容到指定倍数
 /// 3. 在时间线上均匀分布复制包
+/// 4. 纳秒精度的PCAP文件输出保持纳秒精度
 pub fn pcap_augment_timed(
     input_path: &str,
     output_path: &str,
     multiplier: usize,
+    format: OutputFormat,
+    compression: Compression,
 ) -> Result<()> {
     // 验证倍数参数
     if multiplier < 2 {
-        anyhow::bail!("复制倍数必须大于1，当前为: {}", multiplier);
+        anyhow::bail!(PcapError::InvalidArgument(format!("复制倍数必须大于1，当前为: {}", multiplier)));
     }
 
     // 打开输入文件
-    let in_file = File::open(Path::new(input_path))
-        .with_context(|| format!("无法打开输入文件: {}", input_path))?;
-    let mut pcap_reader = PcapReader::new(in_file)
-        .map_err(|e| anyhow!("无效的PCAP文件格式: {}", e))?;
+    let mut pcap_reader = PacketReader::open(input_path)?;
 
     // 创建输出文件
-    let out_file = File::create(Path::new(output_path))
-        .with_context(|| format!("无法创建极出文件: {}", output_path))?;
+    let out_file = create_output(output_path, compression)?;
     
     // 正确创建PcapWriter
-    let header = pcap_reader.header.clone();
-    let mut pcap_writer = PcapWriter::with_header(header, out_file)
-        .map_err(|e| anyhow!("创建PCAP写入器失败: {}", e))?;
-
-    // 读取所有原始包
-    let mut original_packets = Vec::new();
-    while let Some(packet) = pcap_reader.next() {
-        original_packets.push(packet);
+    let header = pcap_reader.header;
+    let mut pcap_writer = PacketWriter::with_format(header, out_file, format)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+    // 秒以下部分的一个单位对应的纳秒数 (微秒精度为1000，纳秒精度为1)
+    let ns_per_tick = (1_000_000_000 / ticks_per_second(&header)) as u128;
+
+    // 读取所有原始包 (超出内存上限的包写入临时文件)
+    let mut original_packets = PacketStore::new(header);
+    let mut first_timestamp = None;
+    let mut last_timestamp = None;
+    for packet in pcap_reader.by_ref() {
+        if first_timestamp.is_none() {
+            first_timestamp = Some((packet.header.ts_sec, packet.header.ts_usec));
+        }
+        last_timestamp = Some((packet.header.ts_sec, packet.header.ts_usec));
+        original_packets.push(packet)?;
     }
 
     // 检查是否有足够的数据包
     if original_packets.is_empty() {
-        anyhow::bail!("输入文件不包含任何数据包");
+        anyhow::bail!(PcapError::EmptyCapture);
     }
 
     // 获取第一个包和最后一个包的时间戳
-    let first_packet = &original_packets[0];
-    let last_packet = original_packets.last().unwrap();
+    let (first_sec, first_usec) = first_timestamp.unwrap();
+    let (last_sec, last_usec) = last_timestamp.unwrap();
     
     // 计算原始时间跨度（纳秒）
-    let first_sec = first_packet.header.ts_sec;
-    let first_usec = first_packet.header.ts_usec;
-    let last_sec = last_packet.header.ts_sec;
-    let last_usec = last_packet.header.ts_usec;
-    
-    // 转换为纳秒精度
-    let first_ns = (first_sec as u128) * 1_000_000_000 + first_usec as u128 * 1000;
-    let last_ns = (last_sec as u128) * 1_000_000_000 + last_usec as u128 * 1000;
+    let first_ns = (first_sec as u128) * 1_000_000_000 + first_usec as u128 * ns_per_tick;
+    let last_ns = (last_sec as u128) * 1_000_000_000 + last_usec as u128 * ns_per_tick;
     
     let total_duration_ns = last_ns - first_ns;
     
@@ -77,45 +88,142 @@ pub fn pcap_augment_timed(
         0
     };
 
-    // 创建新包数组
-    let mut new_packets = Vec::with_capacity(target_packet_count);
-    
-    // 按顺序生成新包
-    for i in 0..target_packet_count {
-        // 计算新包的时间戳（纳秒）
-        let new_ns = first_ns + ideal_interval_ns * i as u128;
-        
-        // 转换为秒和纳秒
-        let new_sec = (new_ns / 1_000_000_000) as u32;
-        let new_ns_residual = (new_ns % 1_000_000_000) as u32;
-        let new_usec = new_ns_residual / 1000; // 转换为微秒
-        
-        // 选择原始包（循环分配）
-        let orig_index = i % original_packets.len();
-        let mut new_packet = original_packets[orig_index].clone();
-        
-        // 设置新时间戳
-        new_packet.header.ts_sec = new_sec;
-        new_packet.header.ts_usec = new_usec;
-        
-        new_packets.push(new_packet);
+    // 按顺序生成新包，每一轮按原始顺序遍历一次所有包（循环分配）
+    let original_count = original_packets.len();
+    let mut new_packet_count = 0;
+    for round in 0..multiplier {
+        for (index, mut new_packet) in original_packets.iter()?.enumerate() {
+            let i = round * original_count + index;
+            
+            // 计算新包的时间戳（纳秒）
+            let new_ns = first_ns + ideal_interval_ns * i as u128;
+            
+            // 转换为秒和纳秒
+            let new_sec = (new_ns / 1_000_000_000) as u32;
+            let new_ns_residual = (new_ns % 1_000_000_000) as u32;
+            let new_usec = new_ns_residual / ns_per_tick as u32; // 转换为文件的精度
+            
+            // 设置新时间戳
+            new_packet.header.ts_sec = new_sec;
+            new_packet.header.ts_usec = new_usec;
+            
+            pcap_writer.write_packet(&new_packet)
+                .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+            digest.record_pcap(&new_packet);
+            new_packet_count += 1;
+        }
     }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功生成增强文件: 原始包数={}, 复制倍数={}, 总包数={}",
+        original_count,
+        multiplier,
+        new_packet_count
+    );
 
-    // 保存新包数量
-    let new_packet_count = new_packets.len();  // 新增行
+    Ok(())
+}
 
-    // 写入所有新包
-    for packet in new_packets {
-        pcap_writer.write_packet(&packet)
-            .map_err(|e| anyhow!("写入包失败: {}", e))?;
+/// 循环拼接短抓包，填满目标时长
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `target_duration_us`: 目标时长 (微秒)，从输入的第一个包开始计算
+///
+/// # 功能
+/// 1. 按原始间隔重复输入的全部包，每一轮紧接上一轮 (两轮之间的间隔为平均包间隔)
+/// 2. 输出时间戳单调不减，超出目标时长的包不再输出
+/// 3. 与 `tcpreplay --unique-ip` 类似，第N轮 (从0开始) 的IPv4/IPv6源和目的地址都加N并更新校验和，
+///    同一个流在各轮中的五元组不同 (地址连续的主机之间可能与其他轮的流重合)
+pub fn pcap_augment_duration(input_path: &str, output_path: &str, target_duration_us: u64) -> Result<()> {
+    if target_duration_us == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("目标时长必须大于0".to_string()));
     }
 
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let header = pcap_reader.header;
+    let linktype: u32 = header.datalink.into();
+
+    // 时间戳按文件的精度 (微秒或纳秒) 计算
+    let ticks_per_second = ticks_per_second(&header);
+    let packet_time = |packet: &Packet| timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second);
+
+    let mut original_packets = PacketStore::new(header);
+    let mut first = None;
+    let mut last = 0i64;
+    for packet in pcap_reader.by_ref() {
+        let time = packet_time(&packet);
+        first.get_or_insert(time);
+        last = last.max(time);
+        original_packets.push(packet)?;
+    }
+    let Some(first) = first else {
+        anyhow::bail!(PcapError::EmptyCapture);
+    };
+
+    // 每一轮的时长: 原始时间跨度加一个平均包间隔 (至少一个时间戳单位)
+    let span = (last - first).max(0) as u64;
+    let gap = span.checked_div(original_packets.len() as u64 - 1).unwrap_or(0).max(1);
+    let period = span + gap;
+    let target_duration = target_duration_us.saturating_mul((ticks_per_second / 1_000_000) as u64);
+    let end = first.saturating_add(target_duration.min(i64::MAX as u64) as i64);
+
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut previous = first;
+    let mut written = 0u64;
+    let mut round = 0u64;
+    'outer: loop {
+        let offset = (round as i128 * period as i128).min(i64::MAX as i128) as i64;
+        for mut packet in original_packets.iter()? {
+            let time = (packet_time(&packet).saturating_add(offset)).max(previous);
+            if time >= end {
+                break 'outer;
+            }
+            previous = time;
+            let (ts_sec, ts_usec) = split_timestamp_ticks(time, ticks_per_second)?;
+            packet.header.ts_sec = ts_sec;
+            packet.header.ts_usec = ts_usec;
+            if round > 0 {
+                shift_addresses(packet.data.to_mut(), linktype, round);
+            }
+            pcap_writer.write_packet(&packet)
+                .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+            digest.record_pcap(&packet);
+            written += 1;
+        }
+        round += 1;
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
     info!(
-        "成功生成增强文件: 原始包数={}, 复制倍数={}, 总包数={}",
+        "成功生成目标时长文件: 原始包数={}, 轮数={}, 总包数={}",
         original_packets.len(),
-        multiplier,
-        new_packet_count  // 修改为临时变量
+        round + 1,
+        written
     );
-
     Ok(())
-}
\ No newline at end of file
+}
+
+/// 源和目的地址都加上轮数 (按地址的整数值回绕)
+fn shift_addresses(data: &mut [u8], linktype: u32, round: u64) {
+    let parsed = parse_packet(data, linktype);
+    let Some(ip) = &parsed.ip else {
+        return;
+    };
+    let shift = |addr: IpAddr| match addr {
+        IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(u32::from(v4).wrapping_add(round as u32))),
+        IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6).wrapping_add(round as u128))),
+    };
+    let (src, dst) = (shift(ip.src), shift(ip.dst));
+    set_ip_addresses(data, &parsed, src, dst);
+}
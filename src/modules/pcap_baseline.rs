@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use anyhow::{Context, Result};
+use log::info;
+
+use crate::modules::io::PacketReader;
+use crate::modules::pcap_comparative_analyzer::{CompareOptions, packet_hash};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::parse_packet;
+use crate::modules::pcap_report::{Table, print_heading};
+use crate::modules::pcap_time_anchor::{ticks_per_second, timestamp_to_us};
+
+/// 基准文件魔数
+const BASELINE_MAGIC: &[u8; 8] = b"PCAPBSL1";
+
+/// 每条包记录的长度
+const RECORD_LEN: usize = 32;
+
+/// 基准文件扩展名
+const BASELINE_EXTENSION: &str = "baseline";
+
+/// 非IP包的流编号
+const NO_FLOW: u32 = u32::MAX;
+
+/// 基准中的一个包 (参与比较的包)
+#[derive(Debug, Clone, Copy)]
+pub struct BaselinePacket {
+    /// 在源文件中的序号
+    pub index: usize,
+    pub len: usize,
+    pub hash: u64,
+    pub time_us: i64,
+    /// 所属的流在 [`Baseline::flows`] 中的编号 (非IP包为None)
+    pub flow: Option<u32>,
+}
+
+/// 保存的基准: 源文件的清单和每个包的哈希
+pub struct Baseline {
+    pub name: String,
+    /// 保存时的源文件路径和大小
+    pub source: String,
+    pub source_size: u64,
+    /// 保存时间 (Unix秒)
+    pub saved_at: i64,
+    /// 计算哈希时使用的比较选项
+    pub options: CompareOptions,
+    pub linktype: u32,
+    /// 源文件中的包数 (包括不参与比较的包)
+    pub packet_count: u64,
+    /// 源文件第一个包的时间 (相对时间格式的起点)
+    pub start_us: i64,
+    /// 五元组的文字形式
+    pub flows: Vec<String>,
+    pub packets: Vec<BaselinePacket>,
+}
+
+impl Baseline {
+    /// 比较时使用的选项: 没有指定选项时使用保存时的选项，指定的选项必须与保存时相同
+    pub fn resolve_options(&self, options: CompareOptions) -> Result<CompareOptions> {
+        if options != CompareOptions::default() && options != self.options {
+            anyhow::bail!(PcapError::InvalidArgument(format!(
+                "基准 {} 保存时的比较选项为 {}，与本次指定的 {} 不同 (哈希无法比较，请重新保存基准)",
+                self.name,
+                describe_options(self.options),
+                describe_options(options)
+            )));
+        }
+        Ok(self.options)
+    }
+}
+
+/// 基准的存放目录: `$PCAP_EDITOR_HOME/baselines`，没有设置时为 `~/.pcap-editor/baselines`
+pub fn baseline_dir() -> Result<PathBuf> {
+    let home = match std::env::var_os("PCAP_EDITOR_HOME") {
+        Some(home) => PathBuf::from(home),
+        None => match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".pcap-editor"),
+            None => anyhow::bail!(PcapError::InvalidArgument(
+                "无法确定基准的存放目录: 请设置 PCAP_EDITOR_HOME 或 HOME".to_string()
+            )),
+        },
+    };
+    Ok(home.join("baselines"))
+}
+
+/// 基准名称只能包含字母、数字和 `-_.`，不能以 `.` 开头
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(PcapError::InvalidArgument(format!(
+            "无效的基准名称: {} (只能包含字母、数字和 -_.，不能以 . 开头)",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn baseline_path(name: &str) -> Result<PathBuf> {
+    check_name(name)?;
+    Ok(baseline_dir()?.join(format!("{}.{}", name, BASELINE_EXTENSION)))
+}
+
+/// 比较选项的文字描述
+fn describe_options(options: CompareOptions) -> String {
+    let mut names = Vec::new();
+    if options.ignore_timestamp {
+        names.push("--ignore-timestamp");
+    }
+    if options.payload_only {
+        names.push("--payload-only");
+    }
+    if options.strip_padding {
+        names.push("--strip-padding");
+    }
+    if names.is_empty() { "(默认)".to_string() } else { names.join(" ") }
+}
+
+/// 计算参考抓包中每个包的哈希，保存为命名的基准
+///
+/// # 参数
+/// - `input_path`: 参考抓包文件路径 (PCAP或pcapng)
+/// - `name`: 基准名称，已存在时覆盖
+/// - `options`: 比较选项 (决定哈希的计算方式，之后 `compare --baseline` 使用相同的选项)
+///
+/// # 功能
+/// 1. 每个参与比较的包记录序号、长度、哈希、时间戳和五元组，不保存包内容
+/// 2. 同时记录源文件路径、大小、包数和保存时间，`baseline list` 列出
+/// 3. 先写临时文件再改名，中断时不会留下不完整的基准
+pub fn save_baseline(input_path: &str, name: &str, options: CompareOptions) -> Result<()> {
+    let path = baseline_path(name)?;
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let ticks_per_second = ticks_per_second(&pcap_reader.header);
+
+    let mut flows: Vec<String> = Vec::new();
+    let mut flow_numbers: HashMap<String, u32> = HashMap::new();
+    let mut packets = Vec::new();
+    let mut packet_count = 0u64;
+    let mut start_us = None;
+    for (index, packet) in pcap_reader.by_ref().enumerate() {
+        packet_count += 1;
+        let time_us = timestamp_to_us(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second);
+        start_us.get_or_insert(time_us);
+        let Some(hash) = packet_hash(&packet, linktype, options) else {
+            continue;
+        };
+        let flow = parse_packet(&packet.data, linktype).flow_key().map(|key| {
+            let text = key.to_string();
+            *flow_numbers.entry(text.clone()).or_insert_with(|| {
+                flows.push(text);
+                flows.len() as u32 - 1
+            })
+        });
+        packets.push(BaselinePacket { index, len: packet.data.len(), hash, time_us, flow });
+    }
+    // 截断的参考文件会让之后的每次比较都报告丢包
+    pcap_reader.check_complete()?;
+
+    let baseline = Baseline {
+        name: name.to_string(),
+        source: input_path.to_string(),
+        source_size: fs::metadata(input_path).map(|metadata| metadata.len()).unwrap_or(0),
+        saved_at: Local::now().timestamp(),
+        options,
+        linktype,
+        packet_count,
+        start_us: start_us.unwrap_or_default(),
+        flows,
+        packets,
+    };
+    write_baseline(&path, &baseline)?;
+
+    info!(
+        "成功保存基准: 名称={}, 包数={}, 参与比较的包数={}, 比较选项={}, 基准文件={}",
+        name,
+        baseline.packet_count,
+        baseline.packets.len(),
+        describe_options(options),
+        path.display()
+    );
+    Ok(())
+}
+
+fn write_baseline(path: &Path, baseline: &Baseline) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)
+        .map_err(|e| PcapError::WriteFailure(format!("无法创建基准目录: {}: {}", dir.display(), e)))?;
+
+    let temp_path = path.with_extension(format!("{}.tmp", BASELINE_EXTENSION));
+    let write = || -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        let flags = u8::from(baseline.options.ignore_timestamp)
+            | u8::from(baseline.options.payload_only) << 1
+            | u8::from(baseline.options.strip_padding) << 2;
+        writer.write_all(BASELINE_MAGIC)?;
+        writer.write_all(&[flags])?;
+        writer.write_all(&baseline.linktype.to_le_bytes())?;
+        writer.write_all(&baseline.saved_at.to_le_bytes())?;
+        writer.write_all(&baseline.source_size.to_le_bytes())?;
+        writer.write_all(&baseline.packet_count.to_le_bytes())?;
+        writer.write_all(&baseline.start_us.to_le_bytes())?;
+        write_string(&mut writer, &baseline.source)?;
+        writer.write_all(&(baseline.flows.len() as u32).to_le_bytes())?;
+        for flow in &baseline.flows {
+            write_string(&mut writer, flow)?;
+        }
+        writer.write_all(&(baseline.packets.len() as u64).to_le_bytes())?;
+        for packet in &baseline.packets {
+            writer.write_all(&(packet.index as u64).to_le_bytes())?;
+            writer.write_all(&(packet.len as u32).to_le_bytes())?;
+            writer.write_all(&packet.flow.unwrap_or(NO_FLOW).to_le_bytes())?;
+            writer.write_all(&packet.hash.to_le_bytes())?;
+            writer.write_all(&packet.time_us.to_le_bytes())?;
+        }
+        writer.into_inner()?.sync_all()?;
+        fs::rename(&temp_path, path)
+    };
+    write().map_err(|e| PcapError::WriteFailure(format!("无法写入基准文件: {}: {}", path.display(), e)))?;
+    Ok(())
+}
+
+fn write_string(writer: &mut impl Write, text: &str) -> std::io::Result<()> {
+    writer.write_all(&(text.len() as u32).to_le_bytes())?;
+    writer.write_all(text.as_bytes())
+}
+
+/// 顺序读取基准文件的字段
+struct Fields<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(len).filter(|end| *end <= self.data.len())
+            .ok_or_else(|| PcapError::InvalidFormat("基准文件被截断".to_string()))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+/// 读取命名的基准
+pub fn load_baseline(name: &str) -> Result<Baseline> {
+    let path = baseline_path(name)?;
+    let data = fs::read(&path)
+        .map_err(|e| PcapError::open_failed(&path.display().to_string(), e))
+        .with_context(|| format!("找不到基准 {} (使用 baseline save 保存，baseline list 列出已有的基准)", name))?;
+    parse_baseline(name, &data).with_context(|| format!("无法读取基准文件: {}", path.display()))
+}
+
+/// 解析基准文件
+fn parse_baseline(name: &str, data: &[u8]) -> Result<Baseline> {
+    let mut fields = Fields { data, position: 0 };
+    if fields.take(8)? != BASELINE_MAGIC {
+        anyhow::bail!(PcapError::InvalidFormat("不是有效的基准文件".to_string()));
+    }
+    let flags = fields.take(1)?[0];
+    let options = CompareOptions {
+        ignore_timestamp: flags & 1 != 0,
+        payload_only: flags & 2 != 0,
+        strip_padding: flags & 4 != 0,
+    };
+    let linktype = fields.u32()?;
+    let saved_at = fields.u64()? as i64;
+    let source_size = fields.u64()?;
+    let packet_count = fields.u64()?;
+    let start_us = fields.u64()? as i64;
+    let source = fields.string()?;
+    let flow_count = fields.u32()? as usize;
+    let mut flows = Vec::with_capacity(flow_count.min(data.len()));
+    for _ in 0..flow_count {
+        flows.push(fields.string()?);
+    }
+    let record_count = fields.u64()? as usize;
+    let records = fields.take(record_count.saturating_mul(RECORD_LEN))?;
+
+    let mut packets = Vec::with_capacity(record_count);
+    for record in records.chunks_exact(RECORD_LEN) {
+        let field = |offset: usize, len: usize| &record[offset..offset + len];
+        let flow = u32::from_le_bytes(field(12, 4).try_into().unwrap());
+        if flow != NO_FLOW && flow as usize >= flows.len() {
+            anyhow::bail!(PcapError::InvalidFormat(format!("基准文件中的流编号无效: {}", flow)));
+        }
+        packets.push(BaselinePacket {
+            index: u64::from_le_bytes(field(0, 8).try_into().unwrap()) as usize,
+            len: u32::from_le_bytes(field(8, 4).try_into().unwrap()) as usize,
+            flow: (flow != NO_FLOW).then_some(flow),
+            hash: u64::from_le_bytes(field(16, 8).try_into().unwrap()),
+            time_us: i64::from_le_bytes(field(24, 8).try_into().unwrap()),
+        });
+    }
+
+    Ok(Baseline {
+        name: name.to_string(),
+        source,
+        source_size,
+        saved_at,
+        options,
+        linktype,
+        packet_count,
+        start_us,
+        flows,
+        packets,
+    })
+}
+
+/// 列出已保存的基准
+pub fn list_baselines() -> Result<()> {
+    let dir = baseline_dir()?;
+    let mut names: Vec<String> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                (path.extension()? == BASELINE_EXTENSION).then_some(path.file_stem()?.to_str()?.to_string())
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    names.sort();
+
+    print_heading(&format!("已保存的基准: {}", dir.display()));
+    if names.is_empty() {
+        println!("  (没有基准，使用 baseline save 保存)");
+        return Ok(());
+    }
+    let mut table = Table::new(&["名称", "包数", "参与比较", "比较选项", "保存时间", "源文件"]).right_align(&[1, 2]);
+    for name in &names {
+        let path = dir.join(format!("{}.{}", name, BASELINE_EXTENSION));
+        let data = fs::read(&path).map_err(|e| PcapError::open_failed(&path.display().to_string(), e))?;
+        let baseline = match parse_baseline(name, &data) {
+            Ok(baseline) => baseline,
+            Err(e) => {
+                table.add_row(vec![name.clone(), "-".to_string(), "-".to_string(), format!("无效: {:#}", e), "-".to_string(), "-".to_string()]);
+                continue;
+            }
+        };
+        let saved_at = DateTime::from_timestamp(baseline.saved_at, 0)
+            .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        table.add_row(vec![
+            name.clone(),
+            baseline.packet_count.to_string(),
+            baseline.packets.len().to_string(),
+            describe_options(baseline.options),
+            saved_at,
+            format!("{} ({} 字节)", baseline.source, baseline.source_size),
+        ]);
+    }
+    table.print();
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::BufWriter;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use pcap_file::{Packet, PcapWriter};
+use seahash::SeaHasher;
+use anyhow::{Context, Result};
+use log::info;
+
+use crate::modules::io::PacketReader;
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::parse_packet;
+use crate::modules::pcap_report::{Table, print_heading};
+
+/// 单项测试结果
+struct BenchResult {
+    name: String,
+    elapsed: Duration,
+    packets: usize,
+    bytes: usize,
+}
+
+/// 测量本机处理PCAP文件的吞吐量
+///
+/// # 参数
+/// - `input_path`: 用于测试的抓包文件路径 (PCAP或pcapng)
+/// - `threads`: 多线程测试使用的线程数
+/// - `limit`: 最多加载的数据包数量 (None表示全部)
+///
+/// # 功能
+/// 1. 测量读取、哈希、解析、写入的吞吐量 (包/秒, MB/秒)
+/// 2. 哈希和解析分别测试单线程与多线程
+/// 3. 写入测试使用临时文件，完成后删除
+pub fn pcap_benchmark(input_path: &str, threads: usize, limit: Option<usize>) -> Result<()> {
+    if threads == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("线程数必须大于0".to_string()));
+    }
+
+    // 读取
+    let start = Instant::now();
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let header = pcap_reader.header;
+    let linktype: u32 = header.datalink.into();
+    let packets: Vec<Packet<'static>> = pcap_reader.by_ref().take(limit.unwrap_or(usize::MAX)).collect();
+    if limit.is_none() {
+        pcap_reader.check_complete()?;
+    }
+    let read_elapsed = start.elapsed();
+
+    if packets.is_empty() {
+        anyhow::bail!(PcapError::EmptyCapture);
+    }
+    let total_bytes: usize = packets.iter().map(|p| p.data.len()).sum();
+    info!("已加载 {} 个数据包 ({} 字节)，开始测试", packets.len(), total_bytes);
+
+    let mut results = vec![BenchResult {
+        name: "读取".to_string(),
+        elapsed: read_elapsed,
+        packets: packets.len(),
+        bytes: total_bytes,
+    }];
+
+    // 哈希
+    results.push(run_bench("哈希 (单线程)", &packets, 1, |packet| {
+        let mut hasher = SeaHasher::new();
+        hasher.write(&packet.data);
+        hasher.finish()
+    }));
+    results.push(run_bench(&format!("哈希 ({}线程)", threads), &packets, threads, |packet| {
+        let mut hasher = SeaHasher::new();
+        hasher.write(&packet.data);
+        hasher.finish()
+    }));
+
+    // 解析
+    results.push(run_bench("解析 (单线程)", &packets, 1, |packet| {
+        parse_packet(&packet.data, linktype).flow_key().is_some() as u64
+    }));
+    results.push(run_bench(&format!("解析 ({}线程)", threads), &packets, threads, |packet| {
+        parse_packet(&packet.data, linktype).flow_key().is_some() as u64
+    }));
+
+    // 写入
+    let temp_path = std::env::temp_dir().join(format!("pcap-editor-bench-{}.pcap", std::process::id()));
+    let start = Instant::now();
+    let write_result = write_packets(&temp_path, header, &packets);
+    let write_elapsed = start.elapsed();
+    let _ = fs::remove_file(&temp_path);
+    write_result?;
+    results.push(BenchResult {
+        name: "写入".to_string(),
+        elapsed: write_elapsed,
+        packets: packets.len(),
+        bytes: total_bytes,
+    });
+
+    print_bench_results(input_path, &results);
+
+    Ok(())
+}
+
+/// 将数据包分块交给多个线程处理并计时
+fn run_bench<F>(name: &str, packets: &[Packet<'static>], threads: usize, work: F) -> BenchResult
+where
+    F: Fn(&Packet<'static>) -> u64 + Sync,
+{
+    let chunk_size = packets.len().div_ceil(threads);
+    let start = Instant::now();
+
+    let checksum = thread::scope(|scope| {
+        let handles: Vec<_> = packets
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| chunk.iter().fold(0u64, |acc, p| acc.wrapping_add(work(p)))))
+            .collect();
+        handles.into_iter().fold(0u64, |acc, h| acc.wrapping_add(h.join().unwrap()))
+    });
+    let elapsed = start.elapsed();
+
+    // 防止编译器优化掉计算
+    std::hint::black_box(checksum);
+
+    BenchResult {
+        name: name.to_string(),
+        elapsed,
+        packets: packets.len(),
+        bytes: packets.iter().map(|p| p.data.len()).sum(),
+    }
+}
+
+fn write_packets(path: &Path, header: pcap_file::pcap_header::PcapHeader, packets: &[Packet<'static>]) -> Result<()> {
+    let out_file = File::create(path)
+        .with_context(|| format!("无法创建临时文件: {}", path.display()))?;
+    let mut pcap_writer = PcapWriter::with_header(header, BufWriter::new(out_file))
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    for packet in packets {
+        pcap_writer.write_packet(packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// 打印测试结果
+fn print_bench_results(input_path: &str, results: &[BenchResult]) {
+    print_heading(&format!("性能测试结果: {}", input_path));
+    let mut table = Table::new(&["测试项", "耗时(ms)", "包/秒", "MB/秒"]).right_align(&[1, 2, 3]);
+    for result in results {
+        let secs = result.elapsed.as_secs_f64().max(1e-9);
+        table.add_row(vec![
+            result.name.to_string(),
+            format!("{:.2}", secs * 1000.0),
+            format!("{:.0}", result.packets as f64 / secs),
+            format!("{:.2}", result.bytes as f64 / secs / 1_000_000.0),
+        ]);
+    }
+    table.print();
+}
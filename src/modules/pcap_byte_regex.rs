@@ -0,0 +1,481 @@
+use anyhow::Result;
+
+/// 单个量词展开后的最大重复次数 (限制编译后的程序大小)
+const MAX_REPEAT: u32 = 1000;
+
+/// 编译后程序的最大指令数 (嵌套量词会成倍展开)
+const MAX_PROGRAM_LEN: u64 = 100_000;
+
+/// 在字节串上匹配的正则表达式 (常用语法的子集)
+///
+/// 支持字面字符 (非ASCII字符按UTF-8字节匹配)、`.`、字符类 (`[a-z]`、`[^\r\n]`)、
+/// `\d \w \s` 及其大写形式、`\xHH`、`\n \r \t \0`、`^`/`$` (载荷的开头和结尾)、
+/// 分组 `(...)`/`(?:...)`、`|`、量词 `* + ? {n} {n,} {n,m}`，以及开头的标志 `(?i)` (ASCII不区分大小写)
+/// 和 `(?s)` (`.` 也匹配换行)。用Pike虚拟机模拟，匹配时间与载荷长度成线性关系，不会因回溯变慢。
+#[derive(Debug, Clone)]
+pub struct ByteRegex {
+    program: Vec<Inst>,
+}
+
+/// 256位的字节集合
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    const EMPTY: ByteSet = ByteSet([0; 4]);
+
+    fn single(byte: u8) -> ByteSet {
+        let mut set = ByteSet::EMPTY;
+        set.insert(byte);
+        set
+    }
+
+    fn range(low: u8, high: u8) -> ByteSet {
+        let mut set = ByteSet::EMPTY;
+        for byte in low..=high {
+            set.insert(byte);
+        }
+        set
+    }
+
+    fn insert(&mut self, byte: u8) {
+        self.0[(byte >> 6) as usize] |= 1 << (byte & 63);
+    }
+
+    fn contains(&self, byte: u8) -> bool {
+        self.0[(byte >> 6) as usize] & (1 << (byte & 63)) != 0
+    }
+
+    fn union(mut self, other: ByteSet) -> ByteSet {
+        for (word, other) in self.0.iter_mut().zip(other.0) {
+            *word |= other;
+        }
+        self
+    }
+
+    fn complement(mut self) -> ByteSet {
+        for word in &mut self.0 {
+            *word = !*word;
+        }
+        self
+    }
+
+    /// 加入ASCII字母的另一种大小写
+    fn case_folded(mut self) -> ByteSet {
+        for byte in b'a'..=b'z' {
+            if self.contains(byte) || self.contains(byte.to_ascii_uppercase()) {
+                self.insert(byte);
+                self.insert(byte.to_ascii_uppercase());
+            }
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Set(ByteSet),
+    /// 载荷开头
+    Start,
+    /// 载荷结尾
+    End,
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32> },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Inst {
+    Byte(ByteSet),
+    /// 同时尝试两个分支
+    Split(usize, usize),
+    Jump(usize),
+    Start,
+    End,
+    Match,
+}
+
+impl ByteRegex {
+    /// 解析正则表达式
+    pub fn parse(pattern: &str) -> Result<ByteRegex> {
+        let mut parser = RegexParser { pattern: pattern.as_bytes(), pos: 0, case_insensitive: false, dot_all: false };
+        parser.parse_flags()?;
+        let node = parser.parse_alternate()?;
+        if parser.pos < parser.pattern.len() {
+            anyhow::bail!("正则表达式中有多余的 `)`: 位置 {}", parser.pos);
+        }
+        if program_len(&node) > MAX_PROGRAM_LEN {
+            anyhow::bail!("正则表达式展开后过大 (超过 {} 条指令)，请减少嵌套量词的重复次数", MAX_PROGRAM_LEN);
+        }
+        let mut program = Vec::new();
+        compile(&node, &mut program);
+        program.push(Inst::Match);
+        Ok(ByteRegex { program })
+    }
+
+    /// 字节串中是否有任一位置开始的子串匹配
+    pub fn is_match(&self, haystack: &[u8]) -> bool {
+        let mut current = ThreadList::new(self.program.len());
+        let mut next = ThreadList::new(self.program.len());
+        for pos in 0..=haystack.len() {
+            // 每个位置都开始一个新的线程，相当于在表达式前加 `.*?`
+            if self.add_thread(&mut current, 0, pos, haystack.len()) {
+                return true;
+            }
+            let Some(&byte) = haystack.get(pos) else {
+                break;
+            };
+            next.clear();
+            for &pc in &current.threads {
+                if let Inst::Byte(set) = self.program[pc]
+                    && set.contains(byte)
+                    && self.add_thread(&mut next, pc + 1, pos + 1, haystack.len())
+                {
+                    return true;
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+        false
+    }
+
+    /// 沿空转移加入线程，到达 `Match` 时返回true
+    fn add_thread(&self, list: &mut ThreadList, pc: usize, pos: usize, len: usize) -> bool {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if !list.insert(pc) {
+                continue;
+            }
+            match self.program[pc] {
+                Inst::Byte(_) => list.threads.push(pc),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Jump(target) => stack.push(target),
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End if pos == len => stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                Inst::Match => return true,
+            }
+        }
+        false
+    }
+}
+
+/// 当前位置上的线程 (每条指令最多一个)
+struct ThreadList {
+    visited: Vec<bool>,
+    visited_list: Vec<usize>,
+    threads: Vec<usize>,
+}
+
+impl ThreadList {
+    fn new(size: usize) -> ThreadList {
+        ThreadList { visited: vec![false; size], visited_list: Vec::new(), threads: Vec::new() }
+    }
+
+    fn insert(&mut self, pc: usize) -> bool {
+        if self.visited[pc] {
+            return false;
+        }
+        self.visited[pc] = true;
+        self.visited_list.push(pc);
+        true
+    }
+
+    fn clear(&mut self) {
+        for &pc in &self.visited_list {
+            self.visited[pc] = false;
+        }
+        self.visited_list.clear();
+        self.threads.clear();
+    }
+}
+
+/// 节点编译后的指令数
+fn program_len(node: &Node) -> u64 {
+    match node {
+        Node::Empty => 0,
+        Node::Set(_) | Node::Start | Node::End => 1,
+        Node::Concat(nodes) => nodes.iter().map(program_len).fold(0, u64::saturating_add),
+        Node::Alternate(nodes) => nodes.iter().map(|node| program_len(node).saturating_add(2)).fold(0, u64::saturating_add),
+        Node::Repeat { node, min, max } => {
+            let len = program_len(node);
+            let optional = match max {
+                None => len.saturating_add(2),
+                Some(max) => (*max - *min) as u64 * len.saturating_add(1),
+            };
+            (*min as u64).saturating_mul(len).saturating_add(optional)
+        }
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Empty => {}
+        Node::Set(set) => program.push(Inst::Byte(*set)),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, program)),
+        Node::Alternate(nodes) => {
+            // Split(分支1, 下一个Split) ... 最后一个分支；每个分支之后跳到结尾
+            let mut jumps = Vec::new();
+            for (i, node) in nodes.iter().enumerate() {
+                if i + 1 < nodes.len() {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(node, program);
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    program[split] = Inst::Split(split + 1, program.len());
+                } else {
+                    compile(node, program);
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(node, program);
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    // 可选的副本: 任一副本处都可以跳到结尾
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(program.len() + 1, 0));
+                        compile(node, program);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct RegexParser<'a> {
+    pattern: &'a [u8],
+    pos: usize,
+    case_insensitive: bool,
+    dot_all: bool,
+}
+
+impl RegexParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<u8> {
+        let byte = self.peek().ok_or_else(|| anyhow::anyhow!("正则表达式意外结束"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn eat(&mut self, text: &[u8]) -> bool {
+        if self.pattern[self.pos..].starts_with(text) {
+            self.pos += text.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 开头的 `(?i)`、`(?s)`、`(?is)`
+    fn parse_flags(&mut self) -> Result<()> {
+        if !self.pattern.starts_with(b"(?") || self.pattern.get(2) == Some(&b':') {
+            return Ok(());
+        }
+        self.pos = 2;
+        loop {
+            match self.next()? {
+                b'i' => self.case_insensitive = true,
+                b's' => self.dot_all = true,
+                b')' => return Ok(()),
+                other => anyhow::bail!("不支持的正则表达式标志: {}", other as char),
+            }
+        }
+    }
+
+    fn parse_alternate(&mut self) -> Result<Node> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat(b"|") {
+            branches.push(self.parse_concat()?);
+        }
+        Ok(if branches.len() == 1 { branches.pop().unwrap_or(Node::Empty) } else { Node::Alternate(branches) })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node> {
+        let mut nodes = Vec::new();
+        while let Some(byte) = self.peek() {
+            if byte == b'|' || byte == b')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node> {
+        let byte = self.next()?;
+        let set = match byte {
+            b'(' => {
+                self.eat(b"?:");
+                let node = self.parse_alternate()?;
+                if !self.eat(b")") {
+                    anyhow::bail!("正则表达式中的 `(` 没有闭合");
+                }
+                return Ok(node);
+            }
+            b'^' => return Ok(Node::Start),
+            b'$' => return Ok(Node::End),
+            b'.' if self.dot_all => ByteSet::EMPTY.complement(),
+            b'.' => ByteSet::single(b'\n').complement(),
+            b'[' => self.parse_class()?,
+            b'\\' => self.parse_escape()?,
+            b'*' | b'+' | b'?' => anyhow::bail!("量词 `{}` 前面没有可重复的内容", byte as char),
+            byte => ByteSet::single(byte),
+        };
+        Ok(Node::Set(if self.case_insensitive { set.case_folded() } else { set }))
+    }
+
+    /// 反斜杠之后的转义
+    fn parse_escape(&mut self) -> Result<ByteSet> {
+        let digit = ByteSet::range(b'0', b'9');
+        let word = digit.union(ByteSet::range(b'a', b'z')).union(ByteSet::range(b'A', b'Z')).union(ByteSet::single(b'_'));
+        let space = [b' ', b'\t', b'\n', b'\r', 0x0b, 0x0c].into_iter().fold(ByteSet::EMPTY, |set, byte| set.union(ByteSet::single(byte)));
+        Ok(match self.next()? {
+            b'd' => digit,
+            b'D' => digit.complement(),
+            b'w' => word,
+            b'W' => word.complement(),
+            b's' => space,
+            b'S' => space.complement(),
+            b'n' => ByteSet::single(b'\n'),
+            b'r' => ByteSet::single(b'\r'),
+            b't' => ByteSet::single(b'\t'),
+            b'0' => ByteSet::single(0),
+            b'x' => {
+                let high = self.next()?;
+                let low = self.next()?;
+                let text = [high, low];
+                let hex = std::str::from_utf8(&text).ok().and_then(|text| u8::from_str_radix(text, 16).ok());
+                ByteSet::single(hex.ok_or_else(|| anyhow::anyhow!("`\\x` 之后应为两位十六进制数"))?)
+            }
+            byte if byte.is_ascii_alphanumeric() => anyhow::bail!("不支持的转义: \\{}", byte as char),
+            byte => ByteSet::single(byte),
+        })
+    }
+
+    /// `[` 之后的字符类
+    fn parse_class(&mut self) -> Result<ByteSet> {
+        let negated = self.eat(b"^");
+        let mut set = ByteSet::EMPTY;
+        let mut first = true;
+        loop {
+            let byte = self.next().map_err(|_| anyhow::anyhow!("正则表达式中的 `[` 没有闭合"))?;
+            if byte == b']' && !first {
+                break;
+            }
+            first = false;
+            let low = if byte == b'\\' {
+                let escaped = self.parse_escape()?;
+                // 多字节的类 (如 `\d`) 不能作为范围的端点
+                if escaped.0.iter().map(|word| word.count_ones()).sum::<u32>() != 1 {
+                    set = set.union(escaped);
+                    continue;
+                }
+                (0..=255u8).find(|&byte| escaped.contains(byte)).unwrap_or(0)
+            } else {
+                byte
+            };
+            if self.peek() == Some(b'-') && self.pattern.get(self.pos + 1).is_some_and(|&next| next != b']') {
+                self.pos += 1;
+                let high = match self.next()? {
+                    b'\\' => {
+                        let escaped = self.parse_escape()?;
+                        (0..=255u8).find(|&byte| escaped.contains(byte)).unwrap_or(0)
+                    }
+                    high => high,
+                };
+                if high < low {
+                    anyhow::bail!("字符类中的范围无效: {}-{}", low as char, high as char);
+                }
+                set = set.union(ByteSet::range(low, high));
+            } else {
+                set = set.union(ByteSet::single(low));
+            }
+        }
+        if self.case_insensitive {
+            set = set.case_folded();
+        }
+        Ok(if negated { set.complement() } else { set })
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node> {
+        let (min, max) = match self.peek() {
+            Some(b'*') => (0, None),
+            Some(b'+') => (1, None),
+            Some(b'?') => (0, Some(1)),
+            Some(b'{') => match self.parse_counts()? {
+                Some(counts) => return self.finish_repeat(atom, counts),
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+        self.finish_repeat(atom, (min, max))
+    }
+
+    /// 量词之后: 跳过非贪婪标记并生成重复节点
+    fn finish_repeat(&mut self, atom: Node, (min, max): (u32, Option<u32>)) -> Result<Node> {
+        // 非贪婪的 `?` 不影响是否匹配
+        self.eat(b"?");
+        if matches!(self.peek(), Some(b'*' | b'+' | b'?')) {
+            anyhow::bail!("量词不能连用: 位置 {}", self.pos);
+        }
+        Ok(Node::Repeat { node: Box::new(atom), min, max })
+    }
+
+    /// `{n}`、`{n,}`、`{n,m}`；不是量词的 `{` 按字面字符处理，返回None
+    fn parse_counts(&mut self) -> Result<Option<(u32, Option<u32>)>> {
+        let rest = &self.pattern[self.pos + 1..];
+        let Some(close) = rest.iter().position(|&byte| byte == b'}') else {
+            return Ok(None);
+        };
+        let text = std::str::from_utf8(&rest[..close]).unwrap_or("");
+        let parse = |text: &str| text.trim().parse::<u32>().ok();
+        let counts = match text.split_once(',') {
+            None => parse(text).map(|n| (n, Some(n))),
+            Some((min, "")) => parse(min).map(|min| (min, None)),
+            Some((min, max)) => parse(min).zip(parse(max)).map(|(min, max)| (min, Some(max))),
+        };
+        let Some((min, max)) = counts else {
+            return Ok(None);
+        };
+        if max.is_some_and(|max| max < min) {
+            anyhow::bail!("量词的范围无效: {{{}}}", text);
+        }
+        if min.max(max.unwrap_or(0)) > MAX_REPEAT {
+            anyhow::bail!("量词的重复次数不能超过 {}: {{{}}}", MAX_REPEAT, text);
+        }
+        self.pos += close + 2;
+        Ok(Some((min, max)))
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use anyhow::{Context, Result, anyhow};
+
+use crate::modules::pcap_error::PcapError;
+
+/// 流水线的检查点选项
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointOptions {
+    /// 流式步骤每处理多少个包保存一次检查点
+    pub interval: u64,
+    /// 从已保存的检查点继续
+    pub resume: bool,
+}
+
+/// 流式步骤的进度: 已读取到的输入偏移 (按PCAP格式计算)、已处理的包数和已写入的输出长度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepProgress {
+    pub input_offset: u64,
+    pub packets: u64,
+    pub output_len: u64,
+}
+
+/// 流式步骤的检查点: 从 `progress` 处继续，每处理 `interval` 个包调用一次 `save`
+///
+/// 调用 `save` 之前输出文件已同步到磁盘，因此检查点记录的输出长度总是完整写入的。
+pub struct StreamCheckpoint<'a> {
+    pub progress: StepProgress,
+    pub interval: u64,
+    pub save: &'a mut dyn FnMut(StepProgress) -> Result<()>,
+}
+
+/// 流水线检查点: 之前的步骤已完成，`step` 从 `current` 读取
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    /// 流水线配置、输入文件和输出路径的指纹，不一致时不能继续
+    pub fingerprint: u64,
+    /// 下一个要执行的步骤 (从0开始)
+    pub step: usize,
+    /// 该步骤的输入文件 (原始输入或上一步的中间文件)
+    pub current: String,
+    /// 该步骤已完成的部分，None表示从头开始
+    pub progress: Option<StepProgress>,
+}
+
+impl Checkpoint {
+    /// 输出文件对应的检查点文件
+    pub fn path_for(output_path: &str) -> String {
+        format!("{}.checkpoint", output_path)
+    }
+
+    /// 读取检查点文件，不存在时返回None
+    pub fn load(path: &str) -> Result<Option<Checkpoint>> {
+        let content = match fs::read_to_string(Path::new(path)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("无法读取检查点文件: {}", path)),
+        };
+
+        let fields: Vec<(&str, &str)> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.split_once('=').ok_or_else(|| anyhow!("检查点文件格式错误: {}", line)))
+            .collect::<Result<_>>()
+            .map_err(|e| PcapError::InvalidFormat(format!("{}: {:#}", path, e)))?;
+        let get = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| PcapError::InvalidFormat(format!("检查点文件缺少 {}: {}", key, path)))
+        };
+        let number = |key: &str| -> Result<u64> {
+            let value = get(key)?;
+            Ok(value.parse().map_err(|_| PcapError::InvalidFormat(format!("检查点文件中 {} 无效: {}", key, value)))?)
+        };
+
+        let fingerprint = u64::from_str_radix(get("fingerprint")?, 16)
+            .map_err(|_| PcapError::InvalidFormat(format!("检查点文件中 fingerprint 无效: {}", path)))?;
+        let progress = match get("packets") {
+            Ok(_) => Some(StepProgress {
+                input_offset: number("input_offset")?,
+                packets: number("packets")?,
+                output_len: number("output_len")?,
+            }),
+            Err(_) => None,
+        };
+        Ok(Some(Checkpoint {
+            fingerprint,
+            step: number("step")? as usize,
+            current: get("current")?.to_string(),
+            progress,
+        }))
+    }
+
+    /// 写入检查点文件 (先写临时文件再改名，中断时不会留下不完整的检查点)
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut content = format!(
+            "# pcap-editor 流水线检查点\nfingerprint={:016x}\nstep={}\ncurrent={}\n",
+            self.fingerprint, self.step, self.current
+        );
+        if let Some(progress) = &self.progress {
+            content.push_str(&format!(
+                "input_offset={}\npackets={}\noutput_len={}\n",
+                progress.input_offset, progress.packets, progress.output_len
+            ));
+        }
+
+        let temp_path = format!("{}.tmp", path);
+        let write = || -> std::io::Result<()> {
+            let mut file = File::create(Path::new(&temp_path))?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&temp_path, path)
+        };
+        write().map_err(|e| PcapError::WriteFailure(format!("无法写入检查点文件: {}: {}", path, e)))?;
+        Ok(())
+    }
+}
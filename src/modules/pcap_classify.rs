@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_flow_id::flow_id;
+use crate::modules::pcap_http_report::is_request_line;
+use crate::modules::pcap_name_resolution::read_dns_name;
+use crate::modules::pcap_packet_parser::{FlowKey, IP_PROTO_TCP, IP_PROTO_UDP, parse_packet, read_u16};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_quic::{QuicConnection, parse_long_header, version_name};
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, print_fields, print_heading, print_section, print_separator, print_verdict, verbosity,
+};
+use crate::modules::pcap_voip::parse_rtp;
+
+/// 流列表中最多列出的流数 (详细模式不限制)
+const MAX_FLOW_ROWS: usize = 50;
+
+/// 每个流最多检查的带载荷的包数，之后只按端口判断
+const MAX_INSPECTED: u32 = 10;
+
+/// 每个UDP流最多检查的QUIC长包头的包数 (取出版本、连接ID和ClientHello)
+const MAX_QUIC_LONG_HEADERS: u64 = 10;
+
+/// TLS记录的最大长度 (密文，2^14 + 2048)
+const MAX_TLS_RECORD: u16 = 16384 + 2048;
+
+/// 应用层协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum AppProtocol {
+    Http,
+    Tls,
+    Dns,
+    Ssh,
+    Quic,
+    Rtp,
+}
+
+impl AppProtocol {
+    fn name(self) -> &'static str {
+        match self {
+            AppProtocol::Http => "HTTP",
+            AppProtocol::Tls => "TLS",
+            AppProtocol::Dns => "DNS",
+            AppProtocol::Ssh => "SSH",
+            AppProtocol::Quic => "QUIC",
+            AppProtocol::Rtp => "RTP",
+        }
+    }
+}
+
+/// 识别的依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Evidence {
+    Signature,
+    Port,
+}
+
+/// 一个流 (不区分方向)，`key` 为第一个包的方向
+struct Flow {
+    key: FlowKey,
+    /// 流ID (见 `flow-ids`)
+    id: String,
+    packets: u64,
+    bytes: u64,
+    /// 按载荷特征识别出的协议
+    signature: Option<AppProtocol>,
+    /// 已检查的带载荷的包数
+    inspected: u32,
+    /// 每个方向上一个像RTP的包的 (SSRC, 序号)，同一SSRC的序号连续时才认为是RTP
+    last_rtp: [Option<(u32, u16)>; 2],
+    /// QUIC的版本和客户端Initial包中的ClientHello
+    quic: QuicConnection,
+}
+
+impl Flow {
+    fn classification(&self) -> Option<(AppProtocol, Evidence)> {
+        match self.signature {
+            Some(protocol) => Some((protocol, Evidence::Signature)),
+            None => port_protocol(&self.key).map(|protocol| (protocol, Evidence::Port)),
+        }
+    }
+
+    /// QUIC流的版本和服务器名称 (SNI)
+    fn details(&self) -> String {
+        let Some(version) = self.quic.version else {
+            return "-".to_string();
+        };
+        match self.quic.client_hello.as_ref().and_then(|hello| hello.server_name.as_ref()) {
+            Some(server_name) => format!("{} SNI={}", version_name(version), server_name),
+            None => version_name(version),
+        }
+    }
+}
+
+/// 一种协议的合计
+#[derive(Default)]
+struct Totals {
+    flows: usize,
+    by_signature: usize,
+    packets: u64,
+    bytes: u64,
+}
+
+/// 按载荷特征判断协议 (不需要同一个流中其他包的特征)
+fn match_signature(protocol: u8, payload: &[u8]) -> Option<AppProtocol> {
+    if protocol == IP_PROTO_TCP {
+        if payload.starts_with(b"SSH-") {
+            return Some(AppProtocol::Ssh);
+        }
+        if payload.starts_with(b"HTTP/1.") || is_request_line(payload) {
+            return Some(AppProtocol::Http);
+        }
+        if is_tls_record(payload) {
+            return Some(AppProtocol::Tls);
+        }
+        // TCP上的DNS带2字节的长度前缀
+        if payload.len() > 2 && read_u16(payload, 0) as usize == payload.len() - 2 && is_dns_message(&payload[2..]) {
+            return Some(AppProtocol::Dns);
+        }
+        return None;
+    }
+    if is_dns_message(payload) {
+        return Some(AppProtocol::Dns);
+    }
+    if parse_long_header(payload).is_some() {
+        return Some(AppProtocol::Quic);
+    }
+    None
+}
+
+/// TLS记录头: 内容类型 (20-23)、版本 3.x 和不超过上限的长度
+fn is_tls_record(payload: &[u8]) -> bool {
+    payload.len() >= 5
+        && (0x14..=0x17).contains(&payload[0])
+        && payload[1] == 0x03
+        && payload[2] <= 0x04
+        && (1..=MAX_TLS_RECORD).contains(&read_u16(payload, 3))
+}
+
+/// DNS消息: 只有一个问题，操作码有效，问题的名称和类型、类别完整
+fn is_dns_message(message: &[u8]) -> bool {
+    if message.len() < 17 || read_u16(message, 4) != 1 {
+        return false;
+    }
+    let opcode = (message[2] >> 3) & 0x0f;
+    if !matches!(opcode, 0 | 1 | 2 | 4 | 5) {
+        return false;
+    }
+    let Some((_, end)) = read_dns_name(message, 12) else {
+        return false;
+    };
+    // 类别: IN、CH、HS、ANY (mDNS的最高位为单播响应标志)
+    end + 4 <= message.len() && matches!(read_u16(message, end + 2) & 0x7fff, 1 | 3 | 4 | 255)
+}
+
+/// 按著名端口判断协议 (任一端口匹配；两端都匹配时取较小的端口)
+fn port_protocol(key: &FlowKey) -> Option<AppProtocol> {
+    let service = |port: u16| match (key.protocol, port) {
+        (IP_PROTO_TCP, 80 | 8000 | 8080) => Some(AppProtocol::Http),
+        (IP_PROTO_TCP, 443 | 853 | 8443) => Some(AppProtocol::Tls),
+        (IP_PROTO_TCP, 22) => Some(AppProtocol::Ssh),
+        (IP_PROTO_TCP | IP_PROTO_UDP, 53) | (IP_PROTO_UDP, 5353) => Some(AppProtocol::Dns),
+        (IP_PROTO_UDP, 443) => Some(AppProtocol::Quic),
+        _ => None,
+    };
+    let (low, high) = (key.src_port.min(key.dst_port), key.src_port.max(key.dst_port));
+    service(low).or_else(|| service(high))
+}
+
+/// 按端口和载荷特征识别每个流的应用协议，统计各协议的流数、包数和字节数
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+///
+/// # 说明
+/// 1. 流按TCP/UDP五元组 (不区分方向) 划分，其他包只计数
+/// 2. 检查每个流前 10 个带载荷的包: HTTP (请求行或状态行)、TLS (记录头)、SSH (版本串)、
+///    DNS (问题段完整的消息，TCP上带长度前缀)、QUIC (v1、v2或草案版本的长包头)、RTP (同一SSRC的序号连续的两个包)
+/// 3. 载荷没有特征时按著名端口判断 (80/8080 HTTP、443 TLS、UDP 443 QUIC、53 DNS、22 SSH 等)，
+///    报告中标明依据；两者都没有时为未知
+/// 4. QUIC流的详情为版本和服务器名称 (SNI，解密客户端的Initial包得到)，TLS握手的详细信息见 `tls` 命令
+pub fn classify_flows(input_path: &str) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut flows: Vec<Flow> = Vec::new();
+    let mut index: HashMap<FlowKey, usize> = HashMap::new();
+    let mut packet_count = 0u64;
+    let mut other_packets = 0u64;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let Some(key) = parsed.flow_key().filter(|key| matches!(key.protocol, IP_PROTO_TCP | IP_PROTO_UDP)) else {
+            other_packets += 1;
+            continue;
+        };
+        let flow_index = *index.entry(key.canonical()).or_insert_with(|| {
+            let time_us = interface.timestamp_ns(packet.timestamp).div_euclid(1000) as i64;
+            flows.push(Flow {
+                key,
+                id: flow_id(&key, time_us),
+                packets: 0,
+                bytes: 0,
+                signature: None,
+                inspected: 0,
+                last_rtp: [None; 2],
+                quic: QuicConnection::default(),
+            });
+            flows.len() - 1
+        });
+        let flow = &mut flows[flow_index];
+        flow.packets += 1;
+        flow.bytes += packet.orig_len as u64;
+
+        let payload = &data[parsed.payload_range(data.len())];
+        if key.protocol == IP_PROTO_UDP
+            && matches!(flow.signature, None | Some(AppProtocol::Quic))
+            && flow.quic.client_hello.is_none()
+            && flow.quic.long_header_packets < MAX_QUIC_LONG_HEADERS
+        {
+            flow.quic.add_datagram(key, payload);
+        }
+        if flow.signature.is_some() || flow.inspected >= MAX_INSPECTED || payload.is_empty() {
+            continue;
+        }
+        flow.inspected += 1;
+        flow.signature = match_signature(key.protocol, payload);
+        if flow.signature.is_none()
+            && key.protocol == IP_PROTO_UDP
+            && let Some(rtp) = parse_rtp(payload)
+        {
+            let last = &mut flow.last_rtp[usize::from(flow.key != key)];
+            if last.is_some_and(|(ssrc, seq)| ssrc == rtp.ssrc && (1..=16).contains(&rtp.seq.wrapping_sub(seq))) {
+                flow.signature = Some(AppProtocol::Rtp);
+            }
+            *last = Some((rtp.ssrc, rtp.seq));
+        }
+    }
+
+    let mut totals: HashMap<Option<AppProtocol>, Totals> = HashMap::new();
+    for flow in &flows {
+        let classification = flow.classification();
+        let entry = totals.entry(classification.map(|(protocol, _)| protocol)).or_default();
+        entry.flows += 1;
+        entry.packets += flow.packets;
+        entry.bytes += flow.bytes;
+        if classification.is_some_and(|(_, evidence)| evidence == Evidence::Signature) {
+            entry.by_signature += 1;
+        }
+    }
+    let by_signature = flows.iter().filter(|flow| flow.signature.is_some()).count();
+    let unknown = totals.get(&None).map_or(0, |totals| totals.flows);
+    let flow_packets: u64 = flows.iter().map(|flow| flow.packets).sum();
+    let flow_bytes: u64 = flows.iter().map(|flow| flow.bytes).sum();
+    let percent = |part: u64, total: u64| if total > 0 { part as f64 * 100.0 / total as f64 } else { 0.0 };
+
+    print_heading(&format!("应用协议识别: {}", input_path));
+    print_fields(&[
+        ("数据包数", packet_count.to_string(), None),
+        ("TCP/UDP流数", flows.len().to_string(), None),
+        ("按载荷特征识别", by_signature.to_string(), None),
+        ("只按端口识别", (flows.len() - by_signature - unknown).to_string(), None),
+        ("未识别", unknown.to_string(), (unknown > 0).then_some(Severity::Warning)),
+        ("非TCP/UDP包数", other_packets.to_string(), None),
+    ]);
+
+    if !flows.is_empty() {
+        print_section("按应用协议:");
+        let mut rows: Vec<(Option<AppProtocol>, &Totals)> = totals.iter().map(|(protocol, totals)| (*protocol, totals)).collect();
+        // 字节数从大到小，未知排在最后
+        rows.sort_by(|a, b| {
+            a.0.is_none()
+                .cmp(&b.0.is_none())
+                .then(b.1.bytes.cmp(&a.1.bytes))
+                .then(a.0.map(AppProtocol::name).cmp(&b.0.map(AppProtocol::name)))
+        });
+        let mut table = Table::new(&["协议", "流数", "特征识别", "端口识别", "包数", "包占比", "字节数", "字节占比"])
+            .right_align(&[1, 2, 3, 4, 5, 6, 7]);
+        for (protocol, totals) in rows {
+            let by_port = if protocol.is_some() { totals.flows - totals.by_signature } else { 0 };
+            table.add_row(vec![
+                protocol.map_or("未知", AppProtocol::name).to_string(),
+                totals.flows.to_string(),
+                totals.by_signature.to_string(),
+                by_port.to_string(),
+                totals.packets.to_string(),
+                format!("{:.2}%", percent(totals.packets, flow_packets)),
+                totals.bytes.to_string(),
+                format!("{:.2}%", percent(totals.bytes, flow_bytes)),
+            ]);
+        }
+        table.print();
+
+        let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_FLOW_ROWS };
+        let mut sorted: Vec<&Flow> = flows.iter().collect();
+        sorted.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.key.cmp(&b.key)));
+        print_section("流 (按字节数，五元组为第一个包的方向):");
+        let mut table = Table::new(&["流ID", "五元组", "协议", "依据", "详情", "包数", "字节数"]).right_align(&[5, 6]);
+        for flow in sorted.iter().take(max_rows) {
+            let (protocol, evidence) = match flow.classification() {
+                Some((protocol, Evidence::Signature)) => (protocol.name(), "载荷特征"),
+                Some((protocol, Evidence::Port)) => (protocol.name(), "端口"),
+                None => ("未知", "-"),
+            };
+            table.add_row(vec![
+                flow.id.clone(),
+                flow.key.to_string(),
+                protocol.to_string(),
+                evidence.to_string(),
+                flow.details(),
+                flow.packets.to_string(),
+                flow.bytes.to_string(),
+            ]);
+        }
+        table.print();
+        if sorted.len() > max_rows {
+            println!("  ... 还有 {} 个流 (使用 --verbose 查看全部)", sorted.len() - max_rows);
+        }
+    }
+
+    print_separator();
+    if flows.is_empty() {
+        print_verdict(Severity::Warning, "没有TCP/UDP流");
+    } else if unknown == 0 {
+        print_verdict(Severity::Ok, &format!("所有 {} 个流都识别出了应用协议", flows.len()));
+    } else {
+        let unknown_bytes = totals.get(&None).map_or(0, |totals| totals.bytes);
+        print_verdict(
+            Severity::Warning,
+            &format!(
+                "{} 个流 ({:.2}% 的字节) 未能识别应用协议 (私有协议、加密隧道或不在识别范围内)",
+                unknown,
+                percent(unknown_bytes, flow_bytes)
+            ),
+        );
+    }
+    Ok(())
+}
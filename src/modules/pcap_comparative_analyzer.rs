@@ -1,199 +1,450 @@
-use std::path::Path;
-use std::fs::File;
-use pcap_file::PcapReader;
+use pcap_file::pcap_header::PcapHeader;
+use pcap_file::Packet;
 use seahash::SeaHasher;
+use std::collections::BTreeMap;
+use std::fs::File;
 use std::hash::Hasher;
-use anyhow::{Context, Result, anyhow};
+use std::thread;
+use anyhow::{Context, Result};
+use log::{info, warn};
+
+use crate::modules::io::PacketReader;
+use crate::modules::pcap_annotate::write_annotated;
+use crate::modules::pcap_baseline::{Baseline, load_baseline};
+use crate::modules::pcap_error::{PCAP_HEADER_LEN, PCAP_RECORD_HEADER_LEN};
+use crate::modules::pcap_packet_parser::parse_packet;
+use crate::modules::pcap_report::{
+    Severity, Table, format_timestamp_us, print_fields, print_heading, print_section, print_separator, print_verdict,
+};
+use crate::modules::pcap_sequence_diff::diff_matches;
+use crate::modules::pcap_index::read_packet_at;
+use crate::modules::pcap_input::is_remote;
+use crate::modules::pcap_time_anchor::{ticks_per_second, timestamp_to_us};
+
+/// 每批读入后并行哈希的最大包数和字节数 (限制内存占用)
+const HASH_BATCH_PACKETS: usize = 8192;
+const HASH_BATCH_BYTES: usize = 64 << 20;
+
+/// 比较选项
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompareOptions {
+    /// 忽略时间戳
+    pub ignore_timestamp: bool,
+    /// 只比较应用层载荷 (忽略二到四层头部以及没有载荷的包)
+    pub payload_only: bool,
+    /// 忽略IP包之后的以太网填充和尾部 (如FCS)
+    pub strip_padding: bool,
+}
 
 /// 比较两个PCAP文件的内容差异（顺序大致相同）
 /// 
 /// # 参数
-/// - `pcap1_path`: 基准PCAP文件路径
-/// - `pcap2_path`: 对比PCAP文件路径
+/// - `pcap1_path`: 基准文件路径 (PCAP或pcapng)
+/// - `pcap2_path`: 对比文件路径 (PCAP或pcapng)
+/// - `options`: 比较选项
+/// - `annotate_paths`: 基准文件和对比文件的注释副本输出路径 (None表示不输出)
 /// 
 /// # 输出
 /// - 打印pcap2相对于pcap1的丢失包和多余包
+/// - 需要时把两个文件分别复制为pcapng，为丢失包和多余包添加注释
 pub fn compare_ordered_pcaps(
     pcap1_path: &str,
     pcap2_path: &str,
-    ignore_timestamp: bool,
+    options: CompareOptions,
+    annotate_paths: [Option<&str>; 2],
 ) -> Result<()> {
-    // 打开文件
-    let file1 = File::open(Path::new(pcap1_path))
-        .with_context(|| format!("无法打开基准文件: {}", pcap1_path))?;
-    let mut pcap1_reader = PcapReader::new(file1)
-        .map_err(|e| anyhow!("无效的PCAP文件格式 (基准文件): {}", e))?;
-    
-    let file2 = File::open(Path::new(pcap2_path))
-        .with_context(|| format!("无法打开对比文件: {}", pcap2_path))?;
-    let mut pcap2_reader = PcapReader::new(file2)
-        .map_err(|e| anyhow!("无效的PCAP文件格式 (对比文件): {}", e))?;
-
-    // 读取所有包并计算哈希
-    let packets1 = read_and_hash_packets(&mut pcap1_reader, ignore_timestamp)?;
-    let packets2 = read_and_hash_packets(&mut pcap2_reader, ignore_timestamp)?;
-    
-    // 初始化变量
-    let mut i = 0; // pcap1索引
-    let mut j = 0; // pcap2索引
-    let mut missing_packets = Vec::new(); // 丢失包
-    let mut extra_packets = Vec::new();   // 多余包
-    
-    // 主比较循环
-    while i < packets1.len() && j < packets2.len() {
-        // 当前包匹配
-        if packets1[i].hash == packets2[j].hash {
-            i += 1;
-            j += 1;
-            continue;
-        }
-        
-        // 查找下一个匹配点
-        let mut found_match = false;
-        
-        // 向前查找匹配点（最多100个包）
-        let max_lookahead = 100;
-        let max_i = (i + max_lookahead).min(packets1.len());
-        let max_j = (j + max_lookahead).min(packets2.len());
-        
-        // 尝试在pcap2中查找当前pcap1包
-        for k in j..max_j {
-            if packets1[i].hash == packets2[k].hash {
-                // j到k之间的包是多余包
-                for idx in j..k {
-                    extra_packets.push((idx, packets2[idx].clone()));
-                }
-                j = k + 1;
-                i += 1;
-                found_match = true;
-                break;
-            }
-        }
-        
-        if found_match {
-            continue;
-        }
-        
-        // 尝试在pcap1中查找当前pcap2包
-        for k in i..max_i {
-            if packets1[k].hash == packets2[j].hash {
-                // i到k之间的包是丢失包
-                for idx in i..k {
-                    missing_packets.push((idx, packets1[idx].clone()));
-                }
-                i = k + 1;
-                j += 1;
-                found_match = true;
-                break;
-            }
-        }
-        
-        if found_match {
-            continue;
-        }
-        
-        // 未找到匹配 - 记录差异
-        missing_packets.push((i, packets1[i].clone()));
-        extra_packets.push((j, packets2[j].clone()));
-        i += 1;
-        j += 1;
-    }
+    // 读取所有包并计算哈希 (只保留序号、偏移、长度和哈希)
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let (format1, packets1) = open_and_hash(pcap1_path, "基准文件", options, threads)?;
+    let (format2, packets2) = open_and_hash(pcap2_path, "对比文件", options, threads)?;
     
-    // 处理剩余包
-    while i < packets1.len() {
-        missing_packets.push((i, packets1[i].clone()));
-        i += 1;
+    let sources = [
+        PacketSource::open(pcap1_path, format1),
+        PacketSource::open(pcap2_path, format2),
+    ];
+    report_differences(&packets1, &packets2, sources, options, [Some(pcap1_path), Some(pcap2_path)], annotate_paths)
+}
+
+/// 把对比文件与保存的基准比较 (基准由 `baseline save` 保存，不需要参考抓包本身)
+///
+/// # 参数
+/// - `baseline_name`: 基准名称
+/// - `pcap2_path`: 对比文件路径 (PCAP或pcapng)
+/// - `options`: 比较选项，未指定时使用保存基准时的选项，指定时必须与之相同
+/// - `annotate_path`: 对比文件的注释副本输出路径 (None表示不输出)
+///
+/// # 输出
+/// 与 [`compare_ordered_pcaps`] 相同，丢失包的时间戳和五元组取自基准中的记录
+pub fn compare_with_baseline(
+    baseline_name: &str,
+    pcap2_path: &str,
+    options: CompareOptions,
+    annotate_path: Option<&str>,
+) -> Result<()> {
+    let baseline = load_baseline(baseline_name)?;
+    let options = baseline.resolve_options(options)?;
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let (format2, packets2) = open_and_hash(pcap2_path, "对比文件", options, threads)?;
+    if baseline.linktype != u32::from(format2.header.datalink) {
+        warn!(
+            "基准 {} 的链路类型 ({}) 与对比文件 ({}) 不同，包的哈希可能无法对应",
+            baseline_name,
+            baseline.linktype,
+            u32::from(format2.header.datalink)
+        );
     }
+    info!("使用基准 {}: 源文件={}, 参与比较的包数={}", baseline_name, baseline.source, baseline.packets.len());
+    let packets1: Vec<PacketWithHash> = baseline.packets
+        .iter()
+        .map(|packet| PacketWithHash { index: packet.index, offset: 0, len: packet.len, hash: packet.hash })
+        .collect();
+    let sources = [PacketSource::baseline(baseline), PacketSource::open(pcap2_path, format2)];
+    report_differences(&packets1, &packets2, sources, options, [None, Some(pcap2_path)], [None, annotate_path])
+}
+
+/// 求最小差异并打印结果，需要时输出带注释的副本 (`inputs` 为None的一侧不能输出副本)
+fn report_differences(
+    packets1: &[PacketWithHash],
+    packets2: &[PacketWithHash],
+    sources: [PacketSource; 2],
+    options: CompareOptions,
+    inputs: [Option<&str>; 2],
+    annotate_paths: [Option<&str>; 2],
+) -> Result<()> {
+    // 求最小差异：未匹配的基准包为丢失包，未匹配的对比包为多余包
+    let (matched1, matched2) = match_packets(packets1, packets2);
+
+    let missing_packets: Vec<(usize, PacketWithHash)> = packets1.iter().cloned().enumerate()
+        .filter(|(idx, _)| !matched1[*idx])
+        .map(|(_, packet)| (packet.index, packet))
+        .collect();
+    let extra_packets: Vec<(usize, PacketWithHash)> = packets2.iter().cloned().enumerate()
+        .filter(|(idx, _)| !matched2[*idx])
+        .map(|(_, packet)| (packet.index, packet))
+        .collect();
     
-    while j < packets2.len() {
-        extra_packets.push((j, packets2[j].clone()));
-        j += 1;
+    // 打印结果，详情中的时间戳和五元组从文件 (或基准) 中重新读取
+    print_comparison_results(packets1, packets2, &missing_packets, &extra_packets, sources, options)?;
+
+    let annotations = [
+        (inputs[0], &missing_packets, "丢失: 对比文件中没有这个包"),
+        (inputs[1], &extra_packets, "多余: 基准文件中没有这个包"),
+    ];
+    for ((input_path, packets, comment), annotate_path) in annotations.into_iter().zip(annotate_paths) {
+        let (Some(input_path), Some(annotate_path)) = (input_path, annotate_path) else {
+            continue;
+        };
+        let comments: BTreeMap<u64, Vec<String>> = packets
+            .iter()
+            .map(|(index, _)| (*index as u64 + 1, vec![comment.to_string()]))
+            .collect();
+        write_annotated(input_path, annotate_path, &comments, "pcap-editor compare")?;
+        info!("成功输出带注释的副本: 注释的包数={}, 输出文件={}", comments.len(), annotate_path);
     }
     
-    // 打印结果
-    print_comparison_results(&packets1, &packets2, &missing_packets, &extra_packets);
-    
     Ok(())
 }
 
-/// 读取PCAP文件并计算每个包的哈希值
-/// 读取PCAP文件并计算每个包的哈希值
+/// 一对文件的比较结果 (只有数量和哈希，不输出详情)
+#[derive(Debug, Clone, Default)]
+pub struct CompareCounts {
+    pub reference_packets: usize,
+    pub comparison_packets: usize,
+    /// 基准文件中有、对比文件中没有的包的哈希
+    pub missing: Vec<u64>,
+    /// 对比文件中有、基准文件中没有的包的哈希
+    pub extra: Vec<u64>,
+}
+
+/// 比较两个PCAP文件，只统计丢失包和多余包的数量
+///
+/// 某一侧为None时视为空文件 (另一侧的包全部计为丢失或多余)；`threads` 为哈希使用的线程数。
+pub fn compare_counts(
+    pcap1_path: Option<&str>,
+    pcap2_path: Option<&str>,
+    options: CompareOptions,
+    threads: usize,
+) -> Result<CompareCounts> {
+    let hash = |path: Option<&str>, role: &str| -> Result<Vec<PacketWithHash>> {
+        match path {
+            Some(path) => Ok(open_and_hash(path, role, options, threads)?.1),
+            None => Ok(Vec::new()),
+        }
+    };
+    let packets1 = hash(pcap1_path, "基准文件")?;
+    let packets2 = hash(pcap2_path, "对比文件")?;
+    let (matched1, matched2) = match_packets(&packets1, &packets2);
+    Ok(CompareCounts {
+        reference_packets: packets1.len(),
+        comparison_packets: packets2.len(),
+        missing: packets1.iter().zip(&matched1).filter(|(_, matched)| !**matched).map(|(p, _)| p.hash).collect(),
+        extra: packets2.iter().zip(&matched2).filter(|(_, matched)| !**matched).map(|(p, _)| p.hash).collect(),
+    })
+}
+
+/// 求两边包的最小差异，返回每个包是否在另一边找到了对应的包
+fn match_packets(packets1: &[PacketWithHash], packets2: &[PacketWithHash]) -> (Vec<bool>, Vec<bool>) {
+    let hashes1: Vec<u64> = packets1.iter().map(|p| p.hash).collect();
+    let hashes2: Vec<u64> = packets2.iter().map(|p| p.hash).collect();
+    let mut matched1 = vec![false; packets1.len()];
+    let mut matched2 = vec![false; packets2.len()];
+    for (i, j) in diff_matches(&hashes1, &hashes2) {
+        matched1[i] = true;
+        matched2[j] = true;
+    }
+    (matched1, matched2)
+}
+
+/// 打开PCAP文件并计算每个包的哈希值，`role` 用于错误信息 (基准文件/对比文件)
+fn open_and_hash(
+    path: &str,
+    role: &str,
+    options: CompareOptions,
+    threads: usize,
+) -> Result<(InputFormat, Vec<PacketWithHash>)> {
+    let mut reader = PacketReader::open(path).with_context(|| format!("无法读取{}", role))?;
+    let packets = read_and_hash_packets(&mut reader, options, threads)?;
+    Ok((InputFormat { header: reader.header, sequential: reader.is_pcapng() || reader.is_compressed() }, packets))
+}
+
+/// 读取输入文件并计算每个包的哈希值
+///
+/// 包按批读入，每批分块交给多个线程哈希，处理完即释放，不在内存中保留包内容。
 fn read_and_hash_packets(
-    reader: &mut PcapReader<File>,
-    ignore_timestamp: bool,
+    reader: &mut PacketReader,
+    options: CompareOptions,
+    threads: usize,
 ) -> Result<Vec<PacketWithHash>> {
     let mut packets = Vec::new();
+    let linktype: u32 = reader.header.datalink.into();
+    let mut read_bytes = PCAP_HEADER_LEN;
+    let mut batch = Vec::with_capacity(HASH_BATCH_PACKETS);
+    let mut batch_bytes = 0;
     
-    while let Some(packet) = reader.next() {
-        let mut hasher = SeaHasher::new();
-        
-        if ignore_timestamp {
-            // 忽略时间戳的哈希计算
-            let mut buffer = Vec::new();
-            buffer.extend_from_slice(&packet.header.incl_len.to_be_bytes());
-            buffer.extend_from_slice(&packet.header.orig_len.to_be_bytes());
-            buffer.extend_from_slice(&packet.data);
-            hasher.write(&buffer);
-        } else {
-            // 包含完整头部和数据的哈希计算
-            hasher.write(&packet.data);
+    for (index, packet) in reader.by_ref().enumerate() {
+        let offset = read_bytes;
+        read_bytes += PCAP_RECORD_HEADER_LEN + packet.data.len() as u64;
+        batch_bytes += packet.data.len();
+        batch.push((index, offset, packet));
+        if batch.len() >= HASH_BATCH_PACKETS || batch_bytes >= HASH_BATCH_BYTES {
+            hash_batch(&batch, linktype, options, threads, &mut packets);
+            batch.clear();
+            batch_bytes = 0;
         }
-        
-        let hash = hasher.finish();
-        
-        packets.push(PacketWithHash {
-            original: packet,
-            hash,
-        });
     }
+    hash_batch(&batch, linktype, options, threads, &mut packets);
+    // 截断的文件会被误报为丢包
+    reader.check_complete()?;
     
     Ok(packets)
 }
 
-/// 带哈希值的包结构
+/// 并行计算一批包的哈希，结果按原顺序追加到 `packets`
+fn hash_batch(
+    batch: &[(usize, u64, Packet<'static>)],
+    linktype: u32,
+    options: CompareOptions,
+    threads: usize,
+    packets: &mut Vec<PacketWithHash>,
+) {
+    let chunk_size = batch.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = batch
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter_map(|(index, offset, packet)| {
+                            let hash = packet_hash(packet, linktype, options)?;
+                            Some(PacketWithHash { index: *index, offset: *offset, len: packet.data.len(), hash })
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            packets.extend(handle.join().unwrap());
+        }
+    });
+}
+
+/// 按比较选项计算单个包的哈希值；不参与比较的包返回None
+pub fn packet_hash(packet: &Packet, linktype: u32, options: CompareOptions) -> Option<u64> {
+    let mut hasher = SeaHasher::new();
+    
+    if options.payload_only {
+        // 只哈希应用层载荷，没有载荷的包 (如纯ACK) 不参与比较
+        let range = parse_packet(&packet.data, linktype).payload_range(packet.data.len());
+        if range.is_empty() {
+            return None;
+        }
+        hasher.write(&packet.data[range]);
+    } else if options.strip_padding {
+        // 按IP长度字段截掉链路层填充 (长度字段随之不同，不参与哈希)
+        let end = parse_packet(&packet.data, linktype)
+            .ip
+            .map(|ip| ip.end().min(packet.data.len()))
+            .unwrap_or(packet.data.len());
+        hasher.write(&packet.data[..end]);
+    } else if options.ignore_timestamp {
+        // 忽略时间戳的哈希计算
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&packet.header.incl_len.to_be_bytes());
+        buffer.extend_from_slice(&packet.header.orig_len.to_be_bytes());
+        buffer.extend_from_slice(&packet.data);
+        hasher.write(&buffer);
+    } else {
+        // 包含完整头部和数据的哈希计算
+        hasher.write(&packet.data);
+    }
+    
+    Some(hasher.finish())
+}
+
+/// 带哈希值的包结构 (只保留报告所需的长度和偏移，不缓存包内容)
 #[derive(Clone)]
 struct PacketWithHash {
-    original: pcap_file::Packet<'static>, // 使用'static生命周期
+    index: usize, // 在原文件中的序号
+    offset: u64, // 包记录在文件中的偏移 (只对PCAP文件有效)
+    len: usize,
     hash: u64, // 使用64位哈希足够
 }
 
+/// 输入文件的格式
+struct InputFormat {
+    header: PcapHeader,
+    /// pcapng或压缩的输入只能顺序读取
+    sequential: bool,
+}
+
+/// 重新读取包内容，用于输出差异详情
+struct PacketSource {
+    reader: SourceReader,
+    header: PcapHeader,
+    /// 第一个包的时间戳 (相对时间格式的起点)
+    start_us: i64,
+}
+
+enum SourceReader {
+    /// PCAP文件按偏移随机访问
+    Pcap(File),
+    /// pcapng或压缩的文件按序号顺序读取 (详情按序号从小到大输出)；`next_index` 为下一个读出的包的序号
+    Sequential { reader: Box<PacketReader>, next_index: usize },
+    /// 保存的基准 (详情取自基准中记录的时间戳和五元组)
+    Baseline(Box<Baseline>),
+    /// 远程输入无法再次读取
+    Unavailable,
+}
+
+impl PacketSource {
+    fn open(path: &str, format: InputFormat) -> PacketSource {
+        let header = format.header;
+        let mut reader = match (is_remote(path), format.sequential) {
+            (true, _) => None,
+            (false, false) => File::open(path).ok().map(SourceReader::Pcap),
+            (false, true) => PacketReader::open(path).ok().map(|reader| SourceReader::Sequential { reader: Box::new(reader), next_index: 0 }),
+        }
+        .unwrap_or(SourceReader::Unavailable);
+        let first = match &mut reader {
+            SourceReader::Pcap(file) => read_packet_at(file, &header, PCAP_HEADER_LEN).ok(),
+            // 读出的第一个包不消耗，重新打开后再读取详情
+            SourceReader::Sequential { .. } => PacketReader::open(path).ok().and_then(|mut reader| reader.next()),
+            SourceReader::Baseline(_) | SourceReader::Unavailable => None,
+        };
+        let start_us = first
+            .map(|packet| timestamp_to_us(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second(&header)))
+            .unwrap_or_default();
+        PacketSource { reader, header, start_us }
+    }
+
+    fn baseline(baseline: Baseline) -> PacketSource {
+        let header = PcapHeader::with_datalink(baseline.linktype.into());
+        let start_us = baseline.start_us;
+        PacketSource { reader: SourceReader::Baseline(Box::new(baseline)), header, start_us }
+    }
+
+    /// 包的时间戳和五元组
+    fn describe(&mut self, packet: &PacketWithHash) -> Result<(String, String)> {
+        let packet = match &mut self.reader {
+            SourceReader::Pcap(file) => read_packet_at(file, &self.header, packet.offset)?,
+            SourceReader::Sequential { reader, next_index } => {
+                let skip = packet.index.checked_sub(*next_index)
+                    .ok_or_else(|| anyhow::anyhow!("包#{}已经读过", packet.index))?;
+                let found = reader.nth(skip)
+                    .ok_or_else(|| anyhow::anyhow!("无法重新读取包#{}", packet.index))?;
+                *next_index = packet.index + 1;
+                found
+            }
+            SourceReader::Baseline(baseline) => {
+                let recorded = baseline.packets.binary_search_by_key(&packet.index, |recorded| recorded.index)
+                    .map(|position| baseline.packets[position])
+                    .map_err(|_| anyhow::anyhow!("基准中没有包#{}", packet.index))?;
+                let flow = match recorded.flow {
+                    Some(flow) => baseline.flows[flow as usize].clone(),
+                    None => "(非IP包)".to_string(),
+                };
+                return Ok((format_timestamp_us(recorded.time_us, self.start_us), flow));
+            }
+            SourceReader::Unavailable => return Ok(("-".to_string(), "-".to_string())),
+        };
+        let flow = match parse_packet(&packet.data, self.header.datalink.into()).flow_key() {
+            Some(key) => key.to_string(),
+            None => "(非IP包)".to_string(),
+        };
+        let time_us = timestamp_to_us(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second(&self.header));
+        Ok((format_timestamp_us(time_us, self.start_us), flow))
+    }
+}
+
 /// 打印比较结果
 fn print_comparison_results(
     pcap1: &[PacketWithHash],
     pcap2: &[PacketWithHash],
     missing: &[(usize, PacketWithHash)],
     extra: &[(usize, PacketWithHash)],
-) {
-    println!("PCAP内容比较结果:");
-    println!("- 基准文件包数: {}", pcap1.len());
-    println!("- 对比文件包数: {}", pcap2.len());
-    println!("- 丢失包数: {}", missing.len());
-    println!("- 多余包数: {}", extra.len());
-    
-    // 打印丢失包详情
-    if !missing.is_empty() {
-        println!("\n丢失包详情 (存在于基准文件但不在对比文件中):");
-        for (idx, packet) in missing {
-            let packet_size = packet.original.data.len();
-            println!("  [基准包 {}] 长度: {} 字节, 哈希: {:016x}", 
-                idx, packet_size, packet.hash);
-        }
+    sources: [PacketSource; 2],
+    options: CompareOptions,
+) -> Result<()> {
+    print_heading("PCAP内容比较结果:");
+    let mut fields = Vec::new();
+    if options.payload_only {
+        fields.push(("比较模式", "只比较应用层载荷 (已跳过无载荷的包)".to_string(), None));
+    } else if options.strip_padding {
+        fields.push(("比较模式", "忽略以太网填充和尾部".to_string(), None));
     }
+    let difference = |count: usize| (count > 0).then_some(Severity::Warning);
+    fields.push(("基准文件包数", pcap1.len().to_string(), None));
+    fields.push(("对比文件包数", pcap2.len().to_string(), None));
+    fields.push(("丢失包数", missing.len().to_string(), difference(missing.len())));
+    fields.push(("多余包数", extra.len().to_string(), difference(extra.len())));
+    print_fields(&fields);
     
-    // 打印多余包详情
-    if !extra.is_empty() {
-        println!("\n多余包详情 (存在于对比文件但不在基准文件中):");
-        for (idx, packet) in extra {
-            let packet_size = packet.original.data.len();
-            println!("  [对比包 {}] 长度: {} 字节, 哈希: {:016x}", 
-                idx, packet_size, packet.hash);
+    // 打印丢失包和多余包详情
+    let [source1, source2] = sources;
+    let details = [
+        ("丢失包详情 (存在于基准文件但不在对比文件中):", "基准包", missing, source1),
+        ("多余包详情 (存在于对比文件但不在基准文件中):", "对比包", extra, source2),
+    ];
+    for (title, column, packets, mut source) in details {
+        if packets.is_empty() {
+            continue;
+        }
+        print_section(title);
+        let mut table = Table::new(&[column, "时间戳", "长度 (字节)", "五元组", "哈希"]).right_align(&[0, 2]);
+        for (idx, packet) in packets {
+            let (timestamp, flow) = source.describe(packet)?;
+            table.add_row(vec![idx.to_string(), timestamp, packet.len.to_string(), flow, format!("{:016x}", packet.hash)]);
         }
+        table.print();
     }
     
     // 总结
+    print_separator();
     if missing.is_empty() && extra.is_empty() {
-        println!("\n✅ 两个PCAP文件内容完全一致");
+        print_verdict(Severity::Ok, "两个PCAP文件内容完全一致");
     } else {
-        println!("\n⚠️ 发现内容差异");
+        print_verdict(Severity::Warning, "发现内容差异");
     }
-}
\ No newline at end of file
+    Ok(())
+}
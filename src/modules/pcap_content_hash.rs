@@ -0,0 +1,127 @@
+use std::hash::Hasher;
+use anyhow::Result;
+use log::debug;
+use seahash::SeaHasher;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{Transport, parse_packet};
+use crate::modules::pcap_padding::strip_padding;
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+
+/// 计算内容哈希时忽略的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HashIgnore {
+    /// 时间戳
+    Timestamps,
+    /// IPv4标识字段
+    IpId,
+    /// IPv4头校验和与TCP/UDP/ICMP校验和
+    Checksums,
+    /// IPv4的TTL和IPv6的跳数限制
+    Ttl,
+    /// IP包之后的以太网填充和FCS (原始长度随之不同，同样忽略)
+    Padding,
+    /// 包的先后顺序
+    Order,
+}
+
+/// 计算整个抓包文件规范化内容的哈希
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `ignore`: 忽略的字段
+///
+/// # 说明
+/// 1. 每个包按链路类型、时间戳 (纳秒)、原始长度和数据计算哈希，忽略的字段按零计算；
+///    同一时刻的时间戳精度不同 (微秒、纳秒) 不影响结果，PCAP和pcapng的同一内容哈希相同
+/// 2. 忽略顺序时各包的哈希按加法合并，否则按顺序合并
+/// 3. 输出格式与 `sha256sum` 相同 (`哈希  文件名`)，用于快速判断两个抓包是否等价；
+///    哈希不同时再用 `compare` 查看差异
+pub fn pcap_content_hash(input_path: &str, ignore: &[HashIgnore]) -> Result<()> {
+    let ignored = |field: HashIgnore| ignore.contains(&field);
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut ordered = SeaHasher::new();
+    let mut unordered = 0u64;
+    let mut packet_count = 0u64;
+    let mut data = Vec::new();
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let linktype = interface.linktype as u32;
+        data.clear();
+        data.extend_from_slice(&packet.data);
+        canonicalize(&mut data, linktype, &ignored);
+
+        let mut hasher = SeaHasher::new();
+        hasher.write_u32(linktype);
+        if !ignored(HashIgnore::Timestamps) {
+            hasher.write_i128(interface.timestamp_ns(packet.timestamp));
+        }
+        if !ignored(HashIgnore::Padding) {
+            hasher.write_u32(packet.orig_len);
+        }
+        hasher.write_usize(data.len());
+        hasher.write(&data);
+        let packet_hash = hasher.finish();
+        ordered.write_u64(packet_hash);
+        unordered = unordered.wrapping_add(packet_hash);
+    }
+
+    let digest = if ignored(HashIgnore::Order) {
+        let mut hasher = SeaHasher::new();
+        hasher.write_u64(packet_count);
+        hasher.write_u64(unordered);
+        hasher.finish()
+    } else {
+        ordered.write_u64(packet_count);
+        ordered.finish()
+    };
+    println!("{:016x}  {}", digest, input_path);
+    debug!("计算内容哈希: 包数={}, 忽略={:?}, 文件={}", packet_count, ignore, input_path);
+
+    Ok(())
+}
+
+/// 把忽略的字段清零 (忽略填充时截掉IP包之后的部分)
+pub fn canonicalize(data: &mut Vec<u8>, linktype: u32, ignored: &impl Fn(HashIgnore) -> bool) {
+    let parsed = parse_packet(data, linktype);
+    let Some(ip) = parsed.ip.as_ref() else {
+        return;
+    };
+    let mut fields: Vec<(usize, usize)> = Vec::new();
+    if ip.version == 4 {
+        if ignored(HashIgnore::IpId) {
+            fields.push((ip.offset + 4, 2));
+        }
+        if ignored(HashIgnore::Ttl) {
+            fields.push((ip.offset + 8, 1));
+        }
+        if ignored(HashIgnore::Checksums) {
+            fields.push((ip.offset + 10, 2));
+        }
+    } else if ignored(HashIgnore::Ttl) {
+        fields.push((ip.offset + 7, 1));
+    }
+    if ignored(HashIgnore::Checksums) {
+        match parsed.transport {
+            Some(Transport::Tcp(tcp)) => fields.push((tcp.offset + 16, 2)),
+            Some(Transport::Udp(udp)) => fields.push((udp.offset + 6, 2)),
+            Some(Transport::Icmp { offset }) => fields.push((offset + 2, 2)),
+            None => {}
+        }
+    }
+    for (offset, len) in fields {
+        if let Some(field) = data.get_mut(offset..offset + len) {
+            field.fill(0);
+        }
+    }
+    if ignored(HashIgnore::Padding) {
+        strip_padding(data, &parsed);
+    }
+}
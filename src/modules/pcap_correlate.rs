@@ -0,0 +1,452 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self};
+use std::net::IpAddr;
+use std::path::Path;
+use pcap_file::pcap_header::{Datalink, PcapHeader};
+use pcap_file::Packet;
+use anyhow::{Context, Result};
+use chrono::TimeZone as _;
+use log::{info, warn};
+
+use crate::modules::io::PacketWriter;
+use crate::modules::pcap_annotate::write_annotated;
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_json::JsonValue;
+use crate::modules::pcap_output::{Output, compression, create_output};
+use crate::modules::pcap_packet_parser::{
+    FlowKey, IP_PROTO_ICMP, IP_PROTO_ICMPV6, IP_PROTO_TCP, IP_PROTO_UDP, LINKTYPE_ETHERNET, linktype_name, parse_packet,
+};
+use crate::modules::pcap_pcapng::{CaptureReader, InterfaceDescription, PcapngBlock, PcapngPacket};
+use crate::modules::pcap_report::{TimeZone, time_zone};
+use crate::modules::pcap_rule_rewrite::split_csv_line;
+use crate::modules::pcap_time_anchor::split_timestamp_us;
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 一条告警或外部日志中的事件
+#[derive(Debug, Clone)]
+struct Event {
+    time_us: i64,
+    /// 事件中没有完整的地址和协议时为None
+    flow: Option<FlowKey>,
+    /// 标注数据包时使用的说明 (告警为空)
+    label: String,
+}
+
+/// `correlate-log` 的选项
+pub struct EventLogOptions<'a> {
+    /// 事件时间所在的字段名
+    pub key: &'a str,
+    /// 作为标注内容的字段名，None表示使用时间以外的全部字段
+    pub label: Option<&'a str>,
+    /// 事件时间前后的时间窗口 (微秒)
+    pub window_us: u64,
+    /// 只匹配与事件五元组相同 (任一方向) 的包
+    pub match_flow: bool,
+    /// 提取时间窗口内的包 (PCAP)，而不是标注全部包 (pcapng)
+    pub extract: bool,
+}
+
+/// 提取每条告警时间前后一段时间内的数据包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `alerts_path`: 告警文件: Suricata的eve.json，或Zeek的JSON/TSV日志 (如notice.log)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `window_us`: 告警时间前后的时间窗口 (微秒)
+/// - `match_flow`: 只提取与告警五元组相同 (任一方向) 的包
+///
+/// eve.json中只使用 `event_type` 为 `alert` 的事件。告警中没有完整五元组时，
+/// 即使指定了 `match_flow` 也只按时间匹配。每个包最多输出一次，顺序与输入相同。
+pub fn pcap_correlate(
+    input_path: &str,
+    alerts_path: &str,
+    output_path: &str,
+    window_us: u64,
+    match_flow: bool,
+) -> Result<()> {
+    let mut alerts = load_alerts(alerts_path)?;
+    if alerts.is_empty() {
+        warn!("告警文件中没有告警: {}", alerts_path);
+    }
+    if match_flow {
+        let without_flow = alerts.iter().filter(|alert| alert.flow.is_none()).count();
+        if without_flow > 0 {
+            warn!("{} 条告警没有完整的五元组，按时间匹配", without_flow);
+        }
+    }
+    alerts.sort_by_key(|alert| alert.time_us);
+    let mut matched_alerts = vec![false; alerts.len()];
+    let (packet_count, written) = extract_windows(input_path, &alerts, output_path, window_us, match_flow, &mut matched_alerts)?;
+
+    let matched = matched_alerts.iter().filter(|&&matched| matched).count();
+    if matched < alerts.len() {
+        warn!("{} 条告警没有找到对应的数据包", alerts.len() - matched);
+    }
+    info!(
+        "告警关联完成: 告警数={}, 找到数据包的告警数={}, 输入包数={}, 输出包数={}, 输出文件={}",
+        alerts.len(),
+        matched,
+        packet_count,
+        written,
+        output_path
+    );
+    Ok(())
+}
+
+/// 把外部日志中的事件与抓包按时间关联: 标注匹配的数据包，或提取事件前后的数据包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `events_path`: 事件文件: 带表头的CSV、每行一个对象的JSON，或对象数组形式的JSON
+/// - `output_path`: 输出文件路径 (标注时为pcapng，提取时为PCAP)
+///
+/// # 说明
+/// 1. 事件时间取自 `key` 字段: epoch秒 (可带小数)、13位的epoch毫秒，或ISO 8601时间；
+///    不带时区的时间按 `--tz` 解释
+/// 2. 事件带有 src_ip/dst_ip/proto (或Suricata、Zeek的字段名) 时，`match_flow` 只匹配同一个流的包
+/// 3. 标注模式复制整个抓包，时间窗口内的每个包添加一条注释 `事件#序号 时间: 说明`；
+///    提取模式与 `correlate` 相同，每个包最多输出一次，顺序与输入相同
+pub fn correlate_log(input_path: &str, events_path: &str, output_path: &str, options: &EventLogOptions) -> Result<()> {
+    let mut events = load_events(events_path, options.key, options.label)?;
+    if events.is_empty() {
+        warn!("事件文件中没有事件: {}", events_path);
+    }
+    if options.match_flow {
+        let without_flow = events.iter().filter(|event| event.flow.is_none()).count();
+        if without_flow > 0 {
+            warn!("{} 个事件没有完整的五元组，按时间匹配", without_flow);
+        }
+    }
+    events.sort_by_key(|event| event.time_us);
+    let mut matched_events = vec![false; events.len()];
+
+    let (packet_count, selected) = if options.extract {
+        extract_windows(input_path, &events, output_path, options.window_us, options.match_flow, &mut matched_events)?
+    } else {
+        let comments = event_comments(input_path, &events, options.window_us, options.match_flow, &mut matched_events)?;
+        let packet_count = write_annotated(input_path, output_path, &comments, "pcap-editor correlate-log")?;
+        (packet_count, comments.len() as u64)
+    };
+
+    let matched = matched_events.iter().filter(|&&matched| matched).count();
+    if matched < events.len() {
+        warn!("{} 个事件没有找到对应的数据包", events.len() - matched);
+    }
+    info!(
+        "事件关联完成: 事件数={}, 找到数据包的事件数={}, 输入包数={}, {}={}, 输出文件={}",
+        events.len(),
+        matched,
+        packet_count,
+        if options.extract { "输出包数" } else { "标注的包数" },
+        selected,
+        output_path
+    );
+    Ok(())
+}
+
+/// 逐包找出时间窗口内 (指定 `match_flow` 时还要五元组相同) 的事件，返回输入包数
+///
+/// 事件已按时间排序。有匹配的事件时调用 `on_match(包序号, 包, 接口, 匹配的事件下标)`，
+/// 同时在 `matched` 中记录找到数据包的事件。
+fn scan_matches(
+    input_path: &str,
+    events: &[Event],
+    window_us: u64,
+    match_flow: bool,
+    matched: &mut [bool],
+    mut on_match: impl FnMut(u64, PcapngPacket, &InterfaceDescription, &[usize]) -> Result<()>,
+) -> Result<u64> {
+    let window_us = window_us.min(i64::MAX as u64) as i64;
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut packet_count = 0u64;
+    let mut matching = Vec::new();
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = (interface.timestamp_ns(packet.timestamp) / 1000) as i64;
+
+        // 时间在窗口内的事件: 事件时间在 [包时间 - 窗口, 包时间 + 窗口] 之间
+        let first = events.partition_point(|event| event.time_us < time_us.saturating_sub(window_us));
+        let last = events.partition_point(|event| event.time_us <= time_us.saturating_add(window_us));
+        if first == last {
+            continue;
+        }
+        let flow = if match_flow { parse_packet(&packet.data, interface.linktype as u32).flow_key() } else { None };
+        matching.clear();
+        for (index, event) in events.iter().enumerate().take(last).skip(first) {
+            let flow_matches = !match_flow
+                || event.flow.is_none_or(|event_flow| flow.is_some_and(|flow| same_flow(&event_flow, &flow)));
+            if flow_matches {
+                matched[index] = true;
+                matching.push(index);
+            }
+        }
+        if !matching.is_empty() {
+            on_match(packet_count, packet, interface, &matching)?;
+        }
+    }
+    Ok(packet_count)
+}
+
+/// 把与事件匹配的包写入PCAP文件，返回 (输入包数, 输出包数)
+fn extract_windows(
+    input_path: &str,
+    events: &[Event],
+    output_path: &str,
+    window_us: u64,
+    match_flow: bool,
+    matched: &mut [bool],
+) -> Result<(u64, u64)> {
+    let out_file = create_output(output_path, compression())?;
+    let mut out_file = Some(out_file);
+    // 输出的链路层类型由第一个选中的包决定
+    let mut output: Option<(PacketWriter<Output>, u32)> = None;
+    let mut digest = OutputDigest::default();
+    let mut written = 0u64;
+    let packet_count = scan_matches(input_path, events, window_us, match_flow, matched, |_, packet, interface, _| {
+        let linktype = interface.linktype as u32;
+        let (pcap_writer, output_linktype) = match &mut output {
+            Some(output) => output,
+            None => {
+                let mut header = PcapHeader::with_datalink(Datalink::from(linktype));
+                if interface.snaplen != 0 {
+                    header.snaplen = interface.snaplen;
+                }
+                let pcap_writer = PacketWriter::with_header(header, out_file.take().unwrap(), output_path)
+                    .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+                output.insert((pcap_writer, linktype))
+            }
+        };
+        if *output_linktype != linktype {
+            anyhow::bail!(PcapError::InvalidArgument(format!(
+                "选出的包包含多种链路层类型 ({} 和 {})",
+                linktype_name(*output_linktype),
+                linktype_name(linktype)
+            )));
+        }
+        let time_us = (interface.timestamp_ns(packet.timestamp) / 1000) as i64;
+        let (ts_sec, ts_usec) = split_timestamp_us(time_us)?;
+        let mut out_packet = Packet::new_owned(ts_sec, ts_usec, packet.data.len() as u32, packet.data);
+        out_packet.header.orig_len = packet.orig_len;
+        pcap_writer.write_packet(&out_packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&out_packet);
+        written += 1;
+        Ok(())
+    })?;
+
+    let pcap_writer = match output {
+        Some((pcap_writer, _)) => pcap_writer,
+        // 没有选中任何包时仍输出有效的空文件
+        None => PacketWriter::with_header(PcapHeader::with_datalink(Datalink::from(LINKTYPE_ETHERNET)), out_file.take().unwrap(), output_path)
+            .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?,
+    };
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+    Ok((packet_count, written))
+}
+
+/// 每个与事件匹配的包的注释: 包序号 -> 每个匹配的事件一条
+fn event_comments(
+    input_path: &str,
+    events: &[Event],
+    window_us: u64,
+    match_flow: bool,
+    matched: &mut [bool],
+) -> Result<BTreeMap<u64, Vec<String>>> {
+    let mut comments = BTreeMap::new();
+    scan_matches(input_path, events, window_us, match_flow, matched, |number, _, _, matching| {
+        let texts = matching.iter().map(|&index| events[index].label.clone()).collect();
+        comments.insert(number, texts);
+        Ok(())
+    })?;
+    Ok(comments)
+}
+
+/// 两个五元组是否属于同一个流 (任一方向)；ICMP只比较地址
+fn same_flow(alert: &FlowKey, packet: &FlowKey) -> bool {
+    if alert.protocol != packet.protocol {
+        return false;
+    }
+    let icmp = matches!(alert.protocol, IP_PROTO_ICMP | IP_PROTO_ICMPV6);
+    let matches = |a: &FlowKey, b: &FlowKey| {
+        a.src == b.src && a.dst == b.dst && (icmp || (a.src_port == b.src_port && a.dst_port == b.dst_port))
+    };
+    matches(alert, packet) || matches(alert, &packet.reversed())
+}
+
+/// 读取告警文件，每行一个JSON对象，或带 `#fields` 头的Zeek TSV日志
+fn load_alerts(path: &str) -> Result<Vec<Event>> {
+    let text = fs::read_to_string(Path::new(path))
+        .map_err(|e| PcapError::open_failed(path, e))?;
+    let mut alerts = Vec::new();
+    // Zeek TSV日志的列名
+    let mut tsv_fields: Option<Vec<String>> = None;
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let context = || format!("告警文件第 {} 行", line_number + 1);
+        if let Some(fields) = line.strip_prefix("#fields\t") {
+            tsv_fields = Some(fields.split('\t').map(str::to_string).collect());
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let record: HashMap<String, String> = match &tsv_fields {
+            Some(names) => names
+                .iter()
+                .cloned()
+                .zip(line.split('\t').map(str::to_string))
+                .filter(|(_, value)| value != "-" && value != "(empty)")
+                .collect(),
+            None => JsonValue::parse(line)
+                .with_context(context)
+                .map_err(|e| PcapError::InvalidArgument(format!("{:#}", e)))?
+                .flatten(),
+        };
+        if record.get("event_type").is_some_and(|event_type| event_type != "alert") {
+            continue;
+        }
+        let time_us = alert_time(&record)
+            .with_context(context)
+            .map_err(|e| PcapError::InvalidArgument(format!("{:#}", e)))?;
+        alerts.push(Event { time_us, flow: alert_flow(&record), label: String::new() });
+    }
+    Ok(alerts)
+}
+
+/// 告警时间 (微秒): Suricata的 `timestamp` (ISO 8601)，或Zeek的 `ts` (epoch秒或ISO 8601)
+fn alert_time(record: &HashMap<String, String>) -> Result<i64> {
+    let text = record
+        .get("timestamp")
+        .or_else(|| record.get("ts"))
+        .ok_or_else(|| anyhow::anyhow!("没有 timestamp 或 ts 字段"))?;
+    parse_time(text)
+}
+
+/// 解析时间 (微秒): epoch秒 (可带小数)、13位整数的epoch毫秒，或ISO 8601时间 (不带时区时按 `--tz` 解释)
+fn parse_time(text: &str) -> Result<i64> {
+    let text = text.trim();
+    if text.len() == 13 && text.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(text.parse::<i64>()? * 1000);
+    }
+    if let Ok(seconds) = text.parse::<f64>() {
+        return Ok((seconds * 1_000_000.0).round() as i64);
+    }
+    // Suricata: 2024-01-02T03:04:05.678901+0000，Zeek: 2024-01-02T03:04:05.678901Z
+    if let Ok(time) = chrono::DateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f%z")
+        .or_else(|_| chrono::DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%z"))
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(text))
+    {
+        return Ok(time.timestamp_micros());
+    }
+    let naive = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(text, format).ok())
+        .with_context(|| format!("无法识别的时间: {}", text))?;
+    let time = match time_zone() {
+        TimeZone::Local => chrono::Local.from_local_datetime(&naive).earliest().map(|time| time.timestamp_micros()),
+        TimeZone::Fixed(offset) => chrono::FixedOffset::east_opt(offset)
+            .and_then(|zone| zone.from_local_datetime(&naive).earliest())
+            .map(|time| time.timestamp_micros()),
+    };
+    time.with_context(|| format!("本地时区中不存在的时间: {}", text))
+}
+
+/// 读取事件文件 (带表头的CSV、每行一个对象的JSON，或对象数组形式的JSON)
+///
+/// 每个事件的说明为 `事件#序号 时间: 内容`，内容为 `label` 字段的值，或时间以外的全部字段 (`名称=值`)。
+fn load_events(path: &str, key: &str, label: Option<&str>) -> Result<Vec<Event>> {
+    let text = fs::read_to_string(Path::new(path))
+        .map_err(|e| PcapError::open_failed(path, e))?;
+    let invalid = |context: String, e: anyhow::Error| PcapError::InvalidArgument(format!("{}: {:#}", context, e));
+    // 每个事件的字段 (CSV按列的顺序，JSON按名称排序)
+    let mut records: Vec<Vec<(String, String)>> = Vec::new();
+    let sorted = |object: &JsonValue| {
+        let mut fields: Vec<(String, String)> = object.flatten().into_iter().collect();
+        fields.sort();
+        fields
+    };
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') {
+        let JsonValue::Array(items) = JsonValue::parse(trimmed).map_err(|e| invalid(format!("事件文件: {}", path), e))? else {
+            unreachable!("以 [ 开头的JSON只能是数组");
+        };
+        records.extend(items.iter().map(sorted));
+    } else if trimmed.starts_with('{') {
+        for (line_number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let value = JsonValue::parse(line).map_err(|e| invalid(format!("事件文件第 {} 行", line_number + 1), e))?;
+            records.push(sorted(&value));
+        }
+    } else {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let Some((_, header)) = lines.next() else {
+            return Ok(Vec::new());
+        };
+        let names: Vec<String> = split_csv_line(header.trim_end_matches('\r'))
+            .map_err(|e| invalid("事件文件表头".to_string(), e))?
+            .iter()
+            .map(|name| name.trim().to_string())
+            .collect();
+        for (line_number, line) in lines {
+            let cells = split_csv_line(line.trim_end_matches('\r')).map_err(|e| invalid(format!("事件文件第 {} 行", line_number + 1), e))?;
+            records.push(
+                names.iter().cloned().zip(cells.into_iter().map(|cell| cell.trim().to_string())).filter(|(_, value)| !value.is_empty()).collect(),
+            );
+        }
+    }
+
+    let mut events = Vec::new();
+    for (index, fields) in records.into_iter().enumerate() {
+        let context = || format!("第 {} 个事件", index + 1);
+        let Some((_, time_text)) = fields.iter().find(|(name, _)| name == key) else {
+            anyhow::bail!(PcapError::InvalidArgument(format!("{}没有 {} 字段", context(), key)));
+        };
+        let time_us = parse_time(time_text).map_err(|e| invalid(context(), e))?;
+        let content = match label {
+            Some(label) => fields.iter().find(|(name, _)| name == label).map_or("", |(_, value)| value.as_str()).to_string(),
+            None => fields
+                .iter()
+                .filter(|(name, _)| name != key)
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        let label = format!("事件#{} {}: {}", index + 1, time_text, content);
+        let record: HashMap<String, String> = fields.into_iter().collect();
+        events.push(Event { time_us, flow: alert_flow(&record), label });
+    }
+    Ok(events)
+}
+
+/// 告警或事件的五元组: Suricata的 `src_ip`/`dest_ip`/`proto`，Zeek的 `id.orig_h`/`id.resp_h`/`proto`，
+/// 或 `dst_ip`/`dst_port`/`protocol`
+fn alert_flow(record: &HashMap<String, String>) -> Option<FlowKey> {
+    let field = |names: &[&str]| names.iter().find_map(|name| record.get(*name));
+    let src: IpAddr = field(&["src_ip", "id.orig_h"])?.parse().ok()?;
+    let dst: IpAddr = field(&["dest_ip", "id.resp_h", "dst_ip"])?.parse().ok()?;
+    let protocol = match field(&["proto", "transport_protocol", "protocol"])?.to_ascii_lowercase().as_str() {
+        "tcp" => IP_PROTO_TCP,
+        "udp" => IP_PROTO_UDP,
+        "icmp" if src.is_ipv6() => IP_PROTO_ICMPV6,
+        "icmp" => IP_PROTO_ICMP,
+        "ipv6-icmp" | "icmpv6" => IP_PROTO_ICMPV6,
+        other => other.parse().ok()?,
+    };
+    let port = |names: &[&str]| field(names).and_then(|port| port.parse::<u16>().ok()).unwrap_or(0);
+    Some(FlowKey {
+        protocol,
+        src,
+        dst,
+        src_port: port(&["src_port", "id.orig_p"]),
+        dst_port: port(&["dest_port", "id.resp_p", "dst_port"]),
+    })
+}
@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use pcap_file::Packet;
+use pcap_file::pcap_header::Datalink;
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{
+    ETHERTYPE_IPV4, ETHERTYPE_IPV6, LINKTYPE_ETHERNET, LINKTYPE_IEEE802_11, LINKTYPE_IEEE802_11_RADIOTAP, LINKTYPE_IPV4,
+    LINKTYPE_IPV6, LINKTYPE_RAW, ParsedPacket, Transport, ethertype_offset, linktype_name, parse_packet, parse_wlan, write_u16,
+};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// GTP-U用户面端口
+const GTPU_PORT: u16 = 2152;
+/// GTP-U消息类型: 封装的用户数据 (G-PDU)
+const GTPU_G_PDU: u8 = 0xff;
+/// GTP头标志: 扩展头(E)、序列号(S)、N-PDU号(PN)
+const GTPU_FLAGS_OPTIONAL: u8 = 0x07;
+const GTPU_FLAG_EXTENSION: u8 = 0x04;
+
+/// 解封装选项
+#[derive(Debug, Clone, Default)]
+pub struct DecapOptions {
+    /// 把802.11数据帧 (可带Radiotap头) 转换为以太网帧
+    pub strip_radiotap: bool,
+    /// 输出的链路层类型 (raw/ipv4/ipv6 表示去掉链路层头)，None表示保留
+    pub output_linktype: Option<u32>,
+}
+
+/// 一个解封装后的隧道包
+struct Tunnel {
+    teid: u32,
+    /// 内层IP包在数据中的偏移
+    inner_offset: usize,
+}
+
+/// 剥除隧道封装，输出内层数据包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `teid_path`: 导出每个解封装包的TEID的CSV文件 (None表示不导出)
+/// - `options`: 802.11转换和输出链路层类型
+///
+/// # 功能
+/// 1. 支持GTP-U (UDP端口2152) 的G-PDU，包括可选字段和扩展头 (如5G的PDU会话容器)
+/// 2. 保留外层链路层头 (以太网类型改为内层IP版本)，删除外层IP、UDP和GTP头
+/// 3. 不是隧道封装的包原样输出
+/// 4. CSV列为输出包序号、TEID、外层源地址、外层目的地址
+/// 5. 转换802.11时只保留带LLC/SNAP封装的未加密数据帧，管理帧、控制帧和加密帧丢弃；
+///    转换后的以太网帧再做隧道解封装
+/// 6. 输出链路层类型为原始IP时最后去掉链路层头和帧尾，非IP包 (以及版本不符的包) 丢弃
+pub fn pcap_decap(input_path: &str, output_path: &str, teid_path: Option<&str>, options: &DecapOptions) -> Result<()> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let input_linktype: u32 = pcap_reader.header.datalink.into();
+    let mut header = pcap_reader.header;
+    let linktype = if options.strip_radiotap {
+        if input_linktype != LINKTYPE_IEEE802_11 && input_linktype != LINKTYPE_IEEE802_11_RADIOTAP {
+            anyhow::bail!(PcapError::InvalidArgument(format!(
+                    "--strip-radiotap 需要802.11或Radiotap输入，输入文件的链路层类型为 {}",
+                    linktype_name(input_linktype)
+            )));
+        }
+        header.datalink = Datalink::from(LINKTYPE_ETHERNET);
+        LINKTYPE_ETHERNET
+    } else {
+        input_linktype
+    };
+    let output_linktype = options.output_linktype.filter(|&output| output != linktype);
+    if let Some(output) = output_linktype {
+        if ![LINKTYPE_RAW, LINKTYPE_IPV4, LINKTYPE_IPV6].contains(&output) {
+            anyhow::bail!(PcapError::InvalidArgument(format!(
+                    "不支持把 {} 转换为输出链路层类型 {} (可选 raw/ipv4/ipv6)",
+                    linktype_name(linktype),
+                    linktype_name(output)
+            )));
+        }
+        header.datalink = Datalink::from(output);
+    }
+
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut teid_writer = match teid_path {
+        Some(path) => {
+            let mut writer = BufWriter::new(
+                File::create(Path::new(path)).map_err(|e| PcapError::WriteFailure(format!("无法创建TEID文件: {}: {}", path, e)))?,
+            );
+            writeln!(writer, "packet,teid,outer_src,outer_dst")?;
+            Some(writer)
+        }
+        None => None,
+    };
+
+    let mut packet_count = 0;
+    let mut decapsulated = 0;
+    let mut mismatched = 0;
+    let mut dropped = 0;
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+
+        if options.strip_radiotap {
+            let Some(wlan) = parse_wlan(&packet.data, input_linktype) else {
+                dropped += 1;
+                continue;
+            };
+            let mut frame = Vec::with_capacity(packet.data.len());
+            frame.extend_from_slice(&wlan.dst);
+            frame.extend_from_slice(&wlan.src);
+            frame.extend_from_slice(&wlan.ethertype.to_be_bytes());
+            frame.extend_from_slice(&packet.data[wlan.payload_offset..packet.data.len() - wlan.fcs_len]);
+            set_data(&mut packet, frame);
+        }
+
+        let mut tunnel_info = None;
+        let parsed = parse_packet(&packet.data, linktype);
+        if let (Some(tunnel), Some(ip)) = (parse_gtpu(&packet.data, &parsed), parsed.ip.as_ref()) {
+            let inner_version = packet.data[tunnel.inner_offset] >> 4;
+            // 链路层类型固定了IP版本时，内层版本不同的包无法表示
+            let fixed = output_linktype.unwrap_or(linktype);
+            if (fixed == LINKTYPE_IPV4 && inner_version != 4) || (fixed == LINKTYPE_IPV6 && inner_version != 6) {
+                mismatched += 1;
+            } else {
+                let mut data = packet.data.to_vec();
+                // 外层帧的填充不属于内层包
+                data.truncate(ip.end().min(data.len()));
+                data.drain(ip.offset..tunnel.inner_offset);
+                if let Some(offset) = ethertype_offset(linktype, ip.offset) {
+                    let ethertype = if inner_version == 4 { ETHERTYPE_IPV4 } else { ETHERTYPE_IPV6 };
+                    write_u16(&mut data, offset, ethertype);
+                }
+                set_data(&mut packet, data);
+                decapsulated += 1;
+                tunnel_info = Some((tunnel.teid, ip.src, ip.dst));
+            }
+        }
+
+        if let Some(output) = output_linktype {
+            let parsed = parse_packet(&packet.data, linktype);
+            let version = match output {
+                LINKTYPE_IPV4 => Some(4),
+                LINKTYPE_IPV6 => Some(6),
+                _ => None,
+            };
+            match parsed.ip {
+                Some(ip) if version.is_none_or(|version| version == ip.version) => {
+                    let data = packet.data[ip.offset..ip.end().min(packet.data.len())].to_vec();
+                    set_data(&mut packet, data);
+                }
+                _ => {
+                    dropped += 1;
+                    continue;
+                }
+            }
+        }
+
+        packet_count += 1;
+        if let (Some(writer), Some((teid, src, dst))) = (teid_writer.as_mut(), tunnel_info) {
+            writeln!(writer, "{},{},{},{}", packet_count, teid, src, dst)?;
+        }
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+    if let Some(writer) = teid_writer.as_mut() {
+        writer.flush()?;
+    }
+
+    if dropped > 0 {
+        info!("丢弃不能转换为输出链路层类型的包: {} 个", dropped);
+    }
+    if mismatched > 0 {
+        warn!("{} 个包的内层IP版本与链路层类型不符，未解封装", mismatched);
+    }
+    info!(
+        "成功解封装: 输出包数={}, 解封装包数={}, 输出文件={}",
+        packet_count, decapsulated, output_path
+    );
+
+    Ok(())
+}
+
+/// 替换包数据，捕获长度和原始长度按减少的字节数同步调整
+fn set_data(packet: &mut Packet, data: Vec<u8>) {
+    let removed = packet.data.len().saturating_sub(data.len()) as u32;
+    packet.header.incl_len = data.len() as u32;
+    packet.header.orig_len = packet.header.orig_len.saturating_sub(removed);
+    packet.data = data.into();
+}
+
+/// 解析GTP-U头，只处理内层为IP包的G-PDU
+fn parse_gtpu(data: &[u8], parsed: &ParsedPacket) -> Option<Tunnel> {
+    let Some(Transport::Udp(udp)) = parsed.transport else {
+        return None;
+    };
+    if udp.src_port != GTPU_PORT && udp.dst_port != GTPU_PORT {
+        return None;
+    }
+    let offset = udp.offset + 8;
+    let header = data.get(offset..offset + 8)?;
+    // 版本1，协议类型为GTP
+    if header[0] >> 5 != 1 || header[0] & 0x10 == 0 || header[1] != GTPU_G_PDU {
+        return None;
+    }
+    let teid = u32::from_be_bytes(header[4..8].try_into().unwrap());
+
+    let mut inner_offset = offset + 8;
+    if header[0] & GTPU_FLAGS_OPTIONAL != 0 {
+        // 序列号(2)、N-PDU号(1)、下一个扩展头类型(1)
+        let mut next_type = *data.get(inner_offset + 3)?;
+        inner_offset += 4;
+        if header[0] & GTPU_FLAG_EXTENSION != 0 {
+            while next_type != 0 {
+                // 扩展头长度以4字节为单位，最后一个字节为下一个扩展头类型
+                let len = *data.get(inner_offset)? as usize * 4;
+                if len == 0 {
+                    return None;
+                }
+                next_type = *data.get(inner_offset + len - 1)?;
+                inner_offset += len;
+            }
+        }
+    }
+
+    let end = parsed.ip.as_ref()?.end().min(data.len());
+    match data.get(inner_offset).map(|b| b >> 4) {
+        Some(4) if end >= inner_offset + 20 => {}
+        Some(6) if end >= inner_offset + 40 => {}
+        _ => return None,
+    }
+    Some(Tunnel { teid, inner_offset })
+}
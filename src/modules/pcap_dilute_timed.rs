@@ -1,104 +1,119 @@
-use std::path::Path;
-use std::fs::File;
-use pcap_file::{PcapReader, PcapWriter};
-use anyhow::{Context, Result, anyhow};
+use anyhow::Result;
 use log::info;
 
+use crate::modules::io::{OutputFormat, PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{Compression, create_output};
+use crate::modules::pcap_packet_store::PacketStore;
+use crate::modules::pcap_time_anchor::ticks_per_second;
+use crate::modules::pcap_verify::OutputDigest;
+
 /// 稀释PCAP文件的时间分布
 /// 
 /// # 参数
-/// - `input_path`: 输入PCAP文件路径
-/// - `output_path`: 输出PCAP文件路径
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
 /// - `dilution_factor`: 稀释因子(大于1的整数)
+/// - `format`: 输出格式 (PCAP或pcapng)
+/// - `compression`: 输出的压缩方式
 /// 
 /// # 功能
 /// 1. 保持原始时间跨度不变
 /// 2. 按稀释因子减少数据包数量
 /// 3. 在时间线上均匀分布保留的数据包
+/// 4. 纳秒精度的PCAP文件按纳秒计算，输出保持纳秒精度
 pub fn pcap_dilute_timed(
     input_path: &str,
     output_path: &str,
     dilution_factor: usize,
+    format: OutputFormat,
+    compression: Compression,
 ) -> Result<()> {
     // 验证稀释因子
     if dilution_factor < 2 {
-        anyhow::bail!("稀释因子必须大于1，当前为: {}", dilution_factor);
+        anyhow::bail!(PcapError::InvalidArgument(format!("稀释因子必须大于1，当前为: {}", dilution_factor)));
     }
 
     // 打开输入文件
-    let in_file = File::open(Path::new(input_path))
-        .with_context(|| format!("无法打开输入文件: {}", input_path))?;
-    let mut pcap_reader = PcapReader::new(in_file)
-        .map_err(|e| anyhow!("无效的PCAP文件格式: {}", e))?;
+    let mut pcap_reader = PacketReader::open(input_path)?;
 
     // 创建输出文件
-    let out_file = File::create(Path::new(output_path))
-        .with_context(|| format!("无法创建输出文件: {}", output_path))?;
+    let out_file = create_output(output_path, compression)?;
     
     // 正确创建PcapWriter
-    let header = pcap_reader.header.clone();
-    let mut pcap_writer = PcapWriter::with_header(header, out_file) // 参数顺序修正
-        .map_err(|e| anyhow!("创建PCAP写入器失败: {}", e))?;
+    let header = pcap_reader.header;
+    // 秒以下部分的单位数 (微秒或纳秒精度的文件)
+    let ticks_per_second = ticks_per_second(&header);
+    let mut pcap_writer = PacketWriter::with_format(header, out_file, format)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
 
-    // 读取所有原始包并计算时间信息
-    let mut original_packets = Vec::new();
+    // 读取所有原始包并计算时间信息 (超出内存上限的包写入临时文件)
+    let mut original_packets = PacketStore::new(header);
+    let mut timestamps = Vec::new();
     let mut first_timestamp = None;
     let mut last_timestamp = None;
     
-    while let Some(packet) = pcap_reader.next() {
+    for packet in pcap_reader.by_ref() {
         // 更新首尾时间戳
         if first_timestamp.is_none() {
             first_timestamp = Some((packet.header.ts_sec, packet.header.ts_usec));
         }
         last_timestamp = Some((packet.header.ts_sec, packet.header.ts_usec));
         
-        original_packets.push(packet);
+        timestamps.push((packet.header.ts_sec, packet.header.ts_usec));
+        original_packets.push(packet)?;
     }
 
     // 检查是否有足够的数据包
     if original_packets.is_empty() {
-        anyhow::bail!("输入文件不包含任何数据包");
+        anyhow::bail!(PcapError::EmptyCapture);
     }
     if original_packets.len() < dilution_factor {
-        anyhow::bail!(
-            "数据包数量({})少于稀释因子({})",
-            original_packets.len(),
-            dilution_factor
-        );
+        anyhow::bail!(PcapError::InvalidArgument(format!(
+                "数据包数量({})少于稀释因子({})",
+                original_packets.len(),
+                dilution_factor
+        )));
     }
 
     // 解包时间戳
     let (first_sec, first_usec) = first_timestamp.unwrap();
     let (last_sec, last_usec) = last_timestamp.unwrap();
     
-    // 计算原始时间跨度（微秒）
-    let total_duration_us = ((last_sec as i64 - first_sec as i64) * 1_000_000) 
+    // 计算原始时间跨度（文件的时间戳精度）
+    let total_duration = ((last_sec as i64 - first_sec as i64) * ticks_per_second)
         + (last_usec as i64 - first_usec as i64);
     
     // 计算目标数据包数量
     let target_packet_count = original_packets.len() / dilution_factor;
     
-    // 计算理想间隔（微秒）
-    let ideal_interval_us = total_duration_us / target_packet_count as i64;
+    // 计算理想间隔（文件的时间戳精度）
+    let ideal_interval = total_duration / target_packet_count as i64;
     
     // 创建时间线位置
     let mut current_target_sec = first_sec;
     let mut current_target_usec = first_usec;
     let mut packet_index = 0;
-    let mut packets_written = 0;
+    let mut selected = Vec::with_capacity(target_packet_count);
     
     // 遍历并选择最接近目标时间点的包
     for i in 0..target_packet_count {
+        // 后续已无可选的包
+        if packet_index >= timestamps.len() {
+            break;
+        }
+        
         // 计算下一个目标时间点（第一个包使用原始时间戳）
         if i > 0 {
             // 计算新的微秒值
-            let mut new_usec = current_target_usec as i64 + ideal_interval_us;
+            let mut new_usec = current_target_usec as i64 + ideal_interval;
             let mut new_sec = current_target_sec as i64;
             
-            // 处理微秒溢出
-            if new_usec >= 1_000_000 {
-                new_sec += new_usec / 1_000_000;
-                new_usec %= 1_000_000;
+            // 处理秒以下部分的溢出
+            if new_usec >= ticks_per_second {
+                new_sec += new_usec / ticks_per_second;
+                new_usec %= ticks_per_second;
             }
             
             current_target_sec = new_sec as u32;
@@ -110,13 +125,12 @@ pub fn pcap_dilute_timed(
         let mut best_diff = i64::MAX;
         
         // 从当前位置向后搜索（提高效率）
-        for j in packet_index..original_packets.len() {
-            let packet = &original_packets[j];
+        for (j, &(ts_sec, ts_usec)) in timestamps.iter().enumerate().skip(packet_index) {
             
-            // 计算时间差（微秒）
-            let sec_diff = packet.header.ts_sec as i64 - current_target_sec as i64;
-            let usec_diff = packet.header.ts_usec as i64 - current_target_usec as i64;
-            let total_diff = (sec_diff * 1_000_000) + usec_diff;
+            // 计算时间差（文件的时间戳精度）
+            let sec_diff = ts_sec as i64 - current_target_sec as i64;
+            let usec_diff = ts_usec as i64 - current_target_usec as i64;
+            let total_diff = (sec_diff * ticks_per_second) + usec_diff;
             
             // 找到更接近的包
             if total_diff.abs() < best_diff {
@@ -132,11 +146,25 @@ pub fn pcap_dilute_timed(
         // 更新下一个搜索起点
         packet_index = best_index + 1;
         
-        // 写入选中的包（保持原始时间戳）
-        pcap_writer.write_packet(&original_packets[best_index])
-            .map_err(|e| anyhow!("写入包失败: {}", e))?;
+        selected.push(best_index);
+    }
+
+    // 按顺序写入选中的包（保持原始时间戳）
+    let mut packets_written = 0;
+    let mut next_selected = selected.iter().peekable();
+    for (index, packet) in original_packets.iter()?.enumerate() {
+        if next_selected.peek() != Some(&&index) {
+            continue;
+        }
+        next_selected.next();
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&packet);
         packets_written += 1;
     }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
 
     info!(
         "成功生成稀释文件: 原始包数={}, 稀释因子={}, 保留包数={}",
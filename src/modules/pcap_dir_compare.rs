@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use anyhow::{Context, Result};
+use log::debug;
+
+use crate::modules::pcap_comparative_analyzer::{CompareCounts, CompareOptions, compare_counts};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, format_timestamp_us, print_fields, print_heading, print_section, print_separator,
+    print_verdict, verbosity,
+};
+use crate::modules::pcap_time_anchor::capture_bounds_us;
+
+/// 配对列表中最多列出的有差异的文件对 (详细模式不限制，并列出全部文件对)
+const MAX_PAIR_ROWS: usize = 50;
+
+/// 两个目录中的文件如何配对
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PairBy {
+    /// 文件名相同的文件配对
+    Name,
+    /// 时间范围重叠最多的文件配对 (两个抓包点的分段边界不同时)
+    Time,
+}
+
+/// 一对待比较的文件，某一侧为None表示该文件在另一个目录中没有对应的文件
+struct FilePair {
+    reference: Option<PathBuf>,
+    comparison: Option<PathBuf>,
+    /// 按时间配对时文件对的开始时间 (基准文件的第一个包，没有基准文件时为对比文件的)
+    start_us: Option<i64>,
+}
+
+/// 比较两个目录中的抓包分段
+///
+/// # 参数
+/// - `reference_dir`: 基准目录
+/// - `comparison_dir`: 对比目录
+/// - `options`: 比较选项 (与单个文件的比较相同)
+/// - `pair_by`: 配对方式
+///
+/// # 说明
+/// 1. 只比较目录下 (不含子目录) 的普通文件，忽略以 `.` 开头的文件
+/// 2. 各文件对按CPU核数并行比较；没有配对的文件中的包全部计为丢失包或多余包
+/// 3. 两个抓包点的分段边界不同时，边界附近的包会落在相邻的分段中。某个文件对的丢失包如果是另一个
+///    文件对的多余包 (内容相同)，不计为丢失和多余，而计为跨文件的包
+/// 4. 比较失败的文件对 (如格式无效) 在列表中标出，汇总输出之后返回错误
+pub fn compare_directories(
+    reference_dir: &str,
+    comparison_dir: &str,
+    options: CompareOptions,
+    pair_by: PairBy,
+) -> Result<()> {
+    let reference_files = list_capture_files(reference_dir)?;
+    let comparison_files = list_capture_files(comparison_dir)?;
+    if reference_files.is_empty() && comparison_files.is_empty() {
+        anyhow::bail!(PcapError::InvalidArgument(format!("两个目录中都没有文件: {}, {}", reference_dir, comparison_dir)));
+    }
+    let pairs = match pair_by {
+        PairBy::Name => pair_by_name(reference_files, comparison_files),
+        PairBy::Time => pair_by_time(reference_files, comparison_files)?,
+    };
+
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let results = run_parallel(&pairs, threads, |pair| {
+        let result = compare_counts(
+            pair.reference.as_deref().map(|path| path.to_str().unwrap()),
+            pair.comparison.as_deref().map(|path| path.to_str().unwrap()),
+            options,
+            1,
+        );
+        debug!("比较完成: {} / {}", display_name(&pair.reference), display_name(&pair.comparison));
+        result
+    });
+
+    print_directory_results(reference_dir, comparison_dir, &pairs, &results, pair_by);
+
+    let failed: Vec<String> = pairs
+        .iter()
+        .zip(&results)
+        .filter_map(|(pair, result)| result.as_ref().err().map(|e| format!("{}: {:#}", pair_name(pair), e)))
+        .collect();
+    if let Some(first) = failed.first() {
+        anyhow::bail!("{} 对文件比较失败，第一个错误: {}", failed.len(), first);
+    }
+    Ok(())
+}
+
+/// 一个文件对扣除跨文件的包之后的结果
+#[derive(Default)]
+struct PairSummary {
+    reference_packets: usize,
+    comparison_packets: usize,
+    missing: usize,
+    extra: usize,
+    /// 在其他文件对中找到的丢失包和多余包
+    shifted: usize,
+}
+
+/// 把各文件对的丢失包与其他文件对的多余包按哈希配对 (优先配对位置最近的文件对)，比较失败的文件对为None
+fn reconcile(results: &[Result<CompareCounts>]) -> Vec<Option<PairSummary>> {
+    let mut summaries: Vec<Option<PairSummary>> = results
+        .iter()
+        .map(|result| {
+            result.as_ref().ok().map(|counts| PairSummary {
+                reference_packets: counts.reference_packets,
+                comparison_packets: counts.comparison_packets,
+                missing: counts.missing.len(),
+                extra: counts.extra.len(),
+                shifted: 0,
+            })
+        })
+        .collect();
+    let mut extras: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, counts) in results.iter().enumerate() {
+        if let Ok(counts) = counts {
+            for &hash in &counts.extra {
+                extras.entry(hash).or_default().push(index);
+            }
+        }
+    }
+    for (index, counts) in results.iter().enumerate() {
+        let Ok(counts) = counts else {
+            continue;
+        };
+        for hash in &counts.missing {
+            let Some(owners) = extras.get_mut(hash).filter(|owners| !owners.is_empty()) else {
+                continue;
+            };
+            let nearest = (0..owners.len()).min_by_key(|&k| owners[k].abs_diff(index)).unwrap();
+            let owner = owners.swap_remove(nearest);
+            for (pair, missing) in [(index, true), (owner, false)] {
+                let summary = summaries[pair].as_mut().unwrap();
+                if missing {
+                    summary.missing -= 1;
+                } else {
+                    summary.extra -= 1;
+                }
+                summary.shifted += 1;
+            }
+        }
+    }
+    summaries
+}
+
+/// 目录下的普通文件 (按文件名排序)
+fn list_capture_files(dir: &str) -> Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(Path::new(dir))
+        .map_err(|e| PcapError::open_failed(dir, e))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("无法读取目录: {}", dir))?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if !hidden && entry.file_type().is_ok_and(|file_type| file_type.is_file()) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn pair_by_name(reference_files: Vec<PathBuf>, comparison_files: Vec<PathBuf>) -> Vec<FilePair> {
+    let mut comparison_by_name: HashMap<_, _> = comparison_files
+        .into_iter()
+        .map(|path| (path.file_name().unwrap().to_os_string(), path))
+        .collect();
+    let mut pairs: Vec<FilePair> = reference_files
+        .into_iter()
+        .map(|path| FilePair {
+            comparison: comparison_by_name.remove(path.file_name().unwrap()),
+            reference: Some(path),
+            start_us: None,
+        })
+        .collect();
+    let mut unmatched: Vec<PathBuf> = comparison_by_name.into_values().collect();
+    unmatched.sort();
+    pairs.extend(unmatched.into_iter().map(|path| FilePair { reference: None, comparison: Some(path), start_us: None }));
+    pairs
+}
+
+/// 按时间范围配对: 重叠时间最长的两个文件先配对，每个文件最多配对一次
+fn pair_by_time(reference_files: Vec<PathBuf>, comparison_files: Vec<PathBuf>) -> Result<Vec<FilePair>> {
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let bounds = |files: &[PathBuf]| -> Result<Vec<(i64, i64)>> {
+        run_parallel(files, threads, |path| {
+            capture_bounds_us(path.to_str().unwrap()).with_context(|| format!("无法读取时间范围: {}", path.display()))
+        })
+        .into_iter()
+        .collect()
+    };
+    let reference_bounds = bounds(&reference_files)?;
+    let comparison_bounds = bounds(&comparison_files)?;
+
+    // (重叠时长, 基准文件下标, 对比文件下标)，时间范围相接 (重叠为0) 也可以配对
+    let mut candidates = Vec::new();
+    for (i, &(start1, end1)) in reference_bounds.iter().enumerate() {
+        for (j, &(start2, end2)) in comparison_bounds.iter().enumerate() {
+            let overlap = end1.min(end2) - start1.max(start2);
+            if overlap >= 0 {
+                candidates.push((overlap, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+    let mut partner: Vec<Option<usize>> = vec![None; reference_files.len()];
+    let mut taken = vec![false; comparison_files.len()];
+    for (_, i, j) in candidates {
+        if partner[i].is_none() && !taken[j] {
+            partner[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+
+    // 按基准文件的开始时间排列，没有配对的对比文件放在最后
+    let mut order: Vec<usize> = (0..reference_files.len()).collect();
+    order.sort_by_key(|&i| reference_bounds[i]);
+    let mut pairs: Vec<FilePair> = order
+        .into_iter()
+        .map(|i| FilePair {
+            reference: Some(reference_files[i].clone()),
+            comparison: partner[i].map(|j| comparison_files[j].clone()),
+            start_us: Some(reference_bounds[i].0),
+        })
+        .collect();
+    pairs.extend(
+        (0..comparison_files.len())
+            .filter(|&j| !taken[j])
+            .map(|j| FilePair {
+                reference: None,
+                comparison: Some(comparison_files[j].clone()),
+                start_us: Some(comparison_bounds[j].0),
+            }),
+    );
+    Ok(pairs)
+}
+
+/// 用 `threads` 个线程对每一项执行 `task`，结果按原顺序返回
+fn run_parallel<T: Sync, R: Send>(items: &[T], threads: usize, task: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.clamp(1, items.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        done.push((index, task(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn display_name(path: &Option<PathBuf>) -> String {
+    match path {
+        Some(path) => path.file_name().unwrap().to_string_lossy().into_owned(),
+        None => "-".to_string(),
+    }
+}
+
+fn pair_name(pair: &FilePair) -> String {
+    match (&pair.reference, &pair.comparison) {
+        (Some(_), Some(_)) if display_name(&pair.reference) == display_name(&pair.comparison) => {
+            display_name(&pair.reference)
+        }
+        _ => format!("{} / {}", display_name(&pair.reference), display_name(&pair.comparison)),
+    }
+}
+
+fn print_directory_results(
+    reference_dir: &str,
+    comparison_dir: &str,
+    pairs: &[FilePair],
+    results: &[Result<CompareCounts>],
+    pair_by: PairBy,
+) {
+    let summaries = reconcile(results);
+    let mut total = PairSummary::default();
+    for summary in summaries.iter().flatten() {
+        total.reference_packets += summary.reference_packets;
+        total.comparison_packets += summary.comparison_packets;
+        total.missing += summary.missing;
+        total.extra += summary.extra;
+        total.shifted += summary.shifted;
+    }
+    // 每个跨文件的包在丢失的一侧和多余的一侧各计一次
+    let shifted = total.shifted / 2;
+    let paired = pairs.iter().filter(|pair| pair.reference.is_some() && pair.comparison.is_some()).count();
+    let only_reference = pairs.iter().filter(|pair| pair.comparison.is_none()).count();
+    let only_comparison = pairs.iter().filter(|pair| pair.reference.is_none()).count();
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    let differing = summaries.iter().flatten().filter(|summary| summary.missing + summary.extra > 0).count();
+
+    print_heading(&format!("目录比较结果: {} vs {}", reference_dir, comparison_dir));
+    let warning = |count: usize| (count > 0).then_some(Severity::Warning);
+    let mut fields = vec![
+        ("配对方式", match pair_by {
+            PairBy::Name => "文件名".to_string(),
+            PairBy::Time => "时间范围".to_string(),
+        }, None),
+        ("配对的文件", paired.to_string(), None),
+        ("只在基准目录中", only_reference.to_string(), warning(only_reference)),
+        ("只在对比目录中", only_comparison.to_string(), warning(only_comparison)),
+        ("有差异的文件对", differing.to_string(), warning(differing)),
+    ];
+    if failed > 0 {
+        fields.push(("比较失败", failed.to_string(), Some(Severity::Error)));
+    }
+    fields.extend([
+        ("基准文件包数", total.reference_packets.to_string(), None),
+        ("对比文件包数", total.comparison_packets.to_string(), None),
+        ("丢失包数", total.missing.to_string(), warning(total.missing)),
+        ("多余包数", total.extra.to_string(), warning(total.extra)),
+        ("跨文件的包数", shifted.to_string(), None),
+    ]);
+    print_fields(&fields);
+
+    // 默认只列出有差异、没有配对或比较失败的文件对
+    let verbose = verbosity() == Verbosity::Verbose;
+    let rows: Vec<(&FilePair, &Result<CompareCounts>, &Option<PairSummary>)> = pairs
+        .iter()
+        .zip(results)
+        .zip(&summaries)
+        .map(|((pair, result), summary)| (pair, result, summary))
+        .filter(|(pair, _, summary)| {
+            let consistent = summary.as_ref().is_some_and(|summary| summary.missing + summary.extra == 0);
+            verbose || !consistent || pair.reference.is_none() || pair.comparison.is_none()
+        })
+        .collect();
+    if !rows.is_empty() {
+        let max_rows = if verbose { usize::MAX } else { MAX_PAIR_ROWS };
+        print_section(if verbose { "各文件对:" } else { "有差异的文件对:" });
+        let mut headers = vec!["基准文件", "对比文件"];
+        if pair_by == PairBy::Time {
+            headers.push("开始时间");
+        }
+        headers.extend(["基准包数", "对比包数", "丢失", "多余", "跨文件", "结果"]);
+        let numeric_start = headers.len() - 6;
+        let numeric: Vec<usize> = (numeric_start..numeric_start + 5).collect();
+        let mut table = Table::new(&headers).right_align(&numeric);
+        let first_us = pairs.iter().filter_map(|pair| pair.start_us).min().unwrap_or_default();
+        for (pair, result, summary) in rows.iter().take(max_rows) {
+            let mut cells = vec![display_name(&pair.reference), display_name(&pair.comparison)];
+            if pair_by == PairBy::Time {
+                cells.push(pair.start_us.map_or("-".to_string(), |start| format_timestamp_us(start, first_us)));
+            }
+            match (result, summary) {
+                (Ok(_), Some(summary)) => {
+                    let verdict = match (&pair.reference, &pair.comparison) {
+                        (None, _) => "只在对比目录中",
+                        (_, None) => "只在基准目录中",
+                        _ if summary.missing + summary.extra > 0 => "有差异",
+                        _ => "一致",
+                    };
+                    cells.extend([
+                        summary.reference_packets.to_string(),
+                        summary.comparison_packets.to_string(),
+                        summary.missing.to_string(),
+                        summary.extra.to_string(),
+                        summary.shifted.to_string(),
+                        verdict.to_string(),
+                    ]);
+                }
+                (result, _) => {
+                    cells.extend(std::iter::repeat_n("-".to_string(), 5));
+                    cells.push(match result {
+                        Err(e) => format!("失败: {:#}", e),
+                        Ok(_) => String::new(),
+                    });
+                }
+            }
+            table.add_row(cells);
+        }
+        table.print();
+        if rows.len() > max_rows {
+            println!("  ... 还有 {} 对文件 (使用 --verbose 查看全部)", rows.len() - max_rows);
+        }
+    }
+
+    print_separator();
+    if failed > 0 {
+        print_verdict(Severity::Error, &format!("{} 对文件比较失败", failed));
+    } else if total.missing == 0 && total.extra == 0 {
+        print_verdict(Severity::Ok, &format!("两个目录的内容完全一致 (共 {} 对文件)", paired));
+    } else {
+        print_verdict(
+            Severity::Warning,
+            &format!("发现内容差异: 丢失 {} 个包, 多余 {} 个包", total.missing, total.extra),
+        );
+    }
+    if shifted > 0 {
+        print_verdict(
+            Severity::Ok,
+            &format!("{} 个包在两个目录中落在了不同的文件中 (分段边界不同)，不计为差异", shifted),
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use anyhow::{Context, Result};
+use log::{debug, error, info};
+
+use crate::modules::io::resolve_output_format;
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::compression;
+use crate::modules::pcap_pipeline::{load_pipeline, run_pipeline};
+
+/// 文件状态快照 (大小, 修改时间)
+type FileSignature = (u64, SystemTime);
+
+/// 监控目录，对每个新完成的PCAP文件执行流水线
+///
+/// # 参数
+/// - `watch_dir`: 监控的输入目录
+/// - `pipeline_path`: 流水线配置文件路径
+/// - `output_dir`: 处理结果输出目录
+/// - `poll_interval_secs`: 轮询间隔(秒)
+///
+/// # 功能
+/// 1. 轮询目录中的 .pcap/.cap/.pcapng 文件 (可以带 .gz 压缩扩展名；输入不支持zstd，.zst 文件被忽略)
+/// 2. 文件大小和修改时间在两次轮询间保持不变时视为写入完成
+/// 3. 结果以相同文件名写入输出目录 (压缩扩展名换成 `--compress` 对应的扩展名)，已存在结果的文件会被跳过
+/// 4. 文件被重新写入后会再次处理
+pub fn watch_directory(
+    watch_dir: &str,
+    pipeline_path: &str,
+    output_dir: &str,
+    poll_interval_secs: u64,
+) -> Result<()> {
+    if poll_interval_secs == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("轮询间隔必须大于0".to_string()));
+    }
+
+    // 启动前先校验流水线配置
+    let steps = load_pipeline(pipeline_path)?;
+
+    let watch_path = Path::new(watch_dir);
+    if !watch_path.is_dir() {
+        anyhow::bail!(PcapError::InvalidArgument(format!("监控目录不存在: {}", watch_dir)));
+    }
+    fs::create_dir_all(output_dir)
+        .map_err(|e| PcapError::WriteFailure(format!("无法创建输出目录: {}: {}", output_dir, e)))?;
+    if fs::canonicalize(watch_path)? == fs::canonicalize(output_dir)? {
+        anyhow::bail!(PcapError::InvalidArgument(format!("输出目录不能与监控目录相同: {}", output_dir)));
+    }
+
+    info!(
+        "开始监控目录: {} (流水线: {}, {} 个步骤, 输出目录: {})",
+        watch_dir, pipeline_path, steps.len(), output_dir
+    );
+
+    let mut pending: HashMap<PathBuf, FileSignature> = HashMap::new();
+    let mut processed: HashMap<PathBuf, FileSignature> = HashMap::new();
+
+    loop {
+        let entries = fs::read_dir(watch_path)
+            .with_context(|| format!("无法读取监控目录: {}", watch_dir))?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !is_capture_file(&path) {
+                continue;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => continue,
+            };
+            let signature = (metadata.len(), metadata.modified()?);
+
+            // 已处理且未被改写
+            if processed.get(&path) == Some(&signature) {
+                continue;
+            }
+
+            let output_path = Path::new(output_dir).join(output_file_name(&path));
+            if !processed.contains_key(&path) && output_path.exists() {
+                debug!("跳过已有结果的文件: {}", path.display());
+                processed.insert(path, signature);
+                continue;
+            }
+
+            // 两次轮询间状态未变化才视为写入完成
+            if pending.get(&path) != Some(&signature) || signature.0 == 0 {
+                pending.insert(path, signature);
+                continue;
+            }
+            pending.remove(&path);
+
+            info!("检测到新完成的文件: {}", path.display());
+            let output = output_path.to_string_lossy();
+            match run_pipeline(&steps, &path.to_string_lossy(), &output, resolve_output_format(&output), None) {
+                Ok(()) => info!("处理完成: {}", output_path.display()),
+                Err(e) => error!("处理文件 {} 失败: {:#}", path.display(), e),
+            }
+            processed.insert(path, signature);
+        }
+
+        // 清理已被移走的文件
+        pending.retain(|path, _| path.exists());
+        processed.retain(|path, _| path.exists());
+
+        thread::sleep(Duration::from_secs(poll_interval_secs));
+    }
+}
+
+/// 去掉 `.gz` 压缩扩展名后的文件名
+fn uncompressed_name(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    let lower = name.to_ascii_lowercase();
+    let suffix_len = if lower.ends_with(".gz") { 3 } else { 0 };
+    Some(&name[..name.len() - suffix_len])
+}
+
+/// 是否为需要处理的抓包文件 (与读取时识别的格式相同: PCAP、pcapng，以及gzip压缩的文件)
+fn is_capture_file(path: &Path) -> bool {
+    uncompressed_name(path)
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .map(|ext| matches!(ext.to_ascii_lowercase().as_str(), "pcap" | "cap" | "pcapng"))
+        .unwrap_or(false)
+}
+
+/// 结果文件名: 输入文件名的压缩扩展名换成输出的压缩扩展名
+fn output_file_name(path: &Path) -> String {
+    format!("{}{}", uncompressed_name(path).unwrap_or_default(), compression().suffix())
+}
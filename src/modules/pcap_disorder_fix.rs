@@ -0,0 +1,188 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use pcap_file::pcap_header::PcapHeader;
+use pcap_file::Packet;
+use anyhow::Result;
+use log::{debug, info};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_store::{PACKET_OVERHEAD, SpillFile, memory_limit};
+use crate::modules::pcap_report::{print_fields, print_heading};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 已排序并写入临时文件的一段数据包
+struct SortedRun {
+    file: SpillFile,
+    /// 每个包在输入文件中的序号 (按排序后的顺序)
+    origins: Vec<u64>,
+}
+
+/// 排序结果统计
+#[derive(Default)]
+struct MoveStats {
+    position: u64,
+    moved: u64,
+    max_distance: u64,
+}
+
+impl MoveStats {
+    fn record(&mut self, origin: u64) {
+        if origin != self.position {
+            self.moved += 1;
+            self.max_distance = self.max_distance.max(origin.abs_diff(self.position));
+        }
+        self.position += 1;
+    }
+}
+
+/// 按时间戳重新排序乱序的数据包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng，dry_run时可为None)
+/// - `dry_run`: 只统计需要移动的包数，不写输出文件
+///
+/// # 功能
+/// 1. 稳定排序：时间戳相同的包保持原有的相对顺序
+/// 2. 超出 `--max-memory` 上限时分段排序后写入临时文件，再多路归并
+pub fn pcap_disorder_fix(input_path: &str, output_path: Option<&str>, dry_run: bool) -> Result<()> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let header = pcap_reader.header;
+
+    let mut pcap_writer = match (dry_run, output_path) {
+        (true, _) => None,
+        (false, Some(path)) => {
+            let out_file = create_output(path, compression())?;
+            Some(PacketWriter::with_header(header, out_file, path)
+                .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?)
+        }
+        (false, None) => anyhow::bail!(PcapError::InvalidArgument("未指定输出文件".to_string())),
+    };
+
+    let mut stats = MoveStats::default();
+    let mut digest = OutputDigest::default();
+    let packet_count = sort_by_timestamp(&mut pcap_reader, |origin, packet| {
+        stats.record(origin);
+        if let Some(writer) = pcap_writer.as_mut() {
+            writer.write_packet(packet)
+                .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+            digest.record_pcap(packet);
+        }
+        Ok(())
+    })?;
+
+    if dry_run {
+        print_heading(&format!("乱序修复预览: {}", input_path));
+        print_fields(&[
+            ("总包数", packet_count.to_string(), None),
+            ("需要移动的包数", stats.moved.to_string(), None),
+            ("最大移动距离", format!("{} 个包", stats.max_distance), None),
+        ]);
+    } else {
+        if let (Some(writer), Some(path)) = (pcap_writer, output_path) {
+            writer.into_inner().finish()?;
+            digest.verify(path)?;
+        }
+        info!(
+            "成功修复乱序: 总包数={}, 移动包数={}, 输出文件={}",
+            packet_count,
+            stats.moved,
+            output_path.unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+/// 按时间戳稳定排序读入的全部数据包，按排序后的顺序对每个包调用 `emit(在输入中的序号, 包)`，返回包数
+///
+/// 超出 `--max-memory` 上限时分段排序后写入临时文件，再多路归并。
+pub fn sort_by_timestamp(
+    pcap_reader: &mut PacketReader,
+    mut emit: impl FnMut(u64, &Packet) -> Result<()>,
+) -> Result<u64> {
+    let header = pcap_reader.header;
+    let limit = memory_limit();
+    let mut runs: Vec<SortedRun> = Vec::new();
+    let mut current: Vec<(u64, Packet<'static>)> = Vec::new();
+    let mut current_bytes = 0u64;
+    let mut packet_count = 0u64;
+
+    for packet in pcap_reader.by_ref() {
+        current_bytes += (packet.data.len() + PACKET_OVERHEAD) as u64;
+        current.push((packet_count, packet));
+        packet_count += 1;
+
+        if limit.is_some_and(|limit| current_bytes > limit) {
+            runs.push(write_run(header, &mut current)?);
+            current_bytes = 0;
+        }
+    }
+    // 截断的输入在写出任何包之前报错
+    pcap_reader.check_complete()?;
+
+    if runs.is_empty() {
+        current.sort_by_key(|(_, packet)| timestamp_key(packet));
+        for (origin, packet) in &current {
+            emit(*origin, packet)?;
+        }
+    } else {
+        if !current.is_empty() {
+            runs.push(write_run(header, &mut current)?);
+        }
+        info!("数据包超出内存上限，分 {} 段排序后归并", runs.len());
+        merge_runs(&mut runs, &mut emit)?;
+    }
+    Ok(packet_count)
+}
+
+/// 排序键 (秒, 微秒)
+fn timestamp_key(packet: &Packet) -> (u32, u32) {
+    (packet.header.ts_sec, packet.header.ts_usec)
+}
+
+/// 稳定排序当前段并写入临时文件
+fn write_run(header: PcapHeader, packets: &mut Vec<(u64, Packet<'static>)>) -> Result<SortedRun> {
+    packets.sort_by_key(|(_, packet)| timestamp_key(packet));
+    let mut file = SpillFile::create(header)?;
+    let mut origins = Vec::with_capacity(packets.len());
+    for (origin, packet) in packets.drain(..) {
+        file.write(&packet)?;
+        origins.push(origin);
+    }
+    debug!("写入排序段: {} 个数据包", origins.len());
+    Ok(SortedRun { file, origins })
+}
+
+/// 多路归并已排序的段；时间戳相同时先输出靠前的段，保证稳定
+fn merge_runs<F>(runs: &mut [SortedRun], emit: &mut F) -> Result<()>
+where
+    F: FnMut(u64, &Packet) -> Result<()>,
+{
+    let mut readers = Vec::with_capacity(runs.len());
+    for run in runs.iter_mut() {
+        readers.push(run.file.packets()?.zip(run.origins.iter().copied()));
+    }
+
+    let mut heads: Vec<Option<(Packet<'static>, u64)>> = readers.iter_mut().map(|r| r.next()).collect();
+    let mut heap = BinaryHeap::new();
+    for (index, head) in heads.iter().enumerate() {
+        if let Some((packet, _)) = head {
+            heap.push(Reverse((timestamp_key(packet), index)));
+        }
+    }
+
+    while let Some(Reverse((_, index))) = heap.pop() {
+        let (packet, origin) = heads[index].take().unwrap();
+        emit(origin, &packet)?;
+
+        heads[index] = readers[index].next();
+        if let Some((next, _)) = &heads[index] {
+            heap.push(Reverse((timestamp_key(next), index)));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::Hasher;
+use seahash::SeaHasher;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::pcap_annotate::write_annotated;
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{ParsedPacket, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{Severity, Table, Verbosity, print_fields, print_heading, print_section, print_separator, print_verdict, verbosity};
+
+/// 详情表中最多列出的重复包数 (详细模式不限制)
+const MAX_DUPLICATE_ROWS: usize = 50;
+
+/// 一个重复包和它之前的相同包
+struct DuplicatePair {
+    /// 重复包的序号 (从1开始)
+    number: u64,
+    /// 之前的相同包的序号
+    original: u64,
+    delta_ns: u64,
+    len: usize,
+    flow: String,
+}
+
+/// 检测过程中的统计
+#[derive(Default)]
+struct DuplicateStats {
+    packet_count: u64,
+    total_bytes: u64,
+    duplicate_bytes: u64,
+    /// 每个重复包与之前的相同包的时间差
+    deltas_ns: Vec<u64>,
+    /// 详情表中列出的重复包
+    pairs: Vec<DuplicatePair>,
+}
+
+/// 重复包的注释
+fn duplicate_comment(original: u64, delta_ns: u64) -> String {
+    format!("重复: 与包#{}相同，时间差 {:.9} 秒", original, delta_ns as f64 / 1e9)
+}
+
+/// 报告窗口内内容相同的重复包，不修改文件
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `window_us`: 时间窗口 (微秒)，与之前的相同包时间差不超过窗口时视为重复
+/// - `ignore_ttl`: 比较时忽略IPv4的TTL和头校验和、IPv6的跳数限制 (包经过路由器后再次被抓到)
+/// - `annotate_path`: 不为None时把输入复制为pcapng，为每个重复包添加注释
+///
+/// # 输出
+/// 重复包数及占比、重复的字节数、与之前的相同包的时间差分布，以及重复包与相同包的序号对。
+/// 连续多次出现的包每次都与上一次出现配对。
+pub fn detect_duplicates(input_path: &str, window_us: u64, ignore_ttl: bool, annotate_path: Option<&str>) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let window_ns = window_us.saturating_mul(1000) as i128;
+    // 窗口内的包: (时间, 哈希, 序号)
+    let mut recent: VecDeque<(i128, u64, u64)> = VecDeque::new();
+    // 哈希 -> 窗口内最近一次出现的 (序号, 时间)
+    let mut last_seen: HashMap<u64, (u64, i128)> = HashMap::new();
+    let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_DUPLICATE_ROWS };
+    let mut stats = DuplicateStats::default();
+    let mut comments: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        stats.packet_count += 1;
+        stats.total_bytes += packet.data.len() as u64;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let linktype = interface.linktype as u32;
+        let time_ns = interface.timestamp_ns(packet.timestamp);
+
+        while let Some(&(time, hash, number)) = recent.front()
+            && time < time_ns - window_ns
+        {
+            recent.pop_front();
+            if last_seen.get(&hash).is_some_and(|&(last, _)| last == number) {
+                last_seen.remove(&hash);
+            }
+        }
+
+        let parsed = parse_packet(&packet.data, linktype);
+        let hash = content_hash(&packet.data, linktype, &parsed, ignore_ttl);
+        if let Some(&(original, original_ns)) = last_seen.get(&hash) {
+            let delta_ns = (time_ns - original_ns).unsigned_abs();
+            if delta_ns <= window_ns as u128 {
+                let delta_ns = delta_ns as u64;
+                stats.deltas_ns.push(delta_ns);
+                stats.duplicate_bytes += packet.data.len() as u64;
+                if annotate_path.is_some() {
+                    comments.insert(stats.packet_count, vec![duplicate_comment(original, delta_ns)]);
+                }
+                if stats.pairs.len() < max_rows {
+                    let flow = match parsed.flow_key() {
+                        Some(key) => key.to_string(),
+                        None => "(非IP包)".to_string(),
+                    };
+                    stats.pairs.push(DuplicatePair {
+                        number: stats.packet_count,
+                        original,
+                        delta_ns,
+                        len: packet.data.len(),
+                        flow,
+                    });
+                }
+            }
+        }
+        last_seen.insert(hash, (stats.packet_count, time_ns));
+        recent.push_back((time_ns, hash, stats.packet_count));
+    }
+
+    print_report(input_path, window_us, ignore_ttl, &mut stats);
+    if let Some(annotate_path) = annotate_path {
+        write_annotated(input_path, annotate_path, &comments, "pcap-editor detect-dupes")?;
+        info!("成功输出带注释的副本: 注释的包数={}, 输出文件={}", comments.len(), annotate_path);
+    }
+    Ok(())
+}
+
+/// 包内容的哈希，`ignore_ttl` 时跳过TTL/跳数限制和IPv4头校验和
+fn content_hash(data: &[u8], linktype: u32, parsed: &ParsedPacket, ignore_ttl: bool) -> u64 {
+    let mut hasher = SeaHasher::new();
+    hasher.write_u32(linktype);
+    let skipped: &[usize] = match &parsed.ip {
+        Some(ip) if ignore_ttl && ip.version == 4 => &[ip.offset + 8, ip.offset + 10, ip.offset + 11],
+        Some(ip) if ignore_ttl && ip.version == 6 => &[ip.offset + 7],
+        _ => &[],
+    };
+    let mut start = 0;
+    for &position in skipped {
+        if position >= data.len() {
+            break;
+        }
+        hasher.write(&data[start..position]);
+        start = position + 1;
+    }
+    hasher.write(&data[start.min(data.len())..]);
+    hasher.finish()
+}
+
+fn print_report(input_path: &str, window_us: u64, ignore_ttl: bool, stats: &mut DuplicateStats) {
+    let DuplicateStats { packet_count, total_bytes, duplicate_bytes, deltas_ns, pairs } = stats;
+    let (packet_count, total_bytes, duplicate_bytes) = (*packet_count, *total_bytes, *duplicate_bytes);
+    let duplicates = deltas_ns.len() as u64;
+    let percent = |part: u64, total: u64| if total > 0 { part as f64 * 100.0 / total as f64 } else { 0.0 };
+    print_heading(&format!("重复包检测结果: {}", input_path));
+    let mut fields = vec![
+        (
+            "比较方式",
+            if ignore_ttl { "忽略TTL/跳数限制和IP头校验和".to_string() } else { "完整内容".to_string() },
+            None,
+        ),
+        ("时间窗口", format!("{:.6} 秒", window_us as f64 / 1_000_000.0), None),
+        ("数据包数", packet_count.to_string(), None),
+        (
+            "重复包数",
+            format!("{} ({:.2}%)", duplicates, percent(duplicates, packet_count)),
+            (duplicates > 0).then_some(Severity::Warning),
+        ),
+        (
+            "重复字节数",
+            format!("{} ({:.2}%)", duplicate_bytes, percent(duplicate_bytes, total_bytes)),
+            None,
+        ),
+    ];
+    if !deltas_ns.is_empty() {
+        deltas_ns.sort_unstable();
+        let seconds = |ns: u64| format!("{:.9}", ns as f64 / 1e9);
+        fields.push((
+            "时间差 (秒)",
+            format!(
+                "最小 {} / 中位数 {} / 最大 {}",
+                seconds(deltas_ns[0]),
+                seconds(deltas_ns[deltas_ns.len() / 2]),
+                seconds(deltas_ns[deltas_ns.len() - 1])
+            ),
+            None,
+        ));
+    }
+    print_fields(&fields);
+
+    if !pairs.is_empty() {
+        print_section("重复包详情:");
+        let mut table = Table::new(&["序号", "相同包序号", "间隔包数", "时间差 (秒)", "长度 (字节)", "五元组"])
+            .right_align(&[0, 1, 2, 3, 4]);
+        for pair in pairs.iter() {
+            table.add_row(vec![
+                pair.number.to_string(),
+                pair.original.to_string(),
+                (pair.number - pair.original - 1).to_string(),
+                format!("{:.9}", pair.delta_ns as f64 / 1e9),
+                pair.len.to_string(),
+                pair.flow.clone(),
+            ]);
+        }
+        table.print();
+        if duplicates > pairs.len() as u64 {
+            println!("  ... 还有 {} 个重复包 (使用 --verbose 查看全部)", duplicates - pairs.len() as u64);
+        }
+    }
+
+    print_separator();
+    if duplicates == 0 {
+        print_verdict(Severity::Ok, &format!("未检测到重复包 (共 {} 个数据包)", packet_count));
+    } else {
+        print_verdict(
+            Severity::Warning,
+            &format!("检测到 {} 个重复包，占 {:.2}%", duplicates, percent(duplicates, packet_count)),
+        );
+    }
+}
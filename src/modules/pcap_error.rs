@@ -0,0 +1,118 @@
+use std::fmt;
+use std::io;
+
+/// 可按原因区分的错误，每种对应固定的退出码
+///
+/// 各模块仍以 `anyhow::Result` 返回错误；需要区分原因的地方把 `PcapError`
+/// 放进错误链，`main` 按链中第一个 `PcapError` 决定退出码。
+///
+/// | 退出码 | 含义 |
+/// | ------ | ---- |
+/// | 0 | 成功 |
+/// | 1 | 其他错误 |
+/// | 2 | 命令行参数无效 (与参数解析错误相同) |
+/// | 3 | 无法打开输入文件 |
+/// | 4 | 输入文件格式无效 |
+/// | 5 | 输入文件被截断 |
+/// | 6 | 输入文件不包含任何数据包 |
+/// | 7 | 创建或写入输出失败 |
+#[derive(Debug)]
+pub enum PcapError {
+    /// 参数取值无效或参数组合不正确
+    InvalidArgument(String),
+    /// 无法打开输入文件
+    OpenFailed { path: String, source: io::Error },
+    /// 不是有效的PCAP/pcapng文件，或文件中的结构无效
+    InvalidFormat(String),
+    /// 文件在数据包或块的中间结束
+    TruncatedFile(String),
+    /// 输入文件不包含任何数据包
+    EmptyCapture,
+    /// 创建输出文件或写入数据包失败
+    WriteFailure(String),
+}
+
+/// 无法归类的错误的退出码
+pub const EXIT_OTHER: u8 = 1;
+
+impl PcapError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            PcapError::InvalidArgument(_) => 2,
+            PcapError::OpenFailed { .. } => 3,
+            PcapError::InvalidFormat(_) => 4,
+            PcapError::TruncatedFile(_) => 5,
+            PcapError::EmptyCapture => 6,
+            PcapError::WriteFailure(_) => 7,
+        }
+    }
+
+    pub fn open_failed(path: &str, source: io::Error) -> PcapError {
+        PcapError::OpenFailed { path: path.to_string(), source }
+    }
+
+    /// PCAP文件头无效
+    pub fn invalid_pcap(reason: impl fmt::Display) -> PcapError {
+        PcapError::InvalidFormat(format!("无效的PCAP文件格式: {}", reason))
+    }
+
+    pub fn create_failed(path: &str, source: io::Error) -> PcapError {
+        PcapError::WriteFailure(format!("无法创建输出文件: {}: {}", path, source))
+    }
+}
+
+impl fmt::Display for PcapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcapError::OpenFailed { path, .. } => write!(f, "无法打开输入文件: {}", path),
+            PcapError::EmptyCapture => write!(f, "输入文件不包含任何数据包"),
+            PcapError::InvalidArgument(message)
+            | PcapError::InvalidFormat(message)
+            | PcapError::TruncatedFile(message)
+            | PcapError::WriteFailure(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PcapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PcapError::OpenFailed { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// PCAP文件头长度
+pub const PCAP_HEADER_LEN: u64 = 24;
+/// PCAP每个包的记录头长度
+pub const PCAP_RECORD_HEADER_LEN: u64 = 16;
+
+/// 检查PCAP文件是否已读到末尾
+///
+/// PCAP读取器遇到不完整的包时直接结束迭代 (不完整的部分同样被读走)，
+/// 因此按已解析的文件头和数据包记录的总字节数与文件大小比较。
+/// `read_bytes` 为 [`PCAP_HEADER_LEN`] 加上每个包的记录头和数据长度。
+pub fn check_fully_read(path: &str, read_bytes: u64) -> Result<(), PcapError> {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return Ok(());
+    };
+    if read_bytes < metadata.len() {
+        return Err(PcapError::TruncatedFile(format!(
+            "输入文件被截断: {} (已读取 {} 字节/总计 {} 字节)",
+            path,
+            read_bytes,
+            metadata.len()
+        )));
+    }
+    Ok(())
+}
+
+/// 错误链中第一个 `PcapError` 对应的退出码，没有时为 [`EXIT_OTHER`]
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<PcapError>())
+        .map(PcapError::exit_code)
+        .unwrap_or(EXIT_OTHER)
+}
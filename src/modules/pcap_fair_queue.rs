@@ -0,0 +1,149 @@
+use std::collections::{HashMap, VecDeque};
+use pcap_file::Packet;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{FlowKey, parse_packet};
+use crate::modules::pcap_time_anchor::{split_timestamp_ticks, ticks_per_second, timestamp_ticks};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 一个流的队列
+#[derive(Default)]
+struct FlowQueue {
+    /// 等待发出的包 (在按时间排序的全部包中的下标)
+    packets: VecDeque<usize>,
+    /// 亏空计数 (字节)
+    deficit: u64,
+}
+
+/// 把并发的流按轮询交错，而不是一个流连续突发
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `quantum`: 每个流每一轮可以发出的字节数
+///
+/// # 功能
+/// 1. 按双向的五元组分流 (非IP包为一个流)，每个流内部的包顺序不变
+/// 2. 输出使用与输入相同的一组时间戳 (按时间排序后的每个时刻)，整体的包速率不变；
+///    每个时刻在已经开始的流 (第一个包的时间已到) 中按亏空轮询 (DRR) 选择发出哪个流的下一个包
+/// 3. 流的第一个包不会早于原始时间，之后的包可能提前或推迟，但不会早于流的开始
+/// 4. 输入按时间戳 (相同时保持原顺序) 处理，所有包读入内存
+pub fn pcap_fair_queue(input_path: &str, output_path: &str, quantum: u64) -> Result<()> {
+    if quantum == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("每轮的字节数必须大于0".to_string()));
+    }
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let header = pcap_reader.header;
+    let linktype: u32 = header.datalink.into();
+    // 时间以文件的精度计 (微秒或纳秒)
+    let ticks_per_second = ticks_per_second(&header);
+
+    // (原始时间, 流号, 包)
+    let mut packets: Vec<(i64, usize, Packet<'static>)> = Vec::new();
+    let mut flow_numbers: HashMap<Option<FlowKey>, usize> = HashMap::new();
+    for packet in pcap_reader.by_ref() {
+        let key = parse_packet(&packet.data, linktype).flow_key().map(|key| key.canonical());
+        let next_number = flow_numbers.len();
+        let flow = *flow_numbers.entry(key).or_insert(next_number);
+        packets.push((timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second), flow, packet));
+    }
+    packets.sort_by_key(|(time, _, _)| *time);
+
+    let mut queues: Vec<FlowQueue> = (0..flow_numbers.len()).map(|_| FlowQueue::default()).collect();
+    // 按开始时间排列的流
+    let mut starts = Vec::new();
+    for (index, &(_, flow, _)) in packets.iter().enumerate() {
+        if queues[flow].packets.is_empty() {
+            starts.push(flow);
+        }
+        queues[flow].packets.push_back(index);
+    }
+    // 有包等待发出的流，队首为当前轮到的流
+    let mut active: VecDeque<usize> = VecDeque::new();
+    // 当前轮到的流是否已经得到本轮的配额
+    let mut turn_started = false;
+    let mut order = Vec::with_capacity(packets.len());
+    let mut started = 0;
+    for &(slot, _, _) in &packets {
+        while started < starts.len() && packets[queues[starts[started]].packets[0]].0 <= slot {
+            active.push_back(starts[started]);
+            started += 1;
+        }
+        // 时刻是按时间排序的原始时间，原始时间不晚于这个时刻的包所在的流都已经开始，剩下的包中至少有一个属于它们
+        loop {
+            let flow = active[0];
+            let queue = &mut queues[flow];
+            if !turn_started {
+                queue.deficit += quantum;
+                turn_started = true;
+            }
+            let next = queue.packets[0];
+            let size = packets[next].2.header.orig_len as u64;
+            if size <= queue.deficit {
+                queue.deficit -= size;
+                queue.packets.pop_front();
+                if queue.packets.is_empty() {
+                    active.pop_front();
+                    turn_started = false;
+                }
+                order.push((next, slot));
+                break;
+            }
+            active.rotate_left(1);
+            turn_started = false;
+        }
+    }
+
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+    let mut moved = 0u64;
+    let mut max_delay = 0i64;
+    let mut max_advance = 0i64;
+    for &(index, slot) in &order {
+        let (original, _, packet) = &mut packets[index];
+        if slot != *original {
+            moved += 1;
+            max_delay = max_delay.max(slot - *original);
+            max_advance = max_advance.max(*original - slot);
+            let (ts_sec, ts_usec) = split_timestamp_ticks(slot, ticks_per_second)?;
+            packet.header.ts_sec = ts_sec;
+            packet.header.ts_usec = ts_usec;
+        }
+        pcap_writer.write_packet(packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功交错各个流: 包数={}, 流数={}, 同一流连续的最长包数: 输入={}, 输出={}, 调整时间的包数={}, 最大推迟={:.3}ms, 最大提前={:.3}ms, 输出文件={}",
+        packets.len(),
+        flow_numbers.len(),
+        longest_run(packets.iter().map(|(_, flow, _)| *flow)),
+        longest_run(order.iter().map(|&(index, _)| packets[index].1)),
+        moved,
+        max_delay as f64 * 1000.0 / ticks_per_second as f64,
+        max_advance as f64 * 1000.0 / ticks_per_second as f64,
+        output_path
+    );
+    Ok(())
+}
+
+/// 同一个流连续出现的最长包数
+fn longest_run(flows: impl Iterator<Item = usize>) -> usize {
+    let mut longest = 0;
+    let mut current = (usize::MAX, 0);
+    for flow in flows {
+        current = if flow == current.0 { (flow, current.1 + 1) } else { (flow, 1) };
+        longest = longest.max(current.1);
+    }
+    longest
+}
@@ -0,0 +1,208 @@
+use pcap_file::pcap_header::Datalink;
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_padding::ETHERNET_MIN_FRAME;
+use crate::modules::pcap_verify::OutputDigest;
+
+const LINKTYPE_ETHERNET: u32 = 1;
+
+/// PCAP文件头链路类型字段中的F位: 置位时第29-31位为FCS长度 (以16位为单位)
+const LINKTYPE_FCS_PRESENT: u32 = 1 << 28;
+
+/// 以太网FCS长度
+const ETHERNET_FCS_LEN: usize = 4;
+
+/// CRC-32 (IEEE 802.3，反射多项式 0xEDB88320) 查找表
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// 计算CRC-32 (以太网FCS、gzip、PNG使用的算法)
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// 在之前数据的CRC-32 (`crc`) 上继续计算，用于分段到达的数据
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    !data.iter().fold(!crc, |crc, &byte| CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+/// 帧的最后4字节是否为正确的FCS (FCS在线路上按小端顺序出现在帧尾)
+fn has_valid_fcs(frame: &[u8]) -> bool {
+    frame.len() >= 14 + ETHERNET_FCS_LEN && {
+        let (body, fcs) = frame.split_at(frame.len() - ETHERNET_FCS_LEN);
+        crc32(body).to_le_bytes() == fcs
+    }
+}
+
+/// 文件头声明的FCS长度 (字节)，没有声明时为None
+fn declared_fcs_len(raw_linktype: u32) -> Option<usize> {
+    (raw_linktype & LINKTYPE_FCS_PRESENT != 0).then(|| ((raw_linktype >> 29) & 0x7) as usize * 2)
+}
+
+fn check_ethernet(raw_linktype: u32) -> Result<()> {
+    if raw_linktype & 0xffff != LINKTYPE_ETHERNET {
+        anyhow::bail!(PcapError::InvalidArgument(format!(
+            "只支持以太网链路类型的FCS，当前链路类型为: {}",
+            raw_linktype & 0xffff
+        )));
+    }
+    Ok(())
+}
+
+/// 删除以太网帧尾的FCS
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `assume_fcs`: 文件头没有声明FCS时，仍认为每个帧都带4字节FCS
+///
+/// # 说明
+/// 1. 文件头的链路类型字段声明了FCS长度时，每个帧都删除该长度，输出文件头清除FCS声明
+/// 2. 没有声明时只删除校验正确的FCS (帧尾4字节等于前面内容的CRC-32)，校验错误的帧保持不变；
+///    `assume_fcs` 时不校验，每个帧都删除4字节
+/// 3. 原始长度同样减去FCS长度；被snaplen截断、没有抓到FCS的帧只修改原始长度
+pub fn pcap_strip_fcs(input_path: &str, output_path: &str, assume_fcs: bool) -> Result<()> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let raw_linktype: u32 = pcap_reader.header.datalink.into();
+    check_ethernet(raw_linktype)?;
+    let declared = declared_fcs_len(raw_linktype);
+    let mut header = pcap_reader.header;
+    header.datalink = Datalink::from(raw_linktype & 0xffff);
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut packet_count = 0;
+    let mut stripped = 0;
+    let mut invalid = 0;
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        packet_count += 1;
+        let complete = packet.header.incl_len >= packet.header.orig_len;
+        let fcs_len = match declared {
+            Some(len) => len,
+            None if assume_fcs => ETHERNET_FCS_LEN,
+            None if complete && has_valid_fcs(&packet.data) => ETHERNET_FCS_LEN,
+            None => {
+                if complete {
+                    invalid += 1;
+                }
+                0
+            }
+        };
+        if fcs_len > 0 && packet.header.orig_len as usize >= fcs_len {
+            let orig_len = packet.header.orig_len as usize - fcs_len;
+            if packet.data.len() > orig_len {
+                packet.data.to_mut().truncate(orig_len);
+                packet.header.incl_len = orig_len as u32;
+            }
+            packet.header.orig_len = orig_len as u32;
+            stripped += 1;
+        }
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    if invalid > 0 && stripped == 0 {
+        warn!("没有帧带正确的FCS，输入可能不带FCS (确认带FCS时可使用 --assume-fcs)");
+    } else if invalid > 0 {
+        warn!("{} 个帧的最后4字节不是正确的FCS，保持不变 (确认带FCS时可使用 --assume-fcs)", invalid);
+    }
+    info!(
+        "成功删除FCS: 包数={}, 删除FCS的包数={}, 文件头声明FCS={}, 输出文件={}",
+        packet_count,
+        stripped,
+        declared.map_or("否".to_string(), |len| format!("{}字节", len)),
+        output_path
+    );
+
+    Ok(())
+}
+
+/// 在以太网帧尾追加正确计算的FCS
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+///
+/// # 说明
+/// 1. 短于60字节的帧先补零到60字节 (与网卡发送时相同)，再追加4字节CRC-32，原始长度同样增加
+/// 2. 已带正确FCS的帧和被snaplen截断的帧 (无法计算FCS) 保持不变
+/// 3. 文件头不声明FCS，链路类型仍为普通以太网，其他命令照常解析；Wireshark把FCS显示为帧尾
+pub fn pcap_add_fcs(input_path: &str, output_path: &str) -> Result<()> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let raw_linktype: u32 = pcap_reader.header.datalink.into();
+    check_ethernet(raw_linktype)?;
+    if declared_fcs_len(raw_linktype).is_some() {
+        anyhow::bail!(PcapError::InvalidArgument("文件头声明帧已带FCS，无需添加".to_string()));
+    }
+    // 帧变长了4字节，snaplen同样增加，免得完整的帧超过snaplen
+    let mut header = pcap_reader.header;
+    header.snaplen = header.snaplen.saturating_add(ETHERNET_FCS_LEN as u32);
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut packet_count = 0;
+    let mut added = 0;
+    let mut already = 0;
+    let mut truncated = 0;
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        packet_count += 1;
+        if packet.header.incl_len < packet.header.orig_len {
+            truncated += 1;
+        } else if packet.data.len() >= ETHERNET_MIN_FRAME + ETHERNET_FCS_LEN && has_valid_fcs(&packet.data) {
+            already += 1;
+        } else {
+            let data = packet.data.to_mut();
+            if data.len() < ETHERNET_MIN_FRAME {
+                data.resize(ETHERNET_MIN_FRAME, 0);
+            }
+            let fcs = crc32(data);
+            data.extend_from_slice(&fcs.to_le_bytes());
+            packet.header.incl_len = data.len() as u32;
+            packet.header.orig_len = data.len() as u32;
+            added += 1;
+        }
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    if truncated > 0 {
+        warn!("{} 个帧被snaplen截断，无法计算FCS，保持不变", truncated);
+    }
+    info!(
+        "成功添加FCS: 包数={}, 添加FCS的包数={}, 已有FCS的包数={}, 输出文件={}",
+        packet_count, added, already, output_path
+    );
+
+    Ok(())
+}
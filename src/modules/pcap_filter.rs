@@ -0,0 +1,630 @@
+use std::net::IpAddr;
+use anyhow::{Result, anyhow};
+
+use crate::modules::pcap_packet_parser::{
+    IP_PROTO_ICMP, IP_PROTO_ICMPV6, LINKTYPE_ETHERNET, ParsedPacket, Transport, read_u16, read_u32,
+};
+
+/// 过滤表达式 (Wireshark显示过滤器语法的子集)
+///
+/// 支持协议名 (`tcp`、`udp`、`ip`、`ipv6`、`vlan` 等)、字段比较 (`ip.src == 10.0.0.0/8`、
+/// `tcp.dstport >= 1024`、`tcp.flags.syn == 1`)、集合 (`tcp.port in {80 443 8000..8080}`)、
+/// 位测试 (`tcp.flags & 0x12`)，以及 `and`/`or`/`not` (或 `&&`/`||`/`!`) 和括号。
+/// 字段有多个值时 (如 `ip.addr`、`tcp.port`) 任一值满足即为真，`!=` 表示所有值都不相等。
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Protocol(Protocol),
+    /// 字段存在
+    Present(Field),
+    Compare(Field, CompareOp, Value),
+    /// 字段的任一值在集合中
+    In(Field, Vec<SetItem>),
+    /// 字段的任一值与掩码按位与不为0
+    BitAnd(Field, u64),
+}
+
+/// 集合中的一项: 单个值，或数值的闭区间 (`a..b`)
+#[derive(Debug, Clone, Copy)]
+enum SetItem {
+    Value(Value),
+    Range(u64, u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Eth,
+    Vlan,
+    Ip,
+    Ipv6,
+    Tcp,
+    Udp,
+    Icmp,
+    Icmpv6,
+}
+
+const PROTOCOLS: [(&str, Protocol); 8] = [
+    ("eth", Protocol::Eth),
+    ("vlan", Protocol::Vlan),
+    ("ip", Protocol::Ip),
+    ("ipv6", Protocol::Ipv6),
+    ("tcp", Protocol::Tcp),
+    ("udp", Protocol::Udp),
+    ("icmp", Protocol::Icmp),
+    ("icmpv6", Protocol::Icmpv6),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    FrameLen,
+    EthSrc,
+    EthDst,
+    EthAddr,
+    EthType,
+    VlanId,
+    IpSrc,
+    IpDst,
+    IpAddr,
+    IpProto,
+    IpTtl,
+    IpLen,
+    IpId,
+    IpDsfield,
+    IpDscp,
+    IpFlagDf,
+    IpFlagMf,
+    /// 分片偏移 (字节)
+    IpFragOffset,
+    TcpSrcPort,
+    TcpDstPort,
+    TcpPort,
+    TcpFlags,
+    /// TCP标志中的一位 (值为0或1)
+    TcpFlag(u16),
+    /// TCP载荷长度
+    TcpLen,
+    TcpHdrLen,
+    TcpSeqRaw,
+    TcpAckRaw,
+    TcpWindow,
+    UdpSrcPort,
+    UdpDstPort,
+    UdpPort,
+    UdpLength,
+    /// ICMP类型，参数表示ICMPv6
+    IcmpType(bool),
+    IcmpCode(bool),
+}
+
+/// 字段名 (与Wireshark相同)；`ip.src`、`ip.dst`、`ip.addr`、`ip.proto`、`ip.ttl`、`ip.len`
+/// 同时适用于IPv4和IPv6 (TTL对应IPv6的跳数限制)，`ip.id`、`ip.flags.*` 等只适用于IPv4
+const FIELDS: [(&str, Field); 49] = [
+    ("frame.len", Field::FrameLen),
+    ("eth.src", Field::EthSrc),
+    ("eth.dst", Field::EthDst),
+    ("eth.addr", Field::EthAddr),
+    ("eth.type", Field::EthType),
+    ("vlan.id", Field::VlanId),
+    ("ip.src", Field::IpSrc),
+    ("ip.dst", Field::IpDst),
+    ("ip.addr", Field::IpAddr),
+    ("ip.proto", Field::IpProto),
+    ("ip.ttl", Field::IpTtl),
+    ("ip.len", Field::IpLen),
+    ("ip.id", Field::IpId),
+    ("ip.dsfield", Field::IpDsfield),
+    ("ip.dsfield.dscp", Field::IpDscp),
+    ("ip.flags.df", Field::IpFlagDf),
+    ("ip.flags.mf", Field::IpFlagMf),
+    ("ip.frag_offset", Field::IpFragOffset),
+    ("ipv6.src", Field::IpSrc),
+    ("ipv6.dst", Field::IpDst),
+    ("ipv6.addr", Field::IpAddr),
+    ("ipv6.nxt", Field::IpProto),
+    ("ipv6.hlim", Field::IpTtl),
+    ("tcp.srcport", Field::TcpSrcPort),
+    ("tcp.dstport", Field::TcpDstPort),
+    ("tcp.port", Field::TcpPort),
+    ("tcp.flags", Field::TcpFlags),
+    ("tcp.flags.fin", Field::TcpFlag(0x001)),
+    ("tcp.flags.syn", Field::TcpFlag(0x002)),
+    ("tcp.flags.reset", Field::TcpFlag(0x004)),
+    ("tcp.flags.push", Field::TcpFlag(0x008)),
+    ("tcp.flags.ack", Field::TcpFlag(0x010)),
+    ("tcp.flags.urg", Field::TcpFlag(0x020)),
+    ("tcp.flags.ece", Field::TcpFlag(0x040)),
+    ("tcp.flags.cwr", Field::TcpFlag(0x080)),
+    ("tcp.flags.ae", Field::TcpFlag(0x100)),
+    ("tcp.len", Field::TcpLen),
+    ("tcp.hdr_len", Field::TcpHdrLen),
+    ("tcp.seq_raw", Field::TcpSeqRaw),
+    ("tcp.ack_raw", Field::TcpAckRaw),
+    ("tcp.window_size_value", Field::TcpWindow),
+    ("udp.srcport", Field::UdpSrcPort),
+    ("udp.dstport", Field::UdpDstPort),
+    ("udp.port", Field::UdpPort),
+    ("udp.length", Field::UdpLength),
+    ("icmp.type", Field::IcmpType(false)),
+    ("icmp.code", Field::IcmpCode(false)),
+    ("icmpv6.type", Field::IcmpType(true)),
+    ("icmpv6.code", Field::IcmpCode(true)),
+];
+
+impl Field {
+    /// 是否为数值字段 (地址字段之外)
+    fn is_numeric(self) -> bool {
+        !matches!(
+            self,
+            Field::IpSrc | Field::IpDst | Field::IpAddr | Field::EthSrc | Field::EthDst | Field::EthAddr
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// 比较的常量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    Number(u64),
+    /// 地址和前缀长度
+    Network(IpAddr, u8),
+    Mac([u8; 6]),
+}
+
+/// 从包中取出的字段值
+#[derive(Debug, Clone, Copy)]
+enum FieldValue {
+    Number(u64),
+    Ip(IpAddr),
+    Mac([u8; 6]),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Op(CompareOp),
+    In,
+    BitAnd,
+    LBrace,
+    RBrace,
+    Comma,
+    Word(String),
+}
+
+impl Filter {
+    /// 解析过滤表达式
+    pub fn parse(text: &str) -> Result<Filter> {
+        let tokens = tokenize(text)?;
+        if tokens.is_empty() {
+            anyhow::bail!("过滤表达式为空");
+        }
+        let mut parser = Parser { tokens: &tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            anyhow::bail!("过滤表达式中有多余的内容: {:?}", token);
+        }
+        Ok(Filter { expr })
+    }
+
+    /// 判断已解析的数据包是否满足条件
+    pub fn matches(&self, data: &[u8], linktype: u32, parsed: &ParsedPacket) -> bool {
+        let packet = PacketView { data, linktype, parsed };
+        packet.eval(&self.expr)
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => anyhow::bail!("过滤表达式缺少右括号"),
+                }
+            }
+            Some(Token::Word(word)) => self.parse_primary(&word),
+            Some(token) => anyhow::bail!("过滤表达式中意外的符号: {:?}", token),
+            None => anyhow::bail!("过滤表达式不完整"),
+        }
+    }
+
+    fn parse_primary(&mut self, word: &str) -> Result<Expr> {
+        let lower = word.to_ascii_lowercase();
+        let field = FIELDS.iter().find(|(name, _)| *name == lower).map(|&(_, field)| field);
+
+        match self.peek().cloned() {
+            Some(Token::Op(op)) => {
+                let field = field.ok_or_else(|| anyhow!("未知的过滤字段: {}", word))?;
+                self.pos += 1;
+                let value = match self.next() {
+                    Some(Token::Word(value)) => parse_value(field, op, &value)?,
+                    _ => anyhow::bail!("过滤字段 {} 缺少比较值", word),
+                };
+                return Ok(Expr::Compare(field, op, value));
+            }
+            Some(Token::In) => {
+                let field = field.ok_or_else(|| anyhow!("未知的过滤字段: {}", word))?;
+                self.pos += 1;
+                return Ok(Expr::In(field, self.parse_set(field)?));
+            }
+            Some(Token::BitAnd) => {
+                let field = field.ok_or_else(|| anyhow!("未知的过滤字段: {}", word))?;
+                self.pos += 1;
+                if !field.is_numeric() {
+                    anyhow::bail!("过滤字段 {} 不是数值，不支持 &", word);
+                }
+                let mask = match self.next() {
+                    Some(Token::Word(mask)) => parse_number(&mask).ok_or_else(|| anyhow!("无效的掩码: {}", mask))?,
+                    _ => anyhow::bail!("过滤字段 {} 缺少掩码", word),
+                };
+                return Ok(Expr::BitAnd(field, mask));
+            }
+            _ => {}
+        }
+
+        if let Some(field) = field {
+            return Ok(Expr::Present(field));
+        }
+        match PROTOCOLS.iter().find(|(name, _)| *name == lower) {
+            Some(&(_, protocol)) => Ok(Expr::Protocol(protocol)),
+            None => anyhow::bail!("未知的协议或字段: {}", word),
+        }
+    }
+}
+
+impl Parser<'_> {
+    /// 集合: `{80 443 8000..8080}`，元素之间可以用空格或逗号分隔
+    fn parse_set(&mut self, field: Field) -> Result<Vec<SetItem>> {
+        if self.next() != Some(Token::LBrace) {
+            anyhow::bail!("in 之后应为 {{");
+        }
+        let mut items = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::RBrace) if !items.is_empty() => return Ok(items),
+                Some(Token::Comma) if !items.is_empty() => {}
+                Some(Token::Word(word)) => items.push(parse_set_item(field, &word)?),
+                Some(token) => anyhow::bail!("集合中意外的符号: {:?}", token),
+                None => anyhow::bail!("集合缺少 }}"),
+            }
+        }
+    }
+}
+
+fn parse_set_item(field: Field, text: &str) -> Result<SetItem> {
+    let Some((low, high)) = text.split_once("..") else {
+        return parse_value(field, CompareOp::Eq, text).map(SetItem::Value);
+    };
+    match (parse_value(field, CompareOp::Eq, low)?, parse_value(field, CompareOp::Eq, high)?) {
+        (Value::Number(low), Value::Number(high)) if low <= high => Ok(SetItem::Range(low, high)),
+        (Value::Number(_), Value::Number(_)) => anyhow::bail!("无效的范围: {}", text),
+        _ => anyhow::bail!("只有数值字段支持范围: {}", text),
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, width) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('{', _) => (Token::LBrace, 1),
+            ('}', _) => (Token::RBrace, 1),
+            (',', _) => (Token::Comma, 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Op(CompareOp::Eq), 2),
+            ('!', Some('=')) => (Token::Op(CompareOp::Ne), 2),
+            ('<', Some('=')) => (Token::Op(CompareOp::Le), 2),
+            ('>', Some('=')) => (Token::Op(CompareOp::Ge), 2),
+            ('<', _) => (Token::Op(CompareOp::Lt), 1),
+            ('>', _) => (Token::Op(CompareOp::Gt), 1),
+            ('!', _) => (Token::Not, 1),
+            ('&', _) => (Token::BitAnd, 1),
+            ('=' | '|', _) => anyhow::bail!("过滤表达式中无效的运算符: {}", c),
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"(){},=!<>&|".contains(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.to_ascii_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "eq" => Token::Op(CompareOp::Eq),
+                    "ne" => Token::Op(CompareOp::Ne),
+                    "lt" => Token::Op(CompareOp::Lt),
+                    "le" => Token::Op(CompareOp::Le),
+                    "gt" => Token::Op(CompareOp::Gt),
+                    "ge" => Token::Op(CompareOp::Ge),
+                    "in" => Token::In,
+                    _ => Token::Word(word),
+                });
+                continue;
+            }
+        };
+        tokens.push(token);
+        i += width;
+    }
+    Ok(tokens)
+}
+
+fn parse_value(field: Field, op: CompareOp, text: &str) -> Result<Value> {
+    let ordered = !matches!(op, CompareOp::Eq | CompareOp::Ne);
+    match field {
+        Field::IpSrc | Field::IpDst | Field::IpAddr => {
+            if ordered {
+                anyhow::bail!("地址字段只支持 == 和 !=");
+            }
+            let (addr, prefix) = text.split_once('/').unwrap_or((text, ""));
+            let addr: IpAddr = addr.parse().map_err(|_| anyhow!("无效的IP地址: {}", text))?;
+            let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+            let prefix = if prefix.is_empty() {
+                max_prefix
+            } else {
+                prefix.parse().ok().filter(|&p| p <= max_prefix).ok_or_else(|| anyhow!("无效的前缀长度: {}", text))?
+            };
+            Ok(Value::Network(addr, prefix))
+        }
+        Field::EthSrc | Field::EthDst | Field::EthAddr => {
+            if ordered {
+                anyhow::bail!("MAC地址字段只支持 == 和 !=");
+            }
+            parse_mac(text).map(Value::Mac).ok_or_else(|| anyhow!("无效的MAC地址: {}", text))
+        }
+        // 标志位可以写 True/False
+        Field::TcpFlag(_) | Field::IpFlagDf | Field::IpFlagMf if text.eq_ignore_ascii_case("true") => Ok(Value::Number(1)),
+        Field::TcpFlag(_) | Field::IpFlagDf | Field::IpFlagMf if text.eq_ignore_ascii_case("false") => Ok(Value::Number(0)),
+        _ => parse_number(text).map(Value::Number).ok_or_else(|| anyhow!("无效的数值: {}", text)),
+    }
+}
+
+/// 解析十进制或 `0x` 开头的十六进制数
+pub fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// 解析 `aa:bb:cc:dd:ee:ff` (或以 `-` 分隔) 格式的MAC地址
+pub fn parse_mac(text: &str) -> Option<[u8; 6]> {
+    let parts: Vec<&str> = text.split([':', '-']).collect();
+    if parts.len() != 6 {
+        return None;
+    }
+    let mut mac = [0u8; 6];
+    for (byte, part) in mac.iter_mut().zip(parts) {
+        if part.is_empty() || part.len() > 2 {
+            return None;
+        }
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+    Some(mac)
+}
+
+/// 求值时的数据包
+struct PacketView<'a> {
+    data: &'a [u8],
+    linktype: u32,
+    parsed: &'a ParsedPacket,
+}
+
+impl PacketView<'_> {
+    fn eval(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::And(a, b) => self.eval(a) && self.eval(b),
+            Expr::Or(a, b) => self.eval(a) || self.eval(b),
+            Expr::Not(a) => !self.eval(a),
+            Expr::Protocol(protocol) => self.has_protocol(*protocol),
+            Expr::Present(field) => !self.field_values(*field).is_empty(),
+            Expr::Compare(field, op, value) => {
+                let values = self.field_values(*field);
+                match op {
+                    CompareOp::Ne => !values.iter().any(|v| compare(v, CompareOp::Eq, value)),
+                    _ => values.iter().any(|v| compare(v, *op, value)),
+                }
+            }
+            Expr::In(field, items) => self.field_values(*field).iter().any(|v| {
+                items.iter().any(|item| match (item, v) {
+                    (SetItem::Value(value), _) => compare(v, CompareOp::Eq, value),
+                    (SetItem::Range(low, high), FieldValue::Number(n)) => (low..=high).contains(&n),
+                    _ => false,
+                })
+            }),
+            Expr::BitAnd(field, mask) => self
+                .field_values(*field)
+                .iter()
+                .any(|v| matches!(v, FieldValue::Number(n) if n & mask != 0)),
+        }
+    }
+
+    fn has_protocol(&self, protocol: Protocol) -> bool {
+        let ip_version = self.parsed.ip.as_ref().map(|ip| ip.version);
+        match protocol {
+            Protocol::Eth => self.linktype == LINKTYPE_ETHERNET && self.data.len() >= 14,
+            Protocol::Vlan => self.parsed.vlan_offset.is_some(),
+            Protocol::Ip => ip_version == Some(4),
+            Protocol::Ipv6 => ip_version == Some(6),
+            Protocol::Tcp => matches!(self.parsed.transport, Some(Transport::Tcp(_))),
+            Protocol::Udp => matches!(self.parsed.transport, Some(Transport::Udp(_))),
+            Protocol::Icmp | Protocol::Icmpv6 => {
+                let expected = if protocol == Protocol::Icmp { IP_PROTO_ICMP } else { IP_PROTO_ICMPV6 };
+                matches!(self.parsed.transport, Some(Transport::Icmp { .. }))
+                    && self.parsed.ip.as_ref().is_some_and(|ip| ip.protocol == expected)
+            }
+        }
+    }
+
+    fn field_values(&self, field: Field) -> Vec<FieldValue> {
+        let data = self.data;
+        let ip = self.parsed.ip.as_ref();
+        let ports = |want_tcp: bool| match self.parsed.transport {
+            Some(Transport::Tcp(tcp)) if want_tcp => Some((tcp.src_port, tcp.dst_port)),
+            Some(Transport::Udp(udp)) if !want_tcp => Some((udp.src_port, udp.dst_port)),
+            _ => None,
+        };
+        let mac = |offset: usize| -> Vec<FieldValue> {
+            if self.has_protocol(Protocol::Eth) {
+                vec![FieldValue::Mac(data[offset..offset + 6].try_into().unwrap())]
+            } else {
+                Vec::new()
+            }
+        };
+        let number = |value: Option<u64>| value.map(FieldValue::Number).into_iter().collect();
+        let ipv4 = ip.filter(|ip| ip.version == 4).map(|ip| ip.offset);
+        let tcp = match self.parsed.transport {
+            Some(Transport::Tcp(tcp)) => Some(tcp),
+            _ => None,
+        };
+        let icmp = |v6: bool| match self.parsed.transport {
+            Some(Transport::Icmp { offset }) if self.has_protocol(if v6 { Protocol::Icmpv6 } else { Protocol::Icmp }) => {
+                Some(offset)
+            }
+            _ => None,
+        };
+
+        match field {
+            Field::FrameLen => vec![FieldValue::Number(data.len() as u64)],
+            Field::EthDst => mac(0),
+            Field::EthSrc => mac(6),
+            Field::EthAddr => [mac(6), mac(0)].concat(),
+            Field::EthType => number(self.parsed.ethertype.map(u64::from)),
+            Field::VlanId => number(self.parsed.vlan_offset.map(|offset| (read_u16(data, offset) & 0x0fff) as u64)),
+            Field::IpSrc => ip.map(|ip| FieldValue::Ip(ip.src)).into_iter().collect(),
+            Field::IpDst => ip.map(|ip| FieldValue::Ip(ip.dst)).into_iter().collect(),
+            Field::IpAddr => ip.map(|ip| vec![FieldValue::Ip(ip.src), FieldValue::Ip(ip.dst)]).unwrap_or_default(),
+            Field::IpProto => number(ip.map(|ip| ip.protocol as u64)),
+            Field::IpTtl => number(ip.map(|ip| {
+                let offset = if ip.version == 4 { ip.offset + 8 } else { ip.offset + 7 };
+                data[offset] as u64
+            })),
+            Field::IpLen => number(ip.map(|ip| ip.total_len as u64)),
+            Field::IpId => number(ipv4.map(|offset| read_u16(data, offset + 4) as u64)),
+            Field::IpDsfield => number(ipv4.map(|offset| data[offset + 1] as u64)),
+            Field::IpDscp => number(ipv4.map(|offset| (data[offset + 1] >> 2) as u64)),
+            Field::IpFlagDf => number(ipv4.map(|offset| ((data[offset + 6] >> 6) & 1) as u64)),
+            Field::IpFlagMf => number(ipv4.map(|offset| ((data[offset + 6] >> 5) & 1) as u64)),
+            Field::IpFragOffset => number(ipv4.map(|offset| (read_u16(data, offset + 6) & 0x1fff) as u64 * 8)),
+            Field::TcpSrcPort => number(ports(true).map(|(src, _)| src as u64)),
+            Field::TcpDstPort => number(ports(true).map(|(_, dst)| dst as u64)),
+            Field::TcpPort => ports(true)
+                .map(|(src, dst)| vec![FieldValue::Number(src as u64), FieldValue::Number(dst as u64)])
+                .unwrap_or_default(),
+            Field::TcpFlags => number(tcp.map(|tcp| (read_u16(data, tcp.offset + 12) & 0x0fff) as u64)),
+            Field::TcpFlag(bit) => number(tcp.map(|tcp| (read_u16(data, tcp.offset + 12) & bit != 0) as u64)),
+            Field::TcpLen => number(tcp.zip(ip).map(|(tcp, ip)| {
+                ip.total_len.saturating_sub(ip.header_len).saturating_sub(tcp.header_len) as u64
+            })),
+            Field::TcpHdrLen => number(tcp.map(|tcp| tcp.header_len as u64)),
+            Field::TcpSeqRaw => number(tcp.map(|tcp| read_u32(data, tcp.offset + 4) as u64)),
+            Field::TcpAckRaw => number(tcp.map(|tcp| read_u32(data, tcp.offset + 8) as u64)),
+            Field::TcpWindow => number(tcp.map(|tcp| read_u16(data, tcp.offset + 14) as u64)),
+            Field::UdpSrcPort => number(ports(false).map(|(src, _)| src as u64)),
+            Field::UdpDstPort => number(ports(false).map(|(_, dst)| dst as u64)),
+            Field::UdpPort => ports(false)
+                .map(|(src, dst)| vec![FieldValue::Number(src as u64), FieldValue::Number(dst as u64)])
+                .unwrap_or_default(),
+            Field::UdpLength => number(match self.parsed.transport {
+                Some(Transport::Udp(udp)) => Some(read_u16(data, udp.offset + 4) as u64),
+                _ => None,
+            }),
+            Field::IcmpType(v6) => number(icmp(v6).map(|offset| data[offset] as u64)),
+            Field::IcmpCode(v6) => number(icmp(v6).map(|offset| data[offset + 1] as u64)),
+        }
+    }
+}
+
+fn compare(actual: &FieldValue, op: CompareOp, expected: &Value) -> bool {
+    match (actual, expected) {
+        (FieldValue::Number(a), Value::Number(b)) => match op {
+            CompareOp::Eq => a == b,
+            CompareOp::Ne => a != b,
+            CompareOp::Lt => a < b,
+            CompareOp::Le => a <= b,
+            CompareOp::Gt => a > b,
+            CompareOp::Ge => a >= b,
+        },
+        (FieldValue::Ip(addr), Value::Network(network, prefix)) => in_network(*addr, *network, *prefix) == (op == CompareOp::Eq),
+        (FieldValue::Mac(a), Value::Mac(b)) => (a == b) == (op == CompareOp::Eq),
+        _ => false,
+    }
+}
+
+/// 地址是否属于网段 (地址族不同时为假)
+pub fn in_network(addr: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (addr, network) {
+        (IpAddr::V4(a), IpAddr::V4(n)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(a) & mask == u32::from(n) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(n)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(a) & mask == u128::from(n) & mask
+        }
+        _ => false,
+    }
+}
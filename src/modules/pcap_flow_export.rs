@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use log::info;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_flow_id::FlowIds;
+use crate::modules::pcap_packet_parser::{FlowKey, IP_PROTO_TCP, Transport, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{TimeFormat, format_timestamp_us, time_format};
+use crate::modules::pcap_zeek_conn::pcap_export_conn_log;
+
+/// IPFIX协议版本号
+const IPFIX_VERSION: u16 = 10;
+/// IPFIX报文的最大长度
+const IPFIX_MAX_MESSAGE_LEN: usize = 65535;
+const IPFIX_HEADER_LEN: usize = 16;
+/// 模板集的集合ID
+const IPFIX_TEMPLATE_SET_ID: u16 = 2;
+const TEMPLATE_ID_IPV4: u16 = 256;
+const TEMPLATE_ID_IPV6: u16 = 257;
+
+/// IPFIX信息元素 (RFC 7012): (ID, 长度)
+const IE_OCTET_DELTA_COUNT: (u16, u16) = (1, 8);
+const IE_PACKET_DELTA_COUNT: (u16, u16) = (2, 8);
+const IE_PROTOCOL_IDENTIFIER: (u16, u16) = (4, 1);
+const IE_IP_CLASS_OF_SERVICE: (u16, u16) = (5, 1);
+const IE_TCP_CONTROL_BITS: (u16, u16) = (6, 2);
+const IE_SOURCE_TRANSPORT_PORT: (u16, u16) = (7, 2);
+const IE_SOURCE_IPV4_ADDRESS: (u16, u16) = (8, 4);
+const IE_DESTINATION_TRANSPORT_PORT: (u16, u16) = (11, 2);
+const IE_DESTINATION_IPV4_ADDRESS: (u16, u16) = (12, 4);
+const IE_SOURCE_IPV6_ADDRESS: (u16, u16) = (27, 16);
+const IE_DESTINATION_IPV6_ADDRESS: (u16, u16) = (28, 16);
+const IE_FLOW_START_MILLISECONDS: (u16, u16) = (152, 8);
+const IE_FLOW_END_MILLISECONDS: (u16, u16) = (153, 8);
+
+/// NetFlow/IPFIX的默认空闲超时 (毫秒)
+const DEFAULT_IDLE_TIMEOUT_MS: u64 = 15_000;
+
+/// 流记录的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum FlowFormat {
+    /// IPFIX文件 (RFC 5655)，可由nfcapd/ipfixcol等工具读取
+    #[default]
+    Ipfix,
+    /// NetFlow v5记录字段的CSV
+    Csv,
+    /// Zeek conn.log格式的TSV (双向连接)
+    ZeekConn,
+}
+
+/// 流记录导出的选项
+#[derive(Debug, Clone, Copy)]
+pub struct FlowExportOptions {
+    pub format: FlowFormat,
+    /// 流持续超过该时间 (毫秒) 时结束当前记录，之后的包开始新记录
+    pub active_timeout_ms: u64,
+    /// 流空闲超过该时间 (毫秒) 后的包开始新记录，None时使用格式的默认值
+    pub idle_timeout_ms: Option<u64>,
+}
+
+/// 单向流记录 (与NetFlow/IPFIX探针相同，两个方向各为一条记录)
+#[derive(Debug, Clone)]
+struct FlowRecord {
+    key: FlowKey,
+    /// 所属流的ID (两个方向和同一五元组的多条记录相同)
+    flow_id: String,
+    tos: u8,
+    tcp_flags: u16,
+    packets: u64,
+    /// IP层字节数 (IP头中的总长度)
+    octets: u64,
+    first_ms: u64,
+    last_ms: u64,
+}
+
+/// 把抓包中的流转换为NetFlow/IPFIX流记录
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径
+/// - `options`: 输出格式和流超时
+///
+/// # 功能
+/// 1. 按五元组分为单向流，统计包数、IP层字节数、首末包时间、TCP标志的并集和首包的ToS
+/// 2. 流超过活动超时或空闲超时、TCP流出现FIN或RST时结束记录，之后的包开始新记录
+/// 3. 记录按流的开始时间排序输出；非IP包不计入
+///
+/// Zeek conn.log格式按双向连接输出，见 [`pcap_export_conn_log`]，不使用活动超时。
+pub fn pcap_export_flows(input_path: &str, output_path: &str, options: &FlowExportOptions) -> Result<()> {
+    if options.format == FlowFormat::ZeekConn {
+        return pcap_export_conn_log(input_path, output_path, options.idle_timeout_ms);
+    }
+    let idle_timeout_ms = options.idle_timeout_ms.unwrap_or(DEFAULT_IDLE_TIMEOUT_MS);
+    if options.active_timeout_ms == 0 || idle_timeout_ms == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("流超时必须大于0".to_string()));
+    }
+
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut active: HashMap<FlowKey, FlowRecord> = HashMap::new();
+    let mut records = Vec::new();
+    let mut flow_ids = FlowIds::default();
+    let mut packet_count = 0u64;
+    let mut skipped = 0u64;
+    // 第一个包的时间 (相对时间格式的起点)
+    let mut start_ms = None;
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_ns = interface.timestamp_ns(packet.timestamp);
+        let time_ms = (time_ns / 1_000_000).max(0) as u64;
+        start_ms.get_or_insert(time_ms);
+        let parsed = parse_packet(&packet.data, interface.linktype as u32);
+        let (Some(mut key), Some(ip)) = (parsed.flow_key(), parsed.ip.as_ref()) else {
+            skipped += 1;
+            continue;
+        };
+        let flow_id = flow_ids.assign(&key, time_ns.div_euclid(1000) as i64);
+        // 与NetFlow探针相同，ICMP的类型和代码记录在目的端口中
+        if let Some(Transport::Icmp { offset }) = parsed.transport
+            && offset + 2 <= packet.data.len()
+        {
+            key.dst_port = u16::from_be_bytes([packet.data[offset], packet.data[offset + 1]]);
+        }
+        let tos = if ip.version == 4 {
+            packet.data[ip.offset + 1]
+        } else {
+            ((u16::from_be_bytes([packet.data[ip.offset], packet.data[ip.offset + 1]]) >> 4) & 0xff) as u8
+        };
+        let tcp_flags = match parsed.transport {
+            Some(Transport::Tcp(tcp)) if tcp.offset + 14 <= packet.data.len() => {
+                u16::from_be_bytes([packet.data[tcp.offset + 12], packet.data[tcp.offset + 13]]) & 0x0fff
+            }
+            _ => 0,
+        };
+
+        if let Some(record) = active.get(&key)
+            && (time_ms.saturating_sub(record.last_ms) > idle_timeout_ms
+                || time_ms.saturating_sub(record.first_ms) > options.active_timeout_ms)
+        {
+            records.extend(active.remove(&key));
+        }
+        let record = active.entry(key).or_insert_with(|| FlowRecord {
+            key,
+            flow_id: flow_id.to_string(),
+            tos,
+            tcp_flags: 0,
+            packets: 0,
+            octets: 0,
+            first_ms: time_ms,
+            last_ms: time_ms,
+        });
+        record.packets += 1;
+        record.octets += ip.total_len as u64;
+        record.tcp_flags |= tcp_flags;
+        record.first_ms = record.first_ms.min(time_ms);
+        record.last_ms = record.last_ms.max(time_ms);
+        // FIN或RST结束TCP流
+        if key.protocol == IP_PROTO_TCP && tcp_flags & 0x05 != 0 {
+            records.extend(active.remove(&key));
+        }
+    }
+    records.extend(active.into_values());
+    records.sort_by_key(|record| (record.first_ms, record.last_ms, record.key));
+
+    let out_file = File::create(Path::new(output_path))
+        .map_err(|e| PcapError::create_failed(output_path, e))?;
+    let mut writer = BufWriter::new(out_file);
+    if options.format == FlowFormat::Ipfix {
+        write_ipfix(&mut writer, &records)?;
+    } else {
+        write_csv(&mut writer, &records, start_ms.unwrap_or_default())?;
+    }
+    writer.flush()
+        .map_err(|e| PcapError::WriteFailure(format!("写入流记录失败: {}: {}", output_path, e)))?;
+
+    info!(
+        "成功导出流记录: 数据包数={}, 非IP包数={}, 流记录数={}, 输出文件={}",
+        packet_count,
+        skipped,
+        records.len(),
+        output_path
+    );
+    Ok(())
+}
+
+/// NetFlow v5记录字段顺序的CSV
+///
+/// 抓包中没有的路由信息 (下一跳、接口索引、AS号、掩码) 为0，`first`/`last` 为Unix时间 (毫秒)；
+/// 指定了 `--time-format iso|relative` 时按该格式输出，`start_ms` 为相对格式的起点。
+/// 最后一列 `flow_id` 为流ID (见 [`crate::modules::pcap_flow_id::flow_id`])。
+fn write_csv(writer: &mut impl Write, records: &[FlowRecord], start_ms: u64) -> Result<()> {
+    let format_time = |time_ms: u64| match time_format() {
+        TimeFormat::Epoch => time_ms.to_string(),
+        _ => format_timestamp_us(time_ms as i64 * 1000, start_ms as i64 * 1000),
+    };
+    writeln!(
+        writer,
+        "srcaddr,dstaddr,nexthop,input,output,dpkts,doctets,first,last,srcport,dstport,tcp_flags,prot,tos,src_as,dst_as,src_mask,dst_mask,flow_id"
+    )?;
+    for record in records {
+        let nexthop = if record.key.src.is_ipv4() { "0.0.0.0" } else { "::" };
+        writeln!(
+            writer,
+            "{},{},{},0,0,{},{},{},{},{},{},{},{},{},0,0,0,0,{}",
+            record.key.src,
+            record.key.dst,
+            nexthop,
+            record.packets,
+            record.octets,
+            format_time(record.first_ms),
+            format_time(record.last_ms),
+            record.key.src_port,
+            record.key.dst_port,
+            // NetFlow v5的TCP标志只有8位
+            record.tcp_flags & 0xff,
+            record.key.protocol,
+            record.tos,
+            record.flow_id
+        )?;
+    }
+    Ok(())
+}
+
+/// 模板中的字段
+fn template_fields(template_id: u16) -> [(u16, u16); 11] {
+    let (src, dst) = if template_id == TEMPLATE_ID_IPV4 {
+        (IE_SOURCE_IPV4_ADDRESS, IE_DESTINATION_IPV4_ADDRESS)
+    } else {
+        (IE_SOURCE_IPV6_ADDRESS, IE_DESTINATION_IPV6_ADDRESS)
+    };
+    [
+        src,
+        dst,
+        IE_SOURCE_TRANSPORT_PORT,
+        IE_DESTINATION_TRANSPORT_PORT,
+        IE_PROTOCOL_IDENTIFIER,
+        IE_IP_CLASS_OF_SERVICE,
+        IE_TCP_CONTROL_BITS,
+        IE_PACKET_DELTA_COUNT,
+        IE_OCTET_DELTA_COUNT,
+        IE_FLOW_START_MILLISECONDS,
+        IE_FLOW_END_MILLISECONDS,
+    ]
+}
+
+/// 按模板字段顺序编码一条数据记录
+fn encode_record(record: &FlowRecord) -> Vec<u8> {
+    let mut data = Vec::with_capacity(72);
+    for addr in [record.key.src, record.key.dst] {
+        match addr {
+            IpAddr::V4(v4) => data.extend_from_slice(&v4.octets()),
+            IpAddr::V6(v6) => data.extend_from_slice(&v6.octets()),
+        }
+    }
+    data.extend_from_slice(&record.key.src_port.to_be_bytes());
+    data.extend_from_slice(&record.key.dst_port.to_be_bytes());
+    data.push(record.key.protocol);
+    data.push(record.tos);
+    data.extend_from_slice(&record.tcp_flags.to_be_bytes());
+    data.extend_from_slice(&record.packets.to_be_bytes());
+    data.extend_from_slice(&record.octets.to_be_bytes());
+    data.extend_from_slice(&record.first_ms.to_be_bytes());
+    data.extend_from_slice(&record.last_ms.to_be_bytes());
+    data
+}
+
+/// IPFIX文件 (RFC 5655): 第一个报文包含IPv4和IPv6模板，之后的报文只包含数据集
+///
+/// 报文长度不超过65535字节，序号为之前报文中的数据记录总数。
+fn write_ipfix(writer: &mut impl Write, records: &[FlowRecord]) -> Result<()> {
+    let export_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as u32;
+
+    let mut templates = Vec::new();
+    for template_id in [TEMPLATE_ID_IPV4, TEMPLATE_ID_IPV6] {
+        let fields = template_fields(template_id);
+        templates.extend_from_slice(&template_id.to_be_bytes());
+        templates.extend_from_slice(&(fields.len() as u16).to_be_bytes());
+        for (id, len) in fields {
+            templates.extend_from_slice(&id.to_be_bytes());
+            templates.extend_from_slice(&len.to_be_bytes());
+        }
+    }
+    let mut message = Vec::new();
+    push_set(&mut message, IPFIX_TEMPLATE_SET_ID, &templates);
+
+    let mut sequence = 0u32;
+    let mut in_message = 0u32;
+    let mut index = 0;
+    while index < records.len() {
+        // 连续的同一地址族的记录放在一个数据集中
+        let template_id = if records[index].key.src.is_ipv4() { TEMPLATE_ID_IPV4 } else { TEMPLATE_ID_IPV6 };
+        let mut set = Vec::new();
+        while index < records.len()
+            && records[index].key.src.is_ipv4() == (template_id == TEMPLATE_ID_IPV4)
+        {
+            let record = encode_record(&records[index]);
+            if IPFIX_HEADER_LEN + message.len() + 4 + set.len() + record.len() > IPFIX_MAX_MESSAGE_LEN {
+                break;
+            }
+            set.extend_from_slice(&record);
+            in_message += 1;
+            index += 1;
+        }
+        if set.is_empty() {
+            // 当前报文已满
+            write_message(writer, &message, export_time, sequence)?;
+            sequence = sequence.wrapping_add(in_message);
+            in_message = 0;
+            message.clear();
+            continue;
+        }
+        push_set(&mut message, template_id, &set);
+    }
+    if !message.is_empty() {
+        write_message(writer, &message, export_time, sequence)?;
+    }
+    Ok(())
+}
+
+fn push_set(message: &mut Vec<u8>, set_id: u16, body: &[u8]) {
+    message.extend_from_slice(&set_id.to_be_bytes());
+    message.extend_from_slice(&((body.len() + 4) as u16).to_be_bytes());
+    message.extend_from_slice(body);
+}
+
+fn write_message(writer: &mut impl Write, sets: &[u8], export_time: u32, sequence: u32) -> Result<()> {
+    let mut header = Vec::with_capacity(IPFIX_HEADER_LEN);
+    header.extend_from_slice(&IPFIX_VERSION.to_be_bytes());
+    header.extend_from_slice(&((IPFIX_HEADER_LEN + sets.len()) as u16).to_be_bytes());
+    header.extend_from_slice(&export_time.to_be_bytes());
+    header.extend_from_slice(&sequence.to_be_bytes());
+    // 观察域ID
+    header.extend_from_slice(&0u32.to_be_bytes());
+    writer.write_all(&header)?;
+    writer.write_all(sets)?;
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::io::BufWriter;
+use std::net::IpAddr;
+use anyhow::Result;
+use log::info;
+use seahash::hash_seeded;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{FlowKey, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, OPT_COMMENT, PcapngBlock, PcapngOption, PcapngWriter, SHB_USERAPPL};
+use crate::modules::pcap_report::{Table, Verbosity, format_timestamp_us, print_fields, print_heading, print_section, verbosity};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 默认列出的流数
+const MAX_ROWS: usize = 20;
+
+/// 流ID: 规范化的五元组 (与方向无关) 和流中第一个包的时间 (微秒) 的哈希，16个十六进制字符
+///
+/// 同一个抓包无论由哪个命令处理，同一个流的ID都相同，可以用来关联不同的报告。
+pub fn flow_id(key: &FlowKey, first_seen_us: i64) -> String {
+    let key = key.canonical();
+    let mut bytes = Vec::with_capacity(48);
+    bytes.push(key.protocol);
+    for addr in [key.src, key.dst] {
+        match addr {
+            IpAddr::V4(v4) => bytes.extend_from_slice(&v4.to_ipv6_mapped().octets()),
+            IpAddr::V6(v6) => bytes.extend_from_slice(&v6.octets()),
+        }
+    }
+    bytes.extend_from_slice(&key.src_port.to_be_bytes());
+    bytes.extend_from_slice(&key.dst_port.to_be_bytes());
+    bytes.extend_from_slice(&first_seen_us.to_be_bytes());
+    format!("{:016x}", hash_seeded(&bytes, 0x666c6f77, 0x2d6964, 0x70636170, 0x6e67))
+}
+
+/// 按抓包顺序为每个流分配ID
+///
+/// 流按规范化的五元组划分 (两个方向、超时或端口复用后的包都属于同一个流)，
+/// 首次出现时间为抓包中该五元组的第一个包的时间。
+#[derive(Default)]
+pub struct FlowIds {
+    ids: HashMap<FlowKey, String>,
+}
+
+impl FlowIds {
+    /// 包所属的流的ID (`time_us` 为包的时间，流的第一个包决定ID)
+    pub fn assign(&mut self, key: &FlowKey, time_us: i64) -> &str {
+        let canonical = key.canonical();
+        self.ids.entry(canonical).or_insert_with(|| flow_id(&canonical, time_us))
+    }
+}
+
+/// 一个流的统计
+struct FlowSummary {
+    key: FlowKey,
+    id: String,
+    first_us: i64,
+    last_us: i64,
+    packets: u64,
+    bytes: u64,
+}
+
+/// 列出抓包中每个流的ID，并可把流ID作为注释写入每个包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出pcapng文件路径，为每个IP包添加一条注释 `flow_id=流ID`
+///
+/// # 功能
+/// 1. 流ID见 [`flow_id`]，与 `export-flows`、`export-payloads` 输出中的流ID相同
+/// 2. 按首次出现的顺序列出流ID、五元组、首末包时间、包数和字节数
+/// 3. pcapng输入的块和已有的注释原样保留，PCAP输入转换为单接口的pcapng
+pub fn pcap_flow_ids(input_path: &str, output_path: Option<&str>) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut writer = match output_path {
+        Some(output_path) => {
+            let mut options = match reader.next_block()? {
+                Some(PcapngBlock::Section(options)) => options,
+                _ => anyhow::bail!(PcapError::InvalidFormat("不是有效的pcapng文件: 缺少节头块".to_string())),
+            };
+            if options.is_empty() {
+                options.push(PcapngOption::string(SHB_USERAPPL, "pcap-editor flow-ids"));
+            }
+            let out_file = BufWriter::new(create_output(output_path, compression())?);
+            Some(PcapngWriter::new(out_file, &options)?)
+        }
+        None => None,
+    };
+
+    let mut flow_ids = FlowIds::default();
+    let mut flows: Vec<FlowSummary> = Vec::new();
+    let mut indexes: HashMap<FlowKey, usize> = HashMap::new();
+    let mut digest = OutputDigest::default();
+    let mut packet_count = 0u64;
+    let mut tagged = 0u64;
+    let mut start_us = None;
+    while let Some(block) = reader.next_block()? {
+        let mut packet = match block {
+            PcapngBlock::Packet(packet) => packet,
+            block => {
+                if let Some(writer) = writer.as_mut() {
+                    writer.write_block(&block)?;
+                }
+                continue;
+            }
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = interface.timestamp_ns(packet.timestamp).div_euclid(1000) as i64;
+        start_us.get_or_insert(time_us);
+        if let Some(key) = parse_packet(&packet.data, interface.linktype as u32).flow_key() {
+            let id = flow_ids.assign(&key, time_us).to_string();
+            let next_index = flows.len();
+            let index = *indexes.entry(key.canonical()).or_insert(next_index);
+            if index == flows.len() {
+                flows.push(FlowSummary { key, id: id.clone(), first_us: time_us, last_us: time_us, packets: 0, bytes: 0 });
+            }
+            let flow = &mut flows[index];
+            flow.last_us = flow.last_us.max(time_us);
+            flow.packets += 1;
+            flow.bytes += packet.orig_len as u64;
+            if writer.is_some() {
+                packet.options.push(PcapngOption::string(OPT_COMMENT, &format!("flow_id={}", id)));
+                tagged += 1;
+            }
+        }
+        if let Some(writer) = writer.as_mut() {
+            writer.write_packet(&packet)?;
+            digest.record_pcapng(&packet, Some(interface));
+        }
+    }
+    if let (Some(writer), Some(output_path)) = (writer, output_path) {
+        writer.into_inner().into_inner()
+            .map_err(|e| PcapError::WriteFailure(format!("写入pcapng文件失败: {}", e.error())))?
+            .finish()?;
+        digest.verify(output_path)?;
+        info!("成功写入流ID注释: 注释的包数={}, 输出文件={}", tagged, output_path);
+    }
+
+    print_heading(&format!("流ID: {}", input_path));
+    print_fields(&[
+        ("数据包数", packet_count.to_string(), None),
+        ("流数", flows.len().to_string(), None),
+    ]);
+    if flows.is_empty() {
+        return Ok(());
+    }
+    print_section("流:");
+    let start_us = start_us.unwrap_or_default();
+    let mut table = Table::new(&["流ID", "五元组", "首包时间", "末包时间", "包数", "字节数"]).right_align(&[4, 5]);
+    let shown = if verbosity() == Verbosity::Verbose { flows.len() } else { flows.len().min(MAX_ROWS) };
+    for flow in &flows[..shown] {
+        table.add_row(vec![
+            flow.id.clone(),
+            flow.key.to_string(),
+            format_timestamp_us(flow.first_us, start_us),
+            format_timestamp_us(flow.last_us, start_us),
+            flow.packets.to_string(),
+            flow.bytes.to_string(),
+        ]);
+    }
+    table.print();
+    if shown < flows.len() {
+        println!("  ... 还有 {} 个流 (使用 --verbose 查看全部)", flows.len() - shown);
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{FlowKey, Transport, parse_packet};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// TCP标志: FIN、SYN、RST
+const TCP_CONTROL_FLAGS: u8 = 0x07;
+
+/// 按流裁剪的限制 (至少指定一个)
+#[derive(Debug, Clone, Copy)]
+pub struct TrimLimits {
+    /// 每个流每个方向保留的载荷字节数
+    pub max_bytes: Option<u64>,
+    /// 每个会话 (两个方向合计) 保留的包数
+    pub max_packets: Option<u64>,
+}
+
+/// 只保留每个流前N字节的载荷 (按流的snaplen) 或前N个包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `limits`: 字节数和包数限制
+///
+/// # 说明
+/// 1. 字节数按五元组区分方向，两个方向各自计数，服务端响应的开头同样保留；
+///    包数按会话计数 (两个方向合计)，只保留连接开头的N个包
+/// 2. 载荷不超过剩余额度的包原样输出；跨过额度的包截断到额度为止 (与snaplen相同，
+///    原始长度和IP头中的长度不变)
+/// 3. 字节额度用完后带载荷的包丢弃，只保留带SYN/FIN/RST的TCP包 (截掉载荷)，连接的建立和结束仍然可见
+/// 4. 额度用完前没有载荷的包 (如纯ACK) 原样输出；非IP包和没有五元组的包原样输出
+/// 5. 同时指定时先按包数限制，因字节额度丢弃的包不计入包数
+pub fn pcap_trim_flows(input_path: &str, output_path: &str, limits: TrimLimits) -> Result<()> {
+    match limits {
+        TrimLimits { max_bytes: None, max_packets: None } => {
+            anyhow::bail!(PcapError::InvalidArgument("需要指定 --bytes 或 --packets".to_string()));
+        }
+        TrimLimits { max_bytes: Some(0), .. } => {
+            anyhow::bail!(PcapError::InvalidArgument("--bytes 必须大于0".to_string()));
+        }
+        TrimLimits { max_packets: Some(0), .. } => {
+            anyhow::bail!(PcapError::InvalidArgument("--packets 必须大于0".to_string()));
+        }
+        _ => {}
+    }
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    // 流 (区分方向) -> 已输出的载荷字节数
+    let mut flows: HashMap<FlowKey, u64> = HashMap::new();
+    // 会话 (与方向无关的五元组) -> 已输出的包数
+    let mut conversations: HashMap<FlowKey, u64> = HashMap::new();
+    let mut packet_count = 0;
+    let mut written = 0;
+    let mut truncated = 0;
+    let mut dropped = 0;
+    let mut input_bytes = 0u64;
+    let mut output_bytes = 0u64;
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        packet_count += 1;
+        input_bytes += packet.data.len() as u64;
+        let parsed = parse_packet(&packet.data, linktype);
+        if let (Some(key), Some(_)) = (parsed.flow_key(), &parsed.ip) {
+            let packets = conversations.entry(key.canonical()).or_default();
+            if limits.max_packets.is_some_and(|max_packets| *packets >= max_packets) {
+                dropped += 1;
+                continue;
+            }
+            if let Some(max_bytes) = limits.max_bytes {
+                let payload = parsed.payload_range(packet.data.len());
+                let used = flows.entry(key).or_default();
+                let remaining = max_bytes - *used;
+                let payload_len = payload.len() as u64;
+                if payload_len > remaining {
+                    let control = match parsed.transport {
+                        Some(Transport::Tcp(tcp)) => packet.data.get(tcp.offset + 13).is_some_and(|flags| flags & TCP_CONTROL_FLAGS != 0),
+                        _ => false,
+                    };
+                    if remaining == 0 && !control {
+                        dropped += 1;
+                        continue;
+                    }
+                    let len = payload.start + remaining as usize;
+                    packet.data.to_mut().truncate(len);
+                    packet.header.incl_len = len as u32;
+                    truncated += 1;
+                    *used = max_bytes;
+                } else {
+                    *used += payload_len;
+                }
+            }
+            *packets += 1;
+        }
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&packet);
+        written += 1;
+        output_bytes += packet.data.len() as u64;
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功裁剪流: 会话数={}, 输入包数={}, 输出包数={}, 截断包数={}, 丢弃包数={}, 包数据 {} -> {} 字节, 输出文件={}",
+        conversations.len(), packet_count, written, truncated, dropped, input_bytes, output_bytes, output_path
+    );
+
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::collections::{BTreeSet, HashMap};
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{
+    FlowKey, IP_PROTO_TCP, IP_PROTO_UDP, Transport, ip_checksum_valid, l4_checksum_valid, parse_packet, read_u32,
+};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{Severity, Table, print_fields, print_heading, print_section, print_separator, print_verdict};
+
+/// 每种问题最多记录的示例
+const MAX_EXAMPLES: usize = 5;
+
+/// 序列号或确认号与同方向已发送的数据相差超过这个值时，视为不合理的跳变
+const MAX_SEQ_JUMP: i64 = 64 << 20;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+/// 一类不一致的统计
+#[derive(Default)]
+struct Issue {
+    count: u64,
+    /// 示例 (包序号或五元组)
+    examples: Vec<String>,
+}
+
+impl Issue {
+    fn record(&mut self, example: String) {
+        self.count += 1;
+        if self.examples.len() < MAX_EXAMPLES {
+            self.examples.push(example);
+        }
+    }
+}
+
+/// TCP连接一个方向的状态
+#[derive(Default)]
+struct TcpDirection {
+    /// SYN的初始序列号
+    isn: Option<u32>,
+    /// 已发送数据的结束序列号 (按序列号空间中最靠后的包)
+    seq_end: Option<u32>,
+}
+
+/// 检查抓包 (特别是经过改写、匿名化、NAT等操作的抓包) 是否仍然自洽
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+///
+/// # 检查内容
+/// 1. IPv4头校验和、TCP/UDP校验和是否正确，IP长度字段是否超出 (未截断的) 帧
+/// 2. TCP序列号: 同一方向的序列号相对已发送的数据跳变超过64MB，确认号超前对方已发送的数据超过64MB，
+///    SYN-ACK的确认号不等于SYN的序列号加1
+/// 3. 两个方向的五元组是否互为镜像: SYN-ACK按确认号对应的SYN不在反方向上，或者两个单向流的端口互为镜像、
+///    只有一端的地址相同 (只改写了一个方向)
+pub fn validate_flows(input_path: &str) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut ip_checksum = Issue::default();
+    let mut l4_checksum = Issue::default();
+    let mut ip_length = Issue::default();
+    let mut seq_jump = Issue::default();
+    let mut ack_ahead = Issue::default();
+    let mut synack_mismatch = Issue::default();
+    let mut tcp: HashMap<FlowKey, TcpDirection> = HashMap::new();
+    // SYN的序列号加1 -> SYN的方向，用于找到SYN-ACK对应的SYN
+    let mut syn_by_ack: HashMap<u32, FlowKey> = HashMap::new();
+    let mut directions: BTreeSet<FlowKey> = BTreeSet::new();
+    let mut mirror_pairs: BTreeSet<(FlowKey, FlowKey)> = BTreeSet::new();
+    let mut packet_count = 0u64;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let Some(ip) = &parsed.ip else {
+            continue;
+        };
+        let number = format!("#{}", packet_count);
+        if ip_checksum_valid(data, &parsed) == Some(false) {
+            ip_checksum.record(number.clone());
+        }
+        if l4_checksum_valid(data, &parsed) == Some(false) {
+            l4_checksum.record(number.clone());
+        }
+        // 截断的帧 (抓包长度小于原始长度) 不检查长度；IPv4总长度为0是TSO的痕迹，由 detect-offload 报告
+        let truncated = (packet.orig_len as usize) > data.len();
+        if !truncated && ip.total_len != 0 && (ip.end() > data.len() || ip.total_len < ip.header_len) {
+            ip_length.record(number.clone());
+        }
+
+        let Some(key) = parsed.flow_key().filter(|key| matches!(key.protocol, IP_PROTO_TCP | IP_PROTO_UDP)) else {
+            continue;
+        };
+        directions.insert(key);
+        let Some(Transport::Tcp(tcp_info)) = parsed.transport else {
+            continue;
+        };
+        if data.len() < tcp_info.offset + 16 {
+            continue;
+        }
+        let seq = read_u32(data, tcp_info.offset + 4);
+        let ack = read_u32(data, tcp_info.offset + 8);
+        let flags = data[tcp_info.offset + 13];
+        let payload_len = parsed.payload_range(data.len()).len() as u32;
+
+        if flags & (TCP_SYN | TCP_ACK) == TCP_SYN {
+            // 新的连接 (可能复用了五元组) 重新开始记录
+            tcp.insert(key, TcpDirection { isn: Some(seq), seq_end: None });
+            syn_by_ack.insert(seq.wrapping_add(1), key);
+        } else if flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK {
+            tcp.insert(key, TcpDirection { isn: Some(seq), seq_end: None });
+            match tcp.get(&key.reversed()).and_then(|peer| peer.isn) {
+                Some(isn) if ack != isn.wrapping_add(1) => synack_mismatch.record(number.clone()),
+                Some(_) => {}
+                None => {
+                    if let Some(&syn_key) = syn_by_ack.get(&ack)
+                        && syn_key != key.reversed()
+                    {
+                        mirror_pairs.insert((syn_key.min(key), syn_key.max(key)));
+                    }
+                }
+            }
+        }
+
+        let sent_len = payload_len + u32::from(flags & TCP_SYN != 0) + u32::from(flags & TCP_FIN != 0);
+        let direction = tcp.entry(key).or_default();
+        if let Some(seq_end) = direction.seq_end
+            && flags & TCP_SYN == 0
+        {
+            let delta = seq.wrapping_sub(seq_end) as i32 as i64;
+            if delta.abs() > MAX_SEQ_JUMP {
+                seq_jump.record(number.clone());
+            }
+        }
+        let end = seq.wrapping_add(sent_len);
+        if direction.seq_end.is_none_or(|seq_end| end.wrapping_sub(seq_end) as i32 > 0) {
+            direction.seq_end = Some(end);
+        }
+        if flags & TCP_ACK != 0
+            && flags & TCP_RST == 0
+            && let Some(peer_end) = tcp.get(&key.reversed()).and_then(|peer| peer.seq_end)
+            && ack.wrapping_sub(peer_end) as i32 as i64 > MAX_SEQ_JUMP
+        {
+            ack_ahead.record(number);
+        }
+    }
+
+    // 只有一个方向的流中，端口互为镜像而只有一端地址相同的一对，很可能是只改写了其中一个方向
+    let one_way: Vec<&FlowKey> = directions.iter().filter(|key| !directions.contains(&key.reversed())).collect();
+    let mut by_ports: HashMap<(u8, u16, u16), Vec<&FlowKey>> = HashMap::new();
+    for key in &one_way {
+        by_ports.entry((key.protocol, key.src_port, key.dst_port)).or_default().push(key);
+    }
+    for key in &one_way {
+        for other in by_ports.get(&(key.protocol, key.dst_port, key.src_port)).into_iter().flatten() {
+            if (other.src == key.dst) != (other.dst == key.src) {
+                let (a, b) = (**key, **other);
+                mirror_pairs.insert((a.min(b), a.max(b)));
+            }
+        }
+    }
+    let mut mirror = Issue::default();
+    for (a, b) in &mirror_pairs {
+        mirror.record(format!("{} / {}", a, b));
+    }
+
+    print_heading(&format!("五元组一致性检查: {}", input_path));
+    let error = |issue: &Issue| (issue.count > 0).then_some(Severity::Error);
+    let issues = [
+        ("IP头校验和错误", &ip_checksum),
+        ("TCP/UDP校验和错误", &l4_checksum),
+        ("IP长度与帧长度不符", &ip_length),
+        ("TCP序列号跳变", &seq_jump),
+        ("确认号超前对方已发送的数据", &ack_ahead),
+        ("SYN-ACK确认号与SYN不符", &synack_mismatch),
+        ("两个方向的五元组不对称", &mirror),
+    ];
+    let mut fields = vec![
+        ("数据包数", packet_count.to_string(), None),
+        ("TCP/UDP流数", directions.iter().map(FlowKey::canonical).collect::<BTreeSet<_>>().len().to_string(), None),
+    ];
+    for (name, issue) in &issues {
+        fields.push((name, issue.count.to_string(), error(issue)));
+    }
+    print_fields(&fields);
+
+    let found: Vec<&(&str, &Issue)> = issues.iter().filter(|(_, issue)| issue.count > 0).collect();
+    if !found.is_empty() {
+        print_section("示例:");
+        let mut table = Table::new(&["问题", "数量", "示例"]).right_align(&[1]);
+        for (name, issue) in &found {
+            table.add_row(vec![name.to_string(), issue.count.to_string(), issue.examples.join(", ")]);
+        }
+        table.print();
+    }
+
+    print_separator();
+    if found.is_empty() {
+        print_verdict(Severity::Ok, "校验和、TCP序列号和两个方向的五元组都一致");
+    } else {
+        print_verdict(
+            Severity::Error,
+            &format!("发现 {} 类不一致，改写 (rewrite/anonymize/NAT等) 可能破坏了抓包的一致性", found.len()),
+        );
+    }
+    Ok(())
+}
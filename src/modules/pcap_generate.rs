@@ -0,0 +1,496 @@
+use std::collections::VecDeque;
+use pcap_file::pcap_header::{Datalink, PcapHeader};
+use pcap_file::Packet;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::io::PacketWriter;
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_malformed::{Malformation, malformed_frames};
+use crate::modules::pcap_output::{Output, compression, create_output};
+use crate::modules::pcap_packet_builder::{ethernet, ipv4, tcp, udp};
+use crate::modules::pcap_packet_parser::{ETHERTYPE_IPV4, IP_PROTO_TCP, IP_PROTO_UDP, LINKTYPE_ETHERNET};
+use crate::modules::pcap_time_anchor::split_timestamp_us;
+use crate::modules::pcap_verify::OutputDigest;
+
+const CLIENT: [u8; 4] = [10, 0, 0, 1];
+const SERVER: [u8; 4] = [10, 0, 0, 2];
+const CLIENT_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+const SERVER_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+/// 第一个流的源端口，之后的流依次加1
+const FIRST_SOURCE_PORT: u16 = 10000;
+/// discard服务
+const DESTINATION_PORT: u16 = 9;
+
+/// 以太网 + IPv4 + UDP头的长度，即最小的帧长
+const HEADERS_LEN: usize = 14 + 20 + 8;
+/// 最大帧长 (与默认的snaplen相同，更长的包无法被读取)
+const MAX_FRAME_LEN: usize = 65535;
+
+/// 包的到达过程
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ArrivalProcess {
+    /// 固定间隔
+    #[default]
+    Constant,
+    /// 泊松过程 (间隔服从指数分布)
+    Poisson,
+    /// 开关过程: 开启期间按泊松过程发送，关闭期间不发送，开启和关闭的时长服从指数分布
+    OnOff,
+}
+
+/// 帧长 (不含FCS) 的分布
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeDistribution {
+    Fixed(usize),
+    /// 闭区间内的均匀分布
+    Uniform(usize, usize),
+    /// 正态分布 (均值, 标准差)
+    Normal(f64, f64),
+    /// Simple IMIX: 60、590、1514字节按 7:4:1 的比例
+    Imix,
+}
+
+/// 解析帧长分布: `N` 或 `fixed:N`、`uniform:MIN-MAX`、`normal:MEAN,STDDEV`、`imix`
+pub fn parse_size_distribution(text: &str) -> Result<SizeDistribution, String> {
+    let (kind, argument) = text.split_once(':').unwrap_or(("", text));
+    let number = |text: &str| text.trim().parse::<usize>().map_err(|_| format!("无效的帧长: {}", text));
+    let distribution = match kind {
+        "" if argument.eq_ignore_ascii_case("imix") => SizeDistribution::Imix,
+        "" | "fixed" => SizeDistribution::Fixed(number(argument)?),
+        "uniform" => {
+            let (low, high) = argument.split_once('-').ok_or_else(|| format!("uniform 应为 MIN-MAX: {}", text))?;
+            let (low, high) = (number(low)?, number(high)?);
+            if low > high {
+                return Err(format!("uniform 的最小值大于最大值: {}", text));
+            }
+            SizeDistribution::Uniform(low, high)
+        }
+        "normal" => {
+            let (mean, stddev) = argument.split_once(',').ok_or_else(|| format!("normal 应为 MEAN,STDDEV: {}", text))?;
+            let parse = |text: &str| text.trim().parse::<f64>().ok().filter(|v| v.is_finite() && *v >= 0.0);
+            match (parse(mean), parse(stddev)) {
+                (Some(mean), Some(stddev)) => SizeDistribution::Normal(mean, stddev),
+                _ => return Err(format!("无效的正态分布参数: {}", text)),
+            }
+        }
+        _ => return Err(format!("未知的帧长分布: {} (可用: fixed、uniform、normal、imix)", text)),
+    };
+    Ok(distribution)
+}
+
+/// 生成流量的参数
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// 最多生成的包数
+    pub count: Option<u64>,
+    /// 最长的时长 (微秒)
+    pub duration_us: Option<u64>,
+    /// 平均发送速率 (包/秒)，开关过程为开启期间的速率
+    pub rate: f64,
+    pub arrival: ArrivalProcess,
+    /// 开关过程中开启和关闭的平均时长 (微秒)
+    pub on_time_us: u64,
+    pub off_time_us: u64,
+    pub size: SizeDistribution,
+    /// 流的数量，每个包随机属于其中一个流
+    pub flows: u16,
+    /// 随机数种子，相同的种子和参数生成相同的文件
+    pub seed: u64,
+    /// 第一个包的时间 (epoch微秒)
+    pub start_us: i64,
+    /// 生成头部故意错误的畸形包
+    pub malformed: bool,
+}
+
+/// 确定性的伪随机数 (SplitMix64)
+pub struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// (0, 1] 上的均匀分布
+    fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// 均值为 `mean` 的指数分布
+    fn exponential(&mut self, mean: f64) -> f64 {
+        -mean * self.next_f64().ln()
+    }
+
+    /// 标准正态分布 (Box-Muller)
+    fn normal(&mut self) -> f64 {
+        let (u1, u2) = (self.next_f64(), self.next_f64());
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+
+    /// [0, n) 上的均匀整数
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+impl SizeDistribution {
+    fn sample(&self, rng: &mut Rng) -> usize {
+        let size = match *self {
+            SizeDistribution::Fixed(size) => size,
+            SizeDistribution::Uniform(low, high) => low + rng.below((high - low) as u64 + 1) as usize,
+            SizeDistribution::Normal(mean, stddev) => (mean + stddev * rng.normal()).round().max(0.0) as usize,
+            SizeDistribution::Imix => match rng.below(12) {
+                0..7 => 60,
+                7..11 => 590,
+                _ => 1514,
+            },
+        };
+        size.clamp(HEADERS_LEN, MAX_FRAME_LEN)
+    }
+}
+
+/// 按到达过程产生包的时间
+struct Arrivals {
+    process: ArrivalProcess,
+    /// 平均包间隔 (微秒)
+    interval_us: f64,
+    on_time_us: f64,
+    off_time_us: f64,
+    /// 当前时间 (相对于第一个包，微秒)
+    now_us: f64,
+    /// 开关过程中当前开启期的结束时间
+    on_until_us: f64,
+}
+
+impl Arrivals {
+    fn new(options: &GenerateOptions, rng: &mut Rng) -> Arrivals {
+        let on_time_us = options.on_time_us as f64;
+        Arrivals {
+            process: options.arrival,
+            interval_us: 1_000_000.0 / options.rate,
+            on_time_us,
+            off_time_us: options.off_time_us as f64,
+            now_us: 0.0,
+            on_until_us: rng.exponential(on_time_us),
+        }
+    }
+
+    /// 下一个包的时间；第一个包总是在0时刻
+    fn next(&mut self, rng: &mut Rng, first: bool) -> f64 {
+        if first {
+            return 0.0;
+        }
+        match self.process {
+            ArrivalProcess::Constant => self.now_us += self.interval_us,
+            ArrivalProcess::Poisson => self.now_us += rng.exponential(self.interval_us),
+            ArrivalProcess::OnOff => {
+                let mut next_us = self.now_us + rng.exponential(self.interval_us);
+                // 超出开启期时跳过关闭期，在下一个开启期的开始发送
+                while next_us > self.on_until_us {
+                    next_us = self.on_until_us + rng.exponential(self.off_time_us);
+                    self.on_until_us = next_us + rng.exponential(self.on_time_us);
+                }
+                self.now_us = next_us;
+            }
+        }
+        self.now_us
+    }
+}
+
+/// 生成合成的UDP流量
+///
+/// # 参数
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `options`: 包数或时长、到达过程、帧长分布等
+///
+/// # 功能
+/// 1. 包的时间按到达过程生成 (固定间隔、泊松或开关过程)，帧长按给定分布随机选取
+/// 2. 包为 10.0.0.1 到 10.0.0.2:9 的UDP数据报，每个流使用不同的源端口，校验和正确
+/// 3. 指定 `malformed` 时每个包随机改为一种畸形包 (见 [`Malformation`])，
+///    重叠分片占两个包，PCAP文件本身仍然有效
+/// 4. 达到包数或时长之一时结束；相同的种子和参数生成逐字节相同的文件
+pub fn pcap_generate(output_path: &str, options: &GenerateOptions) -> Result<()> {
+    validate_options(options)?;
+
+    let mut output = FrameWriter::create(output_path)?;
+    let mut rng = Rng::new(options.seed);
+    let mut arrivals = Arrivals::new(options, &mut rng);
+    let mut last_us = 0i64;
+    let mut malformed_counts = [0u64; Malformation::ALL.len()];
+    while options.count.is_none_or(|count| output.packets < count) {
+        let offset_us = arrivals.next(&mut rng, output.packets == 0).round() as i64;
+        if options.duration_us.is_some_and(|duration| offset_us as u64 >= duration) {
+            break;
+        }
+        let frame_len = options.size.sample(&mut rng);
+        let flow = rng.below(options.flows as u64) as u16;
+        let frame = udp_frame(FIRST_SOURCE_PORT.wrapping_add(flow), frame_len, output.packets);
+        last_us = offset_us;
+        let time_us = options.start_us.saturating_add(offset_us);
+        if options.malformed {
+            let remaining = options.count.map_or(u64::MAX, |count| count - output.packets);
+            let kind = Malformation::sample(&mut rng, remaining);
+            malformed_counts[kind as usize] += 1;
+            for frame in malformed_frames(&frame, kind, &mut rng) {
+                output.write(time_us, &frame)?;
+            }
+        } else {
+            output.write(time_us, &frame)?;
+        }
+    }
+    let (written, bytes) = output.finish()?;
+
+    let seconds = last_us as f64 / 1_000_000.0;
+    info!(
+        "成功生成流量: 包数={}, 时长={:.6} 秒, 平均速率={:.1} 包/秒, 平均帧长={:.1} 字节, 输出文件={}",
+        written,
+        seconds,
+        if seconds > 0.0 { written as f64 / seconds } else { 0.0 },
+        bytes as f64 / written.max(1) as f64,
+        output_path
+    );
+    if options.malformed {
+        let counts: Vec<String> = Malformation::ALL
+            .iter()
+            .zip(malformed_counts)
+            .filter(|(_, count)| *count > 0)
+            .map(|(kind, count)| format!("{}={}", kind.name(), count))
+            .collect();
+        info!("畸形包种类: {}", counts.join(", "));
+    }
+    Ok(())
+}
+
+/// 按时间顺序写入生成的以太网帧，并在结束时校验输出
+struct FrameWriter<'a> {
+    path: &'a str,
+    pcap_writer: PacketWriter<Output>,
+    digest: OutputDigest,
+    packets: u64,
+    bytes: u64,
+}
+
+impl<'a> FrameWriter<'a> {
+    fn create(path: &'a str) -> Result<FrameWriter<'a>> {
+        let out_file = create_output(path, compression())?;
+        let header = PcapHeader::with_datalink(Datalink::from(LINKTYPE_ETHERNET));
+        let pcap_writer = PacketWriter::with_header(header, out_file, path)
+            .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+        Ok(FrameWriter { path, pcap_writer, digest: OutputDigest::default(), packets: 0, bytes: 0 })
+    }
+
+    fn write(&mut self, time_us: i64, frame: &[u8]) -> Result<()> {
+        let (ts_sec, ts_usec) = split_timestamp_us(time_us)?;
+        let packet = Packet::new(ts_sec, ts_usec, frame.len() as u32, frame);
+        self.pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        self.digest.record_pcap(&packet);
+        self.packets += 1;
+        self.bytes += frame.len() as u64;
+        Ok(())
+    }
+
+    /// 关闭文件并校验，返回写入的包数和字节数
+    fn finish(self) -> Result<(u64, u64)> {
+        self.pcap_writer.into_inner().finish()?;
+        self.digest.verify(self.path)?;
+        Ok((self.packets, self.bytes))
+    }
+}
+
+fn validate_options(options: &GenerateOptions) -> Result<()> {
+    if options.count.is_none() && options.duration_us.is_none() {
+        anyhow::bail!(PcapError::InvalidArgument("需要指定包数或时长".to_string()));
+    }
+    if options.count == Some(0) || options.duration_us == Some(0) {
+        anyhow::bail!(PcapError::InvalidArgument("包数和时长必须大于0".to_string()));
+    }
+    if !options.rate.is_finite() || options.rate <= 0.0 {
+        anyhow::bail!(PcapError::InvalidArgument(format!("发送速率必须大于0: {}", options.rate)));
+    }
+    if options.arrival == ArrivalProcess::OnOff && (options.on_time_us == 0 || options.off_time_us == 0) {
+        anyhow::bail!(PcapError::InvalidArgument("开关过程的开启和关闭时长必须大于0".to_string()));
+    }
+    if options.flows == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("流的数量必须大于0".to_string()));
+    }
+    Ok(())
+}
+
+/// 指定帧长的UDP帧，载荷以包序号开头，其余字节为递增的填充
+fn udp_frame(source_port: u16, frame_len: usize, sequence: u64) -> Vec<u8> {
+    let mut payload: Vec<u8> = (0..frame_len - HEADERS_LEN).map(|i| i as u8).collect();
+    let prefix = sequence.to_be_bytes();
+    let prefix_len = prefix.len().min(payload.len());
+    payload[..prefix_len].copy_from_slice(&prefix[..prefix_len]);
+    let datagram = udp(source_port, DESTINATION_PORT, &payload);
+    ethernet(CLIENT_MAC, SERVER_MAC, &[], ETHERTYPE_IPV4, &ipv4(CLIENT, SERVER, IP_PROTO_UDP, &datagram))
+}
+
+/// 批量传输使用的服务端口 (iperf3)
+const BULK_SERVER_PORT: u16 = 5201;
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+/// 双方在SYN中通告的窗口缩放因子
+const WINDOW_SCALE: u8 = 7;
+/// 以太网 + IPv4 + TCP头 (无选项) 的长度
+const TCP_HEADERS_LEN: usize = 14 + 20 + 20;
+
+/// TCP批量传输的参数
+#[derive(Debug, Clone)]
+pub struct TcpBulkOptions {
+    /// 传输的载荷字节数
+    pub transfer_bytes: u64,
+    /// 最大报文段长度
+    pub mss: u16,
+    /// 发送速率 (载荷字节/秒)
+    pub bandwidth: u64,
+    /// 往返时延 (微秒)
+    pub rtt_us: u64,
+    /// 随机数种子 (决定初始序号)
+    pub seed: u64,
+    /// SYN的时间 (epoch微秒)
+    pub start_us: i64,
+}
+
+/// 发送方待收到的ACK
+struct PendingAck {
+    time_us: i64,
+    /// 确认到的载荷字节数
+    acked: u64,
+}
+
+/// 生成一个完整的TCP批量传输会话
+///
+/// # 参数
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `options`: 传输字节数、MSS、发送速率和往返时延
+///
+/// # 功能
+/// 1. 三次握手 (SYN中通告MSS和窗口缩放)，客户端 10.0.0.1 向 10.0.0.2:5201 按发送速率均匀发送MSS大小的数据段
+/// 2. 服务端每收到两个数据段 (以及最后一个数据段) 后确认，ACK在一个往返时延后到达；
+///    未确认的数据不超过接收窗口
+/// 3. 客户端发送FIN，服务端确认并发送FIN，客户端确认后结束
+///
+/// 抓包点位于客户端，序号、确认号和校验和均正确，载荷第k字节为 `k mod 256`，
+/// 可作为其他命令 (流重组、统计、导出等) 的已知结果的输入。
+pub fn pcap_generate_tcp_bulk(output_path: &str, options: &TcpBulkOptions) -> Result<()> {
+    if options.mss == 0 || options.mss as usize > MAX_FRAME_LEN - TCP_HEADERS_LEN {
+        anyhow::bail!(PcapError::InvalidArgument(format!(
+            "MSS必须在 1~{} 之间: {}",
+            MAX_FRAME_LEN - TCP_HEADERS_LEN,
+            options.mss
+        )));
+    }
+    if options.bandwidth == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("发送速率必须大于0".to_string()));
+    }
+
+    let mut output = FrameWriter::create(output_path)?;
+    let mut rng = Rng::new(options.seed);
+    let client_isn = rng.next_u64() as u32;
+    let server_isn = rng.next_u64() as u32;
+    let rtt_us = options.rtt_us.min(i64::MAX as u64) as i64;
+    let window = (u16::MAX as u64) << WINDOW_SCALE;
+    let [mss_high, mss_low] = options.mss.to_be_bytes();
+    let syn_options = [2, 4, mss_high, mss_low, 1, 3, 3, WINDOW_SCALE];
+    let to_server = |seq: u32, ack: u32, flags: u8, options: &[u8], payload: &[u8]| {
+        let segment = tcp(FIRST_SOURCE_PORT, BULK_SERVER_PORT, seq, ack, flags, options, payload);
+        ethernet(CLIENT_MAC, SERVER_MAC, &[], ETHERTYPE_IPV4, &ipv4(CLIENT, SERVER, IP_PROTO_TCP, &segment))
+    };
+    let to_client = |seq: u32, ack: u32, flags: u8, options: &[u8]| {
+        let segment = tcp(BULK_SERVER_PORT, FIRST_SOURCE_PORT, seq, ack, flags, options, &[]);
+        ethernet(SERVER_MAC, CLIENT_MAC, &[], ETHERTYPE_IPV4, &ipv4(SERVER, CLIENT, IP_PROTO_TCP, &segment))
+    };
+
+    // 三次握手
+    let start_us = options.start_us;
+    let client_data = client_isn.wrapping_add(1);
+    let server_data = server_isn.wrapping_add(1);
+    output.write(start_us, &to_server(client_isn, 0, TCP_SYN, &syn_options, &[]))?;
+    let established_us = start_us.saturating_add(rtt_us);
+    output.write(established_us, &to_client(server_isn, client_data, TCP_SYN | TCP_ACK, &syn_options))?;
+    output.write(established_us, &to_server(client_data, server_data, TCP_ACK, &[], &[]))?;
+
+    // 数据段按发送速率均匀发送: 第n个字节在 n / bandwidth 秒时发出
+    let send_time = |offset: u64| -> i64 {
+        let elapsed_us = offset as u128 * 1_000_000 / options.bandwidth as u128;
+        established_us.saturating_add(elapsed_us.min(i64::MAX as u128) as i64)
+    };
+    let mut pending: VecDeque<PendingAck> = VecDeque::new();
+    let mut acked = 0u64;
+    let mut sent = 0u64;
+    let mut segments = 0u64;
+    let mut payload = Vec::with_capacity(options.mss as usize);
+    // 窗口满时要等到ACK到达才能继续发送，之后的发送时间顺延
+    let mut delay_us = 0i64;
+    while sent < options.transfer_bytes {
+        let length = (options.transfer_bytes - sent).min(options.mss as u64);
+        let mut time_us = send_time(sent).saturating_add(delay_us);
+        while let Some(ack) = pending.front()
+            && (ack.time_us <= time_us || sent + length - acked > window)
+        {
+            if ack.time_us > time_us {
+                delay_us = delay_us.saturating_add(ack.time_us - time_us);
+                time_us = ack.time_us;
+            }
+            acked = ack.acked;
+            output.write(ack.time_us, &to_client(server_data, client_data.wrapping_add(acked as u32), TCP_ACK, &[]))?;
+            pending.pop_front();
+        }
+
+        payload.clear();
+        payload.extend((sent..sent + length).map(|offset| offset as u8));
+        sent += length;
+        segments += 1;
+        let last = sent == options.transfer_bytes;
+        let flags = if last { TCP_PSH | TCP_ACK } else { TCP_ACK };
+        let seq = client_data.wrapping_add((sent - length) as u32);
+        output.write(time_us, &to_server(seq, server_data, flags, &[], &payload))?;
+        if last || segments.is_multiple_of(2) {
+            pending.push_back(PendingAck { time_us: time_us.saturating_add(rtt_us), acked: sent });
+        }
+    }
+
+    // 四次挥手
+    let fin_us = send_time(sent).saturating_add(delay_us);
+    let fin_seq = client_data.wrapping_add(sent as u32);
+    let mut fin_sent = false;
+    for ack in pending.drain(..) {
+        if !fin_sent && ack.time_us > fin_us {
+            output.write(fin_us, &to_server(fin_seq, server_data, TCP_FIN | TCP_ACK, &[], &[]))?;
+            fin_sent = true;
+        }
+        output.write(ack.time_us, &to_client(server_data, client_data.wrapping_add(ack.acked as u32), TCP_ACK, &[]))?;
+    }
+    if !fin_sent {
+        output.write(fin_us, &to_server(fin_seq, server_data, TCP_FIN | TCP_ACK, &[], &[]))?;
+    }
+    let close_us = fin_us.saturating_add(rtt_us);
+    output.write(close_us, &to_client(server_data, fin_seq.wrapping_add(1), TCP_FIN | TCP_ACK, &[]))?;
+    output.write(close_us, &to_server(fin_seq.wrapping_add(1), server_data.wrapping_add(1), TCP_ACK, &[], &[]))?;
+    let (written, bytes) = output.finish()?;
+
+    let seconds = (close_us - start_us) as f64 / 1_000_000.0;
+    info!(
+        "成功生成TCP批量传输: 包数={}, 数据段数={}, 载荷={} 字节, 总字节数={}, 时长={:.6} 秒, 吞吐量={:.1} 字节/秒, 输出文件={}",
+        written,
+        segments,
+        sent,
+        bytes,
+        seconds,
+        if seconds > 0.0 { sent as f64 / seconds } else { 0.0 },
+        output_path
+    );
+    Ok(())
+}
@@ -0,0 +1,860 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::modules::pcap_fcs::crc32_update;
+use crate::modules::pcap_lz77::{BitWriter, MatchFinder, MatchParams, Sequence};
+
+/// gzip文件头的魔数
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// deflate的回溯窗口大小
+const WINDOW_SIZE: usize = 32 * 1024;
+
+/// 每次解码时最多产生的输出 (字节)，之后返回给调用者
+const DECODE_CHUNK: usize = 64 * 1024;
+
+/// 压缩时每个deflate块的输入大小
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// 快速查找表的位数，更长的码逐位解码
+const FAST_BITS: u32 = 9;
+
+const FLAG_HCRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+
+/// 长度码 257-285 的基数和额外位数
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// 距离码 0-29 的基数和额外位数
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// 动态块中码长的码长的排列顺序
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("gzip数据损坏: {}", message))
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "gzip数据不完整")
+}
+
+/// 按位读取 (deflate从每个字节的最低位开始)
+struct BitReader<R: Read> {
+    inner: BufReader<R>,
+    buffer: u64,
+    bits: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    /// 保证缓冲区中至少有 `count` 位 (不超过56)
+    fn ensure(&mut self, count: u32) -> io::Result<()> {
+        while self.bits < count {
+            let available = self.inner.fill_buf()?;
+            if available.is_empty() {
+                return Err(truncated());
+            }
+            let take = (((64 - self.bits) / 8) as usize).min(available.len());
+            for &byte in &available[..take] {
+                self.buffer |= (byte as u64) << self.bits;
+                self.bits += 8;
+            }
+            self.inner.consume(take);
+        }
+        Ok(())
+    }
+
+    fn bits(&mut self, count: u32) -> io::Result<u32> {
+        self.ensure(count)?;
+        let value = (self.buffer & ((1u64 << count) - 1)) as u32;
+        self.buffer >>= count;
+        self.bits -= count;
+        Ok(value)
+    }
+
+    /// 丢弃到下一个字节边界的位
+    fn align(&mut self) {
+        let extra = self.bits % 8;
+        self.buffer >>= extra;
+        self.bits -= extra;
+    }
+
+    /// 按字节边界读取 (先取缓冲区中剩余的整字节)
+    fn read_bytes(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let mut filled = 0;
+        while filled < buf.len() && self.bits >= 8 {
+            buf[filled] = self.buffer as u8;
+            self.buffer >>= 8;
+            self.bits -= 8;
+            filled += 1;
+        }
+        self.inner.read_exact(&mut buf[filled..]).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => truncated(),
+            _ => e,
+        })
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0u8];
+        self.read_bytes(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    /// 字节边界处是否已经没有数据
+    fn at_end(&mut self) -> io::Result<bool> {
+        Ok(self.bits < 8 && self.inner.fill_buf()?.is_empty())
+    }
+}
+
+/// 规范哈夫曼码的解码表
+struct Huffman {
+    /// 按码的低 FAST_BITS 位 (先读到的位) 查找: 符号 << 4 | 码长，0表示码更长
+    fast: Vec<u16>,
+    /// 每种码长的码数
+    counts: [u16; 16],
+    /// 按码排列的符号
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// 由每个符号的码长构造，码长为0的符号不出现；码超额时返回错误 (允许不完整的码)
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = left * 2 - count as i32;
+            if left < 0 {
+                return Err(invalid("哈夫曼码超额"));
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        let mut fast = vec![0u16; 1 << FAST_BITS];
+        let mut code = 0u32;
+        let mut index = 0;
+        for len in 1..=FAST_BITS {
+            for _ in 0..counts[len as usize] {
+                // 码按最高位先发送，查找表按读到的顺序 (反转后) 索引
+                let reversed = code.reverse_bits() >> (32 - len);
+                let entry = symbols[index] << 4 | len as u16;
+                for slot in (reversed as usize..fast.len()).step_by(1 << len) {
+                    fast[slot] = entry;
+                }
+                code += 1;
+                index += 1;
+            }
+            code <<= 1;
+        }
+        Ok(Huffman { fast, counts, symbols })
+    }
+
+    fn decode<R: Read>(&self, reader: &mut BitReader<R>) -> io::Result<u16> {
+        // 结束前至少还有gzip尾部的8字节，不够15位说明数据不完整
+        reader.ensure(15)?;
+        let entry = self.fast[(reader.buffer & ((1 << FAST_BITS) - 1)) as usize];
+        if entry != 0 {
+            let len = (entry & 0x0f) as u32;
+            reader.buffer >>= len;
+            reader.bits -= len;
+            return Ok(entry >> 4);
+        }
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("无效的哈夫曼码"))
+    }
+}
+
+/// 当前的deflate块
+enum Block {
+    /// 需要读取下一个块头
+    Header,
+    /// 未压缩的块，剩余的字节数
+    Stored(usize),
+    /// 压缩块的字面量/长度码和距离码
+    Huffman(Box<(Huffman, Huffman)>),
+}
+
+/// 边读取边解压的gzip输入 (RFC 1952、RFC 1951)
+///
+/// 支持多个成员首尾相接的文件 (例如分别压缩后拼接)，每个成员的CRC-32和长度都会校验；
+/// 数据损坏或不完整时读取返回错误。
+pub struct GzipDecoder<R: Read> {
+    reader: BitReader<R>,
+    block: Block,
+    /// 当前块是否为成员的最后一个块
+    last_block: bool,
+    /// 是否处于成员之中 (已读过成员头)
+    in_member: bool,
+    finished: bool,
+    /// 已解压的数据: 回溯窗口和尚未返回的部分
+    output: Vec<u8>,
+    /// `output` 中已返回的位置
+    returned: usize,
+    /// `output` 中已计入CRC的位置
+    checked: usize,
+    crc: u32,
+    member_len: u32,
+    /// 解压后的总字节数
+    total_len: u64,
+}
+
+impl<R: Read> GzipDecoder<R> {
+    pub fn new(inner: R) -> GzipDecoder<R> {
+        GzipDecoder {
+            reader: BitReader { inner: BufReader::new(inner), buffer: 0, bits: 0 },
+            block: Block::Header,
+            last_block: false,
+            in_member: false,
+            finished: false,
+            output: Vec::new(),
+            returned: 0,
+            checked: 0,
+            crc: 0,
+            member_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /// 已校验过的解压字节数 (读完时为解压后的总长度)
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+
+    fn read_member_header(&mut self) -> io::Result<()> {
+        let mut header = [0u8; 10];
+        self.reader.read_bytes(&mut header)?;
+        if header[..2] != GZIP_MAGIC {
+            return Err(invalid("gzip成员之后有多余的数据"));
+        }
+        if header[2] != 8 {
+            return Err(invalid(&format!("不支持的压缩方法: {}", header[2])));
+        }
+        let flags = header[3];
+        if flags & 0xe0 != 0 {
+            return Err(invalid("保留的标志位不为0"));
+        }
+        if flags & FLAG_EXTRA != 0 {
+            let mut len = [0u8; 2];
+            self.reader.read_bytes(&mut len)?;
+            let mut extra = vec![0u8; u16::from_le_bytes(len) as usize];
+            self.reader.read_bytes(&mut extra)?;
+        }
+        for flag in [FLAG_NAME, FLAG_COMMENT] {
+            if flags & flag != 0 {
+                while self.reader.byte()? != 0 {}
+            }
+        }
+        if flags & FLAG_HCRC != 0 {
+            self.reader.read_bytes(&mut [0u8; 2])?;
+        }
+        self.in_member = true;
+        self.last_block = false;
+        self.block = Block::Header;
+        self.crc = 0;
+        self.member_len = 0;
+        Ok(())
+    }
+
+    /// 把新输出计入CRC
+    fn update_crc(&mut self) {
+        self.crc = crc32_update(self.crc, &self.output[self.checked..]);
+        self.member_len = self.member_len.wrapping_add((self.output.len() - self.checked) as u32);
+        self.total_len += (self.output.len() - self.checked) as u64;
+        self.checked = self.output.len();
+    }
+
+    fn finish_member(&mut self) -> io::Result<()> {
+        self.update_crc();
+        self.reader.align();
+        let mut trailer = [0u8; 8];
+        self.reader.read_bytes(&mut trailer)?;
+        if u32::from_le_bytes(trailer[..4].try_into().unwrap()) != self.crc {
+            return Err(invalid("CRC-32校验失败"));
+        }
+        if u32::from_le_bytes(trailer[4..].try_into().unwrap()) != self.member_len {
+            return Err(invalid("长度与尾部记录的不符"));
+        }
+        self.in_member = false;
+        self.finished = self.reader.at_end()?;
+        Ok(())
+    }
+
+    fn read_block_header(&mut self) -> io::Result<()> {
+        self.last_block = self.reader.bits(1)? == 1;
+        self.block = match self.reader.bits(2)? {
+            0 => {
+                self.reader.align();
+                let mut lengths = [0u8; 4];
+                self.reader.read_bytes(&mut lengths)?;
+                let len = u16::from_le_bytes([lengths[0], lengths[1]]);
+                if len != !u16::from_le_bytes([lengths[2], lengths[3]]) {
+                    return Err(invalid("未压缩块的长度校验失败"));
+                }
+                Block::Stored(len as usize)
+            },
+            1 => {
+                let mut lengths = [8u8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                Block::Huffman(Box::new((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?)))
+            },
+            2 => Block::Huffman(Box::new(self.read_dynamic_tables()?)),
+            _ => return Err(invalid("无效的块类型")),
+        };
+        Ok(())
+    }
+
+    fn read_dynamic_tables(&mut self) -> io::Result<(Huffman, Huffman)> {
+        let literals = self.reader.bits(5)? as usize + 257;
+        let distances = self.reader.bits(5)? as usize + 1;
+        let code_lengths = self.reader.bits(4)? as usize + 4;
+        let mut lengths = [0u8; 19];
+        for &position in &CODE_LENGTH_ORDER[..code_lengths] {
+            lengths[position] = self.reader.bits(3)? as u8;
+        }
+        let code_length_code = Huffman::new(&lengths)?;
+
+        let mut lengths = vec![0u8; literals + distances];
+        let mut index = 0;
+        while index < lengths.len() {
+            let symbol = code_length_code.decode(&mut self.reader)?;
+            let (value, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => {
+                    let previous = *lengths[..index].last().ok_or_else(|| invalid("重复码长时没有前一个码长"))?;
+                    (previous, 3 + self.reader.bits(2)? as usize)
+                },
+                17 => (0, 3 + self.reader.bits(3)? as usize),
+                _ => (0, 11 + self.reader.bits(7)? as usize),
+            };
+            if index + repeat > lengths.len() {
+                return Err(invalid("码长超出范围"));
+            }
+            lengths[index..index + repeat].fill(value);
+            index += repeat;
+        }
+        if lengths[256] == 0 {
+            return Err(invalid("没有块结束码"));
+        }
+        Ok((Huffman::new(&lengths[..literals])?, Huffman::new(&lengths[literals..])?))
+    }
+
+    /// 解压一部分数据追加到 `output`
+    fn decode_chunk(&mut self) -> io::Result<()> {
+        // 丢弃已返回且超出回溯窗口的部分
+        if self.returned > 2 * WINDOW_SIZE {
+            self.update_crc();
+            let discard = self.returned - WINDOW_SIZE;
+            self.output.drain(..discard);
+            self.returned -= discard;
+            self.checked -= discard;
+        }
+        let limit = self.output.len() + DECODE_CHUNK;
+        while self.output.len() < limit {
+            if !self.in_member {
+                self.read_member_header()?;
+            }
+            match &mut self.block {
+                Block::Header if self.last_block => {
+                    self.finish_member()?;
+                    return Ok(());
+                },
+                Block::Header => self.read_block_header()?,
+                Block::Stored(remaining) => {
+                    let len = (*remaining).min(limit - self.output.len());
+                    let start = self.output.len();
+                    self.output.resize(start + len, 0);
+                    *remaining -= len;
+                    if *remaining == 0 {
+                        self.block = Block::Header;
+                    }
+                    self.reader.read_bytes(&mut self.output[start..])?;
+                },
+                Block::Huffman(tables) => {
+                    let (literal_code, distance_code) = &**tables;
+                    let symbol = literal_code.decode(&mut self.reader)?;
+                    match symbol {
+                        0..=255 => self.output.push(symbol as u8),
+                        256 => self.block = Block::Header,
+                        257..=285 => {
+                            let index = (symbol - 257) as usize;
+                            let length = LENGTH_BASE[index] as usize + self.reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                            let code = distance_code.decode(&mut self.reader)? as usize;
+                            if code >= DISTANCE_BASE.len() {
+                                return Err(invalid("无效的距离码"));
+                            }
+                            let distance = DISTANCE_BASE[code] as usize + self.reader.bits(DISTANCE_EXTRA[code] as u32)? as usize;
+                            if distance > self.output.len() {
+                                return Err(invalid("距离超出已解压的数据"));
+                            }
+                            let start = self.output.len() - distance;
+                            // 距离小于长度时复制的内容与正在写入的部分重叠，逐字节复制
+                            for offset in 0..length {
+                                let byte = self.output[start + offset];
+                                self.output.push(byte);
+                            }
+                        },
+                        _ => return Err(invalid("无效的字面量/长度码")),
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for GzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.returned == self.output.len() {
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+            self.decode_chunk()?;
+        }
+        let len = buf.len().min(self.output.len() - self.returned);
+        buf[..len].copy_from_slice(&self.output[self.returned..self.returned + len]);
+        self.returned += len;
+        Ok(len)
+    }
+}
+
+/// 按频率构造码长不超过 `limit` 的哈夫曼码，频率为0的符号码长为0
+///
+/// 超过限制时把频率减半后重新构造，直到满足限制。只有一个符号时码长为1。
+fn code_lengths(frequencies: &[u32], limit: u8) -> Vec<u8> {
+    let mut frequencies = frequencies.to_vec();
+    loop {
+        let lengths = huffman_lengths(&frequencies);
+        if lengths.iter().all(|&len| len <= limit) {
+            return lengths;
+        }
+        for frequency in frequencies.iter_mut().filter(|frequency| **frequency > 0) {
+            *frequency = (*frequency >> 1).max(1);
+        }
+    }
+}
+
+fn huffman_lengths(frequencies: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0u8; frequencies.len()];
+    let used: Vec<usize> = (0..frequencies.len()).filter(|&symbol| frequencies[symbol] > 0).collect();
+    if used.len() < 2 {
+        for &symbol in &used {
+            lengths[symbol] = 1;
+        }
+        return lengths;
+    }
+    // 节点: 前面是叶子 (按 `used` 的顺序)，之后是合并出的内部节点
+    let mut parent = vec![usize::MAX; used.len()];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> =
+        used.iter().enumerate().map(|(node, &symbol)| Reverse((frequencies[symbol] as u64, node))).collect();
+    while let (Some(Reverse((first, a))), Some(Reverse((second, b)))) = (heap.pop(), heap.pop()) {
+        let node = parent.len();
+        parent.push(usize::MAX);
+        parent[a] = node;
+        parent[b] = node;
+        heap.push(Reverse((first + second, node)));
+    }
+    for (leaf, &symbol) in used.iter().enumerate() {
+        let mut node = leaf;
+        while parent[node] != usize::MAX {
+            node = parent[node];
+            lengths[symbol] += 1;
+        }
+    }
+    lengths
+}
+
+/// 由码长得到规范哈夫曼码 (已按写出的顺序反转)
+fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let mut counts = [0u16; 16];
+    for &len in lengths {
+        counts[len as usize] += 1;
+    }
+    counts[0] = 0;
+    let mut next = [0u16; 16];
+    for len in 1..16 {
+        next[len] = (next[len - 1] + counts[len - 1]) << 1;
+    }
+    lengths
+        .iter()
+        .map(|&len| {
+            if len == 0 {
+                return 0;
+            }
+            let code = next[len as usize];
+            next[len as usize] += 1;
+            code.reverse_bits() >> (16 - len)
+        })
+        .collect()
+}
+
+/// 取值不超过 `value` 的最大基数的下标
+fn code_for(bases: &[u16], value: usize) -> usize {
+    bases.iter().rposition(|&base| base as usize <= value).unwrap()
+}
+
+/// 固定哈夫曼码的码长
+fn fixed_lengths() -> (Vec<u8>, Vec<u8>) {
+    let mut literals = vec![8u8; 288];
+    literals[144..256].fill(9);
+    literals[256..280].fill(7);
+    (literals, vec![5u8; 30])
+}
+
+/// 写出一个deflate块
+fn write_block(writer: &mut BitWriter, data: &[u8], sequences: &[Sequence], trailing: usize, last: bool) {
+    let mut literal_frequencies = [0u32; 286];
+    let mut distance_frequencies = [0u32; 30];
+    let mut extra_bits = 0usize;
+    let mut pos = 0;
+    for sequence in sequences {
+        for &byte in &data[pos..pos + sequence.literal_len] {
+            literal_frequencies[byte as usize] += 1;
+        }
+        let length_code = code_for(&LENGTH_BASE, sequence.match_len);
+        let distance_code = code_for(&DISTANCE_BASE, sequence.distance);
+        literal_frequencies[257 + length_code] += 1;
+        distance_frequencies[distance_code] += 1;
+        extra_bits += LENGTH_EXTRA[length_code] as usize + DISTANCE_EXTRA[distance_code] as usize;
+        pos += sequence.literal_len + sequence.match_len;
+    }
+    for &byte in &data[pos..pos + trailing] {
+        literal_frequencies[byte as usize] += 1;
+    }
+    literal_frequencies[256] = 1;
+
+    let literal_lengths = code_lengths(&literal_frequencies, 15);
+    let mut distance_lengths = code_lengths(&distance_frequencies, 15);
+    if distance_lengths.iter().all(|&len| len == 0) {
+        distance_lengths[0] = 1;
+    }
+    let literal_count = (257..=286).rev().find(|&count| literal_lengths[count - 1] != 0).unwrap_or(257);
+    let distance_count = (1..=30).rev().find(|&count| distance_lengths[count - 1] != 0).unwrap_or(1);
+
+    // 码长序列按游程编码: (码长码的符号, 额外位的值, 额外位数)
+    let all_lengths: Vec<u8> =
+        literal_lengths[..literal_count].iter().chain(&distance_lengths[..distance_count]).copied().collect();
+    let mut runs = Vec::new();
+    let mut index = 0;
+    while index < all_lengths.len() {
+        let value = all_lengths[index];
+        let mut run = all_lengths[index..].iter().take_while(|&&len| len == value).count();
+        index += run;
+        if value == 0 {
+            while run >= 11 {
+                let repeat = run.min(138);
+                runs.push((18usize, repeat - 11, 7u32));
+                run -= repeat;
+            }
+            if run >= 3 {
+                runs.push((17, run - 3, 3));
+                run = 0;
+            }
+        } else {
+            runs.push((value as usize, 0, 0));
+            run -= 1;
+            while run >= 3 {
+                let repeat = run.min(6);
+                runs.push((16, repeat - 3, 2));
+                run -= repeat;
+            }
+        }
+        runs.extend(std::iter::repeat_n((value as usize, 0, 0), run));
+    }
+    let mut length_frequencies = [0u32; 19];
+    for &(symbol, _, _) in &runs {
+        length_frequencies[symbol] += 1;
+    }
+    let length_lengths = code_lengths(&length_frequencies, 7);
+    let length_count = (4..=19).rev().find(|&count| length_lengths[CODE_LENGTH_ORDER[count - 1]] != 0).unwrap_or(4);
+
+    let data_cost = |literals: &[u8], distances: &[u8]| {
+        let literal_cost: usize = literal_frequencies.iter().zip(literals).map(|(&f, &len)| f as usize * len as usize).sum();
+        let distance_cost: usize = distance_frequencies.iter().zip(distances).map(|(&f, &len)| f as usize * len as usize).sum();
+        literal_cost + distance_cost + extra_bits
+    };
+    let header_cost = 14
+        + 3 * length_count
+        + runs.iter().map(|&(symbol, _, extra)| length_lengths[symbol] as usize + extra as usize).sum::<usize>();
+    let dynamic_cost = header_cost + data_cost(&literal_lengths, &distance_lengths);
+    let (fixed_literals, fixed_distances) = fixed_lengths();
+    let fixed_cost = data_cost(&fixed_literals, &fixed_distances);
+    let stored_cost = data.len() * 8 + data.len().div_ceil(u16::MAX as usize).max(1) * 40;
+
+    if stored_cost <= dynamic_cost.min(fixed_cost) {
+        let mut chunks = data.chunks(u16::MAX as usize).peekable();
+        if chunks.peek().is_none() {
+            writer.bits(last as u32, 1);
+            writer.bits(0, 2);
+            writer.align();
+            writer.bytes(&[0, 0, 0xff, 0xff]);
+        }
+        while let Some(chunk) = chunks.next() {
+            writer.bits((last && chunks.peek().is_none()) as u32, 1);
+            writer.bits(0, 2);
+            writer.align();
+            let len = chunk.len() as u16;
+            writer.bytes(&len.to_le_bytes());
+            writer.bytes(&(!len).to_le_bytes());
+            writer.bytes(chunk);
+        }
+        return;
+    }
+
+    writer.bits(last as u32, 1);
+    let (literal_lengths, distance_lengths) = if fixed_cost <= dynamic_cost {
+        writer.bits(1, 2);
+        (fixed_literals, fixed_distances)
+    } else {
+        writer.bits(2, 2);
+        writer.bits((literal_count - 257) as u32, 5);
+        writer.bits((distance_count - 1) as u32, 5);
+        writer.bits((length_count - 4) as u32, 4);
+        for &position in &CODE_LENGTH_ORDER[..length_count] {
+            writer.bits(length_lengths[position] as u32, 3);
+        }
+        let length_codes = canonical_codes(&length_lengths);
+        for &(symbol, value, extra) in &runs {
+            writer.bits(length_codes[symbol] as u32, length_lengths[symbol] as u32);
+            writer.bits(value as u32, extra);
+        }
+        (literal_lengths, distance_lengths)
+    };
+
+    let literal_codes = canonical_codes(&literal_lengths);
+    let distance_codes = canonical_codes(&distance_lengths);
+    let literal = |writer: &mut BitWriter, symbol: usize| {
+        writer.bits(literal_codes[symbol] as u32, literal_lengths[symbol] as u32)
+    };
+    let mut pos = 0;
+    for sequence in sequences {
+        for &byte in &data[pos..pos + sequence.literal_len] {
+            literal(writer, byte as usize);
+        }
+        let length_code = code_for(&LENGTH_BASE, sequence.match_len);
+        literal(writer, 257 + length_code);
+        writer.bits((sequence.match_len - LENGTH_BASE[length_code] as usize) as u32, LENGTH_EXTRA[length_code] as u32);
+        let distance_code = code_for(&DISTANCE_BASE, sequence.distance);
+        writer.bits(distance_codes[distance_code] as u32, distance_lengths[distance_code] as u32);
+        writer.bits((sequence.distance - DISTANCE_BASE[distance_code] as usize) as u32, DISTANCE_EXTRA[distance_code] as u32);
+        pos += sequence.literal_len + sequence.match_len;
+    }
+    for &byte in &data[pos..pos + trailing] {
+        literal(writer, byte as usize);
+    }
+    literal(writer, 256);
+}
+
+/// 边写入边压缩的gzip输出 (单个成员，级别1-9与gzip相同: 级别越高查找匹配越充分)
+///
+/// 每积累 [`BLOCK_SIZE`] 字节的输入压缩为一个deflate块 (动态、固定哈夫曼码或未压缩中最短的)，
+/// 写完后必须调用 [`GzipEncoder::finish`] 写出最后的块和gzip尾部。
+pub struct GzipEncoder<W: Write> {
+    inner: W,
+    finder: MatchFinder,
+    /// 回溯窗口内的历史数据和尚未压缩的输入
+    buffer: Vec<u8>,
+    /// `buffer` 中已压缩的位置
+    processed: usize,
+    writer: BitWriter,
+    crc: u32,
+    len: u32,
+}
+
+impl<W: Write> GzipEncoder<W> {
+    /// 创建压缩器并写出gzip头
+    pub fn new(mut inner: W, level: u32) -> io::Result<GzipEncoder<W>> {
+        let level = level.clamp(1, 9) as usize;
+        const DEPTH: [usize; 9] = [4, 8, 32, 16, 32, 128, 256, 1024, 4096];
+        const NICE_LEN: [usize; 9] = [8, 16, 32, 16, 32, 128, 128, 258, 258];
+        let params = MatchParams {
+            window_log: WINDOW_SIZE.trailing_zeros(),
+            min_len: 3,
+            max_len: 258,
+            depth: DEPTH[level - 1],
+            nice_len: NICE_LEN[level - 1],
+            lazy: level >= 4,
+        };
+        // 额外标志: 2为最高压缩级别，4为最快的级别；操作系统未知
+        let extra_flags = match level {
+            9 => 2,
+            1 => 4,
+            _ => 0,
+        };
+        inner.write_all(&[GZIP_MAGIC[0], GZIP_MAGIC[1], 8, 0, 0, 0, 0, 0, extra_flags, 255])?;
+        Ok(GzipEncoder {
+            inner,
+            finder: MatchFinder::new(params),
+            buffer: Vec::new(),
+            processed: 0,
+            writer: BitWriter::default(),
+            crc: 0,
+            len: 0,
+        })
+    }
+
+    /// 压缩 `buffer[processed..end]` 为一个块并写出完整的字节
+    fn compress_block(&mut self, end: usize, last: bool) -> io::Result<()> {
+        let (sequences, trailing) = self.finder.parse(&self.buffer, self.processed, end);
+        write_block(&mut self.writer, &self.buffer[self.processed..end], &sequences, trailing, last);
+        self.processed = end;
+        self.processed -= self.finder.trim(&mut self.buffer, self.processed);
+        self.inner.write_all(&self.writer.take_bytes())
+    }
+
+    /// 写出剩余的数据和gzip尾部
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.compress_block(self.buffer.len(), true)?;
+        self.writer.align();
+        self.writer.bytes(&self.crc.to_le_bytes());
+        self.writer.bytes(&self.len.to_le_bytes());
+        self.inner.write_all(&self.writer.take_bytes())?;
+        self.inner.flush()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write> Write for GzipEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc = crc32_update(self.crc, buf);
+        self.len = self.len.wrapping_add(buf.len() as u32);
+        self.buffer.extend_from_slice(buf);
+        while self.buffer.len() - self.processed >= BLOCK_SIZE {
+            self.compress_block(self.processed + BLOCK_SIZE, false)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// 只刷新已写出的完整块，未满一个块的数据在 [`GzipEncoder::finish`] 时写出
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::pcap_payload_filter::parse_hex_bytes;
+
+    /// 可压缩的测试数据: 重复的类包头结构中夹杂伪随机字节
+    fn sample_data(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            data.extend_from_slice(b"\x45\x00\x00\x54\x12\x34\x40\x00\x40\x11");
+            data.extend_from_slice(&state.to_le_bytes()[..(state % 9) as usize]);
+        }
+        data.truncate(len);
+        data
+    }
+
+    fn compress(data: &[u8], level: u32) -> Vec<u8> {
+        let mut encoder = GzipEncoder::new(Vec::new(), level).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap();
+        std::mem::take(encoder.get_mut())
+    }
+
+    fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        GzipDecoder::new(data).read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn decodes_fixed_huffman_member() {
+        let data = parse_hex_bytes("1f8b0800000000000203cb48cdc9c957c84027b9000088590b18000000").unwrap();
+        assert_eq!(decompress(&data).unwrap(), b"hello hello hello hello\n");
+    }
+
+    #[test]
+    fn decodes_dynamic_huffman_member() {
+        let data = parse_hex_bytes(
+            "1f8b080000000000020325c1870100200803b0976851a1ff3f26233180a497d3ee782b32255941e3f0f501e87c389b38000000",
+        )
+        .unwrap();
+        assert_eq!(decompress(&data).unwrap(), b"01122233334444455555566666667889990000111112222223333333");
+    }
+
+    #[test]
+    fn decodes_concatenated_members() {
+        let mut data = compress(b"first ", 6);
+        data.extend_from_slice(&compress(b"second", 1));
+        assert_eq!(decompress(&data).unwrap(), b"first second");
+    }
+
+    #[test]
+    fn round_trips_all_levels() {
+        // 跨越多个块，覆盖块边界处的匹配
+        let data = sample_data(3 * BLOCK_SIZE + 1234);
+        for level in 1..=9 {
+            let compressed = compress(&data, level);
+            assert!(compressed.len() < data.len(), "级别 {} 没有压缩", level);
+            assert_eq!(decompress(&compressed).unwrap(), data, "级别 {}", level);
+        }
+    }
+
+    #[test]
+    fn round_trips_empty_and_incompressible_input() {
+        assert_eq!(decompress(&compress(b"", 6)).unwrap(), b"");
+
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..100_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        assert_eq!(decompress(&compress(&noise, 9)).unwrap(), noise);
+    }
+
+    #[test]
+    fn rejects_corrupted_data() {
+        let mut data = compress(&sample_data(10_000), 6);
+        let crc_offset = data.len() - 8;
+        data[crc_offset] ^= 0xff;
+        assert!(decompress(&data).is_err());
+
+        let data = compress(&sample_data(10_000), 6);
+        assert!(decompress(&data[..data.len() / 2]).is_err());
+    }
+}
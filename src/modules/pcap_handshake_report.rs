@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{FlowKey, Transport, format_endpoint, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{Severity, Table, Verbosity, print_fields, print_heading, print_section, print_separator, print_verdict, verbosity};
+
+/// 客户端/服务端表中最多列出的行数 (详细模式不限制)
+const MAX_PAIR_ROWS: usize = 20;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+/// 一个TCP连接中抓到的握手报文
+struct TcpConnection {
+    /// 客户端到服务端的五元组
+    key: FlowKey,
+    syn: bool,
+    syn_ack: bool,
+    /// 客户端对SYN-ACK的确认
+    ack: bool,
+    /// 出现过FIN或RST，之后的SYN开始新连接 (端口复用)
+    closed: bool,
+    packets: u64,
+}
+
+/// 连接按握手情况的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HandshakeKind {
+    /// 三次握手完整
+    Complete,
+    /// 只有SYN，没有SYN-ACK
+    SynOnly,
+    /// 有SYN和SYN-ACK，没有客户端的ACK
+    MissingAck,
+    /// 有SYN-ACK，没有SYN (抓包开始于握手中间)
+    MissingSyn,
+    /// 没有任何握手报文 (抓包开始于连接中途)
+    MidStream,
+}
+
+impl TcpConnection {
+    fn kind(&self) -> HandshakeKind {
+        match (self.syn, self.syn_ack, self.ack) {
+            (true, true, true) => HandshakeKind::Complete,
+            (true, false, _) => HandshakeKind::SynOnly,
+            (true, true, false) => HandshakeKind::MissingAck,
+            (false, true, _) => HandshakeKind::MissingSyn,
+            (false, false, _) => HandshakeKind::MidStream,
+        }
+    }
+}
+
+/// 按一个TCP连接的第一个包推断客户端到服务端的五元组
+///
+/// SYN的发送方为客户端，SYN-ACK的接收方为客户端；其他情况 (中途开始的连接) 端口较小的一端视为服务端。
+pub fn client_to_server(key: FlowKey, flags: u8) -> FlowKey {
+    let syn_ack = flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK;
+    if flags & (TCP_SYN | TCP_ACK) != TCP_SYN && (syn_ack || key.src_port < key.dst_port) {
+        key.reversed()
+    } else {
+        key
+    }
+}
+
+/// 一个客户端/服务端组合的统计
+#[derive(Default)]
+struct PairStats {
+    connections: u64,
+    complete: u64,
+    incomplete: u64,
+    mid_stream: u64,
+}
+
+/// 报告TCP连接中抓到完整三次握手的比例，按客户端和服务端统计
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+///
+/// # 说明
+/// 客户端和服务端由连接的第一个包推断 (见 [`client_to_server`])，已出现FIN或RST的连接上再次出现SYN时开始新连接。
+/// 有状态重放 (以及状态防火墙、负载均衡) 需要完整的握手，中途开始的连接会被丢弃。
+pub fn check_handshake(input_path: &str) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut connections: Vec<TcpConnection> = Vec::new();
+    // 与方向无关的五元组 -> 当前连接
+    let mut active: HashMap<FlowKey, usize> = HashMap::new();
+    let mut packet_count = 0u64;
+    let mut tcp_packets = 0u64;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let (Some(key), Some(Transport::Tcp(tcp))) = (parsed.flow_key(), parsed.transport) else {
+            continue;
+        };
+        if tcp.offset + 14 > data.len() {
+            continue;
+        }
+        tcp_packets += 1;
+        let flags = data[tcp.offset + 13];
+        let syn_only = flags & (TCP_SYN | TCP_ACK) == TCP_SYN;
+        let syn_ack = flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK;
+
+        let index = match active.get(&key.canonical()) {
+            Some(&index) if !(connections[index].closed && syn_only) => index,
+            _ => {
+                connections.push(TcpConnection {
+                    key: client_to_server(key, flags),
+                    syn: false,
+                    syn_ack: false,
+                    ack: false,
+                    closed: false,
+                    packets: 0,
+                });
+                active.insert(key.canonical(), connections.len() - 1);
+                connections.len() - 1
+            }
+        };
+
+        let connection = &mut connections[index];
+        let from_client = connection.key == key;
+        connection.packets += 1;
+        if from_client && syn_only {
+            connection.syn = true;
+        } else if !from_client && syn_ack {
+            connection.syn_ack = true;
+        } else if from_client && connection.syn_ack && flags & (TCP_SYN | TCP_ACK) == TCP_ACK {
+            connection.ack = true;
+        }
+        if flags & (TCP_FIN | TCP_RST) != 0 {
+            connection.closed = true;
+        }
+    }
+
+    let count = |kind: HandshakeKind| connections.iter().filter(|connection| connection.kind() == kind).count() as u64;
+    let total = connections.len() as u64;
+    let complete = count(HandshakeKind::Complete);
+    let mid_stream = count(HandshakeKind::MidStream);
+    let incomplete = total - complete - mid_stream;
+    let complete_packets: u64 = connections
+        .iter()
+        .filter(|connection| connection.kind() == HandshakeKind::Complete)
+        .map(|connection| connection.packets)
+        .sum();
+    let percent = |part: u64, total: u64| if total > 0 { part as f64 * 100.0 / total as f64 } else { 0.0 };
+    let warning = |count: u64| (count > 0).then_some(Severity::Warning);
+
+    print_heading(&format!("TCP握手完整性: {}", input_path));
+    print_fields(&[
+        ("数据包数", format!("{} (TCP包: {})", packet_count, tcp_packets), None),
+        ("TCP连接数", total.to_string(), None),
+        ("完整握手", format!("{} ({:.2}%)", complete, percent(complete, total)), None),
+        (
+            "握手不完整",
+            format!(
+                "{} (只有SYN: {}，缺少ACK: {}，缺少SYN: {})",
+                incomplete,
+                count(HandshakeKind::SynOnly),
+                count(HandshakeKind::MissingAck),
+                count(HandshakeKind::MissingSyn)
+            ),
+            warning(incomplete),
+        ),
+        ("中途开始", format!("{} ({:.2}%)", mid_stream, percent(mid_stream, total)), warning(mid_stream)),
+        (
+            "完整握手连接的包",
+            format!("{} ({:.2}% 的TCP包)", complete_packets, percent(complete_packets, tcp_packets)),
+            None,
+        ),
+    ]);
+
+    // (客户端地址, 服务端地址, 服务端端口) -> 统计
+    let mut pairs: HashMap<(IpAddr, IpAddr, u16), PairStats> = HashMap::new();
+    for connection in &connections {
+        let stats = pairs.entry((connection.key.src, connection.key.dst, connection.key.dst_port)).or_default();
+        stats.connections += 1;
+        match connection.kind() {
+            HandshakeKind::Complete => stats.complete += 1,
+            HandshakeKind::MidStream => stats.mid_stream += 1,
+            _ => stats.incomplete += 1,
+        }
+    }
+    if !pairs.is_empty() {
+        let mut pairs: Vec<((IpAddr, IpAddr, u16), PairStats)> = pairs.into_iter().collect();
+        // 缺少握手的连接最多的在前
+        pairs.sort_by(|(a_key, a), (b_key, b)| {
+            (b.connections - b.complete)
+                .cmp(&(a.connections - a.complete))
+                .then(b.connections.cmp(&a.connections))
+                .then(a_key.cmp(b_key))
+        });
+        let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_PAIR_ROWS };
+        print_section("按客户端/服务端统计:");
+        let mut table = Table::new(&["客户端", "服务端", "连接数", "完整握手", "握手不完整", "中途开始"])
+            .right_align(&[2, 3, 4, 5]);
+        for ((client, server, port), stats) in pairs.iter().take(max_rows) {
+            table.add_row(vec![
+                client.to_string(),
+                format_endpoint(*server, *port),
+                stats.connections.to_string(),
+                stats.complete.to_string(),
+                stats.incomplete.to_string(),
+                stats.mid_stream.to_string(),
+            ]);
+        }
+        table.print();
+        if pairs.len() > max_rows {
+            println!("  ... 还有 {} 个客户端/服务端组合 (使用 --verbose 查看全部)", pairs.len() - max_rows);
+        }
+    }
+
+    print_separator();
+    if total == 0 {
+        print_verdict(Severity::Warning, "抓包中没有TCP连接");
+    } else if complete == total {
+        print_verdict(Severity::Ok, &format!("全部 {} 个TCP连接都有完整的三次握手，适合有状态重放", total));
+    } else {
+        print_verdict(
+            Severity::Warning,
+            &format!(
+                "{} 个TCP连接缺少完整的三次握手 ({:.2}%)，有状态重放时这些连接会被丢弃",
+                total - complete,
+                percent(total - complete, total)
+            ),
+        );
+    }
+    Ok(())
+}
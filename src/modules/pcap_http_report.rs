@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{FlowKey, Transport, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, format_timestamp_us, print_fields, print_heading, print_section, verbosity,
+};
+use crate::modules::pcap_tcp_reassembly::{Reassembled, TcpStream};
+
+/// 事务表中最多列出的事务数 (详细模式不限制)
+const MAX_TRANSACTION_ROWS: usize = 50;
+
+/// 消息头的最大长度，超过时不再当作HTTP解析
+const MAX_HEADER_LEN: usize = 64 * 1024;
+
+/// 表格中路径的最大显示长度 (CSV中为完整路径)
+const MAX_PATH_CHARS: usize = 60;
+
+const TCP_SYN: u8 = 0x02;
+const TCP_ACK: u8 = 0x10;
+
+/// 一个TCP连接，`key` 为第一个包的方向
+struct Connection {
+    key: FlowKey,
+    /// 发送SYN (不带ACK) 的一方所在的方向
+    client: Option<FlowKey>,
+    forward: TcpStream,
+    reverse: TcpStream,
+}
+
+/// 消息体的长度
+struct Body {
+    len: usize,
+    /// 消息结束 (下一个消息开始) 的偏移
+    end: usize,
+    /// 抓包中是否有完整的消息体
+    complete: bool,
+}
+
+/// 解析出的一个HTTP消息
+struct Message {
+    /// 起始行按空格分成的三部分
+    start_line: [String; 3],
+    headers: Vec<(String, String)>,
+    /// 消息体起始偏移
+    body_start: usize,
+}
+
+impl Message {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    fn chunked(&self) -> bool {
+        self.header("Transfer-Encoding").is_some_and(|value| value.to_ascii_lowercase().contains("chunked"))
+    }
+
+    fn content_length(&self) -> Option<usize> {
+        self.header("Content-Length").and_then(|value| value.trim().parse().ok())
+    }
+}
+
+/// 一个HTTP事务 (请求及对应的响应)
+struct Transaction {
+    /// 请求第一个字节的到达时间
+    time_us: i64,
+    /// 客户端到服务端方向的五元组
+    key: FlowKey,
+    method: String,
+    host: String,
+    path: String,
+    request_size: usize,
+    response: Option<ResponseInfo>,
+}
+
+struct ResponseInfo {
+    status: u16,
+    size: usize,
+    complete: bool,
+    /// 从请求开始到响应最后一个字节的时间
+    duration_us: Option<i64>,
+}
+
+/// 汇总各连接的解析结果
+#[derive(Default)]
+struct Summary {
+    tcp_connections: usize,
+    http_connections: usize,
+    /// 有数据缺失 (没有抓到) 的HTTP连接
+    gap_connections: usize,
+    /// 消息头无法解析、提前停止解析的HTTP连接
+    unparsed_connections: usize,
+}
+
+/// 列出HTTP/1.x事务: 方法、主机、路径、状态码、消息体大小和耗时
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `csv_path`: 把全部事务导出到CSV文件 (None表示不导出)
+///
+/// # 说明
+/// 1. TCP连接的两个方向分别按序列号重组；发送SYN的一方为客户端，没有抓到握手时以请求行开头的方向为客户端
+/// 2. 消息体按 Content-Length 或分块编码划分，支持管道化的多个请求；响应按顺序与请求配对，1xx响应跳过
+/// 3. HEAD请求以及204、304响应没有消息体；响应没有长度信息时消息体持续到连接关闭
+/// 4. 耗时为请求第一个字节到响应最后一个字节的时间；抓包开始于连接中途、数据不以请求行开头的连接不解析
+pub fn http_report(input_path: &str, csv_path: Option<&str>) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut connections: Vec<Connection> = Vec::new();
+    let mut index: HashMap<FlowKey, usize> = HashMap::new();
+    let mut packet_count = 0u64;
+    let mut start_us = None;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = interface.timestamp_ns(packet.timestamp).div_euclid(1000) as i64;
+        start_us.get_or_insert(time_us);
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let (Some(key), Some(Transport::Tcp(tcp))) = (parsed.flow_key(), parsed.transport) else {
+            continue;
+        };
+        if data.len() < tcp.offset + 14 {
+            continue;
+        }
+        let connection_index = *index.entry(key.canonical()).or_insert_with(|| {
+            connections.push(Connection { key, client: None, forward: TcpStream::default(), reverse: TcpStream::default() });
+            connections.len() - 1
+        });
+        let connection = &mut connections[connection_index];
+        if data[tcp.offset + 13] & (TCP_SYN | TCP_ACK) == TCP_SYN {
+            connection.client = Some(key);
+        }
+        let stream = if connection.key == key { &mut connection.forward } else { &mut connection.reverse };
+        stream.add(data, &tcp, &data[parsed.payload_range(data.len())], time_us);
+    }
+
+    let mut summary = Summary { tcp_connections: connections.len(), ..Default::default() };
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for connection in &connections {
+        parse_connection(connection, &mut summary, &mut transactions);
+    }
+    transactions.sort_by_key(|transaction| transaction.time_us);
+    let start_us = start_us.unwrap_or(0);
+
+    if let Some(path) = csv_path {
+        write_csv(path, &transactions, start_us)?;
+        info!("已导出 {} 个HTTP事务到 {}", transactions.len(), path);
+    }
+    print_report(input_path, packet_count, &summary, &transactions, start_us);
+    Ok(())
+}
+
+/// 解析一个TCP连接中的HTTP事务
+fn parse_connection(connection: &Connection, summary: &mut Summary, transactions: &mut Vec<Transaction>) {
+    let forward = connection.forward.reassemble();
+    let reverse = connection.reverse.reassemble();
+    let (client_key, client, server) = match connection.client {
+        Some(key) if key == connection.key => (key, forward, reverse),
+        Some(key) => (key, reverse, forward),
+        None if is_request_line(&forward.data) => (connection.key, forward, reverse),
+        None => (connection.key.reversed(), reverse, forward),
+    };
+    if !is_request_line(&client.data) {
+        return;
+    }
+    summary.http_connections += 1;
+    if client.missing > 0 || server.missing > 0 {
+        summary.gap_connections += 1;
+    }
+
+    let mut unparsed = false;
+    let mut requests: Vec<(Message, usize, Body)> = Vec::new();
+    let mut offset = 0;
+    while offset < client.data.len() {
+        let Some(request) = parse_message(&client.data, offset).filter(|message| message.start_line[2].starts_with("HTTP/1."))
+        else {
+            unparsed = true;
+            break;
+        };
+        let body = request_body(&client.data, &request);
+        let start = offset;
+        offset = body.end;
+        requests.push((request, start, body));
+    }
+
+    let mut responses = parse_responses(&server, &requests, &mut unparsed).into_iter();
+    for (request, start, body) in &requests {
+        let [method, target, _] = &request.start_line;
+        let time_us = client.time_at(*start).unwrap_or(0);
+        let response = responses.next().map(|(status, response_body)| ResponseInfo {
+            status,
+            size: response_body.len,
+            complete: response_body.complete,
+            duration_us: response_body.end.checked_sub(1).and_then(|last| server.time_at(last)).map(|end_us| end_us - time_us),
+        });
+        transactions.push(Transaction {
+            time_us,
+            key: client_key,
+            method: method.clone(),
+            host: request.header("Host").unwrap_or("-").to_string(),
+            path: target.clone(),
+            request_size: body.len,
+            response,
+        });
+    }
+    if unparsed {
+        summary.unparsed_connections += 1;
+    }
+}
+
+/// 按顺序解析服务端方向的响应，与请求一一对应，返回 (状态码, 消息体)
+fn parse_responses(server: &Reassembled, requests: &[(Message, usize, Body)], unparsed: &mut bool) -> Vec<(u16, Body)> {
+    let data = &server.data;
+    let mut responses = Vec::new();
+    let mut offset = 0;
+    for (request, _, _) in requests {
+        let method = request.start_line[0].as_str();
+        let response = loop {
+            if offset >= data.len() {
+                break None;
+            }
+            let Some(response) = parse_message(data, offset).filter(|message| message.start_line[0].starts_with("HTTP/1.")) else {
+                *unparsed = true;
+                break None;
+            };
+            let Ok(status) = response.start_line[1].parse::<u16>() else {
+                *unparsed = true;
+                break None;
+            };
+            let body = response_body(data, &response, method, status);
+            offset = body.end;
+            // 101切换协议以外的1xx是临时响应，后面还有最终响应
+            if (100..200).contains(&status) && status != 101 {
+                continue;
+            }
+            break Some((status, body));
+        };
+        let Some(response) = response else {
+            break;
+        };
+        // 协议切换或隧道建立后，后续数据不再是HTTP
+        let switched = response.0 == 101 || (method == "CONNECT" && (200..300).contains(&response.0));
+        responses.push(response);
+        if switched {
+            break;
+        }
+    }
+    responses
+}
+
+/// 数据是否以HTTP/1.x请求行开头
+pub fn is_request_line(data: &[u8]) -> bool {
+    let line_end = find(&data[..data.len().min(MAX_HEADER_LEN)], b"\r\n").unwrap_or(data.len().min(MAX_HEADER_LEN));
+    let line = String::from_utf8_lossy(&data[..line_end]);
+    let mut parts = line.split(' ');
+    let method = parts.next().unwrap_or("");
+    !method.is_empty()
+        && method.bytes().all(|b| b.is_ascii_uppercase() || b == b'-')
+        && parts.next().is_some()
+        && parts.next().is_some_and(|version| version.starts_with("HTTP/1."))
+}
+
+/// 解析从 `offset` 开始的起始行和头部字段，消息头不完整或格式错误时返回None
+fn parse_message(data: &[u8], offset: usize) -> Option<Message> {
+    let window = &data[offset..data.len().min(offset + MAX_HEADER_LEN)];
+    let head_len = find(window, b"\r\n\r\n")?;
+    let head = String::from_utf8_lossy(&window[..head_len]);
+    let mut lines = head.split("\r\n");
+    let mut parts = lines.next()?.splitn(3, ' ');
+    let start_line = [parts.next()?.to_string(), parts.next()?.to_string(), parts.next().unwrap_or("").to_string()];
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    Some(Message { start_line, headers, body_start: offset + head_len + 4 })
+}
+
+/// 请求的消息体: 分块编码或 Content-Length，都没有时为空
+fn request_body(data: &[u8], request: &Message) -> Body {
+    if request.chunked() {
+        chunked_body(data, request.body_start)
+    } else {
+        fixed_body(data, request.body_start, request.content_length().unwrap_or(0))
+    }
+}
+
+/// 响应的消息体，没有长度信息时持续到连接关闭
+fn response_body(data: &[u8], response: &Message, method: &str, status: u16) -> Body {
+    if method == "HEAD" || (100..200).contains(&status) || status == 204 || status == 304 {
+        fixed_body(data, response.body_start, 0)
+    } else if response.chunked() {
+        chunked_body(data, response.body_start)
+    } else if let Some(len) = response.content_length() {
+        fixed_body(data, response.body_start, len)
+    } else {
+        Body { len: data.len() - response.body_start, end: data.len(), complete: true }
+    }
+}
+
+fn fixed_body(data: &[u8], start: usize, len: usize) -> Body {
+    match start.checked_add(len).filter(|&end| end <= data.len()) {
+        Some(end) => Body { len, end, complete: true },
+        None => Body { len: data.len() - start, end: data.len(), complete: false },
+    }
+}
+
+/// 解析分块编码的消息体，长度为各块数据之和；数据不完整时到数据末尾为止
+fn chunked_body(data: &[u8], start: usize) -> Body {
+    let mut len = 0;
+    let mut offset = start;
+    while let Some(line_len) = find(&data[offset..], b"\r\n") {
+        let line = String::from_utf8_lossy(&data[offset..offset + line_len]);
+        let Ok(size) = usize::from_str_radix(line.split(';').next().unwrap_or("").trim(), 16) else {
+            break;
+        };
+        offset += line_len + 2;
+        if size == 0 {
+            // 尾部字段，以空行结束
+            while let Some(line_len) = find(&data[offset..], b"\r\n") {
+                offset += line_len + 2;
+                if line_len == 0 {
+                    return Body { len, end: offset, complete: true };
+                }
+            }
+            break;
+        }
+        let Some(next) = offset.checked_add(size).and_then(|end| end.checked_add(2)).filter(|&next| next <= data.len()) else {
+            len += size.min(data.len() - offset);
+            break;
+        };
+        len += size;
+        offset = next;
+    }
+    Body { len, end: data.len(), complete: false }
+}
+
+fn find(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len()).position(|window| window == pattern)
+}
+
+fn format_duration(duration_us: Option<i64>) -> String {
+    duration_us.map_or_else(|| "-".to_string(), |us| format!("{:.3}", us as f64 / 1000.0))
+}
+
+fn print_report(input_path: &str, packet_count: u64, summary: &Summary, transactions: &[Transaction], start_us: i64) {
+    let responses: Vec<&ResponseInfo> = transactions.iter().filter_map(|transaction| transaction.response.as_ref()).collect();
+    let unanswered = transactions.len() - responses.len();
+    let incomplete = responses.iter().filter(|response| !response.complete).count();
+    let client_errors = responses.iter().filter(|response| (400..500).contains(&response.status)).count();
+    let server_errors = responses.iter().filter(|response| response.status >= 500).count();
+    let warning = |value: usize| (value > 0).then_some(Severity::Warning);
+
+    print_heading(&format!("HTTP事务: {}", input_path));
+    print_fields(&[
+        ("数据包数", packet_count.to_string(), None),
+        ("TCP连接数", summary.tcp_connections.to_string(), None),
+        ("HTTP连接数", summary.http_connections.to_string(), None),
+        ("HTTP事务数", transactions.len().to_string(), None),
+        ("没有响应的请求", unanswered.to_string(), warning(unanswered)),
+        ("响应不完整", incomplete.to_string(), warning(incomplete)),
+        ("客户端错误 (4xx)", client_errors.to_string(), warning(client_errors)),
+        ("服务端错误 (5xx)", server_errors.to_string(), (server_errors > 0).then_some(Severity::Error)),
+        ("有数据缺失的连接", summary.gap_connections.to_string(), warning(summary.gap_connections)),
+        ("无法完整解析的连接", summary.unparsed_connections.to_string(), warning(summary.unparsed_connections)),
+    ]);
+
+    if transactions.is_empty() {
+        return;
+    }
+    let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_TRANSACTION_ROWS };
+    print_section("HTTP事务 (按请求时间):");
+    let mut table = Table::new(&["时间", "五元组", "方法", "主机", "路径", "状态", "请求体", "响应体", "耗时(ms)"])
+        .right_align(&[5, 6, 7, 8]);
+    for transaction in transactions.iter().take(max_rows) {
+        let path = if transaction.path.chars().count() > MAX_PATH_CHARS {
+            format!("{}...", transaction.path.chars().take(MAX_PATH_CHARS).collect::<String>())
+        } else {
+            transaction.path.clone()
+        };
+        let response = transaction.response.as_ref();
+        table.add_row(vec![
+            format_timestamp_us(transaction.time_us, start_us),
+            transaction.key.to_string(),
+            transaction.method.clone(),
+            transaction.host.clone(),
+            path,
+            response.map_or_else(|| "-".to_string(), |response| response.status.to_string()),
+            transaction.request_size.to_string(),
+            response.map_or_else(|| "-".to_string(), |response| response.size.to_string()),
+            format_duration(response.and_then(|response| response.duration_us)),
+        ]);
+    }
+    table.print();
+    if transactions.len() > max_rows {
+        println!("  ... 还有 {} 个事务 (使用 --verbose 查看全部)", transactions.len() - max_rows);
+    }
+}
+
+/// CSV字段中含有逗号、引号或换行时加引号
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_csv(path: &str, transactions: &[Transaction], start_us: i64) -> Result<()> {
+    let mut writer = BufWriter::new(
+        File::create(Path::new(path)).map_err(|e| PcapError::WriteFailure(format!("无法创建CSV文件: {}: {}", path, e)))?,
+    );
+    writeln!(
+        writer,
+        "time,client,client_port,server,server_port,method,host,path,status,request_bytes,response_bytes,response_complete,duration_ms"
+    )?;
+    for transaction in transactions {
+        let response = transaction.response.as_ref();
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            format_timestamp_us(transaction.time_us, start_us),
+            transaction.key.src,
+            transaction.key.src_port,
+            transaction.key.dst,
+            transaction.key.dst_port,
+            csv_field(&transaction.method),
+            csv_field(&transaction.host),
+            csv_field(&transaction.path),
+            response.map_or_else(String::new, |response| response.status.to_string()),
+            transaction.request_size,
+            response.map_or_else(String::new, |response| response.size.to_string()),
+            response.map_or_else(String::new, |response| response.complete.to_string()),
+            response.and_then(|response| response.duration_us).map_or_else(String::new, |us| format!("{:.3}", us as f64 / 1000.0)),
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
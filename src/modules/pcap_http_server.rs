@@ -0,0 +1,516 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use log::{debug, error, info, warn};
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::io::{OutputFormat, output_format, resolve_output_format};
+use crate::modules::pcap_output::{Compression, compression};
+use crate::modules::pcap_pipeline::{PipelineStep, parse_pipeline, run_pipeline};
+
+/// 请求体大小上限 (流水线配置文本)
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// 请求行和所有请求头合计的大小上限
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+
+/// 请求头的个数上限
+const MAX_HEADER_COUNT: usize = 100;
+
+/// 处理连接的线程数
+const CONNECTION_WORKERS: usize = 16;
+
+/// 等待处理的连接数上限，超出时直接返回503
+const MAX_PENDING_CONNECTIONS: usize = 64;
+
+/// 排队等待执行的作业数上限，超出时返回503
+const MAX_QUEUED_JOBS: usize = 256;
+
+/// 连接的读写超时
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 请求行或请求头超出上限 (返回431)
+#[derive(Debug)]
+struct HeaderTooLarge(String);
+
+impl fmt::Display for HeaderTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for HeaderTooLarge {}
+
+/// 作业状态
+#[derive(Clone)]
+enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed(String),
+}
+
+impl JobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed(_) => "failed",
+        }
+    }
+}
+
+/// 服务端记录的单个作业
+#[derive(Clone)]
+struct Job {
+    input: String,
+    output: PathBuf,
+    content_type: &'static str,
+    steps: usize,
+    status: JobStatus,
+}
+
+/// 等待作业线程执行的作业
+struct QueuedJob {
+    id: u64,
+    input: String,
+    output: PathBuf,
+    steps: Vec<PipelineStep>,
+}
+
+/// 所有连接共享的服务状态
+struct ServerState {
+    work_dir: PathBuf,
+    data_root: PathBuf,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    next_id: Mutex<u64>,
+    queue: SyncSender<QueuedJob>,
+}
+
+/// 解析后的HTTP请求
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// 以HTTP服务方式提供工具箱操作
+///
+/// # 参数
+/// - `listen_addr`: 监听地址，例如 `127.0.0.1:8080`
+/// - `work_dir`: 作业结果存放目录
+/// - `data_root`: 作业输入文件所在的目录，`input` 为相对路径时相对于该目录，不在该目录下的文件被拒绝
+/// - `job_workers`: 同时执行的作业数，其余作业保持 `queued` 状态排队
+///
+/// # 接口
+/// - `POST /jobs?input=<数据目录下的文件路径>`: 请求体为流水线配置 (与 `watch` 相同的格式)，返回作业ID
+/// - `GET /jobs`: 列出所有作业
+/// - `GET /jobs/<id>`: 查询作业状态
+/// - `GET /jobs/<id>/result`: 下载作业输出文件
+///
+/// 作业输出的格式和压缩方式与命令行相同，由 `--output-format` 和 `--compress` 决定，结果文件名带相应的扩展名。
+/// 连接由固定数量的线程处理，每个连接设置读写超时；等待处理的连接或排队的作业超出上限时返回503。
+pub fn serve(listen_addr: &str, work_dir: &str, data_root: &str, job_workers: usize) -> Result<()> {
+    if job_workers == 0 {
+        return Err(PcapError::InvalidArgument("作业线程数必须大于0".to_string()).into());
+    }
+
+    fs::create_dir_all(work_dir)
+        .with_context(|| format!("无法创建工作目录: {}", work_dir))?;
+    let data_root = fs::canonicalize(data_root)
+        .with_context(|| format!("无法打开数据目录: {}", data_root))?;
+
+    let listener = TcpListener::bind(listen_addr)
+        .with_context(|| format!("无法监听地址: {}", listen_addr))?;
+
+    let (job_sender, job_receiver) = mpsc::sync_channel(MAX_QUEUED_JOBS);
+    let state = Arc::new(ServerState {
+        work_dir: PathBuf::from(work_dir),
+        data_root,
+        jobs: Mutex::new(BTreeMap::new()),
+        next_id: Mutex::new(1),
+        queue: job_sender,
+    });
+
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+    for _ in 0..job_workers {
+        let state = Arc::clone(&state);
+        let receiver = Arc::clone(&job_receiver);
+        thread::spawn(move || job_worker(&state, &receiver));
+    }
+
+    let (connection_sender, connection_receiver) = mpsc::sync_channel::<TcpStream>(MAX_PENDING_CONNECTIONS);
+    let connection_receiver = Arc::new(Mutex::new(connection_receiver));
+    for _ in 0..CONNECTION_WORKERS {
+        let state = Arc::clone(&state);
+        let receiver = Arc::clone(&connection_receiver);
+        thread::spawn(move || connection_worker(&state, &receiver));
+    }
+
+    info!(
+        "HTTP服务已启动: http://{} (工作目录: {}，数据目录: {}，{} 个作业线程)",
+        listen_addr, work_dir, state.data_root.display(), job_workers
+    );
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("接受连接失败: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = stream
+            .set_read_timeout(Some(IO_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        {
+            warn!("设置连接超时失败: {}", e);
+            continue;
+        }
+
+        match connection_sender.try_send(stream) {
+            Ok(()) => {}
+            Err(TrySendError::Full(mut stream)) => {
+                warn!("等待处理的连接超过 {} 个，拒绝新连接", MAX_PENDING_CONNECTIONS);
+                let _ = write_json(&mut stream, 503, &error_json("服务繁忙，请稍后重试"));
+            }
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// 连接线程: 依次处理队列中的连接
+fn connection_worker(state: &Arc<ServerState>, receiver: &Mutex<Receiver<TcpStream>>) {
+    loop {
+        let stream = match receiver.lock().unwrap().recv() {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+        if let Err(e) = handle_connection(stream, state) {
+            debug!("处理连接失败: {:#}", e);
+        }
+    }
+}
+
+/// 作业线程: 依次执行队列中的作业
+fn job_worker(state: &Arc<ServerState>, receiver: &Mutex<Receiver<QueuedJob>>) {
+    loop {
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+
+        set_status(state, job.id, JobStatus::Running);
+        let output = job.output.to_string_lossy();
+        let status = match run_pipeline(&job.steps, &job.input, &output, resolve_output_format(&output), None) {
+            Ok(()) => {
+                info!("作业 #{} 完成", job.id);
+                JobStatus::Done
+            }
+            Err(e) => {
+                error!("作业 #{} 失败: {:#}", job.id, e);
+                JobStatus::Failed(format!("{:#}", e))
+            }
+        };
+        set_status(state, job.id, status);
+    }
+}
+
+/// 处理单个HTTP连接 (每个连接只处理一个请求)
+fn handle_connection(mut stream: TcpStream, state: &Arc<ServerState>) -> Result<()> {
+    let request = match read_request(&mut stream) {
+        Ok(request) => request,
+        Err(e) => {
+            let code = if e.is::<HeaderTooLarge>() { 431 } else { 400 };
+            return write_json(&mut stream, code, &error_json(&format!("{:#}", e)));
+        }
+    };
+
+    debug!("{} {}", request.method, request.path);
+
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["jobs"]) => submit_job(&mut stream, state, &request),
+        ("GET", ["jobs"]) => list_jobs(&mut stream, state),
+        ("GET", ["jobs", id]) => match find_job(state, id) {
+            Some((id, job)) => write_json(&mut stream, 200, &job_json(id, &job)),
+            None => write_json(&mut stream, 404, &error_json("作业不存在")),
+        },
+        ("GET", ["jobs", id, "result"]) => match find_job(state, id) {
+            Some((_, job)) => send_result(&mut stream, &job),
+            None => write_json(&mut stream, 404, &error_json("作业不存在")),
+        },
+        _ => write_json(&mut stream, 404, &error_json("未知的接口")),
+    }
+}
+
+/// 提交新作业，放入队列等待作业线程执行
+fn submit_job(stream: &mut TcpStream, state: &Arc<ServerState>, request: &HttpRequest) -> Result<()> {
+    let input = match request.query.iter().find(|(k, _)| k == "input") {
+        Some((_, input)) => input.clone(),
+        None => return write_json(stream, 400, &error_json("缺少 input 参数")),
+    };
+    let input_path = match fs::canonicalize(state.data_root.join(&input)) {
+        Ok(path) if path.is_file() => path,
+        _ => return write_json(stream, 400, &error_json(&format!("输入文件不存在: {}", input))),
+    };
+    if !input_path.starts_with(&state.data_root) {
+        return write_json(stream, 403, &error_json(&format!("输入文件不在数据目录下: {}", input)));
+    }
+
+    let body = String::from_utf8_lossy(&request.body);
+    let steps: Vec<PipelineStep> = match parse_pipeline(&body, "请求体") {
+        Ok(steps) => steps,
+        Err(e) => return write_json(stream, 400, &error_json(&format!("{:#}", e))),
+    };
+
+    let id = {
+        let mut next_id = state.next_id.lock().unwrap();
+        let id = *next_id;
+        *next_id += 1;
+        id
+    };
+
+    let extension = match output_format() {
+        OutputFormat::Pcapng => "pcapng",
+        OutputFormat::Auto | OutputFormat::Pcap => "pcap",
+    };
+    let output = state.work_dir.join(format!("job-{}.{}{}", id, extension, compression().suffix()));
+    let content_type = match compression() {
+        Compression::Gzip(_) => "application/gzip",
+        Compression::Zstd(_) => "application/zstd",
+        Compression::None if extension == "pcapng" => "application/x-pcapng",
+        Compression::None => "application/vnd.tcpdump.pcap",
+    };
+    let job = Job {
+        input: input.clone(),
+        output: output.clone(),
+        content_type,
+        steps: steps.len(),
+        status: JobStatus::Queued,
+    };
+    state.jobs.lock().unwrap().insert(id, job);
+
+    let step_count = steps.len();
+    match state.queue.try_send(QueuedJob { id, input: input_path.to_string_lossy().into_owned(), output, steps }) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+            state.jobs.lock().unwrap().remove(&id);
+            return write_json(stream, 503, &error_json(&format!("排队的作业超过 {} 个，请稍后重试", MAX_QUEUED_JOBS)));
+        }
+    }
+    info!("收到作业 #{}: {} ({} 个步骤)", id, input, step_count);
+
+    write_json(stream, 202, &job_json(id, &state.jobs.lock().unwrap()[&id]))
+}
+
+/// 列出所有作业
+fn list_jobs(stream: &mut TcpStream, state: &Arc<ServerState>) -> Result<()> {
+    let jobs = state.jobs.lock().unwrap();
+    let items: Vec<String> = jobs.iter().map(|(id, job)| job_json(*id, job)).collect();
+    write_json(stream, 200, &format!("[{}]", items.join(",")))
+}
+
+/// 下载作业结果
+fn send_result(stream: &mut TcpStream, job: &Job) -> Result<()> {
+    match &job.status {
+        JobStatus::Done => {}
+        JobStatus::Failed(_) => return write_json(stream, 409, &error_json("作业执行失败，没有结果")),
+        _ => return write_json(stream, 409, &error_json("作业尚未完成")),
+    }
+
+    let mut file = File::open(&job.output)
+        .with_context(|| format!("无法打开结果文件: {}", job.output.display()))?;
+    let size = file.metadata()?.len();
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Content-Disposition: attachment; filename=\"{}\"\r\nConnection: close\r\n\r\n",
+        job.content_type,
+        size,
+        job.output.file_name().unwrap_or_default().to_string_lossy()
+    )?;
+    io::copy(&mut file, stream)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+fn find_job(state: &Arc<ServerState>, id: &str) -> Option<(u64, Job)> {
+    let id: u64 = id.parse().ok()?;
+    state.jobs.lock().unwrap().get(&id).map(|job| (id, job.clone()))
+}
+
+fn set_status(state: &Arc<ServerState>, id: u64, status: JobStatus) {
+    if let Some(job) = state.jobs.lock().unwrap().get_mut(&id) {
+        job.status = status;
+    }
+}
+
+/// 读取请求行、请求头和请求体
+///
+/// 请求行和请求头合计最多读取 `MAX_HEADER_SIZE` 字节、`MAX_HEADER_COUNT` 个请求头，超出时返回 [`HeaderTooLarge`]。
+fn read_request(stream: &mut TcpStream) -> Result<HttpRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut head = reader.by_ref().take(MAX_HEADER_SIZE as u64);
+    let mut read_head_line = |line: &mut String| -> Result<usize> {
+        let len = head.read_line(line)?;
+        // 读满上限时最后一行不完整，或者还没有读到请求头后的空行
+        if head.limit() == 0 && (len == 0 || !line.ends_with('\n')) {
+            anyhow::bail!(HeaderTooLarge(format!("请求头超过 {} 字节", MAX_HEADER_SIZE)));
+        }
+        Ok(len)
+    };
+
+    let mut request_line = String::new();
+    read_head_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().context("请求行为空")?.to_string();
+    let target = parts.next().context("请求行缺少路径")?;
+
+    let mut content_length = 0usize;
+    let mut header_count = 0usize;
+    loop {
+        let mut line = String::new();
+        if read_head_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        header_count += 1;
+        if header_count > MAX_HEADER_COUNT {
+            anyhow::bail!(HeaderTooLarge(format!("请求头超过 {} 个", MAX_HEADER_COUNT)));
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().context("Content-Length 无效")?;
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        anyhow::bail!("请求体过大: {} 字节", content_length);
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, parse_query(query)),
+        None => (target, Vec::new()),
+    };
+
+    Ok(HttpRequest {
+        method,
+        path: percent_decode(path),
+        query,
+        body,
+    })
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// URL百分号解码 (`+` 视为空格)
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| (b as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        out.push((high * 16 + low) as u8);
+                        i += 3;
+                        continue;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            b'+' => out.push(b' '),
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn write_json(stream: &mut TcpStream, code: u16, body: &str) -> Result<()> {
+    let reason = match code {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn job_json(id: u64, job: &Job) -> String {
+    let error = match &job.status {
+        JobStatus::Failed(message) => format!(",\"error\":\"{}\"", json_escape(message)),
+        _ => String::new(),
+    };
+    format!(
+        "{{\"id\":{},\"status\":\"{}\",\"input\":\"{}\",\"steps\":{}{}}}",
+        id,
+        job.status.as_str(),
+        json_escape(&job.input),
+        job.steps,
+        error
+    )
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}", json_escape(message))
+}
+
+fn json_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,210 @@
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use pcap_file::pcap_header::{Endianness, PcapHeader};
+use pcap_file::{Packet, PcapReader};
+use seahash::SeaHasher;
+use anyhow::{Context, Result};
+use log::{info, warn};
+
+use crate::modules::pcap_error::{PcapError, check_fully_read};
+use crate::modules::pcap_input::is_gzip_file;
+use crate::modules::pcap_packet_parser::{FlowKey, ip_octets, parse_packet};
+use crate::modules::pcap_pcapng::is_pcapng_file;
+
+/// 索引文件魔数
+const INDEX_MAGIC: &[u8; 8] = b"PCAPIDX1";
+
+/// PCAP全局头长度
+const PCAP_HEADER_LEN: u64 = 24;
+
+/// 每条索引记录的长度
+const ENTRY_LEN: usize = 28;
+
+/// 单个数据包的索引记录
+#[derive(Debug, Clone, Copy)]
+pub struct IndexEntry {
+    /// 包记录头在文件中的偏移
+    pub offset: u64,
+    pub ts_sec: u32,
+    pub ts_usec: u32,
+    pub incl_len: u32,
+    /// 规范化五元组的哈希 (非IP包为0)
+    pub flow_hash: u64,
+}
+
+/// 加载到内存中的索引
+pub struct PcapIndex {
+    pub header: PcapHeader,
+    pub entries: Vec<IndexEntry>,
+}
+
+/// 索引文件路径 (`<文件名>.idx`)
+pub fn index_path(input_path: &str) -> String {
+    format!("{}.idx", input_path)
+}
+
+/// 规范化五元组的哈希 (与方向无关)
+pub fn flow_hash(key: &FlowKey) -> u64 {
+    let key = key.canonical();
+    let mut hasher = SeaHasher::new();
+    hasher.write_u8(key.protocol);
+    hasher.write(&ip_octets(key.src));
+    hasher.write(&ip_octets(key.dst));
+    hasher.write_u16(key.src_port);
+    hasher.write_u16(key.dst_port);
+    // 保留0作为"非IP包"
+    hasher.finish().max(1)
+}
+
+/// 为PCAP文件生成索引
+///
+/// # 参数
+/// - `input_path`: 输入PCAP文件路径
+///
+/// # 功能
+/// 1. 记录每个包的文件偏移、时间戳、长度和流哈希
+/// 2. 索引写入 `<文件名>.idx`，并记录源文件大小和修改时间用于检测过期
+/// 3. 只支持PCAP文件 (pcapng文件的 `extract`、`show` 顺序扫描)
+/// 4. `split` 不使用索引: 按接口拆分只对pcapng有意义，PCAP文件只有一个接口，需要顺序读完整个文件
+pub fn build_index(input_path: &str) -> Result<()> {
+    // 索引记录的是PCAP记录的文件偏移
+    if is_pcapng_file(input_path)? {
+        anyhow::bail!(PcapError::InvalidArgument(format!("索引只支持PCAP文件，不支持pcapng: {}", input_path)));
+    }
+    if is_gzip_file(input_path) {
+        anyhow::bail!(PcapError::InvalidArgument(format!("索引只支持未压缩的PCAP文件: {}", input_path)));
+    }
+    let in_file = File::open(Path::new(input_path))
+        .map_err(|e| PcapError::open_failed(input_path, e))?;
+    let (size, mtime) = file_signature(&in_file)?;
+    let mut pcap_reader = PcapReader::new(BufReader::new(in_file))
+        .map_err(PcapError::invalid_pcap)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+
+    let mut entries = Vec::new();
+    let mut offset = PCAP_HEADER_LEN;
+    for packet in pcap_reader.by_ref() {
+        let flow_hash = parse_packet(&packet.data, linktype)
+            .flow_key()
+            .map(|key| flow_hash(&key))
+            .unwrap_or(0);
+        entries.push(IndexEntry {
+            offset,
+            ts_sec: packet.header.ts_sec,
+            ts_usec: packet.header.ts_usec,
+            incl_len: packet.header.incl_len,
+            flow_hash,
+        });
+        offset += 16 + packet.header.incl_len as u64;
+    }
+    // 截断的文件末尾不完整的包不能建立索引
+    check_fully_read(input_path, offset)?;
+
+    let output_path = index_path(input_path);
+    let out_file = File::create(&output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("无法创建索引文件: {}: {}", output_path, e)))?;
+    let mut writer = BufWriter::new(out_file);
+    writer.write_all(INDEX_MAGIC)?;
+    writer.write_all(&size.to_le_bytes())?;
+    writer.write_all(&mtime.to_le_bytes())?;
+    writer.write_all(&(entries.len() as u64).to_le_bytes())?;
+    for entry in &entries {
+        writer.write_all(&entry.offset.to_le_bytes())?;
+        writer.write_all(&entry.ts_sec.to_le_bytes())?;
+        writer.write_all(&entry.ts_usec.to_le_bytes())?;
+        writer.write_all(&entry.incl_len.to_le_bytes())?;
+        writer.write_all(&entry.flow_hash.to_le_bytes())?;
+    }
+    writer.flush()
+        .with_context(|| format!("写入索引文件失败: {}", output_path))?;
+
+    info!("成功生成索引文件: {} ({} 个数据包)", output_path, entries.len());
+
+    Ok(())
+}
+
+/// 加载索引；索引不存在、损坏或已过期时返回None
+pub fn load_index(input_path: &str) -> Result<Option<PcapIndex>> {
+    let path = index_path(input_path);
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(_) => return Ok(None),
+    };
+
+    let in_file = File::open(Path::new(input_path))
+        .map_err(|e| PcapError::open_failed(input_path, e))?;
+    let (size, mtime) = file_signature(&in_file)?;
+
+    let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    if data.len() < 32 || &data[0..8] != INDEX_MAGIC {
+        warn!("索引文件格式无效，忽略: {}", path);
+        return Ok(None);
+    }
+    if read_u64(8) != size || read_u64(16) != mtime {
+        warn!("索引文件已过期，忽略: {} (请重新执行 index)", path);
+        return Ok(None);
+    }
+    let count = read_u64(24) as usize;
+    if data.len() != 32 + count * ENTRY_LEN {
+        warn!("索引文件长度不匹配，忽略: {}", path);
+        return Ok(None);
+    }
+
+    let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let entries = (0..count)
+        .map(|i| {
+            let base = 32 + i * ENTRY_LEN;
+            IndexEntry {
+                offset: read_u64(base),
+                ts_sec: read_u32(base + 8),
+                ts_usec: read_u32(base + 12),
+                incl_len: read_u32(base + 16),
+                flow_hash: read_u64(base + 20),
+            }
+        })
+        .collect();
+
+    let header = PcapHeader::from_reader(&mut BufReader::new(in_file))
+        .map_err(PcapError::invalid_pcap)?;
+
+    Ok(Some(PcapIndex { header, entries }))
+}
+
+/// 读取文件中指定偏移处的数据包
+pub fn read_packet_at(file: &mut File, header: &PcapHeader, offset: u64) -> Result<Packet<'static>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut record = [0u8; 16];
+    file.read_exact(&mut record)
+        .with_context(|| format!("读取偏移 {} 处的包头失败", offset))?;
+
+    let field = |i: usize| {
+        let bytes: [u8; 4] = record[i * 4..i * 4 + 4].try_into().unwrap();
+        match header.endianness() {
+            Endianness::Big => u32::from_be_bytes(bytes),
+            Endianness::Little => u32::from_le_bytes(bytes),
+        }
+    };
+    let (ts_sec, ts_usec, incl_len, orig_len) = (field(0), field(1), field(2), field(3));
+
+    let mut data = vec![0u8; incl_len as usize];
+    file.read_exact(&mut data)
+        .with_context(|| format!("读取偏移 {} 处的包数据失败", offset))?;
+
+    let mut packet = Packet::new_owned(ts_sec, ts_usec, incl_len, data);
+    packet.header.orig_len = orig_len;
+    Ok(packet)
+}
+
+/// 源文件的大小和修改时间 (纳秒)
+fn file_signature(file: &File) -> Result<(u64, u64)> {
+    let metadata = file.metadata()?;
+    let mtime = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    Ok((metadata.len(), mtime))
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_input::is_gzip_file;
+use crate::modules::pcap_interval_stats::CaptureDrops;
+use crate::modules::pcap_packet_parser::linktype_name;
+use crate::modules::pcap_pcapng::{CaptureReader, IF_NAME, PcapngBlock, option_str};
+use crate::modules::pcap_report::{
+    Severity, Table, format_timestamp_ns, format_timestamp_us, print_fields, print_heading, print_section,
+};
+
+/// 一个接口的概要
+struct InterfaceSummary {
+    name: Option<String>,
+    linktype: u32,
+    snaplen: u32,
+    units_per_second: u64,
+    packet_count: u64,
+}
+
+/// 输出抓包文件的概要 (与 capinfos 类似)
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+///
+/// # 输出
+/// 1. 文件格式、节数、包数、字节数 (原始长度和实际保存的长度)
+/// 2. 首包和末包时间 (格式由 `--time-format` 决定)、时长、平均包速率和比特率，以及时间戳倒退的包数
+/// 3. 每个接口的链路类型、截断长度、时间戳精度和包数；多个节中的接口按出现顺序编号
+/// 4. pcapng接口统计块中的抓包丢包计数器 (与 `stats` 相同)
+pub fn pcap_info(input_path: &str) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let pcapng = matches!(reader, CaptureReader::Pcapng(_));
+    let mut drops = CaptureDrops::default();
+    let mut interfaces: Vec<InterfaceSummary> = Vec::new();
+    // 当前节的接口ID -> interfaces中的下标
+    let mut section_interfaces: Vec<usize> = Vec::new();
+    let mut sections = 0u64;
+    let mut packet_count = 0u64;
+    let mut bytes = 0u64;
+    let mut captured_bytes = 0u64;
+    let mut out_of_order = 0u64;
+    let mut first_ns: Option<i128> = None;
+    let mut last_ns = 0i128;
+    let mut earliest_ns = i128::MAX;
+    let mut latest_ns = i128::MIN;
+    while let Some(block) = reader.next_block()? {
+        match block {
+            PcapngBlock::Section(_) => {
+                sections += 1;
+                section_interfaces.clear();
+            }
+            PcapngBlock::Interface(interface) => {
+                section_interfaces.push(interfaces.len());
+                interfaces.push(InterfaceSummary {
+                    name: option_str(&interface.options, IF_NAME),
+                    linktype: interface.linktype as u32,
+                    snaplen: interface.snaplen,
+                    units_per_second: interface.units_per_second(),
+                    packet_count: 0,
+                });
+            }
+            PcapngBlock::Statistics(statistics) => drops.record(&reader, &statistics),
+            PcapngBlock::Packet(packet) => {
+                let (Some(interface), Some(&index)) = (
+                    reader.interface(packet.interface_id),
+                    section_interfaces.get(packet.interface_id as usize),
+                ) else {
+                    anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+                };
+                let time_ns = interface.timestamp_ns(packet.timestamp);
+                interfaces[index].packet_count += 1;
+                packet_count += 1;
+                bytes += packet.orig_len as u64;
+                captured_bytes += packet.data.len() as u64;
+                if first_ns.is_some() && time_ns < last_ns {
+                    out_of_order += 1;
+                }
+                first_ns.get_or_insert(time_ns);
+                last_ns = time_ns;
+                earliest_ns = earliest_ns.min(time_ns);
+                latest_ns = latest_ns.max(time_ns);
+            }
+            PcapngBlock::Other { .. } => {}
+        }
+    }
+
+    print_heading(&format!("文件概要: {}", input_path));
+    let mut format = if pcapng { "pcapng".to_string() } else { "PCAP".to_string() };
+    if is_gzip_file(input_path) {
+        format.push_str(" (gzip压缩)");
+    }
+    let mut fields = vec![("格式", format, None)];
+    if pcapng {
+        fields.push(("节数", sections.to_string(), None));
+    }
+    fields.push(("包数", packet_count.to_string(), None));
+    fields.push(("字节数", format!("{} (保存 {})", bytes, captured_bytes), None));
+    if let Some(first_ns) = first_ns {
+        let duration_ns = latest_ns - earliest_ns;
+        let seconds = duration_ns as f64 / 1e9;
+        // 有精度高于微秒的接口时按纳秒显示
+        let nanosecond = interfaces.iter().any(|interface| interface.units_per_second > 1_000_000);
+        let timestamp = |time_ns: i128| {
+            if nanosecond {
+                format_timestamp_ns(time_ns, first_ns)
+            } else {
+                format_timestamp_us((time_ns / 1000) as i64, (first_ns / 1000) as i64)
+            }
+        };
+        fields.push(("首包时间", timestamp(earliest_ns), None));
+        fields.push(("末包时间", timestamp(latest_ns), None));
+        fields.push(("时长", format!("{:.*} 秒", if nanosecond { 9 } else { 6 }, seconds), None));
+        if duration_ns > 0 {
+            fields.push(("平均速率", format!("{:.1} 包/秒, {:.0} bit/s", packet_count as f64 / seconds, bytes as f64 * 8.0 / seconds), None));
+        }
+        let severity = (out_of_order > 0).then_some(Severity::Warning);
+        fields.push(("时间戳倒退的包", out_of_order.to_string(), severity));
+    }
+    print_fields(&fields);
+
+    print_section("接口:");
+    let mut table = Table::new(&["接口", "链路类型", "截断长度", "时间精度", "包数"]).right_align(&[2, 4]);
+    for (id, interface) in interfaces.iter().enumerate() {
+        table.add_row(vec![
+            interface.name.as_ref().map_or(id.to_string(), |name| format!("{} ({})", id, name)),
+            linktype_name(interface.linktype),
+            interface.snaplen.to_string(),
+            resolution_name(interface.units_per_second),
+            interface.packet_count.to_string(),
+        ]);
+    }
+    table.print();
+
+    drops.print();
+
+    Ok(())
+}
+
+/// 时间戳精度的显示名称
+fn resolution_name(units_per_second: u64) -> String {
+    match units_per_second {
+        1 => "秒".to_string(),
+        1_000 => "毫秒".to_string(),
+        1_000_000 => "微秒".to_string(),
+        1_000_000_000 => "纳秒".to_string(),
+        units => format!("1/{} 秒", units),
+    }
+}
@@ -0,0 +1,304 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use anyhow::Result;
+
+use crate::modules::pcap_error::{PcapError, check_fully_read};
+use crate::modules::pcap_gzip::{GZIP_MAGIC, GzipDecoder};
+
+/// 是否持续读取正在写入的输入 (`--follow`)
+static FOLLOW: AtomicBool = AtomicBool::new(false);
+/// 收到SIGINT/SIGTERM: 持续读取的输入按文件结束处理，实时抓包停止
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 解压失败的gzip输入 (路径, 错误)
+static CORRUPT_GZIP: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// 持续读取时等待新数据的轮询间隔
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 设置是否持续读取本地输入
+///
+/// 启用后读到文件末尾时等待新的数据，而不是结束 (类似 `tail -f`)，
+/// 可以读取仍在写入的文件或命名管道 (例如 dumpcap 的输出)。
+/// 收到SIGINT或SIGTERM时停止等待，命令按正常结束处理并写完输出。
+pub fn set_follow(enabled: bool) {
+    FOLLOW.store(enabled, Ordering::Relaxed);
+    if enabled {
+        install_stop_handler();
+    }
+}
+
+/// 是否持续读取本地输入
+pub fn follow() -> bool {
+    FOLLOW.load(Ordering::Relaxed)
+}
+
+/// 把SIGINT和SIGTERM改为请求停止 (由 [`stop_requested`] 查询)，再次收到时立即退出
+///
+/// 不设置SA_RESTART，阻塞在命名管道或套接字上的读取被信号中断后可以检查是否停止。
+pub fn install_stop_handler() {
+    // SAFETY: 信号处理函数只访问原子变量和调用_exit
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle_stop_signal as *const () as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+        libc::sigaction(libc::SIGTERM, &action, std::ptr::null_mut());
+    }
+}
+
+/// 是否已收到停止信号
+pub fn stop_requested() -> bool {
+    STOP_REQUESTED.load(Ordering::SeqCst)
+}
+
+extern "C" fn handle_stop_signal(_signal: libc::c_int) {
+    if STOP_REQUESTED.swap(true, Ordering::SeqCst) {
+        // SAFETY: _exit是异步信号安全的
+        unsafe { libc::_exit(130) };
+    }
+}
+
+/// 读取本地文件，文件末尾时等待新的数据，直到收到停止信号
+fn follow_read(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        if stop_requested() {
+            return Ok(0);
+        }
+        match file.read(buf) {
+            Ok(0) if !buf.is_empty() => thread::sleep(FOLLOW_POLL_INTERVAL),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            result => return result,
+        }
+    }
+}
+
+/// 输入文件: 本地文件，或以流的方式下载的远程地址；gzip压缩的输入边读取边解压
+pub enum Input {
+    File(File),
+    #[cfg(feature = "remote")]
+    Remote(remote::RemoteInput),
+    Gzip { path: String, decoder: Box<GzipDecoder<Input>> },
+}
+
+/// 是否为远程地址 (`http://`、`https://`、`s3://`)
+pub fn is_remote(path: &str) -> bool {
+    ["http://", "https://", "s3://"].iter().any(|scheme| path.starts_with(scheme))
+}
+
+/// 打开输入文件
+///
+/// 远程地址需要在编译时启用 `remote` 特性，边下载边读取，不在本地保存副本，
+/// 因此只能用于顺序读取一遍输入的命令。
+/// 以gzip魔数开头的输入 (与扩展名无关) 边读取边解压，同样只能顺序读取。
+pub fn open_input(path: &str) -> Result<Input> {
+    let input = if is_remote(path) {
+        #[cfg(feature = "remote")]
+        {
+            Input::Remote(remote::RemoteInput::open(path)?)
+        }
+        #[cfg(not(feature = "remote"))]
+        anyhow::bail!(PcapError::InvalidArgument(format!(
+            "不支持远程输入 (编译时未启用 remote 特性): {}",
+            path
+        )));
+    } else {
+        let file = File::open(Path::new(path))
+            .map_err(|e| PcapError::open_failed(path, e))?;
+        Input::File(file)
+    };
+    if input.starts_with(&GZIP_MAGIC) {
+        return Ok(Input::Gzip { path: path.to_string(), decoder: Box::new(GzipDecoder::new(input)) });
+    }
+    Ok(input)
+}
+
+/// 本地文件是否以gzip魔数开头 (命名管道等无法按位置读取的输入视为否)
+pub fn is_gzip_file(path: &str) -> bool {
+    File::open(Path::new(path)).is_ok_and(|file| Input::File(file).starts_with(&GZIP_MAGIC))
+}
+
+impl Input {
+    /// 输入是否以 `prefix` 开头 (不移动读取位置)
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        match self {
+            Input::File(file) => {
+                let mut head = vec![0u8; prefix.len()];
+                file.read_at(&mut head, 0).is_ok_and(|len| len == prefix.len()) && head == prefix
+            }
+            #[cfg(feature = "remote")]
+            Input::Remote(remote) => remote.starts_with(prefix),
+            Input::Gzip { .. } => false,
+        }
+    }
+
+    /// 压缩的输入解压后的字节数 (读完时为总长度)，未压缩的输入为None
+    pub fn decompressed_len(&self) -> Option<u64> {
+        match self {
+            Input::Gzip { decoder, .. } => Some(decoder.total_len()),
+            _ => None,
+        }
+    }
+
+    /// 检查PCAP读取器是否读完了输入 (压缩的输入与解压后的长度比较)
+    ///
+    /// 读取器遇到不完整的包时只是提前结束，`read_bytes` 为已读取的文件头和完整的包的字节数。
+    /// 持续读取 (`--follow`) 时文件仍在写入，不做检查。
+    pub fn check_read(&self, path: &str, read_bytes: u64) -> Result<(), PcapError> {
+        match self.decompressed_len() {
+            Some(len) if read_bytes < len => Err(PcapError::TruncatedFile(format!(
+                "输入文件被截断: {} (已读取 {} 字节/解压后总计 {} 字节)",
+                path, read_bytes, len
+            ))),
+            Some(_) => Ok(()),
+            None if FOLLOW.load(Ordering::Relaxed) => Ok(()),
+            None => check_fully_read(path, read_bytes),
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(file) if FOLLOW.load(Ordering::Relaxed) => follow_read(file, buf),
+            Input::File(file) => file.read(buf),
+            #[cfg(feature = "remote")]
+            Input::Remote(remote) => remote.read(buf),
+            Input::Gzip { path, decoder } => decoder.read(buf).inspect_err(|e| {
+                CORRUPT_GZIP.lock().unwrap().push((path.clone(), e.to_string()));
+            }),
+        }
+    }
+}
+
+/// 检查远程输入是否都已完整下载、gzip输入是否都已正确解压
+///
+/// PCAP读取器遇到读取错误时只是提前结束，无法与文件结束区分，
+/// 因此下载中断和解压错误记录下来，在命令结束时统一报错。
+pub fn check_inputs() -> Result<()> {
+    #[cfg(feature = "remote")]
+    if let Some(url) = remote::first_incomplete() {
+        anyhow::bail!(PcapError::TruncatedFile(format!("远程输入未完整下载: {}", url)));
+    }
+    if let Some((path, error)) = CORRUPT_GZIP.lock().unwrap().first() {
+        anyhow::bail!(PcapError::TruncatedFile(format!("gzip输入解压失败: {}: {}", path, error)));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "remote")]
+mod remote {
+    use std::io::{self, Read};
+    use std::process::{Child, ChildStdout, Command, Stdio};
+    use std::sync::Mutex;
+    use anyhow::Result;
+
+    use crate::modules::pcap_error::PcapError;
+
+    /// 下载中断的远程地址
+    static INCOMPLETE: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    pub fn first_incomplete() -> Option<String> {
+        INCOMPLETE.lock().unwrap().first().cloned()
+    }
+
+    /// 由下载进程输出到管道的远程文件
+    ///
+    /// `http://`、`https://` 由 `curl` 下载，`s3://` 由 `aws s3 cp` 下载 (使用AWS CLI的凭证配置)。
+    pub struct RemoteInput {
+        url: String,
+        child: Child,
+        stdout: ChildStdout,
+        /// 打开时预读的数据，用于在打开时就发现地址无效等错误
+        head: Vec<u8>,
+        head_pos: usize,
+        finished: bool,
+    }
+
+    impl RemoteInput {
+        pub fn open(url: &str) -> Result<RemoteInput> {
+            let (program, mut command) = if url.starts_with("s3://") {
+                let mut command = Command::new("aws");
+                command.args(["s3", "cp", "--only-show-errors", url, "-"]);
+                ("aws", command)
+            } else {
+                let mut command = Command::new("curl");
+                command.args(["--silent", "--show-error", "--fail", "--location", url]);
+                ("curl", command)
+            };
+            let mut child = command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| PcapError::open_failed(url, io::Error::new(e.kind(), format!("无法启动 {}: {}", program, e))))?;
+            let stdout = child.stdout.take().expect("stdout为管道");
+
+            let mut input = RemoteInput {
+                url: url.to_string(),
+                child,
+                stdout,
+                head: Vec::new(),
+                head_pos: 0,
+                finished: false,
+            };
+            let mut head = vec![0; 64 * 1024];
+            let len = input.read_pipe(&mut head)
+                .map_err(|e| PcapError::open_failed(url, e))?;
+            head.truncate(len);
+            input.head = head;
+            Ok(input)
+        }
+
+        /// 从管道读取；管道结束时等待下载进程，退出状态不为0时记录为下载中断
+        fn read_pipe(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.finished {
+                return Ok(0);
+            }
+            let len = self.stdout.read(buf)?;
+            if len == 0 && !buf.is_empty() {
+                self.finished = true;
+                let status = self.child.wait()?;
+                if !status.success() {
+                    INCOMPLETE.lock().unwrap().push(self.url.clone());
+                    return Err(io::Error::other(format!("下载失败 ({}): {}", status, self.url)));
+                }
+            }
+            Ok(len)
+        }
+    }
+
+    impl RemoteInput {
+        /// 预读的数据是否以 `prefix` 开头
+        pub fn starts_with(&self, prefix: &[u8]) -> bool {
+            self.head.starts_with(prefix)
+        }
+    }
+
+    impl Read for RemoteInput {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.head_pos < self.head.len() {
+                let len = buf.len().min(self.head.len() - self.head_pos);
+                buf[..len].copy_from_slice(&self.head[self.head_pos..self.head_pos + len]);
+                self.head_pos += len;
+                return Ok(len);
+            }
+            self.read_pipe(buf)
+        }
+    }
+
+    impl Drop for RemoteInput {
+        fn drop(&mut self) {
+            // 未读完就结束时停止下载
+            if !self.finished {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+            }
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use anyhow::{Context, Result};
+use log::warn;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_filter::Filter;
+use crate::modules::pcap_packet_parser::parse_packet;
+use crate::modules::pcap_pcapng::{
+    CaptureReader, IF_NAME, ISB_IFDROP, ISB_IFRECV, ISB_OSDROP, ISB_USRDELIV, InterfaceStatistics, PcapngBlock, option_str,
+};
+use crate::modules::pcap_report::{
+    Severity, Table, TimeFormat, format_timestamp_us, print_fields, print_heading, print_section, print_separator, print_verdict,
+    time_format,
+};
+
+/// 解析统计区间，如 `1s`、`100ms`、`500us`、`1m`，不带单位时为秒 (可以是小数)，返回微秒
+///
+/// 0表示整个文件为一个区间。
+pub fn parse_interval(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let scale = match unit {
+        "" | "s" => 1_000_000.0,
+        "ms" => 1_000.0,
+        "us" => 1.0,
+        "m" => 60_000_000.0,
+        _ => return Err(format!("无效的时间单位: {} (可用: s、ms、us、m)", unit)),
+    };
+    let value: f64 = number.parse().map_err(|_| format!("无效的统计区间: {}", text))?;
+    let interval = value * scale;
+    if !interval.is_finite() || interval < 0.0 || (interval > 0.0 && interval < 1.0) {
+        return Err(format!("统计区间必须为0或不小于1微秒: {}", text));
+    }
+    Ok(interval.round() as u64)
+}
+
+/// 一个统计列: 过滤表达式为None时统计全部包
+struct Column {
+    filter: Option<Filter>,
+    name: String,
+}
+
+/// 每个区间、每列的包数和字节数
+struct IntervalCounts {
+    /// 区间长度 (微秒)，0表示整个文件
+    interval_us: u64,
+    /// 第一个包的时间 (Unix时间，微秒)
+    start_us: i64,
+    /// 最后一个包相对于第一个包的时间 (微秒)
+    duration_us: u64,
+    /// rows[区间][列] = (包数, 字节数)
+    rows: Vec<Vec<(u64, u64)>>,
+    /// pcapng接口统计块中的丢包计数
+    drops: CaptureDrops,
+}
+
+/// 一个接口最后一个统计块中的计数器 (计数器是累计值，后面的块覆盖前面的)
+struct InterfaceDrops {
+    name: Option<String>,
+    /// isb_ifrecv: 接口收到的包数
+    received: Option<u64>,
+    /// isb_ifdrop: 接口 (网卡驱动) 因缓冲区不足等原因丢弃的包数
+    interface_drops: Option<u64>,
+    /// isb_osdrop: 操作系统 (抓包缓冲区) 丢弃的包数
+    os_drops: Option<u64>,
+    /// isb_usrdeliv: 交付给抓包程序的包数
+    delivered: Option<u64>,
+}
+
+/// pcapng接口统计块 (ISB) 报告的抓包丢包，PCAP文件没有这些信息
+#[derive(Default)]
+pub struct CaptureDrops {
+    interfaces: BTreeMap<u32, InterfaceDrops>,
+}
+
+impl CaptureDrops {
+    pub fn record(&mut self, reader: &CaptureReader, statistics: &InterfaceStatistics) {
+        let name = reader
+            .interface(statistics.interface_id)
+            .and_then(|interface| option_str(&interface.options, IF_NAME));
+        self.interfaces.insert(statistics.interface_id, InterfaceDrops {
+            name,
+            received: statistics.counter(ISB_IFRECV),
+            interface_drops: statistics.counter(ISB_IFDROP),
+            os_drops: statistics.counter(ISB_OSDROP),
+            delivered: statistics.counter(ISB_USRDELIV),
+        });
+    }
+
+    /// 所有接口丢弃的包数合计
+    pub fn total(&self) -> u64 {
+        self.interfaces
+            .values()
+            .map(|drops| drops.interface_drops.unwrap_or(0) + drops.os_drops.unwrap_or(0))
+            .sum()
+    }
+
+    /// 输出各接口的计数器，有丢包时给出警告；没有接口统计块时不输出
+    pub fn print(&self) {
+        if self.interfaces.is_empty() {
+            return;
+        }
+        print_section("抓包丢包 (pcapng接口统计块):");
+        let mut table = Table::new(&["接口", "收到", "接口丢弃", "系统丢弃", "交付", "丢包率"]).right_align(&[1, 2, 3, 4, 5]);
+        let count = |value: Option<u64>| value.map_or("-".to_string(), |value| value.to_string());
+        for (id, drops) in &self.interfaces {
+            let dropped = drops.interface_drops.unwrap_or(0) + drops.os_drops.unwrap_or(0);
+            let rate = match drops.received {
+                Some(received) if received > 0 => format!("{:.2}%", dropped as f64 * 100.0 / received as f64),
+                _ => "-".to_string(),
+            };
+            table.add_row(vec![
+                drops.name.as_ref().map_or(id.to_string(), |name| format!("{} ({})", id, name)),
+                count(drops.received),
+                count(drops.interface_drops),
+                count(drops.os_drops),
+                count(drops.delivered),
+                rate,
+            ]);
+        }
+        table.print();
+        let total = self.total();
+        if total > 0 {
+            print_separator();
+            print_verdict(
+                Severity::Warning,
+                &format!("抓包过程中丢失了 {} 个包，比较结果中的丢包可能来自抓包本身而不是网络", total),
+            );
+        }
+    }
+}
+
+/// 按固定时间区间统计包数和字节数
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `interval_us`: 区间长度 (微秒)，0表示整个文件为一个区间
+/// - `filters`: 每个过滤表达式统计一列，为空时只统计全部包
+/// - `wireshark_format`: 按 `tshark -q -z io,stat` 的格式输出
+///
+/// 区间以第一个包的时间为起点，时间早于第一个包的乱序包计入第一个区间；字节数为原始长度。
+pub fn pcap_interval_stats(input_path: &str, interval_us: u64, filters: &[String], wireshark_format: bool) -> Result<()> {
+    let columns: Vec<Column> = if filters.is_empty() {
+        vec![Column { filter: None, name: "Frames and bytes".to_string() }]
+    } else {
+        filters
+            .iter()
+            .map(|text| {
+                let filter = Filter::parse(text)
+                    .with_context(|| format!("无效的过滤表达式: {}", text))
+                    .map_err(|e| PcapError::InvalidArgument(format!("{:#}", e)))?;
+                Ok(Column { filter: Some(filter), name: text.clone() })
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let counts = count_intervals(input_path, interval_us, &columns)?;
+    if wireshark_format {
+        print_io_stat(&counts, &columns);
+        // tshark格式不能多出内容，丢包只写日志
+        let total = counts.drops.total();
+        if total > 0 {
+            warn!("pcapng接口统计块报告抓包过程中丢失了 {} 个包", total);
+        }
+    } else {
+        print_report(input_path, &counts, &columns);
+    }
+    Ok(())
+}
+
+/// 一个包间隔: 按间隔大小排序，间隔相同时先出现的较大 (排在前面，最后才被淘汰)
+#[derive(Debug, PartialEq, Eq)]
+struct Gap {
+    /// 间隔 (微秒)
+    gap_us: u64,
+    /// 间隔之后的包的序号
+    number: u64,
+    /// 间隔之前 (时间戳最晚) 的包的序号
+    previous: u64,
+    /// 间隔开始的时间 (微秒)
+    start_us: i64,
+}
+
+impl Ord for Gap {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.gap_us.cmp(&other.gap_us).then(other.number.cmp(&self.number))
+    }
+}
+
+impl PartialOrd for Gap {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 列出相邻两个包之间最大的时间间隔
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `top`: 列出的间隔个数
+///
+/// 按文件中的顺序计算每个包与此前最晚时间戳之间的间隔，时间戳倒退的乱序包不计间隔 (只统计个数)，
+/// 也不影响后面的包；间隔相同时先出现的排在前面。
+/// 用于找出抓包中断的位置，决定在哪里拆分文件。
+pub fn pcap_gap_report(input_path: &str, top: usize) -> Result<()> {
+    if top == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("--top 必须大于0".to_string()));
+    }
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut drops = CaptureDrops::default();
+    // 最小堆，只保留最大的top个间隔
+    let mut heap: BinaryHeap<Reverse<Gap>> = BinaryHeap::new();
+    let mut packet_count = 0u64;
+    let mut backwards = 0u64;
+    let mut first_us = None;
+    // 此前时间戳最晚的包: (时间, 序号)
+    let mut latest = (0i64, 0u64);
+    while let Some(block) = reader.next_block()? {
+        let packet = match block {
+            PcapngBlock::Packet(packet) => packet,
+            PcapngBlock::Statistics(statistics) => {
+                drops.record(&reader, &statistics);
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = (interface.timestamp_ns(packet.timestamp) / 1000) as i64;
+        packet_count += 1;
+        if first_us.is_none() {
+            first_us = Some(time_us);
+        } else if time_us < latest.0 {
+            backwards += 1;
+            continue;
+        } else {
+            heap.push(Reverse(Gap {
+                gap_us: (time_us - latest.0) as u64,
+                number: packet_count,
+                previous: latest.1,
+                start_us: latest.0,
+            }));
+            if heap.len() > top {
+                heap.pop();
+            }
+        }
+        latest = (time_us, packet_count);
+    }
+    let Some(first_us) = first_us else {
+        anyhow::bail!(PcapError::EmptyCapture);
+    };
+    let mut gaps: Vec<Gap> = heap.into_iter().map(|Reverse(gap)| gap).collect();
+    gaps.sort_by(|a, b| b.cmp(a));
+
+    print_heading(&format!("最大包间隔: {}", input_path));
+    let duration_us = (latest.0 - first_us).max(0) as u64;
+    let mut fields = vec![
+        ("数据包数", packet_count.to_string(), None),
+        ("时长", format!("{} 秒", format_seconds(duration_us, 6)), None),
+    ];
+    if packet_count > 1 {
+        fields.push(("平均间隔", format!("{} 秒", format_seconds(duration_us / (packet_count - 1), 6)), None));
+    }
+    if backwards > 0 {
+        fields.push(("时间戳倒退", format!("{} 个包 (不计间隔)", backwards), None));
+    }
+    print_fields(&fields);
+    drops.print();
+    if gaps.is_empty() {
+        return Ok(());
+    }
+
+    print_section(&format!("最大的 {} 个间隔:", gaps.len()));
+    let mut table = Table::new(&["排名", "位置", "间隔 (秒)", "开始时间", "结束时间"]).right_align(&[0, 2]);
+    for (rank, gap) in gaps.iter().enumerate() {
+        table.add_row(vec![
+            (rank + 1).to_string(),
+            format!("#{} -> #{}", gap.previous, gap.number),
+            format_seconds(gap.gap_us, 6),
+            format_timestamp_us(gap.start_us, first_us),
+            format_timestamp_us(gap.start_us + gap.gap_us as i64, first_us),
+        ]);
+    }
+    table.print();
+    Ok(())
+}
+
+fn count_intervals(input_path: &str, interval_us: u64, columns: &[Column]) -> Result<IntervalCounts> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut counts = IntervalCounts { interval_us, start_us: 0, duration_us: 0, rows: Vec::new(), drops: CaptureDrops::default() };
+    let mut first_us = None;
+    while let Some(block) = reader.next_block()? {
+        let packet = match block {
+            PcapngBlock::Packet(packet) => packet,
+            PcapngBlock::Statistics(statistics) => {
+                counts.drops.record(&reader, &statistics);
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let linktype = interface.linktype as u32;
+        let time_us = (interface.timestamp_ns(packet.timestamp) / 1000) as i64;
+        let first_us = *first_us.get_or_insert_with(|| {
+            counts.start_us = time_us;
+            time_us
+        });
+        let relative_us = (time_us - first_us).max(0) as u64;
+        counts.duration_us = counts.duration_us.max(relative_us);
+
+        let index = relative_us.checked_div(interval_us).unwrap_or(0) as usize;
+        if counts.rows.len() <= index {
+            counts.rows.resize(index + 1, vec![(0, 0); columns.len()]);
+        }
+        let parsed = parse_packet(&packet.data, linktype);
+        for (column, cell) in columns.iter().zip(counts.rows[index].iter_mut()) {
+            if column.filter.as_ref().is_none_or(|f| f.matches(&packet.data, linktype, &parsed)) {
+                cell.0 += 1;
+                cell.1 += packet.orig_len as u64;
+            }
+        }
+    }
+    if first_us.is_none() {
+        anyhow::bail!(PcapError::EmptyCapture);
+    }
+    Ok(counts)
+}
+
+impl IntervalCounts {
+    /// 区间长度 (微秒)，整个文件为一个区间时为文件的时长
+    fn effective_interval_us(&self) -> u64 {
+        if self.interval_us == 0 { self.duration_us.max(1) } else { self.interval_us }
+    }
+
+    /// 区间长度的小数位数 (按微秒精度)
+    fn precision(&self) -> usize {
+        let mut usec = self.effective_interval_us() % 1_000_000;
+        if usec == 0 {
+            return 0;
+        }
+        let mut precision = 6;
+        while usec.is_multiple_of(10) {
+            usec /= 10;
+            precision -= 1;
+        }
+        precision
+    }
+}
+
+/// 以秒为单位、指定小数位数格式化微秒
+fn format_seconds(us: u64, precision: usize) -> String {
+    if precision == 0 {
+        (us / 1_000_000).to_string()
+    } else {
+        let fraction = (us % 1_000_000) / 10u64.pow(6 - precision as u32);
+        format!("{}.{:0width$}", us / 1_000_000, fraction, width = precision)
+    }
+}
+
+fn print_report(input_path: &str, counts: &IntervalCounts, columns: &[Column]) {
+    let precision = counts.precision();
+    print_heading(&format!("区间统计: {}", input_path));
+    let total = |column: usize| counts.rows.iter().fold((0, 0), |(p, b), row| (p + row[column].0, b + row[column].1));
+    let (packets, bytes) = total(0);
+    let mut fields = vec![
+        ("时长", format!("{} 秒", format_seconds(counts.duration_us, 6)), None),
+        ("区间", format!("{} 秒", format_seconds(counts.effective_interval_us(), precision)), None),
+    ];
+    if columns[0].filter.is_none() {
+        fields.push(("包数", packets.to_string(), None));
+        fields.push(("字节数", bytes.to_string(), None));
+    }
+    print_fields(&fields);
+    counts.drops.print();
+
+    print_section("各区间:");
+    // ISO格式时多一列区间开始的时刻，便于对照设备日志
+    let absolute = time_format() == TimeFormat::Iso;
+    let mut headers = vec!["区间 (秒)".to_string()];
+    if absolute {
+        headers.push("开始时间".to_string());
+    }
+    for column in columns {
+        if column.filter.is_none() {
+            headers.extend(["包数".to_string(), "字节数".to_string()]);
+        } else {
+            headers.extend([format!("{} 包数", column.name), format!("{} 字节数", column.name)]);
+        }
+    }
+    let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+    let numeric: Vec<usize> = ((1 + absolute as usize)..headers.len()).collect();
+    let mut table = Table::new(&headers).right_align(&numeric);
+    let interval = counts.effective_interval_us();
+    for (i, row) in counts.rows.iter().enumerate() {
+        let start = i as u64 * interval;
+        let mut cells = vec![format!(
+            "{} - {}",
+            format_seconds(start, precision),
+            format_seconds(start + interval, precision)
+        )];
+        if absolute {
+            cells.push(format_timestamp_us(counts.start_us + start as i64, counts.start_us));
+        }
+        for &(packets, bytes) in row {
+            cells.push(packets.to_string());
+            cells.push(bytes.to_string());
+        }
+        table.add_row(cells);
+    }
+    table.print();
+}
+
+/// 按 `tshark -q -z io,stat,<区间>[,<过滤器>...]` 的格式输出
+///
+/// 框线、列宽、`<>` 分隔的区间和最后一个区间的 `Dur` 与tshark一致，解析tshark输出的脚本可以直接使用。
+fn print_io_stat(counts: &IntervalCounts, columns: &[Column]) {
+    let precision = counts.precision();
+    let interval = counts.effective_interval_us();
+    // 区间起止时间的宽度: 整数部分至少2位
+    let magnitude = (counts.duration_us / 1_000_000).to_string().len().max(2);
+    let time_width = if precision == 0 { magnitude } else { magnitude + precision + 1 };
+    let interval_column_width = 2 * time_width + 8;
+
+    // 每列的包数和字节数宽度
+    let widths: Vec<(usize, usize)> = (0..columns.len())
+        .map(|j| {
+            let frames = counts.rows.iter().map(|row| row[j].0).max().unwrap_or(0);
+            let bytes = counts.rows.iter().map(|row| row[j].1).max().unwrap_or(0);
+            (frames.to_string().len().max(6), bytes.to_string().len().max(5))
+        })
+        .collect();
+    let table_width = interval_column_width + widths.iter().map(|(frames, bytes)| frames + bytes + 6).sum::<usize>();
+
+    let duration = format_seconds(counts.duration_us, precision.max(6));
+    let info_lines = [
+        "IO Statistics".to_string(),
+        String::new(),
+        format!("Duration: {} secs", duration),
+        format!("Interval: {} secs", format_seconds(interval, precision)),
+        String::new(),
+    ];
+    let column_lines: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(j, column)| {
+            let prefix = if j == 0 { "Col" } else { "   " };
+            format!("{}{:>2}: {}", prefix, j + 1, column.name)
+        })
+        .collect();
+    let border_width = info_lines
+        .iter()
+        .chain(&column_lines)
+        .map(|line| line.len() + 3)
+        .max()
+        .unwrap_or(0)
+        .max(table_width);
+    let boxed = |text: &str| format!("| {}{}|", text, " ".repeat(border_width - 3 - text.len()));
+    // 表格比框窄时在行尾补齐
+    let filler = if table_width < border_width {
+        format!("{}|", " ".repeat(border_width - table_width - 1))
+    } else {
+        String::new()
+    };
+
+    println!();
+    println!("{}", "=".repeat(border_width));
+    for line in info_lines.iter().chain(&column_lines) {
+        println!("{}", boxed(line));
+    }
+    println!("|{}|", "-".repeat(border_width - 2));
+
+    let mut numbers = format!("|{}|", " ".repeat(interval_column_width - 2));
+    let mut labels = format!("| Interval{}|", " ".repeat(interval_column_width - 11));
+    for (j, &(frames, bytes)) in widths.iter().enumerate() {
+        numbers.push_str(&format!("{:<2}{}|", j + 1, " ".repeat(frames + bytes + 3)));
+        labels.push_str(&format!(" {} | {} |", center("Frames", frames), center("Bytes", bytes)));
+    }
+    println!("{}{}", numbers, filler);
+    println!("{}{}", labels, filler);
+    println!("|{}|{}", "-".repeat(table_width - 2), filler);
+
+    for (i, row) in counts.rows.iter().enumerate() {
+        let start = i as u64 * interval;
+        let end = start + interval;
+        let mut line = if end > counts.duration_us {
+            format!(
+                "| {:>tw$} <> {:<dw$}|",
+                format_seconds(start, precision),
+                "Dur",
+                tw = time_width,
+                dw = time_width + 1
+            )
+        } else {
+            format!(
+                "| {:>tw$} <> {:>tw$} |",
+                format_seconds(start, precision),
+                format_seconds(end, precision),
+                tw = time_width
+            )
+        };
+        for (&(packets, bytes), &(frames_width, bytes_width)) in row.iter().zip(&widths) {
+            line.push_str(&format!(" {:>fw$} | {:>bw$} |", packets, bytes, fw = frames_width, bw = bytes_width));
+        }
+        println!("{}{}", line, filler);
+    }
+    println!("{}", "=".repeat(border_width));
+}
+
+/// 在指定宽度中居中 (多出的空格放在右边)
+fn center(text: &str, width: usize) -> String {
+    let left = (width - text.len()) / 2;
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(width - text.len() - left))
+}
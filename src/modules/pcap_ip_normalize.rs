@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::modules::pcap_packet_parser::{FlowKey, ParsedPacket, read_u16, replace_field};
+
+/// IPv4标志/片偏移字段中的MF位和片偏移
+const IPV4_MORE_FRAGMENTS: u16 = 0x2000;
+const IPV4_FRAGMENT_OFFSET: u16 = 0x1fff;
+
+/// 把IPv4标识字段改写为每个流 (按方向) 从1开始递增的序列
+///
+/// 同一数据报的各个分片共用一个新标识，由最先出现的分片分配。
+#[derive(Default)]
+pub struct IpIdRenumberer {
+    next_ids: HashMap<FlowKey, u16>,
+    /// 分片数据报 (源地址, 目的地址, 协议, 原标识) 对应的新标识
+    fragments: HashMap<(IpAddr, IpAddr, u8, u16), u16>,
+}
+
+impl IpIdRenumberer {
+    /// 改写一个包的IPv4标识并增量更新IP头校验和，不是IPv4包时返回false
+    pub fn renumber(&mut self, data: &mut [u8], parsed: &ParsedPacket) -> bool {
+        let Some(ip) = parsed.ip.as_ref().filter(|ip| ip.version == 4) else {
+            return false;
+        };
+        let Some(key) = parsed.flow_key() else {
+            return false;
+        };
+        let id = read_u16(data, ip.offset + 4);
+        let fragmented = read_u16(data, ip.offset + 6) & (IPV4_MORE_FRAGMENTS | IPV4_FRAGMENT_OFFSET) != 0;
+
+        let mut next_id = || {
+            let next = self.next_ids.entry(key).or_insert(1);
+            let id = *next;
+            *next = next.wrapping_add(1);
+            id
+        };
+        let new_id = if fragmented {
+            let datagram = (ip.src, ip.dst, ip.protocol, id);
+            match self.fragments.get(&datagram) {
+                Some(&new_id) => new_id,
+                None => {
+                    let new_id = next_id();
+                    self.fragments.insert(datagram, new_id);
+                    new_id
+                }
+            }
+        } else {
+            next_id()
+        };
+
+        replace_field(data, ip.offset + 4, &new_id.to_be_bytes(), &[ip.offset + 10]);
+        true
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+
+/// JSON值 (只用于读取外部工具输出的日志，数字保留原文以免丢失精度)
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// 解析一个完整的JSON文本
+    pub fn parse(text: &str) -> Result<JsonValue> {
+        let mut parser = Parser { bytes: text.as_bytes(), pos: 0 };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(parser.error("JSON值之后有多余的内容"));
+        }
+        Ok(value)
+    }
+
+    /// 把对象展开为 `键 -> 文本` 的映射，嵌套对象的键以 `.` 连接 (如 `alert.signature`)
+    ///
+    /// 字符串、数字和布尔值转换为文本，null和数组忽略。
+    pub fn flatten(&self) -> HashMap<String, String> {
+        let mut fields = HashMap::new();
+        flatten_into(self, "", &mut fields);
+        fields
+    }
+}
+
+fn flatten_into(value: &JsonValue, prefix: &str, fields: &mut HashMap<String, String>) {
+    match value {
+        JsonValue::Object(members) => {
+            for (key, value) in members {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten_into(value, &key, fields);
+            }
+        }
+        JsonValue::String(text) | JsonValue::Number(text) => {
+            fields.insert(prefix.to_string(), text.clone());
+        }
+        JsonValue::Bool(value) => {
+            fields.insert(prefix.to_string(), value.to_string());
+        }
+        JsonValue::Null | JsonValue::Array(_) => {}
+    }
+}
+
+/// 嵌套层数上限 (防止恶意输入耗尽栈空间)
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> anyhow::Error {
+        anyhow!("{} (位置 {})", message, self.pos)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && matches!(self.bytes[self.pos], b' ' | b'\t' | b'\r' | b'\n') {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) != Some(&byte) {
+            return Err(self.error(&format!("应为 '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("JSON嵌套层数过多"));
+        }
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.bytes.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    members.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.bytes.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(JsonValue::Object(members));
+                        }
+                        _ => return Err(self.error("对象中应为 ',' 或 '}'")),
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.bytes.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                loop {
+                    items.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.bytes.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(JsonValue::Array(items));
+                        }
+                        _ => return Err(self.error("数组中应为 ',' 或 ']'")),
+                    }
+                }
+            }
+            Some(b'"') => Ok(JsonValue::String(self.string()?)),
+            Some(b't') => self.literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.literal("false", JsonValue::Bool(false)),
+            Some(b'n') => self.literal("null", JsonValue::Null),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.pos;
+                while self.pos < self.bytes.len() && matches!(self.bytes[self.pos], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') {
+                    self.pos += 1;
+                }
+                let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+                if text.parse::<f64>().is_err() {
+                    self.pos = start;
+                    return Err(self.error("无效的数字"));
+                }
+                Ok(JsonValue::Number(text.to_string()))
+            }
+            Some(_) => Err(self.error("无效的JSON值")),
+            None => Err(self.error("JSON文本不完整")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: JsonValue) -> Result<JsonValue> {
+        if self.bytes[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("无效的JSON值"))
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        if self.bytes.get(self.pos) != Some(&b'"') {
+            return Err(self.error("应为字符串"));
+        }
+        self.pos += 1;
+        let mut text = Vec::new();
+        loop {
+            let Some(&byte) = self.bytes.get(self.pos) else {
+                return Err(self.error("字符串没有结束"));
+            };
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.bytes.get(self.pos) else {
+                        return Err(self.error("字符串没有结束"));
+                    };
+                    self.pos += 1;
+                    let decoded = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("无效的转义字符")),
+                    };
+                    let mut buffer = [0; 4];
+                    text.extend_from_slice(decoded.encode_utf8(&mut buffer).as_bytes());
+                }
+                _ => text.push(byte),
+            }
+        }
+        String::from_utf8(text).map_err(|_| self.error("字符串不是有效的UTF-8"))
+    }
+
+    /// `\uXXXX` 转义，包括UTF-16代理对
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        if (0xd800..0xdc00).contains(&high) {
+            if self.bytes[self.pos..].starts_with(b"\\u") {
+                self.pos += 2;
+                let low = self.hex4()?;
+                if (0xdc00..0xe000).contains(&low) {
+                    let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                    return char::from_u32(code).ok_or_else(|| self.error("无效的Unicode转义"));
+                }
+            }
+            return Ok(char::REPLACEMENT_CHARACTER);
+        }
+        Ok(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let digits = self.bytes.get(self.pos..self.pos + 4).ok_or_else(|| self.error("Unicode转义不完整"))?;
+        let text = std::str::from_utf8(digits).unwrap_or_default();
+        let value = u32::from_str_radix(text, 16).map_err(|_| self.error("无效的Unicode转义"))?;
+        self.pos += 4;
+        Ok(value)
+    }
+}
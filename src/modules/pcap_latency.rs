@@ -0,0 +1,206 @@
+use std::collections::{HashMap, VecDeque};
+use anyhow::Result;
+use log::debug;
+
+use crate::modules::io::PacketReader;
+use crate::modules::pcap_comparative_analyzer::{CompareOptions, packet_hash};
+use crate::modules::pcap_packet_parser::{FlowKey, parse_packet};
+use crate::modules::pcap_report::{Severity, Table, Verbosity, print_fields, print_heading, print_section, print_separator, print_verdict, verbosity};
+use crate::modules::pcap_time_anchor::{ticks_per_second, timestamp_to_us};
+
+/// 按流统计表中最多列出的流数 (详细模式不限制)
+const MAX_FLOW_ROWS: usize = 20;
+/// 直方图中最长的条形 (字符数)
+const BAR_WIDTH: u64 = 40;
+
+/// 计算两个抓包点之间每个数据包的单向时延，输出分位数、直方图和按流的统计
+///
+/// # 参数
+/// - `reference_path`: 上游抓包点 (A) 的抓包文件路径 (PCAP或pcapng)
+/// - `comparison_path`: 下游抓包点 (B) 的抓包文件路径 (PCAP或pcapng)
+/// - `offset_us`: B端时钟比A端快的微秒数，从时延中扣除
+/// - `options`: 包匹配方式 (与 `compare` 相同，忽略 `ignore_timestamp`)
+///
+/// # 说明
+/// 内容相同的包按出现顺序依次配对 (A中第k次出现对应B中第k次出现)，时延 = B时间 - A时间 - 偏移。
+/// 两端时钟需要已经同步 (如PTP)，否则时延中包含时钟偏移；出现负时延时给出警告。
+/// 五元组取自A端的包，非IP包归入同一组。
+pub fn pcap_latency(reference_path: &str, comparison_path: &str, offset_us: i64, options: CompareOptions) -> Result<()> {
+    let options = CompareOptions { ignore_timestamp: false, ..options };
+
+    // 包哈希 -> A端尚未配对的 (时间, 五元组)
+    let mut pending: HashMap<u64, VecDeque<(i64, Option<FlowKey>)>> = HashMap::new();
+    let mut reference_count = 0u64;
+    let mut pcap_reader = PacketReader::open(reference_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let reference_ticks = ticks_per_second(&pcap_reader.header);
+    for packet in pcap_reader.by_ref() {
+        let Some(hash) = packet_hash(&packet, linktype, options) else {
+            continue;
+        };
+        reference_count += 1;
+        let time = timestamp_to_us(packet.header.ts_sec, packet.header.ts_usec, reference_ticks);
+        let flow = parse_packet(&packet.data, linktype).flow_key();
+        pending.entry(hash).or_default().push_back((time, flow));
+    }
+    pcap_reader.check_complete()?;
+
+    let mut delays: Vec<i64> = Vec::new();
+    let mut flows: HashMap<Option<FlowKey>, Vec<i64>> = HashMap::new();
+    let mut comparison_count = 0u64;
+    let mut pcap_reader = PacketReader::open(comparison_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let comparison_ticks = ticks_per_second(&pcap_reader.header);
+    for packet in pcap_reader.by_ref() {
+        let Some(hash) = packet_hash(&packet, linktype, options) else {
+            continue;
+        };
+        comparison_count += 1;
+        let Some((time, flow)) = pending.get_mut(&hash).and_then(VecDeque::pop_front) else {
+            continue;
+        };
+        let delay = timestamp_to_us(packet.header.ts_sec, packet.header.ts_usec, comparison_ticks) - time - offset_us;
+        delays.push(delay);
+        flows.entry(flow).or_default().push(delay);
+    }
+    pcap_reader.check_complete()?;
+    if delays.is_empty() {
+        anyhow::bail!("两个文件没有可以配对的相同数据包");
+    }
+    debug!("配对的数据包: {} 个", delays.len());
+
+    let matched = delays.len() as u64;
+    delays.sort_unstable();
+    let negative = delays.iter().filter(|&&delay| delay < 0).count();
+    let mean = delays.iter().map(|&delay| delay as f64).sum::<f64>() / delays.len() as f64;
+
+    print_heading(&format!("单向时延: {} -> {}", reference_path, comparison_path));
+    let mut fields = vec![
+        ("配对数据包", format!("{} 个", matched), None),
+        ("仅在A中", format!("{} 个 (丢失或未到达B)", reference_count - matched), None),
+        ("仅在B中", format!("{} 个", comparison_count - matched), None),
+    ];
+    if offset_us != 0 {
+        fields.push(("扣除的时钟偏移", format_delay(offset_us), None));
+    }
+    fields.extend([
+        ("最小", format_delay(delays[0]), None),
+        ("平均", format!("{:.3} 毫秒", mean / 1000.0), None),
+        ("p50", format_delay(percentile(&delays, 50.0)), None),
+        ("p95", format_delay(percentile(&delays, 95.0)), None),
+        ("p99", format_delay(percentile(&delays, 99.0)), None),
+        ("最大", format_delay(delays[delays.len() - 1]), None),
+    ]);
+    if negative > 0 {
+        fields.push(("负时延", format!("{} 个", negative), Some(Severity::Warning)));
+    }
+    print_fields(&fields);
+
+    print_section("时延分布:");
+    let buckets = histogram(&delays);
+    let peak = buckets.iter().map(|&(_, count)| count).max().unwrap_or(1);
+    let mut table = Table::new(&["范围", "包数", "占比", ""]).right_align(&[1, 2]);
+    for (label, count) in buckets {
+        table.add_row(vec![
+            label,
+            count.to_string(),
+            format!("{:.2}%", count as f64 * 100.0 / matched as f64),
+            "#".repeat(count.saturating_mul(BAR_WIDTH).div_ceil(peak) as usize),
+        ]);
+    }
+    table.print();
+
+    let mut flows: Vec<(Option<FlowKey>, Vec<i64>)> = flows.into_iter().collect();
+    for (_, flow_delays) in &mut flows {
+        flow_delays.sort_unstable();
+    }
+    // 尾部时延最大的流在前
+    flows.sort_by(|(a_key, a), (b_key, b)| {
+        percentile(b, 99.0).cmp(&percentile(a, 99.0)).then(b.len().cmp(&a.len())).then(a_key.cmp(b_key))
+    });
+    let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_FLOW_ROWS };
+    print_section("按流统计 (按p99从大到小):");
+    let mut table = Table::new(&["五元组", "包数", "最小 (毫秒)", "p50", "p95", "p99", "最大"]).right_align(&[1, 2, 3, 4, 5, 6]);
+    let milliseconds = |delay_us: i64| format!("{:.3}", delay_us as f64 / 1000.0);
+    for (key, flow_delays) in flows.iter().take(max_rows) {
+        table.add_row(vec![
+            key.map_or("(非IP包)".to_string(), |key| key.to_string()),
+            flow_delays.len().to_string(),
+            milliseconds(flow_delays[0]),
+            milliseconds(percentile(flow_delays, 50.0)),
+            milliseconds(percentile(flow_delays, 95.0)),
+            milliseconds(percentile(flow_delays, 99.0)),
+            milliseconds(flow_delays[flow_delays.len() - 1]),
+        ]);
+    }
+    table.print();
+    if flows.len() > max_rows {
+        println!("  ... 还有 {} 个流 (使用 --verbose 查看全部)", flows.len() - max_rows);
+    }
+
+    print_separator();
+    if negative > 0 {
+        print_verdict(
+            Severity::Warning,
+            &format!("{} 个包的时延为负，两端时钟未同步，可用 --offset 扣除已知的时钟偏移", negative),
+        );
+    } else {
+        print_verdict(
+            Severity::Ok,
+            &format!("p99 单向时延 {}，最大 {}", format_delay(percentile(&delays, 99.0)), format_delay(delays[delays.len() - 1])),
+        );
+    }
+    Ok(())
+}
+
+/// 已排序的时延的分位数 (最近秩法)
+fn percentile(sorted: &[i64], percent: f64) -> i64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn format_delay(delay_us: i64) -> String {
+    format!("{:.3} 毫秒", delay_us as f64 / 1000.0)
+}
+
+/// 按 1-2-5 递增的对数桶统计已排序的时延，负时延单独一桶；返回从最小的非空桶到最大的非空桶之间的各桶
+fn histogram(sorted: &[i64]) -> Vec<(String, u64)> {
+    let mut buckets = Vec::new();
+    let negative = sorted.iter().take_while(|&&delay| delay < 0).count();
+    if negative > 0 {
+        buckets.push(("< 0".to_string(), negative as u64));
+    }
+    let rest = &sorted[negative..];
+    let Some(&max) = rest.last() else {
+        return buckets;
+    };
+
+    // 第i个桶的上界: 1, 2, 5, 10, 20, 50 ... 微秒
+    let upper_bound = |i: u32| [1i64, 2, 5][i as usize % 3] * 10i64.pow(i / 3);
+    let mut lower = 0i64;
+    let mut start = 0;
+    let mut i = 0;
+    while lower <= max {
+        let upper = upper_bound(i);
+        let end = start + rest[start..].iter().take_while(|&&delay| delay < upper).count();
+        // 跳过最小的非空桶之前的空桶
+        if end > start || start > 0 {
+            buckets.push((format!("{} ~ {}", format_bound(lower), format_bound(upper)), (end - start) as u64));
+        }
+        start = end;
+        lower = upper;
+        i += 1;
+    }
+    buckets
+}
+
+/// 桶边界: 不足1毫秒时以微秒表示
+fn format_bound(bound_us: i64) -> String {
+    if bound_us < 1000 {
+        format!("{}µs", bound_us)
+    } else if bound_us < 1_000_000 {
+        format!("{}ms", bound_us / 1000)
+    } else {
+        format!("{}s", bound_us / 1_000_000)
+    }
+}
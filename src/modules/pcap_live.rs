@@ -0,0 +1,305 @@
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use pcap_file::pcap_header::{Datalink, PcapHeader};
+use pcap_file::Packet;
+use anyhow::{Context, Result, anyhow};
+use log::info;
+
+use crate::modules::io::PacketWriter;
+use crate::modules::pcap_anonymize::Anonymizer;
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_filter::Filter;
+use crate::modules::pcap_input::{install_stop_handler, stop_requested};
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{LINKTYPE_ETHERNET, LINKTYPE_RAW, parse_packet};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 接收缓冲区大小 (超过的部分被截断，原始长度仍按实际长度记录)
+const CAPTURE_BUFFER_LEN: usize = 65536;
+/// 等待数据包时检查停止信号的间隔
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// 实时处理流水线中的一个步骤
+#[derive(Debug)]
+pub enum LiveStage {
+    /// 只保留匹配过滤表达式的包
+    Filter(Filter),
+    /// 截断到指定字节数
+    Snap(usize),
+    /// 匿名化MAC地址和IP地址
+    Anonymize(Anonymizer),
+}
+
+/// 解析实时处理流水线，例如 `filter 'udp.port == 53' | snap 128 | anonymize`
+///
+/// 步骤之间以 `|` 分隔 (引号中的 `|` 不分隔)，参数可以用单引号或双引号括起来。
+/// `anonymize` 可以带一个十进制或 `0x` 开头的十六进制密钥，省略时每次运行使用随机密钥；
+/// 带 `keep-oui` 时保留MAC地址的厂商前缀 (如 `anonymize keep-oui 0x5eed`)。
+pub fn parse_live_pipeline(text: &str) -> Result<Vec<LiveStage>> {
+    let mut stages = Vec::new();
+    for stage in split_stages(text)? {
+        let stage = stage.trim();
+        if stage.is_empty() {
+            anyhow::bail!(PcapError::InvalidArgument(format!("流水线中有空的步骤: {}", text)));
+        }
+        let (name, argument) = stage.split_once(char::is_whitespace).unwrap_or((stage, ""));
+        let argument = unquote(argument.trim());
+        let stage = match name {
+            "filter" => {
+                if argument.is_empty() {
+                    anyhow::bail!(PcapError::InvalidArgument("filter 需要过滤表达式".to_string()));
+                }
+                LiveStage::Filter(Filter::parse(argument).map_err(|e| PcapError::InvalidArgument(format!("{:#}", e)))?)
+            }
+            "snap" => match argument.parse::<usize>() {
+                Ok(len) if len > 0 => LiveStage::Snap(len),
+                _ => anyhow::bail!(PcapError::InvalidArgument(format!("snap 的长度必须为正整数: {}", argument))),
+            },
+            "anonymize" => {
+                let mut keep_oui = false;
+                let mut key = None;
+                for word in argument.split_whitespace() {
+                    if word == "keep-oui" {
+                        keep_oui = true;
+                    } else if key.is_none() {
+                        key = Some(crate::modules::pcap_filter::parse_number(word)
+                            .ok_or_else(|| PcapError::InvalidArgument(format!("anonymize 的密钥无效: {}", word)))?);
+                    } else {
+                        anyhow::bail!(PcapError::InvalidArgument(format!("anonymize 的参数过多: {}", argument)));
+                    }
+                }
+                let anonymizer = key.map_or_else(Anonymizer::random, Anonymizer::new);
+                LiveStage::Anonymize(if keep_oui { anonymizer.keep_oui() } else { anonymizer })
+            }
+            _ => anyhow::bail!(PcapError::InvalidArgument(format!(
+                "未知的流水线步骤: {} (可用: filter、snap、anonymize)",
+                name
+            ))),
+        };
+        stages.push(stage);
+    }
+    Ok(stages)
+}
+
+/// 按不在引号中的 `|` 分隔步骤
+fn split_stages(text: &str) -> Result<Vec<&str>> {
+    let mut stages = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '|') => {
+                stages.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() {
+        anyhow::bail!(PcapError::InvalidArgument(format!("流水线中的引号不匹配: {}", text)));
+    }
+    stages.push(&text[start..]);
+    Ok(stages)
+}
+
+fn unquote(text: &str) -> &str {
+    for quote in ['\'', '"'] {
+        if let Some(inner) = text.strip_prefix(quote).and_then(|t| t.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    text
+}
+
+/// 从网络接口实时抓包，经过流水线处理后写入PCAP文件
+///
+/// # 参数
+/// - `interface`: 网络接口名 (如 `eth0`)
+/// - `stages`: 按顺序执行的处理步骤；包在写入之前完成全部处理，原始数据不落盘
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `count`: 写入指定数量的包后停止，None表示直到按Ctrl-C
+///
+/// 使用Linux的AF_PACKET套接字，需要root或CAP_NET_RAW权限。时间戳为接收时的系统时间。
+pub fn pcap_live(interface: &str, stages: &[LiveStage], output_path: &str, count: Option<u64>) -> Result<()> {
+    let arphrd = interface_type(interface)?;
+    let linktype = match arphrd {
+        libc::ARPHRD_ETHER | libc::ARPHRD_LOOPBACK => LINKTYPE_ETHERNET,
+        // 如tun设备
+        libc::ARPHRD_NONE => LINKTYPE_RAW,
+        other => anyhow::bail!(PcapError::InvalidArgument(format!(
+            "不支持的接口类型: {} (ARPHRD类型 {})",
+            interface, other
+        ))),
+    };
+    let socket = CaptureSocket::open(interface, arphrd == libc::ARPHRD_LOOPBACK)?;
+    install_stop_handler();
+
+    let snaplen = stages
+        .iter()
+        .filter_map(|stage| match stage {
+            LiveStage::Snap(len) => Some(*len as u32),
+            _ => None,
+        })
+        .min()
+        .unwrap_or(CAPTURE_BUFFER_LEN as u32);
+    let header = PcapHeader { snaplen, ..PcapHeader::with_datalink(Datalink::from(linktype)) };
+    // 出错时保留已经抓到的包
+    let out_file = create_output(output_path, compression())?.keep_partial();
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    info!("开始在 {} 上抓包，按Ctrl-C结束", interface);
+    let mut buffer = vec![0u8; CAPTURE_BUFFER_LEN];
+    let mut captured = 0u64;
+    let mut written = 0u64;
+    while count.is_none_or(|count| written < count) {
+        let Some(orig_len) = socket.receive(&mut buffer)? else {
+            if stop_requested() {
+                break;
+            }
+            continue;
+        };
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        captured += 1;
+
+        let mut data = buffer[..orig_len.min(buffer.len())].to_vec();
+        if !apply_stages(stages, &mut data, linktype) {
+            continue;
+        }
+        let mut packet = Packet::new(timestamp.as_secs() as u32, timestamp.subsec_micros(), data.len() as u32, &data);
+        packet.header.orig_len = orig_len as u32;
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入数据包失败: {}", e)))?;
+        digest.record_pcap(&packet);
+        written += 1;
+    }
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "抓包结束: 接口={}, 收到包数={}, 写入包数={}, 输出文件={}",
+        interface, captured, written, output_path
+    );
+    Ok(())
+}
+
+/// 依次执行流水线步骤，包被过滤掉时返回false
+fn apply_stages(stages: &[LiveStage], data: &mut Vec<u8>, linktype: u32) -> bool {
+    for stage in stages {
+        match stage {
+            LiveStage::Filter(filter) => {
+                if !filter.matches(data, linktype, &parse_packet(data, linktype)) {
+                    return false;
+                }
+            }
+            LiveStage::Snap(len) => data.truncate(*len),
+            LiveStage::Anonymize(anonymizer) => {
+                let parsed = parse_packet(data, linktype);
+                anonymizer.anonymize(data, linktype, &parsed);
+            }
+        }
+    }
+    true
+}
+
+/// 接口的ARPHRD类型 (`/sys/class/net/<接口>/type`)
+fn interface_type(interface: &str) -> Result<u16> {
+    let path = format!("/sys/class/net/{}/type", interface);
+    let text = fs::read_to_string(&path)
+        .map_err(|e| PcapError::open_failed(interface, io::Error::new(e.kind(), format!("网络接口不存在: {}", interface))))?;
+    text.trim().parse().with_context(|| format!("无法识别接口类型: {}", path))
+}
+
+/// 绑定到一个接口的AF_PACKET套接字
+struct CaptureSocket {
+    fd: libc::c_int,
+    /// 是否为回环接口 (发出的包会再被收到一次，需要跳过)
+    loopback: bool,
+}
+
+impl CaptureSocket {
+    fn open(interface: &str, loopback: bool) -> Result<CaptureSocket> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be() as libc::c_int;
+        let name = std::ffi::CString::new(interface).context("接口名无效")?;
+        // SAFETY: 以下调用只传入有效的参数和本函数中的局部变量
+        unsafe {
+            let index = libc::if_nametoindex(name.as_ptr());
+            if index == 0 {
+                anyhow::bail!(PcapError::open_failed(interface, io::Error::last_os_error()));
+            }
+            let fd = libc::socket(libc::AF_PACKET, libc::SOCK_RAW, protocol);
+            if fd < 0 {
+                let error = io::Error::last_os_error();
+                let error = if error.kind() == io::ErrorKind::PermissionDenied {
+                    io::Error::new(error.kind(), "需要root或CAP_NET_RAW权限")
+                } else {
+                    error
+                };
+                anyhow::bail!(PcapError::open_failed(interface, error));
+            }
+            let socket = CaptureSocket { fd, loopback };
+
+            let mut address: libc::sockaddr_ll = std::mem::zeroed();
+            address.sll_family = libc::AF_PACKET as u16;
+            address.sll_protocol = protocol as u16;
+            address.sll_ifindex = index as i32;
+            if libc::bind(
+                fd,
+                &address as *const libc::sockaddr_ll as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            ) < 0
+            {
+                anyhow::bail!(PcapError::open_failed(interface, io::Error::last_os_error()));
+            }
+
+            let timeout = libc::timeval { tv_sec: 0, tv_usec: RECEIVE_TIMEOUT.as_micros() as libc::suseconds_t };
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+            );
+            Ok(socket)
+        }
+    }
+
+    /// 接收一个包，返回原始长度 (可能大于缓冲区)；超时或被信号中断时返回None
+    fn receive(&self, buffer: &mut [u8]) -> Result<Option<usize>> {
+        let mut address: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        let mut address_len = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+        // SAFETY: 缓冲区和地址结构在调用期间有效，长度与实际大小一致
+        let len = unsafe {
+            libc::recvfrom(
+                self.fd,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+                libc::MSG_TRUNC,
+                &mut address as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                &mut address_len,
+            )
+        };
+        if len < 0 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted => Ok(None),
+                _ => Err(anyhow!(error).context("接收数据包失败")),
+            };
+        }
+        if self.loopback && address.sll_pkttype == libc::PACKET_OUTGOING {
+            return Ok(None);
+        }
+        Ok(Some(len as usize))
+    }
+}
+
+impl Drop for CaptureSocket {
+    fn drop(&mut self) {
+        // SAFETY: fd由本结构持有，只关闭一次
+        unsafe { libc::close(self.fd) };
+    }
+}
@@ -0,0 +1,193 @@
+/// LZ77匹配: 先输出 `literal_len` 个字面量，再从 `distance` 字节之前复制 `match_len` 字节
+#[derive(Debug, Clone, Copy)]
+pub struct Sequence {
+    pub literal_len: usize,
+    pub match_len: usize,
+    pub distance: usize,
+}
+
+/// 匹配查找的参数 (按压缩级别选择)
+#[derive(Debug, Clone, Copy)]
+pub struct MatchParams {
+    /// 回溯窗口大小的位数 (最大距离为窗口大小 - 1)
+    pub window_log: u32,
+    /// 最短和最长的匹配长度
+    pub min_len: usize,
+    pub max_len: usize,
+    /// 每个位置最多比较的候选数
+    pub depth: usize,
+    /// 找到这么长的匹配后不再继续查找
+    pub nice_len: usize,
+    /// 是否延迟匹配: 下一个位置的匹配更长时先输出一个字面量
+    pub lazy: bool,
+}
+
+const HASH_BITS: u32 = 16;
+
+/// 哈希链查找LZ77匹配
+///
+/// 位置都是相对调用者的缓冲区的下标 (存储时加1，0表示没有)；
+/// 缓冲区前面的历史数据通过 [`MatchFinder::trim`] 丢弃，同时调整记录的位置。
+pub struct MatchFinder {
+    params: MatchParams,
+    /// 每个哈希值最近的位置
+    head: Vec<u32>,
+    /// 按 位置 & (窗口大小 - 1) 记录同一哈希值的上一个位置
+    chain: Vec<u32>,
+}
+
+impl MatchFinder {
+    pub fn new(params: MatchParams) -> MatchFinder {
+        MatchFinder {
+            params,
+            head: vec![0; 1 << HASH_BITS],
+            chain: vec![0; 1 << params.window_log],
+        }
+    }
+
+    pub fn window_size(&self) -> usize {
+        1 << self.params.window_log
+    }
+
+    fn hash(&self, data: &[u8]) -> usize {
+        let value = data[..self.params.min_len].iter().fold(0u32, |value, &byte| (value << 8) | byte as u32);
+        (value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, data: &[u8], pos: usize) {
+        if pos + self.params.min_len > data.len() {
+            return;
+        }
+        let hash = self.hash(&data[pos..]);
+        let mask = self.window_size() - 1;
+        self.chain[pos & mask] = self.head[hash];
+        self.head[hash] = pos as u32 + 1;
+    }
+
+    /// `pos` 处最长的匹配 (长度, 距离)，匹配不超过 `end`
+    fn longest_match(&self, data: &[u8], pos: usize, end: usize) -> (usize, usize) {
+        let limit = self.params.max_len.min(end - pos);
+        if limit < self.params.min_len {
+            return (0, 0);
+        }
+        let mask = self.window_size() - 1;
+        let (mut best_len, mut best_distance) = (0, 0);
+        let mut candidate = self.head[self.hash(&data[pos..])] as usize;
+        let mut depth = self.params.depth;
+        while candidate != 0 && depth > 0 {
+            let start = candidate - 1;
+            let distance = pos - start;
+            if distance > mask {
+                break;
+            }
+            if data[start + best_len] == data[pos + best_len] {
+                let len = data[start..start + limit].iter().zip(&data[pos..pos + limit]).take_while(|(a, b)| a == b).count();
+                if len > best_len {
+                    (best_len, best_distance) = (len, distance);
+                    if len >= self.params.nice_len || len == limit {
+                        break;
+                    }
+                }
+            }
+            let next = self.chain[start & mask] as usize;
+            // 槽位被更新的位置覆盖后链不再递减
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+            depth -= 1;
+        }
+        if best_len < self.params.min_len { (0, 0) } else { (best_len, best_distance) }
+    }
+
+    /// 查找 `data[start..end]` 中的匹配，`data[..start]` 为已处理过的历史数据
+    ///
+    /// 返回匹配序列和最后剩余的字面量个数；匹配只引用 `end` 之前的数据。
+    pub fn parse(&mut self, data: &[u8], start: usize, end: usize) -> (Vec<Sequence>, usize) {
+        let mut sequences = Vec::new();
+        let mut literal_start = start;
+        let mut pos = start;
+        while pos < end {
+            let (mut len, mut distance) = self.longest_match(data, pos, end);
+            self.insert(data, pos);
+            if len == 0 {
+                pos += 1;
+                continue;
+            }
+            if self.params.lazy {
+                while len < self.params.nice_len && pos + 1 < end {
+                    let (next_len, next_distance) = self.longest_match(data, pos + 1, end);
+                    if next_len <= len {
+                        break;
+                    }
+                    pos += 1;
+                    self.insert(data, pos);
+                    (len, distance) = (next_len, next_distance);
+                }
+            }
+            sequences.push(Sequence { literal_len: pos - literal_start, match_len: len, distance });
+            for covered in pos + 1..pos + len {
+                self.insert(data, covered);
+            }
+            pos += len;
+            literal_start = pos;
+        }
+        (sequences, end - literal_start)
+    }
+
+    /// 丢弃 `buffer` 中超出回溯窗口的历史数据 (`processed` 之前的部分)，返回丢弃的字节数
+    pub fn trim(&mut self, buffer: &mut Vec<u8>, processed: usize) -> usize {
+        let window = self.window_size();
+        if processed < 2 * window {
+            return 0;
+        }
+        let shift = (processed - window) / window * window;
+        buffer.drain(..shift);
+        // 丢弃的是窗口大小的整数倍，链表的槽位不变
+        for entry in self.head.iter_mut().chain(self.chain.iter_mut()) {
+            *entry = entry.saturating_sub(shift as u32);
+        }
+        shift
+    }
+}
+
+/// 按位写出 (从每个字节的最低位开始，deflate和zstd都使用这种顺序)
+#[derive(Default)]
+pub struct BitWriter {
+    output: Vec<u8>,
+    buffer: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// 写出 `value` 的低 `count` 位 (不超过32)
+    pub fn bits(&mut self, value: u32, count: u32) {
+        self.buffer |= ((value as u64) & ((1u64 << count) - 1)) << self.bits;
+        self.bits += count;
+        while self.bits >= 8 {
+            self.output.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    /// 用0补齐到字节边界
+    pub fn align(&mut self) {
+        if self.bits > 0 {
+            self.output.push(self.buffer as u8);
+            self.buffer = 0;
+            self.bits = 0;
+        }
+    }
+
+    /// 按字节边界写出 (调用前需要对齐)
+    pub fn bytes(&mut self, data: &[u8]) {
+        debug_assert_eq!(self.bits, 0);
+        self.output.extend_from_slice(data);
+    }
+
+    /// 取出已经完整的字节
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+}
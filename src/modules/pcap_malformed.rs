@@ -0,0 +1,247 @@
+use crate::modules::pcap_generate::Rng;
+use crate::modules::pcap_packet_builder::{internet_checksum, ipv4, tcp};
+use crate::modules::pcap_packet_parser::{IP_PROTO_TCP, IP_PROTO_UDP};
+
+/// 以太网头长度
+const ETH_LEN: usize = 14;
+/// IPv4头的起始位置和长度
+const IP: usize = ETH_LEN;
+const IP_LEN: usize = 20;
+/// UDP或TCP头的起始位置
+const L4: usize = IP + IP_LEN;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_PSH: u8 = 0x08;
+const TCP_URG: u8 = 0x20;
+
+/// 畸形包的种类，每种只包含一处错误，其余字段 (包括其他校验和) 保持正确
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Malformation {
+    /// IP版本号不是4
+    BadVersion,
+    /// IP头长度小于20字节
+    ShortIhl,
+    /// IP头长度大于IP总长度
+    LongIhl,
+    /// IP总长度大于实际数据
+    TotalLengthTooLarge,
+    /// IP总长度小于IP头长度
+    TotalLengthTooSmall,
+    /// 帧在IP头中间结束
+    TruncatedHeader,
+    /// IP头校验和错误
+    BadIpChecksum,
+    /// 保留位置位，或DF与MF/片偏移同时出现
+    IllegalIpFlags,
+    /// UDP长度大于IP载荷
+    UdpLengthTooLarge,
+    /// UDP长度小于8字节
+    UdpLengthTooSmall,
+    /// SYN+FIN、SYN+RST、空标志、Xmas等非法的TCP标志组合
+    IllegalTcpFlags,
+    /// TCP头长度小于20字节或大于报文段长度
+    BadTcpDataOffset,
+    /// 内容不同且相互重叠的两个IP分片
+    OverlappingFragments,
+}
+
+impl Malformation {
+    pub const ALL: [Malformation; 13] = [
+        Malformation::BadVersion,
+        Malformation::ShortIhl,
+        Malformation::LongIhl,
+        Malformation::TotalLengthTooLarge,
+        Malformation::TotalLengthTooSmall,
+        Malformation::TruncatedHeader,
+        Malformation::BadIpChecksum,
+        Malformation::IllegalIpFlags,
+        Malformation::UdpLengthTooLarge,
+        Malformation::UdpLengthTooSmall,
+        Malformation::IllegalTcpFlags,
+        Malformation::BadTcpDataOffset,
+        Malformation::OverlappingFragments,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Malformation::BadVersion => "bad-version",
+            Malformation::ShortIhl => "short-ihl",
+            Malformation::LongIhl => "long-ihl",
+            Malformation::TotalLengthTooLarge => "total-length-too-large",
+            Malformation::TotalLengthTooSmall => "total-length-too-small",
+            Malformation::TruncatedHeader => "truncated-header",
+            Malformation::BadIpChecksum => "bad-ip-checksum",
+            Malformation::IllegalIpFlags => "illegal-ip-flags",
+            Malformation::UdpLengthTooLarge => "udp-length-too-large",
+            Malformation::UdpLengthTooSmall => "udp-length-too-small",
+            Malformation::IllegalTcpFlags => "illegal-tcp-flags",
+            Malformation::BadTcpDataOffset => "bad-tcp-data-offset",
+            Malformation::OverlappingFragments => "overlapping-fragments",
+        }
+    }
+
+    /// 随机选择一种；`max_frames` 为1时不选需要两个帧的种类
+    pub fn sample(rng: &mut Rng, max_frames: u64) -> Malformation {
+        // 需要两个帧的重叠分片排在最后
+        let kinds = if max_frames < 2 { &Malformation::ALL[..Malformation::ALL.len() - 1] } else { &Malformation::ALL[..] };
+        kinds[rng.below(kinds.len() as u64) as usize]
+    }
+}
+
+/// 把一个有效的 以太网 + IPv4 + UDP 帧改为指定种类的畸形包
+///
+/// 返回一个或多个帧 (重叠分片为两个)，以太网头始终有效。
+pub fn malformed_frames(frame: &[u8], kind: Malformation, rng: &mut Rng) -> Vec<Vec<u8>> {
+    let mut frame = frame.to_vec();
+    let ip_len = frame.len() - IP;
+    match kind {
+        Malformation::BadVersion => {
+            let version = [0u8, 1, 2, 3, 5, 7, 15][rng.below(7) as usize];
+            frame[IP] = (version << 4) | 5;
+            fix_ip_checksum(&mut frame);
+        }
+        Malformation::ShortIhl => {
+            frame[IP] = 0x40 | rng.below(5) as u8;
+            fix_ip_checksum(&mut frame);
+        }
+        Malformation::LongIhl => {
+            // 头长度为60字节，总长度和实际数据都不足60字节
+            let total = ip_len.min(IP_LEN + 8 + rng.below(24) as usize);
+            frame.truncate(IP + total);
+            frame[IP] = 0x4f;
+            frame[IP + 2..IP + 4].copy_from_slice(&(total as u16).to_be_bytes());
+            fix_ip_checksum(&mut frame);
+        }
+        Malformation::TotalLengthTooLarge => {
+            if ip_len >= u16::MAX as usize {
+                frame.truncate(frame.len() - 1 - rng.below(1000) as usize);
+            }
+            let claimed = (frame.len() - IP + 1 + rng.below(1500) as usize).min(u16::MAX as usize);
+            frame[IP + 2..IP + 4].copy_from_slice(&(claimed as u16).to_be_bytes());
+            fix_ip_checksum(&mut frame);
+        }
+        Malformation::TotalLengthTooSmall => {
+            frame[IP + 2..IP + 4].copy_from_slice(&(rng.below(IP_LEN as u64) as u16).to_be_bytes());
+            fix_ip_checksum(&mut frame);
+        }
+        Malformation::TruncatedHeader => {
+            frame.truncate(IP + 1 + rng.below(IP_LEN as u64 - 1) as usize);
+        }
+        Malformation::BadIpChecksum => {
+            let wrong = 1 + rng.below(u16::MAX as u64) as u16;
+            frame[IP + 10] ^= (wrong >> 8) as u8;
+            frame[IP + 11] ^= wrong as u8;
+        }
+        Malformation::IllegalIpFlags => {
+            // 第6、7字节: 3位标志 (保留、DF、MF) 和13位片偏移
+            let flags_offset: u16 = match rng.below(3) {
+                0 => 0x8000,
+                1 => 0x6000,
+                _ => 0x4000 | (1 + rng.below(0x1fff) as u16),
+            };
+            frame[IP + 6..IP + 8].copy_from_slice(&flags_offset.to_be_bytes());
+            fix_ip_checksum(&mut frame);
+        }
+        Malformation::UdpLengthTooLarge | Malformation::UdpLengthTooSmall => {
+            let actual = frame.len() - L4;
+            let claimed = if kind == Malformation::UdpLengthTooLarge {
+                (actual + 1 + rng.below(1500) as usize).min(u16::MAX as usize)
+            } else {
+                rng.below(8) as usize
+            };
+            frame[L4 + 4..L4 + 6].copy_from_slice(&(claimed as u16).to_be_bytes());
+            // 校验和为0表示不校验，避免同时出现校验和错误
+            frame[L4 + 6..L4 + 8].fill(0);
+        }
+        Malformation::IllegalTcpFlags => {
+            let flags = [
+                TCP_SYN | TCP_FIN,
+                TCP_SYN | TCP_RST,
+                TCP_FIN | TCP_RST,
+                0,
+                TCP_FIN | TCP_PSH | TCP_URG,
+                0xff,
+            ][rng.below(6) as usize];
+            frame = tcp_frame(&frame, flags);
+        }
+        Malformation::BadTcpDataOffset => {
+            frame = tcp_frame(&frame, TCP_PSH);
+            let segment_len = frame.len() - L4;
+            // 头长度的单位为4字节: 小于5，或超出报文段
+            let data_offset = if rng.below(2) == 0 || segment_len >= 60 {
+                rng.below(5) as u8
+            } else {
+                (segment_len / 4 + 1).max(6) as u8
+            };
+            frame[L4 + 12] = data_offset << 4;
+            fix_tcp_checksum(&mut frame);
+        }
+        Malformation::OverlappingFragments => return overlapping_fragments(&frame, rng),
+    }
+    vec![frame]
+}
+
+/// 重新计算IPv4头校验和
+fn fix_ip_checksum(frame: &mut [u8]) {
+    frame[IP + 10..IP + 12].fill(0);
+    let checksum = internet_checksum(&frame[IP..IP + IP_LEN]);
+    frame[IP + 10..IP + 12].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// 按实际的报文段重新计算TCP校验和 (不依赖头中的长度字段)
+fn fix_tcp_checksum(frame: &mut [u8]) {
+    frame[L4 + 16..L4 + 18].fill(0);
+    let segment = &frame[L4..];
+    let mut pseudo = Vec::with_capacity(12 + segment.len());
+    pseudo.extend_from_slice(&frame[IP + 12..IP + 20]);
+    pseudo.extend_from_slice(&[0, IP_PROTO_TCP]);
+    pseudo.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+    pseudo.extend_from_slice(segment);
+    let checksum = internet_checksum(&pseudo);
+    frame[L4 + 16..L4 + 18].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// 用UDP帧的地址、端口和载荷构造同样长度的TCP帧 (校验和正确)
+fn tcp_frame(frame: &[u8], flags: u8) -> Vec<u8> {
+    let address = |offset: usize| -> [u8; 4] { frame[offset..offset + 4].try_into().unwrap() };
+    let port = |offset: usize| u16::from_be_bytes([frame[offset], frame[offset + 1]]);
+    // TCP头比UDP头长12字节，载荷相应缩短以保持帧长
+    let payload = &frame[(L4 + 20).min(frame.len())..];
+    let segment = tcp(port(L4), port(L4 + 2), 1, 1, flags, &[], payload);
+    let packet = ipv4(address(IP + 12), address(IP + 16), IP_PROTO_TCP, &segment);
+    let mut tcp_frame = frame[..ETH_LEN].to_vec();
+    tcp_frame.extend_from_slice(&packet);
+    tcp_frame
+}
+
+/// 同一IP标识的两个分片: 第一个为数据报的前16字节，第二个从第8字节开始，重叠部分内容不同
+fn overlapping_fragments(frame: &[u8], rng: &mut Rng) -> Vec<Vec<u8>> {
+    let mut datagram = frame[L4..].to_vec();
+    if datagram.len() < 24 {
+        datagram.resize(24, 0);
+        let length = datagram.len() as u16;
+        datagram[4..6].copy_from_slice(&length.to_be_bytes());
+    }
+    let id = rng.below(u16::MAX as u64 + 1) as u16;
+    let first = &datagram[..16];
+    let mut second = datagram[8..].to_vec();
+    for byte in &mut second[..8] {
+        *byte ^= 0xff;
+    }
+    // 片偏移以8字节为单位；第一个分片置MF
+    [(first.to_vec(), 0x2000u16), (second, 1)]
+        .into_iter()
+        .map(|(payload, flags_offset)| {
+            let mut fragment = frame[..L4].to_vec();
+            fragment.extend_from_slice(&payload);
+            fragment[IP + 2..IP + 4].copy_from_slice(&((IP_LEN + payload.len()) as u16).to_be_bytes());
+            fragment[IP + 4..IP + 6].copy_from_slice(&id.to_be_bytes());
+            fragment[IP + 6..IP + 8].copy_from_slice(&flags_offset.to_be_bytes());
+            fragment[IP + 9] = IP_PROTO_UDP;
+            fix_ip_checksum(&mut fragment);
+            fragment
+        })
+        .collect()
+}
@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, HashMap};
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{FlowKey, parse_packet, read_u16};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{Severity, Table, Verbosity, print_fields, print_heading, print_section, print_separator, print_verdict, verbosity};
+
+/// 流详情表中最多列出的流数 (详细模式不限制)
+const MAX_FLOW_ROWS: usize = 20;
+
+/// 解析VLAN的MTU: `VLAN=MTU`，例如 `100=9000`
+pub fn parse_vlan_mtu(text: &str) -> Result<(u16, usize), String> {
+    let (vlan, mtu) = text.split_once('=').ok_or_else(|| format!("应为 VLAN=MTU: {}", text))?;
+    let vlan: u16 = vlan.trim().parse().ok().filter(|&vlan| vlan < 4096).ok_or_else(|| format!("无效的VLAN ID: {}", vlan))?;
+    let mtu: usize = mtu.trim().parse().ok().filter(|&mtu| mtu > 0).ok_or_else(|| format!("无效的MTU: {}", mtu))?;
+    Ok((vlan, mtu))
+}
+
+/// 一个VLAN (或无VLAN) 的统计
+#[derive(Default)]
+struct VlanStats {
+    packets: u64,
+    oversize: u64,
+    largest: usize,
+}
+
+/// 一个流中超过MTU的包
+struct OversizeFlow {
+    packets: u64,
+    largest: usize,
+    /// 第一个超过MTU的包的序号 (从1开始)
+    first: u64,
+}
+
+/// 报告IP长度超过MTU的包，按VLAN分别使用不同的MTU
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `mtu`: 没有VLAN标签或未单独指定的VLAN使用的MTU
+/// - `vlan_mtus`: 各VLAN (按最外层标签) 的MTU
+///
+/// IP长度取自IP头的长度字段 (为0时按原始包长计算)，不受抓包截断的影响；非IP包不检查。
+/// 这些包重放到MTU较小的测试网络时会被丢弃或需要分片。
+pub fn check_mtu(input_path: &str, mtu: usize, vlan_mtus: &[(u16, usize)]) -> Result<()> {
+    let vlan_mtus: HashMap<u16, usize> = vlan_mtus.iter().copied().collect();
+    let mtu_of = |vlan: Option<u16>| vlan.and_then(|vlan| vlan_mtus.get(&vlan).copied()).unwrap_or(mtu);
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut vlans: BTreeMap<Option<u16>, VlanStats> = BTreeMap::new();
+    // (VLAN, 五元组) -> 超过MTU的包，同一五元组出现在不同VLAN时分别统计
+    let mut flows: HashMap<(Option<u16>, FlowKey), OversizeFlow> = HashMap::new();
+    let mut packet_count = 0u64;
+    let mut non_ip = 0u64;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let parsed = parse_packet(&packet.data, interface.linktype as u32);
+        let (Some(ip), Some(flow)) = (&parsed.ip, parsed.flow_key()) else {
+            non_ip += 1;
+            continue;
+        };
+        let vlan = parsed.vlan_offset.map(|offset| read_u16(&packet.data, offset) & 0x0fff);
+        let ip_len = if ip.total_len == 0 { (packet.orig_len as usize).saturating_sub(ip.offset) } else { ip.total_len };
+
+        let stats = vlans.entry(vlan).or_default();
+        stats.packets += 1;
+        if ip_len <= mtu_of(vlan) {
+            continue;
+        }
+        stats.oversize += 1;
+        stats.largest = stats.largest.max(ip_len);
+        let entry = flows.entry((vlan, flow)).or_insert(OversizeFlow { packets: 0, largest: 0, first: packet_count });
+        entry.packets += 1;
+        entry.largest = entry.largest.max(ip_len);
+    }
+
+    let oversize: u64 = vlans.values().map(|stats| stats.oversize).sum();
+    let vlan_name = |vlan: Option<u16>| vlan.map_or("(无)".to_string(), |vlan| vlan.to_string());
+    print_heading(&format!("MTU检查结果: {}", input_path));
+    print_fields(&[
+        ("数据包数", format!("{} (非IP包: {}，不检查)", packet_count, non_ip), None),
+        ("默认MTU", mtu.to_string(), None),
+        (
+            "超过MTU的包",
+            format!("{} (涉及 {} 个流)", oversize, flows.len()),
+            (oversize > 0).then_some(Severity::Warning),
+        ),
+    ]);
+
+    if !vlans.is_empty() {
+        print_section("按VLAN统计:");
+        let mut table = Table::new(&["VLAN", "MTU", "IP包数", "超过MTU", "最大IP长度"]).right_align(&[1, 2, 3, 4]);
+        for (&vlan, stats) in &vlans {
+            table.add_row(vec![
+                vlan_name(vlan),
+                mtu_of(vlan).to_string(),
+                stats.packets.to_string(),
+                stats.oversize.to_string(),
+                if stats.oversize > 0 { stats.largest.to_string() } else { "-".to_string() },
+            ]);
+        }
+        table.print();
+    }
+
+    if !flows.is_empty() {
+        let mut flows: Vec<((Option<u16>, FlowKey), OversizeFlow)> = flows.into_iter().collect();
+        flows.sort_by(|(a_key, a), (b_key, b)| b.packets.cmp(&a.packets).then(a.first.cmp(&b.first)).then(a_key.cmp(b_key)));
+        let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_FLOW_ROWS };
+        print_section("超过MTU的流:");
+        let mut table = Table::new(&["五元组", "VLAN", "包数", "最大IP长度", "首个包序号"]).right_align(&[2, 3, 4]);
+        for ((vlan, key), flow) in flows.iter().take(max_rows) {
+            table.add_row(vec![
+                key.to_string(),
+                vlan_name(*vlan),
+                flow.packets.to_string(),
+                flow.largest.to_string(),
+                flow.first.to_string(),
+            ]);
+        }
+        table.print();
+        if flows.len() > max_rows {
+            println!("  ... 还有 {} 个流 (使用 --verbose 查看全部)", flows.len() - max_rows);
+        }
+    }
+
+    print_separator();
+    if oversize == 0 {
+        print_verdict(Severity::Ok, "没有超过MTU的包");
+    } else {
+        print_verdict(
+            Severity::Warning,
+            &format!("{} 个包超过MTU，重放到相同MTU的网络时会被丢弃或需要分片", oversize),
+        );
+    }
+    Ok(())
+}
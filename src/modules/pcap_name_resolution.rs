@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::BufWriter;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use anyhow::Result;
+use log::info;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{Transport, parse_packet, read_u16};
+use crate::modules::pcap_pcapng::{
+    BLOCK_NAME_RESOLUTION, CaptureReader, PcapngBlock, PcapngOption, PcapngWriter, SHB_USERAPPL,
+};
+use crate::modules::pcap_report::{Table, print_fields, print_heading};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 名称解析块记录类型
+const NRB_RECORD_END: u16 = 0;
+const NRB_RECORD_IPV4: u16 = 1;
+const NRB_RECORD_IPV6: u16 = 2;
+
+const DNS_PORT: u16 = 53;
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_AAAA: u16 = 28;
+
+/// 地址到名称的映射
+type NameTable = BTreeMap<IpAddr, BTreeSet<String>>;
+
+/// 提取名称解析数据，并可把DNS应答中的映射写入输出文件
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出pcapng文件路径 (None表示只打印)
+/// - `from_dns`: 从DNS应答 (A/AAAA记录) 中收集映射
+///
+/// # 功能
+/// 1. 打印pcapng名称解析块中已有的地址和名称
+/// 2. DNS应答中的地址同时对应应答记录的名称和查询的名称
+/// 3. 输出时原有名称解析块原样保留，新的映射写入节头之后的一个名称解析块
+pub fn pcap_names(input_path: &str, output_path: Option<&str>, from_dns: bool) -> Result<()> {
+    let mut existing = NameTable::new();
+    let mut observed = NameTable::new();
+
+    let mut reader = CaptureReader::open(input_path)?;
+    while let Some(block) = reader.next_block()? {
+        match block {
+            PcapngBlock::Other { block_type: BLOCK_NAME_RESOLUTION, body, big_endian } => {
+                for (addr, name) in parse_name_records(&body, big_endian) {
+                    existing.entry(addr).or_default().insert(name);
+                }
+            }
+            PcapngBlock::Packet(packet) if from_dns => {
+                let Some(interface) = reader.interface(packet.interface_id) else {
+                    continue;
+                };
+                for (addr, name) in dns_answers(&packet.data, interface.linktype as u32) {
+                    observed.entry(addr).or_default().insert(name);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 只写出名称解析块中还没有的映射
+    let mut added = NameTable::new();
+    for (addr, names) in &observed {
+        let known = existing.get(addr);
+        let new_names: BTreeSet<String> = names
+            .iter()
+            .filter(|name| known.is_none_or(|known| !known.contains(*name)))
+            .cloned()
+            .collect();
+        if !new_names.is_empty() {
+            added.insert(*addr, new_names);
+        }
+    }
+
+    print_heading("名称解析数据:");
+    let mut fields = vec![("名称解析块中的地址", format!("{} 个", existing.len()), None)];
+    if from_dns {
+        fields.push(("DNS应答中的地址", format!("{} 个 (新增 {} 个)", observed.len(), added.len()), None));
+    }
+    print_fields(&fields);
+    let mut all = existing;
+    for (addr, names) in observed {
+        all.entry(addr).or_default().extend(names);
+    }
+    if !all.is_empty() {
+        let mut table = Table::new(&["地址", "名称"]);
+        for (addr, names) in &all {
+            table.add_row(vec![addr.to_string(), names.iter().cloned().collect::<Vec<_>>().join(", ")]);
+        }
+        table.print();
+    }
+
+    if let Some(output_path) = output_path {
+        write_with_names(input_path, output_path, &added)?;
+    }
+
+    Ok(())
+}
+
+/// 复制输入并在第一个节头之后插入名称解析块
+fn write_with_names(input_path: &str, output_path: &str, names: &NameTable) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let out_file = BufWriter::new(create_output(output_path, compression())?);
+    let mut writer = match reader.next_block()? {
+        Some(PcapngBlock::Section(mut options)) => {
+            if options.is_empty() {
+                options.push(PcapngOption::string(SHB_USERAPPL, "pcap-editor names"));
+            }
+            PcapngWriter::new(out_file, &options)?
+        }
+        _ => anyhow::bail!(PcapError::InvalidFormat("不是有效的pcapng文件: 缺少节头块".to_string())),
+    };
+    if !names.is_empty() {
+        writer.write_other(BLOCK_NAME_RESOLUTION, &build_name_records(names), false)?;
+    }
+
+    let mut packet_count = 0;
+    let mut digest = OutputDigest::default();
+    while let Some(block) = reader.next_block()? {
+        writer.write_block(&block)?;
+        if let PcapngBlock::Packet(packet) = &block {
+            packet_count += 1;
+            digest.record_pcapng(packet, reader.interface(packet.interface_id));
+        }
+    }
+    writer.into_inner().into_inner()
+        .map_err(|e| PcapError::WriteFailure(format!("写入pcapng文件失败: {}", e.error())))?
+        .finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功写出名称解析数据: 新增地址={}, 包数={}, 输出文件={}",
+        names.len(),
+        packet_count,
+        output_path
+    );
+
+    Ok(())
+}
+
+/// 解析名称解析块的记录部分，返回 (地址, 名称)
+fn parse_name_records(body: &[u8], big_endian: bool) -> Vec<(IpAddr, String)> {
+    let read = |offset: usize| {
+        let bytes = [body[offset], body[offset + 1]];
+        if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    };
+
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= body.len() {
+        let record_type = read(offset);
+        let len = read(offset + 2) as usize;
+        let Some(value) = body.get(offset + 4..offset + 4 + len) else {
+            break;
+        };
+        let (addr, names) = match record_type {
+            NRB_RECORD_END => break,
+            NRB_RECORD_IPV4 if len > 4 => {
+                let octets: [u8; 4] = value[..4].try_into().unwrap();
+                (IpAddr::V4(Ipv4Addr::from(octets)), &value[4..])
+            }
+            NRB_RECORD_IPV6 if len > 16 => {
+                let octets: [u8; 16] = value[..16].try_into().unwrap();
+                (IpAddr::V6(Ipv6Addr::from(octets)), &value[16..])
+            }
+            _ => {
+                offset += 4 + len.div_ceil(4) * 4;
+                continue;
+            }
+        };
+        for name in names.split(|&b| b == 0).filter(|name| !name.is_empty()) {
+            entries.push((addr, String::from_utf8_lossy(name).into_owned()));
+        }
+        offset += 4 + len.div_ceil(4) * 4;
+    }
+    entries
+}
+
+/// 生成名称解析块的记录部分 (无选项)
+fn build_name_records(names: &NameTable) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (addr, addr_names) in names {
+        let (record_type, mut value) = match addr {
+            IpAddr::V4(v4) => (NRB_RECORD_IPV4, v4.octets().to_vec()),
+            IpAddr::V6(v6) => (NRB_RECORD_IPV6, v6.octets().to_vec()),
+        };
+        for name in addr_names {
+            value.extend_from_slice(name.as_bytes());
+            value.push(0);
+        }
+        body.extend_from_slice(&record_type.to_le_bytes());
+        body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        body.extend_from_slice(&value);
+        body.resize(body.len().div_ceil(4) * 4, 0);
+    }
+    body.extend_from_slice(&NRB_RECORD_END.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body
+}
+
+/// 从DNS应答包中提取 (地址, 名称)
+fn dns_answers(data: &[u8], linktype: u32) -> Vec<(IpAddr, String)> {
+    let parsed = parse_packet(data, linktype);
+    let payload = &data[parsed.payload_range(data.len())];
+    let message = match parsed.transport {
+        Some(Transport::Udp(udp)) if udp.src_port == DNS_PORT => payload,
+        // TCP上的DNS消息带2字节长度前缀，只处理完整包含在一个段中的消息
+        Some(Transport::Tcp(tcp)) if tcp.src_port == DNS_PORT && payload.len() >= 2 => {
+            let len = read_u16(payload, 0) as usize;
+            match payload.get(2..2 + len) {
+                Some(message) => message,
+                None => return Vec::new(),
+            }
+        }
+        _ => return Vec::new(),
+    };
+    parse_dns_response(message).unwrap_or_default()
+}
+
+fn parse_dns_response(message: &[u8]) -> Option<Vec<(IpAddr, String)>> {
+    if message.len() < 12 || message[2] & 0x80 == 0 {
+        return None;
+    }
+    let questions = read_u16(message, 4);
+    let answers = read_u16(message, 6);
+
+    let mut offset = 12;
+    let mut query_name = None;
+    for _ in 0..questions {
+        let (name, next) = read_dns_name(message, offset)?;
+        query_name.get_or_insert(name);
+        offset = next + 4;
+    }
+
+    let mut entries = Vec::new();
+    for _ in 0..answers {
+        let (name, next) = read_dns_name(message, offset)?;
+        let header = message.get(next..next + 10)?;
+        let record_type = read_u16(header, 0);
+        let rdata_len = read_u16(header, 8) as usize;
+        let rdata = message.get(next + 10..next + 10 + rdata_len)?;
+        offset = next + 10 + rdata_len;
+
+        let addr = match (record_type, rdata_len) {
+            (DNS_TYPE_A, 4) => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(rdata).ok()?)),
+            (DNS_TYPE_AAAA, 16) => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(rdata).ok()?)),
+            _ => continue,
+        };
+        // 经过CNAME时，查询的名称比应答记录的名称更有意义
+        if let Some(query_name) = &query_name
+            && *query_name != name
+        {
+            entries.push((addr, query_name.clone()));
+        }
+        entries.push((addr, name));
+    }
+
+    Some(entries)
+}
+
+/// 读取DNS名称 (支持压缩指针)，返回名称和名称之后的偏移
+pub fn read_dns_name(message: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    // 限制跳转次数，防止指针循环
+    for _ in 0..128 {
+        let len = *message.get(offset)? as usize;
+        match len {
+            0 => {
+                let name = if labels.is_empty() { ".".to_string() } else { labels.join(".") };
+                return Some((name, end.unwrap_or(offset + 1)));
+            }
+            0xC0..=0xFF => {
+                let pointer = (len & 0x3F) << 8 | *message.get(offset + 1)? as usize;
+                end.get_or_insert(offset + 2);
+                offset = pointer;
+            }
+            1..=63 => {
+                let label = message.get(offset + 1..offset + 1 + len)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset += 1 + len;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
@@ -0,0 +1,122 @@
+use pcap_file::Packet;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_content_hash::{HashIgnore, canonicalize};
+use crate::modules::pcap_disorder_fix::sort_by_timestamp;
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_time_anchor::{split_timestamp_ticks, ticks_per_second, timestamp_ticks};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 规范化步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum NormalizeStep {
+    /// IPv4头校验和与TCP/UDP/ICMP校验和清零
+    Checksums,
+    /// 删除IP包之后的以太网填充和FCS
+    Padding,
+    /// IPv4的TTL和IPv6的跳数限制清零
+    Ttl,
+    /// IPv4标识字段清零
+    IpId,
+    /// 按时间戳稳定排序
+    Sort,
+    /// 时间平移到从0开始 (最早的包时间戳为0)
+    Rebase,
+}
+
+/// 一次性应用一组规范化，输出便于逐字节比较或计算哈希的文件
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `steps`: 应用的规范化步骤
+///
+/// # 说明
+/// 1. 字段清零与 `hash --ignore` 对应的字段相同，清零后的校验和不再有效，输出只用于比较
+/// 2. 排序与 `fix-disorder` 相同 (稳定排序，超出 `--max-memory` 时分段归并)
+/// 3. 平移时间时不排序也以最早的包为0点 (需要多读一遍输入)
+pub fn pcap_normalize(input_path: &str, output_path: &str, steps: &[NormalizeStep]) -> Result<()> {
+    let applied = |step: NormalizeStep| steps.contains(&step);
+    let ignored = |field: HashIgnore| match field {
+        HashIgnore::Checksums => applied(NormalizeStep::Checksums),
+        HashIgnore::Padding => applied(NormalizeStep::Padding),
+        HashIgnore::Ttl => applied(NormalizeStep::Ttl),
+        HashIgnore::IpId => applied(NormalizeStep::IpId),
+        HashIgnore::Timestamps | HashIgnore::Order => false,
+    };
+
+    // 不排序时先找出最早的时间戳；排序时第一个输出的包就是最早的
+    // 时间以文件的精度计 (微秒或纳秒)
+    let mut base = None;
+    if applied(NormalizeStep::Rebase) && !applied(NormalizeStep::Sort) {
+        let pcap_reader = PacketReader::open(input_path)?;
+        let ticks_per_second = ticks_per_second(&pcap_reader.header);
+        base = pcap_reader.map(|packet| timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second)).min();
+    }
+
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let ticks_per_second = ticks_per_second(&pcap_reader.header);
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut written = 0u64;
+    let mut moved = 0u64;
+    let mut stripped_bytes = 0u64;
+    let mut emit = |origin: u64, packet: &Packet| -> Result<()> {
+        let mut packet = packet.clone();
+        if origin != written {
+            moved += 1;
+        }
+        written += 1;
+
+        let data = packet.data.to_mut();
+        let original_len = data.len();
+        canonicalize(data, linktype, &ignored);
+        let removed = original_len - data.len();
+        if removed > 0 {
+            stripped_bytes += removed as u64;
+            packet.header.incl_len = data.len() as u32;
+            packet.header.orig_len = packet.header.orig_len.saturating_sub(removed as u32);
+        }
+
+        if applied(NormalizeStep::Rebase) {
+            let time = timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second);
+            let base = *base.get_or_insert(time);
+            let (new_sec, new_usec) = split_timestamp_ticks(time - base, ticks_per_second)?;
+            packet.header.ts_sec = new_sec;
+            packet.header.ts_usec = new_usec;
+        }
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", written, e)))?;
+        digest.record_pcap(&packet);
+        Ok(())
+    };
+
+    let packet_count = if applied(NormalizeStep::Sort) {
+        sort_by_timestamp(&mut pcap_reader, &mut emit)?
+    } else {
+        let mut count = 0;
+        for packet in pcap_reader.by_ref() {
+            emit(count, &packet)?;
+            count += 1;
+        }
+        count
+    };
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功规范化: 包数={}, 步骤={:?}, 移动的包数={}, 删除填充={}字节, 输出文件={}",
+        packet_count, steps, moved, stripped_bytes, output_path
+    );
+
+    Ok(())
+}
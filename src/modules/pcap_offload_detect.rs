@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{Transport, ip_checksum_valid, l4_checksum_valid, parse_packet, read_u16};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{Severity, Table, print_fields, print_heading, print_section, print_separator, print_verdict};
+
+/// 每种迹象最多记录的示例包序号
+const MAX_EXAMPLES: usize = 5;
+/// 连续相同的纯ACK至少有这么多个时视为一段
+const MIN_ACK_RUN: u64 = 3;
+const TCP_FLAG_ACK: u8 = 0x10;
+
+/// 一种卸载迹象的统计
+#[derive(Default)]
+struct Artifact {
+    count: u64,
+    /// 示例包序号 (从1开始)
+    examples: Vec<u64>,
+}
+
+impl Artifact {
+    fn record(&mut self, number: u64) {
+        self.count += 1;
+        if self.examples.len() < MAX_EXAMPLES {
+            self.examples.push(number);
+        }
+    }
+}
+
+/// 校验和错误的统计 (按发送方地址计数，定位做了发送卸载的抓包主机)
+#[derive(Default)]
+struct ChecksumArtifact {
+    artifact: Artifact,
+    /// 校验和为0的包数
+    zero: u64,
+    by_source: HashMap<IpAddr, u64>,
+}
+
+impl ChecksumArtifact {
+    fn record(&mut self, number: u64, source: IpAddr, zero: bool) {
+        self.artifact.record(number);
+        self.zero += zero as u64;
+        *self.by_source.entry(source).or_default() += 1;
+    }
+
+    /// 校验和错误最多的发送方及其包数
+    fn top_source(&self) -> Option<(IpAddr, u64)> {
+        self.by_source.iter().map(|(&source, &count)| (source, count)).max_by_key(|&(source, count)| (count, source))
+    }
+}
+
+/// 检测抓包端网卡卸载 (TSO/GSO/GRO/LRO、校验和卸载) 留下的痕迹
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `mtu`: 链路MTU，IP包长度超过MTU视为卸载合并的巨型帧
+///
+/// # 检测内容
+/// 1. 巨型帧: IP长度超过MTU (TSO/GSO发送前、GRO/LRO接收后合并的报文段)，或IPv4总长度为0
+/// 2. 校验和为0或错误: IPv4头校验和、TCP/UDP校验和 (发送校验和卸载时由网卡填写)
+/// 3. 连续相同的纯ACK: 文件中相邻的内容完全相同的纯ACK至少3个 (GRO/LRO拆分或重复镜像)
+///
+/// 抓包存在以上迹象时不能代表线路上的真实报文，用作 `compare` 的基准会产生误报。
+pub fn detect_offload(input_path: &str, mtu: usize) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut giant = Artifact::default();
+    let mut largest_ip = 0usize;
+    let mut ip_checksum = ChecksumArtifact::default();
+    let mut l4_checksum = ChecksumArtifact::default();
+    let mut ack_runs = Artifact::default();
+    let mut ack_run_packets = 0u64;
+    let mut longest_run = 0u64;
+    let mut packet_count = 0u64;
+    let mut ip_packets = 0u64;
+    // 上一个包为纯ACK时的内容和当前连续相同的个数
+    let mut previous_ack: Option<Vec<u8>> = None;
+    let mut run = 0u64;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+
+        let mut pure_ack = false;
+        if let Some(ip) = &parsed.ip {
+            ip_packets += 1;
+            // IPv4总长度为0: 部分驱动在TSO时不填写长度字段
+            let ip_len = if ip.total_len == 0 { data.len().saturating_sub(ip.offset) } else { ip.total_len };
+            if ip_len > mtu || (ip.version == 4 && ip.total_len == 0) {
+                giant.record(packet_count);
+                largest_ip = largest_ip.max(ip_len);
+            }
+            if ip_checksum_valid(data, &parsed) == Some(false) {
+                ip_checksum.record(packet_count, ip.src, read_u16(data, ip.offset + 10) == 0);
+            }
+            let checksum_offset = match parsed.transport {
+                Some(Transport::Tcp(tcp)) => Some(tcp.offset + 16),
+                Some(Transport::Udp(udp)) => Some(udp.offset + 6),
+                _ => None,
+            };
+            if let Some(checksum_offset) = checksum_offset
+                && l4_checksum_valid(data, &parsed) == Some(false)
+            {
+                l4_checksum.record(packet_count, ip.src, read_u16(data, checksum_offset) == 0);
+            }
+            if let Some(Transport::Tcp(tcp)) = parsed.transport {
+                pure_ack = data[tcp.offset + 13] == TCP_FLAG_ACK && parsed.payload_range(data.len()).is_empty();
+            }
+        }
+
+        // 连续相同的纯ACK
+        if pure_ack && previous_ack.as_deref() == Some(data.as_slice()) {
+            run += 1;
+        } else {
+            if run >= MIN_ACK_RUN {
+                ack_run_packets += run;
+            }
+            run = if pure_ack { 1 } else { 0 };
+            previous_ack = pure_ack.then(|| data.to_vec());
+        }
+        if run == MIN_ACK_RUN {
+            ack_runs.record(packet_count + 1 - MIN_ACK_RUN);
+        }
+        longest_run = longest_run.max(run);
+    }
+    if run >= MIN_ACK_RUN {
+        ack_run_packets += run;
+    }
+    if longest_run < MIN_ACK_RUN {
+        longest_run = 0;
+    }
+
+    print_heading(&format!("网卡卸载痕迹检测结果: {}", input_path));
+    let warning = |count: u64| (count > 0).then_some(Severity::Warning);
+    let checksum_text = |checksum: &ChecksumArtifact| {
+        let mut text = format!("{} (其中为0: {})", checksum.artifact.count, checksum.zero);
+        if let Some((source, count)) = checksum.top_source() {
+            text.push_str(&format!("，最多的发送方: {} ({} 个)", source, count));
+        }
+        text
+    };
+    let mut fields = vec![
+        ("数据包数", format!("{} (IP包: {})", packet_count, ip_packets), None),
+        ("MTU", mtu.to_string(), None),
+        ("超过MTU的巨型帧", giant.count.to_string(), warning(giant.count)),
+    ];
+    if giant.count > 0 {
+        fields.push(("最大IP长度", format!("{} 字节", largest_ip), None));
+    }
+    fields.push(("IP头校验和错误", checksum_text(&ip_checksum), warning(ip_checksum.artifact.count)));
+    fields.push(("TCP/UDP校验和错误", checksum_text(&l4_checksum), warning(l4_checksum.artifact.count)));
+    fields.push((
+        "连续相同的纯ACK",
+        format!("{} 段 (共 {} 个包，最长 {} 个)", ack_runs.count, ack_run_packets, longest_run),
+        warning(ack_runs.count),
+    ));
+    print_fields(&fields);
+
+    let artifacts = [
+        ("巨型帧 (TSO/GSO/GRO/LRO)", &giant),
+        ("IP头校验和错误", &ip_checksum.artifact),
+        ("TCP/UDP校验和错误 (校验和卸载)", &l4_checksum.artifact),
+        ("连续相同的纯ACK (每段的第一个包)", &ack_runs),
+    ];
+    if artifacts.iter().any(|(_, artifact)| artifact.count > 0) {
+        print_section("示例:");
+        let mut table = Table::new(&["迹象", "数量", "示例包序号"]).right_align(&[1]);
+        for (name, artifact) in artifacts.iter().filter(|(_, artifact)| artifact.count > 0) {
+            let examples: Vec<String> = artifact.examples.iter().map(u64::to_string).collect();
+            table.add_row(vec![name.to_string(), artifact.count.to_string(), examples.join(", ")]);
+        }
+        table.print();
+    }
+
+    print_separator();
+    if artifacts.iter().all(|(_, artifact)| artifact.count == 0) {
+        print_verdict(Severity::Ok, "未发现网卡卸载的痕迹");
+    } else {
+        print_verdict(
+            Severity::Warning,
+            "抓包中有网卡卸载的痕迹，不是线路上的真实报文，用作 compare 的基准可能产生误报",
+        );
+        print_verdict(
+            Severity::Warning,
+            "可在抓包前用 ethtool -K <网卡> tso off gso off gro off lro off tx off rx off 关闭卸载",
+        );
+    }
+
+    Ok(())
+}
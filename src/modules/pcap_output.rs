@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_gzip::GzipEncoder;
+use crate::modules::pcap_zstd::ZstdEncoder;
+
+/// 输出文件的压缩方式 (`--compress`)
+static COMPRESSION: Mutex<Compression> = Mutex::new(Compression::None);
+
+/// 输出文件的压缩方式和级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    /// gzip，级别1-9
+    Gzip(u32),
+    /// zstd，级别1-19
+    Zstd(u32),
+}
+
+impl Compression {
+    /// 自动命名的输出文件在扩展名后追加的后缀
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip(_) => ".gz",
+            Compression::Zstd(_) => ".zst",
+        }
+    }
+}
+
+/// 解析 `gzip|zstd[:级别]`，例如 `gzip`、`zstd:19`；默认级别与gzip和zstd命令相同 (6和3)
+pub fn parse_compression(text: &str) -> Result<Compression, String> {
+    let text = text.trim().to_ascii_lowercase();
+    let (method, level) = match text.split_once(':') {
+        Some((method, level)) => {
+            let level: u32 = level.trim().parse().map_err(|_| format!("无效的压缩级别: {}", level))?;
+            (method.trim(), Some(level))
+        }
+        None => (text.as_str(), None),
+    };
+    let (compression, max_level) = match method {
+        "gzip" | "gz" => (Compression::Gzip(level.unwrap_or(6)), 9),
+        "zstd" | "zst" => (Compression::Zstd(level.unwrap_or(3)), 19),
+        other => return Err(format!("未知的压缩方式: {} (可选 gzip、zstd)", other)),
+    };
+    if let Some(level) = level
+        && !(1..=max_level).contains(&level)
+    {
+        return Err(format!("{} 的压缩级别必须在 1-{} 之间: {}", method, max_level, level));
+    }
+    Ok(compression)
+}
+
+/// 设置输出文件的压缩方式
+pub fn set_compression(compression: Compression) {
+    *COMPRESSION.lock().unwrap() = compression;
+}
+
+/// `--compress` 指定的压缩方式
+pub fn compression() -> Compression {
+    *COMPRESSION.lock().unwrap()
+}
+
+/// 输出文件: 边写入边压缩
+///
+/// 压缩器缓冲未满一个块的数据，写完后调用 [`Output::finish`] 写出剩余的数据和压缩格式的尾部。
+/// 不压缩时与直接写入文件相同，不缓冲。
+///
+/// 没有调用 `finish` 就丢弃 (命令出错，例如输入被截断) 时，[`create_output`] 创建的文件被删除，
+/// 不留下不完整的输出；[`Output::keep_partial`] 和 [`Output::new`] 的输出保留已写出的部分。
+pub struct Output {
+    sink: Sink,
+    finished: bool,
+    /// 未完成时删除的文件
+    path: Option<String>,
+}
+
+enum Sink {
+    File(File),
+    Gzip(Box<GzipEncoder<File>>),
+    Zstd(Box<ZstdEncoder<File>>),
+}
+
+/// 创建输出文件，按 `compression` 压缩 (命令的输出使用 [`compression`]，即 `--compress` 指定的方式)
+pub fn create_output(path: &str, compression: Compression) -> Result<Output> {
+    let file = File::create(Path::new(path))
+        .map_err(|e| PcapError::create_failed(path, e))?;
+    let mut output = Output::new(file, compression).map_err(|e| PcapError::create_failed(path, e))?;
+    output.path = Some(path.to_string());
+    Ok(output)
+}
+
+impl Output {
+    /// 把已打开的文件作为输出 (写入位置之后按 `compression` 压缩)
+    pub fn new(file: File, compression: Compression) -> io::Result<Output> {
+        let sink = match compression {
+            Compression::None => Sink::File(file),
+            Compression::Gzip(level) => Sink::Gzip(Box::new(GzipEncoder::new(file, level)?)),
+            Compression::Zstd(level) => Sink::Zstd(Box::new(ZstdEncoder::new(file, level)?)),
+        };
+        Ok(Output { sink, finished: false, path: None })
+    }
+
+    /// 出错时保留已写出的部分 (实时抓包的数据、保存检查点的输出)
+    pub fn keep_partial(mut self) -> Output {
+        self.path = None;
+        self
+    }
+
+    /// 写出压缩器中剩余的数据和尾部
+    pub fn finish(mut self) -> Result<()> {
+        self.finished = true;
+        let result = match &mut self.sink {
+            Sink::File(file) => file.flush(),
+            Sink::Gzip(encoder) => encoder.finish(),
+            Sink::Zstd(encoder) => encoder.finish(),
+        };
+        result.map_err(|e| PcapError::WriteFailure(format!("写出压缩数据失败: {}", e)).into())
+    }
+
+    /// 底层的文件 (压缩时其中为已写出的压缩数据)
+    pub fn file(&mut self) -> &mut File {
+        match &mut self.sink {
+            Sink::File(file) => file,
+            Sink::Gzip(encoder) => encoder.get_mut(),
+            Sink::Zstd(encoder) => encoder.get_mut(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.sink {
+            Sink::File(file) => file.write(buf),
+            Sink::Gzip(encoder) => encoder.write(buf),
+            Sink::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::File(file) => file.flush(),
+            Sink::Gzip(encoder) => encoder.flush(),
+            Sink::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(Path::new(path));
+            return;
+        }
+        let _ = match &mut self.sink {
+            Sink::File(_) => Ok(()),
+            Sink::Gzip(encoder) => encoder.finish(),
+            Sink::Zstd(encoder) => encoder.finish(),
+        };
+    }
+}
@@ -0,0 +1,79 @@
+use crate::modules::pcap_packet_parser::{ETHERTYPE_VLAN, LINKTYPE_RAW, parse_packet, update_l4_checksum};
+
+/// 以太网帧，`vlans` 为由外到内的VLAN ID
+pub fn ethernet(src: [u8; 6], dst: [u8; 6], vlans: &[u16], ethertype: u16, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(18 + payload.len());
+    frame.extend_from_slice(&dst);
+    frame.extend_from_slice(&src);
+    for vlan in vlans {
+        frame.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+        frame.extend_from_slice(&vlan.to_be_bytes());
+    }
+    frame.extend_from_slice(&ethertype.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// IPv4包 (头校验和与TCP/UDP校验和均已计算)
+pub fn ipv4(src: [u8; 4], dst: [u8; 4], protocol: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0x45, 0];
+    packet.extend_from_slice(&((20 + payload.len()) as u16).to_be_bytes());
+    packet.extend_from_slice(&[0x12, 0x34, 0x40, 0, 64, protocol, 0, 0]);
+    packet.extend_from_slice(&src);
+    packet.extend_from_slice(&dst);
+    let checksum = internet_checksum(&packet);
+    packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+    packet.extend_from_slice(payload);
+    with_l4_checksum(packet)
+}
+
+/// IPv6包 (TCP/UDP校验和已计算)
+pub fn ipv6(src: [u8; 16], dst: [u8; 16], next_header: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0x60, 0, 0, 0];
+    packet.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[next_header, 64]);
+    packet.extend_from_slice(&src);
+    packet.extend_from_slice(&dst);
+    packet.extend_from_slice(payload);
+    with_l4_checksum(packet)
+}
+
+/// IPv4头和ICMP使用的反码和校验和
+pub fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data.chunks(2).map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32).sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn with_l4_checksum(mut packet: Vec<u8>) -> Vec<u8> {
+    let parsed = parse_packet(&packet, LINKTYPE_RAW);
+    update_l4_checksum(&mut packet, &parsed);
+    packet
+}
+
+/// TCP段，校验和由 [`ipv4`]/[`ipv6`] 计算
+pub fn tcp(src_port: u16, dst_port: u16, seq: u32, ack: u32, flags: u8, options: &[u8], payload: &[u8]) -> Vec<u8> {
+    let header_len = 20 + options.len();
+    let mut segment = Vec::with_capacity(header_len + payload.len());
+    segment.extend_from_slice(&src_port.to_be_bytes());
+    segment.extend_from_slice(&dst_port.to_be_bytes());
+    segment.extend_from_slice(&seq.to_be_bytes());
+    segment.extend_from_slice(&ack.to_be_bytes());
+    segment.extend_from_slice(&[((header_len / 4) << 4) as u8, flags, 0xff, 0xff, 0, 0, 0, 0]);
+    segment.extend_from_slice(options);
+    segment.extend_from_slice(payload);
+    segment
+}
+
+/// UDP数据报，校验和由 [`ipv4`]/[`ipv6`] 计算 (先填非0值，表示启用校验和)
+pub fn udp(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+    let mut datagram = Vec::with_capacity(8 + payload.len());
+    datagram.extend_from_slice(&src_port.to_be_bytes());
+    datagram.extend_from_slice(&dst_port.to_be_bytes());
+    datagram.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+    datagram.extend_from_slice(&[0xff, 0xff]);
+    datagram.extend_from_slice(payload);
+    datagram
+}
@@ -0,0 +1,293 @@
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_filter::{parse_mac, parse_number};
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{
+    LINKTYPE_ETHERNET, ParsedPacket, Transport, parse_packet, read_u16, replace_field, set_ip_addresses, set_ports,
+    write_u16,
+};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 可修改的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditField {
+    EthSrc,
+    EthDst,
+    VlanId,
+    IpSrc,
+    IpDst,
+    IpTtl,
+    IpId,
+    IpTos,
+    TcpSrcPort,
+    TcpDstPort,
+    TcpSeq,
+    TcpAck,
+    TcpFlags,
+    TcpWindow,
+    UdpSrcPort,
+    UdpDstPort,
+}
+
+/// 字段名 (与过滤表达式一致)；`ip.*` 同时适用于IPv4和IPv6 (TTL对应IPv6的跳数限制)
+const EDIT_FIELDS: [(&str, EditField); 18] = [
+    ("eth.src", EditField::EthSrc),
+    ("eth.dst", EditField::EthDst),
+    ("vlan.id", EditField::VlanId),
+    ("ip.src", EditField::IpSrc),
+    ("ip.dst", EditField::IpDst),
+    ("ipv6.src", EditField::IpSrc),
+    ("ipv6.dst", EditField::IpDst),
+    ("ip.ttl", EditField::IpTtl),
+    ("ip.id", EditField::IpId),
+    ("ip.tos", EditField::IpTos),
+    ("tcp.srcport", EditField::TcpSrcPort),
+    ("tcp.dstport", EditField::TcpDstPort),
+    ("tcp.seq", EditField::TcpSeq),
+    ("tcp.ack", EditField::TcpAck),
+    ("tcp.flags", EditField::TcpFlags),
+    ("tcp.window", EditField::TcpWindow),
+    ("udp.srcport", EditField::UdpSrcPort),
+    ("udp.dstport", EditField::UdpDstPort),
+];
+
+/// TCP标志的字母表示 (按位从低到高)
+const TCP_FLAG_LETTERS: [char; 8] = ['F', 'S', 'R', 'P', 'A', 'U', 'E', 'C'];
+
+/// 字段的新值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditValue {
+    Number(u32),
+    Ip(IpAddr),
+    Mac([u8; 6]),
+}
+
+/// 一个字段赋值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldEdit {
+    field: EditField,
+    value: EditValue,
+}
+
+impl FieldEdit {
+    /// 解析 `字段=值` (用于命令行参数)
+    pub fn parse(text: &str) -> Result<FieldEdit, String> {
+        let (name, value) = text
+            .split_once('=')
+            .ok_or_else(|| format!("赋值格式应为 字段=值: {}", text))?;
+        let name = name.trim().to_ascii_lowercase();
+        let field = EDIT_FIELDS
+            .iter()
+            .find(|(field_name, _)| *field_name == name)
+            .map(|(_, field)| *field)
+            .ok_or_else(|| {
+                let names: Vec<&str> = EDIT_FIELDS.iter().map(|(name, _)| *name).collect();
+                format!("未知的字段: {} (可选 {})", name, names.join("、"))
+            })?;
+        FieldEdit::new(field, value.trim())
+    }
+
+    /// 按字段类型解析值
+    pub fn new(field: EditField, value: &str) -> Result<FieldEdit, String> {
+        let invalid = || format!("无效的值: {}", value);
+        let number = |max: u64| {
+            parse_number(value)
+                .filter(|&v| v <= max)
+                .map(|v| EditValue::Number(v as u32))
+                .ok_or_else(invalid)
+        };
+        let value = match field {
+            EditField::EthSrc | EditField::EthDst => EditValue::Mac(parse_mac(value).ok_or_else(invalid)?),
+            EditField::IpSrc | EditField::IpDst => EditValue::Ip(value.parse().map_err(|_| invalid())?),
+            EditField::VlanId => number(4095)?,
+            EditField::IpTtl | EditField::IpTos => number(u8::MAX as u64)?,
+            EditField::TcpFlags => match parse_number(value) {
+                Some(flags) if flags <= u8::MAX as u64 => EditValue::Number(flags as u32),
+                Some(_) => return Err(invalid()),
+                None => EditValue::Number(parse_tcp_flags(value).ok_or_else(invalid)? as u32),
+            },
+            EditField::TcpSeq | EditField::TcpAck => number(u32::MAX as u64)?,
+            _ => number(u16::MAX as u64)?,
+        };
+        Ok(FieldEdit { field, value })
+    }
+
+    /// 修改包中的字段并增量更新校验和，包中没有该字段 (或地址族不一致) 时返回false
+    pub fn apply(&self, data: &mut [u8], linktype: u32, parsed: &ParsedPacket) -> bool {
+        let ip = parsed.ip.as_ref();
+        let tcp = match parsed.transport {
+            Some(Transport::Tcp(tcp)) => Some(tcp),
+            _ => None,
+        };
+        let number = match self.value {
+            EditValue::Number(number) => number,
+            _ => 0,
+        };
+        match (self.field, self.value) {
+            (EditField::EthSrc | EditField::EthDst, EditValue::Mac(mac)) => {
+                if linktype != LINKTYPE_ETHERNET || data.len() < 14 {
+                    return false;
+                }
+                let offset = if self.field == EditField::EthSrc { 6 } else { 0 };
+                data[offset..offset + 6].copy_from_slice(&mac);
+            }
+            (EditField::VlanId, _) => {
+                let Some(offset) = parsed.vlan_offset else {
+                    return false;
+                };
+                let tci = read_u16(data, offset);
+                write_u16(data, offset, (tci & 0xf000) | number as u16);
+            }
+            (EditField::IpSrc | EditField::IpDst, EditValue::Ip(addr)) => {
+                let Some(ip) = ip.filter(|ip| addr.is_ipv4() == (ip.version == 4)) else {
+                    return false;
+                };
+                let (src, dst) = if self.field == EditField::IpSrc { (addr, ip.dst) } else { (ip.src, addr) };
+                set_ip_addresses(data, parsed, src, dst);
+            }
+            (EditField::IpTtl, _) => match ip {
+                Some(ip) if ip.version == 4 => {
+                    // TTL与协议号组成一个16位字段
+                    let field = [number as u8, data[ip.offset + 9]];
+                    replace_field(data, ip.offset + 8, &field, &[ip.offset + 10]);
+                }
+                Some(ip) => data[ip.offset + 7] = number as u8,
+                None => return false,
+            },
+            (EditField::IpId | EditField::IpTos, _) => {
+                let Some(ip) = ip.filter(|ip| ip.version == 4) else {
+                    return false;
+                };
+                if self.field == EditField::IpId {
+                    replace_field(data, ip.offset + 4, &(number as u16).to_be_bytes(), &[ip.offset + 10]);
+                } else {
+                    let field = [data[ip.offset], number as u8];
+                    replace_field(data, ip.offset, &field, &[ip.offset + 10]);
+                }
+            }
+            (EditField::TcpSrcPort | EditField::TcpDstPort, _) => {
+                let Some(tcp) = tcp else {
+                    return false;
+                };
+                set_edited_port(data, parsed, (tcp.src_port, tcp.dst_port), self.field == EditField::TcpSrcPort, number as u16);
+            }
+            (EditField::UdpSrcPort | EditField::UdpDstPort, _) => {
+                let Some(Transport::Udp(udp)) = parsed.transport else {
+                    return false;
+                };
+                set_edited_port(data, parsed, (udp.src_port, udp.dst_port), self.field == EditField::UdpSrcPort, number as u16);
+            }
+            (EditField::TcpSeq | EditField::TcpAck | EditField::TcpFlags | EditField::TcpWindow, _) => {
+                let Some(tcp) = tcp else {
+                    return false;
+                };
+                let checksum = [tcp.offset + 16];
+                match self.field {
+                    EditField::TcpSeq => replace_field(data, tcp.offset + 4, &number.to_be_bytes(), &checksum),
+                    EditField::TcpAck => replace_field(data, tcp.offset + 8, &number.to_be_bytes(), &checksum),
+                    EditField::TcpFlags => {
+                        // 数据偏移与标志组成一个16位字段
+                        let field = [data[tcp.offset + 12], number as u8];
+                        replace_field(data, tcp.offset + 12, &field, &checksum);
+                    }
+                    _ => replace_field(data, tcp.offset + 14, &(number as u16).to_be_bytes(), &checksum),
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// 字段名 (用于日志)
+    pub fn field_name(&self) -> &'static str {
+        EDIT_FIELDS.iter().find(|(_, field)| *field == self.field).map(|(name, _)| *name).unwrap_or("")
+    }
+}
+
+fn set_edited_port(data: &mut [u8], parsed: &ParsedPacket, ports: (u16, u16), source: bool, port: u16) {
+    let (src, dst) = if source { (port, ports.1) } else { (ports.0, port) };
+    set_ports(data, parsed, src, dst);
+}
+
+/// 解析 `SA`、`FPA` 等字母形式的TCP标志
+fn parse_tcp_flags(text: &str) -> Option<u8> {
+    let mut flags = 0u8;
+    for c in text.chars() {
+        let bit = TCP_FLAG_LETTERS.iter().position(|&letter| letter == c.to_ascii_uppercase())?;
+        flags |= 1 << bit;
+    }
+    (!text.is_empty()).then_some(flags)
+}
+
+/// 按序号修改指定数据包的字段
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `numbers`: 要修改的数据包序号 (从1开始)
+/// - `edits`: 字段赋值，按顺序执行
+///
+/// # 功能
+/// 1. 每个赋值之后重新解析包，后面的赋值看到的是修改后的包
+/// 2. 修改后增量更新IP、TCP、UDP校验和
+/// 3. 包中没有要修改的字段时报错，不会静默跳过
+pub fn pcap_edit(input_path: &str, output_path: &str, numbers: &[usize], edits: &[FieldEdit]) -> Result<()> {
+    let numbers: BTreeSet<usize> = numbers.iter().copied().collect();
+    if numbers.contains(&0) {
+        anyhow::bail!(PcapError::InvalidArgument("数据包序号从1开始".to_string()));
+    }
+    if edits.is_empty() {
+        anyhow::bail!(PcapError::InvalidArgument("至少需要一个 --set 赋值".to_string()));
+    }
+
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut packet_count = 0;
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        packet_count += 1;
+
+        if numbers.contains(&packet_count) {
+            let data = packet.data.to_mut();
+            for edit in edits {
+                let parsed = parse_packet(data, linktype);
+                if !edit.apply(data, linktype, &parsed) {
+                    anyhow::bail!("数据包#{}中没有可修改的字段 {}", packet_count, edit.field_name());
+                }
+            }
+        }
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    if let Some(&last) = numbers.last()
+        && last > packet_count
+    {
+        anyhow::bail!(PcapError::InvalidArgument(format!("数据包序号超出范围: {} (共 {} 个)", last, packet_count)));
+    }
+
+    info!(
+        "成功修改数据包: 包数={}, 修改包数={}, 输出文件={}",
+        packet_count,
+        numbers.len(),
+        output_path
+    );
+
+    Ok(())
+}
@@ -0,0 +1,369 @@
+use std::fs::File;
+use std::path::Path;
+use pcap_file::pcap_header::{Datalink, PcapHeader};
+use pcap_file::{Packet, PcapReader};
+use anyhow::{Result, anyhow};
+use log::{debug, info, warn};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_index::{flow_hash, load_index, read_packet_at};
+use crate::modules::pcap_input::open_input;
+use crate::modules::pcap_output::{Output, compression, create_output};
+use crate::modules::pcap_packet_parser::{LINKTYPE_ETHERNET, linktype_name, parse_packet};
+use crate::modules::pcap_pcapng::{
+    CaptureReader, InterfaceDescription, OPT_COMMENT, PcapngBlock, PcapngPacket, is_pcapng_file, option_str,
+    IF_NAME,
+};
+use crate::modules::pcap_report::{format_timestamp_us, print_fields, print_heading, print_separator};
+use crate::modules::pcap_time_anchor::{split_timestamp_us, ticks_per_second, timestamp_to_us};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 数据包选择条件 (序号从1开始)
+pub struct PacketSelection {
+    /// 起始序号
+    pub start: Option<usize>,
+    /// 最多选择的包数
+    pub count: Option<usize>,
+    /// 起始时间 (epoch秒，含)
+    pub from_time: Option<f64>,
+    /// 结束时间 (epoch秒，含)
+    pub to_time: Option<f64>,
+    /// 只选择与该序号的包属于同一个流的包
+    pub flow_of: Option<usize>,
+    /// 只选择该链路层类型的包
+    pub linktype: Option<u32>,
+}
+
+impl PacketSelection {
+    fn linktype_matches(&self, linktype: u32) -> bool {
+        let matches = self.linktype.is_none_or(|wanted| wanted == linktype);
+        if !matches {
+            warn!("输入文件的链路层类型为 {}，没有可选择的包", linktype_name(linktype));
+        }
+        matches
+    }
+
+    fn matches(&self, number: usize, ts_sec: u32, ts_usec: u32, flow: u64, target_flow: Option<u64>) -> bool {
+        if number < self.start.unwrap_or(1) {
+            return false;
+        }
+        let ts = ts_sec as f64 + ts_usec as f64 / 1_000_000.0;
+        if self.from_time.is_some_and(|from| ts < from) || self.to_time.is_some_and(|to| ts > to) {
+            return false;
+        }
+        match target_flow {
+            Some(target) => flow == target,
+            None => true,
+        }
+    }
+}
+
+/// 按序号、时间范围或所属流提取数据包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `selection`: 选择条件
+///
+/// # 功能
+/// 1. 存在有效索引 (`index` 命令生成) 时直接定位所选数据包
+/// 2. 没有索引时顺序扫描整个文件
+/// 3. pcapng输入按每个包所属接口的链路层类型解析；选出的包必须属于同一种链路层类型，
+///    混合时需要用 `linktype` 选择
+pub fn pcap_extract(input_path: &str, output_path: &str, selection: &PacketSelection) -> Result<()> {
+    let mut in_file = File::open(Path::new(input_path))
+        .map_err(|e| PcapError::open_failed(input_path, e))?;
+
+    let out_file = create_output(output_path, compression())?;
+
+    let limit = selection.count.unwrap_or(usize::MAX);
+    let mut written = 0;
+    let mut digest = OutputDigest::default();
+
+    if is_pcapng_file(input_path)? {
+        written = extract_pcapng(input_path, output_path, out_file, selection, limit, &mut digest)?;
+    } else if let Some(index) = load_index(input_path)? {
+        debug!("使用索引文件提取数据包");
+        let target_flow = match selection.flow_of {
+            Some(number) => Some(index.entries.get(number.wrapping_sub(1))
+                .ok_or_else(|| anyhow!("数据包序号超出范围: {} (共 {} 个)", number, index.entries.len()))?
+                .flow_hash),
+            None => None,
+        };
+
+        let mut pcap_writer = PacketWriter::with_header(index.header, out_file, output_path)
+            .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+        let linktype_matches = selection.linktype_matches(index.header.datalink.into());
+        let selected = index.entries.iter().enumerate().filter(|(i, entry)| {
+            linktype_matches && selection.matches(i + 1, entry.ts_sec, entry.ts_usec, entry.flow_hash, target_flow)
+        });
+        for (_, entry) in selected.take(limit) {
+            let packet = read_packet_at(&mut in_file, &index.header, entry.offset)?;
+            pcap_writer.write_packet(&packet)
+                .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+            digest.record_pcap(&packet);
+            written += 1;
+        }
+        pcap_writer.into_inner().finish()?;
+    } else {
+        let mut pcap_reader = PacketReader::open(input_path)?;
+        let linktype: u32 = pcap_reader.header.datalink.into();
+        let header = pcap_reader.header;
+        let packet_flow = |packet: &Packet| {
+            parse_packet(&packet.data, linktype).flow_key().map(|key| flow_hash(&key)).unwrap_or(0)
+        };
+
+        // 需要先找到目标包所属的流
+        let target_flow = match selection.flow_of {
+            Some(number) => {
+                let mut reader = PcapReader::new(open_input(input_path)?)
+                    .map_err(PcapError::invalid_pcap)?;
+                let packet = reader.nth(number.wrapping_sub(1))
+                    .ok_or_else(|| anyhow!("数据包序号超出范围: {}", number))?;
+                Some(packet_flow(&packet))
+            }
+            None => None,
+        };
+
+        let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+            .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+        let linktype_matches = selection.linktype_matches(linktype);
+        for (i, packet) in pcap_reader.by_ref().enumerate() {
+            if written >= limit || !linktype_matches {
+                break;
+            }
+            let flow = if target_flow.is_some() { packet_flow(&packet) } else { 0 };
+            if !selection.matches(i + 1, packet.header.ts_sec, packet.header.ts_usec, flow, target_flow) {
+                continue;
+            }
+            pcap_writer.write_packet(&packet)
+                .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+            digest.record_pcap(&packet);
+            written += 1;
+        }
+        // 选够了包提前结束时不需要读到文件末尾
+        if written < limit && linktype_matches {
+            pcap_reader.check_complete()?;
+        }
+        pcap_writer.into_inner().finish()?;
+    }
+    digest.verify(output_path)?;
+
+    info!("成功提取数据包: 输出包数={}, 输出文件={}", written, output_path);
+
+    Ok(())
+}
+
+/// pcapng输入：逐包按所属接口的链路层类型解析，输出单一链路层类型的文件，返回输出包数
+fn extract_pcapng(
+    input_path: &str,
+    output_path: &str,
+    out_file: Output,
+    selection: &PacketSelection,
+    limit: usize,
+    digest: &mut OutputDigest,
+) -> Result<usize> {
+    let packet_flow = |data: &[u8], linktype: u32| {
+        parse_packet(data, linktype).flow_key().map(|key| flow_hash(&key)).unwrap_or(0)
+    };
+
+    // 需要先找到目标包所属的流
+    let target_flow = match selection.flow_of {
+        Some(number) => {
+            let (packet, interface) = nth_pcapng_packet(input_path, number)?;
+            Some(packet_flow(&packet.data, interface.linktype as u32))
+        }
+        None => None,
+    };
+
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut out_file = Some(out_file);
+    // 输出的链路层类型由第一个选中的包决定
+    let mut output: Option<(PacketWriter<Output>, u32)> = None;
+    let mut number = 0;
+    let mut written = 0;
+    while written < limit {
+        let Some(block) = reader.next_block()? else {
+            break;
+        };
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        number += 1;
+        let interface = reader.interface(packet.interface_id)
+            .ok_or_else(|| anyhow!("数据包#{}引用了不存在的接口 {}", number, packet.interface_id))?;
+        let linktype = interface.linktype as u32;
+        if selection.linktype.is_some_and(|wanted| wanted != linktype) {
+            continue;
+        }
+        let (ts_sec, ts_usec) = split_timestamp_us((interface.timestamp_ns(packet.timestamp) / 1_000) as i64)?;
+        let flow = if target_flow.is_some() { packet_flow(&packet.data, linktype) } else { 0 };
+        if !selection.matches(number, ts_sec, ts_usec, flow, target_flow) {
+            continue;
+        }
+
+        let (pcap_writer, output_linktype) = match &mut output {
+            Some(output) => output,
+            None => {
+                let mut header = PcapHeader::with_datalink(Datalink::from(linktype));
+                if interface.snaplen != 0 {
+                    header.snaplen = interface.snaplen;
+                }
+                let pcap_writer = PacketWriter::with_header(header, out_file.take().unwrap(), output_path)
+                    .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+                output.insert((pcap_writer, linktype))
+            }
+        };
+        if *output_linktype != linktype {
+            anyhow::bail!(PcapError::InvalidArgument(format!(
+                    "选出的包包含多种链路层类型 ({} 和 {})，请用 --linktype 选择其中一种",
+                    linktype_name(*output_linktype),
+                    linktype_name(linktype)
+            )));
+        }
+
+        let mut out_packet = Packet::new_owned(ts_sec, ts_usec, packet.data.len() as u32, packet.data);
+        out_packet.header.orig_len = packet.orig_len;
+        pcap_writer.write_packet(&out_packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&out_packet);
+        written += 1;
+    }
+
+    let pcap_writer = match output {
+        Some((pcap_writer, _)) => pcap_writer,
+        // 没有选中任何包时仍输出有效的空文件
+        None => {
+            let header = PcapHeader::with_datalink(Datalink::from(selection.linktype.unwrap_or(LINKTYPE_ETHERNET)));
+            PacketWriter::with_header(header, out_file.take().unwrap(), output_path)
+                .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?
+        }
+    };
+    pcap_writer.into_inner().finish()?;
+
+    Ok(written)
+}
+
+/// 读取pcapng中的第N个包及其所属接口
+fn nth_pcapng_packet(input_path: &str, number: usize) -> Result<(PcapngPacket, InterfaceDescription)> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut seen = 0;
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        seen += 1;
+        if seen == number {
+            let interface = reader.interface(packet.interface_id)
+                .ok_or_else(|| anyhow!("数据包#{}引用了不存在的接口 {}", number, packet.interface_id))?;
+            let interface = interface.clone();
+            return Ok((packet, interface));
+        }
+    }
+    anyhow::bail!(PcapError::InvalidArgument(format!("数据包序号超出范围: {} (共 {} 个)", number, seen)))
+}
+
+/// 显示单个数据包的详细信息和十六进制内容
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `number`: 数据包序号 (从1开始)
+pub fn pcap_show(input_path: &str, number: usize) -> Result<()> {
+    if number == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("数据包序号从1开始".to_string()));
+    }
+
+    // pcapng输入额外显示所属接口和注释
+    let mut pcapng_details = None;
+    let (packet, linktype, ticks_per_second) = if is_pcapng_file(input_path)? {
+        let (packet, interface) = nth_pcapng_packet(input_path, number)?;
+        let (ts_sec, ts_usec) = split_timestamp_us((interface.timestamp_ns(packet.timestamp) / 1_000) as i64)?;
+        let comments: Vec<String> = packet.options
+            .iter()
+            .filter(|o| o.code == OPT_COMMENT)
+            .map(|o| String::from_utf8_lossy(&o.value).into_owned())
+            .collect();
+        let interface_label = match option_str(&interface.options, IF_NAME) {
+            Some(name) => format!("#{} ({})", packet.interface_id, name),
+            None => format!("#{}", packet.interface_id),
+        };
+        pcapng_details = Some((interface_label, comments));
+
+        let mut pcap_packet = Packet::new_owned(ts_sec, ts_usec, packet.data.len() as u32, packet.data);
+        pcap_packet.header.orig_len = packet.orig_len;
+        (pcap_packet, interface.linktype as u32, 1_000_000)
+    } else {
+        match load_index(input_path)? {
+            Some(index) => {
+                let entry = index.entries.get(number - 1)
+                    .ok_or_else(|| anyhow!("数据包序号超出范围: {} (共 {} 个)", number, index.entries.len()))?;
+                let mut in_file = File::open(Path::new(input_path))
+                    .map_err(|e| PcapError::open_failed(input_path, e))?;
+                let packet = read_packet_at(&mut in_file, &index.header, entry.offset)?;
+                (packet, u32::from(index.header.datalink), ticks_per_second(&index.header))
+            }
+            None => {
+                let mut pcap_reader = PcapReader::new(open_input(input_path)?)
+                    .map_err(PcapError::invalid_pcap)?;
+                let linktype: u32 = pcap_reader.header.datalink.into();
+                let ticks_per_second = ticks_per_second(&pcap_reader.header);
+                let packet = pcap_reader.nth(number - 1)
+                    .ok_or_else(|| anyhow!("数据包序号超出范围: {}", number))?;
+                (packet, linktype, ticks_per_second)
+            }
+        }
+    };
+
+    let mut details = Vec::new();
+    if let Some((interface_label, comments)) = &pcapng_details {
+        details.push(format!("接口: {} 链路层类型: {}", interface_label, linktype_name(linktype)));
+        details.extend(comments.iter().map(|comment| format!("注释: {}", comment)));
+    }
+    print_packet(number, &packet, linktype, ticks_per_second, first_packet_us(input_path)?, &details);
+
+    Ok(())
+}
+
+/// 第一个包的时间 (微秒)，相对时间格式的起点
+fn first_packet_us(input_path: &str) -> Result<i64> {
+    let mut reader = CaptureReader::open(input_path)?;
+    while let Some(block) = reader.next_block()? {
+        if let PcapngBlock::Packet(packet) = block
+            && let Some(interface) = reader.interface(packet.interface_id)
+        {
+            return Ok((interface.timestamp_ns(packet.timestamp) / 1_000) as i64);
+        }
+    }
+    Ok(0)
+}
+
+/// 打印数据包的基本信息和十六进制内容
+///
+/// `ticks_per_second` 为包的时间戳精度 (微秒或纳秒)，`start_us` 为抓包中第一个包的时间 (相对时间格式的起点)，
+/// `details` 为附加的信息行。
+pub fn print_packet(number: usize, packet: &Packet, linktype: u32, ticks_per_second: i64, start_us: i64, details: &[String]) {
+    print_heading(&format!("数据包 #{}:", number));
+    let flow = match parse_packet(&packet.data, linktype).flow_key() {
+        Some(key) => key.to_string(),
+        None => "(非IP包)".to_string(),
+    };
+    print_fields(&[
+        ("时间戳", format_timestamp_us(timestamp_to_us(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second), start_us), None),
+        ("捕获长度", format!("{} 字节 (原始长度: {} 字节)", packet.header.incl_len, packet.header.orig_len), None),
+        ("五元组", flow, None),
+    ]);
+    for line in details {
+        println!("- {}", line);
+    }
+
+    print_separator();
+    for (line, chunk) in packet.data.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        println!("  {:04x}  {:<47}  {}", line * 16, hex.join(" "), ascii);
+    }
+}
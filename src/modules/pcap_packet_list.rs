@@ -0,0 +1,225 @@
+use std::net::Ipv4Addr;
+use anyhow::{Context, Result};
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_filter::Filter;
+use crate::modules::pcap_packet_parser::{
+    IP_PROTO_ICMP, IP_PROTO_ICMPV6, IP_PROTO_TCP, IP_PROTO_UDP, LINKTYPE_ETHERNET, ParsedPacket, TcpInfo, Transport, parse_packet,
+    read_u16, read_u32,
+};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{Table, format_timestamp_us};
+
+const ETHERTYPE_ARP: u16 = 0x0806;
+
+const TCP_ACK: u8 = 0x10;
+
+/// TCP标志位及其名称 (按Wireshark的顺序)
+const TCP_FLAG_NAMES: [(u8, &str); 8] = [
+    (0x02, "SYN"),
+    (0x01, "FIN"),
+    (0x04, "RST"),
+    (0x08, "PSH"),
+    (TCP_ACK, "ACK"),
+    (0x20, "URG"),
+    (0x40, "ECE"),
+    (0x80, "CWR"),
+];
+
+/// 以表格列出数据包 (与Wireshark的包列表相同)
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `filter`: 过滤表达式，只列出匹配的包
+/// - `limit`: 最多列出的包数 (None表示全部)
+///
+/// # 说明
+/// 序号为包在文件中的序号 (过滤时不重新编号)，时间按 `--time-format` 格式化 (本命令默认为相对时间)，
+/// 长度为原始长度；信息列为端口、TCP标志和序列号、载荷长度等简要内容。
+pub fn pcap_list(input_path: &str, filter: Option<&str>, limit: Option<usize>) -> Result<()> {
+    let filter = match filter {
+        Some(text) => Some(
+            Filter::parse(text)
+                .with_context(|| format!("无效的过滤表达式: {}", text))
+                .map_err(|e| PcapError::InvalidArgument(format!("{:#}", e)))?,
+        ),
+        None => None,
+    };
+    let limit = limit.unwrap_or(usize::MAX);
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut table = Table::new(&["序号", "时间", "源地址", "目的地址", "协议", "长度", "信息"]).right_align(&[0, 5]);
+    let mut packet_count = 0u64;
+    let mut listed = 0usize;
+    // 第一个包的时间 (相对时间格式的起点)
+    let mut start_us = None;
+
+    while listed < limit
+        && let Some(block) = reader.next_block()?
+    {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = interface.timestamp_ns(packet.timestamp).div_euclid(1000) as i64;
+        let start_us = *start_us.get_or_insert(time_us);
+        let linktype = interface.linktype as u32;
+        let data = &packet.data;
+        let parsed = parse_packet(data, linktype);
+        if filter.as_ref().is_some_and(|filter| !filter.matches(data, linktype, &parsed)) {
+            continue;
+        }
+
+        let summary = summarize(data, linktype, &parsed);
+        table.add_row(vec![
+            packet_count.to_string(),
+            format_timestamp_us(time_us, start_us),
+            summary.src,
+            summary.dst,
+            summary.protocol,
+            packet.orig_len.to_string(),
+            summary.info,
+        ]);
+        listed += 1;
+    }
+
+    table.print();
+    if listed == limit {
+        while let Some(block) = reader.next_block()? {
+            if let PcapngBlock::Packet(_) = block {
+                println!("  ... 只列出了前 {} 个包 (使用 --limit 调整)", limit);
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 一个包在列表中的各列
+struct PacketSummary {
+    src: String,
+    dst: String,
+    protocol: String,
+    info: String,
+}
+
+fn summarize(data: &[u8], linktype: u32, parsed: &ParsedPacket) -> PacketSummary {
+    let Some(ip) = parsed.ip.as_ref() else {
+        return summarize_non_ip(data, linktype, parsed);
+    };
+    let (protocol, info) = match parsed.transport {
+        Some(Transport::Tcp(tcp)) => ("TCP".to_string(), tcp_info(data, parsed, tcp)),
+        Some(Transport::Udp(udp)) => {
+            let len = parsed.payload_range(data.len()).len();
+            ("UDP".to_string(), format!("{} → {} Len={}", udp.src_port, udp.dst_port, len))
+        }
+        Some(Transport::Icmp { offset }) if offset + 2 <= data.len() => {
+            let version6 = ip.protocol == IP_PROTO_ICMPV6;
+            let (icmp_type, code) = (data[offset], data[offset + 1]);
+            let name = icmp_name(version6, icmp_type).map_or(String::new(), |name| format!("{} ", name));
+            let protocol = if version6 { "ICMPv6" } else { "ICMP" };
+            (protocol.to_string(), format!("{}(type={}, code={})", name, icmp_type, code))
+        }
+        _ if ip.is_fragment => (ip_protocol_name(ip.protocol), "IP分片".to_string()),
+        _ => (ip_protocol_name(ip.protocol), String::new()),
+    };
+    PacketSummary { src: ip.src.to_string(), dst: ip.dst.to_string(), protocol, info }
+}
+
+fn summarize_non_ip(data: &[u8], linktype: u32, parsed: &ParsedPacket) -> PacketSummary {
+    let ethernet = linktype == LINKTYPE_ETHERNET && data.len() >= 14;
+    let (src, dst) = if ethernet {
+        (format_mac(&data[6..12]), format_mac(&data[0..6]))
+    } else {
+        ("-".to_string(), "-".to_string())
+    };
+    let (protocol, info) = match parsed.ethertype {
+        // 只解析不带VLAN标签的ARP
+        Some(ETHERTYPE_ARP) if ethernet && parsed.vlan_offset.is_none() => ("ARP".to_string(), arp_info(&data[14..])),
+        Some(0x88cc) => ("LLDP".to_string(), String::new()),
+        Some(0x8809) => ("LACP".to_string(), String::new()),
+        Some(ethertype) => (format!("0x{:04x}", ethertype), String::new()),
+        None => ("-".to_string(), String::new()),
+    };
+    PacketSummary { src, dst, protocol, info }
+}
+
+fn tcp_info(data: &[u8], parsed: &ParsedPacket, tcp: TcpInfo) -> String {
+    let offset = tcp.offset;
+    let mut info = format!("{} → {}", tcp.src_port, tcp.dst_port);
+    if offset + 20 > data.len() {
+        return info;
+    }
+    let flags = data[offset + 13];
+    let names: Vec<&str> = TCP_FLAG_NAMES
+        .iter()
+        .filter(|&&(bit, _)| flags & bit != 0)
+        .map(|&(_, name)| name)
+        .collect();
+    info.push_str(&format!(" [{}] Seq={}", names.join(", "), read_u32(data, offset + 4)));
+    if flags & TCP_ACK != 0 {
+        info.push_str(&format!(" Ack={}", read_u32(data, offset + 8)));
+    }
+    info.push_str(&format!(
+        " Win={} Len={}",
+        read_u16(data, offset + 14),
+        parsed.payload_range(data.len()).len()
+    ));
+    if tcp.header_len > 20 {
+        info.push_str(&format!(" 选项={}字节", tcp.header_len - 20));
+    }
+    info
+}
+
+/// ARP请求和应答的说明 (只解析以太网/IPv4的ARP)
+fn arp_info(arp: &[u8]) -> String {
+    if arp.len() < 28 || read_u16(arp, 0) != 1 || read_u16(arp, 2) != 0x0800 {
+        return String::new();
+    }
+    let sender_ip = Ipv4Addr::new(arp[14], arp[15], arp[16], arp[17]);
+    let target_ip = Ipv4Addr::new(arp[24], arp[25], arp[26], arp[27]);
+    match read_u16(arp, 6) {
+        1 => format!("谁是 {}? 告诉 {}", target_ip, sender_ip),
+        2 => format!("{} 在 {}", sender_ip, format_mac(&arp[8..14])),
+        op => format!("操作码={}", op),
+    }
+}
+
+fn icmp_name(version6: bool, icmp_type: u8) -> Option<&'static str> {
+    let name = match (version6, icmp_type) {
+        (false, 0) | (true, 129) => "Echo应答",
+        (false, 8) | (true, 128) => "Echo请求",
+        (false, 3) | (true, 1) => "目的不可达",
+        (false, 11) | (true, 3) => "超时",
+        (false, 5) => "重定向",
+        (true, 2) => "包过大",
+        (true, 133) => "路由器请求",
+        (true, 134) => "路由器通告",
+        (true, 135) => "邻居请求",
+        (true, 136) => "邻居通告",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn ip_protocol_name(protocol: u8) -> String {
+    match protocol {
+        IP_PROTO_TCP => "TCP".to_string(),
+        IP_PROTO_UDP => "UDP".to_string(),
+        IP_PROTO_ICMP => "ICMP".to_string(),
+        IP_PROTO_ICMPV6 => "ICMPv6".to_string(),
+        2 => "IGMP".to_string(),
+        47 => "GRE".to_string(),
+        50 => "ESP".to_string(),
+        51 => "AH".to_string(),
+        89 => "OSPF".to_string(),
+        132 => "SCTP".to_string(),
+        other => format!("IP{}", other),
+    }
+}
+
+fn format_mac(mac: &[u8]) -> String {
+    mac.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":")
+}
@@ -0,0 +1,750 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// 以太网
+pub const LINKTYPE_ETHERNET: u32 = 1;
+/// 原始IP (无链路层头)
+pub const LINKTYPE_RAW: u32 = 101;
+/// IEEE 802.11 (无线局域网)
+pub const LINKTYPE_IEEE802_11: u32 = 105;
+/// Linux cooked capture (SLL)
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+/// Radiotap头 + IEEE 802.11
+pub const LINKTYPE_IEEE802_11_RADIOTAP: u32 = 127;
+/// 原始IPv4
+pub const LINKTYPE_IPV4: u32 = 228;
+/// 原始IPv6
+pub const LINKTYPE_IPV6: u32 = 229;
+/// Linux cooked capture v2 (`tcpdump -i any`)
+pub const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+/// 可按名称指定的链路层类型
+const LINKTYPE_NAMES: [(&str, u32); 8] = [
+    ("ethernet", LINKTYPE_ETHERNET),
+    ("raw", LINKTYPE_RAW),
+    ("ieee802-11", LINKTYPE_IEEE802_11),
+    ("linux-sll", LINKTYPE_LINUX_SLL),
+    ("radiotap", LINKTYPE_IEEE802_11_RADIOTAP),
+    ("ipv4", LINKTYPE_IPV4),
+    ("ipv6", LINKTYPE_IPV6),
+    ("linux-sll2", LINKTYPE_LINUX_SLL2),
+];
+
+/// 解析链路层类型名称或编号 (用于命令行参数)
+pub fn parse_linktype(text: &str) -> Result<u32, String> {
+    if let Some(&(_, linktype)) = LINKTYPE_NAMES.iter().find(|(name, _)| name.eq_ignore_ascii_case(text)) {
+        return Ok(linktype);
+    }
+    text.parse().map_err(|_| {
+        let names: Vec<&str> = LINKTYPE_NAMES.iter().map(|(name, _)| *name).collect();
+        format!("未知的链路层类型: {} (可选 {} 或数字编号)", text, names.join("/"))
+    })
+}
+
+/// 链路层类型的显示名称
+pub fn linktype_name(linktype: u32) -> String {
+    match LINKTYPE_NAMES.iter().find(|(_, value)| *value == linktype) {
+        Some((name, _)) => name.to_string(),
+        None => format!("linktype {}", linktype),
+    }
+}
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+pub const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+pub const IP_PROTO_ICMP: u8 = 1;
+pub const IP_PROTO_TCP: u8 = 6;
+pub const IP_PROTO_UDP: u8 = 17;
+pub const IP_PROTO_ICMPV6: u8 = 58;
+
+/// IP头信息
+#[derive(Debug, Clone)]
+pub struct IpInfo {
+    /// IP头在包中的偏移
+    pub offset: usize,
+    pub version: u8,
+    /// IP头长度 (IPv6包含扩展头)
+    pub header_len: usize,
+    /// IP包总长度 (含IP头，取自长度字段)
+    pub total_len: usize,
+    /// 上层协议 (IPv6为扩展头之后的协议)
+    pub protocol: u8,
+    pub src: IpAddr,
+    pub dst: IpAddr,
+    /// 是否为非首个分片 (不含传输层头)
+    pub is_fragment: bool,
+}
+
+impl IpInfo {
+    /// 传输层头偏移
+    pub fn l4_offset(&self) -> usize {
+        self.offset + self.header_len
+    }
+
+    /// IP包在数据中的结束位置 (之后为链路层填充或尾部)
+    pub fn end(&self) -> usize {
+        self.offset + self.total_len
+    }
+}
+
+/// TCP头信息
+#[derive(Debug, Clone, Copy)]
+pub struct TcpInfo {
+    pub offset: usize,
+    pub src_port: u16,
+    pub dst_port: u16,
+    /// TCP头长度 (含选项)
+    pub header_len: usize,
+}
+
+/// UDP头信息
+#[derive(Debug, Clone, Copy)]
+pub struct UdpInfo {
+    pub offset: usize,
+    pub src_port: u16,
+    pub dst_port: u16,
+}
+
+/// 传输层信息
+#[derive(Debug, Clone, Copy)]
+pub enum Transport {
+    Tcp(TcpInfo),
+    Udp(UdpInfo),
+    Icmp { offset: usize },
+}
+
+/// 单个数据包的解析结果
+///
+/// 只记录各层头部的偏移和关键字段，不复制包数据；
+/// 截断或无法识别的部分对应字段为 `None`。
+#[derive(Debug, Clone, Default)]
+pub struct ParsedPacket {
+    /// 最内层的以太网类型
+    pub ethertype: Option<u16>,
+    /// 最外层VLAN标签中TCI字段的偏移
+    pub vlan_offset: Option<usize>,
+    pub ip: Option<IpInfo>,
+    pub transport: Option<Transport>,
+}
+
+/// 传输层五元组
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FlowKey {
+    pub protocol: u8,
+    pub src: IpAddr,
+    pub dst: IpAddr,
+    pub src_port: u16,
+    pub dst_port: u16,
+}
+
+impl FlowKey {
+    /// 反方向的五元组
+    pub fn reversed(&self) -> FlowKey {
+        FlowKey {
+            protocol: self.protocol,
+            src: self.dst,
+            dst: self.src,
+            src_port: self.dst_port,
+            dst_port: self.src_port,
+        }
+    }
+
+    /// 与方向无关的规范形式 (较小的端点在前)
+    pub fn canonical(&self) -> FlowKey {
+        if (self.src, self.src_port) <= (self.dst, self.dst_port) {
+            *self
+        } else {
+            self.reversed()
+        }
+    }
+}
+
+impl std::fmt::Display for FlowKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let proto = match self.protocol {
+            IP_PROTO_TCP => "TCP".to_string(),
+            IP_PROTO_UDP => "UDP".to_string(),
+            IP_PROTO_ICMP => "ICMP".to_string(),
+            IP_PROTO_ICMPV6 => "ICMPv6".to_string(),
+            other => format!("IP{}", other),
+        };
+        write!(
+            f,
+            "{} {} -> {}",
+            proto,
+            format_endpoint(self.src, self.src_port),
+            format_endpoint(self.dst, self.dst_port)
+        )
+    }
+}
+
+/// 格式化地址和端口 (IPv6地址加方括号)
+pub fn format_endpoint(addr: IpAddr, port: u16) -> String {
+    match addr {
+        IpAddr::V4(v4) => format!("{}:{}", v4, port),
+        IpAddr::V6(v6) => format!("[{}]:{}", v6, port),
+    }
+}
+
+impl ParsedPacket {
+    /// 五元组 (无传输层端口时端口为0)
+    pub fn flow_key(&self) -> Option<FlowKey> {
+        let ip = self.ip.as_ref()?;
+        let (src_port, dst_port) = match self.transport {
+            Some(Transport::Tcp(tcp)) => (tcp.src_port, tcp.dst_port),
+            Some(Transport::Udp(udp)) => (udp.src_port, udp.dst_port),
+            _ => (0, 0),
+        };
+        Some(FlowKey {
+            protocol: ip.protocol,
+            src: ip.src,
+            dst: ip.dst,
+            src_port,
+            dst_port,
+        })
+    }
+
+    /// 应用层载荷在数据中的范围
+    ///
+    /// TCP/UDP为传输层头之后的部分，其他IP包为IP载荷，非IP包为整个帧；
+    /// 结束位置按IP长度字段计算，不含链路层填充。
+    pub fn payload_range(&self, data_len: usize) -> std::ops::Range<usize> {
+        let Some(ip) = self.ip.as_ref() else {
+            return 0..data_len;
+        };
+        let start = match self.transport {
+            Some(Transport::Tcp(tcp)) => tcp.offset + tcp.header_len,
+            Some(Transport::Udp(udp)) => udp.offset + 8,
+            _ => ip.l4_offset(),
+        };
+        let end = ip.end().min(data_len);
+        start.min(end)..end
+    }
+}
+
+/// 按链路层类型解析数据包
+pub fn parse_packet(data: &[u8], linktype: u32) -> ParsedPacket {
+    let mut parsed = ParsedPacket::default();
+
+    let l3_offset = match linktype {
+        LINKTYPE_ETHERNET => match parse_ethernet(data, &mut parsed) {
+            Some(offset) => offset,
+            None => return parsed,
+        },
+        LINKTYPE_LINUX_SLL => {
+            if data.len() < 16 {
+                return parsed;
+            }
+            parsed.ethertype = Some(read_u16(data, 14));
+            16
+        }
+        LINKTYPE_LINUX_SLL2 => {
+            if data.len() < 20 {
+                return parsed;
+            }
+            parsed.ethertype = Some(read_u16(data, 0));
+            20
+        }
+        LINKTYPE_IEEE802_11 | LINKTYPE_IEEE802_11_RADIOTAP => match parse_wlan(data, linktype) {
+            Some(wlan) => {
+                parsed.ethertype = Some(wlan.ethertype);
+                wlan.payload_offset
+            }
+            None => return parsed,
+        },
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => {
+            parsed.ethertype = match data.first().map(|b| b >> 4) {
+                Some(4) => Some(ETHERTYPE_IPV4),
+                Some(6) => Some(ETHERTYPE_IPV6),
+                _ => return parsed,
+            };
+            0
+        }
+        _ => return parsed,
+    };
+
+    match parsed.ethertype {
+        Some(ETHERTYPE_IPV4) => parse_ipv4(data, l3_offset, &mut parsed),
+        Some(ETHERTYPE_IPV6) => parse_ipv6(data, l3_offset, &mut parsed),
+        _ => {}
+    }
+
+    parsed
+}
+
+/// 链路层头中以太网类型 (协议类型) 字段的偏移，没有该字段的链路层类型返回None
+pub fn ethertype_offset(linktype: u32, l3_offset: usize) -> Option<usize> {
+    match linktype {
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => None,
+        LINKTYPE_LINUX_SLL2 => Some(0),
+        // 以太网、SLL和802.11的SNAP头都紧挨三层头
+        _ => l3_offset.checked_sub(2),
+    }
+}
+
+/// 解析以太网头和VLAN标签，返回三层头偏移
+fn parse_ethernet(data: &[u8], parsed: &mut ParsedPacket) -> Option<usize> {
+    if data.len() < 14 {
+        return None;
+    }
+    let mut offset = 12;
+    let mut ethertype = read_u16(data, offset);
+    while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+        if data.len() < offset + 6 {
+            return None;
+        }
+        parsed.vlan_offset.get_or_insert(offset + 2);
+        offset += 4;
+        ethertype = read_u16(data, offset);
+    }
+
+    parsed.ethertype = Some(ethertype);
+    Some(offset + 2)
+}
+
+/// 802.11数据帧中与以太网帧对应的部分
+#[derive(Debug, Clone, Copy)]
+pub struct WlanFrame {
+    /// 目的地址和源地址 (按ToDS/FromDS从802.11地址字段中选取)
+    pub dst: [u8; 6],
+    pub src: [u8; 6],
+    /// LLC/SNAP头中的以太网类型
+    pub ethertype: u16,
+    /// SNAP头之后的三层数据偏移
+    pub payload_offset: usize,
+    /// 帧末尾的FCS长度 (Radiotap标志指明时为4)
+    pub fcs_len: usize,
+}
+
+/// Radiotap标志字段: 帧末尾带FCS
+const RADIOTAP_FLAG_FCS: u8 = 0x10;
+
+/// 802.11数据帧的头部位置
+#[derive(Debug, Clone, Copy)]
+pub struct WlanHeader {
+    /// 802.11头的偏移 (Radiotap头的长度)
+    pub offset: usize,
+    /// 802.11头的长度 (含QoS控制和HT控制字段)
+    pub header_len: usize,
+    /// 帧末尾的FCS长度 (Radiotap标志指明时为4)
+    pub fcs_len: usize,
+}
+
+/// 解析 (Radiotap头和) 802.11数据帧头 (包括受保护的帧)，其他类型的帧返回None
+pub fn parse_wlan_header(data: &[u8], linktype: u32) -> Option<WlanHeader> {
+    let mut offset = 0;
+    let mut fcs_len = 0;
+    if linktype == LINKTYPE_IEEE802_11_RADIOTAP {
+        if data.len() < 8 || data[0] != 0 {
+            return None;
+        }
+        let header_len = u16::from_le_bytes([data[2], data[3]]) as usize;
+        // 存在位图可以有多个字，最高位表示后面还有一个字
+        let mut fields = 4;
+        while u32::from_le_bytes(data.get(fields..fields + 4)?.try_into().unwrap()) & 0x8000_0000 != 0 {
+            fields += 4;
+        }
+        let present = u32::from_le_bytes(data[4..8].try_into().unwrap());
+        fields += 4;
+        // 标志字段之前只有8字节对齐的TSFT字段
+        if present & 0x01 != 0 {
+            fields = fields.div_ceil(8) * 8 + 8;
+        }
+        if present & 0x02 != 0 && *data.get(fields)? & RADIOTAP_FLAG_FCS != 0 {
+            fcs_len = 4;
+        }
+        offset = header_len;
+    }
+
+    let frame = data.get(offset..data.len().checked_sub(fcs_len)?)?;
+    if frame.len() < 24 {
+        return None;
+    }
+    let (fc0, fc1) = (frame[0], frame[1]);
+    // 类型2为数据帧，子类型最高位为QoS
+    if (fc0 >> 2) & 0x03 != 2 {
+        return None;
+    }
+    let mut header_len = if fc1 & 0x03 == 0x03 { 30 } else { 24 };
+    if fc0 & 0x80 != 0 {
+        header_len += 2;
+        // QoS数据帧的Order位表示带HT控制字段
+        if fc1 & 0x80 != 0 {
+            header_len += 4;
+        }
+    }
+    (frame.len() >= header_len).then_some(WlanHeader { offset, header_len, fcs_len })
+}
+
+/// 解析 (Radiotap头和) 802.11头，只返回带LLC/SNAP封装、未加密的数据帧
+pub fn parse_wlan(data: &[u8], linktype: u32) -> Option<WlanFrame> {
+    let WlanHeader { offset, header_len, fcs_len } = parse_wlan_header(data, linktype)?;
+    let frame = &data[offset..data.len() - fcs_len];
+    let fc1 = frame[1];
+    // 受保护帧无法解析
+    if fc1 & 0x40 != 0 {
+        return None;
+    }
+    let to_ds = fc1 & 0x01 != 0;
+    let from_ds = fc1 & 0x02 != 0;
+
+    let addr = |n: usize| -> [u8; 6] { frame[4 + n * 6..10 + n * 6].try_into().unwrap() };
+    let (dst, src) = match (to_ds, from_ds) {
+        (false, false) => (addr(0), addr(1)),
+        (true, false) => (addr(2), addr(1)),
+        (false, true) => (addr(0), addr(2)),
+        (true, true) => (addr(2), frame[24..30].try_into().unwrap()),
+    };
+
+    let snap = frame.get(header_len..header_len + 8)?;
+    if snap[..6] != [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00] {
+        return None;
+    }
+    Some(WlanFrame {
+        dst,
+        src,
+        ethertype: read_u16(snap, 6),
+        payload_offset: offset + header_len + 8,
+        fcs_len,
+    })
+}
+
+fn parse_ipv4(data: &[u8], offset: usize, parsed: &mut ParsedPacket) {
+    if data.len() < offset + 20 || data[offset] >> 4 != 4 {
+        return;
+    }
+    let header_len = ((data[offset] & 0x0f) as usize) * 4;
+    if header_len < 20 || data.len() < offset + header_len {
+        return;
+    }
+    let flags_fragment = read_u16(data, offset + 6);
+    let ip = IpInfo {
+        offset,
+        version: 4,
+        header_len,
+        // 长度字段为0时 (TSO卸载) 按实际数据计算
+        total_len: match read_u16(data, offset + 2) as usize {
+            0 => data.len() - offset,
+            len => len.max(header_len),
+        },
+        protocol: data[offset + 9],
+        src: IpAddr::V4(Ipv4Addr::new(data[offset + 12], data[offset + 13], data[offset + 14], data[offset + 15])),
+        dst: IpAddr::V4(Ipv4Addr::new(data[offset + 16], data[offset + 17], data[offset + 18], data[offset + 19])),
+        is_fragment: flags_fragment & 0x1fff != 0,
+    };
+    if !ip.is_fragment {
+        parsed.transport = parse_transport(data, ip.l4_offset(), ip.protocol);
+    }
+    parsed.ip = Some(ip);
+}
+
+fn parse_ipv6(data: &[u8], offset: usize, parsed: &mut ParsedPacket) {
+    if data.len() < offset + 40 || data[offset] >> 4 != 6 {
+        return;
+    }
+    let src: [u8; 16] = data[offset + 8..offset + 24].try_into().unwrap();
+    let dst: [u8; 16] = data[offset + 24..offset + 40].try_into().unwrap();
+
+    // 跳过扩展头
+    let mut next_header = data[offset + 6];
+    let mut header_len = 40;
+    let mut is_fragment = false;
+    loop {
+        let ext = offset + header_len;
+        match next_header {
+            0 | 43 | 60 => {
+                if data.len() < ext + 2 {
+                    break;
+                }
+                next_header = data[ext];
+                header_len += (data[ext + 1] as usize + 1) * 8;
+            }
+            44 => {
+                if data.len() < ext + 8 {
+                    break;
+                }
+                next_header = data[ext];
+                is_fragment = read_u16(data, ext + 2) & 0xfff8 != 0;
+                header_len += 8;
+            }
+            51 => {
+                if data.len() < ext + 2 {
+                    break;
+                }
+                next_header = data[ext];
+                header_len += (data[ext + 1] as usize + 2) * 4;
+            }
+            _ => break,
+        }
+    }
+
+    let ip = IpInfo {
+        offset,
+        version: 6,
+        header_len,
+        // 载荷长度为0时 (巨型帧) 按实际数据计算
+        total_len: match read_u16(data, offset + 4) as usize {
+            0 => data.len() - offset,
+            len => (40 + len).max(header_len),
+        },
+        protocol: next_header,
+        src: IpAddr::V6(Ipv6Addr::from(src)),
+        dst: IpAddr::V6(Ipv6Addr::from(dst)),
+        is_fragment,
+    };
+    if !ip.is_fragment {
+        parsed.transport = parse_transport(data, ip.l4_offset(), ip.protocol);
+    }
+    parsed.ip = Some(ip);
+}
+
+fn parse_transport(data: &[u8], offset: usize, protocol: u8) -> Option<Transport> {
+    match protocol {
+        IP_PROTO_TCP => {
+            if data.len() < offset + 20 {
+                return None;
+            }
+            Some(Transport::Tcp(TcpInfo {
+                offset,
+                src_port: read_u16(data, offset),
+                dst_port: read_u16(data, offset + 2),
+                header_len: ((data[offset + 12] >> 4) as usize * 4).max(20),
+            }))
+        }
+        IP_PROTO_UDP => {
+            if data.len() < offset + 8 {
+                return None;
+            }
+            Some(Transport::Udp(UdpInfo {
+                offset,
+                src_port: read_u16(data, offset),
+                dst_port: read_u16(data, offset + 2),
+            }))
+        }
+        IP_PROTO_ICMP | IP_PROTO_ICMPV6 => {
+            if data.len() < offset + 4 {
+                return None;
+            }
+            Some(Transport::Icmp { offset })
+        }
+        _ => None,
+    }
+}
+
+pub fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+pub fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+pub fn write_u16(data: &mut [u8], offset: usize, value: u16) {
+    data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+fn checksum_fold(mut sum: u32) -> u16 {
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// 按RFC 1624增量更新校验和 (`old`/`new` 为被替换的16位对齐字段)
+pub fn checksum_adjust(checksum: u16, old: &[u8], new: &[u8]) -> u16 {
+    let mut sum = (!checksum) as u32;
+    for (o, n) in old.chunks(2).zip(new.chunks(2)) {
+        let o = u16::from_be_bytes([o[0], *o.get(1).unwrap_or(&0)]);
+        let n = u16::from_be_bytes([n[0], *n.get(1).unwrap_or(&0)]);
+        sum += (!o) as u32 + n as u32;
+    }
+    checksum_fold(sum)
+}
+
+/// 替换包中一个16位对齐的字段，并增量更新相关的校验和
+///
+/// `checksum_offsets` 为需要同步更新的校验和字段偏移
+/// (例如修改IPv4地址时需同时更新IP头校验和与TCP/UDP校验和)。
+pub fn replace_field(data: &mut [u8], offset: usize, new: &[u8], checksum_offsets: &[usize]) {
+    let old = data[offset..offset + new.len()].to_vec();
+    if old == new {
+        return;
+    }
+    for &checksum_offset in checksum_offsets {
+        if checksum_offset + 2 > data.len() {
+            continue;
+        }
+        let checksum = read_u16(data, checksum_offset);
+        write_u16(data, checksum_offset, checksum_adjust(checksum, &old, new));
+    }
+    data[offset..offset + new.len()].copy_from_slice(new);
+}
+
+/// 数据按16位累加 (奇数长度末尾补0)
+fn checksum_sum(data: &[u8]) -> u32 {
+    data.chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum()
+}
+
+/// 按完整数据重新计算TCP/UDP校验和 (含伪首部)
+///
+/// 数据被截断时无法计算，返回false；未启用校验和的IPv4 UDP包保持为0。
+pub fn update_l4_checksum(data: &mut [u8], parsed: &ParsedPacket) -> bool {
+    let Some(ip) = &parsed.ip else {
+        return false;
+    };
+    let (offset, checksum_offset) = match parsed.transport {
+        Some(Transport::Tcp(tcp)) => (tcp.offset, tcp.offset + 16),
+        Some(Transport::Udp(udp)) => {
+            if ip.version == 4 && read_u16(data, udp.offset + 6) == 0 {
+                return true;
+            }
+            (udp.offset, udp.offset + 6)
+        }
+        _ => return false,
+    };
+    let end = ip.end();
+    if end > data.len() || end < offset {
+        return false;
+    }
+
+    let l4_len = end - offset;
+    let mut sum = checksum_sum(&ip_octets(ip.src)) + checksum_sum(&ip_octets(ip.dst));
+    sum += ip.protocol as u32 + (l4_len as u32 >> 16) + (l4_len as u32 & 0xffff);
+    write_u16(data, checksum_offset, 0);
+    sum += checksum_sum(&data[offset..end]);
+    let checksum = match checksum_fold(sum) {
+        // UDP校验和为0表示未启用，计算结果为0时写作全1
+        0 if matches!(parsed.transport, Some(Transport::Udp(_))) => 0xffff,
+        checksum => checksum,
+    };
+    write_u16(data, checksum_offset, checksum);
+    true
+}
+
+/// IPv4头校验和是否正确 (IPv6没有头校验和，头被截断时返回None)
+pub fn ip_checksum_valid(data: &[u8], parsed: &ParsedPacket) -> Option<bool> {
+    let ip = parsed.ip.as_ref().filter(|ip| ip.version == 4)?;
+    let header = data.get(ip.offset..ip.offset + ip.header_len)?;
+    Some(checksum_fold(checksum_sum(header)) == 0)
+}
+
+/// TCP/UDP校验和是否正确 (含伪首部)
+///
+/// 数据被截断、不是TCP/UDP或为IPv4首个分片时返回None；未启用校验和的IPv4 UDP包视为正确。
+pub fn l4_checksum_valid(data: &[u8], parsed: &ParsedPacket) -> Option<bool> {
+    let ip = parsed.ip.as_ref()?;
+    if ip.version == 4 && data[ip.offset + 6] & 0x20 != 0 {
+        return None;
+    }
+    let offset = match parsed.transport? {
+        Transport::Tcp(tcp) => tcp.offset,
+        Transport::Udp(udp) => {
+            if ip.version == 4 && read_u16(data, udp.offset + 6) == 0 {
+                return Some(true);
+            }
+            udp.offset
+        }
+        Transport::Icmp { .. } => return None,
+    };
+    let end = ip.end();
+    if end > data.len() || end < offset {
+        return None;
+    }
+    let l4_len = end - offset;
+    let mut sum = checksum_sum(&ip_octets(ip.src)) + checksum_sum(&ip_octets(ip.dst));
+    sum += ip.protocol as u32 + (l4_len as u32 >> 16) + (l4_len as u32 & 0xffff);
+    sum += checksum_sum(&data[offset..end]);
+    Some(checksum_fold(sum) == 0)
+}
+
+/// 修改IP包的总长度 (IPv4总长度字段并更新头校验和，IPv6载荷长度字段)
+pub fn set_ip_total_len(data: &mut [u8], parsed: &ParsedPacket, total_len: usize) {
+    let Some(ip) = &parsed.ip else {
+        return;
+    };
+    if ip.version == 4 {
+        replace_field(data, ip.offset + 2, &(total_len as u16).to_be_bytes(), &[ip.offset + 10]);
+    } else {
+        write_u16(data, ip.offset + 4, total_len.saturating_sub(40) as u16);
+    }
+}
+
+/// IP地址修改时需要更新的传输层校验和偏移 (UDP校验和为0表示未启用，返回None)
+pub fn l4_checksum_offset(data: &[u8], parsed: &ParsedPacket) -> Option<usize> {
+    match parsed.transport? {
+        Transport::Tcp(tcp) => Some(tcp.offset + 16),
+        Transport::Udp(udp) => {
+            let offset = udp.offset + 6;
+            (offset + 2 <= data.len() && read_u16(data, offset) != 0).then_some(offset)
+        }
+        // ICMPv4校验和不含伪首部，地址变化不影响
+        Transport::Icmp { offset, .. } => {
+            (parsed.ip.as_ref()?.version == 6).then_some(offset + 2)
+        }
+    }
+}
+
+/// 在IP地址或端口修改后需要同步更新的校验和偏移
+///
+/// `in_ip_header` 为真时表示修改的是IPv4头中的字段 (需更新IP头校验和)，
+/// `in_l4_checksum` 为真时表示该字段被传输层校验和覆盖 (包括伪首部中的地址)。
+pub fn dependent_checksums(
+    data: &[u8],
+    parsed: &ParsedPacket,
+    in_ip_header: bool,
+    in_l4_checksum: bool,
+) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    if let Some(ip) = &parsed.ip
+        && in_ip_header
+        && ip.version == 4
+    {
+        offsets.push(ip.offset + 10);
+    }
+    if in_l4_checksum && let Some(offset) = l4_checksum_offset(data, parsed) {
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// IP地址在包中的字节表示
+pub fn ip_octets(addr: IpAddr) -> Vec<u8> {
+    match addr {
+        IpAddr::V4(v4) => v4.octets().to_vec(),
+        IpAddr::V6(v6) => v6.octets().to_vec(),
+    }
+}
+
+/// 修改源/目的IP地址 (地址族必须一致)，同步更新校验和
+pub fn set_ip_addresses(data: &mut [u8], parsed: &ParsedPacket, src: IpAddr, dst: IpAddr) {
+    let ip = match &parsed.ip {
+        Some(ip) => ip,
+        None => return,
+    };
+    let (src_offset, dst_offset) = if ip.version == 4 {
+        (ip.offset + 12, ip.offset + 16)
+    } else {
+        (ip.offset + 8, ip.offset + 24)
+    };
+    if src.is_ipv4() != (ip.version == 4) || dst.is_ipv4() != (ip.version == 4) {
+        return;
+    }
+    let checksums = dependent_checksums(data, parsed, true, true);
+    replace_field(data, src_offset, &ip_octets(src), &checksums);
+    replace_field(data, dst_offset, &ip_octets(dst), &checksums);
+}
+
+/// 修改TCP/UDP源/目的端口，同步更新校验和
+pub fn set_ports(data: &mut [u8], parsed: &ParsedPacket, src_port: u16, dst_port: u16) {
+    let offset = match parsed.transport {
+        Some(Transport::Tcp(tcp)) => tcp.offset,
+        Some(Transport::Udp(udp)) => udp.offset,
+        _ => return,
+    };
+    let checksums = dependent_checksums(data, parsed, false, true);
+    replace_field(data, offset, &src_port.to_be_bytes(), &checksums);
+    replace_field(data, offset + 2, &dst_port.to_be_bytes(), &checksums);
+}
@@ -0,0 +1,169 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use pcap_file::pcap_header::PcapHeader;
+use pcap_file::{Packet, PcapReader, PcapWriter};
+use anyhow::{Context, Result, anyhow};
+use log::{debug, info};
+
+/// 全局内存上限 (字节)，0表示不限制
+static MEMORY_LIMIT: AtomicU64 = AtomicU64::new(0);
+
+/// 用于生成唯一临时文件名
+static SPILL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 每个缓存包的额外内存开销估计 (包头和容器)
+pub const PACKET_OVERHEAD: usize = 64;
+
+/// 设置缓存数据包可使用的内存上限 (None表示不限制)
+pub fn set_memory_limit(limit: Option<u64>) {
+    MEMORY_LIMIT.store(limit.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// 当前内存上限
+pub fn memory_limit() -> Option<u64> {
+    match MEMORY_LIMIT.load(Ordering::Relaxed) {
+        0 => None,
+        limit => Some(limit),
+    }
+}
+
+/// 解析带单位的大小，例如 `512M`、`2G`、`1048576`
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(index) => text.split_at(index),
+        None => (text, ""),
+    };
+    let number: f64 = number.parse().map_err(|_| format!("无效的大小: {}", text))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        other => return Err(format!("未知的大小单位: {}", other)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// 超出内存上限后写入临时文件的数据包缓存
+///
+/// 前面的包保存在内存中，超出上限后的包按PCAP格式追加到临时文件，
+/// 遍历时先返回内存中的包再顺序读取临时文件。临时文件在释放时删除。
+pub struct PacketStore {
+    header: PcapHeader,
+    memory: Vec<Packet<'static>>,
+    memory_bytes: u64,
+    limit: Option<u64>,
+    spill: Option<SpillFile>,
+    len: usize,
+}
+
+/// 临时PCAP文件，释放时自动删除
+pub struct SpillFile {
+    path: PathBuf,
+    writer: PcapWriter<BufWriter<File>>,
+    packets: usize,
+}
+
+impl PacketStore {
+    /// 按全局内存上限创建缓存
+    pub fn new(header: PcapHeader) -> PacketStore {
+        PacketStore {
+            header,
+            memory: Vec::new(),
+            memory_bytes: 0,
+            limit: memory_limit(),
+            spill: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 添加一个数据包
+    pub fn push(&mut self, packet: Packet<'static>) -> Result<()> {
+        self.len += 1;
+        let size = (packet.data.len() + PACKET_OVERHEAD) as u64;
+
+        let fits = match self.limit {
+            Some(limit) => self.spill.is_none() && self.memory_bytes + size <= limit,
+            None => true,
+        };
+        if fits {
+            self.memory_bytes += size;
+            self.memory.push(packet);
+            return Ok(());
+        }
+
+        if self.spill.is_none() {
+            self.spill = Some(SpillFile::create(self.header)?);
+            info!(
+                "缓存数据包超出内存上限 ({} 字节)，后续数据包写入临时文件",
+                self.limit.unwrap_or(0)
+            );
+        }
+        self.spill.as_mut().unwrap().write(&packet)
+    }
+
+    /// 按加入顺序遍历所有数据包
+    pub fn iter(&mut self) -> Result<impl Iterator<Item = Packet<'static>> + '_> {
+        let spilled = match self.spill.as_mut() {
+            Some(spill) => Some(spill.packets()?),
+            None => None,
+        };
+
+        Ok(self.memory.iter().cloned().chain(spilled.into_iter().flatten()))
+    }
+}
+
+impl SpillFile {
+    /// 在系统临时目录创建临时文件
+    pub fn create(header: PcapHeader) -> Result<SpillFile> {
+        let path = std::env::temp_dir().join(format!(
+            "pcap-editor-spill-{}-{}.pcap",
+            std::process::id(),
+            SPILL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::create(&path)
+            .with_context(|| format!("无法创建临时文件: {}", path.display()))?;
+        let writer = PcapWriter::with_header(header, BufWriter::new(file))
+            .map_err(|e| anyhow!("创建临时文件写入器失败: {}", e))?;
+        debug!("创建临时文件: {}", path.display());
+
+        Ok(SpillFile { path, writer, packets: 0 })
+    }
+
+    /// 追加一个数据包
+    pub fn write(&mut self, packet: &Packet) -> Result<()> {
+        self.writer.write_packet(packet)
+            .map_err(|e| anyhow!("写入临时文件失败: {}", e))?;
+        self.packets += 1;
+        Ok(())
+    }
+
+    /// 从头读取已写入的数据包
+    pub fn packets(&mut self) -> Result<impl Iterator<Item = Packet<'static>> + use<>> {
+        self.writer.get_mut().flush()
+            .with_context(|| format!("刷新临时文件失败: {}", self.path.display()))?;
+        let file = File::open(&self.path)
+            .with_context(|| format!("无法打开临时文件: {}", self.path.display()))?;
+        let reader = PcapReader::new(BufReader::new(file))
+            .map_err(|e| anyhow!("读取临时文件失败: {}", e))?;
+        Ok(reader.take(self.packets))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
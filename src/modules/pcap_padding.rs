@@ -0,0 +1,43 @@
+use crate::modules::pcap_packet_parser::ParsedPacket;
+
+const LINKTYPE_ETHERNET: u32 = 1;
+
+/// 不含FCS的以太网最小帧长
+pub const ETHERNET_MIN_FRAME: usize = 60;
+
+/// 以太网填充的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingAction {
+    /// 删除IP总长度之后的字节
+    Strip,
+    /// 把短帧补零到以太网最小帧长
+    Pad,
+}
+
+/// 删除IP数据报之后的字节 (以太网最小帧长的填充、交换机附加的尾部、FCS)，返回删除的字节数
+///
+/// 非IP包、IP长度为0 (TSO卸载) 和被截断的包 (IP数据报超过抓到的长度) 不修改。
+pub fn strip_padding(data: &mut Vec<u8>, parsed: &ParsedPacket) -> usize {
+    let Some(ip) = parsed.ip.as_ref() else {
+        return 0;
+    };
+    let end = ip.end();
+    if ip.total_len == 0 || end >= data.len() {
+        return 0;
+    }
+    let removed = data.len() - end;
+    data.truncate(end);
+    removed
+}
+
+/// 把短于60字节的以太网帧补零到60字节 (网卡发送时的填充)，返回增加的字节数
+///
+/// 其他链路类型不修改。
+pub fn pad_frame(data: &mut Vec<u8>, linktype: u32) -> usize {
+    if linktype != LINKTYPE_ETHERNET || data.len() >= ETHERNET_MIN_FRAME {
+        return 0;
+    }
+    let added = ETHERNET_MIN_FRAME - data.len();
+    data.resize(ETHERNET_MIN_FRAME, 0);
+    added
+}
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_byte_regex::ByteRegex;
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_filter::Filter;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::parse_packet;
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 过滤条件 (均为命令行上的原始文本)
+#[derive(Debug, Clone)]
+pub struct PayloadFilterOptions {
+    /// 过滤表达式 (与 `stats --column`、`rewrite` 规则的 match 列相同的语法)
+    pub expression: Option<String>,
+    /// 载荷需要匹配的正则表达式
+    pub payload_regex: Option<String>,
+    /// 载荷需要包含的字节序列 (十六进制)
+    pub payload_hex: Option<String>,
+    /// 只输出不满足条件的包
+    pub invert: bool,
+}
+
+/// 解析十六进制字节序列，字节之间可以用 `:`、`-` 或空格分隔 (例如 `16030100`、`16:03:01:00`)
+pub fn parse_hex_bytes(text: &str) -> Result<Vec<u8>> {
+    let digits: String = text.chars().filter(|c| !matches!(c, ':' | '-' | ' ')).collect();
+    let digits = digits.strip_prefix("0x").unwrap_or(&digits);
+    if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("无效的十六进制字节序列: {} (应为偶数个十六进制数字)", text);
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| anyhow::anyhow!("无效的十六进制字节: {}", e)))
+        .collect()
+}
+
+/// 按过滤表达式和L4载荷内容筛选数据包 (pcap的grep)
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `options`: 过滤表达式、载荷正则表达式、载荷字节序列和是否取反
+///
+/// # 说明
+/// 载荷为TCP/UDP头之后的字节 (其他IP协议为IP头之后)，不含以太网帧尾的填充；非IP包没有载荷，
+/// 指定了载荷条件时不匹配。多个条件同时指定时需要全部满足。每个包单独匹配，不做TCP流重组，
+/// 跨越多个报文段的内容匹配不到。
+pub fn pcap_filter_payload(input_path: &str, output_path: &str, options: &PayloadFilterOptions) -> Result<()> {
+    let invalid = |e: anyhow::Error| PcapError::InvalidArgument(format!("{:#}", e));
+    let filter = match &options.expression {
+        Some(text) => Some(Filter::parse(text).with_context(|| format!("无效的过滤表达式: {}", text)).map_err(invalid)?),
+        None => None,
+    };
+    let regex = match &options.payload_regex {
+        Some(text) => Some(ByteRegex::parse(text).with_context(|| format!("无效的正则表达式: {}", text)).map_err(invalid)?),
+        None => None,
+    };
+    let needle = options.payload_hex.as_deref().map(parse_hex_bytes).transpose().map_err(invalid)?;
+    if filter.is_none() && regex.is_none() && needle.is_none() {
+        anyhow::bail!(PcapError::InvalidArgument(
+            "需要指定过滤表达式、--payload-regex 或 --payload-hex 中的至少一个".to_string()
+        ));
+    }
+
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut packet_count = 0;
+    let mut written = 0;
+    for packet in pcap_reader.by_ref() {
+        packet_count += 1;
+        let data = &packet.data;
+        let parsed = parse_packet(data, linktype);
+        let payload = parsed.ip.as_ref().map(|_| &data[parsed.payload_range(data.len())]);
+        let matches = filter.as_ref().is_none_or(|filter| filter.matches(data, linktype, &parsed))
+            && regex.as_ref().is_none_or(|regex| payload.is_some_and(|payload| regex.is_match(payload)))
+            && needle.as_ref().is_none_or(|needle| {
+                payload.is_some_and(|payload| payload.windows(needle.len()).any(|window| window == needle.as_slice()))
+            });
+        if matches == options.invert {
+            continue;
+        }
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&packet);
+        written += 1;
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!("成功过滤数据包: 输入包数={}, 输出包数={}, 输出文件={}", packet_count, written, output_path);
+
+    Ok(())
+}
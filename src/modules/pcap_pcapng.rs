@@ -0,0 +1,567 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use pcap_file::pcap_header::TsResolution;
+use pcap_file::pcap_header::PcapHeader;
+use pcap_file::PcapReader;
+use anyhow::Result;
+use log::warn;
+
+use crate::modules::pcap_error::{PCAP_HEADER_LEN, PCAP_RECORD_HEADER_LEN, PcapError};
+use crate::modules::pcap_input::{Input, open_input};
+
+/// pcapng块类型
+pub const BLOCK_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+pub const BLOCK_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+pub const BLOCK_PACKET: u32 = 0x0000_0002;
+pub const BLOCK_SIMPLE_PACKET: u32 = 0x0000_0003;
+pub const BLOCK_NAME_RESOLUTION: u32 = 0x0000_0004;
+pub const BLOCK_INTERFACE_STATISTICS: u32 = 0x0000_0005;
+pub const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+/// 通用选项
+pub const OPT_END: u16 = 0;
+pub const OPT_COMMENT: u16 = 1;
+
+/// 节头块选项
+pub const SHB_USERAPPL: u16 = 4;
+
+/// 接口描述块选项
+pub const IF_NAME: u16 = 2;
+pub const IF_DESCRIPTION: u16 = 3;
+pub const IF_SPEED: u16 = 8;
+pub const IF_TSRESOL: u16 = 9;
+pub const IF_TSOFFSET: u16 = 14;
+
+/// 接口统计块选项 (计数器均为64位)
+pub const ISB_IFRECV: u16 = 4;
+pub const ISB_IFDROP: u16 = 5;
+pub const ISB_OSDROP: u16 = 7;
+pub const ISB_USRDELIV: u16 = 8;
+
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+/// 单个块的最大长度，超出视为文件损坏
+const MAX_BLOCK_LEN: u32 = 256 * 1024 * 1024;
+
+/// 块选项 (数值类选项已统一为小端字节序)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcapngOption {
+    pub code: u16,
+    pub value: Vec<u8>,
+}
+
+impl PcapngOption {
+    pub fn string(code: u16, text: &str) -> PcapngOption {
+        PcapngOption { code, value: text.as_bytes().to_vec() }
+    }
+}
+
+/// 查找字符串选项
+pub fn option_str(options: &[PcapngOption], code: u16) -> Option<String> {
+    options
+        .iter()
+        .find(|o| o.code == code)
+        .map(|o| String::from_utf8_lossy(&o.value).into_owned())
+}
+
+/// 设置 (替换) 字符串选项
+pub fn set_option_str(options: &mut Vec<PcapngOption>, code: u16, text: &str) {
+    options.retain(|o| o.code != code);
+    options.push(PcapngOption::string(code, text));
+}
+
+/// 接口描述块
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceDescription {
+    pub linktype: u16,
+    pub snaplen: u32,
+    pub options: Vec<PcapngOption>,
+}
+
+impl InterfaceDescription {
+    /// 每秒的时间戳单位数 (由 if_tsresol 决定，默认微秒)
+    pub fn units_per_second(&self) -> u64 {
+        match self.options.iter().find(|o| o.code == IF_TSRESOL).and_then(|o| o.value.first()) {
+            Some(&resol) if resol & 0x80 != 0 => 1u64.checked_shl((resol & 0x7f) as u32).unwrap_or(u64::MAX),
+            Some(&resol) => 10u64.checked_pow(resol as u32).unwrap_or(u64::MAX),
+            None => 1_000_000,
+        }
+    }
+
+    /// 时间戳偏移秒数 (if_tsoffset)
+    pub fn ts_offset(&self) -> i64 {
+        self.options
+            .iter()
+            .find(|o| o.code == IF_TSOFFSET && o.value.len() == 8)
+            .map(|o| i64::from_le_bytes(o.value[..8].try_into().unwrap()))
+            .unwrap_or(0)
+    }
+
+    /// 原始时间戳转为纳秒
+    pub fn timestamp_ns(&self, timestamp: u64) -> i128 {
+        let units = self.units_per_second() as i128;
+        let raw = timestamp as i128;
+        (raw / units + self.ts_offset() as i128) * 1_000_000_000 + (raw % units) * 1_000_000_000 / units
+    }
+}
+
+/// 数据包 (增强包块；简单包块和旧式包块读取时也转换为此结构)
+#[derive(Debug, Clone)]
+pub struct PcapngPacket {
+    pub interface_id: u32,
+    /// 以接口的时间戳精度表示的原始时间戳
+    pub timestamp: u64,
+    pub orig_len: u32,
+    pub data: Vec<u8>,
+    pub options: Vec<PcapngOption>,
+}
+
+/// 接口统计块
+#[derive(Debug, Clone)]
+pub struct InterfaceStatistics {
+    pub interface_id: u32,
+    pub timestamp: u64,
+    pub options: Vec<PcapngOption>,
+}
+
+impl InterfaceStatistics {
+    /// 64位计数器选项 (isb_ifrecv、isb_ifdrop 等)，没有该选项时为None
+    pub fn counter(&self, code: u16) -> Option<u64> {
+        self.options
+            .iter()
+            .find(|o| o.code == code && o.value.len() == 8)
+            .map(|o| u64::from_le_bytes(o.value[..8].try_into().unwrap()))
+    }
+}
+
+/// pcapng中的一个块
+#[derive(Debug, Clone)]
+pub enum PcapngBlock {
+    Section(Vec<PcapngOption>),
+    Interface(InterfaceDescription),
+    Packet(PcapngPacket),
+    Statistics(InterfaceStatistics),
+    /// 其他块 (名称解析、自定义块等) 保留原始内容
+    Other { block_type: u32, body: Vec<u8>, big_endian: bool },
+}
+
+/// PCAP文件头对应的接口描述 (纳秒精度的文件带上 if_tsresol)
+pub fn interface_for_pcap(header: &PcapHeader) -> InterfaceDescription {
+    let mut options = Vec::new();
+    if matches!(header.ts_resolution(), TsResolution::NanoSecond) {
+        options.push(PcapngOption { code: IF_TSRESOL, value: vec![9] });
+    }
+    InterfaceDescription {
+        linktype: u32::from(header.datalink) as u16,
+        snaplen: header.snaplen,
+        options,
+    }
+}
+
+/// 读取pcapng文件第一个节头块的选项
+pub fn first_section_options(path: &str) -> Result<Vec<PcapngOption>> {
+    match PcapngReader::new(BufReader::new(open_input(path)?)).next_block()? {
+        Some(PcapngBlock::Section(options)) => Ok(options),
+        _ => anyhow::bail!(PcapError::InvalidFormat(format!("不是有效的pcapng文件: {}", path))),
+    }
+}
+
+/// 检查文件是否为pcapng格式 (gzip压缩的文件按解压后的内容判断)
+pub fn is_pcapng_file(path: &str) -> Result<bool> {
+    let mut file = open_input(path)?;
+    let mut magic = [0u8; 4];
+    Ok(file.read_exact(&mut magic).is_ok() && u32::from_le_bytes(magic) == BLOCK_SECTION_HEADER)
+}
+
+/// 以pcapng块的形式读取PCAP或pcapng文件
+///
+/// PCAP文件表现为一个节头块、一个接口描述块和其后的数据包。
+pub enum CaptureReader {
+    Pcap {
+        path: String,
+        reader: PcapReader<BufReader<Input>>,
+        interface: InterfaceDescription,
+        /// 已返回的头部块数 (节头块、接口描述块)
+        header_blocks: u8,
+        /// 已读取的字节数
+        read_bytes: u64,
+    },
+    Pcapng(PcapngReader<BufReader<Input>>),
+}
+
+impl CaptureReader {
+    /// 打开PCAP或pcapng文件 (也可以是远程地址，只读取一遍)
+    pub fn open(path: &str) -> Result<CaptureReader> {
+        let mut file = BufReader::new(open_input(path)?);
+        // 从缓冲区中查看文件头的魔数，不单独打开一次文件
+        let head = file.fill_buf().map_err(|e| PcapError::open_failed(path, e))?;
+        let pcapng = head.len() >= 4 && u32::from_le_bytes(head[0..4].try_into().unwrap()) == BLOCK_SECTION_HEADER;
+        if pcapng {
+            return Ok(CaptureReader::Pcapng(PcapngReader::new(file)));
+        }
+
+        let reader = PcapReader::new(file)
+            .map_err(PcapError::invalid_pcap)?;
+        let interface = interface_for_pcap(&reader.header);
+        Ok(CaptureReader::Pcap { path: path.to_string(), reader, interface, header_blocks: 0, read_bytes: PCAP_HEADER_LEN })
+    }
+
+    /// 当前节中的接口
+    pub fn interface(&self, interface_id: u32) -> Option<&InterfaceDescription> {
+        match self {
+            CaptureReader::Pcap { interface, .. } => (interface_id == 0).then_some(interface),
+            CaptureReader::Pcapng(reader) => reader.interfaces.get(interface_id as usize),
+        }
+    }
+
+    /// 读取下一个块，文件结束时返回None
+    pub fn next_block(&mut self) -> Result<Option<PcapngBlock>> {
+        match self {
+            CaptureReader::Pcap { path, reader, interface, header_blocks, read_bytes } => {
+                *header_blocks = header_blocks.saturating_add(1);
+                match header_blocks {
+                    1 => return Ok(Some(PcapngBlock::Section(Vec::new()))),
+                    2 => return Ok(Some(PcapngBlock::Interface(interface.clone()))),
+                    _ => {}
+                }
+                let units_per_second = interface.units_per_second();
+                let Some(packet) = reader.next() else {
+                    // 末尾不完整的包使读取提前结束，与pcapng一样按截断报错
+                    reader.get_ref().get_ref().check_read(path, *read_bytes)?;
+                    return Ok(None);
+                };
+                *read_bytes += PCAP_RECORD_HEADER_LEN + packet.data.len() as u64;
+                Ok(Some(PcapngBlock::Packet(PcapngPacket {
+                    interface_id: 0,
+                    timestamp: packet.header.ts_sec as u64 * units_per_second + packet.header.ts_usec as u64,
+                    orig_len: packet.header.orig_len,
+                    data: packet.data.into_owned(),
+                    options: Vec::new(),
+                })))
+            }
+            CaptureReader::Pcapng(reader) => reader.next_block(),
+        }
+    }
+}
+
+/// pcapng读取器
+///
+/// 按块顺序读取；遇到新的节头块时重置接口列表。
+pub struct PcapngReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    /// 当前节的接口
+    pub interfaces: Vec<InterfaceDescription>,
+    /// 已读取的节数
+    pub sections: usize,
+}
+
+impl<R: Read> PcapngReader<R> {
+    pub fn new(reader: R) -> PcapngReader<R> {
+        PcapngReader { reader, big_endian: false, interfaces: Vec::new(), sections: 0 }
+    }
+
+    /// 读取下一个块，文件结束时返回None
+    pub fn next_block(&mut self) -> Result<Option<PcapngBlock>> {
+        let mut head = [0u8; 8];
+        match read_full(&mut self.reader, &mut head)? {
+            0 => return Ok(None),
+            8 => {}
+            _ => anyhow::bail!(PcapError::TruncatedFile("pcapng文件在块头处截断".to_string())),
+        }
+
+        let block_type_le = u32::from_le_bytes(head[0..4].try_into().unwrap());
+        if block_type_le == BLOCK_SECTION_HEADER {
+            // 节头块中的字节序标记决定本节的字节序
+            let mut magic = [0u8; 4];
+            self.reader.read_exact(&mut magic).map_err(|_| PcapError::TruncatedFile("pcapng节头块截断".to_string()))?;
+            self.big_endian = match u32::from_le_bytes(magic) {
+                BYTE_ORDER_MAGIC => false,
+                m if m.swap_bytes() == BYTE_ORDER_MAGIC => true,
+                _ => anyhow::bail!(PcapError::InvalidFormat("无效的pcapng字节序标记".to_string())),
+            };
+            let total_len = self.u32_at(&head, 4);
+            let body = self.read_body(total_len, 4)?;
+            self.interfaces.clear();
+            self.sections += 1;
+            // 版本(4字节) + 节长度(8字节) 之后是选项
+            if body.len() < 12 {
+                anyhow::bail!(PcapError::InvalidFormat("pcapng节头块长度无效".to_string()));
+            }
+            return Ok(Some(PcapngBlock::Section(self.parse_options(BLOCK_SECTION_HEADER, &body[12..]))));
+        }
+        if self.sections == 0 {
+            anyhow::bail!(PcapError::InvalidFormat("不是有效的pcapng文件: 缺少节头块".to_string()));
+        }
+
+        let block_type = self.u32_at(&head, 0);
+        let total_len = self.u32_at(&head, 4);
+        let body = self.read_body(total_len, 0)?;
+
+        let block = match block_type {
+            BLOCK_INTERFACE_DESCRIPTION => {
+                if body.len() < 8 {
+                    anyhow::bail!(PcapError::InvalidFormat("pcapng接口描述块长度无效".to_string()));
+                }
+                let interface = InterfaceDescription {
+                    linktype: self.u16_at(&body, 0),
+                    snaplen: self.u32_at(&body, 4),
+                    options: self.parse_options(block_type, &body[8..]),
+                };
+                self.interfaces.push(interface.clone());
+                PcapngBlock::Interface(interface)
+            }
+            BLOCK_ENHANCED_PACKET => {
+                if body.len() < 20 {
+                    anyhow::bail!(PcapError::InvalidFormat("pcapng增强包块长度无效".to_string()));
+                }
+                let caplen = self.u32_at(&body, 12) as usize;
+                let data_end = 20 + caplen;
+                if body.len() < data_end {
+                    anyhow::bail!(PcapError::InvalidFormat("pcapng增强包块的捕获长度超出块长度".to_string()));
+                }
+                PcapngBlock::Packet(PcapngPacket {
+                    interface_id: self.u32_at(&body, 0),
+                    timestamp: (self.u32_at(&body, 4) as u64) << 32 | self.u32_at(&body, 8) as u64,
+                    orig_len: self.u32_at(&body, 16),
+                    data: body[20..data_end].to_vec(),
+                    options: self.parse_options(block_type, body.get(padded(data_end)..).unwrap_or(&[])),
+                })
+            }
+            BLOCK_SIMPLE_PACKET => {
+                if body.len() < 4 {
+                    anyhow::bail!(PcapError::InvalidFormat("pcapng简单包块长度无效".to_string()));
+                }
+                let orig_len = self.u32_at(&body, 0);
+                let snaplen = self.interfaces.first().map(|i| i.snaplen).filter(|&s| s > 0).unwrap_or(u32::MAX);
+                let caplen = (orig_len.min(snaplen) as usize).min(body.len() - 4);
+                PcapngBlock::Packet(PcapngPacket {
+                    interface_id: 0,
+                    timestamp: 0,
+                    orig_len,
+                    data: body[4..4 + caplen].to_vec(),
+                    options: Vec::new(),
+                })
+            }
+            BLOCK_PACKET => {
+                if body.len() < 20 {
+                    anyhow::bail!(PcapError::InvalidFormat("pcapng包块长度无效".to_string()));
+                }
+                let caplen = self.u32_at(&body, 12) as usize;
+                let data_end = 20 + caplen;
+                if body.len() < data_end {
+                    anyhow::bail!(PcapError::InvalidFormat("pcapng包块的捕获长度超出块长度".to_string()));
+                }
+                PcapngBlock::Packet(PcapngPacket {
+                    interface_id: self.u16_at(&body, 0) as u32,
+                    timestamp: (self.u32_at(&body, 4) as u64) << 32 | self.u32_at(&body, 8) as u64,
+                    orig_len: self.u32_at(&body, 16),
+                    data: body[20..data_end].to_vec(),
+                    options: self.parse_options(BLOCK_ENHANCED_PACKET, body.get(padded(data_end)..).unwrap_or(&[])),
+                })
+            }
+            BLOCK_INTERFACE_STATISTICS => {
+                if body.len() < 12 {
+                    anyhow::bail!(PcapError::InvalidFormat("pcapng接口统计块长度无效".to_string()));
+                }
+                PcapngBlock::Statistics(InterfaceStatistics {
+                    interface_id: self.u32_at(&body, 0),
+                    timestamp: (self.u32_at(&body, 4) as u64) << 32 | self.u32_at(&body, 8) as u64,
+                    options: self.parse_options(block_type, &body[12..]),
+                })
+            }
+            _ => PcapngBlock::Other { block_type, body, big_endian: self.big_endian },
+        };
+
+        Ok(Some(block))
+    }
+
+    /// 读取块体 (块头之后、结尾长度之前的部分)；`already_read` 为已读取的块体字节数
+    fn read_body(&mut self, total_len: u32, already_read: usize) -> Result<Vec<u8>> {
+        if total_len < 12 || !total_len.is_multiple_of(4) || total_len > MAX_BLOCK_LEN || (total_len as usize) < 12 + already_read {
+            anyhow::bail!(PcapError::InvalidFormat(format!("pcapng块长度无效: {}", total_len)));
+        }
+        let mut body = vec![0u8; total_len as usize - 12 - already_read];
+        self.reader.read_exact(&mut body).map_err(|_| PcapError::TruncatedFile("pcapng块数据截断".to_string()))?;
+        let mut trailer = [0u8; 4];
+        self.reader.read_exact(&mut trailer).map_err(|_| PcapError::TruncatedFile("pcapng块尾截断".to_string()))?;
+        if self.u32_at(&trailer, 0) != total_len {
+            anyhow::bail!(PcapError::InvalidFormat("pcapng块首尾长度不一致".to_string()));
+        }
+        Ok(body)
+    }
+
+    fn parse_options(&self, block_type: u32, mut data: &[u8]) -> Vec<PcapngOption> {
+        let mut options = Vec::new();
+        while data.len() >= 4 {
+            let code = self.u16_at(data, 0);
+            let len = self.u16_at(data, 2) as usize;
+            if code == OPT_END || data.len() < 4 + len {
+                break;
+            }
+            let mut value = data[4..4 + len].to_vec();
+            if self.big_endian {
+                normalize_option(block_type, code, &mut value);
+            }
+            options.push(PcapngOption { code, value });
+            data = data.get(4 + padded(len)..).unwrap_or(&[]);
+        }
+        options
+    }
+
+    fn u16_at(&self, data: &[u8], offset: usize) -> u16 {
+        let bytes: [u8; 2] = data[offset..offset + 2].try_into().unwrap();
+        if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    }
+
+    fn u32_at(&self, data: &[u8], offset: usize) -> u32 {
+        let bytes: [u8; 4] = data[offset..offset + 4].try_into().unwrap();
+        if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    }
+}
+
+/// 将大端节中的数值类选项转为小端
+fn normalize_option(block_type: u32, code: u16, value: &mut [u8]) {
+    let width = match (block_type, code) {
+        (BLOCK_INTERFACE_DESCRIPTION, IF_SPEED | IF_TSOFFSET) => 8,
+        // epb_flags, epb_queue
+        (BLOCK_ENHANCED_PACKET, 2 | 6) => 4,
+        // epb_dropcount, epb_packetid
+        (BLOCK_ENHANCED_PACKET, 4 | 5) => 8,
+        // isb_starttime, isb_endtime (高低两个32位)
+        (BLOCK_INTERFACE_STATISTICS, 2 | 3) => 4,
+        // isb_ifrecv 等计数器
+        (BLOCK_INTERFACE_STATISTICS, 4..=8) => 8,
+        _ => return,
+    };
+    for chunk in value.chunks_exact_mut(width) {
+        chunk.reverse();
+    }
+}
+
+/// 读满缓冲区，返回实际读取的字节数 (只在文件结束时小于缓冲区长度)
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+/// pcapng写入器 (始终以小端字节序写出)
+pub struct PcapngWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// 创建写入器并写出节头块
+    pub fn new(writer: W, section_options: &[PcapngOption]) -> Result<PcapngWriter<W>> {
+        let mut pcapng_writer = PcapngWriter { writer };
+        pcapng_writer.write_section(section_options)?;
+        Ok(pcapng_writer)
+    }
+
+    /// 开始新的节；之后的接口编号从0重新开始
+    pub fn write_section(&mut self, section_options: &[PcapngOption]) -> Result<()> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // 节长度未知
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        push_options(&mut body, section_options);
+        self.write_raw(BLOCK_SECTION_HEADER, &body)
+    }
+
+    pub fn write_interface(&mut self, interface: &InterfaceDescription) -> Result<()> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&interface.linktype.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        body.extend_from_slice(&interface.snaplen.to_le_bytes());
+        push_options(&mut body, &interface.options);
+        self.write_raw(BLOCK_INTERFACE_DESCRIPTION, &body)
+    }
+
+    /// 以增强包块写出数据包
+    pub fn write_packet(&mut self, packet: &PcapngPacket) -> Result<()> {
+        let mut body = Vec::with_capacity(32 + packet.data.len());
+        body.extend_from_slice(&packet.interface_id.to_le_bytes());
+        body.extend_from_slice(&((packet.timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.timestamp as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&packet.orig_len.to_le_bytes());
+        body.extend_from_slice(&packet.data);
+        body.resize(padded(body.len()), 0);
+        push_options(&mut body, &packet.options);
+        self.write_raw(BLOCK_ENHANCED_PACKET, &body)
+    }
+
+    pub fn write_statistics(&mut self, statistics: &InterfaceStatistics) -> Result<()> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&statistics.interface_id.to_le_bytes());
+        body.extend_from_slice(&((statistics.timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(statistics.timestamp as u32).to_le_bytes());
+        push_options(&mut body, &statistics.options);
+        self.write_raw(BLOCK_INTERFACE_STATISTICS, &body)
+    }
+
+    /// 按块类型写出
+    pub fn write_block(&mut self, block: &PcapngBlock) -> Result<()> {
+        match block {
+            PcapngBlock::Section(options) => self.write_section(options),
+            PcapngBlock::Interface(interface) => self.write_interface(interface),
+            PcapngBlock::Packet(packet) => self.write_packet(packet),
+            PcapngBlock::Statistics(statistics) => self.write_statistics(statistics),
+            PcapngBlock::Other { block_type, body, big_endian } => self.write_other(*block_type, body, *big_endian),
+        }
+    }
+
+    /// 写出保留原始内容的块；大端节中的未知块无法转换字节序，跳过
+    pub fn write_other(&mut self, block_type: u32, body: &[u8], big_endian: bool) -> Result<()> {
+        if big_endian {
+            warn!("跳过大端字节序的pcapng块 (类型 0x{:08x})", block_type);
+            return Ok(());
+        }
+        self.write_raw(block_type, body)
+    }
+
+    fn write_raw(&mut self, block_type: u32, body: &[u8]) -> Result<()> {
+        let total_len = (12 + padded(body.len())) as u32;
+        self.writer.write_all(&block_type.to_le_bytes())?;
+        self.writer.write_all(&total_len.to_le_bytes())?;
+        self.writer.write_all(body)?;
+        self.writer.write_all(&[0u8; 3][..padded(body.len()) - body.len()])?;
+        self.writer.write_all(&total_len.to_le_bytes())?;
+        Ok(())
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn push_options(body: &mut Vec<u8>, options: &[PcapngOption]) {
+    if options.is_empty() {
+        return;
+    }
+    for option in options {
+        body.extend_from_slice(&option.code.to_le_bytes());
+        body.extend_from_slice(&(option.value.len() as u16).to_le_bytes());
+        body.extend_from_slice(&option.value);
+        body.resize(padded(body.len()), 0);
+    }
+    body.extend_from_slice(&OPT_END.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+}
@@ -0,0 +1,345 @@
+use std::fs;
+use std::hash::Hasher;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use anyhow::{Context, Result, anyhow};
+use log::{debug, info, warn};
+use seahash::SeaHasher;
+
+use crate::modules::io::OutputFormat;
+use crate::modules::pcap_checkpoint::{Checkpoint, CheckpointOptions, StepProgress, StreamCheckpoint};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_input::open_input;
+use crate::modules::pcap_output::{Compression, compression, create_output};
+use crate::modules::pcap_time_anchor::TimeAnchor;
+use crate::modules::{
+    pcap_augment_timed, pcap_dilute_timed, pcap_shuffle_tester, pcap_time_dilator,
+    pcap_time_reducer,
+};
+
+/// 流水线中的单个处理步骤
+#[derive(Debug, Clone)]
+pub enum PipelineStep {
+    /// 时间轴压缩
+    TimeCompress { factor: f64, anchor: TimeAnchor },
+    /// 时间轴拉伸
+    TimeStretch { factor: f64, anchor: TimeAnchor },
+    /// 数据包稀释
+    Dilute { factor: usize },
+    /// 数据包增强
+    Augment { factor: usize },
+    /// 乱序检测 (只做检查，不产生新文件)
+    DisorderDetect,
+}
+
+impl PipelineStep {
+    /// 步骤名称，与命令行子命令保持一致
+    pub fn name(&self) -> &'static str {
+        match self {
+            PipelineStep::TimeCompress { .. } => "time-compress",
+            PipelineStep::TimeStretch { .. } => "time-stretch",
+            PipelineStep::Dilute { .. } => "dilute",
+            PipelineStep::Augment { .. } => "augment",
+            PipelineStep::DisorderDetect => "disorder-detect",
+        }
+    }
+
+    /// 该步骤是否会生成新的输出文件
+    fn produces_output(&self) -> bool {
+        !matches!(self, PipelineStep::DisorderDetect)
+    }
+
+    /// 该步骤是否逐包流式处理，可以在步骤中间保存检查点
+    fn streaming(&self) -> bool {
+        matches!(self, PipelineStep::TimeCompress { .. } | PipelineStep::TimeStretch { .. })
+    }
+
+    /// 执行步骤，输出按 `format` 写出、按 `compression` 压缩；只读步骤忽略 `output_path`，非流式步骤忽略 `checkpoint`
+    fn run(
+        &self,
+        input_path: &str,
+        output_path: &str,
+        format: OutputFormat,
+        compression: Compression,
+        checkpoint: Option<StreamCheckpoint>,
+    ) -> Result<()> {
+        match self {
+            PipelineStep::TimeCompress { factor, anchor } => pcap_time_reducer::pcap_time_compressor(
+                input_path, output_path, *factor, *anchor, format, compression, checkpoint,
+            ),
+            PipelineStep::TimeStretch { factor, anchor } => pcap_time_dilator::pcap_time_dilator(
+                input_path, output_path, *factor, *anchor, format, compression, checkpoint,
+            ),
+            PipelineStep::Dilute { factor } => {
+                pcap_dilute_timed::pcap_dilute_timed(input_path, output_path, *factor, format, compression)
+            }
+            PipelineStep::Augment { factor } => {
+                pcap_augment_timed::pcap_augment_timed(input_path, output_path, *factor, format, compression)
+            }
+            PipelineStep::DisorderDetect => pcap_shuffle_tester::detect_pcap_disorder(input_path, None),
+        }
+    }
+
+    /// 根据步骤名称和参数表构造步骤
+    fn from_fields(fields: &[(String, String)]) -> Result<PipelineStep> {
+        let get = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+        let op = get("op").ok_or_else(|| anyhow!("流水线步骤缺少 op 字段: {:?}", fields))?;
+        let factor = || get("factor").ok_or_else(|| anyhow!("步骤 {} 缺少 factor 参数", op));
+        let anchor = || get("anchor").map(TimeAnchor::parse).unwrap_or(Ok(TimeAnchor::Start));
+
+        let step = match op {
+            "time-compress" => PipelineStep::TimeCompress {
+                factor: factor()?.parse().with_context(|| format!("步骤 {} 的 factor 无效", op))?,
+                anchor: anchor()?,
+            },
+            "time-stretch" => PipelineStep::TimeStretch {
+                factor: factor()?.parse().with_context(|| format!("步骤 {} 的 factor 无效", op))?,
+                anchor: anchor()?,
+            },
+            "dilute" => PipelineStep::Dilute {
+                factor: factor()?.parse().with_context(|| format!("步骤 {} 的 factor 无效", op))?,
+            },
+            "augment" => PipelineStep::Augment {
+                factor: factor()?.parse().with_context(|| format!("步骤 {} 的 factor 无效", op))?,
+            },
+            "disorder-detect" => PipelineStep::DisorderDetect,
+            other => anyhow::bail!("未知的流水线操作: {}", other),
+        };
+
+        Ok(step)
+    }
+}
+
+/// 从配置文件加载流水线
+///
+/// 支持YAML的一个简单子集:
+///
+/// ```yaml
+/// steps:
+///   - op: time-compress
+///     factor: 2.0
+///   - op: dilute
+///     factor: 3
+/// ```
+pub fn load_pipeline(pipeline_path: &str) -> Result<Vec<PipelineStep>> {
+    let content = fs::read_to_string(Path::new(pipeline_path))
+        .with_context(|| format!("无法读取流水线配置: {}", pipeline_path))?;
+
+    parse_pipeline(&content, pipeline_path)
+}
+
+/// 解析流水线配置文本，`source` 仅用于错误信息
+pub fn parse_pipeline(content: &str, source: &str) -> Result<Vec<PipelineStep>> {
+    let mut step_fields: Vec<Vec<(String, String)>> = Vec::new();
+
+    for (line_no, raw_line) in content.lines().enumerate() {
+        // 去掉注释和空行
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() || line == "steps:" {
+            continue;
+        }
+
+        // "- " 开始一个新步骤
+        let entry = match line.strip_prefix('-') {
+            Some(rest) => {
+                step_fields.push(Vec::new());
+                rest.trim()
+            }
+            None => line,
+        };
+        if entry.is_empty() {
+            continue;
+        }
+
+        let (key, value) = entry.split_once(':').ok_or_else(|| {
+            anyhow!("流水线配置第{}行格式错误: {}", line_no + 1, raw_line)
+        })?;
+        let fields = step_fields.last_mut().ok_or_else(|| {
+            anyhow!("流水线配置第{}行不属于任何步骤: {}", line_no + 1, raw_line)
+        })?;
+        let value = value.trim().trim_matches('"').trim_matches('\'');
+        fields.push((key.trim().to_string(), value.to_string()));
+    }
+
+    let steps = step_fields
+        .iter()
+        .map(|fields| PipelineStep::from_fields(fields))
+        .collect::<Result<Vec<_>>>()?;
+
+    if steps.is_empty() {
+        anyhow::bail!("流水线配置不包含任何步骤: {}", source);
+    }
+
+    Ok(steps)
+}
+
+/// 依次执行流水线中的所有步骤
+///
+/// # 参数
+/// - `steps`: 流水线步骤
+/// - `input_path`: 输入PCAP文件路径
+/// - `output_path`: 最终输出文件路径
+/// - `format`: 输出格式 (PCAP或pcapng)，中间文件没有输出文件的扩展名，所有步骤都按这个格式写出
+/// - `checkpoint`: 检查点选项，None表示不保存检查点
+///
+/// # 功能
+/// 1. 每个步骤的输出作为下一个步骤的输入，中间文件在完成后删除
+/// 2. 只读步骤（如乱序检测）直接检查当前文件
+/// 3. 若没有任何步骤产生新文件，则原样复制输入文件
+/// 4. 保存检查点时，每个步骤开始和完成时写入 `<输出>.checkpoint`，
+///    流式步骤 (时间压缩/拉伸) 另外每处理 `interval` 个包保存输入偏移和已写入的输出长度；
+///    失败时保留检查点和中间文件，`resume` 时跳过已完成的步骤并从中断处继续
+/// 5. 检查点记录流水线配置、输出格式、输入文件大小和修改时间的指纹，与当前不一致时拒绝继续
+/// 6. `--compress` 只压缩最后一个产生文件的步骤的输出，中间文件不压缩；
+///    压缩的输出不能从中间继续写，该步骤不保存步骤内的进度
+pub fn run_pipeline(
+    steps: &[PipelineStep],
+    input_path: &str,
+    output_path: &str,
+    format: OutputFormat,
+    checkpoint: Option<&CheckpointOptions>,
+) -> Result<()> {
+    let checkpoint_path = Checkpoint::path_for(output_path);
+    let fingerprint = pipeline_fingerprint(steps, input_path, output_path, format)?;
+    let compression = compression();
+    let last_output = steps.iter().rposition(PipelineStep::produces_output);
+    if matches!(compression, Compression::Zstd(_)) && last_output.is_some_and(|last| last + 1 < steps.len()) {
+        anyhow::bail!(PcapError::InvalidArgument(
+            "最后一个产生文件的步骤之后还有只读步骤，无法读取zstd压缩的输出 (可以使用 --compress gzip)".to_string()
+        ));
+    }
+    let mut state = Checkpoint { fingerprint, step: 0, current: input_path.to_string(), progress: None };
+
+    if let Some(options) = checkpoint {
+        if options.interval == 0 {
+            anyhow::bail!(PcapError::InvalidArgument("检查点间隔必须大于0".to_string()));
+        }
+        match Checkpoint::load(&checkpoint_path)? {
+            Some(saved) if options.resume => {
+                if saved.fingerprint != fingerprint || saved.step > steps.len() {
+                    anyhow::bail!(PcapError::InvalidArgument(format!(
+                            "检查点与当前的流水线配置或输入文件不一致: {}",
+                            checkpoint_path
+                    )));
+                }
+                info!(
+                    "从检查点继续: 步骤 #{}, 已处理 {} 个包",
+                    saved.step + 1,
+                    saved.progress.map_or(0, |progress| progress.packets)
+                );
+                state = saved;
+            }
+            None if options.resume => warn!("没有找到检查点，从头开始: {}", checkpoint_path),
+            Some(_) => warn!("忽略已有的检查点 (使用 --resume 继续): {}", checkpoint_path),
+            None => {}
+        }
+    }
+    let save = |state: &Checkpoint| match checkpoint {
+        Some(_) => state.save(&checkpoint_path),
+        None => Ok(()),
+    };
+
+    for (index, step) in steps.iter().enumerate().skip(state.step) {
+        debug!("执行流水线步骤 #{}: {}", index + 1, step.name());
+        state.step = index;
+        save(&state)?;
+
+        let failed = |e: anyhow::Error| match checkpoint {
+            Some(_) => e.context(format!(
+                "流水线步骤 #{} ({}) 失败，已保存检查点 (使用 --resume 继续)",
+                index + 1,
+                step.name()
+            )),
+            None => e.context(format!("流水线步骤 #{} ({}) 失败", index + 1, step.name())),
+        };
+
+        if !step.produces_output() {
+            step.run(&state.current, output_path, format, Compression::None, None).map_err(failed)?;
+            continue;
+        }
+
+        let step_output = format!("{}.step{}.tmp", output_path, index + 1);
+        let compressed = Some(index) == last_output && compression != Compression::None;
+        let step_compression = if compressed { compression } else { Compression::None };
+        let result = match checkpoint {
+            Some(options) if step.streaming() && !compressed => {
+                let mut save_progress = |progress: StepProgress| {
+                    let progress_state = Checkpoint { progress: Some(progress), ..state.clone() };
+                    save(&progress_state)
+                };
+                let stream_checkpoint = StreamCheckpoint {
+                    progress: state.progress.unwrap_or_default(),
+                    interval: options.interval,
+                    save: &mut save_progress,
+                };
+                step.run(&state.current, &step_output, format, step_compression, Some(stream_checkpoint))
+            }
+            _ => step.run(&state.current, &step_output, format, step_compression, None),
+        };
+
+        if let Err(e) = result {
+            // 保存检查点时保留中间文件以便继续
+            if checkpoint.is_none() {
+                if state.current != input_path {
+                    let _ = fs::remove_file(&state.current);
+                }
+                let _ = fs::remove_file(&step_output);
+            }
+            return Err(failed(e));
+        }
+
+        // 先记录本步骤已完成，再删除上一步的中间文件
+        let previous = std::mem::replace(&mut state.current, step_output);
+        state.step = index + 1;
+        state.progress = None;
+        save(&state)?;
+        if previous != input_path {
+            let _ = fs::remove_file(&previous);
+        }
+    }
+
+    if state.current == input_path && compression != Compression::None {
+        let mut input = open_input(input_path)?;
+        let mut output = create_output(output_path, compression)?;
+        io::copy(&mut input, &mut output)
+            .with_context(|| format!("无法复制文件到: {}", output_path))?;
+        output.finish()?;
+    } else if state.current == input_path {
+        fs::copy(input_path, output_path)
+            .with_context(|| format!("无法复制文件到: {}", output_path))?;
+    } else {
+        fs::rename(&state.current, output_path)
+            .with_context(|| format!("无法移动结果文件到: {}", output_path))?;
+    }
+    if checkpoint.is_some() {
+        let _ = fs::remove_file(&checkpoint_path);
+    }
+
+    info!("流水线执行完成: {} -> {} ({} 个步骤)", input_path, output_path, steps.len());
+
+    Ok(())
+}
+
+/// 流水线配置、输入文件 (路径、大小、修改时间) 和输出路径的指纹
+fn pipeline_fingerprint(steps: &[PipelineStep], input_path: &str, output_path: &str, format: OutputFormat) -> Result<u64> {
+    let metadata = fs::metadata(Path::new(input_path))
+        .map_err(|e| PcapError::open_failed(input_path, e))?;
+    let modified_ns = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_nanos() as u64);
+
+    let mut hasher = SeaHasher::new();
+    hasher.write(format!("{:?}", steps).as_bytes());
+    hasher.write(input_path.as_bytes());
+    hasher.write(output_path.as_bytes());
+    // 输出格式不同时已写出的部分不能继续使用
+    hasher.write(format!("{:?}", format).as_bytes());
+    hasher.write(format!("{:?}", compression()).as_bytes());
+    hasher.write_u64(metadata.len());
+    hasher.write_u64(modified_ns);
+    Ok(hasher.finish())
+}
@@ -0,0 +1,177 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_int, c_void};
+use pcap_file::Packet;
+use anyhow::{Result, anyhow};
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 插件导出的处理函数名称
+const TRANSFORM_SYMBOL: &str = "pcap_editor_transform";
+
+/// 传递给插件的数据包 (C ABI)
+///
+/// 插件可以原地修改 `data` 中的内容、时间戳以及 `len`，
+/// 但 `len` 不能超过 `capacity`。
+#[repr(C)]
+pub struct PluginPacket {
+    pub ts_sec: u32,
+    pub ts_usec: u32,
+    pub orig_len: u32,
+    pub len: u32,
+    pub capacity: u32,
+    pub data: *mut u8,
+}
+
+/// 插件处理函数签名
+///
+/// 返回值为该包的输出次数: 0 = 丢弃, 1 = 保留, N = 复制N次，负数表示出错并中止处理。
+type TransformFn = unsafe extern "C" fn(packet: *mut PluginPacket) -> c_int;
+
+/// 动态加载的插件库
+struct Plugin {
+    handle: *mut c_void,
+    transform: TransformFn,
+}
+
+impl Plugin {
+    fn load(plugin_path: &str) -> Result<Plugin> {
+        let c_path = CString::new(plugin_path)
+            .map_err(|_| anyhow!("插件路径包含非法字符: {}", plugin_path))?;
+
+        // SAFETY: 路径是以NUL结尾的合法C字符串
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            anyhow::bail!("无法加载插件 {}: {}", plugin_path, last_dl_error());
+        }
+
+        let symbol = CString::new(TRANSFORM_SYMBOL).unwrap();
+        // SAFETY: handle 来自成功的 dlopen 调用
+        let address = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+        if address.is_null() {
+            // SAFETY: handle 有效且之后不再使用
+            unsafe { libc::dlclose(handle) };
+            anyhow::bail!("插件 {} 未导出函数 {}", plugin_path, TRANSFORM_SYMBOL);
+        }
+
+        // SAFETY: 插件约定该符号的签名为 TransformFn
+        let transform = unsafe { std::mem::transmute::<*mut c_void, TransformFn>(address) };
+
+        Ok(Plugin { handle, transform })
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        // SAFETY: handle 来自成功的 dlopen 调用，且只关闭一次
+        unsafe { libc::dlclose(self.handle) };
+    }
+}
+
+/// 使用外部插件逐包处理PCAP文件
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `plugin_path`: 插件动态库路径 (.so)
+/// - `max_packet_size`: 插件可写入的最大包长 (至少为原始包长)
+///
+/// # 功能
+/// 1. 对每个包调用插件导出的 `pcap_editor_transform`
+/// 2. 插件可修改包内容、长度和时间戳
+/// 3. 根据返回值丢弃、保留或复制数据包
+pub fn pcap_plugin_transform(
+    input_path: &str,
+    output_path: &str,
+    plugin_path: &str,
+    max_packet_size: usize,
+) -> Result<()> {
+    let plugin = Plugin::load(plugin_path)?;
+
+    // 打开输入文件
+    let mut pcap_reader = PacketReader::open(input_path)?;
+
+    // 创建输出文件
+    let out_file = create_output(output_path, compression())?;
+    let header = pcap_reader.header;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut buffer = vec![0u8; max_packet_size];
+    let mut packet_count = 0;
+    let mut written = 0;
+    let mut dropped = 0;
+
+    for packet in pcap_reader.by_ref() {
+        packet_count += 1;
+
+        // 保证缓冲区至少能容纳原始包
+        let len = packet.data.len();
+        if buffer.len() < len {
+            buffer.resize(len, 0);
+        }
+        buffer[..len].copy_from_slice(&packet.data);
+
+        let mut plugin_packet = PluginPacket {
+            ts_sec: packet.header.ts_sec,
+            ts_usec: packet.header.ts_usec,
+            orig_len: packet.header.orig_len,
+            len: len as u32,
+            capacity: buffer.len() as u32,
+            data: buffer.as_mut_ptr(),
+        };
+
+        // SAFETY: plugin_packet 指向的缓冲区在调用期间有效，容量已告知插件
+        let copies = unsafe { (plugin.transform)(&mut plugin_packet) };
+        if copies < 0 {
+            anyhow::bail!("插件处理包#{}时返回错误: {}", packet_count, copies);
+        }
+        if plugin_packet.len > plugin_packet.capacity {
+            anyhow::bail!(
+                "插件处理包#{}后长度越界: {} > {}",
+                packet_count, plugin_packet.len, plugin_packet.capacity
+            );
+        }
+        if copies == 0 {
+            dropped += 1;
+            continue;
+        }
+
+        let data = &buffer[..plugin_packet.len as usize];
+        let mut new_packet = Packet::new(plugin_packet.ts_sec, plugin_packet.ts_usec, data.len() as u32, data);
+        new_packet.header.orig_len = plugin_packet.orig_len.max(data.len() as u32);
+
+        for _ in 0..copies {
+            pcap_writer.write_packet(&new_packet)
+                .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+            digest.record_pcap(&new_packet);
+            written += 1;
+        }
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "插件处理完成: 原始包数={}, 丢弃包数={}, 输出包数={}",
+        packet_count, dropped, written
+    );
+
+    Ok(())
+}
+
+/// 读取 dlerror() 的错误描述
+fn last_dl_error() -> String {
+    // SAFETY: dlerror 返回NULL或指向静态错误字符串的指针
+    let message = unsafe { libc::dlerror() };
+    if message.is_null() {
+        "未知错误".to_string()
+    } else {
+        // SAFETY: 非NULL时为以NUL结尾的C字符串
+        unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+    }
+}
@@ -0,0 +1,361 @@
+use crate::modules::pcap_packet_parser::FlowKey;
+use crate::modules::pcap_tls::{ClientHello, parse_client_hello};
+use crate::modules::pcap_wifi_crypto::{Aes128, gcm_decrypt, hmac_sha256};
+
+pub const QUIC_V1: u32 = 0x0000_0001;
+pub const QUIC_V2: u32 = 0x6b33_43cf;
+
+/// 派生Initial密钥用的盐 (RFC 9001 5.2)
+const INITIAL_SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad, 0xcc, 0xbb, 0x7f, 0x0a,
+];
+
+/// QUIC v2的盐 (RFC 9369 3.3.1)
+const INITIAL_SALT_V2: [u8; 20] = [
+    0x0d, 0xed, 0xe3, 0xde, 0xf7, 0x00, 0xa6, 0xdb, 0x81, 0x93, 0x81, 0xbe, 0x6e, 0x26, 0x9d, 0xcb, 0xf9, 0xbd, 0x2e, 0xd9,
+];
+
+/// 连接ID的最大长度
+const MAX_CID_LEN: usize = 20;
+
+/// 一个连接中最多收集的CRYPTO帧数据 (字节)，ClientHello不会超过它
+const MAX_CRYPTO_BYTES: u64 = 64 * 1024;
+
+const FRAME_PADDING: u64 = 0x00;
+const FRAME_PING: u64 = 0x01;
+const FRAME_ACK: u64 = 0x02;
+const FRAME_ACK_ECN: u64 = 0x03;
+const FRAME_CRYPTO: u64 = 0x06;
+const FRAME_CONNECTION_CLOSE: u64 = 0x1c;
+
+/// 长包头的包类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    Initial,
+    ZeroRtt,
+    Handshake,
+    Retry,
+    VersionNegotiation,
+}
+
+/// 解析出的长包头
+pub struct LongHeader<'a> {
+    pub packet_type: PacketType,
+    pub version: u32,
+    pub dcid: &'a [u8],
+    pub scid: &'a [u8],
+    /// 包号的偏移 (Initial、0-RTT和Handshake包)
+    pn_offset: usize,
+    /// 包的长度，数据报中之后的部分为合并的下一个包
+    pub len: usize,
+}
+
+/// 版本的名称，例如 `v1`、`draft-29`
+pub fn version_name(version: u32) -> String {
+    match version {
+        0 => "版本协商".to_string(),
+        QUIC_V1 => "v1".to_string(),
+        QUIC_V2 => "v2".to_string(),
+        0xff00_0000..=0xff00_00ff => format!("draft-{}", version & 0xff),
+        other => format!("0x{:08x}", other),
+    }
+}
+
+/// 连接ID的十六进制表示，空的连接ID为 `-`
+pub fn format_cid(cid: &[u8]) -> String {
+    if cid.is_empty() {
+        return "-".to_string();
+    }
+    cid.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 读取变长整数 (前两位为长度)，越界时返回None
+fn read_varint(data: &[u8], offset: &mut usize) -> Option<u64> {
+    let first = *data.get(*offset)?;
+    let len = 1usize << (first >> 6);
+    let bytes = data.get(*offset..*offset + len)?;
+    *offset += len;
+    Some(bytes[1..].iter().fold((first & 0x3f) as u64, |value, &b| value << 8 | b as u64))
+}
+
+/// 解析数据报中一个包的长包头，不是长包头或版本不能识别时返回None
+///
+/// 能识别的版本为 v1、v2、草案版本 (包格式与v1相同) 和版本协商包。
+pub fn parse_long_header(data: &[u8]) -> Option<LongHeader<'_>> {
+    let first = *data.first()?;
+    if first & 0x80 == 0 {
+        return None;
+    }
+    let version = u32::from_be_bytes(data.get(1..5)?.try_into().unwrap());
+    let mut offset = 5;
+    let connection_id = |offset: &mut usize| {
+        let len = *data.get(*offset)? as usize;
+        let cid = data.get(*offset + 1..*offset + 1 + len).filter(|_| len <= MAX_CID_LEN)?;
+        *offset += 1 + len;
+        Some(cid)
+    };
+    let dcid = connection_id(&mut offset)?;
+    let scid = connection_id(&mut offset)?;
+    let type_bits = (first >> 4) & 0x03;
+    let packet_type = match version {
+        0 => PacketType::VersionNegotiation,
+        _ if first & 0x40 == 0 => return None,
+        QUIC_V1 | 0xff00_0000..=0xff00_00ff => [PacketType::Initial, PacketType::ZeroRtt, PacketType::Handshake, PacketType::Retry][type_bits as usize],
+        QUIC_V2 => [PacketType::Retry, PacketType::Initial, PacketType::ZeroRtt, PacketType::Handshake][type_bits as usize],
+        _ => return None,
+    };
+    let mut header = LongHeader { packet_type, version, dcid, scid, pn_offset: 0, len: data.len() };
+    if matches!(packet_type, PacketType::VersionNegotiation | PacketType::Retry) {
+        return Some(header);
+    }
+    if packet_type == PacketType::Initial {
+        let token_len = read_varint(data, &mut offset)?;
+        offset = offset.checked_add(usize::try_from(token_len).ok()?)?;
+    }
+    let length = usize::try_from(read_varint(data, &mut offset)?).ok()?;
+    header.pn_offset = offset;
+    header.len = offset.checked_add(length).filter(|&len| len <= data.len())?;
+    Some(header)
+}
+
+/// 客户端Initial包的密钥
+struct InitialKeys {
+    key: [u8; 16],
+    iv: [u8; 12],
+    header_protection: Aes128,
+}
+
+/// TLS 1.3的HKDF-Expand-Label (上下文为空)
+fn expand_label(secret: &[u8], label: &str, len: usize) -> Vec<u8> {
+    let full_label = format!("tls13 {}", label);
+    let mut info = (len as u16).to_be_bytes().to_vec();
+    info.push(full_label.len() as u8);
+    info.extend_from_slice(full_label.as_bytes());
+    info.push(0);
+    let mut output = Vec::with_capacity(len + 32);
+    let mut block: Vec<u8> = Vec::new();
+    let mut counter = 1u8;
+    while output.len() < len {
+        block = hmac_sha256(secret, &[&block, &info, &[counter]]).to_vec();
+        output.extend_from_slice(&block);
+        counter += 1;
+    }
+    output.truncate(len);
+    output
+}
+
+/// 由客户端第一个Initial包的目的连接ID派生客户端的Initial密钥，不支持的版本返回None
+fn client_initial_keys(version: u32, dcid: &[u8]) -> Option<InitialKeys> {
+    let (salt, prefix) = match version {
+        QUIC_V1 => (&INITIAL_SALT_V1, "quic"),
+        QUIC_V2 => (&INITIAL_SALT_V2, "quicv2"),
+        _ => return None,
+    };
+    let initial_secret = hmac_sha256(salt, &[dcid]);
+    let client_secret = expand_label(&initial_secret, "client in", 32);
+    let header_protection = expand_label(&client_secret, &format!("{} hp", prefix), 16);
+    Some(InitialKeys {
+        key: expand_label(&client_secret, &format!("{} key", prefix), 16).try_into().unwrap(),
+        iv: expand_label(&client_secret, &format!("{} iv", prefix), 12).try_into().unwrap(),
+        header_protection: Aes128::new(&header_protection.try_into().unwrap()),
+    })
+}
+
+/// 去掉包头保护并解密一个Initial包，返回明文 (帧序列)；校验失败时返回None
+///
+/// 包号直接使用包头中截断的值: 连接开始时的Initial包号很小，截断不影响结果。
+fn decrypt_initial(packet: &[u8], header: &LongHeader, keys: &InitialKeys) -> Option<Vec<u8>> {
+    let pn_offset = header.pn_offset;
+    let sample: &[u8; 16] = packet.get(pn_offset + 4..pn_offset + 20)?.try_into().unwrap();
+    let mask = keys.header_protection.encrypt(sample);
+    let first = packet[0] ^ (mask[0] & 0x0f);
+    let pn_len = (first & 0x03) as usize + 1;
+    let mut aad = packet[..pn_offset + pn_len].to_vec();
+    aad[0] = first;
+    let mut nonce = keys.iv;
+    for i in 0..pn_len {
+        aad[pn_offset + i] ^= mask[1 + i];
+        nonce[12 - pn_len + i] ^= aad[pn_offset + i];
+    }
+    gcm_decrypt(&keys.key, &nonce, &aad, &packet[pn_offset + pn_len..header.len])
+}
+
+/// 从Initial包的帧序列中取出CRYPTO帧 (偏移, 数据)，遇到Initial包中不允许的帧时停止
+fn crypto_frames(frames: &[u8]) -> Vec<(u64, &[u8])> {
+    let mut crypto = Vec::new();
+    let mut offset = 0;
+    while offset < frames.len() && skip_frame(frames, &mut offset, &mut crypto).is_some() {}
+    crypto
+}
+
+/// 跳过一个帧，CRYPTO帧的数据加入 `crypto`；不认识的帧或帧不完整时返回None
+fn skip_frame<'a>(frames: &'a [u8], offset: &mut usize, crypto: &mut Vec<(u64, &'a [u8])>) -> Option<()> {
+    let frame_type = read_varint(frames, offset)?;
+    match frame_type {
+        FRAME_PADDING | FRAME_PING => {},
+        FRAME_ACK | FRAME_ACK_ECN => {
+            // 最大确认号、延迟、范围数、第一个范围，之后每个范围两个值，ECN计数三个值
+            read_varint(frames, offset)?;
+            read_varint(frames, offset)?;
+            let ranges = read_varint(frames, offset)?;
+            read_varint(frames, offset)?;
+            let extra = if frame_type == FRAME_ACK_ECN { 3 } else { 0 };
+            for _ in 0..ranges.checked_mul(2)?.checked_add(extra)? {
+                read_varint(frames, offset)?;
+            }
+        },
+        FRAME_CRYPTO => {
+            let crypto_offset = read_varint(frames, offset)?;
+            let len = usize::try_from(read_varint(frames, offset)?).ok()?;
+            let data = frames.get(*offset..offset.checked_add(len)?)?;
+            *offset += len;
+            crypto.push((crypto_offset, data));
+        },
+        FRAME_CONNECTION_CLOSE => {
+            read_varint(frames, offset)?;
+            read_varint(frames, offset)?;
+            let reason_len = usize::try_from(read_varint(frames, offset)?).ok()?;
+            *offset = offset.checked_add(reason_len)?;
+        },
+        _ => return None,
+    }
+    Some(())
+}
+
+/// 一个QUIC连接 (一个UDP流)，从客户端的Initial包中取出ClientHello
+#[derive(Default)]
+pub struct QuicConnection {
+    /// 客户端 (发送第一个Initial包的一方) 到服务端的方向
+    pub client: Option<FlowKey>,
+    /// 客户端最后使用的版本 (收到版本协商包后可能改变)
+    pub version: Option<u32>,
+    /// 客户端第一个Initial包的目的连接ID和源连接ID
+    pub dcid: Vec<u8>,
+    pub scid: Vec<u8>,
+    /// 服务端的源连接ID
+    pub server_scid: Option<Vec<u8>>,
+    /// 派生Initial密钥用的连接ID (收到Retry包后为Retry包的源连接ID)
+    key_cid: Vec<u8>,
+    /// 客户端Initial包中的CRYPTO帧 (偏移, 数据)
+    crypto: Vec<(u64, Vec<u8>)>,
+    pub client_hello: Option<ClientHello>,
+    /// 长包头的包数
+    pub long_header_packets: u64,
+    /// 无法解密的客户端Initial包数 (不支持的版本或校验失败)
+    pub undecryptable: u64,
+}
+
+impl QuicConnection {
+    /// 记录一个UDP数据报 (`key` 为它的方向)，返回其中是否有QUIC长包头
+    pub fn add_datagram(&mut self, key: FlowKey, datagram: &[u8]) -> bool {
+        let mut offset = 0;
+        while let Some(header) = parse_long_header(&datagram[offset..]) {
+            self.long_header_packets += 1;
+            self.add_packet(key, &datagram[offset..offset + header.len], &header);
+            // 合并的包之后可能是短包头的包或填充，它们不能解析为长包头
+            offset += header.len;
+        }
+        offset > 0
+    }
+
+    fn add_packet(&mut self, key: FlowKey, packet: &[u8], header: &LongHeader) {
+        if self.client.is_none() {
+            if header.packet_type != PacketType::Initial {
+                return;
+            }
+            self.client = Some(key);
+            self.dcid = header.dcid.to_vec();
+            self.scid = header.scid.to_vec();
+            self.key_cid = self.dcid.clone();
+        }
+        if self.client != Some(key) {
+            match header.packet_type {
+                PacketType::Retry => {
+                    // 客户端之后的Initial包使用新的连接ID派生密钥，并重新发送ClientHello
+                    self.key_cid = header.scid.to_vec();
+                    self.crypto.clear();
+                },
+                PacketType::VersionNegotiation => {},
+                _ => {
+                    self.server_scid.get_or_insert_with(|| header.scid.to_vec());
+                },
+            }
+            return;
+        }
+        if header.packet_type == PacketType::VersionNegotiation {
+            return;
+        }
+        self.version = Some(header.version);
+        if header.packet_type != PacketType::Initial || self.client_hello.is_some() {
+            return;
+        }
+        let Some(frames) = client_initial_keys(header.version, &self.key_cid)
+            .and_then(|keys| decrypt_initial(packet, header, &keys))
+        else {
+            self.undecryptable += 1;
+            return;
+        };
+        for (offset, data) in crypto_frames(&frames) {
+            if offset + (data.len() as u64) <= MAX_CRYPTO_BYTES {
+                self.crypto.push((offset, data.to_vec()));
+            }
+        }
+        self.client_hello = parse_client_hello(&self.crypto_prefix());
+    }
+
+    /// 从偏移0开始连续的CRYPTO数据 (重复和重叠的部分只保留一份)
+    fn crypto_prefix(&mut self) -> Vec<u8> {
+        self.crypto.sort_by_key(|(offset, _)| *offset);
+        let mut prefix: Vec<u8> = Vec::new();
+        for (offset, data) in &self.crypto {
+            let start = *offset as usize;
+            if start > prefix.len() {
+                break;
+            }
+            if start + data.len() > prefix.len() {
+                prefix.extend_from_slice(&data[prefix.len() - start..]);
+            }
+        }
+        prefix
+    }
+
+    /// 是否看到了客户端的Initial包
+    pub fn is_quic(&self) -> bool {
+        self.client.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::pcap_payload_filter::parse_hex_bytes;
+
+    fn hex(text: &str) -> Vec<u8> {
+        parse_hex_bytes(text).unwrap()
+    }
+
+    #[test]
+    fn client_initial_keys_match_rfc_9001() {
+        // RFC 9001 附录A.1，包头保护的掩码见附录A.2
+        let keys = client_initial_keys(QUIC_V1, &hex("8394c8f03e515708")).unwrap();
+        assert_eq!(keys.key.to_vec(), hex("1f369613dd76d5467730efcbe3b1a22d"));
+        assert_eq!(keys.iv.to_vec(), hex("fa044b2f42a3fd3b46fb255c"));
+        let sample = hex("d1b1c98dd7689fb8ec11d242b123dc9b").try_into().unwrap();
+        assert_eq!(keys.header_protection.encrypt(&sample)[..5], hex("437b9aec36")[..]);
+    }
+
+    #[test]
+    fn client_initial_keys_match_rfc_9369() {
+        let keys = client_initial_keys(QUIC_V2, &hex("8394c8f03e515708")).unwrap();
+        assert_eq!(keys.key.to_vec(), hex("8b1a0bc121284290a29e0971b5cd045d"));
+        assert_eq!(keys.iv.to_vec(), hex("91f73e2351d8fa91660e909f"));
+        let expected_hp = Aes128::new(&hex("45b95e15235d6f45a6b19cbcb0294ba9").try_into().unwrap());
+        let sample = [0x5a; 16];
+        assert_eq!(keys.header_protection.encrypt(&sample), expected_hp.encrypt(&sample));
+    }
+
+    #[test]
+    fn unsupported_version_has_no_keys() {
+        assert!(client_initial_keys(0xff00_001d, &hex("8394c8f03e515708")).is_none());
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use pcap_file::Packet;
+use pcap_file::pcap_header::PcapHeader;
+use anyhow::{Result, anyhow};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_filter::Filter;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_edit::FieldEdit;
+use crate::modules::pcap_packet_extractor::print_packet;
+use crate::modules::pcap_packet_parser::{Transport, parse_packet};
+use crate::modules::pcap_report::{Table, format_timestamp_us, print_fields, print_heading};
+use crate::modules::pcap_time_anchor::{ticks_per_second, timestamp_to_us};
+use crate::modules::pcap_verify::OutputDigest;
+
+const HELP: &str = "\
+可用命令:
+  stats                 显示当前选择的包数、字节数、时间范围和协议分布
+  list [N]              列出当前选择的前N个包 (默认20)
+  show <序号>           显示数据包详情 (序号为原始文件中的序号)
+  filter [表达式]       按过滤表达式选择包，不带表达式时恢复为全部包
+  edit <序号> <字段=值>...  修改内存中的数据包字段 (同步更新校验和)
+  save <文件>           把当前选择的包保存为PCAP文件
+  help                  显示本帮助
+  quit                  退出";
+
+/// 一次会话中保存在内存中的抓包
+struct Session {
+    header: PcapHeader,
+    linktype: u32,
+    packets: Vec<Packet<'static>>,
+    /// 当前选择的包 (下标)，None表示全部
+    selection: Option<Vec<usize>>,
+    /// 是否有未保存的修改
+    modified: bool,
+}
+
+/// 交互式地查看、过滤和修改抓包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+///
+/// # 功能
+/// 1. 启动时把整个文件读入内存，之后的命令都不再读取文件
+/// 2. `filter` 的结果作为后续 `stats`、`list`、`save` 的范围
+/// 3. 单个命令出错只打印错误，不退出会话
+pub fn pcap_repl(input_path: &str) -> Result<()> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let header = pcap_reader.header;
+    let mut packets = Vec::new();
+    for packet in pcap_reader.by_ref() {
+        packets.push(packet.into_owned());
+    }
+    pcap_reader.check_complete()?;
+
+    let mut session = Session {
+        header,
+        linktype: header.datalink.into(),
+        packets,
+        selection: None,
+        modified: false,
+    };
+    println!("已载入 {}: {} 个包，输入 help 查看命令", input_path, session.packets.len());
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("pcap> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            println!();
+            break;
+        };
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+        let result = match command {
+            "quit" | "exit" => {
+                if session.modified {
+                    println!("注意: 有未保存的修改");
+                }
+                break;
+            }
+            "help" => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            "stats" => {
+                session.print_stats();
+                Ok(())
+            }
+            "list" => session.list(args),
+            "show" => session.show(args),
+            "filter" => session.filter(args),
+            "edit" => session.edit(args),
+            "save" => session.save(args),
+            _ => Err(anyhow!("未知的命令: {} (输入 help 查看命令)", command)),
+        };
+        if let Err(e) = result {
+            println!("错误: {:#}", e);
+        }
+    }
+
+    Ok(())
+}
+
+impl Session {
+    /// 当前选择的包的下标
+    fn selected(&self) -> Vec<usize> {
+        match &self.selection {
+            Some(selection) => selection.clone(),
+            None => (0..self.packets.len()).collect(),
+        }
+    }
+
+    fn packet_index(&self, text: &str) -> Result<usize> {
+        let number: usize = text.parse().map_err(|_| anyhow!("无效的数据包序号: {}", text))?;
+        if number == 0 || number > self.packets.len() {
+            anyhow::bail!("数据包序号超出范围: {} (共 {} 个)", number, self.packets.len());
+        }
+        Ok(number - 1)
+    }
+
+    fn print_stats(&self) {
+        let selected = self.selected();
+        let mut bytes = 0u64;
+        let (mut tcp, mut udp, mut icmp, mut other_ip, mut non_ip) = (0, 0, 0, 0, 0);
+        let mut flows = HashSet::new();
+        for &i in &selected {
+            let packet = &self.packets[i];
+            bytes += packet.header.orig_len as u64;
+            let parsed = parse_packet(&packet.data, self.linktype);
+            match (&parsed.ip, parsed.transport) {
+                (None, _) => non_ip += 1,
+                (Some(_), Some(Transport::Tcp(_))) => tcp += 1,
+                (Some(_), Some(Transport::Udp(_))) => udp += 1,
+                (Some(_), Some(Transport::Icmp { .. })) => icmp += 1,
+                (Some(_), None) => other_ip += 1,
+            }
+            if let Some(key) = parsed.flow_key() {
+                flows.insert(key.canonical());
+            }
+        }
+
+        print_heading(&format!("统计 (选择 {} / 共 {} 个包):", selected.len(), self.packets.len()));
+        let mut fields = vec![("总字节数", bytes.to_string(), None)];
+        let timestamps = selected.first().zip(selected.last()).map(|(&first, &last)| (self.time_us(first), self.time_us(last)));
+        if let Some((first, last)) = timestamps {
+            let start_us = self.start_us();
+            fields.push((
+                "时间范围",
+                format!(
+                    "{} - {} (持续 {:.6} 秒)",
+                    format_timestamp_us(first, start_us),
+                    format_timestamp_us(last, start_us),
+                    (last - first) as f64 / 1_000_000.0
+                ),
+                None,
+            ));
+        }
+        fields.push(("流数", flows.len().to_string(), None));
+        print_fields(&fields);
+
+        let mut table = Table::new(&["协议", "包数"]).right_align(&[1]);
+        for (name, count) in [("TCP", tcp), ("UDP", udp), ("ICMP", icmp), ("其他IP", other_ip), ("非IP", non_ip)] {
+            table.add_row(vec![name.to_string(), count.to_string()]);
+        }
+        table.print();
+    }
+
+    fn list(&self, args: &str) -> Result<()> {
+        let limit = if args.is_empty() {
+            20
+        } else {
+            args.parse().map_err(|_| anyhow!("无效的数量: {}", args))?
+        };
+        let selected = self.selected();
+        for &i in selected.iter().take(limit) {
+            let packet = &self.packets[i];
+            let flow = match parse_packet(&packet.data, self.linktype).flow_key() {
+                Some(key) => key.to_string(),
+                None => "(非IP包)".to_string(),
+            };
+            println!(
+                "{:>8}  {}  {:>6}  {}",
+                i + 1,
+                format_timestamp_us(self.time_us(i), self.start_us()),
+                packet.header.orig_len,
+                flow
+            );
+        }
+        if selected.len() > limit {
+            println!("... 还有 {} 个包", selected.len() - limit);
+        }
+        Ok(())
+    }
+
+    fn show(&self, args: &str) -> Result<()> {
+        let index = self.packet_index(args)?;
+        print_packet(index + 1, &self.packets[index], self.linktype, ticks_per_second(&self.header), self.start_us(), &[]);
+        Ok(())
+    }
+
+    /// 第i个包的时间 (微秒)
+    fn time_us(&self, i: usize) -> i64 {
+        let header = &self.packets[i].header;
+        timestamp_to_us(header.ts_sec, header.ts_usec, ticks_per_second(&self.header))
+    }
+
+    /// 第一个包的时间 (相对时间格式的起点)
+    fn start_us(&self) -> i64 {
+        if self.packets.is_empty() { 0 } else { self.time_us(0) }
+    }
+
+    fn filter(&mut self, args: &str) -> Result<()> {
+        if args.is_empty() {
+            self.selection = None;
+            println!("已选择全部 {} 个包", self.packets.len());
+            return Ok(());
+        }
+        let filter = Filter::parse(args)?;
+        let selection: Vec<usize> = self.packets
+            .iter()
+            .enumerate()
+            .filter(|(_, packet)| filter.matches(&packet.data, self.linktype, &parse_packet(&packet.data, self.linktype)))
+            .map(|(i, _)| i)
+            .collect();
+        println!("选择了 {} / {} 个包", selection.len(), self.packets.len());
+        self.selection = Some(selection);
+        Ok(())
+    }
+
+    fn edit(&mut self, args: &str) -> Result<()> {
+        let mut parts = args.split_whitespace();
+        let index = self.packet_index(parts.next().ok_or_else(|| anyhow!("用法: edit <序号> <字段=值>..."))?)?;
+        let edits = parts.map(FieldEdit::parse).collect::<Result<Vec<_>, String>>().map_err(|e| anyhow!(e))?;
+        if edits.is_empty() {
+            anyhow::bail!("用法: edit <序号> <字段=值>...");
+        }
+
+        // 全部赋值成功才修改
+        let mut data = self.packets[index].data.to_vec();
+        for edit in &edits {
+            let parsed = parse_packet(&data, self.linktype);
+            if !edit.apply(&mut data, self.linktype, &parsed) {
+                anyhow::bail!("数据包#{}中没有可修改的字段 {}", index + 1, edit.field_name());
+            }
+        }
+        self.packets[index].data = data.into();
+        self.modified = true;
+        println!("已修改数据包 #{}", index + 1);
+        Ok(())
+    }
+
+    fn save(&mut self, args: &str) -> Result<()> {
+        if args.is_empty() {
+            anyhow::bail!("用法: save <文件>");
+        }
+        let out_file = create_output(args, compression())?;
+        let mut pcap_writer = PacketWriter::with_header(self.header, out_file, args)
+            .map_err(|e| anyhow!("创建PCAP写入器失败: {}", e))?;
+        let mut digest = OutputDigest::default();
+        let selected = self.selected();
+        for &i in &selected {
+            pcap_writer.write_packet(&self.packets[i])
+                .map_err(|e| anyhow!("写入包#{}失败: {}", i + 1, e))?;
+            digest.record_pcap(&self.packets[i]);
+        }
+        pcap_writer.into_inner().finish()?;
+        digest.verify(args)?;
+        self.modified = false;
+        println!("已保存 {} 个包到 {}", selected.len(), args);
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, Ordering};
+use chrono::{DateTime, FixedOffset, Local};
+
+/// 是否输出ANSI颜色
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(false);
+/// 当前的输出详细程度 (`Verbosity` 的取值)
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+/// 报告中时间戳的格式 (`TimeFormat` 的取值)
+static TIME_FORMAT: AtomicU8 = AtomicU8::new(TimeFormat::Epoch as u8);
+/// ISO格式使用的时区相对UTC的秒数，`LOCAL_TIME_ZONE` 表示本地时区
+static TIME_ZONE: AtomicI32 = AtomicI32::new(LOCAL_TIME_ZONE);
+
+const LOCAL_TIME_ZONE: i32 = i32::MIN;
+
+/// 终端宽度未知时使用的宽度
+const DEFAULT_WIDTH: usize = 100;
+
+/// 设置是否输出颜色: 指定 `--no-color`、设置了 `NO_COLOR` 环境变量或标准输出不是终端时关闭
+pub fn init_color(no_color: bool) {
+    let enabled = !no_color
+        && std::env::var_os("NO_COLOR").is_none()
+        // SAFETY: isatty只读取文件描述符的状态
+        && unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1;
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn color_enabled() -> bool {
+    COLOR_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 输出详细程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// 只输出结果: 不输出报告标题、分隔空行和进度日志
+    Quiet,
+    Normal,
+    /// 额外输出每个步骤的详细信息
+    Verbose,
+}
+
+/// 设置输出详细程度 (`--quiet`、`--verbose`)
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+fn quiet() -> bool {
+    verbosity() == Verbosity::Quiet
+}
+
+/// 报告和导出文件中时间戳的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TimeFormat {
+    /// Unix时间 (秒，带小数)
+    #[default]
+    Epoch,
+    /// ISO 8601日期时间，带时区偏移 (按 `--tz`)
+    Iso,
+    /// 相对抓包中第一个包的秒数
+    Relative,
+}
+
+/// ISO时间格式使用的时区
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeZone {
+    Local,
+    /// 相对UTC的固定偏移 (秒)
+    Fixed(i32),
+}
+
+/// 设置时间戳格式和ISO格式使用的时区 (`--time-format`、`--tz`)
+pub fn set_time_format(format: TimeFormat, zone: TimeZone) {
+    TIME_FORMAT.store(format as u8, Ordering::Relaxed);
+    let offset = match zone {
+        TimeZone::Local => LOCAL_TIME_ZONE,
+        TimeZone::Fixed(offset) => offset,
+    };
+    TIME_ZONE.store(offset, Ordering::Relaxed);
+}
+
+pub fn time_format() -> TimeFormat {
+    match TIME_FORMAT.load(Ordering::Relaxed) {
+        0 => TimeFormat::Epoch,
+        1 => TimeFormat::Iso,
+        _ => TimeFormat::Relative,
+    }
+}
+
+/// `--tz` 指定的时区 (解析不带时区的时间时使用)
+pub fn time_zone() -> TimeZone {
+    match TIME_ZONE.load(Ordering::Relaxed) {
+        LOCAL_TIME_ZONE => TimeZone::Local,
+        offset => TimeZone::Fixed(offset),
+    }
+}
+
+/// 解析时区: `local`、`utc` (或 `Z`)，或相对UTC的偏移 `+08:00`、`-0530`、`+8`
+pub fn parse_time_zone(text: &str) -> Result<TimeZone, String> {
+    let text = text.trim();
+    match text.to_ascii_lowercase().as_str() {
+        "local" => return Ok(TimeZone::Local),
+        "utc" | "z" | "gmt" => return Ok(TimeZone::Fixed(0)),
+        _ => {}
+    }
+    let error = || format!("无效的时区: {} (可用: local、utc 或 +08:00 形式的偏移)", text);
+    let (sign, rest) = match text.strip_prefix('+') {
+        Some(rest) => (1, rest),
+        None => (-1, text.strip_prefix('-').ok_or_else(error)?),
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if rest.len() > 2 => rest.split_at(rest.len() - 2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().map_err(|_| error())?;
+    let minutes: i32 = minutes.parse().map_err(|_| error())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(error());
+    }
+    Ok(TimeZone::Fixed(sign * (hours * 3600 + minutes * 60)))
+}
+
+/// 按 `--time-format` 格式化微秒时间戳，`start_us` 为抓包中第一个包的时间 (相对格式的起点)
+pub fn format_timestamp_us(time_us: i64, start_us: i64) -> String {
+    format_timestamp(time_us as i128 * 1000, start_us as i128 * 1000, 6)
+}
+
+/// 按 `--time-format` 格式化纳秒时间戳，`start_ns` 为抓包中第一个包的时间 (相对格式的起点)
+pub fn format_timestamp_ns(time_ns: i128, start_ns: i128) -> String {
+    format_timestamp(time_ns, start_ns, 9)
+}
+
+/// 保留 `digits` 位小数 (6或9)
+fn format_timestamp(time_ns: i128, start_ns: i128, digits: usize) -> String {
+    let divisor = 10i128.pow(9 - digits as u32);
+    let seconds = |ns: i128| format!("{}.{:0digits$}", ns.div_euclid(1_000_000_000), ns.rem_euclid(1_000_000_000) / divisor);
+    match time_format() {
+        TimeFormat::Epoch => seconds(time_ns),
+        TimeFormat::Relative => {
+            let elapsed = time_ns - start_ns;
+            if elapsed < 0 { format!("-{}", seconds(-elapsed)) } else { seconds(elapsed) }
+        }
+        TimeFormat::Iso => {
+            let Some(utc) = i64::try_from(time_ns.div_euclid(1_000_000_000))
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs, time_ns.rem_euclid(1_000_000_000) as u32))
+            else {
+                return seconds(time_ns);
+            };
+            let pattern = if digits == 9 { "%Y-%m-%dT%H:%M:%S%.9f%:z" } else { "%Y-%m-%dT%H:%M:%S%.6f%:z" };
+            match TIME_ZONE.load(Ordering::Relaxed) {
+                LOCAL_TIME_ZONE => utc.with_timezone(&Local).format(pattern).to_string(),
+                offset => match FixedOffset::east_opt(offset) {
+                    Some(zone) => utc.with_timezone(&zone).format(pattern).to_string(),
+                    None => utc.format(pattern).to_string(),
+                },
+            }
+        }
+    }
+}
+
+/// 终端宽度 (列数)，依次取终端窗口大小、`COLUMNS` 环境变量
+pub fn terminal_width() -> usize {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    // SAFETY: TIOCGWINSZ只写入传入的winsize结构
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+        return size.ws_col as usize;
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// 结论的严重程度，决定显示颜色和标记
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn color_code(self) -> &'static str {
+        match self {
+            Severity::Ok => "32",
+            Severity::Warning => "33",
+            Severity::Error => "31",
+        }
+    }
+
+    fn marker(self) -> &'static str {
+        match self {
+            Severity::Ok => "✅",
+            Severity::Warning => "⚠️",
+            Severity::Error => "❌",
+        }
+    }
+}
+
+/// 按严重程度着色
+pub fn paint(severity: Severity, text: &str) -> String {
+    if color_enabled() {
+        format!("\x1b[{}m{}\x1b[0m", severity.color_code(), text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// 报告标题 (加粗)，安静模式下不输出
+pub fn print_heading(text: &str) {
+    if quiet() {
+        return;
+    }
+    if color_enabled() {
+        println!("\x1b[1m{}\x1b[0m", text);
+    } else {
+        println!("{}", text);
+    }
+}
+
+/// 报告中的小节标题 (前面空一行)，安静模式下不输出
+pub fn print_section(title: &str) {
+    if !quiet() {
+        println!("\n{}", title);
+    }
+}
+
+/// 结论之前的空行，安静模式下不输出
+pub fn print_separator() {
+    if !quiet() {
+        println!();
+    }
+}
+
+/// 打印一条带标记和颜色的结论
+pub fn print_verdict(severity: Severity, text: &str) {
+    println!("{} {}", severity.marker(), paint(severity, text));
+}
+
+/// 打印对齐的 `名称: 值` 列表，`severity` 不为None的行着色
+pub fn print_fields(fields: &[(&str, String, Option<Severity>)]) {
+    let width = fields.iter().map(|(name, _, _)| display_width(name)).max().unwrap_or(0);
+    for (name, value, severity) in fields {
+        let value = match severity {
+            Some(severity) => paint(*severity, value),
+            None => value.clone(),
+        };
+        println!("- {}{}: {}", name, " ".repeat(width - display_width(name)), value);
+    }
+}
+
+/// 字符串在终端中占的列数 (中日韩文字和表情符号占两列)
+pub fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115f | 0x2e80..=0xa4cf | 0xac00..=0xd7a3 | 0xf900..=0xfaff | 0xfe30..=0xfe4f
+            | 0xff00..=0xff60 | 0xffe0..=0xffe6 | 0x1f300..=0x1faff | 0x20000..=0x3fffd => 2,
+            // 组合字符和变体选择符不占宽度
+            0x0300..=0x036f | 0x200b..=0x200f | 0xfe00..=0xfe0f => 0,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// 按终端列数截断，被截断时末尾为 `…`
+fn truncate_to_width(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+    let mut result = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = display_width(c.encode_utf8(&mut [0; 4]));
+        if used + w + 1 > width {
+            break;
+        }
+        result.push(c);
+        used += w;
+    }
+    result.push('…');
+    result
+}
+
+/// 列对齐的表格
+///
+/// 数字列右对齐，其余左对齐；总宽度超过终端宽度时截断最后一列。
+pub struct Table {
+    headers: Vec<String>,
+    right_aligned: Vec<bool>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Table {
+        Table {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            right_aligned: vec![false; headers.len()],
+            rows: Vec::new(),
+        }
+    }
+
+    /// 指定右对齐的列 (从0开始)
+    pub fn right_align(mut self, columns: &[usize]) -> Table {
+        for &column in columns {
+            self.right_aligned[column] = true;
+        }
+        self
+    }
+
+    pub fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    pub fn print(&self) {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| display_width(h)).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(display_width(cell));
+            }
+        }
+
+        // 缩进2列，列之间隔2列
+        let last = widths.len().saturating_sub(1);
+        let fixed: usize = 2 + widths[..last].iter().map(|w| w + 2).sum::<usize>();
+        let last_width = terminal_width().saturating_sub(fixed).max(8);
+
+        let format_row = |row: &[String], bold: bool| {
+            let mut line = String::from("  ");
+            for (i, cell) in row.iter().enumerate() {
+                let cell = if i == last { truncate_to_width(cell, last_width) } else { cell.clone() };
+                let padding = " ".repeat(widths[i].saturating_sub(display_width(&cell)));
+                if self.right_aligned[i] {
+                    line.push_str(&padding);
+                    line.push_str(&cell);
+                } else {
+                    line.push_str(&cell);
+                    // 最后一列左对齐时不补尾部空格
+                    if i != last {
+                        line.push_str(&padding);
+                    }
+                }
+                if i != last {
+                    line.push_str("  ");
+                }
+            }
+            if bold && color_enabled() {
+                format!("\x1b[1m{}\x1b[0m", line)
+            } else {
+                line
+            }
+        };
+
+        println!("{}", format_row(&self.headers, true));
+        for row in &self.rows {
+            println!("{}", format_row(row, false));
+        }
+    }
+}
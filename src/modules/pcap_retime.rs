@@ -0,0 +1,299 @@
+use std::fs::{self};
+use std::path::Path;
+use anyhow::{Context, Result, anyhow};
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_time_anchor::{capture_bounds, split_timestamp_ticks, ticks_per_second, timestamp_ticks};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 按外部时间戳文件重新设置每个包的时间
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `times_path`: 时间戳文件，每行一个时间戳 (秒，可带小数)，或CSV中的某一列
+/// - `column`: CSV中时间戳所在的列 (从1开始)
+/// - `relative`: 时间戳为相对首包原始时间的偏移
+///
+/// # 功能
+/// 1. 第i个包使用文件中的第i个时间戳
+/// 2. 时间戳数量必须与包数一致，且不能递减
+/// 3. 空行和 `#` 开头的行忽略，CSV表头自动跳过
+/// 4. 纳秒精度的PCAP文件按纳秒解析时间戳，输出保持纳秒精度
+pub fn pcap_retime_from_file(
+    input_path: &str,
+    output_path: &str,
+    times_path: &str,
+    column: usize,
+    relative: bool,
+) -> Result<()> {
+    if column == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("列号从1开始".to_string()));
+    }
+    // 先核对包数
+    let mut counter = PacketReader::open(input_path)?;
+    let ticks_per_second = ticks_per_second(&counter.header);
+    let times = load_times(times_path, column, ticks_per_second)?;
+    let packet_count = counter.by_ref().count();
+    counter.check_complete()?;
+    if packet_count != times.len() {
+        anyhow::bail!(PcapError::InvalidArgument(format!(
+                "时间戳数量与包数不一致: 时间戳 {} 个, 数据包 {} 个",
+                times.len(),
+                packet_count
+        )));
+    }
+    if packet_count == 0 {
+        anyhow::bail!(PcapError::EmptyCapture);
+    }
+
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut base = None;
+    for (packet, time) in pcap_reader.by_ref().zip(&times) {
+        let mut packet = packet;
+        let base = *base.get_or_insert_with(|| {
+            if relative {
+                timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second)
+            } else {
+                0
+            }
+        });
+        let (new_sec, new_usec) = split_timestamp_ticks(base + time, ticks_per_second)?;
+        packet.header.ts_sec = new_sec;
+        packet.header.ts_usec = new_usec;
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功按时间戳文件重新计时: 包数={}, 时间戳文件={}, 输出文件={}",
+        packet_count, times_path, output_path
+    );
+
+    Ok(())
+}
+
+/// 读取时间戳文件，返回以 `ticks_per_second` 为精度的时间戳
+fn load_times(times_path: &str, column: usize, ticks_per_second: i64) -> Result<Vec<i64>> {
+    let content = fs::read_to_string(Path::new(times_path))
+        .with_context(|| format!("无法读取时间戳文件: {}", times_path))?;
+
+    let mut times: Vec<i64> = Vec::new();
+    let mut first_line = true;
+    for (line_no, raw_line) in content.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let is_first = std::mem::replace(&mut first_line, false);
+        let field = line
+            .split(',')
+            .nth(column - 1)
+            .ok_or_else(|| anyhow!("时间戳文件第{}行没有第{}列: {}", line_no + 1, column, raw_line))?
+            .trim();
+
+        let time = match parse_seconds(field, ticks_per_second) {
+            Some(time) => time,
+            // 第一行不是数字时视为CSV表头
+            None if is_first => continue,
+            None => anyhow::bail!("时间戳文件第{}行格式错误: {}", line_no + 1, raw_line),
+        };
+        if let Some(&prev) = times.last()
+            && time < prev
+        {
+            anyhow::bail!(
+                "时间戳文件第{}行的时间戳递减: {} < 上一个时间戳",
+                line_no + 1,
+                field
+            );
+        }
+        times.push(time);
+    }
+
+    Ok(times)
+}
+
+/// 解析十进制秒数为 `ticks_per_second` (10^6或10^9) 精度的单位数，不经过浮点以免丢失精度 (多出的小数位四舍五入)
+fn parse_seconds(text: &str, ticks_per_second: i64) -> Option<i64> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole.bytes().all(|b| b.is_ascii_digit()) || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let seconds: i64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let digits = if ticks_per_second == 1_000_000 { 6 } else { 9 };
+    let mut ticks: i64 = 0;
+    for (i, digit) in fraction.bytes().take(digits + 1).enumerate() {
+        let digit = (digit - b'0') as i64;
+        match i {
+            _ if i < digits => ticks = ticks * 10 + digit,
+            _ if digit >= 5 => ticks += 1,
+            _ => {}
+        }
+    }
+    for _ in fraction.len()..digits {
+        ticks *= 10;
+    }
+
+    let total = seconds.checked_mul(ticks_per_second)?.checked_add(ticks)?;
+    Some(if negative { -total } else { total })
+}
+
+/// 把每个包的时间戳舍入到固定间隔的整数倍
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `grid_us`: 间隔 (微秒)
+///
+/// # 功能
+/// 1. 舍入到最近的网格点，恰好在两个网格点中间时取较晚的一个
+/// 2. 舍入是单调的，包的先后顺序不变；落到同一网格点的包保持文件中的顺序
+/// 3. 用于比较前统一时钟精度不同的设备的抓包 (例如一端为微秒、另一端为毫秒)
+/// 4. 纳秒精度的PCAP文件按纳秒计算，输出保持纳秒精度
+pub fn pcap_quantize(input_path: &str, output_path: &str, grid_us: u64) -> Result<()> {
+    if grid_us == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("--quantize 的间隔必须大于0".to_string()));
+    }
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let header = pcap_reader.header;
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+    // 网格和时间戳都以文件的精度计 (微秒或纳秒)
+    let ticks_per_second = ticks_per_second(&header);
+    let ticks_per_us = ticks_per_second / 1_000_000;
+    let grid = (grid_us as i64).saturating_mul(ticks_per_us);
+
+    let mut packet_count = 0;
+    let mut changed = 0;
+    let mut max_adjustment = 0;
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        packet_count += 1;
+
+        let original = timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second);
+        let quantized = (original + grid / 2).div_euclid(grid) * grid;
+        if quantized != original {
+            changed += 1;
+            max_adjustment = max_adjustment.max((quantized - original).abs());
+        }
+        let (new_sec, new_usec) = split_timestamp_ticks(quantized, ticks_per_second)?;
+        packet.header.ts_sec = new_sec;
+        packet.header.ts_usec = new_usec;
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功量化时间戳: 包数={}, 间隔={}微秒, 调整的包数={}, 最大调整量={:.3}微秒, 输出文件={}",
+        packet_count, grid_us, changed, max_adjustment as f64 / ticks_per_us as f64, output_path
+    );
+
+    Ok(())
+}
+
+/// 线性修正抓包时钟的漂移
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `ppm`: 抓包时钟相对参考时钟快了多少ppm (负数表示偏慢)
+/// - `offsets`: 首包和末包处抓包时钟领先参考时钟的秒数，与 `ppm` 二选一
+///
+/// # 功能
+/// 1. `ppm` 模式以首包为基准，经过的时间按 1/(1+ppm×10⁻⁶) 缩放
+/// 2. 偏移模式在首末包之间线性插值修正量，首包减去起始偏移，末包减去结束偏移
+/// 3. 纳秒精度的PCAP文件按纳秒计算，输出保持纳秒精度
+pub fn pcap_deskew(
+    input_path: &str,
+    output_path: &str,
+    ppm: Option<f64>,
+    offsets: Option<(f64, f64)>,
+) -> Result<()> {
+    // 时间以文件的精度计 (微秒或纳秒)
+    let (first, last, ticks_per_second) = capture_bounds(input_path)?;
+    let ticks_per_second_f = ticks_per_second as f64;
+
+    // 修正后时间 = 首包时间 + 经过时间 × scale - start_offset
+    let (scale, start_offset) = match (ppm, offsets) {
+        (Some(ppm), None) => {
+            if ppm <= -1_000_000.0 {
+                anyhow::bail!(PcapError::InvalidArgument(format!("ppm必须大于-1000000，当前为: {}", ppm)));
+            }
+            (1.0 / (1.0 + ppm * 1e-6), 0.0)
+        }
+        (None, Some((start_offset, end_offset))) => {
+            let span = (last - first) as f64;
+            let drift = (end_offset - start_offset) * ticks_per_second_f;
+            if span <= 0.0 && drift != 0.0 {
+                anyhow::bail!("首末包时间相同，无法按偏移修正漂移");
+            }
+            let scale = if span > 0.0 { (span - drift) / span } else { 1.0 };
+            if scale <= 0.0 {
+                anyhow::bail!("偏移之差超过了抓包时长，修正后时间会倒流");
+            }
+            (scale, start_offset * ticks_per_second_f)
+        }
+        _ => anyhow::bail!(PcapError::InvalidArgument("必须且只能指定 --ppm 或 --start-offset/--end-offset 之一".to_string())),
+    };
+
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut packet_count = 0;
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        packet_count += 1;
+
+        let elapsed = timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second) - first;
+        let corrected = first + (elapsed as f64 * scale - start_offset).round() as i64;
+        let (new_sec, new_usec) = split_timestamp_ticks(corrected, ticks_per_second)?;
+        packet.header.ts_sec = new_sec;
+        packet.header.ts_usec = new_usec;
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    let total_correction = ((last - first) as f64 * (1.0 - scale) + start_offset) / ticks_per_second_f;
+    info!(
+        "成功修正时钟漂移: 包数={}, 末包修正量={:.6}秒, 输出文件={}",
+        packet_count, total_correction, output_path
+    );
+
+    Ok(())
+}
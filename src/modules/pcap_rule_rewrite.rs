@@ -0,0 +1,319 @@
+use std::fs::{self};
+use std::path::Path;
+use anyhow::{Context, Result, anyhow};
+use log::{info, warn};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_filter::Filter;
+use crate::modules::pcap_ip_normalize::IpIdRenumberer;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_tcp_normalize::{SeqNormalizer, TcpOptionStrip, strip_tcp_options};
+use crate::modules::pcap_packet_edit::{EditField, FieldEdit};
+use crate::modules::pcap_packet_parser::parse_packet;
+use crate::modules::pcap_padding::{PaddingAction, pad_frame, strip_padding};
+use crate::modules::pcap_subnet_map::{SubnetMapping, map_subnets};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 规则文件中可赋值的列及对应的字段 (端口列同时适用于TCP和UDP)
+const ASSIGNMENT_COLUMNS: [(&str, &[EditField]); 8] = [
+    ("src_ip", &[EditField::IpSrc]),
+    ("dst_ip", &[EditField::IpDst]),
+    ("src_port", &[EditField::TcpSrcPort, EditField::UdpSrcPort]),
+    ("dst_port", &[EditField::TcpDstPort, EditField::UdpDstPort]),
+    ("ttl", &[EditField::IpTtl]),
+    ("vlan", &[EditField::VlanId]),
+    ("src_mac", &[EditField::EthSrc]),
+    ("dst_mac", &[EditField::EthDst]),
+];
+
+/// 规则之外的改写选项
+#[derive(Debug, Clone, Default)]
+pub struct RewriteOptions {
+    /// 网段映射 (替换网络前缀，保留主机位)
+    pub subnet_maps: Vec<SubnetMapping>,
+    /// 删除TCP选项
+    pub strip_tcp_options: Option<TcpOptionStrip>,
+    /// 把每个TCP流的序列号平移到该起始值
+    pub tcp_isn: Option<u32>,
+    /// 把IPv4标识改写为每个流递增的序列
+    pub renumber_ip_id: bool,
+    /// 删除或补齐以太网填充
+    pub padding: Option<PaddingAction>,
+}
+
+/// 一条改写规则
+struct RewriteRule {
+    /// 规则所在行号
+    line: usize,
+    /// 匹配条件，为空表示所有包
+    filter: Option<Filter>,
+    /// 每一列的赋值，包中有其中任一字段即可
+    assignments: Vec<Vec<FieldEdit>>,
+    matched: usize,
+}
+
+/// 按CSV规则文件批量改写数据包字段，并可做TCP选项等规范化
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `rules_path`: 规则文件，首行为列名，`match` 列为过滤表达式，其余列为要赋值的字段
+/// - `options`: 规则之外的改写选项
+///
+/// # 功能
+/// 1. 所有规则都按原始包判断是否匹配，匹配的规则按文件中的顺序依次赋值
+///    (因此互换地址的规则不会相互影响)
+/// 2. 单元格为空表示不修改该字段
+/// 3. 修改后增量更新IP、TCP、UDP校验和
+/// 4. 规则之后再执行 `options` 中的改写；网段映射最先执行，每个地址按前缀最长的网段替换一次，删除TCP选项会缩短包长并重新计算TCP校验和，
+///    序列号平移和IP标识重新编号按流的每个方向分别进行
+/// 5. 填充最后处理: 删除IP总长度之后的字节，或把完整抓到的短帧补零到60字节，原始长度同步修改
+pub fn pcap_rewrite(
+    input_path: &str,
+    output_path: &str,
+    rules_path: Option<&str>,
+    options: &RewriteOptions,
+) -> Result<()> {
+    let mut rules = match rules_path {
+        Some(rules_path) => load_rules(rules_path)?,
+        None => Vec::new(),
+    };
+    if rules.is_empty()
+        && options.subnet_maps.is_empty()
+        && options.strip_tcp_options.is_none()
+        && options.tcp_isn.is_none()
+        && !options.renumber_ip_id
+        && options.padding.is_none()
+    {
+        anyhow::bail!(PcapError::InvalidArgument("需要指定 --rules 或至少一个改写选项".to_string()));
+    }
+
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut packet_count = 0;
+    let mut rewritten = 0;
+    let mut skipped = 0;
+    let mut options_stripped = 0;
+    let mut seq_normalizer = options.tcp_isn.map(SeqNormalizer::new);
+    let mut seq_normalized = 0;
+    let mut ip_id_renumberer = options.renumber_ip_id.then(IpIdRenumberer::default);
+    let mut ip_id_renumbered = 0;
+    let mut padding_changed = 0;
+    let mut padding_bytes = 0;
+    // 每个网段映射替换的地址数
+    let mut subnet_mapped = vec![0u64; options.subnet_maps.len()];
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        packet_count += 1;
+
+        let parsed = parse_packet(&packet.data, linktype);
+        let matching: Vec<usize> = rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.filter.as_ref().is_none_or(|f| f.matches(&packet.data, linktype, &parsed)))
+            .map(|(i, _)| i)
+            .collect();
+        let mut modified = !matching.is_empty();
+
+        let data = packet.data.to_mut();
+        for i in matching {
+            let rule = &mut rules[i];
+            rule.matched += 1;
+            for assignment in &rule.assignments {
+                let parsed = parse_packet(data, linktype);
+                if !assignment.iter().any(|edit| edit.apply(data, linktype, &parsed)) {
+                    skipped += 1;
+                }
+            }
+        }
+
+        if !options.subnet_maps.is_empty() {
+            let parsed = parse_packet(data, linktype);
+            let (src, dst) = map_subnets(&options.subnet_maps, data, &parsed);
+            for i in [src, dst].into_iter().flatten() {
+                subnet_mapped[i] += 1;
+                modified = true;
+            }
+        }
+        if let Some(strip) = &options.strip_tcp_options {
+            let parsed = parse_packet(data, linktype);
+            if let Some(removed) = strip_tcp_options(data, &parsed, strip)
+                && removed > 0
+            {
+                packet.header.incl_len -= removed as u32;
+                packet.header.orig_len = packet.header.orig_len.saturating_sub(removed as u32);
+                options_stripped += 1;
+                modified = true;
+            }
+        }
+        if let Some(normalizer) = seq_normalizer.as_mut() {
+            let parsed = parse_packet(data, linktype);
+            if normalizer.normalize(data, &parsed) {
+                seq_normalized += 1;
+                modified = true;
+            }
+        }
+        if let Some(renumberer) = ip_id_renumberer.as_mut() {
+            let parsed = parse_packet(data, linktype);
+            if renumberer.renumber(data, &parsed) {
+                ip_id_renumbered += 1;
+                modified = true;
+            }
+        }
+        match options.padding {
+            Some(PaddingAction::Strip) => {
+                let parsed = parse_packet(data, linktype);
+                let removed = strip_padding(data, &parsed);
+                if removed > 0 {
+                    packet.header.incl_len -= removed as u32;
+                    packet.header.orig_len = packet.header.orig_len.saturating_sub(removed as u32);
+                    padding_changed += 1;
+                    padding_bytes += removed;
+                    modified = true;
+                }
+            }
+            // 被snaplen截断的帧不知道线路上的长度，不补齐
+            Some(PaddingAction::Pad) if packet.header.incl_len >= packet.header.orig_len => {
+                let added = pad_frame(data, linktype);
+                if added > 0 {
+                    packet.header.incl_len += added as u32;
+                    packet.header.orig_len = packet.header.incl_len;
+                    padding_changed += 1;
+                    padding_bytes += added;
+                    modified = true;
+                }
+            }
+            _ => {}
+        }
+        if modified {
+            rewritten += 1;
+        }
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    for (i, rule) in rules.iter().enumerate() {
+        info!("规则 #{} (第{}行): 匹配 {} 个包", i + 1, rule.line, rule.matched);
+    }
+    if skipped > 0 {
+        warn!("{} 处赋值因包中没有对应字段或地址族不一致而跳过", skipped);
+    }
+    for (mapping, count) in options.subnet_maps.iter().zip(&subnet_mapped) {
+        info!("网段 {}/{} -> {}/{}: 替换 {} 个地址", mapping.from, mapping.prefix, mapping.to, mapping.prefix, count);
+    }
+    if options.strip_tcp_options.is_some() {
+        info!("删除TCP选项的包: {} 个", options_stripped);
+    }
+    if let Some(isn) = options.tcp_isn {
+        info!("序列号平移到起始值 {} 的TCP包: {} 个", isn, seq_normalized);
+    }
+    if options.renumber_ip_id {
+        info!("重新编号IP标识的包: {} 个", ip_id_renumbered);
+    }
+    match options.padding {
+        Some(PaddingAction::Strip) => info!("删除填充的包: {} 个, 共 {} 字节", padding_changed, padding_bytes),
+        Some(PaddingAction::Pad) => info!("补齐填充的包: {} 个, 共 {} 字节", padding_changed, padding_bytes),
+        None => {}
+    }
+    info!(
+        "成功改写: 包数={}, 改写包数={}, 输出文件={}",
+        packet_count, rewritten, output_path
+    );
+
+    Ok(())
+}
+
+fn load_rules(rules_path: &str) -> Result<Vec<RewriteRule>> {
+    let content = fs::read_to_string(Path::new(rules_path))
+        .with_context(|| format!("无法读取规则文件: {}", rules_path))?;
+
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    let (_, header) = lines.next().ok_or_else(|| anyhow!("规则文件为空: {}", rules_path))?;
+    let columns: Vec<String> = split_csv_line(header)?.iter().map(|c| c.trim().to_ascii_lowercase()).collect();
+    for column in &columns {
+        if column != "match" && !ASSIGNMENT_COLUMNS.iter().any(|(name, _)| name == column) {
+            let names: Vec<&str> = ASSIGNMENT_COLUMNS.iter().map(|(name, _)| *name).collect();
+            anyhow::bail!("规则文件中未知的列: {} (可选 match、{})", column, names.join("、"));
+        }
+    }
+
+    let mut rules = Vec::new();
+    for (line_no, line) in lines {
+        let cells = split_csv_line(line).with_context(|| format!("规则文件第{}行格式错误", line_no))?;
+        if cells.len() > columns.len() {
+            anyhow::bail!("规则文件第{}行的列数多于表头", line_no);
+        }
+
+        let mut filter = None;
+        let mut assignments = Vec::new();
+        for (column, cell) in columns.iter().zip(&cells) {
+            let cell = cell.trim();
+            if cell.is_empty() {
+                continue;
+            }
+            let context = || format!("规则文件第{}行 {} 列", line_no, column);
+            if column == "match" {
+                filter = Some(Filter::parse(cell).with_context(context)?);
+            } else {
+                assignments.push(parse_assignment(column, cell).with_context(context)?);
+            }
+        }
+        rules.push(RewriteRule { line: line_no, filter, assignments, matched: 0 });
+    }
+
+    if rules.is_empty() {
+        anyhow::bail!("规则文件中没有规则: {}", rules_path);
+    }
+    Ok(rules)
+}
+
+fn parse_assignment(column: &str, value: &str) -> Result<Vec<FieldEdit>> {
+    let (_, fields) = ASSIGNMENT_COLUMNS
+        .iter()
+        .find(|(name, _)| *name == column)
+        .ok_or_else(|| anyhow!("未知的列: {}", column))?;
+    fields
+        .iter()
+        .map(|&field| FieldEdit::new(field, value).map_err(|e| anyhow!(e)))
+        .collect()
+}
+
+/// 拆分一行CSV，支持双引号包围的单元格 (`""` 表示引号本身)
+pub fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("引号未闭合");
+    }
+    cells.push(cell);
+    Ok(cells)
+}
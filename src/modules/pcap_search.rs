@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::parse_packet;
+use crate::modules::pcap_payload_filter::parse_hex_bytes;
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{Severity, Table, format_timestamp_us, print_fields, print_heading, print_separator, print_verdict};
+
+/// 一个包最多列出的匹配位置
+const MAX_OFFSETS: usize = 8;
+
+/// 搜索选项
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    /// 要查找的字节序列 (十六进制)
+    pub hex: Option<String>,
+    /// 要查找的文本 (按UTF-8字节匹配)
+    pub text: Option<String>,
+    /// 在整个帧中查找 (默认只查找L4载荷)
+    pub whole_frame: bool,
+    /// 每个匹配前后列出的包数
+    pub context: usize,
+    /// 找到这么多个匹配的包后停止
+    pub limit: Option<usize>,
+}
+
+/// 列表中的一行 (匹配的包或上下文)
+struct Row {
+    number: u64,
+    time_us: i64,
+    flow: String,
+    len: u32,
+    /// 匹配位置 (载荷偏移, 帧偏移)，上下文行为空
+    offsets: Vec<(Option<usize>, usize)>,
+    /// 同一个包中的匹配总数
+    match_count: usize,
+}
+
+/// 在抓包中查找字节序列，列出每个包含该序列的包及其位置
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `options`: 搜索选项
+///
+/// # 说明
+/// 1. 默认只在L4载荷 (TCP/UDP头之后，不含帧尾填充) 中查找，`whole_frame` 时在整个帧中查找
+/// 2. 每个包单独查找，不做TCP流重组，跨越报文段的内容找不到
+/// 3. 上下文与 `grep -C` 相同，相邻的匹配共用上下文，不相邻的用 `--` 分隔
+pub fn pcap_search(input_path: &str, options: &SearchOptions) -> Result<()> {
+    let needle = match (&options.hex, &options.text) {
+        (Some(hex), None) => parse_hex_bytes(hex).map_err(|e| PcapError::InvalidArgument(format!("{:#}", e)))?,
+        (None, Some(text)) if !text.is_empty() => text.as_bytes().to_vec(),
+        _ => anyhow::bail!(PcapError::InvalidArgument("必须且只能指定非空的 --hex 或 --text 之一".to_string())),
+    };
+    let limit = options.limit.unwrap_or(usize::MAX);
+
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut rows: Vec<Option<Row>> = Vec::new();
+    // 上一个匹配之前的包 (作为下一个匹配的前置上下文)
+    let mut before: VecDeque<Row> = VecDeque::with_capacity(options.context);
+    let mut after_remaining = 0;
+    let mut last_listed = 0u64;
+    let mut packet_count = 0u64;
+    let mut matched_packets = 0usize;
+    let mut total_matches = 0usize;
+    let mut start_us = None;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        if matched_packets >= limit && after_remaining == 0 {
+            break;
+        }
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = interface.timestamp_ns(packet.timestamp).div_euclid(1000) as i64;
+        start_us.get_or_insert(time_us);
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let payload = parsed.ip.as_ref().map(|_| parsed.payload_range(data.len()));
+
+        // 载荷中的位置同时给出帧中的位置；整个帧查找时落在载荷中的匹配也给出载荷偏移
+        let offsets: Vec<(Option<usize>, usize)> = if matched_packets < limit {
+            let (haystack, base) = match (&payload, options.whole_frame) {
+                (_, true) => (&data[..], 0),
+                (Some(range), false) => (&data[range.clone()], range.start),
+                (None, false) => (&data[..0], 0),
+            };
+            find_all(haystack, &needle)
+                .map(|offset| base + offset)
+                .map(|frame_offset| {
+                    let payload_offset = payload
+                        .as_ref()
+                        .filter(|range| range.contains(&frame_offset))
+                        .map(|range| frame_offset - range.start);
+                    (payload_offset, frame_offset)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let row = Row {
+            number: packet_count,
+            time_us,
+            flow: parsed.flow_key().map_or("(非IP包)".to_string(), |key| key.to_string()),
+            len: packet.orig_len,
+            match_count: offsets.len(),
+            offsets,
+        };
+
+        if row.match_count > 0 {
+            matched_packets += 1;
+            total_matches += row.match_count;
+            let first = before.front().map_or(row.number, |row| row.number);
+            if options.context > 0 && last_listed > 0 && first > last_listed + 1 {
+                rows.push(None);
+            }
+            rows.extend(before.drain(..).map(Some));
+            last_listed = row.number;
+            rows.push(Some(row));
+            after_remaining = options.context;
+        } else if after_remaining > 0 {
+            after_remaining -= 1;
+            last_listed = row.number;
+            rows.push(Some(row));
+        } else if options.context > 0 {
+            if before.len() == options.context {
+                before.pop_front();
+            }
+            before.push_back(row);
+        }
+    }
+
+    print_heading(&format!("字节序列搜索结果: {}", input_path));
+    print_fields(&[
+        ("查找内容", format_needle(&needle), None),
+        ("查找范围", if options.whole_frame { "整个帧" } else { "L4载荷" }.to_string(), None),
+        ("搜索的包数", packet_count.to_string(), None),
+        ("匹配的包数", matched_packets.to_string(), None),
+        ("匹配次数", total_matches.to_string(), None),
+    ]);
+
+    if !rows.is_empty() {
+        let start_us = start_us.unwrap_or_default();
+        let mut table = Table::new(&["", "序号", "时间", "五元组", "长度", "载荷偏移", "帧偏移"]).right_align(&[1, 4]);
+        for row in &rows {
+            let Some(row) = row else {
+                table.add_row(vec!["--".to_string()]);
+                continue;
+            };
+            let list = |offsets: Vec<String>| {
+                let mut text = offsets.join(", ");
+                if row.match_count > MAX_OFFSETS {
+                    text.push_str(&format!(" 等{}处", row.match_count));
+                }
+                text
+            };
+            let shown = &row.offsets[..row.offsets.len().min(MAX_OFFSETS)];
+            table.add_row(vec![
+                if row.match_count > 0 { "*" } else { "" }.to_string(),
+                row.number.to_string(),
+                format_timestamp_us(row.time_us, start_us),
+                row.flow.clone(),
+                row.len.to_string(),
+                list(shown.iter().map(|(payload, _)| payload.map_or("-".to_string(), |offset| offset.to_string())).collect()),
+                list(shown.iter().map(|(_, frame)| frame.to_string()).collect()),
+            ]);
+        }
+        table.print();
+    }
+
+    print_separator();
+    if matched_packets == 0 {
+        print_verdict(Severity::Warning, "没有包含该字节序列的包");
+    } else if matched_packets >= limit {
+        print_verdict(Severity::Ok, &format!("找到 {} 个匹配的包后停止 (--limit)", matched_packets));
+    } else {
+        print_verdict(Severity::Ok, &format!("{} 个包包含该字节序列", matched_packets));
+    }
+    Ok(())
+}
+
+/// 字节序列在数据中的全部出现位置 (可以重叠)
+fn find_all<'a>(haystack: &'a [u8], needle: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(move |(_, window)| *window == needle)
+        .map(|(offset, _)| offset)
+}
+
+/// 十六进制显示要查找的内容，可打印时附上文本
+fn format_needle(needle: &[u8]) -> String {
+    let hex: String = needle.iter().map(|b| format!("{:02x}", b)).collect();
+    if needle.iter().all(|&b| b.is_ascii_graphic() || b == b' ') {
+        format!("{} (\"{}\")", hex, String::from_utf8_lossy(needle))
+    } else {
+        hex
+    }
+}
@@ -0,0 +1,452 @@
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use pcap_file::pcap_header::{Datalink, PcapHeader};
+use pcap_file::{Packet, PcapWriter};
+use anyhow::{Context, Result, anyhow};
+use seahash::SeaHasher;
+
+use crate::modules::io::{OutputFormat, output_format, set_output_format};
+use crate::modules::pcap_annotate::pcap_annotate;
+use crate::modules::pcap_augment_timed::pcap_augment_timed;
+use crate::modules::pcap_decap::{DecapOptions, pcap_decap};
+use crate::modules::pcap_dilute_timed::pcap_dilute_timed;
+use crate::modules::pcap_disorder_fix::pcap_disorder_fix;
+use crate::modules::pcap_merge::pcap_merge_named;
+use crate::modules::pcap_packet_edit::{FieldEdit, pcap_edit};
+use crate::modules::pcap_input::{follow, set_follow};
+use crate::modules::pcap_output::{Compression, compression, set_compression};
+use crate::modules::pcap_packet_extractor::{PacketSelection, pcap_extract};
+use crate::modules::pcap_packet_builder::{ethernet, internet_checksum, ipv4, ipv6, tcp, udp};
+use crate::modules::pcap_packet_parser::{
+    ETHERTYPE_IPV4, ETHERTYPE_IPV6, IP_PROTO_ICMP, IP_PROTO_TCP, IP_PROTO_UDP, LINKTYPE_ETHERNET,
+};
+use crate::modules::pcap_packet_store::{memory_limit, set_memory_limit};
+use crate::modules::pcap_payload_export::pcap_export_payloads;
+use crate::modules::pcap_report::{Severity, Table, print_heading, print_separator, print_verdict};
+use crate::modules::pcap_retime::{pcap_deskew, pcap_retime_from_file};
+use crate::modules::pcap_rule_rewrite::{RewriteOptions, pcap_rewrite};
+use crate::modules::pcap_tcp_normalize::TcpOptionStrip;
+use crate::modules::pcap_template_expand::pcap_template_expand;
+use crate::modules::pcap_time_anchor::TimeAnchor;
+use crate::modules::pcap_time_dilator::pcap_time_dilator;
+use crate::modules::pcap_time_reducer::pcap_time_compressor;
+use crate::modules::pcap_verify::{set_verify, verify_enabled};
+
+/// 内置样例抓包的文件名 (位于临时目录中)
+const FIXTURE: &str = "fixture.pcap";
+/// 重新计时用的时间戳文件
+const TIMES_FILE: &str = "times.csv";
+/// 改写规则文件
+const RULES_FILE: &str = "rules.csv";
+
+/// 样例抓包的首包时间 (epoch秒)
+const BASE_SECONDS: u32 = 1_700_000_000;
+
+const CLIENT: [u8; 4] = [10, 0, 0, 1];
+const SERVER: [u8; 4] = [10, 0, 0, 2];
+const DNS_SERVER: [u8; 4] = [10, 0, 0, 53];
+const CLIENT_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+const SERVER_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+
+/// 一个自测用例: 用内置样例执行一个命令，输出写入给定路径
+struct Case {
+    name: &'static str,
+    /// 输出文件的扩展名，为空表示输出目录
+    extension: &'static str,
+    run: fn(&Inputs, &str) -> Result<()>,
+}
+
+/// 临时目录中各用例的输入文件路径
+struct Inputs {
+    fixture: String,
+    times: String,
+    rules: String,
+}
+
+/// 影响命令输出的全局选项 (`--output-format`、`--compress`、`--verify`、`--follow`、`--max-memory`)
+///
+/// 自测期间固定为默认值，结束后恢复，使结果与命令行上的全局选项无关。
+struct GlobalOptions {
+    output_format: OutputFormat,
+    compression: Compression,
+    verify: bool,
+    follow: bool,
+    memory_limit: Option<u64>,
+    log_level: log::LevelFilter,
+}
+
+impl GlobalOptions {
+    /// 保存当前的全局选项并设为默认值
+    fn pin() -> GlobalOptions {
+        let previous = GlobalOptions {
+            output_format: output_format(),
+            compression: compression(),
+            verify: verify_enabled(),
+            follow: follow(),
+            memory_limit: memory_limit(),
+            log_level: log::max_level(),
+        };
+        // 输出格式由每个用例的扩展名决定
+        set_output_format(OutputFormat::Auto);
+        set_compression(Compression::None);
+        set_verify(false);
+        set_follow(false);
+        set_memory_limit(None);
+        // 各命令的进度日志与自测结果无关
+        log::set_max_level(previous.log_level.min(log::LevelFilter::Warn));
+        previous
+    }
+
+    fn restore(self) {
+        set_output_format(self.output_format);
+        set_compression(self.compression);
+        set_verify(self.verify);
+        set_follow(self.follow);
+        set_memory_limit(self.memory_limit);
+        log::set_max_level(self.log_level);
+    }
+}
+
+const CASES: [Case; 17] = [
+    Case { name: "time-compress", extension: "pcap", run: |inputs, out| pcap_time_compressor(&inputs.fixture, out, 2.0, TimeAnchor::Start, OutputFormat::Pcap, Compression::None, None) },
+    Case { name: "time-stretch", extension: "pcap", run: |inputs, out| pcap_time_dilator(&inputs.fixture, out, 1.5, TimeAnchor::Midpoint, OutputFormat::Pcap, Compression::None, None) },
+    Case { name: "dilute", extension: "pcap", run: |inputs, out| pcap_dilute_timed(&inputs.fixture, out, 2, OutputFormat::Pcap, Compression::None) },
+    Case { name: "augment", extension: "pcap", run: |inputs, out| pcap_augment_timed(&inputs.fixture, out, 3, OutputFormat::Pcap, Compression::None) },
+    Case { name: "template-expand", extension: "pcap", run: |inputs, out| pcap_template_expand(&inputs.fixture, out, 3, 1.0, 1, 10) },
+    Case { name: "disorder-fix", extension: "pcap", run: |inputs, out| pcap_disorder_fix(&inputs.fixture, Some(out), false) },
+    Case { name: "disorder-fix-spill", extension: "pcap", run: disorder_fix_spill },
+    Case { name: "extract", extension: "pcap", run: extract_flow },
+    Case { name: "retime", extension: "pcap", run: |inputs, out| pcap_retime_from_file(&inputs.fixture, out, &inputs.times, 1, false) },
+    Case { name: "deskew", extension: "pcap", run: |inputs, out| pcap_deskew(&inputs.fixture, out, Some(50.0), None) },
+    Case { name: "rewrite", extension: "pcap", run: rewrite },
+    Case { name: "decap", extension: "pcap", run: |inputs, out| pcap_decap(&inputs.fixture, out, None, &DecapOptions::default()) },
+    Case { name: "edit", extension: "pcap", run: edit },
+    Case { name: "merge", extension: "pcapng", run: |inputs, out| pcap_merge_named(out, &[(&inputs.fixture, FIXTURE), (&inputs.fixture, FIXTURE)]) },
+    Case { name: "annotate", extension: "pcapng", run: |inputs, out| pcap_annotate(&inputs.fixture, out, 6, "self-test") },
+    Case { name: "export-payloads", extension: "", run: |inputs, out| pcap_export_payloads(&inputs.fixture, out, true) },
+    Case { name: "export-payloads-packets", extension: "", run: |inputs, out| pcap_export_payloads(&inputs.fixture, out, false) },
+];
+
+/// 用内置样例验证相同的输入和参数总是得到逐字节相同的输出
+///
+/// # 参数
+/// - `golden_dir`: 保存基准输出的目录 (None表示只比较两次运行)
+/// - `update`: 用本次的输出覆盖基准输出
+///
+/// # 功能
+/// 1. 在临时目录中生成样例抓包 (TCP/UDP/DNS/ICMP/VLAN/GTP-U/IPv6，包含乱序和重复包)，
+///    不改变进程的当前目录；`--output-format`、`--compress` 等全局选项在自测期间固定为默认值
+/// 2. 每个用例运行两次并比较输出；输出目录按文件名排序后逐个比较
+/// 3. 指定 `golden_dir` 时再与其中的基准输出比较，没有基准输出的用例写入本次的输出
+/// 4. 任一用例不一致时返回错误
+pub fn pcap_self_test(golden_dir: Option<&str>, update: bool) -> Result<()> {
+    let golden_dir = match golden_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir).with_context(|| format!("无法创建基准输出目录: {}", dir))?;
+            Some(fs::canonicalize(dir).with_context(|| format!("无法访问基准输出目录: {}", dir))?)
+        }
+        None => None,
+    };
+
+    let work_dir = std::env::temp_dir().join(format!("pcap-editor-self-test-{}", std::process::id()));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir).with_context(|| format!("无法清理临时目录: {}", work_dir.display()))?;
+    }
+    fs::create_dir_all(&work_dir).with_context(|| format!("无法创建临时目录: {}", work_dir.display()))?;
+
+    let previous = GlobalOptions::pin();
+    let result = run_cases(&work_dir, golden_dir.as_deref(), update);
+    previous.restore();
+    let _ = fs::remove_dir_all(&work_dir);
+
+    let failures = result?;
+    if failures > 0 {
+        anyhow::bail!("{} 个用例的输出不一致", failures);
+    }
+    Ok(())
+}
+
+/// 运行全部用例并打印结果，返回失败的用例数
+fn run_cases(work_dir: &Path, golden_dir: Option<&Path>, update: bool) -> Result<usize> {
+    let path = |name: &str| work_dir.join(name).to_string_lossy().into_owned();
+    let inputs = Inputs { fixture: path(FIXTURE), times: path(TIMES_FILE), rules: path(RULES_FILE) };
+    write_fixture(&inputs.fixture)?;
+    write_times(&inputs.times)?;
+    fs::write(&inputs.rules, "match,src_ip,dst_port\ntcp.dstport == 80,10.9.9.9,8080\n")
+        .with_context(|| format!("无法写入规则文件: {}", inputs.rules))?;
+
+    print_heading(&format!("自测: {} 个用例", CASES.len()));
+    let mut table = Table::new(&["用例", "哈希", "结果"]);
+    let mut failures = 0;
+    for case in &CASES {
+        let outputs = [1, 2].map(|run| match case.extension {
+            "" => path(&format!("{}-{}", case.name, run)),
+            extension => path(&format!("{}-{}.{}", case.name, run, extension)),
+        });
+        let mut contents = Vec::with_capacity(outputs.len());
+        for output in &outputs {
+            (case.run)(&inputs, output).with_context(|| format!("用例 {} 执行失败", case.name))?;
+            contents.push(read_output(output)?);
+        }
+
+        let hash = content_hash(&contents[0]);
+        let mut result = if contents[0] == contents[1] {
+            (Severity::Ok, "一致".to_string())
+        } else {
+            (Severity::Error, format!("两次运行不一致 (第二次 {:016x})", content_hash(&contents[1])))
+        };
+        if let Some(dir) = golden_dir
+            && result.0 == Severity::Ok
+        {
+            result = check_golden(&dir.join(golden_name(case)), &contents[0], update)?;
+        }
+        if result.0 == Severity::Error {
+            failures += 1;
+        }
+        table.add_row(vec![case.name.to_string(), format!("{:016x}", hash), result.1]);
+    }
+    table.print();
+
+    print_separator();
+    if failures == 0 {
+        print_verdict(Severity::Ok, &format!("全部 {} 个用例的输出一致", CASES.len()));
+    } else {
+        print_verdict(Severity::Error, &format!("{} / {} 个用例的输出不一致", failures, CASES.len()));
+    }
+    Ok(failures)
+}
+
+/// 基准输出的文件名 (输出目录的内容合并为一个 `.bin` 文件，格式同 [`read_output`])
+fn golden_name(case: &Case) -> String {
+    match case.extension {
+        "" => format!("{}.bin", case.name),
+        extension => format!("{}.{}", case.name, extension),
+    }
+}
+
+/// 与基准输出比较，没有基准输出或指定更新时写入
+fn check_golden(path: &Path, content: &[u8], update: bool) -> Result<(Severity, String)> {
+    let write = |message: &str| -> Result<(Severity, String)> {
+        fs::write(path, content).with_context(|| format!("无法写入基准输出: {}", path.display()))?;
+        Ok((Severity::Warning, message.to_string()))
+    };
+    if update {
+        return write("已更新基准输出");
+    }
+    match fs::read(path) {
+        Ok(golden) if golden == content => Ok((Severity::Ok, "与基准输出一致".to_string())),
+        Ok(golden) => Ok((
+            Severity::Error,
+            format!("与基准输出不一致 (基准 {:016x})", content_hash(&golden)),
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => write("已写入基准输出"),
+        Err(e) => Err(e).with_context(|| format!("无法读取基准输出: {}", path.display())),
+    }
+}
+
+/// 读取输出文件；输出目录按文件名排序，依次拼接 `文件名\n长度\n内容`
+fn read_output(path: &str) -> Result<Vec<u8>> {
+    let path = PathBuf::from(path);
+    if !path.is_dir() {
+        return fs::read(&path).with_context(|| format!("无法读取输出文件: {}", path.display()));
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&path).with_context(|| format!("无法读取输出目录: {}", path.display()))? {
+        names.push(entry?.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    let mut content = Vec::new();
+    for name in names {
+        let data = fs::read(path.join(&name)).with_context(|| format!("无法读取输出文件: {}", name))?;
+        content.extend_from_slice(format!("{}\n{}\n", name, data.len()).as_bytes());
+        content.extend_from_slice(&data);
+    }
+    Ok(content)
+}
+
+fn content_hash(content: &[u8]) -> u64 {
+    let mut hasher = SeaHasher::new();
+    hasher.write(content);
+    hasher.finish()
+}
+
+/// 用小内存上限执行乱序修复，覆盖分段排序后多路归并的路径
+fn disorder_fix_spill(inputs: &Inputs, output: &str) -> Result<()> {
+    let previous = memory_limit();
+    set_memory_limit(Some(512));
+    let result = pcap_disorder_fix(&inputs.fixture, Some(output), false);
+    set_memory_limit(previous);
+    result
+}
+
+/// 提取第3个包 (TCP握手的SYN) 所在的流
+fn extract_flow(inputs: &Inputs, output: &str) -> Result<()> {
+    let selection = PacketSelection {
+        start: None,
+        count: None,
+        from_time: None,
+        to_time: None,
+        flow_of: Some(3),
+        linktype: None,
+    };
+    pcap_extract(&inputs.fixture, output, &selection)
+}
+
+fn rewrite(inputs: &Inputs, output: &str) -> Result<()> {
+    let options = RewriteOptions {
+        subnet_maps: Vec::new(),
+        strip_tcp_options: Some(TcpOptionStrip::All),
+        tcp_isn: Some(0),
+        renumber_ip_id: true,
+        padding: None,
+    };
+    pcap_rewrite(&inputs.fixture, output, Some(&inputs.rules), &options)
+}
+
+fn edit(inputs: &Inputs, output: &str) -> Result<()> {
+    let edits = ["ip.ttl=32", "tcp.window=1000"]
+        .map(|text| FieldEdit::parse(text).map_err(anyhow::Error::msg))
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+    pcap_edit(&inputs.fixture, output, &[3, 6], &edits)
+}
+
+/// 重新计时用的时间戳: 每个包一行，间隔0.25秒
+fn write_times(path: &str) -> Result<()> {
+    let mut content = String::from("time\n");
+    for i in 0..fixture_packets().len() {
+        content.push_str(&format!("{}.{:06}\n", BASE_SECONDS + 100 + i as u32 / 4, i % 4 * 250_000));
+    }
+    fs::write(path, content).with_context(|| format!("无法写入时间戳文件: {}", path))
+}
+
+/// 写入样例抓包
+fn write_fixture(path: &str) -> Result<()> {
+    let header = PcapHeader::with_datalink(Datalink::from(LINKTYPE_ETHERNET));
+    let file = File::create(path).with_context(|| format!("无法创建样例文件: {}", path))?;
+    let mut writer = PcapWriter::with_header(header, file)
+        .map_err(|e| anyhow!("创建PCAP写入器失败: {}", e))?;
+    for (offset_us, data) in fixture_packets() {
+        let ts_us = offset_us as u64;
+        let (ts_sec, ts_usec) = (BASE_SECONDS + (ts_us / 1_000_000) as u32, (ts_us % 1_000_000) as u32);
+        let packet = Packet::new(ts_sec, ts_usec, data.len() as u32, &data);
+        writer.write_packet(&packet).map_err(|e| anyhow!("写入样例数据包失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 样例数据包: (距首包的微秒数, 以太网帧)
+fn fixture_packets() -> Vec<(u32, Vec<u8>)> {
+    let syn_options = [
+        2, 4, 0x05, 0xb4, // MSS 1460
+        4, 2, // 允许SACK
+        8, 10, 0, 0, 0, 1, 0, 0, 0, 0, // 时间戳
+        1, // NOP
+        3, 3, 7, // 窗口扩大
+    ];
+    let request = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+    let (client_isn, server_isn) = (1000u32, 5000u32);
+    let client_data = client_isn + 1;
+    let server_data = server_isn + 1;
+    let client_fin = client_data + request.len() as u32;
+    let server_fin = server_data + response.len() as u32;
+
+    let to_server = |seq, ack, flags, options: &[u8], payload: &[u8]| {
+        let tcp = tcp(40000, 80, seq, ack, flags, options, payload);
+        ethernet(CLIENT_MAC, SERVER_MAC, &[], ETHERTYPE_IPV4, &ipv4(CLIENT, SERVER, IP_PROTO_TCP, &tcp))
+    };
+    let to_client = |seq, ack, flags, options: &[u8], payload: &[u8]| {
+        let tcp = tcp(80, 40000, seq, ack, flags, options, payload);
+        ethernet(SERVER_MAC, CLIENT_MAC, &[], ETHERTYPE_IPV4, &ipv4(SERVER, CLIENT, IP_PROTO_TCP, &tcp))
+    };
+
+    // GTP-U封装的TCP包 (G-PDU，TEID 0x11223344)
+    let inner = ipv4([10, 1, 0, 1], [10, 1, 0, 2], IP_PROTO_TCP, &tcp(1234, 80, 1, 1, TCP_PSH | TCP_ACK, &[], b"inner"));
+    let mut gtp = vec![0x30, 0xff];
+    gtp.extend_from_slice(&(inner.len() as u16).to_be_bytes());
+    gtp.extend_from_slice(&0x1122_3344u32.to_be_bytes());
+    gtp.extend_from_slice(&inner);
+    let gtp_outer = ipv4([192, 168, 0, 1], [192, 168, 0, 2], IP_PROTO_UDP, &udp(2152, 2152, &gtp));
+
+    let ipv6_src = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    let ipv6_dst = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+
+    let last_ack = to_server(client_fin + 1, server_fin + 1, TCP_ACK, &[], &[]);
+    vec![
+        (0, ethernet(CLIENT_MAC, SERVER_MAC, &[], ETHERTYPE_IPV4, &ipv4(CLIENT, DNS_SERVER, IP_PROTO_UDP, &udp(40001, 53, &dns(false))))),
+        (5_000, ethernet(SERVER_MAC, CLIENT_MAC, &[], ETHERTYPE_IPV4, &ipv4(DNS_SERVER, CLIENT, IP_PROTO_UDP, &udp(53, 40001, &dns(true))))),
+        (10_000, to_server(client_isn, 0, TCP_SYN, &syn_options, &[])),
+        (12_000, to_client(server_isn, client_data, TCP_SYN | TCP_ACK, &syn_options, &[])),
+        (14_000, to_server(client_data, server_data, TCP_ACK, &[], &[])),
+        (20_000, to_server(client_data, server_data, TCP_PSH | TCP_ACK, &[], request)),
+        (30_000, to_client(server_data, client_fin, TCP_PSH | TCP_ACK, &[], response)),
+        (60_000, to_server(client_fin, server_fin, TCP_ACK, &[], &[])),
+        // 时间戳早于上一个包
+        (55_000, ethernet(CLIENT_MAC, SERVER_MAC, &[], ETHERTYPE_IPV4, &ipv4(CLIENT, SERVER, IP_PROTO_ICMP, &icmp_echo()))),
+        (70_000, ethernet(CLIENT_MAC, SERVER_MAC, &[100], ETHERTYPE_IPV4, &ipv4(CLIENT, SERVER, IP_PROTO_UDP, &udp(5000, 5001, b"vlan payload")))),
+        (80_000, ethernet(CLIENT_MAC, SERVER_MAC, &[], ETHERTYPE_IPV4, &gtp_outer)),
+        (90_000, ethernet(CLIENT_MAC, SERVER_MAC, &[], ETHERTYPE_IPV6, &ipv6(ipv6_src, ipv6_dst, IP_PROTO_UDP, &udp(6000, 6001, b"ipv6 payload")))),
+        (100_000, to_server(client_fin, server_fin, TCP_FIN | TCP_ACK, &[], &[])),
+        (110_000, to_client(server_fin, client_fin + 1, TCP_FIN | TCP_ACK, &[], &[])),
+        (120_000, last_ack.clone()),
+        // 与上一个包完全相同
+        (120_000, last_ack),
+    ]
+}
+
+fn icmp_echo() -> Vec<u8> {
+    let mut message = vec![8, 0, 0, 0, 0x00, 0x01, 0x00, 0x01];
+    message.extend_from_slice(b"ping");
+    let checksum = internet_checksum(&message);
+    message[2..4].copy_from_slice(&checksum.to_be_bytes());
+    message
+}
+
+/// example.com 的A记录查询或应答 (应答地址为服务器地址)
+fn dns(response: bool) -> Vec<u8> {
+    let mut message = vec![0x12, 0x34];
+    message.extend_from_slice(if response { &[0x81, 0x80] } else { &[0x01, 0x00] });
+    message.extend_from_slice(&[0, 1, 0, response as u8, 0, 0, 0, 0]);
+    message.extend_from_slice(b"\x07example\x03com\x00");
+    message.extend_from_slice(&[0, 1, 0, 1]);
+    if response {
+        message.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0x01, 0x2c, 0, 4]);
+        message.extend_from_slice(&SERVER);
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 全局选项不是默认值时，自测仍然全部一致，结束后全局选项恢复原值
+    #[test]
+    fn self_test_is_reproducible_and_ignores_global_options() {
+        set_output_format(OutputFormat::Pcapng);
+        set_compression(Compression::Gzip(1));
+        set_verify(true);
+        set_memory_limit(Some(1 << 20));
+
+        let result = pcap_self_test(None, false);
+
+        assert_eq!(output_format(), OutputFormat::Pcapng);
+        assert_eq!(compression(), Compression::Gzip(1));
+        assert!(verify_enabled());
+        assert_eq!(memory_limit(), Some(1 << 20));
+        set_output_format(OutputFormat::Auto);
+        set_compression(Compression::None);
+        set_verify(false);
+        set_memory_limit(None);
+
+        result.unwrap();
+    }
+}
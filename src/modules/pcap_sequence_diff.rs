@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+/// 计算两个哈希序列的最小差异，返回相互匹配的下标对 (按下标递增)
+///
+/// 先用patience diff以两边都只出现一次的哈希为锚点切分序列，
+/// 锚点之间无法再切分的区间用Myers差分算法 (线性空间) 求最小编辑。
+/// 未出现在结果中的下标即为只存在于一侧的元素。
+pub fn diff_matches(a: &[u64], b: &[u64]) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    patience_diff(a, 0, b, 0, &mut matches);
+    matches
+}
+
+/// 以唯一哈希为锚点递归切分
+fn patience_diff(a: &[u64], a_base: usize, b: &[u64], b_base: usize, matches: &mut Vec<(usize, usize)>) {
+    let (a, b, prefix, suffix) = trim_common(a, b);
+    matches.extend((0..prefix).map(|i| (a_base + i, b_base + i)));
+    let (a_base, b_base) = (a_base + prefix, b_base + prefix);
+
+    if !a.is_empty() && !b.is_empty() {
+        // 统计每个哈希在两边的出现次数及位置
+        let mut occurrences: HashMap<u64, (usize, usize, usize, usize)> = HashMap::new();
+        for (i, hash) in a.iter().enumerate() {
+            let entry = occurrences.entry(*hash).or_insert((0, 0, 0, 0));
+            entry.0 += 1;
+            entry.1 = i;
+        }
+        for (j, hash) in b.iter().enumerate() {
+            if let Some(entry) = occurrences.get_mut(hash) {
+                entry.2 += 1;
+                entry.3 = j;
+            }
+        }
+
+        if occurrences.values().any(|&(_, _, count_b, _)| count_b > 0) {
+            let mut anchors: Vec<(usize, usize)> = occurrences
+                .values()
+                .filter(|&&(count_a, _, count_b, _)| count_a == 1 && count_b == 1)
+                .map(|&(_, i, _, j)| (i, j))
+                .collect();
+            anchors.sort_unstable();
+            let anchors = longest_increasing(&anchors);
+
+            if anchors.is_empty() {
+                myers_diff(a, a_base, b, b_base, matches);
+            } else {
+                let (mut last_i, mut last_j) = (0, 0);
+                for (i, j) in anchors {
+                    patience_diff(&a[last_i..i], a_base + last_i, &b[last_j..j], b_base + last_j, matches);
+                    matches.push((a_base + i, b_base + j));
+                    last_i = i + 1;
+                    last_j = j + 1;
+                }
+                patience_diff(&a[last_i..], a_base + last_i, &b[last_j..], b_base + last_j, matches);
+            }
+        }
+    }
+
+    let (a_end, b_end) = (a_base + a.len(), b_base + b.len());
+    matches.extend((0..suffix).map(|i| (a_end + i, b_end + i)));
+}
+
+/// 按第一个下标排好序的锚点中，第二个下标严格递增的最长子序列
+fn longest_increasing(anchors: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // tails[k]: 长度为k+1的递增子序列中末尾最小的锚点下标
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; anchors.len()];
+
+    for (index, &(_, j)) in anchors.iter().enumerate() {
+        let position = tails.partition_point(|&t| anchors[t].1 < j);
+        if position > 0 {
+            previous[index] = Some(tails[position - 1]);
+        }
+        if position == tails.len() {
+            tails.push(index);
+        } else {
+            tails[position] = index;
+        }
+    }
+
+    let mut result = Vec::with_capacity(tails.len());
+    let mut current = tails.last().copied();
+    while let Some(index) = current {
+        result.push(anchors[index]);
+        current = previous[index];
+    }
+    result.reverse();
+    result
+}
+
+/// 去掉公共前缀和后缀，返回剩余部分及前后缀长度
+fn trim_common<'a>(a: &'a [u64], b: &'a [u64]) -> (&'a [u64], &'a [u64], usize, usize) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a.iter().rev().zip(b.iter().rev()).take_while(|(x, y)| x == y).count();
+    (&a[..a.len() - suffix], &b[..b.len() - suffix], prefix, suffix)
+}
+
+/// Myers差分 (分治求中间蛇形，线性空间)
+fn myers_diff(a: &[u64], a_base: usize, b: &[u64], b_base: usize, matches: &mut Vec<(usize, usize)>) {
+    let (a, b, prefix, suffix) = trim_common(a, b);
+    matches.extend((0..prefix).map(|i| (a_base + i, b_base + i)));
+    let (a_base, b_base) = (a_base + prefix, b_base + prefix);
+
+    if !a.is_empty() && !b.is_empty() {
+        let (x, y, u, v) = middle_snake(a, b);
+        myers_diff(&a[..x], a_base, &b[..y], b_base, matches);
+        matches.extend((0..u - x).map(|k| (a_base + x + k, b_base + y + k)));
+        myers_diff(&a[u..], a_base + u, &b[v..], b_base + v, matches);
+    }
+
+    let (a_end, b_end) = (a_base + a.len(), b_base + b.len());
+    matches.extend((0..suffix).map(|i| (a_end + i, b_end + i)));
+}
+
+/// 找到最短编辑路径中间的蛇形 (起点x,y 到终点u,v)
+fn middle_snake(a: &[u64], b: &[u64]) -> (usize, usize, usize, usize) {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    let size = (2 * offset + 1) as usize;
+    let mut forward = vec![0isize; size];
+    let mut backward = vec![0isize; size];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        // 正向搜索
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+
+            let reverse_k = delta - k;
+            if odd && reverse_k.abs() < d && x + backward[at(reverse_k)] >= n {
+                return (x0 as usize, y0 as usize, x as usize, y as usize);
+            }
+        }
+
+        // 反向搜索 (坐标从末尾算起)
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+
+            let forward_k = delta - k;
+            if !odd && forward_k.abs() <= d && x + forward[at(forward_k)] >= n {
+                return ((n - x) as usize, (m - y) as usize, (n - x0) as usize, (m - y0) as usize);
+            }
+        }
+    }
+
+    unreachable!("最短编辑路径长度不会超过 n + m")
+}
@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_time_anchor::{split_timestamp_ticks, ticks_per_second, timestamp_ticks};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 解析带单位的比特率 (十进制)，例如 `100M`、`1.5Gbps`、`800k`、`64000`
+pub fn parse_bitrate(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(index) => text.split_at(index),
+        None => (text, ""),
+    };
+    let number: f64 = number.parse().map_err(|_| format!("无效的比特率: {}", text))?;
+    let unit = unit.trim().to_ascii_lowercase();
+    let multiplier: f64 = match unit.strip_suffix("bps").or_else(|| unit.strip_suffix("bit/s")).unwrap_or(&unit) {
+        "" => 1.0,
+        "k" => 1e3,
+        "m" => 1e6,
+        "g" => 1e9,
+        "t" => 1e12,
+        other => return Err(format!("未知的比特率单位: {}", other)),
+    };
+    let bps = number * multiplier;
+    if bps < 1.0 || bps >= u64::MAX as f64 {
+        return Err(format!("比特率超出范围: {}", text));
+    }
+    Ok(bps as u64)
+}
+
+/// 模拟路径上的整形器: 推迟数据包的时间戳，使任意一个时间窗口内的比特率都不超过上限
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `max_bps`: 比特率上限 (按帧的原始长度计算)
+/// - `window_us`: 滑动窗口的长度 (微秒)
+///
+/// # 功能
+/// 1. 包按输入顺序先进先出，每个包尽可能早地发出: 不早于原始时间和上一个包，
+///    并且加上它之后，(发出时间 - 窗口, 发出时间] 内的字节数不超过 上限 × 窗口
+/// 2. 不丢弃、不重排数据包，只修改时间戳；没有超过上限的部分时间保持不变
+/// 3. 单个帧就超过一个窗口的预算时，等到窗口内没有其他包时发出，并给出警告
+/// 4. 纳秒精度的PCAP文件按纳秒计算，输出保持纳秒精度
+pub fn pcap_shape(input_path: &str, output_path: &str, max_bps: u64, window_us: u64) -> Result<()> {
+    if max_bps == 0 || window_us == 0 {
+        anyhow::bail!(PcapError::InvalidArgument("比特率上限和时间窗口必须大于0".to_string()));
+    }
+    let window_us = window_us.min(i64::MAX as u64 / 2) as i64;
+    // 每个窗口允许的字节数
+    let budget = (max_bps as f64 * window_us as f64 / 8e6).floor() as u64;
+
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let header = pcap_reader.header;
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+    // 时间以文件的精度计 (微秒或纳秒)
+    let ticks_per_second = ticks_per_second(&header);
+    let window = window_us.saturating_mul(ticks_per_second / 1_000_000).min(i64::MAX / 2);
+
+    // 窗口内已发出的包: (发出时间, 字节数)
+    let mut sent: VecDeque<(i64, u64)> = VecDeque::new();
+    let mut sent_bytes = 0u64;
+    let mut last_time = i64::MIN;
+    let mut packet_count = 0u64;
+    let mut delayed = 0u64;
+    let mut oversized = 0u64;
+    let mut max_delay = 0i64;
+    let mut total_delay = 0i64;
+    let mut last_original = None;
+    for mut packet in pcap_reader.by_ref() {
+        packet_count += 1;
+        let original = timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second);
+        let size = packet.header.orig_len as u64;
+        let mut time = original.max(last_time);
+        loop {
+            while let Some(&(sent_time, bytes)) = sent.front()
+                && sent_time <= time - window
+            {
+                sent.pop_front();
+                sent_bytes -= bytes;
+            }
+            let Some(&(oldest, _)) = sent.front() else {
+                break;
+            };
+            if sent_bytes + size <= budget {
+                break;
+            }
+            // 等到最早的包离开窗口
+            time = oldest + window;
+        }
+        if size > budget {
+            oversized += 1;
+        }
+        sent.push_back((time, size));
+        sent_bytes += size;
+        last_time = time;
+        last_original = Some(original);
+
+        let delay = time - original;
+        if delay > 0 {
+            delayed += 1;
+            total_delay += delay;
+            max_delay = max_delay.max(delay);
+            let (ts_sec, ts_usec) = split_timestamp_ticks(time, ticks_per_second)?;
+            packet.header.ts_sec = ts_sec;
+            packet.header.ts_usec = ts_usec;
+        }
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    if oversized > 0 {
+        warn!("{} 个帧超过一个窗口的预算 ({} 字节)，请增大 --window", oversized, budget);
+    }
+    let milliseconds = |ticks: i64| format!("{:.3}ms", ticks as f64 * 1000.0 / ticks_per_second as f64);
+    info!(
+        "成功整形: 包数={}, 推迟的包数={}, 平均推迟={}, 最大推迟={}, 结束时间推迟={}, 输出文件={}",
+        packet_count,
+        delayed,
+        milliseconds(if delayed > 0 { total_delay / delayed as i64 } else { 0 }),
+        milliseconds(max_delay),
+        milliseconds(last_original.map_or(0, |original| last_time - original)),
+        output_path
+    );
+
+    Ok(())
+}
@@ -1,24 +1,44 @@
-use std::path::Path;
-use std::fs::File;
-use std::io::{Seek}; // 添加 Seek trait 导入
-use pcap_file::{PcapReader};
-use log::{error, info, warn};
-use anyhow::{Context, Result, anyhow};
+use anyhow::Result;
+use log::info;
+use std::collections::BTreeMap;
 use std::time::Duration;
 
-pub fn detect_pcap_disorder(input_path: &str) -> Result<()> {
-    let file = File::open(Path::new(input_path))
-        .with_context(|| format!("无法打开文件: {}", input_path))?;
-    
-    let mut pcap_reader = PcapReader::new(file)
-        .map_err(|e| anyhow!("无效的PCAP文件格式: {}", e))?;
+use crate::modules::io::PacketReader;
+use crate::modules::pcap_annotate::write_annotated;
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, format_timestamp_ns, print_fields, print_heading, print_section, print_separator, print_verdict,
+    verbosity,
+};
+
+/// 详情表中最多列出的乱序包数 (详细模式不限制)
+const MAX_DISORDER_ROWS: usize = 50;
+
+/// 检测时间戳乱序和重复的数据包
+///
+/// `annotate_path` 不为None时，把输入复制为pcapng并为每个乱序包添加注释。
+pub fn detect_pcap_disorder(input_path: &str, annotate_path: Option<&str>) -> Result<()> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
 
     let mut prev_timestamp: Option<Duration> = None;
+    // 第一个包的时间戳 (相对时间格式的起点)
+    let mut first_timestamp = Duration::ZERO;
     let mut disorder_count = 0;
+    // (序号, 时间戳, 前包时间戳)
+    let mut disordered = Vec::new();
     let mut packet_count = 0;
-    let mut read_errors = 0;
+    // 详细模式下列出全部乱序包
+    let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_DISORDER_ROWS };
+    // 时间戳重复统计 (与前一个包时间戳相同)
+    let mut duplicate_count = 0;
+    let mut duplicate_groups = 0;
+    let mut current_group = 1;
+    let mut largest_group = 1;
+    // 所有时间戳共同的微秒粒度 (用于估计时钟精度)
+    let mut resolution_usec = 1_000_000;
+    // 需要写入注释副本时记录全部乱序包: 序号 -> 注释
+    let mut comments: BTreeMap<u64, Vec<String>> = BTreeMap::new();
 
-    while let Some(packet) = pcap_reader.next() {
+    for packet in pcap_reader.by_ref() {
         packet_count += 1;
         
         // 从包头获取时间戳
@@ -27,56 +47,111 @@ pub fn detect_pcap_disorder(input_path: &str) -> Result<()> {
             header.ts_sec as u64,    // 秒部分
             header.ts_usec * 1000    // 微秒转纳秒
         );
+        if packet_count == 1 {
+            first_timestamp = current_timestamp;
+        }
+        while resolution_usec > 1 && header.ts_usec % resolution_usec != 0 {
+            resolution_usec /= 10;
+        }
         
-        if let Some(prev_ts) = prev_timestamp {
-            if current_timestamp < prev_ts {
-                disorder_count += 1;
-                
-                let time_diff = prev_ts - current_timestamp;
-                let time_diff_sec = time_diff.as_secs_f64();
-                
-                warn!(
-                    "乱序包 #{}: 时间戳 {}.{:09} < 前包 {}.{:09} (差值: {:.9}秒)",
-                    packet_count,
-                    current_timestamp.as_secs(),
-                    current_timestamp.subsec_nanos(),
-                    prev_ts.as_secs(),
-                    prev_ts.subsec_nanos(),
-                    time_diff_sec
-                );
+        if prev_timestamp == Some(current_timestamp) {
+            duplicate_count += 1;
+            if current_group == 1 {
+                duplicate_groups += 1;
+            }
+            current_group += 1;
+            largest_group = largest_group.max(current_group);
+        } else {
+            current_group = 1;
+        }
+        
+        if let Some(prev_ts) = prev_timestamp
+            && current_timestamp < prev_ts
+        {
+            disorder_count += 1;
+            if disordered.len() < max_rows {
+                disordered.push((packet_count, current_timestamp, prev_ts));
+            }
+            if annotate_path.is_some() {
+                let comment = format!("乱序: 时间戳比前一个包早 {:.9} 秒", (prev_ts - current_timestamp).as_secs_f64());
+                comments.insert(packet_count, vec![comment]);
             }
         }
         prev_timestamp = Some(current_timestamp);
     }
 
-    // 检测是否提前结束
-    if let Ok(metadata) = std::fs::metadata(input_path) {
-        let file_size = metadata.len();
-        
-        // 修复点：使用 Seek trait 的方法
-        let mut reader = pcap_reader.into_reader();
-        let pos = reader.stream_position()?; // 现在可以调用 stream_position()
-        
-        if pos < file_size {
-            warn!(
-                "⚠️ 文件未完全读取: 已读取 {} 字节/总计 {} 字节 ({} 个数据包)",
-                pos, file_size, packet_count
-            );
-            read_errors += 1;
+    // 检测是否提前结束 (文件被截断或损坏)
+    let unread = pcap_reader.check_complete().err();
+
+    print_heading(&format!("乱序检测结果: {}", input_path));
+    let mut fields = vec![
+        ("数据包数", packet_count.to_string(), None),
+        ("乱序包数", disorder_count.to_string(), (disorder_count > 0).then_some(Severity::Error)),
+    ];
+    // 时间戳重复会影响稀释时的最近包选择和比较时的时间匹配
+    if duplicate_count > 0 {
+        fields.push((
+            "时间戳重复的包",
+            format!(
+                "{} ({:.2}%)，共 {} 组，最大一组 {} 个包",
+                duplicate_count,
+                duplicate_count as f64 * 100.0 / packet_count as f64,
+                duplicate_groups,
+                largest_group
+            ),
+            Some(Severity::Warning),
+        ));
+    } else {
+        fields.push(("时间戳重复的包", "0".to_string(), None));
+    }
+    if let Some(error) = &unread {
+        fields.push(("未读取的数据", error.to_string(), Some(Severity::Error)));
+    }
+    print_fields(&fields);
+
+    if !disordered.is_empty() {
+        print_section("乱序包详情:");
+        let mut table = Table::new(&["序号", "时间戳", "前包时间戳", "差值 (秒)"]).right_align(&[0, 1, 2, 3]);
+        let format_time = |timestamp: &Duration| format_timestamp_ns(timestamp.as_nanos() as i128, first_timestamp.as_nanos() as i128);
+        for (number, timestamp, prev_ts) in &disordered {
+            table.add_row(vec![
+                number.to_string(),
+                format_time(timestamp),
+                format_time(prev_ts),
+                format!("{:.9}", (*prev_ts - *timestamp).as_secs_f64()),
+            ]);
+        }
+        table.print();
+        if disorder_count > disordered.len() {
+            println!("  ... 还有 {} 个乱序包 (使用 --verbose 查看全部)", disorder_count - disordered.len());
         }
     }
 
-    // 结果报告（保持不变）
-    if disorder_count == 0 && read_errors == 0 {
-        info!("✅ 未检测到乱序包 (共 {} 个数据包)", packet_count);
+    print_separator();
+    if disorder_count == 0 && unread.is_none() {
+        print_verdict(Severity::Ok, &format!("未检测到乱序包 (共 {} 个数据包)", packet_count));
     } else {
         if disorder_count > 0 {
-            error!("⚠️ 检测到 {} 个乱序包", disorder_count);
+            print_verdict(Severity::Error, &format!("检测到 {} 个乱序包", disorder_count));
         }
-        if read_errors > 0 {
-            error!("⚠️ 检测到 {} 个读取错误", read_errors);
+        if unread.is_some() {
+            print_verdict(Severity::Error, "文件未完全读取");
         }
-        info!("共处理 {} 个数据包", packet_count);
+    }
+    if duplicate_count > 0 && resolution_usec > 1 {
+        print_verdict(
+            Severity::Warning,
+            &format!("时间戳均为 {} 微秒的整数倍，抓包时钟精度可能较粗", resolution_usec),
+        );
+    }
+    // 报告之后再以截断错误退出，便于调用方区分
+    if let Some(error) = unread {
+        return Err(error.into());
+    }
+
+    if let Some(annotate_path) = annotate_path {
+        write_annotated(input_path, annotate_path, &comments, "pcap-editor disorder-detect")?;
+        info!("成功输出带注释的副本: 注释的包数={}, 输出文件={}", comments.len(), annotate_path);
     }
     
     Ok(())
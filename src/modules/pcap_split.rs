@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{Output, compression, create_output};
+use crate::modules::pcap_pcapng::{
+    CaptureReader, IF_NAME, InterfaceDescription, PcapngBlock, PcapngOption, PcapngWriter, SHB_USERAPPL, option_str,
+};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 一个接口的输出文件
+struct SplitOutput {
+    path: String,
+    writer: PcapngWriter<BufWriter<Output>>,
+    /// 已写入该文件的接口描述 (下标为输出文件中的接口ID)
+    interfaces: Vec<InterfaceDescription>,
+    packet_count: u64,
+    digest: OutputDigest,
+}
+
+/// 按抓包接口拆分文件，每个接口描述块 (IDB) 输出一个pcapng文件
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_dir`: 输出目录 (不存在时创建)
+///
+/// # 说明
+/// 1. 文件名为 `<输入文件名>_<接口名>.pcapng`，接口名取自 if_name，没有时为 `if<接口ID>`；
+///    文件名中不能使用的字符替换为 `_`
+/// 2. 每个输出保留输入的节头选项、接口描述及其选项、包的注释等选项，接口ID重新从0开始编号
+/// 3. 接口统计块写入对应接口的文件，名称解析等其他块写入当时已有的全部输出
+/// 4. 多个节中同名的接口写入同一个文件 (如 `dumpcap -b` 轮转后合并的文件)
+/// 5. 不使用 `index` 生成的索引: 索引只支持PCAP文件，而PCAP文件只有一个接口，拆分总要顺序读取全部数据包
+pub fn pcap_split_by_interface(input_path: &str, output_dir: &str) -> Result<()> {
+    fs::create_dir_all(Path::new(output_dir))
+        .map_err(|e| PcapError::WriteFailure(format!("无法创建输出目录: {}: {}", output_dir, e)))?;
+    let stem = Path::new(input_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "capture".to_string());
+
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut section_options: Vec<PcapngOption> = Vec::new();
+    let mut outputs: Vec<SplitOutput> = Vec::new();
+    // 接口名 -> 输出文件的下标
+    let mut output_names: HashMap<String, usize> = HashMap::new();
+    // 当前节的接口ID -> (输出文件的下标, 输出文件中的接口ID)
+    let mut section_interfaces: Vec<(usize, u32)> = Vec::new();
+    let mut packet_count = 0u64;
+    while let Some(block) = reader.next_block()? {
+        match block {
+            PcapngBlock::Section(options) => {
+                if outputs.is_empty() {
+                    section_options = options;
+                    if section_options.is_empty() {
+                        section_options.push(PcapngOption::string(SHB_USERAPPL, "pcap-editor split"));
+                    }
+                }
+                section_interfaces.clear();
+            }
+            PcapngBlock::Interface(interface) => {
+                let name = option_str(&interface.options, IF_NAME)
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or_else(|| format!("if{}", section_interfaces.len()));
+                let index = match output_names.get(&name) {
+                    Some(&index) => index,
+                    None => {
+                        let path = Path::new(output_dir).join(format!("{}_{}.pcapng{}", stem, file_name_part(&name), compression().suffix()));
+                        let path = path.to_string_lossy().into_owned();
+                        if outputs.iter().any(|output| output.path == path) {
+                            anyhow::bail!(PcapError::InvalidArgument(format!(
+                                "接口名替换特殊字符后重复，无法区分输出文件: {}",
+                                path
+                            )));
+                        }
+                        let file = BufWriter::new(create_output(&path, compression())?);
+                        outputs.push(SplitOutput {
+                            writer: PcapngWriter::new(file, &section_options)?,
+                            path,
+                            interfaces: Vec::new(),
+                            packet_count: 0,
+                            digest: OutputDigest::default(),
+                        });
+                        output_names.insert(name, outputs.len() - 1);
+                        outputs.len() - 1
+                    }
+                };
+                let output = &mut outputs[index];
+                let local_id = match output.interfaces.iter().position(|existing| *existing == interface) {
+                    Some(local_id) => local_id,
+                    None => {
+                        output.writer.write_interface(&interface)?;
+                        output.interfaces.push(interface);
+                        output.interfaces.len() - 1
+                    }
+                };
+                section_interfaces.push((index, local_id as u32));
+            }
+            PcapngBlock::Packet(mut packet) => {
+                packet_count += 1;
+                let Some(&(index, local_id)) = section_interfaces.get(packet.interface_id as usize) else {
+                    anyhow::bail!(PcapError::InvalidFormat(format!(
+                        "数据包#{}引用了不存在的接口: {}",
+                        packet_count, packet.interface_id
+                    )));
+                };
+                let output = &mut outputs[index];
+                packet.interface_id = local_id;
+                output.writer.write_packet(&packet)?;
+                output.digest.record_pcapng(&packet, output.interfaces.get(local_id as usize));
+                output.packet_count += 1;
+            }
+            PcapngBlock::Statistics(mut statistics) => {
+                if let Some(&(index, local_id)) = section_interfaces.get(statistics.interface_id as usize) {
+                    statistics.interface_id = local_id;
+                    outputs[index].writer.write_statistics(&statistics)?;
+                }
+            }
+            block => {
+                for output in &mut outputs {
+                    output.writer.write_block(&block)?;
+                }
+            }
+        }
+    }
+    if outputs.is_empty() {
+        anyhow::bail!(PcapError::InvalidFormat("输入文件中没有接口描述块".to_string()));
+    }
+
+    let output_count = outputs.len();
+    for output in outputs {
+        output.writer.into_inner().into_inner()
+            .map_err(|e| PcapError::WriteFailure(format!("写入pcapng文件失败: {}", e.error())))?
+            .finish()?;
+        output.digest.verify(&output.path)?;
+        info!("接口输出: 包数={}, 输出文件={}", output.packet_count, output.path);
+    }
+    info!(
+        "成功按接口拆分: 包数={}, 接口数={}, 输出目录={}",
+        packet_count,
+        output_count,
+        output_dir
+    );
+
+    Ok(())
+}
+
+/// 接口名转为可用作文件名的字符串 (如 Windows 的 `\Device\NPF_{...}`)
+fn file_name_part(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect()
+}
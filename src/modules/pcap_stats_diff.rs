@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashSet};
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{FlowKey, IP_PROTO_ICMP, IP_PROTO_ICMPV6, IP_PROTO_TCP, IP_PROTO_UDP, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{Severity, Table, print_fields, print_heading, print_section, print_separator, print_verdict};
+
+/// 协议分布中的类别 (按列出的顺序)
+const PROTOCOLS: [&str; 7] = ["IPv4 TCP", "IPv4 UDP", "ICMP", "IPv6 TCP", "IPv6 UDP", "ICMPv6", "其他IP"];
+
+/// 非IP包的类别
+const NON_IP: &str = "非IP";
+
+/// 一个抓包的统计
+struct CaptureProfile {
+    packets: u64,
+    bytes: u64,
+    duration_us: u64,
+    flows: usize,
+    /// 类别 -> (包数, 字节数)
+    protocols: BTreeMap<&'static str, (u64, u64)>,
+    /// 每个区间的 (包数, 字节数)，包括首末包之间没有包的区间
+    intervals: Vec<(u64, u64)>,
+}
+
+fn protocol_class(version: u8, protocol: u8) -> &'static str {
+    match (version, protocol) {
+        (4, IP_PROTO_TCP) => "IPv4 TCP",
+        (4, IP_PROTO_UDP) => "IPv4 UDP",
+        (_, IP_PROTO_ICMP) => "ICMP",
+        (6, IP_PROTO_TCP) => "IPv6 TCP",
+        (6, IP_PROTO_UDP) => "IPv6 UDP",
+        (_, IP_PROTO_ICMPV6) => "ICMPv6",
+        _ => "其他IP",
+    }
+}
+
+fn profile(input_path: &str, interval_us: u64) -> Result<CaptureProfile> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut profile = CaptureProfile {
+        packets: 0,
+        bytes: 0,
+        duration_us: 0,
+        flows: 0,
+        protocols: BTreeMap::new(),
+        intervals: Vec::new(),
+    };
+    let mut flows: HashSet<FlowKey> = HashSet::new();
+    let mut first_us = None;
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = (interface.timestamp_ns(packet.timestamp) / 1000) as i64;
+        let first_us = *first_us.get_or_insert(time_us);
+        // 早于第一个包的乱序包计入第一个区间
+        let relative_us = (time_us - first_us).max(0) as u64;
+        profile.duration_us = profile.duration_us.max(relative_us);
+        let bytes = packet.orig_len as u64;
+        profile.packets += 1;
+        profile.bytes += bytes;
+
+        let parsed = parse_packet(&packet.data, interface.linktype as u32);
+        let class = match &parsed.ip {
+            Some(ip) => protocol_class(ip.version, ip.protocol),
+            None => NON_IP,
+        };
+        let counts = profile.protocols.entry(class).or_default();
+        counts.0 += 1;
+        counts.1 += bytes;
+        if let Some(key) = parsed.flow_key() {
+            flows.insert(key.canonical());
+        }
+
+        let index = relative_us.checked_div(interval_us).unwrap_or(0) as usize;
+        if profile.intervals.len() <= index {
+            profile.intervals.resize(index + 1, (0, 0));
+        }
+        profile.intervals[index].0 += 1;
+        profile.intervals[index].1 += bytes;
+    }
+    if first_us.is_none() {
+        anyhow::bail!(PcapError::EmptyCapture);
+    }
+    profile.flows = flows.len();
+    Ok(profile)
+}
+
+impl CaptureProfile {
+    fn duration_secs(&self) -> f64 {
+        self.duration_us as f64 / 1e6
+    }
+
+    /// 平均速率 (每秒)；只有一个时刻的抓包没有速率
+    fn average_rate(&self, count: u64) -> Option<f64> {
+        (self.duration_us > 0).then(|| count as f64 / self.duration_secs())
+    }
+
+    /// 每个区间的包速率和比特率的分位数 (最近秩法)
+    fn rate_percentile(&self, interval_us: u64, percent: f64) -> (f64, f64) {
+        let seconds = interval_us as f64 / 1e6;
+        let mut packets: Vec<u64> = self.intervals.iter().map(|(packets, _)| *packets).collect();
+        let mut bytes: Vec<u64> = self.intervals.iter().map(|(_, bytes)| *bytes).collect();
+        packets.sort_unstable();
+        bytes.sort_unstable();
+        let rank = ((percent / 100.0 * packets.len() as f64).ceil() as usize).clamp(1, packets.len()) - 1;
+        (packets[rank] as f64 / seconds, bytes[rank] as f64 * 8.0 / seconds)
+    }
+}
+
+/// 变化量和变化率，`decimals` 为小数位数
+fn delta(a: f64, b: f64, decimals: usize) -> (String, String) {
+    let change = b - a;
+    let rate = if a != 0.0 {
+        format!("{:+.2}%", change * 100.0 / a)
+    } else if change == 0.0 {
+        "0.00%".to_string()
+    } else {
+        "-".to_string()
+    };
+    (format!("{:+.decimals$}", change), rate)
+}
+
+/// 对两个抓包计算相同的统计并输出差异，用于在逐包比较之前回答"变了什么"
+///
+/// # 参数
+/// - `reference_path`: 基准文件路径 (PCAP或pcapng)
+/// - `comparison_path`: 对比文件路径 (PCAP或pcapng)
+/// - `interval_us`: 计算速率分位数的区间长度 (微秒)，0表示不计算
+///
+/// # 输出
+/// 1. 包数、字节数、时长、平均速率、平均包长和流数 (规范化五元组) 的对比和变化率
+/// 2. 协议分布: 每一类的包数和占比，以及占比变化的百分点
+/// 3. 每个区间的包速率和比特率的 p50/p95/p99/最大值 (首末包之间没有包的区间计为0)
+pub fn pcap_stats_diff(reference_path: &str, comparison_path: &str, interval_us: u64) -> Result<()> {
+    let a = profile(reference_path, interval_us)?;
+    let b = profile(comparison_path, interval_us)?;
+
+    print_heading(&format!("统计差异: {} -> {}", reference_path, comparison_path));
+    print_fields(&[
+        ("基准文件 (A)", reference_path.to_string(), None),
+        ("对比文件 (B)", comparison_path.to_string(), None),
+    ]);
+
+    print_section("总体:");
+    let mut table = Table::new(&["指标", "A", "B", "变化", "变化率"]).right_align(&[1, 2, 3, 4]);
+    let mut add_row = |name: &str, a: Option<f64>, b: Option<f64>, decimals: usize| {
+        let format = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{:.decimals$}", value));
+        let (change, rate) = match (a, b) {
+            (Some(a), Some(b)) => delta(a, b, decimals),
+            _ => ("-".to_string(), "-".to_string()),
+        };
+        table.add_row(vec![name.to_string(), format(a), format(b), change, rate]);
+    };
+    add_row("包数", Some(a.packets as f64), Some(b.packets as f64), 0);
+    add_row("字节数", Some(a.bytes as f64), Some(b.bytes as f64), 0);
+    add_row("时长 (秒)", Some(a.duration_secs()), Some(b.duration_secs()), 6);
+    add_row("平均包速率 (包/秒)", a.average_rate(a.packets), b.average_rate(b.packets), 1);
+    add_row("平均比特率 (比特/秒)", a.average_rate(a.bytes * 8), b.average_rate(b.bytes * 8), 0);
+    add_row("平均包长 (字节)", Some(a.bytes as f64 / a.packets as f64), Some(b.bytes as f64 / b.packets as f64), 1);
+    add_row("流数", Some(a.flows as f64), Some(b.flows as f64), 0);
+    table.print();
+
+    print_section("协议分布:");
+    let mut table = Table::new(&["协议", "A 包数", "A 占比", "B 包数", "B 占比", "包数变化", "占比变化"])
+        .right_align(&[1, 2, 3, 4, 5, 6]);
+    let share = |profile: &CaptureProfile, packets: u64| packets as f64 * 100.0 / profile.packets as f64;
+    for class in PROTOCOLS.iter().chain([&NON_IP]) {
+        let count_a = a.protocols.get(class).map_or(0, |counts| counts.0);
+        let count_b = b.protocols.get(class).map_or(0, |counts| counts.0);
+        if count_a == 0 && count_b == 0 {
+            continue;
+        }
+        table.add_row(vec![
+            class.to_string(),
+            count_a.to_string(),
+            format!("{:.2}%", share(&a, count_a)),
+            count_b.to_string(),
+            format!("{:.2}%", share(&b, count_b)),
+            format!("{:+}", count_b as i64 - count_a as i64),
+            format!("{:+.2} 百分点", share(&b, count_b) - share(&a, count_a)),
+        ]);
+    }
+    table.print();
+
+    if interval_us > 0 {
+        print_section(&format!("速率分位数 (每 {} 秒区间):", interval_us as f64 / 1e6));
+        let mut table = Table::new(&["分位", "A 包/秒", "B 包/秒", "变化率", "A 比特/秒", "B 比特/秒", "变化率"])
+            .right_align(&[1, 2, 3, 4, 5, 6]);
+        for (name, percent) in [("p50", 50.0), ("p95", 95.0), ("p99", 99.0), ("最大", 100.0)] {
+            let (pps_a, bps_a) = a.rate_percentile(interval_us, percent);
+            let (pps_b, bps_b) = b.rate_percentile(interval_us, percent);
+            table.add_row(vec![
+                name.to_string(),
+                format!("{:.1}", pps_a),
+                format!("{:.1}", pps_b),
+                delta(pps_a, pps_b, 1).1,
+                format!("{:.0}", bps_a),
+                format!("{:.0}", bps_b),
+                delta(bps_a, bps_b, 0).1,
+            ]);
+        }
+        table.print();
+    }
+
+    print_separator();
+    if a.packets == b.packets && a.bytes == b.bytes {
+        print_verdict(Severity::Ok, "两个抓包的包数和字节数相同 (内容是否相同请用 compare 检查)");
+    } else {
+        let difference = |a: u64, b: u64| {
+            let change = b as i64 - a as i64;
+            format!("{} {} ({:+.2}%)", if change < 0 { "少" } else { "多" }, change.unsigned_abs(), change as f64 * 100.0 / a as f64)
+        };
+        print_verdict(
+            Severity::Warning,
+            &format!(
+                "B 比 A 包数{}，字节数{} (使用 compare 查看具体的包)",
+                difference(a.packets, b.packets),
+                difference(a.bytes, b.bytes)
+            ),
+        );
+    }
+    Ok(())
+}
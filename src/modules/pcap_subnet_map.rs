@@ -0,0 +1,87 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::modules::pcap_filter::in_network;
+use crate::modules::pcap_packet_parser::{ParsedPacket, set_ip_addresses};
+
+/// 网段映射: 替换地址的网络前缀，保留主机位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubnetMapping {
+    pub from: IpAddr,
+    pub to: IpAddr,
+    pub prefix: u8,
+}
+
+impl SubnetMapping {
+    /// 解析 原网段=新网段 (如 10.12.0.0/16=172.31.0.0/16，新网段的前缀长度可省略，用于命令行参数)
+    pub fn parse(text: &str) -> Result<SubnetMapping, String> {
+        let (from, to) = text.split_once('=').ok_or_else(|| format!("应为 原网段=新网段: {}", text))?;
+        let (from, prefix) = parse_network(from.trim())?;
+        let prefix = prefix.ok_or_else(|| format!("原网段必须带前缀长度: {}", from))?;
+        let (to, to_prefix) = parse_network(to.trim())?;
+        if from.is_ipv4() != to.is_ipv4() {
+            return Err(format!("两个网段的地址族不同: {}", text));
+        }
+        if to_prefix.is_some_and(|to_prefix| to_prefix != prefix) {
+            return Err(format!("两个网段的前缀长度必须相同: {}", text));
+        }
+        Ok(SubnetMapping { from, to, prefix })
+    }
+
+    /// 属于原网段的地址换成新网段中主机位相同的地址
+    fn translate(&self, addr: IpAddr) -> Option<IpAddr> {
+        if !in_network(addr, self.from, self.prefix) {
+            return None;
+        }
+        Some(match (addr, self.to) {
+            (IpAddr::V4(addr), IpAddr::V4(to)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                IpAddr::V4(Ipv4Addr::from((u32::from(to) & mask) | (u32::from(addr) & !mask)))
+            }
+            (IpAddr::V6(addr), IpAddr::V6(to)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from((u128::from(to) & mask) | (u128::from(addr) & !mask)))
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// 解析 地址[/前缀长度]
+fn parse_network(text: &str) -> Result<(IpAddr, Option<u8>), String> {
+    let (addr, prefix) = match text.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (text, None),
+    };
+    let addr: IpAddr = addr.parse().map_err(|_| format!("无效的IP地址: {}", text))?;
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => Some(prefix.parse().ok().filter(|&p| p <= max_prefix).ok_or_else(|| format!("无效的前缀长度: {}", text))?),
+        None => None,
+    };
+    Ok((addr, prefix))
+}
+
+/// 按网段映射改写一个包的源/目的IP地址
+///
+/// 每个地址独立查找，多个网段都匹配时使用前缀最长的一个；所有映射同时生效，每个地址最多替换一次。
+/// 改写后同步更新IP和TCP/UDP校验和，返回每个地址使用的映射下标 (源, 目的)。
+pub fn map_subnets(mappings: &[SubnetMapping], data: &mut [u8], parsed: &ParsedPacket) -> (Option<usize>, Option<usize>) {
+    let Some(ip) = parsed.ip.as_ref() else {
+        return (None, None);
+    };
+    let lookup = |addr: IpAddr| {
+        mappings
+            .iter()
+            .enumerate()
+            .filter_map(|(i, mapping)| mapping.translate(addr).map(|new| (i, mapping.prefix, new)))
+            .max_by_key(|&(i, prefix, _)| (prefix, std::cmp::Reverse(i)))
+            .map(|(i, _, new)| (i, new))
+    };
+    let (src, dst) = (lookup(ip.src), lookup(ip.dst));
+    if src.is_some() || dst.is_some() {
+        let new_src = src.map_or(ip.src, |(_, addr)| addr);
+        let new_dst = dst.map_or(ip.dst, |(_, addr)| addr);
+        set_ip_addresses(data, parsed, new_src, new_dst);
+    }
+    (src.map(|(i, _)| i), dst.map(|(i, _)| i))
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use crate::modules::pcap_packet_parser::{
+    FlowKey, ParsedPacket, Transport, replace_field, set_ip_total_len, update_l4_checksum,
+};
+
+/// TCP选项类型
+const TCP_OPT_EOL: u8 = 0;
+const TCP_OPT_NOP: u8 = 1;
+const TCP_OPT_SACK: u8 = 5;
+
+const TCP_FLAG_SYN: u8 = 0x02;
+const TCP_FLAG_ACK: u8 = 0x10;
+
+/// 可按名称指定的TCP选项 (`sack` 同时包括SACK允许和SACK块)
+const TCP_OPTION_NAMES: [(&str, &[u8]); 5] = [
+    ("mss", &[2]),
+    ("wscale", &[3]),
+    ("sack", &[4, 5]),
+    ("timestamps", &[8]),
+    ("tfo", &[34]),
+];
+
+/// 要删除的TCP选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TcpOptionStrip {
+    All,
+    Kinds(Vec<u8>),
+}
+
+impl TcpOptionStrip {
+    /// 解析逗号分隔的选项名或类型编号，`all` 表示全部 (用于命令行参数)
+    pub fn parse(text: &str) -> Result<TcpOptionStrip, String> {
+        let mut kinds = Vec::new();
+        for item in text.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let item = item.to_ascii_lowercase();
+            if item == "all" {
+                return Ok(TcpOptionStrip::All);
+            }
+            match TCP_OPTION_NAMES.iter().find(|(name, _)| *name == item) {
+                Some((_, values)) => kinds.extend_from_slice(values),
+                None => kinds.push(item.parse().map_err(|_| {
+                    let names: Vec<&str> = TCP_OPTION_NAMES.iter().map(|(name, _)| *name).collect();
+                    format!("未知的TCP选项: {} (可选 all/{} 或类型编号)", item, names.join("/"))
+                })?),
+            }
+        }
+        if kinds.is_empty() {
+            return Err("至少需要指定一个TCP选项".to_string());
+        }
+        Ok(TcpOptionStrip::Kinds(kinds))
+    }
+
+    fn strips(&self, kind: u8) -> bool {
+        match self {
+            TcpOptionStrip::All => true,
+            TcpOptionStrip::Kinds(kinds) => kinds.contains(&kind),
+        }
+    }
+}
+
+/// 删除TCP选项，缩短TCP头并更新数据偏移、IP长度和校验和
+///
+/// 保留的选项按原顺序排列，末尾用EOL补齐到4字节；返回删除的字节数。
+/// 选项格式错误或数据被截断 (无法重新计算校验和) 时不修改，返回None。
+pub fn strip_tcp_options(data: &mut Vec<u8>, parsed: &ParsedPacket, strip: &TcpOptionStrip) -> Option<usize> {
+    let Some(Transport::Tcp(tcp)) = parsed.transport else {
+        return None;
+    };
+    let ip = parsed.ip.as_ref()?;
+    let options_start = tcp.offset + 20;
+    let options_end = tcp.offset + tcp.header_len;
+    if options_end == options_start || ip.end() > data.len() {
+        return None;
+    }
+
+    let mut kept = Vec::new();
+    let mut changed = false;
+    let mut pos = options_start;
+    while pos < options_end {
+        let kind = data[pos];
+        match kind {
+            TCP_OPT_EOL => break,
+            TCP_OPT_NOP => {
+                pos += 1;
+                continue;
+            }
+            _ => {}
+        }
+        let len = *data.get(pos + 1)? as usize;
+        if len < 2 || pos + len > options_end {
+            return None;
+        }
+        if strip.strips(kind) {
+            changed = true;
+        } else {
+            kept.extend_from_slice(&data[pos..pos + len]);
+        }
+        pos += len;
+    }
+    if !changed {
+        return Some(0);
+    }
+
+    kept.resize(kept.len().div_ceil(4) * 4, TCP_OPT_EOL);
+    let removed = (options_end - options_start) - kept.len();
+    data.splice(options_start..options_end, kept);
+
+    let header_len = tcp.header_len - removed;
+    data[tcp.offset + 12] = (data[tcp.offset + 12] & 0x0f) | ((header_len / 4) as u8) << 4;
+    set_ip_total_len(data, parsed, ip.total_len - removed);
+
+    // 长度变化后偏移保持不变，只有结束位置前移
+    let mut shrunk = parsed.clone();
+    if let Some(ip) = shrunk.ip.as_mut() {
+        ip.total_len -= removed;
+    }
+    update_l4_checksum(data, &shrunk);
+    Some(removed)
+}
+
+/// 把每个TCP流的序列号和确认号平移到固定的起始值
+///
+/// 每个方向以首次出现的序列号为基准 (SYN包的序列号，抓包时连接已建立则为第一个包的序列号)，
+/// 确认号和SACK块按对端方向的基准平移。新的SYN表示连接重建，两个方向重新取基准。
+pub struct SeqNormalizer {
+    isn: u32,
+    bases: HashMap<FlowKey, u32>,
+}
+
+impl SeqNormalizer {
+    pub fn new(isn: u32) -> SeqNormalizer {
+        SeqNormalizer { isn, bases: HashMap::new() }
+    }
+
+    /// 改写一个包的序列号、确认号和SACK块并增量更新校验和，不是TCP包时返回false
+    pub fn normalize(&mut self, data: &mut [u8], parsed: &ParsedPacket) -> bool {
+        let Some(Transport::Tcp(tcp)) = parsed.transport else {
+            return false;
+        };
+        let Some(key) = parsed.flow_key() else {
+            return false;
+        };
+        let reverse = key.reversed();
+        let seq = read_u32(data, tcp.offset + 4);
+        let ack = read_u32(data, tcp.offset + 8);
+        let flags = data[tcp.offset + 13];
+
+        if flags & TCP_FLAG_SYN != 0 {
+            if flags & TCP_FLAG_ACK == 0 {
+                self.bases.remove(&reverse);
+            }
+            self.bases.insert(key, seq);
+        }
+        let seq_base = *self.bases.entry(key).or_insert(seq);
+        // 对端还没有发过包时，以确认号作为对端的基准
+        let ack_base = (flags & TCP_FLAG_ACK != 0).then(|| *self.bases.entry(reverse).or_insert(ack));
+
+        let checksum = [tcp.offset + 16];
+        let rebase = |value: u32, base: u32| value.wrapping_sub(base).wrapping_add(self.isn);
+        replace_field(data, tcp.offset + 4, &rebase(seq, seq_base).to_be_bytes(), &checksum);
+        let Some(ack_base) = ack_base else {
+            return true;
+        };
+        replace_field(data, tcp.offset + 8, &rebase(ack, ack_base).to_be_bytes(), &checksum);
+
+        // SACK块中的序列号属于对端方向
+        let options_end = (tcp.offset + tcp.header_len).min(data.len());
+        let mut pos = tcp.offset + 20;
+        while pos < options_end {
+            let kind = data[pos];
+            match kind {
+                TCP_OPT_EOL => break,
+                TCP_OPT_NOP => {
+                    pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(&len) = data.get(pos + 1) else {
+                break;
+            };
+            let len = len as usize;
+            if len < 2 || pos + len > options_end {
+                break;
+            }
+            if kind == TCP_OPT_SACK {
+                for edge in (pos + 2..pos + len - 3).step_by(4) {
+                    // 选项不一定16位对齐，按对齐的范围增量更新校验和
+                    let start = edge - (edge - tcp.offset) % 2;
+                    let end = (edge + 4 - start).div_ceil(2) * 2 + start;
+                    if end > data.len() {
+                        break;
+                    }
+                    let mut field = data[start..end].to_vec();
+                    let value = rebase(read_u32(data, edge), ack_base);
+                    field[edge - start..edge - start + 4].copy_from_slice(&value.to_be_bytes());
+                    replace_field(data, start, &field, &checksum);
+                }
+            }
+            pos += len;
+        }
+        true
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
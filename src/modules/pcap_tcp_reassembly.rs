@@ -0,0 +1,77 @@
+use crate::modules::pcap_packet_parser::{TcpInfo, read_u32};
+
+const TCP_FLAG_SYN: u8 = 0x02;
+
+/// 一个方向的TCP数据段，按序列号重组
+#[derive(Default)]
+pub struct TcpStream {
+    /// SYN包的序列号
+    syn_seq: Option<u32>,
+    /// (序列号, 到达时间 (微秒), 数据)
+    segments: Vec<(u32, i64, Vec<u8>)>,
+}
+
+/// 一个方向的重组结果
+pub struct Reassembled {
+    pub data: Vec<u8>,
+    /// 缺失 (没有抓到) 的字节数
+    pub missing: u64,
+    /// 每一段数据在 `data` 中的起始偏移和到达时间 (按偏移升序)
+    times: Vec<(usize, i64)>,
+}
+
+impl TcpStream {
+    /// 记录一个TCP包 (`frame` 为整个帧，`payload` 为其中的TCP载荷)
+    pub fn add(&mut self, frame: &[u8], tcp: &TcpInfo, payload: &[u8], time_us: i64) {
+        let seq = read_u32(frame, tcp.offset + 4);
+        if frame[tcp.offset + 13] & TCP_FLAG_SYN != 0 {
+            self.syn_seq = Some(seq);
+        }
+        if !payload.is_empty() {
+            self.segments.push((seq, time_us, payload.to_vec()));
+        }
+    }
+
+    /// 按序列号重组，重传和重叠的数据只保留先到的一份，缺失的部分跳过
+    ///
+    /// 抓到SYN时从SYN之后的序列号开始，否则从序列号最小的段开始。
+    pub fn reassemble(&self) -> Reassembled {
+        let mut reassembled = Reassembled { data: Vec::new(), missing: 0, times: Vec::new() };
+        let Some(first) = self.segments.first() else {
+            return reassembled;
+        };
+        let base = self.syn_seq.map(|seq| seq.wrapping_add(1)).unwrap_or(first.0);
+
+        // 相对起始序列号的偏移，抓包开始前的段可能为负
+        let mut segments: Vec<(i64, i64, &[u8])> = self.segments
+            .iter()
+            .map(|(seq, time_us, data)| (seq.wrapping_sub(base) as i32 as i64, *time_us, data.as_slice()))
+            .collect();
+        segments.sort_by_key(|&(offset, _, _)| offset);
+
+        let mut cursor = if self.syn_seq.is_some() { 0 } else { segments[0].0 };
+        for (offset, time_us, segment) in segments {
+            let end = offset + segment.len() as i64;
+            if end <= cursor {
+                continue;
+            }
+            reassembled.times.push((reassembled.data.len(), time_us));
+            if offset > cursor {
+                reassembled.missing += (offset - cursor) as u64;
+                reassembled.data.extend_from_slice(segment);
+            } else {
+                reassembled.data.extend_from_slice(&segment[(cursor - offset) as usize..]);
+            }
+            cursor = end;
+        }
+        reassembled
+    }
+}
+
+impl Reassembled {
+    /// 重组数据中某个偏移处的字节到达的时间
+    pub fn time_at(&self, offset: usize) -> Option<i64> {
+        let index = self.times.partition_point(|&(start, _)| start <= offset);
+        index.checked_sub(1).map(|index| self.times[index].1)
+    }
+}
@@ -0,0 +1,193 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::net::IpAddr;
+use pcap_file::Packet;
+use anyhow::Result;
+use log::info;
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{FlowKey, parse_packet, set_ip_addresses, set_ports};
+use crate::modules::pcap_time_anchor::ticks_per_second;
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 以现有流为模板生成新的流
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `copies`: 每个流生成的新流数量
+/// - `time_shift`: 第k个副本相对原始流的时间偏移为 k * time_shift (秒)
+/// - `addr_step`: 第k个副本的客户端地址增加 k * addr_step
+/// - `port_step`: 第k个副本的客户端端口增加 k * port_step
+///
+/// # 功能
+/// 1. 以流的第一个包的源端作为客户端，只改写客户端地址和端口，服务端保持不变
+/// 2. 保持原始的包长和包间隔，校验和同步更新
+/// 3. 原始流与所有副本按时间戳合并输出，非IP包只保留原始的一份
+pub fn pcap_template_expand(
+    input_path: &str,
+    output_path: &str,
+    copies: usize,
+    time_shift: f64,
+    addr_step: u32,
+    port_step: u16,
+) -> Result<()> {
+    if copies < 1 {
+        anyhow::bail!(PcapError::InvalidArgument(format!("副本数量必须大于0，当前为: {}", copies)));
+    }
+    if time_shift < 0.0 {
+        anyhow::bail!(PcapError::InvalidArgument(format!("时间偏移不能为负数，当前为: {}", time_shift)));
+    }
+
+    // 第一遍: 记录每个流的发起方向
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let header = pcap_reader.header;
+    let mut initiators: HashMap<FlowKey, FlowKey> = HashMap::new();
+    let mut packet_count = 0usize;
+    for packet in pcap_reader.by_ref() {
+        packet_count += 1;
+        if let Some(key) = parse_packet(&packet.data, linktype).flow_key() {
+            initiators.entry(key.canonical()).or_insert(key);
+        }
+    }
+    pcap_reader.check_complete()?;
+    if packet_count == 0 {
+        anyhow::bail!(PcapError::EmptyCapture);
+    }
+
+    // 创建输出文件
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    // 第二遍: 每个副本各用一个读取器，按时间戳多路合并
+    // 时间以文件的精度计 (微秒或纳秒)
+    let ticks_per_second = ticks_per_second(&header) as u64;
+    let shift = (time_shift * ticks_per_second as f64).round() as u64;
+    let mut streams = Vec::with_capacity(copies + 1);
+    for copy in 0..=copies {
+        streams.push(TemplateStream {
+            reader: PacketReader::open(input_path)?,
+            copy: copy as u32,
+            shift: shift * copy as u64,
+            ticks_per_second,
+        });
+    }
+
+    let mut heads: Vec<Option<Packet<'static>>> = Vec::with_capacity(streams.len());
+    let mut heap = BinaryHeap::new();
+    for (index, stream) in streams.iter_mut().enumerate() {
+        let head = stream.next_packet(&initiators, linktype, addr_step, port_step);
+        if let Some(packet) = &head {
+            heap.push(Reverse((timestamp_ticks(packet, ticks_per_second), index)));
+        }
+        heads.push(head);
+    }
+
+    let mut written = 0usize;
+    while let Some(Reverse((_, index))) = heap.pop() {
+        let packet = heads[index].take().unwrap();
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&packet);
+        written += 1;
+
+        heads[index] = streams[index].next_packet(&initiators, linktype, addr_step, port_step);
+        if let Some(packet) = &heads[index] {
+            heap.push(Reverse((timestamp_ticks(packet, ticks_per_second), index)));
+        }
+    }
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!(
+        "成功生成流模板扩展文件: 原始包数={}, 原始流数={}, 每流副本数={}, 输出包数={}",
+        packet_count,
+        initiators.len(),
+        copies,
+        written
+    );
+
+    Ok(())
+}
+
+/// 单个副本的数据流
+struct TemplateStream {
+    reader: PacketReader,
+    /// 副本编号，0表示原始流
+    copy: u32,
+    /// 时间偏移 (以文件的精度计)
+    shift: u64,
+    ticks_per_second: u64,
+}
+
+impl TemplateStream {
+    /// 读取并改写该副本的下一个包
+    fn next_packet(
+        &mut self,
+        initiators: &HashMap<FlowKey, FlowKey>,
+        linktype: u32,
+        addr_step: u32,
+        port_step: u16,
+    ) -> Option<Packet<'static>> {
+        for mut packet in self.reader.by_ref() {
+            if self.copy == 0 {
+                return Some(packet);
+            }
+
+            let parsed = parse_packet(&packet.data, linktype);
+            let key = match parsed.flow_key() {
+                Some(key) => key,
+                // 非IP包只在原始流中保留
+                None => continue,
+            };
+            let initiator = initiators[&key.canonical()];
+            let from_client = key == initiator;
+
+            let client_addr = shift_addr(initiator.src, self.copy.wrapping_mul(addr_step));
+            let client_port = shift_port(initiator.src_port, port_step.wrapping_mul(self.copy as u16));
+
+            let data = packet.data.to_mut();
+            if from_client {
+                set_ip_addresses(data, &parsed, client_addr, key.dst);
+                set_ports(data, &parsed, client_port, key.dst_port);
+            } else {
+                set_ip_addresses(data, &parsed, key.src, client_addr);
+                set_ports(data, &parsed, key.src_port, client_port);
+            }
+
+            let ts = timestamp_ticks(&packet, self.ticks_per_second) + self.shift;
+            packet.header.ts_sec = (ts / self.ticks_per_second) as u32;
+            packet.header.ts_usec = (ts % self.ticks_per_second) as u32;
+            return Some(packet);
+        }
+        None
+    }
+}
+
+fn timestamp_ticks(packet: &Packet, ticks_per_second: u64) -> u64 {
+    packet.header.ts_sec as u64 * ticks_per_second + packet.header.ts_usec as u64
+}
+
+/// 地址按整数值偏移 (回绕)
+fn shift_addr(addr: IpAddr, step: u32) -> IpAddr {
+    match addr {
+        IpAddr::V4(v4) => IpAddr::V4(u32::from(v4).wrapping_add(step).into()),
+        IpAddr::V6(v6) => IpAddr::V6(u128::from(v6).wrapping_add(step as u128).into()),
+    }
+}
+
+/// 端口偏移，保持0端口 (无端口协议) 不变并避开0
+fn shift_port(port: u16, step: u16) -> u16 {
+    if port == 0 {
+        return 0;
+    }
+    match port.wrapping_add(step) {
+        0 => 1,
+        shifted => shifted,
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_handshake_report::client_to_server;
+use crate::modules::pcap_packet_parser::{FlowKey, Transport, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, format_timestamp_us, print_fields, print_heading, print_section, print_separator, print_verdict,
+    verbosity,
+};
+
+/// 异常结束列表中最多列出的连接数 (详细模式不限制)
+const MAX_ABNORMAL_ROWS: usize = 50;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+/// 连接一端的状态
+#[derive(Default)]
+struct Side {
+    fin: bool,
+    /// 最近一个非RST包的TTL/跳数限制
+    ttl: Option<u8>,
+}
+
+/// 一个TCP连接的结束情况
+struct TcpConnection {
+    /// 客户端到服务端的五元组
+    key: FlowKey,
+    first_ns: i128,
+    last_ns: i128,
+    packets: u64,
+    client: Side,
+    server: Side,
+    /// 第一个RST
+    reset: Option<Reset>,
+}
+
+/// 连接中的第一个RST
+struct Reset {
+    from_client: bool,
+    /// 包序号 (从1开始)
+    number: u64,
+    /// RST的TTL与同一方向之前的包不同时为 (RST的TTL, 之前的TTL)
+    changed_ttl: Option<(u8, u8)>,
+}
+
+/// 连接的结束方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Termination {
+    /// 双方都发出了FIN
+    Fin,
+    /// 只有一方发出了FIN
+    HalfClosed,
+    ResetByClient,
+    ResetByServer,
+    /// 没有FIN或RST，最后一个包之后空闲超过超时时间
+    Timeout,
+    /// 没有FIN或RST，抓包结束时连接仍然活动
+    Unterminated,
+}
+
+impl Termination {
+    fn name(self) -> &'static str {
+        match self {
+            Termination::Fin => "FIN/FIN",
+            Termination::HalfClosed => "单向FIN",
+            Termination::ResetByClient => "客户端RST",
+            Termination::ResetByServer => "服务端RST",
+            Termination::Timeout => "超时",
+            Termination::Unterminated => "未结束",
+        }
+    }
+
+    /// 是否为异常结束 (抓包结束时仍活动的连接不算)
+    fn abnormal(self) -> bool {
+        !matches!(self, Termination::Fin | Termination::Unterminated)
+    }
+}
+
+impl TcpConnection {
+    fn termination(&self, capture_end_ns: i128, timeout_ns: i128) -> Termination {
+        match &self.reset {
+            Some(reset) if reset.from_client => Termination::ResetByClient,
+            Some(_) => Termination::ResetByServer,
+            None if self.client.fin && self.server.fin => Termination::Fin,
+            None if self.client.fin || self.server.fin => Termination::HalfClosed,
+            None if capture_end_ns - self.last_ns > timeout_ns => Termination::Timeout,
+            None => Termination::Unterminated,
+        }
+    }
+
+    fn closed(&self) -> bool {
+        self.reset.is_some() || self.client.fin && self.server.fin
+    }
+}
+
+/// 按结束方式 (FIN/FIN、RST、超时、未结束) 对TCP连接分类，并列出异常结束的连接
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `idle_timeout_us`: 空闲超时，没有FIN或RST的连接在抓包结束前空闲超过该时间视为超时
+///
+/// # 说明
+/// 客户端和服务端的推断与 `check-handshake` 相同。连接空闲超过超时时间，或已关闭后出现新的SYN时，
+/// 之后的包开始新连接。RST的TTL (IPv6为跳数限制) 与同一方向之前的包不同时，RST可能由中间设备
+/// (防火墙、IPS、负载均衡) 代为发出，在列表中注明。
+pub fn check_termination(input_path: &str, idle_timeout_us: u64) -> Result<()> {
+    let timeout_ns = idle_timeout_us as i128 * 1000;
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut connections: Vec<TcpConnection> = Vec::new();
+    // 与方向无关的五元组 -> 当前连接
+    let mut active: HashMap<FlowKey, usize> = HashMap::new();
+    let mut packet_count = 0u64;
+    let mut capture_end_ns = i128::MIN;
+    // 第一个包的时间 (相对时间格式的起点)
+    let mut capture_start_ns = None;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_ns = interface.timestamp_ns(packet.timestamp);
+        capture_end_ns = capture_end_ns.max(time_ns);
+        capture_start_ns.get_or_insert(time_ns);
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let (Some(key), Some(ip), Some(Transport::Tcp(tcp))) = (parsed.flow_key(), &parsed.ip, parsed.transport) else {
+            continue;
+        };
+        if tcp.offset + 14 > data.len() {
+            continue;
+        }
+        let flags = data[tcp.offset + 13];
+        let ttl = data[ip.offset + if ip.version == 4 { 8 } else { 7 }];
+
+        let index = match active.get(&key.canonical()) {
+            Some(&index)
+                if time_ns - connections[index].last_ns <= timeout_ns
+                    && !(connections[index].closed() && flags & (TCP_SYN | TCP_ACK) == TCP_SYN) =>
+            {
+                index
+            }
+            _ => {
+                connections.push(TcpConnection {
+                    key: client_to_server(key, flags),
+                    first_ns: time_ns,
+                    last_ns: time_ns,
+                    packets: 0,
+                    client: Side::default(),
+                    server: Side::default(),
+                    reset: None,
+                });
+                active.insert(key.canonical(), connections.len() - 1);
+                connections.len() - 1
+            }
+        };
+
+        let connection = &mut connections[index];
+        let from_client = connection.key == key;
+        connection.packets += 1;
+        connection.first_ns = connection.first_ns.min(time_ns);
+        connection.last_ns = connection.last_ns.max(time_ns);
+        let side = if from_client { &mut connection.client } else { &mut connection.server };
+        if flags & TCP_RST != 0 {
+            if connection.reset.is_none() {
+                let changed_ttl = side.ttl.filter(|&previous| previous != ttl).map(|previous| (ttl, previous));
+                connection.reset = Some(Reset { from_client, number: packet_count, changed_ttl });
+            }
+        } else {
+            side.ttl = Some(ttl);
+        }
+        if flags & TCP_FIN != 0 {
+            side.fin = true;
+        }
+    }
+
+    let terminations: Vec<Termination> = connections
+        .iter()
+        .map(|connection| connection.termination(capture_end_ns, timeout_ns))
+        .collect();
+    let total = connections.len() as u64;
+    let count = |kind: Termination| terminations.iter().filter(|&&termination| termination == kind).count() as u64;
+    let percent = |part: u64| if total > 0 { part as f64 * 100.0 / total as f64 } else { 0.0 };
+    let abnormal = terminations.iter().filter(|termination| termination.abnormal()).count() as u64;
+    let ttl_resets = connections
+        .iter()
+        .filter(|connection| connection.reset.as_ref().is_some_and(|reset| reset.changed_ttl.is_some()))
+        .count() as u64;
+
+    print_heading(&format!("TCP连接结束方式: {}", input_path));
+    let mut fields = vec![
+        ("数据包数", packet_count.to_string(), None),
+        ("空闲超时", format!("{:.3} 秒", idle_timeout_us as f64 / 1_000_000.0), None),
+        ("TCP连接数", total.to_string(), None),
+    ];
+    for kind in [
+        Termination::Fin,
+        Termination::HalfClosed,
+        Termination::ResetByClient,
+        Termination::ResetByServer,
+        Termination::Timeout,
+        Termination::Unterminated,
+    ] {
+        let value = count(kind);
+        let severity = (kind.abnormal() && value > 0).then_some(Severity::Warning);
+        fields.push((kind.name(), format!("{} ({:.2}%)", value, percent(value)), severity));
+    }
+    fields.push(("TTL异常的RST", ttl_resets.to_string(), (ttl_resets > 0).then_some(Severity::Warning)));
+    print_fields(&fields);
+
+    if abnormal > 0 {
+        let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_ABNORMAL_ROWS };
+        let start_ns = capture_start_ns.unwrap_or_default();
+        let start_time = |ns: i128| format_timestamp_us(ns.div_euclid(1000) as i64, start_ns.div_euclid(1000) as i64);
+        print_section("异常结束的连接:");
+        let mut table = Table::new(&["五元组 (客户端 -> 服务端)", "结束方式", "开始时间", "持续时间 (秒)", "包数", "RST"])
+            .right_align(&[3, 4]);
+        let abnormal_connections = connections.iter().zip(&terminations).filter(|(_, termination)| termination.abnormal());
+        for (connection, termination) in abnormal_connections.take(max_rows) {
+            let note = match &connection.reset {
+                Some(Reset { number, changed_ttl: Some((ttl, previous)), .. }) => {
+                    format!("包#{}，TTL {} (之前为 {})，疑似中间设备", number, ttl, previous)
+                }
+                Some(reset) => format!("包#{}", reset.number),
+                None => String::new(),
+            };
+            table.add_row(vec![
+                connection.key.to_string(),
+                termination.name().to_string(),
+                start_time(connection.first_ns),
+                format!("{:.6}", (connection.last_ns - connection.first_ns) as f64 / 1e9),
+                connection.packets.to_string(),
+                note,
+            ]);
+        }
+        table.print();
+        if abnormal > max_rows as u64 {
+            println!("  ... 还有 {} 个连接 (使用 --verbose 查看全部)", abnormal - max_rows as u64);
+        }
+    }
+
+    print_separator();
+    if abnormal == 0 {
+        print_verdict(Severity::Ok, &format!("没有异常结束的连接 (共 {} 个TCP连接)", total));
+    } else {
+        print_verdict(
+            Severity::Warning,
+            &format!("{} 个连接异常结束 ({:.2}%)", abnormal, percent(abnormal)),
+        );
+        if ttl_resets > 0 {
+            print_verdict(
+                Severity::Warning,
+                &format!("{} 个RST的TTL与连接中的其他包不同，可能是中间设备断开了连接", ttl_resets),
+            );
+        }
+    }
+    Ok(())
+}
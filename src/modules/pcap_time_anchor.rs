@@ -0,0 +1,225 @@
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+use pcap_file::pcap_header::{PcapHeader, TsResolution};
+use anyhow::Result;
+
+use crate::modules::io::{OutputFormat, PacketReader, PacketWriter};
+use crate::modules::pcap_checkpoint::{StepProgress, StreamCheckpoint};
+use crate::modules::pcap_error::{PCAP_HEADER_LEN, PCAP_RECORD_HEADER_LEN, PcapError};
+use crate::modules::pcap_output::{Compression, Output, create_output};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 重新计时时保持不变的时间点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TimeAnchor {
+    /// 保持首包时间不变
+    #[default]
+    Start,
+    /// 保持末包时间不变
+    End,
+    /// 保持首末包的中点不变
+    Midpoint,
+}
+
+impl TimeAnchor {
+    /// 解析锚点名称 (用于流水线配置)
+    pub fn parse(text: &str) -> Result<TimeAnchor> {
+        match text {
+            "start" => Ok(TimeAnchor::Start),
+            "end" => Ok(TimeAnchor::End),
+            "midpoint" => Ok(TimeAnchor::Midpoint),
+            other => anyhow::bail!(PcapError::InvalidArgument(format!("未知的时间锚点: {} (可选 start/end/midpoint)", other))),
+        }
+    }
+
+    /// 锚点时间 (以文件的时间戳精度计)；`end`/`midpoint` 需要先扫描一遍文件找到末包
+    pub fn resolve_ticks(&self, input_path: &str) -> Result<i64> {
+        match self {
+            TimeAnchor::Start => capture_first(input_path),
+            TimeAnchor::End => Ok(capture_bounds(input_path)?.1),
+            TimeAnchor::Midpoint => {
+                let (first, last, _) = capture_bounds(input_path)?;
+                Ok(first + (last - first) / 2)
+            }
+        }
+    }
+}
+
+/// 首包时间戳 (以文件的时间戳精度计)
+fn capture_first(input_path: &str) -> Result<i64> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let ticks_per_second = ticks_per_second(&pcap_reader.header);
+    match pcap_reader.next() {
+        Some(packet) => Ok(timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second)),
+        None => anyhow::bail!(PcapError::EmptyCapture),
+    }
+}
+
+/// 首包和末包时间戳 (以文件的时间戳精度计) 和每秒的单位数
+pub fn capture_bounds(input_path: &str) -> Result<(i64, i64, i64)> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let ticks_per_second = ticks_per_second(&pcap_reader.header);
+    let first = match pcap_reader.next() {
+        Some(packet) => timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second),
+        None => anyhow::bail!(PcapError::EmptyCapture),
+    };
+    let last = pcap_reader
+        .by_ref()
+        .last()
+        .map(|packet| timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second))
+        .unwrap_or(first);
+    pcap_reader.check_complete()?;
+    Ok((first, last, ticks_per_second))
+}
+
+/// 首包和末包时间戳 (微秒)
+pub fn capture_bounds_us(input_path: &str) -> Result<(i64, i64)> {
+    let (first, last, ticks_per_second) = capture_bounds(input_path)?;
+    Ok((ticks_to_us(first, ticks_per_second), ticks_to_us(last, ticks_per_second)))
+}
+
+/// 每秒的时间戳单位数: 纳秒精度的PCAP文件 (magic 0xa1b23c4d) 为10^9，其他为10^6
+pub fn ticks_per_second(header: &PcapHeader) -> i64 {
+    match header.ts_resolution() {
+        TsResolution::NanoSecond => 1_000_000_000,
+        TsResolution::MicroSecond => 1_000_000,
+    }
+}
+
+/// 时间戳转为文件精度的单位数，`ts_frac` 为秒以下的部分 (微秒或纳秒)
+pub fn timestamp_ticks(ts_sec: u32, ts_frac: u32, ticks_per_second: i64) -> i64 {
+    ts_sec as i64 * ticks_per_second + ts_frac as i64
+}
+
+/// 文件精度的时间戳转为微秒 (向下取整)
+pub fn ticks_to_us(ticks: i64, ticks_per_second: i64) -> i64 {
+    ticks.div_euclid(ticks_per_second / 1_000_000)
+}
+
+/// 时间戳转为微秒，`ts_frac` 为秒以下的部分 (纳秒精度的文件向下取整)
+pub fn timestamp_to_us(ts_sec: u32, ts_frac: u32, ticks_per_second: i64) -> i64 {
+    ticks_to_us(timestamp_ticks(ts_sec, ts_frac, ticks_per_second), ticks_per_second)
+}
+
+/// 文件精度的时间戳拆分为 (秒, 秒以下的部分)，超出PCAP可表示范围时报错
+pub fn split_timestamp_ticks(ticks: i64, ticks_per_second: i64) -> Result<(u32, u32)> {
+    if ticks < 0 || ticks / ticks_per_second > u32::MAX as i64 {
+        let unit = if ticks_per_second == 1_000_000 { "微秒" } else { "纳秒" };
+        anyhow::bail!("调整后的时间戳超出PCAP可表示范围: {} {}", ticks, unit);
+    }
+    Ok(((ticks / ticks_per_second) as u32, (ticks % ticks_per_second) as u32))
+}
+
+/// 以锚点为中心按比例缩放时间戳，返回新的 (秒, 秒以下的部分)；时间戳和锚点都以文件的精度计
+pub fn scale_timestamp(
+    ts_sec: u32,
+    ts_frac: u32,
+    anchor_ticks: i64,
+    scale: f64,
+    ticks_per_second: i64,
+) -> Result<(u32, u32)> {
+    let offset = timestamp_ticks(ts_sec, ts_frac, ticks_per_second) - anchor_ticks;
+    split_timestamp_ticks(anchor_ticks + (offset as f64 * scale).round() as i64, ticks_per_second)
+}
+
+/// 微秒时间戳拆分为 (秒, 微秒)，超出PCAP可表示范围时报错
+pub fn split_timestamp_us(time_us: i64) -> Result<(u32, u32)> {
+    split_timestamp_ticks(time_us, 1_000_000)
+}
+
+/// 以锚点为中心按比例缩放所有数据包的时间戳 (时间压缩和拉伸共用)，返回包数
+///
+/// 输出按 `format` 写出、按 `compression` 压缩 (流水线的中间文件按最终输出的格式，不压缩)；
+/// 指定 `checkpoint` 时从其中的进度继续: 输出截断到已完成的长度，输入跳过已处理的包；
+/// 之后每处理 `interval` 个包同步输出文件并保存一次进度。
+pub fn rescale_capture(
+    input_path: &str,
+    output_path: &str,
+    anchor: TimeAnchor,
+    scale: f64,
+    format: OutputFormat,
+    compression: Compression,
+    mut checkpoint: Option<StreamCheckpoint>,
+) -> Result<u64> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let header = pcap_reader.header;
+
+    let resume = checkpoint.as_ref().map(|c| c.progress).filter(|progress| progress.packets > 0);
+    let out_file = match resume {
+        Some(progress) => {
+            let file = OpenOptions::new().write(true).open(Path::new(output_path))
+                .map_err(|e| PcapError::create_failed(output_path, e))?;
+            file.set_len(progress.output_len)
+                .map_err(|e| PcapError::WriteFailure(format!("无法截断输出文件: {}: {}", output_path, e)))?;
+            // 跳过已处理的包 (pcapng输入没有可以定位的PCAP偏移)
+            if pcap_reader.by_ref().take(progress.packets as usize).count() as u64 != progress.packets {
+                anyhow::bail!(PcapError::InvalidArgument(format!(
+                    "检查点记录的包数 ({}) 超过输入文件中的包数: {}",
+                    progress.packets, input_path
+                )));
+            }
+            // 只有不压缩的输出保存进度 (压缩的数据无法从中间截断后继续)
+            Output::new(file, Compression::None)
+                .map_err(|e| PcapError::create_failed(output_path, e))?
+        }
+        // 保存检查点时出错后保留已写出的部分以便继续
+        None if checkpoint.is_some() => create_output(output_path, compression)?.keep_partial(),
+        None => create_output(output_path, compression)?,
+    };
+    // 继续时重新写入相同的文件头，再从已完成的部分之后追加
+    let mut pcap_writer = PacketWriter::with_format(header, out_file, format)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+    if resume.is_some() {
+        pcap_writer.get_mut().file().seek(SeekFrom::End(0))?;
+        digest.record_existing(output_path)?;
+    }
+
+    let ticks_per_second = ticks_per_second(&header);
+    let anchor_ticks = anchor.resolve_ticks(input_path)?;
+    let mut progress = match resume {
+        Some(progress) => progress,
+        None => StepProgress {
+            input_offset: PCAP_HEADER_LEN,
+            packets: 0,
+            output_len: pcap_writer.get_mut().file().stream_position()?,
+        },
+    };
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        progress.packets += 1;
+        let record_len = PCAP_RECORD_HEADER_LEN + packet.data.len() as u64;
+
+        let (new_sec, new_usec) = scale_timestamp(
+            packet.header.ts_sec,
+            packet.header.ts_usec,
+            anchor_ticks,
+            scale,
+            ticks_per_second
+        )?;
+        packet.header.ts_sec = new_sec;
+        packet.header.ts_usec = new_usec;
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", progress.packets, e)))?;
+        digest.record_pcap(&packet);
+        progress.input_offset += record_len;
+
+        if let Some(checkpoint) = checkpoint.as_mut()
+            && progress.packets.is_multiple_of(checkpoint.interval)
+        {
+            let out_file = pcap_writer.get_mut().file();
+            out_file.sync_data()
+                .map_err(|e| PcapError::WriteFailure(format!("同步输出文件失败: {}: {}", output_path, e)))?;
+            // 写入器不缓冲，文件位置就是已写出的长度 (与输出格式无关)
+            progress.output_len = out_file.stream_position()?;
+            (checkpoint.save)(progress)?;
+        }
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    Ok(progress.packets)
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use log::{debug, info};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_comparative_analyzer::{CompareOptions, packet_hash};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_report::{print_fields, print_heading};
+use crate::modules::pcap_time_anchor::{split_timestamp_ticks, ticks_per_second, ticks_to_us, timestamp_ticks};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 离群匹配的最小容差 (微秒)
+const MIN_TOLERANCE_US: i64 = 1_000;
+
+/// 估计的时间关系：对比时间 - 基准时间 = offset + skew × (对比时间 - origin)
+struct ClockModel {
+    origin_us: i64,
+    offset_us: f64,
+    skew: f64,
+}
+
+impl ClockModel {
+    fn offset_at(&self, time_us: i64) -> f64 {
+        self.offset_us + self.skew * (time_us - self.origin_us) as f64
+    }
+}
+
+/// 估计两个抓包文件之间的时间偏移，并输出对齐后的对比文件
+///
+/// # 参数
+/// - `reference_path`: 基准文件路径 (PCAP或pcapng)
+/// - `comparison_path`: 对比文件路径 (PCAP或pcapng)
+/// - `output_path`: 对齐后的对比文件输出路径 (None表示只打印估计结果)
+/// - `estimate_skew`: 同时估计时钟漂移
+/// - `options`: 包匹配方式 (与 `compare` 相同)
+///
+/// # 功能
+/// 1. 以两边都只出现一次的包哈希配对，计算每对的时间差
+/// 2. 取时间差的中位数作为偏移，剔除离群的配对
+/// 3. 需要时用最小二乘法拟合漂移 (ppm)
+pub fn pcap_time_sync(
+    reference_path: &str,
+    comparison_path: &str,
+    output_path: Option<&str>,
+    estimate_skew: bool,
+    options: CompareOptions,
+) -> Result<()> {
+    let reference = unique_packet_times(reference_path, options)?;
+    let comparison = unique_packet_times(comparison_path, options)?;
+
+    // (对比时间, 时间差)
+    let mut pairs: Vec<(i64, i64)> = comparison
+        .iter()
+        .filter_map(|(hash, &t2)| reference.get(hash).map(|&t1| (t2, t2 - t1)))
+        .collect();
+    if pairs.is_empty() {
+        anyhow::bail!("两个文件没有可以配对的相同数据包");
+    }
+    pairs.sort_unstable();
+    debug!("配对的数据包: {} 个", pairs.len());
+
+    // 中位数和离群剔除
+    let median = median_of(pairs.iter().map(|&(_, d)| d).collect());
+    let mad = median_of(pairs.iter().map(|&(_, d)| (d - median).abs()).collect());
+    let tolerance = (mad * 5).max(MIN_TOLERANCE_US);
+    let mut inliers: Vec<(i64, i64)> = pairs
+        .iter()
+        .copied()
+        .filter(|&(_, d)| (d - median).abs() <= tolerance)
+        .collect();
+
+    let origin_us = pairs[0].0;
+    let mut model = ClockModel { origin_us, offset_us: median as f64, skew: 0.0 };
+    if estimate_skew {
+        // 漂移较大时按常数偏移剔除会误删两端的配对，拟合后按直线重新筛选
+        model = fit_line(&inliers, origin_us);
+        for _ in 0..3 {
+            let deviations: Vec<i64> = pairs
+                .iter()
+                .map(|&(t2, d)| (d as f64 - model.offset_at(t2)).abs().round() as i64)
+                .collect();
+            let tolerance = (median_of(deviations.clone()) * 5).max(MIN_TOLERANCE_US);
+            inliers = pairs
+                .iter()
+                .zip(&deviations)
+                .filter(|(_, deviation)| **deviation <= tolerance)
+                .map(|(pair, _)| *pair)
+                .collect();
+            model = fit_line(&inliers, origin_us);
+        }
+    }
+
+    let residuals: Vec<f64> = inliers
+        .iter()
+        .map(|&(t2, d)| d as f64 - model.offset_at(t2))
+        .collect();
+    let rms = (residuals.iter().map(|r| r * r).sum::<f64>() / residuals.len() as f64).sqrt();
+    let max_residual = residuals.iter().fold(0.0f64, |acc, r| acc.max(r.abs()));
+
+    print_heading("时间对齐估计结果:");
+    let mut fields = vec![
+        ("配对数据包", format!("{} 个 (离群 {} 个)", pairs.len(), pairs.len() - inliers.len()), None),
+        ("时间偏移", format!("{:.6} 秒 (对比文件 - 基准文件)", model.offset_us / 1_000_000.0), None),
+    ];
+    if estimate_skew {
+        fields.push(("时钟漂移", format!("{:.3} ppm", model.skew * 1_000_000.0), None));
+    }
+    fields.push(("残差", format!("RMS {:.1} 微秒, 最大 {:.1} 微秒", rms, max_residual), None));
+    print_fields(&fields);
+
+    if let Some(output_path) = output_path {
+        write_aligned(comparison_path, output_path, &model)?;
+    }
+
+    Ok(())
+}
+
+/// 每个只出现一次的包哈希对应的时间戳 (微秒)
+fn unique_packet_times(input_path: &str, options: CompareOptions) -> Result<HashMap<u64, i64>> {
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    let ticks_per_second = ticks_per_second(&pcap_reader.header);
+
+    let mut times: HashMap<u64, Option<i64>> = HashMap::new();
+    for packet in pcap_reader.by_ref() {
+        if let Some(hash) = packet_hash(&packet, linktype, options) {
+            let ticks = timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second);
+            let time = ticks_to_us(ticks, ticks_per_second);
+            times
+                .entry(hash)
+                .and_modify(|entry| *entry = None)
+                .or_insert(Some(time));
+        }
+    }
+    pcap_reader.check_complete()?;
+
+    Ok(times.into_iter().filter_map(|(hash, time)| time.map(|t| (hash, t))).collect())
+}
+
+fn median_of(mut values: Vec<i64>) -> i64 {
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+/// 最小二乘拟合 时间差 = a + b × (对比时间 - origin)
+fn fit_line(pairs: &[(i64, i64)], origin_us: i64) -> ClockModel {
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|&(t, _)| (t - origin_us) as f64).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|&(_, d)| d as f64).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for &(t, d) in pairs {
+        let dx = (t - origin_us) as f64 - mean_x;
+        covariance += dx * (d as f64 - mean_y);
+        variance += dx * dx;
+    }
+
+    // 所有配对时间相同时无法估计漂移
+    let skew = if variance > 0.0 { covariance / variance } else { 0.0 };
+    ClockModel {
+        origin_us,
+        offset_us: mean_y - skew * mean_x,
+        skew,
+    }
+}
+
+/// 按估计结果修正对比文件的时间戳
+fn write_aligned(comparison_path: &str, output_path: &str, model: &ClockModel) -> Result<()> {
+    let mut pcap_reader = PacketReader::open(comparison_path)?;
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+    // 模型以微秒计，纳秒精度的文件按纳秒修正
+    let ticks_per_second = ticks_per_second(&pcap_reader.header);
+    let ticks_per_us = (ticks_per_second / 1_000_000) as f64;
+
+    let mut packet_count = 0;
+    for packet in pcap_reader.by_ref() {
+        let mut packet = packet;
+        packet_count += 1;
+
+        let time = timestamp_ticks(packet.header.ts_sec, packet.header.ts_usec, ticks_per_second);
+        let offset = model.offset_at(ticks_to_us(time, ticks_per_second)) * ticks_per_us;
+        let (new_sec, new_usec) = split_timestamp_ticks(time - offset.round() as i64, ticks_per_second)?;
+        packet.header.ts_sec = new_sec;
+        packet.header.ts_usec = new_usec;
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    info!("成功输出对齐后的文件: 包数={}, 输出文件={}", packet_count, output_path);
+
+    Ok(())
+}
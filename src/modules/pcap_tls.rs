@@ -0,0 +1,187 @@
+/// TLS记录的内容类型: 握手
+const TLS_HANDSHAKE: u8 = 0x16;
+
+const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+const HANDSHAKE_SERVER_HELLO: u8 = 2;
+
+const EXTENSION_SERVER_NAME: u16 = 0;
+const EXTENSION_ALPN: u16 = 16;
+const EXTENSION_SUPPORTED_VERSIONS: u16 = 43;
+
+/// ClientHello中与分析有关的字段
+#[derive(Debug, Clone, Default)]
+pub struct ClientHello {
+    /// 服务器名称指示 (SNI) 中的主机名
+    pub server_name: Option<String>,
+    /// 应用层协议协商 (ALPN) 中客户端提供的协议
+    pub alpn: Vec<String>,
+    /// 客户端支持的最高版本 (有 supported_versions 扩展时取其中的最大值)
+    pub max_version: u16,
+}
+
+/// 按字节读取的游标，越界时返回None
+struct Cursor<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Cursor<'a> {
+        Cursor { data, offset: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        self.bytes(3).map(|bytes| (bytes[0] as usize) << 16 | (bytes[1] as usize) << 8 | bytes[2] as usize)
+    }
+
+    /// 带1字节长度前缀的字段
+    fn vector8(&mut self) -> Option<&'a [u8]> {
+        let len = self.u8()? as usize;
+        self.bytes(len)
+    }
+
+    /// 带2字节长度前缀的字段
+    fn vector16(&mut self) -> Option<&'a [u8]> {
+        let len = self.u16()? as usize;
+        self.bytes(len)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+}
+
+/// 版本号的名称，例如 `TLS 1.3`
+pub fn version_name(version: u16) -> String {
+    match version {
+        0x0300 => "SSL 3.0".to_string(),
+        0x0301 => "TLS 1.0".to_string(),
+        0x0302 => "TLS 1.1".to_string(),
+        0x0303 => "TLS 1.2".to_string(),
+        0x0304 => "TLS 1.3".to_string(),
+        other => format!("0x{:04x}", other),
+    }
+}
+
+/// GREASE值 (RFC 8701) 不是真正的版本
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+/// 取出TCP流开头连续的握手记录中的握手消息 (遇到其他类型的记录或不完整的记录时停止)
+pub fn handshake_messages(stream: &[u8]) -> Vec<u8> {
+    let mut messages = Vec::new();
+    let mut cursor = Cursor::new(stream);
+    while let Some(header) = cursor.bytes(5) {
+        if header[0] != TLS_HANDSHAKE || header[1] != 0x03 {
+            break;
+        }
+        let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let Some(fragment) = cursor.bytes(len) else {
+            // 最后一个记录没有抓全时保留已有的部分
+            messages.extend_from_slice(&stream[cursor.offset..]);
+            break;
+        };
+        messages.extend_from_slice(fragment);
+    }
+    messages
+}
+
+/// 握手消息序列中第一个消息的类型和内容，内容不完整时返回None
+fn first_message(messages: &[u8]) -> Option<(u8, &[u8])> {
+    let mut cursor = Cursor::new(messages);
+    let message_type = cursor.u8()?;
+    let len = cursor.u24()?;
+    Some((message_type, cursor.bytes(len)?))
+}
+
+/// 解析握手消息序列开头的ClientHello，不是ClientHello或不完整时返回None
+pub fn parse_client_hello(messages: &[u8]) -> Option<ClientHello> {
+    let (HANDSHAKE_CLIENT_HELLO, body) = first_message(messages)? else {
+        return None;
+    };
+    let mut cursor = Cursor::new(body);
+    let mut hello = ClientHello { max_version: cursor.u16()?, ..Default::default() };
+    cursor.bytes(32)?;
+    cursor.vector8()?;
+    cursor.vector16()?;
+    cursor.vector8()?;
+    if cursor.is_empty() {
+        return Some(hello);
+    }
+    let mut extensions = Cursor::new(cursor.vector16()?);
+    while !extensions.is_empty() {
+        let extension_type = extensions.u16()?;
+        let mut extension = Cursor::new(extensions.vector16()?);
+        match extension_type {
+            EXTENSION_SERVER_NAME => {
+                let mut names = Cursor::new(extension.vector16()?);
+                while !names.is_empty() {
+                    let name_type = names.u8()?;
+                    let name = names.vector16()?;
+                    // 只有主机名一种类型
+                    if name_type == 0 && hello.server_name.is_none() {
+                        hello.server_name = Some(String::from_utf8_lossy(name).into_owned());
+                    }
+                }
+            },
+            EXTENSION_ALPN => {
+                let mut protocols = Cursor::new(extension.vector16()?);
+                while !protocols.is_empty() {
+                    hello.alpn.push(String::from_utf8_lossy(protocols.vector8()?).into_owned());
+                }
+            },
+            EXTENSION_SUPPORTED_VERSIONS => {
+                let versions = extension.vector8()?;
+                let max = versions
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .filter(|&version| !is_grease(version))
+                    .max();
+                if let Some(max) = max {
+                    hello.max_version = max;
+                }
+            },
+            _ => {},
+        }
+    }
+    Some(hello)
+}
+
+/// 解析握手消息序列开头的ServerHello，返回服务端选择的版本；不是ServerHello或不完整时返回None
+pub fn server_hello_version(messages: &[u8]) -> Option<u16> {
+    let (HANDSHAKE_SERVER_HELLO, body) = first_message(messages)? else {
+        return None;
+    };
+    let mut cursor = Cursor::new(body);
+    let mut version = cursor.u16()?;
+    cursor.bytes(32)?;
+    cursor.vector8()?;
+    cursor.bytes(3)?;
+    if cursor.is_empty() {
+        return Some(version);
+    }
+    let mut extensions = Cursor::new(cursor.vector16()?);
+    while !extensions.is_empty() {
+        let extension_type = extensions.u16()?;
+        let extension = extensions.vector16()?;
+        if extension_type == EXTENSION_SUPPORTED_VERSIONS && extension.len() == 2 {
+            version = u16::from_be_bytes([extension[0], extension[1]]);
+        }
+    }
+    Some(version)
+}
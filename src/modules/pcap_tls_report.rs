@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::{FlowKey, Transport, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_quic::{QuicConnection, format_cid};
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, format_timestamp_us, print_fields, print_heading, print_section, verbosity,
+};
+use crate::modules::pcap_tcp_reassembly::TcpStream;
+use crate::modules::pcap_tls::{ClientHello, handshake_messages, parse_client_hello, server_hello_version, version_name};
+
+/// 每个表格中最多列出的连接数 (详细模式不限制)
+const MAX_CONNECTION_ROWS: usize = 50;
+
+/// 每个方向最多收集的TCP载荷 (字节)，握手消息在连接的开头
+const MAX_HANDSHAKE_BYTES: usize = 64 * 1024;
+
+const TCP_SYN: u8 = 0x02;
+const TCP_ACK: u8 = 0x10;
+
+/// 一个TCP连接，`key` 为第一个包的方向
+struct TcpConnection {
+    key: FlowKey,
+    first_us: i64,
+    /// 发送SYN (不带ACK) 的一方所在的方向
+    client: Option<FlowKey>,
+    forward: TcpStream,
+    reverse: TcpStream,
+    /// 两个方向已收集的载荷字节数
+    collected: [usize; 2],
+}
+
+/// 一个UDP流，`key` 为第一个包的方向
+struct UdpFlow {
+    first_us: i64,
+    quic: QuicConnection,
+}
+
+/// 一个TLS连接 (TCP) 的握手信息
+struct TlsHandshake {
+    first_us: i64,
+    /// 客户端到服务端方向的五元组
+    key: FlowKey,
+    client_hello: Option<ClientHello>,
+    server_version: Option<u16>,
+}
+
+/// 列出TLS和QUIC连接的握手信息: 版本、服务器名称 (SNI)、ALPN，QUIC连接另外列出连接ID
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+///
+/// # 说明
+/// 1. TCP连接的两个方向分别按序列号重组 (每个方向只看开头的 64KB)，客户端的数据以TLS握手记录开头的为TLS连接；
+///    发送SYN的一方为客户端，没有抓到握手时以ClientHello开头的方向为客户端
+/// 2. 协商版本取自ServerHello (TLS 1.3 为 supported_versions 扩展中的版本)
+/// 3. UDP流中发送第一个QUIC Initial包的一方为客户端；客户端Initial包的密钥由它的目的连接ID派生 (v1、v2)，
+///    解密后从CRYPTO帧中重组ClientHello，ClientHello可以跨多个Initial包；收到Retry包时改用Retry包的连接ID
+/// 4. 抓包开始于连接中途 (没有Initial包) 的QUIC连接只有短包头，不在列表中
+pub fn tls_report(input_path: &str) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut tcp_connections: Vec<TcpConnection> = Vec::new();
+    let mut tcp_index: HashMap<FlowKey, usize> = HashMap::new();
+    let mut udp_flows: Vec<UdpFlow> = Vec::new();
+    let mut udp_index: HashMap<FlowKey, usize> = HashMap::new();
+    let mut packet_count = 0u64;
+    let mut start_us = None;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = interface.timestamp_ns(packet.timestamp).div_euclid(1000) as i64;
+        start_us.get_or_insert(time_us);
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let Some(key) = parsed.flow_key() else {
+            continue;
+        };
+        let payload = &data[parsed.payload_range(data.len())];
+        match parsed.transport {
+            Some(Transport::Tcp(tcp)) if data.len() >= tcp.offset + 14 => {
+                let connection_index = *tcp_index.entry(key.canonical()).or_insert_with(|| {
+                    tcp_connections.push(TcpConnection {
+                        key,
+                        first_us: time_us,
+                        client: None,
+                        forward: TcpStream::default(),
+                        reverse: TcpStream::default(),
+                        collected: [0; 2],
+                    });
+                    tcp_connections.len() - 1
+                });
+                let connection = &mut tcp_connections[connection_index];
+                if data[tcp.offset + 13] & (TCP_SYN | TCP_ACK) == TCP_SYN {
+                    connection.client = Some(key);
+                }
+                let direction = usize::from(connection.key != key);
+                if connection.collected[direction] >= MAX_HANDSHAKE_BYTES {
+                    continue;
+                }
+                connection.collected[direction] += payload.len();
+                let stream = if direction == 0 { &mut connection.forward } else { &mut connection.reverse };
+                stream.add(data, &tcp, payload, time_us);
+            },
+            Some(Transport::Udp(_)) => {
+                let flow_index = *udp_index.entry(key.canonical()).or_insert_with(|| {
+                    udp_flows.push(UdpFlow { first_us: time_us, quic: QuicConnection::default() });
+                    udp_flows.len() - 1
+                });
+                udp_flows[flow_index].quic.add_datagram(key, payload);
+            },
+            _ => {},
+        }
+    }
+
+    let handshakes: Vec<TlsHandshake> = tcp_connections.iter().filter_map(tls_handshake).collect();
+    let mut quic: Vec<(i64, &QuicConnection)> =
+        udp_flows.iter().filter(|flow| flow.quic.is_quic()).map(|flow| (flow.first_us, &flow.quic)).collect();
+    quic.sort_by_key(|(first_us, _)| *first_us);
+    print_report(input_path, packet_count, &handshakes, &quic, start_us.unwrap_or(0));
+    Ok(())
+}
+
+/// 判断TCP连接是否为TLS，并取出ClientHello和ServerHello中的信息
+fn tls_handshake(connection: &TcpConnection) -> Option<TlsHandshake> {
+    let forward = handshake_messages(&connection.forward.reassemble().data);
+    let reverse = handshake_messages(&connection.reverse.reassemble().data);
+    let forward_hello = parse_client_hello(&forward);
+    let reverse_hello = parse_client_hello(&reverse);
+    let client_is_forward = match connection.client {
+        Some(client) => client == connection.key,
+        None => forward_hello.is_some() || reverse_hello.is_none(),
+    };
+    let (client_messages, server_messages, client_hello, key) = if client_is_forward {
+        (forward, reverse, forward_hello, connection.key)
+    } else {
+        (reverse, forward, reverse_hello, connection.key.reversed())
+    };
+    // ClientHello没有抓全时至少要以握手消息开头
+    if client_hello.is_none() && client_messages.first() != Some(&1) {
+        return None;
+    }
+    Some(TlsHandshake {
+        first_us: connection.first_us,
+        key,
+        client_hello,
+        server_version: server_hello_version(&server_messages),
+    })
+}
+
+/// 服务器名称、ALPN的显示文本
+fn hello_fields(hello: Option<&ClientHello>) -> (String, String) {
+    match hello {
+        Some(hello) => (
+            hello.server_name.clone().unwrap_or_else(|| "-".to_string()),
+            if hello.alpn.is_empty() { "-".to_string() } else { hello.alpn.join(",") },
+        ),
+        None => ("(不完整)".to_string(), "-".to_string()),
+    }
+}
+
+fn print_report(input_path: &str, packet_count: u64, handshakes: &[TlsHandshake], quic: &[(i64, &QuicConnection)], start_us: i64) {
+    let incomplete = handshakes.iter().filter(|handshake| handshake.client_hello.is_none()).count()
+        + quic.iter().filter(|(_, connection)| connection.client_hello.is_none()).count();
+    let hellos = handshakes
+        .iter()
+        .filter_map(|handshake| handshake.client_hello.as_ref())
+        .chain(quic.iter().filter_map(|(_, connection)| connection.client_hello.as_ref()));
+    let without_sni = hellos.filter(|hello| hello.server_name.is_none()).count();
+    let undecryptable = quic.iter().filter(|(_, connection)| connection.undecryptable > 0).count();
+    let warning = |value: usize| (value > 0).then_some(Severity::Warning);
+
+    print_heading(&format!("TLS/QUIC握手: {}", input_path));
+    print_fields(&[
+        ("数据包数", packet_count.to_string(), None),
+        ("TLS连接数 (TCP)", handshakes.len().to_string(), None),
+        ("QUIC连接数", quic.len().to_string(), None),
+        ("没有SNI", without_sni.to_string(), None),
+        ("ClientHello不完整", incomplete.to_string(), warning(incomplete)),
+        ("有无法解密的Initial包", undecryptable.to_string(), warning(undecryptable)),
+    ]);
+    if handshakes.is_empty() && quic.is_empty() {
+        return;
+    }
+
+    // 服务器名称 -> (TLS连接数, QUIC连接数)
+    let mut names: HashMap<String, (usize, usize)> = HashMap::new();
+    for hello in handshakes.iter().filter_map(|handshake| handshake.client_hello.as_ref()) {
+        names.entry(hello.server_name.clone().unwrap_or_else(|| "(无)".to_string())).or_default().0 += 1;
+    }
+    for hello in quic.iter().filter_map(|(_, connection)| connection.client_hello.as_ref()) {
+        names.entry(hello.server_name.clone().unwrap_or_else(|| "(无)".to_string())).or_default().1 += 1;
+    }
+    let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_CONNECTION_ROWS };
+    let print_more = |total: usize, what: &str| {
+        if total > max_rows {
+            println!("  ... 还有 {} 个{} (使用 --verbose 查看全部)", total - max_rows, what);
+        }
+    };
+    if !names.is_empty() {
+        let mut names: Vec<(String, (usize, usize))> = names.into_iter().collect();
+        names.sort_by(|a, b| (b.1.0 + b.1.1).cmp(&(a.1.0 + a.1.1)).then(a.0.cmp(&b.0)));
+        print_section("服务器名称 (SNI):");
+        let mut table = Table::new(&["服务器名称", "TLS连接", "QUIC连接"]).right_align(&[1, 2]);
+        for (name, (tls, quic)) in names.iter().take(max_rows) {
+            table.add_row(vec![name.clone(), tls.to_string(), quic.to_string()]);
+        }
+        table.print();
+        print_more(names.len(), "服务器名称");
+    }
+
+    if !handshakes.is_empty() {
+        print_section("TLS连接 (TCP，按开始时间):");
+        let mut table = Table::new(&["时间", "五元组", "客户端最高版本", "协商版本", "服务器名称", "ALPN"]);
+        for handshake in handshakes.iter().take(max_rows) {
+            let (server_name, alpn) = hello_fields(handshake.client_hello.as_ref());
+            table.add_row(vec![
+                format_timestamp_us(handshake.first_us, start_us),
+                handshake.key.to_string(),
+                handshake.client_hello.as_ref().map_or_else(|| "-".to_string(), |hello| version_name(hello.max_version)),
+                handshake.server_version.map_or_else(|| "-".to_string(), version_name),
+                server_name,
+                alpn,
+            ]);
+        }
+        table.print();
+        print_more(handshakes.len(), "TLS连接");
+    }
+
+    if !quic.is_empty() {
+        print_section("QUIC连接 (按开始时间，连接ID为客户端第一个Initial包中的):");
+        let mut table = Table::new(&["时间", "五元组", "版本", "目的连接ID", "源连接ID", "服务端连接ID", "服务器名称", "ALPN"]);
+        for (first_us, connection) in quic.iter().take(max_rows) {
+            let (server_name, alpn) = hello_fields(connection.client_hello.as_ref());
+            table.add_row(vec![
+                format_timestamp_us(*first_us, start_us),
+                connection.client.map_or_else(|| "-".to_string(), |key| key.to_string()),
+                connection.version.map_or_else(|| "-".to_string(), crate::modules::pcap_quic::version_name),
+                format_cid(&connection.dcid),
+                format_cid(&connection.scid),
+                connection.server_scid.as_deref().map_or_else(|| "-".to_string(), format_cid),
+                server_name,
+                alpn,
+            ]);
+        }
+        table.print();
+        print_more(quic.len(), "QUIC连接");
+    }
+}
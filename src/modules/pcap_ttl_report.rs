@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use anyhow::Result;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_packet_parser::parse_packet;
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, print_fields, print_heading, print_section, print_separator, print_verdict, verbosity,
+};
+
+/// 异常源地址列表中最多列出的地址数 (详细模式不限制，并列出全部源地址)
+const MAX_SOURCE_ROWS: usize = 50;
+
+/// 常见的初始TTL，观察到的TTL向上取整到其中之一
+const INITIAL_TTLS: [u8; 4] = [32, 64, 128, 255];
+
+/// 初始TTL对应的系统提示
+fn os_hint(initial: u8) -> &'static str {
+    match initial {
+        32 => "Windows 9x/嵌入式设备",
+        64 => "Linux/macOS/Unix",
+        128 => "Windows",
+        _ => "网络设备 (Cisco/Solaris等)",
+    }
+}
+
+/// 推断的初始TTL (不小于观察值的最小常见初始TTL)
+fn initial_ttl(ttl: u8) -> u8 {
+    INITIAL_TTLS.into_iter().find(|&initial| initial >= ttl).unwrap_or(255)
+}
+
+/// 一个源地址的TTL分布
+#[derive(Default)]
+struct SourceTtls {
+    packets: u64,
+    /// 观察到的TTL -> 包数
+    ttls: BTreeMap<u8, u64>,
+}
+
+/// 同一初始TTL的一组包
+struct Cluster {
+    initial: u8,
+    packets: u64,
+    min_hops: u8,
+    max_hops: u8,
+}
+
+impl SourceTtls {
+    /// 按初始TTL归类 (初始TTL升序)
+    fn clusters(&self) -> Vec<Cluster> {
+        let mut clusters: Vec<Cluster> = Vec::new();
+        for (&ttl, &count) in &self.ttls {
+            let initial = initial_ttl(ttl);
+            let hops = initial - ttl;
+            match clusters.last_mut() {
+                Some(cluster) if cluster.initial == initial => {
+                    cluster.packets += count;
+                    cluster.min_hops = cluster.min_hops.min(hops);
+                    cluster.max_hops = cluster.max_hops.max(hops);
+                }
+                _ => clusters.push(Cluster { initial, packets: count, min_hops: hops, max_hops: hops }),
+            }
+        }
+        clusters
+    }
+}
+
+/// 源地址的TTL异常
+fn anomaly(clusters: &[Cluster]) -> Option<&'static str> {
+    if clusters.len() > 1 {
+        Some("多个初始TTL")
+    } else if clusters.iter().any(|cluster| cluster.max_hops - cluster.min_hops > 1) {
+        Some("跳数不一致")
+    } else {
+        None
+    }
+}
+
+/// 按源地址报告观察到的初始TTL分组和系统提示，列出TTL异常的源地址
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+///
+/// # 说明
+/// 观察到的TTL (IPv6为跳数限制) 向上取整到32、64、128、255中最近的一个作为初始TTL，差值为经过的跳数。
+/// 同一源地址出现多个初始TTL，或同一初始TTL的跳数相差超过1时，可能是NAT后有多台主机、
+/// 路径发生变化或源地址被伪造。系统提示只依据初始TTL，仅供参考 (TTL可以被修改)。
+pub fn check_ttl(input_path: &str) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut sources: HashMap<IpAddr, SourceTtls> = HashMap::new();
+    let mut packet_count = 0u64;
+    let mut ip_packets = 0u64;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let Some(ip) = parsed.ip.as_ref() else {
+            continue;
+        };
+        let Some(&ttl) = data.get(ip.offset + if ip.version == 4 { 8 } else { 7 }) else {
+            continue;
+        };
+        ip_packets += 1;
+        let source = sources.entry(ip.src).or_default();
+        source.packets += 1;
+        *source.ttls.entry(ttl).or_default() += 1;
+    }
+
+    // 按包数降序，相同时按地址
+    let mut sources: Vec<(IpAddr, SourceTtls)> = sources.into_iter().collect();
+    sources.sort_by(|a, b| b.1.packets.cmp(&a.1.packets).then(a.0.cmp(&b.0)));
+    let analyzed: Vec<(IpAddr, SourceTtls, Vec<Cluster>)> = sources
+        .into_iter()
+        .map(|(addr, source)| {
+            let clusters = source.clusters();
+            (addr, source, clusters)
+        })
+        .collect();
+    let anomalies = analyzed.iter().filter(|(_, _, clusters)| anomaly(clusters).is_some()).count();
+
+    print_heading(&format!("TTL分布: {}", input_path));
+    print_fields(&[
+        ("数据包数", packet_count.to_string(), None),
+        ("IP包数", ip_packets.to_string(), None),
+        ("源地址数", analyzed.len().to_string(), None),
+        ("TTL异常的源地址", anomalies.to_string(), (anomalies > 0).then_some(Severity::Warning)),
+    ]);
+
+    if !analyzed.is_empty() {
+        // 每个源地址计入其包数最多的初始TTL
+        let mut by_initial: BTreeMap<u8, (u64, u64)> = BTreeMap::new();
+        for (_, _, clusters) in &analyzed {
+            for cluster in clusters {
+                by_initial.entry(cluster.initial).or_default().1 += cluster.packets;
+            }
+            if let Some(main) = clusters.iter().max_by_key(|cluster| cluster.packets) {
+                by_initial.entry(main.initial).or_default().0 += 1;
+            }
+        }
+        print_section("初始TTL分布:");
+        let mut table = Table::new(&["初始TTL", "系统提示", "源地址数", "包数"]).right_align(&[0, 2, 3]);
+        for (initial, (addresses, packets)) in &by_initial {
+            table.add_row(vec![initial.to_string(), os_hint(*initial).to_string(), addresses.to_string(), packets.to_string()]);
+        }
+        table.print();
+    }
+
+    let verbose = verbosity() == Verbosity::Verbose;
+    let rows: Vec<_> = analyzed
+        .iter()
+        .filter(|(_, _, clusters)| verbose || anomaly(clusters).is_some())
+        .collect();
+    if !rows.is_empty() {
+        let max_rows = if verbose { usize::MAX } else { MAX_SOURCE_ROWS };
+        print_section(if verbose { "各源地址:" } else { "TTL异常的源地址:" });
+        let mut table = Table::new(&["源地址", "包数", "初始TTL (包数, 跳数)", "系统提示", "说明"]).right_align(&[1]);
+        for (addr, source, clusters) in rows.iter().take(max_rows) {
+            let groups: Vec<String> = clusters
+                .iter()
+                .map(|cluster| {
+                    let hops = if cluster.min_hops == cluster.max_hops {
+                        cluster.min_hops.to_string()
+                    } else {
+                        format!("{}-{}", cluster.min_hops, cluster.max_hops)
+                    };
+                    format!("{} ({}, {})", cluster.initial, cluster.packets, hops)
+                })
+                .collect();
+            let hints: Vec<&str> = clusters.iter().map(|cluster| os_hint(cluster.initial)).collect();
+            table.add_row(vec![
+                addr.to_string(),
+                source.packets.to_string(),
+                groups.join(" / "),
+                hints.join(" / "),
+                anomaly(clusters).unwrap_or_default().to_string(),
+            ]);
+        }
+        table.print();
+        if rows.len() > max_rows {
+            println!("  ... 还有 {} 个源地址 (使用 --verbose 查看全部)", rows.len() - max_rows);
+        }
+    }
+
+    print_separator();
+    if anomalies == 0 {
+        print_verdict(Severity::Ok, &format!("每个源地址的TTL都一致 (共 {} 个源地址)", analyzed.len()));
+    } else {
+        print_verdict(
+            Severity::Warning,
+            &format!("{} 个源地址的TTL不一致，可能是NAT后的多台主机、路径变化或伪造的源地址", anomalies),
+        );
+    }
+    Ok(())
+}
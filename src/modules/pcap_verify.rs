@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use pcap_file::{Packet, PcapReader};
+use anyhow::Result;
+use log::{info, warn};
+use seahash::SeaHasher;
+
+use crate::modules::pcap_error::{PCAP_HEADER_LEN, PCAP_RECORD_HEADER_LEN, PcapError};
+use crate::modules::pcap_gzip::GzipDecoder;
+use crate::modules::pcap_input::is_gzip_file;
+use crate::modules::pcap_output::Compression;
+use crate::modules::pcap_pcapng::{InterfaceDescription, PcapngBlock, PcapngPacket, PcapngReader, is_pcapng_file};
+
+/// 是否在写完输出后重新读取并校验 (`--verify`)
+static VERIFY_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 设置是否校验输出文件
+pub fn set_verify(enabled: bool) {
+    VERIFY_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// 是否校验输出文件
+pub fn verify_enabled() -> bool {
+    VERIFY_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 写入输出时记录的摘要，写完后与重新读取的内容比较
+///
+/// 记录包数、数据字节数、时间戳倒退的次数，以及按顺序对时间戳、原始长度和数据计算的哈希。
+/// 时间戳按 (秒, 秒以下的部分) 记录，秒以下的部分使用文件本身的精度，
+/// 因此PCAP数据包写为相同精度的pcapng时摘要不变。
+/// 未启用 `--verify` 时不做任何记录。
+pub struct OutputDigest {
+    enabled: bool,
+    packets: usize,
+    bytes: u64,
+    backward_steps: usize,
+    last_timestamp: Option<u64>,
+    hasher: SeaHasher,
+}
+
+impl Default for OutputDigest {
+    fn default() -> OutputDigest {
+        OutputDigest {
+            enabled: VERIFY_ENABLED.load(Ordering::Relaxed),
+            packets: 0,
+            bytes: 0,
+            backward_steps: 0,
+            last_timestamp: None,
+            hasher: SeaHasher::new(),
+        }
+    }
+}
+
+impl OutputDigest {
+    /// 记录写入PCAP文件的包
+    pub fn record_pcap(&mut self, packet: &Packet) {
+        let timestamp = ((packet.header.ts_sec as u64) << 32) | packet.header.ts_usec as u64;
+        self.record(timestamp, packet.header.orig_len, &packet.data);
+    }
+
+    /// 记录写入pcapng文件的包，`interface` 为包所属的接口 (没有时按微秒精度)
+    pub fn record_pcapng(&mut self, packet: &PcapngPacket, interface: Option<&InterfaceDescription>) {
+        let units = interface.map_or(1_000_000, |interface| interface.units_per_second());
+        let timestamp = ((packet.timestamp / units) << 32) | (packet.timestamp % units);
+        self.record(timestamp, packet.orig_len, &packet.data);
+    }
+
+    /// 记录输出文件中已有的包 (从检查点继续写入时)
+    pub fn record_existing(&mut self, path: &str) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        *self = read_digest(path)?;
+        Ok(())
+    }
+
+    fn record(&mut self, timestamp: u64, orig_len: u32, data: &[u8]) {
+        if !self.enabled {
+            return;
+        }
+        self.packets += 1;
+        self.bytes += data.len() as u64;
+        if self.last_timestamp.is_some_and(|last| timestamp < last) {
+            self.backward_steps += 1;
+        }
+        self.last_timestamp = Some(timestamp);
+        self.hasher.write_u64(timestamp);
+        self.hasher.write_u32(orig_len);
+        self.hasher.write_u64(data.len() as u64);
+        self.hasher.write(data);
+    }
+
+    /// 重新读取输出文件并与记录的摘要比较 (未启用 `--verify` 时直接返回)
+    ///
+    /// 输出文件必须已关闭或刷新。
+    pub fn verify(&self, path: &str) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let actual = read_digest(path)?;
+        let mismatch = |detail: String| PcapError::WriteFailure(format!("输出校验失败: {}: {}", path, detail));
+
+        if actual.packets != self.packets {
+            anyhow::bail!(mismatch(format!("包数不一致 (写入 {} 个，读回 {} 个)", self.packets, actual.packets)));
+        }
+        if actual.bytes != self.bytes {
+            anyhow::bail!(mismatch(format!("数据字节数不一致 (写入 {}，读回 {})", self.bytes, actual.bytes)));
+        }
+        if actual.backward_steps != self.backward_steps {
+            anyhow::bail!(mismatch(format!(
+                "时间戳顺序不一致 (写入时倒退 {} 处，读回 {} 处)",
+                self.backward_steps, actual.backward_steps
+            )));
+        }
+        if actual.hasher.finish() != self.hasher.finish() {
+            anyhow::bail!(mismatch("数据包内容与写入的不一致".to_string()));
+        }
+
+        if self.backward_steps > 0 {
+            warn!("输出文件中有 {} 处时间戳倒退 (与写入的顺序一致): {}", self.backward_steps, path);
+        }
+        info!("输出校验通过: {}: 包数={}, 数据字节数={}", path, self.packets, self.bytes);
+        Ok(())
+    }
+}
+
+/// 读取输出文件的摘要；PCAP文件末尾有不完整的包时报错
+///
+/// gzip压缩的输出按解压后的内容读取，解压失败时同样报错。
+fn read_digest(path: &str) -> Result<OutputDigest> {
+    let mut digest = OutputDigest { enabled: true, ..OutputDigest::default() };
+    let file = File::open(Path::new(path))
+        .map_err(|e| PcapError::open_failed(path, e))?;
+    let failed = |detail: String| PcapError::WriteFailure(format!("输出校验失败: {}: {}", path, detail));
+    let gzip = is_gzip_file(path);
+
+    if is_pcapng_file(path)? {
+        let input: Box<dyn Read> = if gzip { Box::new(GzipDecoder::new(file)) } else { Box::new(file) };
+        let mut reader = PcapngReader::new(BufReader::new(input));
+        while let Some(block) = reader.next_block().map_err(|e| failed(format!("{:#}", e)))? {
+            if let PcapngBlock::Packet(packet) = block {
+                digest.record_pcapng(&packet, reader.interfaces.get(packet.interface_id as usize));
+            }
+        }
+        return Ok(digest);
+    }
+
+    let (read_bytes, total_len) = if gzip {
+        // 解压器自身有缓冲，不再包一层缓冲，读完包之后剩下的就是未读的数据
+        let mut decoder = GzipDecoder::new(file);
+        let read_bytes = record_pcap_packets(&mut digest, &mut decoder).map_err(failed)?;
+        match decoder.read(&mut [0u8]) {
+            Ok(0) => (read_bytes, decoder.total_len()),
+            Ok(_) => anyhow::bail!(failed(format!("第{}个包之后有不完整的数据", digest.packets))),
+            Err(e) => anyhow::bail!(failed(e.to_string())),
+        }
+    } else {
+        let read_bytes = record_pcap_packets(&mut digest, BufReader::new(file)).map_err(failed)?;
+        (read_bytes, std::fs::metadata(path).map(|m| m.len()).unwrap_or(read_bytes))
+    };
+    if read_bytes < total_len {
+        anyhow::bail!(failed(format!(
+            "第{}个包之后有 {} 字节不完整的数据",
+            digest.packets,
+            total_len - read_bytes
+        )));
+    }
+    Ok(digest)
+}
+
+/// 记录PCAP文件中的包，返回读取的字节数
+fn record_pcap_packets<R: Read>(digest: &mut OutputDigest, input: R) -> Result<u64, String> {
+    let reader = PcapReader::new(input).map_err(|e| format!("无法读取PCAP文件头: {}", e))?;
+    let mut read_bytes = PCAP_HEADER_LEN;
+    for packet in reader {
+        read_bytes += PCAP_RECORD_HEADER_LEN + packet.data.len() as u64;
+        digest.record_pcap(&packet);
+    }
+    Ok(read_bytes)
+}
+
+/// 检查 `--verify` 能否读取按 `--compress` 写出的输出 (只能读取gzip压缩的输出)
+pub fn check_compression(compression: Compression) -> Result<()> {
+    if VERIFY_ENABLED.load(Ordering::Relaxed) && matches!(compression, Compression::Zstd(_)) {
+        anyhow::bail!(PcapError::InvalidArgument("--verify 不支持zstd压缩的输出 (可以使用 --compress gzip)".to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self};
+use std::path::Path;
+use anyhow::{Context, Result};
+use log::{info, warn};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{ETHERTYPE_QINQ, ETHERTYPE_VLAN, LINKTYPE_ETHERNET, read_u16};
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 解析 `--remap` 的一项，格式为 原VLAN=新VLAN
+pub fn parse_vlan_mapping(text: &str) -> Result<(u16, u16), String> {
+    let (from, to) = text.split_once('=').ok_or_else(|| format!("应为 原VLAN=新VLAN: {}", text))?;
+    let vid = |value: &str| value.trim().parse().ok().filter(|&vid: &u16| vid < 4096).ok_or_else(|| format!("无效的VLAN ID: {}", value));
+    Ok((vid(from)?, vid(to)?))
+}
+
+/// 读取映射文件，每行一项 `原VLAN=新VLAN` (也可用逗号或空白分隔)，`#` 开头为注释
+fn load_mapping_file(path: &str) -> Result<Vec<(u16, u16)>> {
+    let content = fs::read_to_string(Path::new(path))
+        .with_context(|| format!("无法读取VLAN映射文件: {}", path))?;
+    let mut mappings = Vec::new();
+    for (line_no, raw_line) in content.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(|c: char| c == '=' || c == ',' || c.is_whitespace()).filter(|f| !f.is_empty()).collect();
+        let [from, to] = fields[..] else {
+            anyhow::bail!(PcapError::InvalidArgument(format!("VLAN映射文件第{}行格式错误: {}", line_no + 1, raw_line)));
+        };
+        let mapping = parse_vlan_mapping(&format!("{}={}", from, to))
+            .map_err(|e| PcapError::InvalidArgument(format!("VLAN映射文件第{}行: {}", line_no + 1, e)))?;
+        mappings.push(mapping);
+    }
+    Ok(mappings)
+}
+
+/// 按映射表改写802.1Q/802.1ad标签中的VLAN ID
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `mappings`: 命令行指定的映射 (原VLAN, 新VLAN)
+/// - `mapping_file`: 映射文件路径，与 `mappings` 合并
+///
+/// # 说明
+/// 1. 所有映射同时生效，每个标签最多替换一次，因此可以互换两个VLAN (如 100=200,200=100)
+/// 2. QinQ的外层和内层标签都按同一映射表替换，只修改VID，优先级和DEI保持不变
+/// 3. 同一个原VLAN映射到不同的新VLAN时报错；不在映射表中的VLAN和不带标签的包保持不变
+/// 4. 只处理以太网链路类型，VLAN标签不影响校验和
+pub fn pcap_vlan_remap(input_path: &str, output_path: &str, mappings: &[(u16, u16)], mapping_file: Option<&str>) -> Result<()> {
+    let mut all = mappings.to_vec();
+    if let Some(path) = mapping_file {
+        all.extend(load_mapping_file(path)?);
+    }
+    let mut table: HashMap<u16, u16> = HashMap::new();
+    for &(from, to) in &all {
+        if let Some(&previous) = table.get(&from)
+            && previous != to
+        {
+            anyhow::bail!(PcapError::InvalidArgument(format!("VLAN {} 同时映射到 {} 和 {}", from, previous, to)));
+        }
+        table.insert(from, to);
+    }
+    if table.is_empty() {
+        anyhow::bail!(PcapError::InvalidArgument("必须用 --remap 或 --remap-file 指定至少一个映射".to_string()));
+    }
+
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    if linktype != LINKTYPE_ETHERNET {
+        warn!("链路层类型 {} 不是以太网，没有VLAN标签可以替换", linktype);
+    }
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut packet_count = 0u64;
+    let mut modified = 0u64;
+    // 原VLAN -> 替换的标签数
+    let mut remapped: BTreeMap<u16, u64> = BTreeMap::new();
+    let mut unmapped: BTreeSet<u16> = BTreeSet::new();
+    for mut packet in pcap_reader.by_ref() {
+        packet_count += 1;
+        if linktype == LINKTYPE_ETHERNET {
+            let data = packet.data.to_mut();
+            let mut changed = false;
+            let mut offset = 12;
+            while offset + 6 <= data.len() && matches!(read_u16(data, offset), ETHERTYPE_VLAN | ETHERTYPE_QINQ) {
+                let tci = read_u16(data, offset + 2);
+                let vid = tci & 0x0fff;
+                match table.get(&vid) {
+                    Some(&new_vid) => {
+                        data[offset + 2..offset + 4].copy_from_slice(&((tci & 0xf000) | new_vid).to_be_bytes());
+                        *remapped.entry(vid).or_default() += 1;
+                        changed |= new_vid != vid;
+                    }
+                    None => {
+                        unmapped.insert(vid);
+                    }
+                }
+                offset += 4;
+            }
+            if changed {
+                modified += 1;
+            }
+        }
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    for (vid, count) in &remapped {
+        info!("VLAN {} -> {}: {} 个标签", vid, table[vid], count);
+    }
+    let unused: BTreeSet<u16> = table.keys().filter(|vid| !remapped.contains_key(vid)).copied().collect();
+    if !unused.is_empty() {
+        warn!("映射表中的VLAN没有出现在文件中: {:?}", unused);
+    }
+    if !unmapped.is_empty() {
+        info!("不在映射表中、保持不变的VLAN: {:?}", unmapped);
+    }
+    info!(
+        "成功替换VLAN: 包数={}, 修改的包数={}, 替换的标签数={}, 输出文件={}",
+        packet_count, modified, remapped.values().sum::<u64>(), output_path
+    );
+
+    Ok(())
+}
@@ -0,0 +1,757 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::net::IpAddr;
+use std::ops::Range;
+use std::path::Path;
+use pcap_file::pcap_header::{Datalink, PcapHeader};
+use pcap_file::Packet;
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::modules::io::{OutputFormat, PacketWriter, output_format};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{FlowKey, Transport, parse_packet, read_u16, read_u32};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+use crate::modules::pcap_report::{
+    Severity, Table, Verbosity, format_timestamp_us, print_fields, print_heading, print_section, print_separator,
+    print_verdict, verbosity,
+};
+use crate::modules::pcap_time_anchor::split_timestamp_us;
+use crate::modules::pcap_verify::OutputDigest;
+
+/// 呼叫表和RTP流表中最多列出的行数 (详细模式不限制)
+const MAX_ROWS: usize = 50;
+
+/// SIP的默认端口，这些端口上的UDP包不当作RTP
+const SIP_PORT: u16 = 5060;
+
+/// MOS估计值低于这个值时认为通话质量差
+const POOR_MOS: f64 = 3.6;
+
+/// G.711的采样率
+const AUDIO_SAMPLE_RATE: u32 = 8000;
+
+/// 每个呼叫导出的音频的最大时长 (秒)，防止时间戳跳变导致文件过大
+const MAX_AUDIO_SECONDS: u32 = 3600;
+
+/// 一个SIP呼叫 (按Call-ID划分)
+struct SipCall {
+    call_id: String,
+    /// 主叫和被叫的SIP URI
+    from: String,
+    to: String,
+    first_us: i64,
+    last_us: i64,
+    /// INVITE的2xx响应时间
+    answer_us: Option<i64>,
+    /// BYE的时间
+    end_us: Option<i64>,
+    /// INVITE的失败响应 (3xx及以上)
+    failure: Option<u16>,
+    cancelled: bool,
+    /// SDP中的动态负载类型: 负载类型 -> (编码名, 时钟频率)
+    codecs: HashMap<u8, (String, u32)>,
+}
+
+impl SipCall {
+    fn state(&self) -> String {
+        match (self.answer_us, self.end_us) {
+            (Some(_), Some(_)) => "已结束".to_string(),
+            (Some(_), None) => "已接通".to_string(),
+            _ if self.cancelled => "已取消".to_string(),
+            _ => match self.failure {
+                Some(status) => format!("失败 ({})", status),
+                None => "未接通".to_string(),
+            },
+        }
+    }
+
+    /// 通话时长: 接通到BYE (没有BYE时到呼叫的最后一个包)
+    fn duration_us(&self) -> Option<i64> {
+        self.answer_us.map(|answer_us| self.end_us.unwrap_or(self.last_us) - answer_us)
+    }
+}
+
+/// SDP中声明的媒体地址
+struct MediaEndpoint {
+    addr: IpAddr,
+    port: u16,
+    call: usize,
+    time_us: i64,
+}
+
+struct RtpPacket {
+    arrival_us: i64,
+    seq: u16,
+    timestamp: u32,
+    /// 编码后的音频数据 (只在导出音频时保存，负载类型与流不同的包如DTMF事件为空)
+    audio: Vec<u8>,
+}
+
+/// RTP头中用到的字段
+pub struct RtpHeader {
+    pub ssrc: u32,
+    pub payload_type: u8,
+    pub seq: u16,
+    pub timestamp: u32,
+    /// 负载在UDP载荷中的范围 (去掉CSRC、扩展头和填充)
+    pub payload: Range<usize>,
+}
+
+/// 一个RTP流 (五元组和SSRC相同)
+struct RtpStream {
+    key: FlowKey,
+    ssrc: u32,
+    payload_type: u8,
+    linktype: u32,
+    snaplen: u32,
+    packets: Vec<RtpPacket>,
+}
+
+/// RTP流的质量指标
+struct StreamStats {
+    expected: u64,
+    lost: u64,
+    out_of_order: u64,
+    /// RFC 3550的到达间隔抖动 (毫秒)，平均值和最大值
+    mean_jitter_ms: f64,
+    max_jitter_ms: f64,
+    /// 相邻两个包的最大到达间隔 (毫秒)
+    max_delta_ms: f64,
+    mos: f64,
+}
+
+impl StreamStats {
+    fn loss_percent(&self) -> f64 {
+        if self.expected > 0 { self.lost as f64 * 100.0 / self.expected as f64 } else { 0.0 }
+    }
+}
+
+/// SDP中与RTP流关联有关的部分
+struct SessionDescription {
+    /// 媒体地址 (地址, 端口)
+    endpoints: Vec<(IpAddr, u16)>,
+    /// rtpmap: 负载类型 -> (编码名, 时钟频率)
+    codecs: Vec<(u8, (String, u32))>,
+}
+
+/// 解析出的SIP消息
+struct SipMessage<'a> {
+    /// 请求的方法 (响应为None)
+    method: Option<String>,
+    /// 响应的状态码
+    status: Option<u16>,
+    headers: Vec<(String, String)>,
+    body: &'a [u8],
+}
+
+impl SipMessage<'_> {
+    /// 按名称查找头部字段，同时匹配紧凑形式 (如 Call-ID 的 i)
+    fn header(&self, name: &str, compact: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name) || key.eq_ignore_ascii_case(compact))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// 识别SIP呼叫及其RTP流，报告每个流的丢包、抖动和MOS估计值
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `extract_dir`: 把每个RTP流导出为单独的PCAP文件的目录 (None表示不导出)
+/// - `audio_dir`: 把每个呼叫的G.711音频解码为WAV文件的目录 (None表示不导出)
+///
+/// # 说明
+/// 1. 解析UDP上的SIP消息，按Call-ID划分呼叫，从INVITE及其响应确定呼叫状态和通话时长
+/// 2. SIP消息中的SDP给出媒体地址和端口，发往或来自这些地址的RTP包按 (五元组, SSRC) 划分为流，
+///    编码名和时钟频率取自SDP的rtpmap，其次为静态负载类型
+/// 3. 丢包按扩展序列号计算，抖动按RFC 3550计算；MOS由抖动和丢包率按简化的E模型估算 (不含网络时延)
+/// 4. 导出时每个RTP流输出为 `call呼叫编号_SSRC.pcap` (pcapng输出为 `.pcapng`)，需要再读一遍输入文件
+/// 5. 导出音频时每个呼叫输出一个双声道的 `call呼叫编号.wav`，左声道为先发送RTP的一侧，右声道为另一侧
+pub fn voip_report(input_path: &str, extract_dir: Option<&str>, audio_dir: Option<&str>) -> Result<()> {
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut calls: Vec<SipCall> = Vec::new();
+    let mut call_index: HashMap<String, usize> = HashMap::new();
+    let mut media: Vec<MediaEndpoint> = Vec::new();
+    let mut streams: Vec<RtpStream> = Vec::new();
+    let mut stream_index: HashMap<(FlowKey, u32), usize> = HashMap::new();
+    let mut packet_count = 0u64;
+    let mut sip_messages = 0u64;
+    let mut start_us = None;
+
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = interface.timestamp_ns(packet.timestamp).div_euclid(1000) as i64;
+        start_us.get_or_insert(time_us);
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let (Some(key), Some(Transport::Udp(_))) = (parsed.flow_key(), parsed.transport) else {
+            continue;
+        };
+        let payload = &data[parsed.payload_range(data.len())];
+
+        if let Some(message) = parse_sip(payload) {
+            sip_messages += 1;
+            let Some(call_id) = message.header("Call-ID", "i") else {
+                continue;
+            };
+            let index = *call_index.entry(call_id.to_string()).or_insert_with(|| {
+                calls.push(SipCall {
+                    call_id: call_id.to_string(),
+                    from: sip_uri(message.header("From", "f").unwrap_or("-")),
+                    to: sip_uri(message.header("To", "t").unwrap_or("-")),
+                    first_us: time_us,
+                    last_us: time_us,
+                    answer_us: None,
+                    end_us: None,
+                    failure: None,
+                    cancelled: false,
+                    codecs: HashMap::new(),
+                });
+                calls.len() - 1
+            });
+            let call = &mut calls[index];
+            call.last_us = time_us;
+            let cseq_method = message.header("CSeq", "CSeq").and_then(|cseq| cseq.split_whitespace().nth(1)).unwrap_or("");
+            match (message.method.as_deref(), message.status) {
+                (Some("BYE"), _) => {
+                    call.end_us.get_or_insert(time_us);
+                }
+                (Some("CANCEL"), _) => call.cancelled = true,
+                (None, Some(status)) if cseq_method == "INVITE" => {
+                    if (200..300).contains(&status) {
+                        call.answer_us.get_or_insert(time_us);
+                    } else if status >= 300 && call.answer_us.is_none() {
+                        call.failure = Some(status);
+                    }
+                }
+                _ => {}
+            }
+            if let Ok(sdp) = std::str::from_utf8(message.body) {
+                let sdp = parse_sdp(sdp);
+                for (addr, port) in sdp.endpoints {
+                    media.push(MediaEndpoint { addr, port, call: index, time_us });
+                }
+                call.codecs.extend(sdp.codecs);
+            }
+            continue;
+        }
+
+        if key.src_port == SIP_PORT || key.dst_port == SIP_PORT {
+            continue;
+        }
+        let Some(rtp) = parse_rtp(payload) else {
+            continue;
+        };
+        let index = *stream_index.entry((key, rtp.ssrc)).or_insert_with(|| {
+            streams.push(RtpStream {
+                key,
+                ssrc: rtp.ssrc,
+                payload_type: rtp.payload_type,
+                linktype: interface.linktype as u32,
+                snaplen: interface.snaplen,
+                packets: Vec::new(),
+            });
+            streams.len() - 1
+        });
+        let stream = &mut streams[index];
+        let audio = if audio_dir.is_some() && rtp.payload_type == stream.payload_type { payload[rtp.payload].to_vec() } else { Vec::new() };
+        stream.packets.push(RtpPacket { arrival_us: time_us, seq: rtp.seq, timestamp: rtp.timestamp, audio });
+    }
+
+    // 只保留SDP中声明过的媒体地址上的流，并关联到呼叫
+    let mut associated: Vec<(usize, RtpStream)> = streams
+        .into_iter()
+        .filter_map(|stream| associate(&media, &stream).map(|call| (call, stream)))
+        .collect();
+    associated.sort_by_key(|(call, stream)| (*call, stream.packets[0].arrival_us));
+    for (call, stream) in &associated {
+        calls[*call].last_us = calls[*call].last_us.max(stream.packets.last().map_or(0, |packet| packet.arrival_us));
+    }
+    let stats: Vec<StreamStats> = associated
+        .iter()
+        .map(|(call, stream)| stream_stats(stream, codec(&calls[*call], stream.payload_type).1))
+        .collect();
+
+    if let Some(dir) = extract_dir {
+        extract_streams(input_path, dir, &associated)?;
+    }
+    if let Some(dir) = audio_dir {
+        export_audio(dir, &calls, &associated)?;
+    }
+    print_report(input_path, packet_count, sip_messages, &calls, &associated, &stats, start_us.unwrap_or(0));
+    Ok(())
+}
+
+/// 解析SIP请求或响应，不是SIP时返回None
+fn parse_sip(payload: &[u8]) -> Option<SipMessage<'_>> {
+    let head_len = payload.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&payload[..head_len]).ok()?;
+    let mut lines = head.split("\r\n");
+    let start_line = lines.next()?;
+    let (method, status) = if let Some(rest) = start_line.strip_prefix("SIP/2.0 ") {
+        (None, Some(rest.split(' ').next()?.parse().ok()?))
+    } else {
+        let mut parts = start_line.split(' ');
+        let method = parts.next()?;
+        let uri = parts.next()?;
+        if parts.next() != Some("SIP/2.0") || !method.bytes().all(|b| b.is_ascii_uppercase()) || !uri.contains(':') {
+            return None;
+        }
+        (Some(method.to_string()), None)
+    };
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let mut message = SipMessage { method, status, headers, body: &[] };
+    let body = &payload[head_len + 4..];
+    let len = message.header("Content-Length", "l").and_then(|len| len.parse().ok()).unwrap_or(body.len());
+    message.body = &body[..len.min(body.len())];
+    Some(message)
+}
+
+/// 从 From/To 头部取出SIP URI (去掉显示名和tag等参数)
+fn sip_uri(value: &str) -> String {
+    match value.split_once('<').and_then(|(_, rest)| rest.split_once('>')) {
+        Some((uri, _)) => uri.to_string(),
+        None => value.split(';').next().unwrap_or(value).trim().to_string(),
+    }
+}
+
+/// 解析SDP中的媒体地址和rtpmap
+fn parse_sdp(sdp: &str) -> SessionDescription {
+    let mut session_addr = None;
+    // (端口, 媒体级的连接地址)
+    let mut media: Vec<(u16, Option<IpAddr>)> = Vec::new();
+    let mut codecs = Vec::new();
+    for line in sdp.lines().map(str::trim) {
+        if let Some(connection) = line.strip_prefix("c=") {
+            let addr = connection.split_whitespace().nth(2).and_then(|addr| addr.split('/').next()?.parse().ok());
+            match media.last_mut() {
+                Some((_, media_addr)) => *media_addr = addr,
+                None => session_addr = addr,
+            }
+        } else if let Some(description) = line.strip_prefix("m=") {
+            let mut fields = description.split_whitespace();
+            let port = fields.nth(1).and_then(|port| port.split('/').next()?.parse().ok()).unwrap_or(0);
+            // 端口0表示关闭的媒体流，仍然占位以免之后的c=行被归到上一个媒体流
+            media.push((port, None));
+        } else if let Some(rtpmap) = line.strip_prefix("a=rtpmap:")
+            && let Some((payload_type, encoding)) = rtpmap.split_once(' ')
+            && let Ok(payload_type) = payload_type.trim().parse::<u8>()
+        {
+            let mut parts = encoding.trim().split('/');
+            let name = parts.next().unwrap_or("").to_string();
+            let rate = parts.next().and_then(|rate| rate.parse().ok()).unwrap_or(8000);
+            codecs.push((payload_type, (name, rate)));
+        }
+    }
+    let endpoints = media
+        .into_iter()
+        .filter(|&(port, _)| port != 0)
+        .filter_map(|(port, addr)| addr.or(session_addr).map(|addr| (addr, port)))
+        .collect();
+    SessionDescription { endpoints, codecs }
+}
+
+/// 检查并解析RTP头，不像RTP时返回None
+pub fn parse_rtp(payload: &[u8]) -> Option<RtpHeader> {
+    if payload.len() < 12 || payload[0] >> 6 != 2 {
+        return None;
+    }
+    let csrc_count = (payload[0] & 0x0f) as usize;
+    let payload_type = payload[1] & 0x7f;
+    // 72-76是RTCP的包类型 (去掉标记位后)
+    let mut start = 12 + csrc_count * 4;
+    if payload.len() < start || (72..=76).contains(&payload_type) {
+        return None;
+    }
+    if payload[0] & 0x10 != 0 {
+        // 扩展头: 2字节标识、2字节长度 (32位字为单位)
+        if payload.len() < start + 4 {
+            return None;
+        }
+        start += 4 + read_u16(payload, start + 2) as usize * 4;
+    }
+    let mut end = payload.len();
+    if payload[0] & 0x20 != 0 {
+        end = end.checked_sub(payload[end - 1] as usize)?;
+    }
+    if start > end {
+        return None;
+    }
+    Some(RtpHeader {
+        ssrc: read_u32(payload, 8),
+        payload_type,
+        seq: read_u16(payload, 2),
+        timestamp: read_u32(payload, 4),
+        payload: start..end,
+    })
+}
+
+/// 找到流所属的呼叫: 目的或源地址在SDP中声明过，多个呼叫使用同一地址时取流开始前最近的一个
+fn associate(media: &[MediaEndpoint], stream: &RtpStream) -> Option<usize> {
+    let start_us = stream.packets[0].arrival_us;
+    let key = &stream.key;
+    let candidates: Vec<&MediaEndpoint> = media
+        .iter()
+        .filter(|endpoint| {
+            (endpoint.addr == key.dst && endpoint.port == key.dst_port) || (endpoint.addr == key.src && endpoint.port == key.src_port)
+        })
+        .collect();
+    candidates
+        .iter()
+        .filter(|endpoint| endpoint.time_us <= start_us)
+        .max_by_key(|endpoint| endpoint.time_us)
+        .or(candidates.first())
+        .map(|endpoint| endpoint.call)
+}
+
+/// 负载类型的编码名和时钟频率: 优先取SDP中的rtpmap，其次为常见的静态负载类型
+fn codec(call: &SipCall, payload_type: u8) -> (String, u32) {
+    if let Some(codec) = call.codecs.get(&payload_type) {
+        return codec.clone();
+    }
+    let name = match payload_type {
+        0 => "PCMU",
+        3 => "GSM",
+        4 => "G723",
+        8 => "PCMA",
+        9 => "G722",
+        18 => "G729",
+        _ => return (format!("PT{}", payload_type), 8000),
+    };
+    (name.to_string(), 8000)
+}
+
+fn stream_stats(stream: &RtpStream, clock_rate: u32) -> StreamStats {
+    let rate = clock_rate.max(1) as f64;
+    let first = &stream.packets[0];
+    let (mut min_ext, mut max_ext) = (first.seq as i64, first.seq as i64);
+    let mut out_of_order = 0;
+    let (mut jitter, mut jitter_sum, mut max_jitter) = (0.0f64, 0.0f64, 0.0f64);
+    let mut max_delta_us = 0;
+    for pair in stream.packets.windows(2) {
+        let (previous, packet) = (&pair[0], &pair[1]);
+        // 按与当前最大序列号的差扩展16位序列号，处理回绕
+        let delta = packet.seq.wrapping_sub(max_ext as u16) as i16 as i64;
+        let ext = max_ext + delta;
+        if delta < 0 {
+            out_of_order += 1;
+        }
+        min_ext = min_ext.min(ext);
+        max_ext = max_ext.max(ext);
+
+        let arrival = (packet.arrival_us - previous.arrival_us) as f64 * rate / 1_000_000.0;
+        let sent = packet.timestamp.wrapping_sub(previous.timestamp) as i32 as f64;
+        jitter += ((arrival - sent).abs() - jitter) / 16.0;
+        jitter_sum += jitter;
+        max_jitter = max_jitter.max(jitter);
+        max_delta_us = max_delta_us.max(packet.arrival_us - previous.arrival_us);
+    }
+    let expected = (max_ext - min_ext + 1) as u64;
+    let lost = expected.saturating_sub(stream.packets.len() as u64);
+    let samples = (stream.packets.len() - 1).max(1) as f64;
+    let mean_jitter_ms = jitter_sum / samples / rate * 1000.0;
+    let loss_percent = if expected > 0 { lost as f64 * 100.0 / expected as f64 } else { 0.0 };
+    StreamStats {
+        expected,
+        lost,
+        out_of_order,
+        mean_jitter_ms,
+        max_jitter_ms: max_jitter / rate * 1000.0,
+        max_delta_ms: max_delta_us as f64 / 1000.0,
+        mos: estimate_mos(mean_jitter_ms, loss_percent),
+    }
+}
+
+/// 简化的E模型: 有效时延取抖动的两倍加10毫秒编解码时延，每1%丢包R值减2.5
+fn estimate_mos(jitter_ms: f64, loss_percent: f64) -> f64 {
+    let latency = jitter_ms * 2.0 + 10.0;
+    let mut r = if latency < 160.0 { 93.2 - latency / 40.0 } else { 93.2 - (latency - 120.0) / 10.0 };
+    r -= loss_percent * 2.5;
+    let r = r.clamp(0.0, 100.0);
+    (1.0 + 0.035 * r + 0.000007 * r * (r - 60.0) * (100.0 - r)).clamp(1.0, 4.5)
+}
+
+/// 再读一遍输入文件，每个RTP流写入一个文件 (按 `--output-format` 为PCAP或pcapng)
+fn extract_streams(input_path: &str, output_dir: &str, streams: &[(usize, RtpStream)]) -> Result<()> {
+    fs::create_dir_all(Path::new(output_dir))
+        .map_err(|e| PcapError::WriteFailure(format!("无法创建输出目录: {}: {}", output_dir, e)))?;
+    let extension = match output_format() {
+        OutputFormat::Pcapng => "pcapng",
+        OutputFormat::Auto | OutputFormat::Pcap => "pcap",
+    };
+    let mut writers = Vec::new();
+    let mut index: HashMap<(FlowKey, u32), usize> = HashMap::new();
+    for (call, stream) in streams {
+        let file_name = format!("call{:03}_{:08x}.{}{}", call + 1, stream.ssrc, extension, compression().suffix());
+        let path = Path::new(output_dir).join(file_name);
+        let path = path.to_str().unwrap().to_string();
+        let out_file = create_output(&path, compression())?;
+        let mut header = PcapHeader::with_datalink(Datalink::from(stream.linktype));
+        if stream.snaplen != 0 {
+            header.snaplen = stream.snaplen;
+        }
+        let pcap_writer = PacketWriter::with_header(header, out_file, &path)
+            .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+        index.insert((stream.key, stream.ssrc), writers.len());
+        writers.push((pcap_writer, stream.linktype, OutputDigest::default(), path));
+    }
+
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut written = 0u64;
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let linktype = interface.linktype as u32;
+        let parsed = parse_packet(&packet.data, linktype);
+        let (Some(key), Some(Transport::Udp(_))) = (parsed.flow_key(), parsed.transport) else {
+            continue;
+        };
+        let Some(rtp) = parse_rtp(&packet.data[parsed.payload_range(packet.data.len())]) else {
+            continue;
+        };
+        let Some(&i) = index.get(&(key, rtp.ssrc)) else {
+            continue;
+        };
+        let (pcap_writer, stream_linktype, digest, _) = &mut writers[i];
+        // 同一五元组出现在不同链路层类型的接口上时只导出与第一个包相同的
+        if *stream_linktype != linktype {
+            continue;
+        }
+        let time_us = interface.timestamp_ns(packet.timestamp).div_euclid(1000) as i64;
+        let (ts_sec, ts_usec) = split_timestamp_us(time_us)?;
+        let mut out_packet = Packet::new_owned(ts_sec, ts_usec, packet.data.len() as u32, packet.data);
+        out_packet.header.orig_len = packet.orig_len;
+        pcap_writer.write_packet(&out_packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包失败: {}", e)))?;
+        digest.record_pcap(&out_packet);
+        written += 1;
+    }
+    for (pcap_writer, _, digest, path) in writers {
+        pcap_writer.into_inner().finish()?;
+        digest.verify(&path)?;
+    }
+    info!("已导出 {} 个RTP流 ({} 个包) 到 {}", streams.len(), written, output_dir);
+    Ok(())
+}
+
+/// 把每个呼叫的G.711流解码为双声道WAV: 左声道为与呼叫的第一个流同一侧发出的流，右声道为另一侧
+///
+/// 各流按第一个包的到达时间对齐，流内按RTP时间戳放置采样，丢失的包为静音。
+fn export_audio(output_dir: &str, calls: &[SipCall], streams: &[(usize, RtpStream)]) -> Result<()> {
+    fs::create_dir_all(Path::new(output_dir))
+        .map_err(|e| PcapError::WriteFailure(format!("无法创建输出目录: {}: {}", output_dir, e)))?;
+    let max_samples = (AUDIO_SAMPLE_RATE * MAX_AUDIO_SECONDS) as usize;
+    let mut file_count = 0;
+    for (call_number, call) in calls.iter().enumerate() {
+        let mut decodable = Vec::new();
+        for (_, stream) in streams.iter().filter(|(stream_call, _)| *stream_call == call_number) {
+            let (name, rate) = codec(call, stream.payload_type);
+            let decode: fn(u8) -> i16 = match name.to_ascii_uppercase().as_str() {
+                "PCMU" if rate == AUDIO_SAMPLE_RATE => ulaw_to_linear,
+                "PCMA" if rate == AUDIO_SAMPLE_RATE => alaw_to_linear,
+                _ => {
+                    warn!("呼叫 {} 的RTP流 0x{:08x} 编码为 {}，只支持导出G.711 (PCMU/PCMA) 音频", call_number + 1, stream.ssrc, name);
+                    continue;
+                }
+            };
+            decodable.push((stream, decode));
+        }
+        let Some(start_us) = decodable.iter().map(|(stream, _)| stream.packets[0].arrival_us).min() else {
+            continue;
+        };
+
+        let caller = (decodable[0].0.key.src, decodable[0].0.key.src_port);
+        let mut channels: [Vec<i16>; 2] = [Vec::new(), Vec::new()];
+        let mut truncated = false;
+        for (stream, decode) in &decodable {
+            let channel = &mut channels[usize::from((stream.key.src, stream.key.src_port) != caller)];
+            let first = &stream.packets[0];
+            let offset = (first.arrival_us - start_us) * AUDIO_SAMPLE_RATE as i64 / 1_000_000;
+            for packet in &stream.packets {
+                // 比第一个包更早的时间戳 (乱序到达) 放到流开始之前
+                let position = offset + packet.timestamp.wrapping_sub(first.timestamp) as i32 as i64;
+                if position < 0 || packet.audio.is_empty() {
+                    continue;
+                }
+                let position = position as usize;
+                let end = position + packet.audio.len();
+                if end > max_samples {
+                    truncated = true;
+                    continue;
+                }
+                if channel.len() < end {
+                    channel.resize(end, 0);
+                }
+                for (sample, &byte) in channel[position..end].iter_mut().zip(&packet.audio) {
+                    *sample = decode(byte);
+                }
+            }
+        }
+        if truncated {
+            warn!("呼叫 {} 的音频超过 {} 秒，超出部分没有导出", call_number + 1, MAX_AUDIO_SECONDS);
+        }
+        let path = Path::new(output_dir).join(format!("call{:03}.wav", call_number + 1));
+        write_wav(path.to_str().unwrap(), &channels)?;
+        file_count += 1;
+    }
+    info!("已导出 {} 个呼叫的音频到 {}", file_count, output_dir);
+    Ok(())
+}
+
+/// G.711 μ律解码为16位线性PCM
+fn ulaw_to_linear(byte: u8) -> i16 {
+    let byte = !byte;
+    let magnitude = ((((byte & 0x0f) as i32) << 3) + 0x84) << ((byte & 0x70) >> 4);
+    (if byte & 0x80 != 0 { 0x84 - magnitude } else { magnitude - 0x84 }) as i16
+}
+
+/// G.711 A律解码为16位线性PCM
+fn alaw_to_linear(byte: u8) -> i16 {
+    let byte = byte ^ 0x55;
+    let segment = (byte & 0x70) >> 4;
+    let mut magnitude = ((byte & 0x0f) as i32) << 4;
+    magnitude += if segment == 0 { 8 } else { 0x108 };
+    if segment > 1 {
+        magnitude <<= segment - 1;
+    }
+    (if byte & 0x80 != 0 { magnitude } else { -magnitude }) as i16
+}
+
+/// 写入8kHz、16位的双声道WAV文件，较短的声道补静音
+fn write_wav(path: &str, channels: &[Vec<i16>; 2]) -> Result<()> {
+    let frames = channels[0].len().max(channels[1].len());
+    let data_len = (frames * 4) as u32;
+    let mut writer = BufWriter::new(File::create(Path::new(path)).map_err(|e| PcapError::create_failed(path, e))?);
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    // PCM格式、2个声道、采样率、每秒字节数、每帧字节数、每个采样的位数
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?;
+    writer.write_all(&AUDIO_SAMPLE_RATE.to_le_bytes())?;
+    writer.write_all(&(AUDIO_SAMPLE_RATE * 4).to_le_bytes())?;
+    writer.write_all(&4u16.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    for frame in 0..frames {
+        for channel in channels {
+            writer.write_all(&channel.get(frame).copied().unwrap_or(0).to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn print_report(
+    input_path: &str,
+    packet_count: u64,
+    sip_messages: u64,
+    calls: &[SipCall],
+    streams: &[(usize, RtpStream)],
+    stats: &[StreamStats],
+    start_us: i64,
+) {
+    let answered = calls.iter().filter(|call| call.answer_us.is_some()).count();
+    let failed = calls.iter().filter(|call| call.answer_us.is_none() && call.failure.is_some()).count();
+    let poor: Vec<usize> = (0..stats.len()).filter(|&i| stats[i].mos < POOR_MOS).collect();
+    let lost: u64 = stats.iter().map(|stats| stats.lost).sum();
+    let expected: u64 = stats.iter().map(|stats| stats.expected).sum();
+    let warning = |value: usize| (value > 0).then_some(Severity::Warning);
+
+    print_heading(&format!("VoIP分析: {}", input_path));
+    print_fields(&[
+        ("数据包数", packet_count.to_string(), None),
+        ("SIP消息数", sip_messages.to_string(), None),
+        ("呼叫数", calls.len().to_string(), None),
+        ("接通的呼叫", answered.to_string(), None),
+        ("失败的呼叫", failed.to_string(), warning(failed)),
+        ("RTP流数", streams.len().to_string(), None),
+        (
+            "RTP丢包",
+            format!("{} ({:.2}%)", lost, if expected > 0 { lost as f64 * 100.0 / expected as f64 } else { 0.0 }),
+            (lost > 0).then_some(Severity::Warning),
+        ),
+        ("质量差的RTP流", poor.len().to_string(), warning(poor.len())),
+    ]);
+
+    let max_rows = if verbosity() == Verbosity::Verbose { usize::MAX } else { MAX_ROWS };
+    if !calls.is_empty() {
+        print_section("SIP呼叫:");
+        let mut table = Table::new(&["呼叫", "开始时间", "主叫", "被叫", "状态", "时长(s)", "RTP流", "Call-ID"]).right_align(&[0, 5, 6]);
+        for (i, call) in calls.iter().enumerate().take(max_rows) {
+            table.add_row(vec![
+                (i + 1).to_string(),
+                format_timestamp_us(call.first_us, start_us),
+                call.from.clone(),
+                call.to.clone(),
+                call.state(),
+                call.duration_us().map_or_else(|| "-".to_string(), |us| format!("{:.3}", us as f64 / 1_000_000.0)),
+                streams.iter().filter(|(stream_call, _)| *stream_call == i).count().to_string(),
+                call.call_id.clone(),
+            ]);
+        }
+        table.print();
+        if calls.len() > max_rows {
+            println!("  ... 还有 {} 个呼叫 (使用 --verbose 查看全部)", calls.len() - max_rows);
+        }
+    }
+
+    if !streams.is_empty() {
+        print_section("RTP流:");
+        let mut table = Table::new(&[
+            "呼叫", "五元组", "SSRC", "编码", "包数", "丢包", "丢包率", "乱序", "平均抖动(ms)", "最大抖动(ms)", "最大间隔(ms)", "MOS",
+        ])
+        .right_align(&[0, 4, 5, 6, 7, 8, 9, 10, 11]);
+        for ((call, stream), stats) in streams.iter().zip(stats).take(max_rows) {
+            table.add_row(vec![
+                (call + 1).to_string(),
+                stream.key.to_string(),
+                format!("0x{:08x}", stream.ssrc),
+                codec(&calls[*call], stream.payload_type).0,
+                stream.packets.len().to_string(),
+                stats.lost.to_string(),
+                format!("{:.2}%", stats.loss_percent()),
+                stats.out_of_order.to_string(),
+                format!("{:.2}", stats.mean_jitter_ms),
+                format!("{:.2}", stats.max_jitter_ms),
+                format!("{:.2}", stats.max_delta_ms),
+                format!("{:.2}", stats.mos),
+            ]);
+        }
+        table.print();
+        if streams.len() > max_rows {
+            println!("  ... 还有 {} 个RTP流 (使用 --verbose 查看全部)", streams.len() - max_rows);
+        }
+    }
+
+    print_separator();
+    if calls.is_empty() {
+        print_verdict(Severity::Warning, "没有找到SIP呼叫 (只识别UDP上的SIP)");
+    } else if !poor.is_empty() {
+        print_verdict(
+            Severity::Warning,
+            &format!("{} 个RTP流的MOS估计值低于 {:.1}，通话质量可能较差", poor.len(), POOR_MOS),
+        );
+    } else {
+        print_verdict(Severity::Ok, &format!("{} 个呼叫、{} 个RTP流，通话质量良好", calls.len(), streams.len()));
+    }
+}
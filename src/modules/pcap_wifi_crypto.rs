@@ -0,0 +1,559 @@
+/// AES S盒 (按有限域求逆并做仿射变换生成)
+const SBOX: [u8; 256] = {
+    let mut sbox = [0u8; 256];
+    let mut p: u8 = 1;
+    let mut q: u8 = 1;
+    loop {
+        // p乘以3，q除以3，保持 q 为 p 的乘法逆元
+        p = p ^ (p << 1) ^ if p & 0x80 != 0 { 0x1b } else { 0 };
+        q ^= q << 1;
+        q ^= q << 2;
+        q ^= q << 4;
+        if q & 0x80 != 0 {
+            q ^= 0x09;
+        }
+        let affine = q ^ q.rotate_left(1) ^ q.rotate_left(2) ^ q.rotate_left(3) ^ q.rotate_left(4);
+        sbox[p as usize] = affine ^ 0x63;
+        if p == 1 {
+            break;
+        }
+    }
+    sbox[0] = 0x63;
+    sbox
+};
+
+/// AES逆S盒
+const INV_SBOX: [u8; 256] = {
+    let mut inverse = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inverse[SBOX[i] as usize] = i as u8;
+        i += 1;
+    }
+    inverse
+};
+
+/// 计算SHA-1摘要
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..40 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, value) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+/// 计算HMAC-SHA1，消息可以分成多段
+pub fn hmac_sha1(key: &[u8], parts: &[&[u8]]) -> [u8; 20] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..20].copy_from_slice(&sha1(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    for part in parts {
+        inner.extend_from_slice(part);
+    }
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&sha1(&inner));
+    sha1(&outer)
+}
+
+/// SHA-256的轮常数
+const SHA256_K: [u32; 64] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5,
+    0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174,
+    0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+    0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967,
+    0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+    0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+    0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+    0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+];
+
+/// 计算SHA-256摘要
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a, 0x510e_527f, 0x9b05_688c, 0x1f83_d9ab, 0x5be0_cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (&word, &k) in w.iter().zip(&SHA256_K) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(k).wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (chunk, value) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+/// 计算HMAC-SHA256，消息可以分成多段
+pub fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    for part in parts {
+        inner.extend_from_slice(part);
+    }
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+/// 由口令和SSID计算WPA2的PMK (PBKDF2-HMAC-SHA1，4096次迭代，32字节)
+pub fn wpa_pmk(passphrase: &[u8], ssid: &[u8]) -> [u8; 32] {
+    let mut pmk = [0u8; 32];
+    for (index, chunk) in pmk.chunks_mut(20).enumerate() {
+        let counter = (index as u32 + 1).to_be_bytes();
+        let mut u = hmac_sha1(passphrase, &[ssid, &counter]);
+        let mut t = u;
+        for _ in 1..4096 {
+            u = hmac_sha1(passphrase, &[&u]);
+            for (t, u) in t.iter_mut().zip(u) {
+                *t ^= u;
+            }
+        }
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+    pmk
+}
+
+/// IEEE 802.11的PRF (基于HMAC-SHA1)，输出 `len` 字节
+pub fn prf(key: &[u8], label: &str, data: &[u8], len: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(len + 20);
+    let mut counter = 0u8;
+    while output.len() < len {
+        output.extend_from_slice(&hmac_sha1(key, &[label.as_bytes(), &[0], data, &[counter]]));
+        counter += 1;
+    }
+    output.truncate(len);
+    output
+}
+
+/// AES-128 (轮密钥在创建时展开)
+pub struct Aes128 {
+    round_keys: [[u8; 16]; 11],
+}
+
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// 有限域GF(2^8)上的乘法
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+impl Aes128 {
+    pub fn new(key: &[u8; 16]) -> Aes128 {
+        let mut round_keys = [[0u8; 16]; 11];
+        round_keys[0] = *key;
+        let mut rcon = 1u8;
+        for round in 1..11 {
+            let previous = round_keys[round - 1];
+            let mut word = [previous[13], previous[14], previous[15], previous[12]];
+            for b in &mut word {
+                *b = SBOX[*b as usize];
+            }
+            word[0] ^= rcon;
+            rcon = xtime(rcon);
+            let mut next = [0u8; 16];
+            for i in 0..16 {
+                let before = if i < 4 { word[i] } else { next[i - 4] };
+                next[i] = previous[i] ^ before;
+            }
+            round_keys[round] = next;
+        }
+        Aes128 { round_keys }
+    }
+
+    pub fn encrypt(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut state = *block;
+        add_round_key(&mut state, &self.round_keys[0]);
+        for round in 1..11 {
+            for b in &mut state {
+                *b = SBOX[*b as usize];
+            }
+            shift_rows(&mut state, false);
+            if round < 10 {
+                for column in state.chunks_mut(4) {
+                    let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+                    let all = a0 ^ a1 ^ a2 ^ a3;
+                    column[0] ^= all ^ xtime(a0 ^ a1);
+                    column[1] ^= all ^ xtime(a1 ^ a2);
+                    column[2] ^= all ^ xtime(a2 ^ a3);
+                    column[3] ^= all ^ xtime(a3 ^ a0);
+                }
+            }
+            add_round_key(&mut state, &self.round_keys[round]);
+        }
+        state
+    }
+
+    pub fn decrypt(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut state = *block;
+        add_round_key(&mut state, &self.round_keys[10]);
+        for round in (0..10).rev() {
+            shift_rows(&mut state, true);
+            for b in &mut state {
+                *b = INV_SBOX[*b as usize];
+            }
+            add_round_key(&mut state, &self.round_keys[round]);
+            if round > 0 {
+                for column in state.chunks_mut(4) {
+                    let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+                    column[0] = gf_mul(a0, 14) ^ gf_mul(a1, 11) ^ gf_mul(a2, 13) ^ gf_mul(a3, 9);
+                    column[1] = gf_mul(a0, 9) ^ gf_mul(a1, 14) ^ gf_mul(a2, 11) ^ gf_mul(a3, 13);
+                    column[2] = gf_mul(a0, 13) ^ gf_mul(a1, 9) ^ gf_mul(a2, 14) ^ gf_mul(a3, 11);
+                    column[3] = gf_mul(a0, 11) ^ gf_mul(a1, 13) ^ gf_mul(a2, 9) ^ gf_mul(a3, 14);
+                }
+            }
+        }
+        state
+    }
+}
+
+fn add_round_key(state: &mut [u8; 16], key: &[u8; 16]) {
+    for (b, k) in state.iter_mut().zip(key) {
+        *b ^= k;
+    }
+}
+
+/// 第r行循环左移r字节 (状态按列存放)，`inverse` 时右移
+fn shift_rows(state: &mut [u8; 16], inverse: bool) {
+    let original = *state;
+    for row in 1..4 {
+        for column in 0..4 {
+            let from = if inverse { (column + 4 - row) % 4 } else { (column + row) % 4 };
+            state[column * 4 + row] = original[from * 4 + row];
+        }
+    }
+}
+
+/// AES密钥解包 (RFC 3394)，校验值不符时返回None
+pub fn aes_key_unwrap(kek: &[u8; 16], wrapped: &[u8]) -> Option<Vec<u8>> {
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return None;
+    }
+    let aes = Aes128::new(kek);
+    let n = wrapped.len() / 8 - 1;
+    let mut a: [u8; 8] = wrapped[..8].try_into().unwrap();
+    let mut r: Vec<[u8; 8]> = wrapped[8..].chunks(8).map(|chunk| chunk.try_into().unwrap()).collect();
+    for j in (0..6).rev() {
+        for i in (0..n).rev() {
+            let t = (n * j + i + 1) as u64;
+            let mut block = [0u8; 16];
+            block[..8].copy_from_slice(&(u64::from_be_bytes(a) ^ t).to_be_bytes());
+            block[8..].copy_from_slice(&r[i]);
+            let b = aes.decrypt(&block);
+            a.copy_from_slice(&b[..8]);
+            r[i].copy_from_slice(&b[8..]);
+        }
+    }
+    (a == [0xa6; 8]).then(|| r.concat())
+}
+
+/// CCMP解密 (AES-CCM，MIC为8字节，长度字段为2字节)，MIC校验失败时返回None
+///
+/// - `nonce`: 13字节的CCM随机数 (优先级、发送地址、包编号)
+/// - `aad`: 附加认证数据 (屏蔽了可变字段的802.11头)
+/// - `data`: 密文及其后的8字节MIC
+pub fn ccmp_decrypt(tk: &[u8; 16], nonce: &[u8; 13], aad: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let payload_len = data.len().checked_sub(8)?;
+    if payload_len > u16::MAX as usize || aad.len() > 30 {
+        return None;
+    }
+    let aes = Aes128::new(tk);
+    let counter_block = |counter: u16| {
+        let mut block = [0u8; 16];
+        block[0] = 0x01;
+        block[1..14].copy_from_slice(nonce);
+        block[14..].copy_from_slice(&counter.to_be_bytes());
+        aes.encrypt(&block)
+    };
+
+    let (ciphertext, mic) = data.split_at(payload_len);
+    let mut plaintext = Vec::with_capacity(payload_len);
+    for (index, chunk) in ciphertext.chunks(16).enumerate() {
+        let keystream = counter_block(index as u16 + 1);
+        plaintext.extend(chunk.iter().zip(keystream).map(|(c, k)| c ^ k));
+    }
+
+    // CBC-MAC: B0、带2字节长度前缀的AAD、明文，各自补零到16字节
+    let mut b0 = [0u8; 16];
+    b0[0] = 0x59;
+    b0[1..14].copy_from_slice(nonce);
+    b0[14..].copy_from_slice(&(payload_len as u16).to_be_bytes());
+    let mut mac = aes.encrypt(&b0);
+    let mut header = (aad.len() as u16).to_be_bytes().to_vec();
+    header.extend_from_slice(aad);
+    for chunk in header.chunks(16).chain(plaintext.chunks(16)) {
+        for (m, b) in mac.iter_mut().zip(chunk) {
+            *m ^= b;
+        }
+        mac = aes.encrypt(&mac);
+    }
+    let s0 = counter_block(0);
+    let expected: Vec<u8> = mac[..8].iter().zip(s0).map(|(m, s)| m ^ s).collect();
+    (expected == mic).then_some(plaintext)
+}
+
+/// GF(2^128)上的乘法 (GCM的位序: 最高位为x^0)
+fn gf128_mul(x: u128, y: u128) -> u128 {
+    let mut product = 0u128;
+    let mut v = y;
+    for bit in (0..128).rev() {
+        if (x >> bit) & 1 != 0 {
+            product ^= v;
+        }
+        v = if v & 1 != 0 { (v >> 1) ^ (0xe1 << 120) } else { v >> 1 };
+    }
+    product
+}
+
+/// AES-128-GCM解密 (12字节随机数，标签为16字节)，标签校验失败时返回None
+///
+/// - `aad`: 附加认证数据
+/// - `data`: 密文及其后的16字节标签
+pub fn gcm_decrypt(key: &[u8; 16], nonce: &[u8; 12], aad: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let payload_len = data.len().checked_sub(16)?;
+    let aes = Aes128::new(key);
+    let counter_block = |counter: u32| {
+        let mut block = [0u8; 16];
+        block[..12].copy_from_slice(nonce);
+        block[12..].copy_from_slice(&counter.to_be_bytes());
+        aes.encrypt(&block)
+    };
+    let (ciphertext, tag) = data.split_at(payload_len);
+
+    // GHASH: AAD和密文各自补零到16字节，最后是两者的比特长度
+    let h = u128::from_be_bytes(aes.encrypt(&[0u8; 16]));
+    let mut hash = 0u128;
+    for chunk in aad.chunks(16).chain(ciphertext.chunks(16)) {
+        let mut block = [0u8; 16];
+        block[..chunk.len()].copy_from_slice(chunk);
+        hash = gf128_mul(hash ^ u128::from_be_bytes(block), h);
+    }
+    let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+    hash = gf128_mul(hash ^ lengths, h);
+    let expected = (hash ^ u128::from_be_bytes(counter_block(1))).to_be_bytes();
+    if expected != tag {
+        return None;
+    }
+
+    let mut plaintext = Vec::with_capacity(payload_len);
+    for (index, chunk) in ciphertext.chunks(16).enumerate() {
+        let keystream = counter_block(index as u32 + 2);
+        plaintext.extend(chunk.iter().zip(keystream).map(|(c, k)| c ^ k));
+    }
+    Some(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::pcap_payload_filter::parse_hex_bytes;
+
+    fn hex(text: &str) -> Vec<u8> {
+        parse_hex_bytes(text).unwrap()
+    }
+
+    #[test]
+    fn sha1_matches_fips_180() {
+        assert_eq!(sha1(b"abc").to_vec(), hex("a9993e364706816aba3e25717850c26c9cd0d89d"));
+        assert_eq!(
+            sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq").to_vec(),
+            hex("84983e441c3bd26ebaae4aa1f95129e5e54670f1")
+        );
+    }
+
+    #[test]
+    fn sha256_matches_fips_180() {
+        assert_eq!(
+            sha256(b"abc").to_vec(),
+            hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq").to_vec(),
+            hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")
+        );
+        // 跨越多个块
+        assert_eq!(
+            sha256(&[b'a'; 1000]).to_vec(),
+            hex("41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3")
+        );
+    }
+
+    #[test]
+    fn hmac_matches_rfc_2202_and_4231() {
+        // 数据可以分成多段传入
+        assert_eq!(
+            hmac_sha1(b"Jefe", &[b"what do ya want ", b"for nothing?"]).to_vec(),
+            hex("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79")
+        );
+        assert_eq!(
+            hmac_sha256(b"Jefe", &[b"what do ya want for nothing?"]).to_vec(),
+            hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+    }
+
+    #[test]
+    fn wpa_pmk_and_prf_match_ieee_802_11() {
+        assert_eq!(
+            wpa_pmk(b"password", b"IEEE").to_vec(),
+            hex("f42c6fc52df0ebef9ebb4b90b38a5f902e83fe1b135a70e23aed762e9710a12e")
+        );
+        assert_eq!(
+            prf(&[0x0b; 20], "prefix", b"Hi There", 64),
+            hex(
+                "bcd4c650b30b9684951829e0d75f9d54b862175ed9f00606e17d8da35402ffee\
+                 75df78c3d31e0f889f012120c0862beb67753e7439ae242edb8373698356cf5a"
+            )
+        );
+    }
+
+    #[test]
+    fn aes128_matches_fips_197() {
+        let aes = Aes128::new(&hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap());
+        let plaintext: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let ciphertext = aes.encrypt(&plaintext);
+        assert_eq!(ciphertext.to_vec(), hex("69c4e0d86a7b0430d8cdb78070b4c55a"));
+        assert_eq!(aes.decrypt(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn aes_key_unwrap_matches_rfc_3394() {
+        let kek = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+        let mut wrapped = hex("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5");
+        assert_eq!(aes_key_unwrap(&kek, &wrapped), Some(hex("00112233445566778899aabbccddeeff")));
+        wrapped[0] ^= 1;
+        assert_eq!(aes_key_unwrap(&kek, &wrapped), None);
+    }
+
+    #[test]
+    fn ccmp_decrypt_matches_rfc_3610() {
+        // RFC 3610 第1组: M=8、L=2，与CCMP相同
+        let key = hex("c0c1c2c3c4c5c6c7c8c9cacbcccdcecf").try_into().unwrap();
+        let nonce = hex("00000003020100a0a1a2a3a4a5").try_into().unwrap();
+        let aad = hex("0001020304050607");
+        let mut data = hex("588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0");
+        assert_eq!(
+            ccmp_decrypt(&key, &nonce, &aad, &data),
+            Some(hex("08090a0b0c0d0e0f101112131415161718191a1b1c1d1e"))
+        );
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert_eq!(ccmp_decrypt(&key, &nonce, &aad, &data), None);
+    }
+
+    #[test]
+    fn gcm_decrypt_matches_nist_vectors() {
+        // 测试用例2: 全零的密钥、随机数和明文
+        assert_eq!(
+            gcm_decrypt(&[0; 16], &[0; 12], &[], &hex("0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf")),
+            Some(vec![0; 16])
+        );
+
+        // 测试用例4: 带附加认证数据，明文不是块长的整数倍
+        let key = hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap();
+        let nonce = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let mut data = hex(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091\
+             5bc94fbc3221a5db94fae95ae7121a47",
+        );
+        assert_eq!(
+            gcm_decrypt(&key, &nonce, &aad, &data),
+            Some(hex(
+                "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                 1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39"
+            ))
+        );
+        data[0] ^= 1;
+        assert_eq!(gcm_decrypt(&key, &nonce, &aad, &data), None);
+    }
+}
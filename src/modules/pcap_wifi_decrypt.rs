@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::modules::io::{PacketReader, PacketWriter};
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_fcs::crc32;
+use crate::modules::pcap_output::{compression, create_output};
+use crate::modules::pcap_packet_parser::{
+    LINKTYPE_IEEE802_11, LINKTYPE_IEEE802_11_RADIOTAP, linktype_name, parse_wlan_header, read_u16,
+};
+use crate::modules::pcap_verify::OutputDigest;
+use crate::modules::pcap_wifi_crypto::{aes_key_unwrap, ccmp_decrypt, hmac_sha1, prf, wpa_pmk};
+
+const ETHERTYPE_EAPOL: u16 = 0x888e;
+
+/// LLC/SNAP头 (以太网类型之前的6字节)
+const SNAP_HEADER: [u8; 6] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00];
+
+/// CCMP头和MIC的长度
+const CCMP_HEADER_LEN: usize = 8;
+const CCMP_MIC_LEN: usize = 8;
+
+/// 帧控制字段第2字节: 受保护帧
+const FC_PROTECTED: u8 = 0x40;
+
+/// EAPOL-Key的密钥信息字段
+const KEY_INFO_VERSION: u16 = 0x0007;
+const KEY_INFO_PAIRWISE: u16 = 0x0008;
+const KEY_INFO_ACK: u16 = 0x0080;
+const KEY_INFO_MIC: u16 = 0x0100;
+const KEY_INFO_ENCRYPTED_DATA: u16 = 0x1000;
+
+/// 密钥描述符版本2: HMAC-SHA1-128 MIC，AES密钥封装 (CCMP)
+const KEY_VERSION_AES: u16 = 2;
+
+/// RSN密钥描述符类型
+const KEY_DESCRIPTOR_RSN: u8 = 2;
+
+type Mac = [u8; 6];
+
+/// 成对临时密钥中用到的部分
+#[derive(Clone, Copy)]
+struct Ptk {
+    kck: [u8; 16],
+    kek: [u8; 16],
+    tk: [u8; 16],
+}
+
+/// 一个AP与客户端之间的握手状态
+#[derive(Default)]
+struct Session {
+    /// 消息1或3中AP的随机数
+    anonce: Option<[u8; 32]>,
+    /// 消息2中客户端的随机数和整个EAPOL帧 (用于校验MIC)
+    pending: Option<([u8; 32], Vec<u8>)>,
+    ptk: Option<Ptk>,
+    /// 已报告过MIC校验失败
+    mic_failed: bool,
+}
+
+/// 一个EAPOL-Key帧中用到的字段
+struct EapolKey<'a> {
+    key_info: u16,
+    nonce: [u8; 32],
+    mic: [u8; 16],
+    key_data: &'a [u8],
+    /// 整个EAPOL帧
+    frame: &'a [u8],
+}
+
+/// 解析EAPOL-Key帧 (只支持RSN密钥描述符)
+fn parse_eapol_key(eapol: &[u8]) -> Option<EapolKey<'_>> {
+    if eapol.len() < 99 || eapol[1] != 3 || eapol[4] != KEY_DESCRIPTOR_RSN {
+        return None;
+    }
+    let frame = eapol.get(..4 + read_u16(eapol, 2) as usize)?;
+    let key_data_len = read_u16(frame, 97) as usize;
+    Some(EapolKey {
+        key_info: read_u16(frame, 5),
+        nonce: frame[17..49].try_into().unwrap(),
+        mic: frame[81..97].try_into().unwrap(),
+        key_data: frame.get(99..99 + key_data_len)?,
+        frame,
+    })
+}
+
+impl EapolKey<'_> {
+    /// 用KCK校验MIC (计算时MIC字段置零)
+    fn verify_mic(&self, kck: &[u8; 16]) -> bool {
+        let mut frame = self.frame.to_vec();
+        frame[81..97].fill(0);
+        hmac_sha1(kck, &[&frame])[..16] == self.mic
+    }
+}
+
+/// 从 (解包后的) 密钥数据中取出GTK KDE (密钥编号, GTK)
+fn find_gtk(key_data: &[u8]) -> Option<(u8, [u8; 16])> {
+    let mut offset = 0;
+    while offset + 2 <= key_data.len() {
+        let (kind, len) = (key_data[offset], key_data[offset + 1] as usize);
+        // 0xdd之后长度为0表示填充
+        if kind == 0xdd && len == 0 {
+            break;
+        }
+        let body = key_data.get(offset + 2..offset + 2 + len)?;
+        if kind == 0xdd && body.len() >= 6 + 16 && body[..4] == [0x00, 0x0f, 0xac, 0x01] {
+            return Some((body[4] & 0x03, body[6..22].try_into().unwrap()));
+        }
+        offset += 2 + len;
+    }
+    None
+}
+
+/// 解密统计
+#[derive(Default)]
+struct DecryptStats {
+    protected: u64,
+    decrypted: u64,
+    no_key: u64,
+    mic_errors: u64,
+    unsupported: u64,
+    handshakes: u64,
+    group_keys: u64,
+    wrong_key_handshakes: u64,
+}
+
+/// 解密状态: 按 (BSSID, 客户端) 的会话和按 (BSSID, 编号) 的组密钥
+struct Decryptor {
+    pmk: [u8; 32],
+    sessions: HashMap<(Mac, Mac), Session>,
+    group_keys: HashMap<(Mac, u8), [u8; 16]>,
+    stats: DecryptStats,
+}
+
+impl Decryptor {
+    /// 处理一个EAPOL帧: 记录随机数、推导PTK、取出GTK
+    fn handle_eapol(&mut self, bssid: Mac, sta: Mac, eapol: &[u8]) {
+        let Some(key) = parse_eapol_key(eapol) else {
+            return;
+        };
+        if key.key_info & KEY_INFO_VERSION != KEY_VERSION_AES {
+            self.stats.unsupported += 1;
+            return;
+        }
+        let pmk = self.pmk;
+        let session = self.sessions.entry((bssid, sta)).or_default();
+        let from_ap = key.key_info & KEY_INFO_ACK != 0;
+        let has_mic = key.key_info & KEY_INFO_MIC != 0;
+
+        if key.key_info & KEY_INFO_PAIRWISE == 0 {
+            // 组密钥握手的消息1: 用KEK解包新的GTK
+            if from_ap
+                && has_mic
+                && let Some(ptk) = session.ptk
+                && key.verify_mic(&ptk.kck)
+                && let Some((key_id, gtk)) = unwrap_key_data(&key, &ptk.kek).and_then(|data| find_gtk(&data))
+            {
+                self.group_keys.insert((bssid, key_id), gtk);
+                self.stats.group_keys += 1;
+            }
+            return;
+        }
+
+        match (from_ap, has_mic) {
+            // 消息1和3带AP的随机数
+            (true, _) => session.anonce = Some(key.nonce),
+            // 消息2带客户端随机数，消息4的随机数为0
+            (false, true) if key.nonce != [0; 32] => session.pending = Some((key.nonce, key.frame.to_vec())),
+            _ => return,
+        }
+
+        if let (Some(anonce), Some((snonce, frame))) = (session.anonce, session.pending.take()) {
+            let ptk = derive_ptk(&pmk, &bssid, &sta, &anonce, &snonce);
+            if parse_eapol_key(&frame).is_some_and(|message2| message2.verify_mic(&ptk.kck)) {
+                session.ptk = Some(ptk);
+                self.stats.handshakes += 1;
+            } else {
+                if !session.mic_failed {
+                    warn!(
+                        "AP {} 与客户端 {} 的握手MIC校验失败，SSID或口令可能不正确",
+                        format_mac(&bssid),
+                        format_mac(&sta)
+                    );
+                }
+                session.mic_failed = true;
+                self.stats.wrong_key_handshakes += 1;
+            }
+        }
+
+        // 消息3的密钥数据中有GTK
+        if from_ap
+            && has_mic
+            && let Some(ptk) = session.ptk
+            && key.verify_mic(&ptk.kck)
+            && let Some((key_id, gtk)) = unwrap_key_data(&key, &ptk.kek).and_then(|data| find_gtk(&data))
+        {
+            self.group_keys.insert((bssid, key_id), gtk);
+            self.stats.group_keys += 1;
+        }
+    }
+
+    /// 解密一个受保护的数据帧 (`frame` 为802.11帧，不含Radiotap头和FCS)，返回明文 (不含CCMP头和MIC)
+    fn decrypt(&mut self, frame: &[u8], header_len: usize, bssid: Mac, sta: Mac) -> Option<Vec<u8>> {
+        let ccmp = frame.get(header_len..header_len + CCMP_HEADER_LEN)?;
+        // 扩展IV位置位时为CCMP/TKIP，TKIP的密钥握手不支持，到这里的只有CCMP
+        if ccmp[3] & 0x20 == 0 || frame.len() < header_len + CCMP_HEADER_LEN + CCMP_MIC_LEN {
+            self.stats.unsupported += 1;
+            return None;
+        }
+        let key_id = ccmp[3] >> 6;
+        let group = frame[4] & 0x01 != 0;
+        let tk = if group {
+            self.group_keys.get(&(bssid, key_id)).copied()
+        } else {
+            self.sessions.get(&(bssid, sta)).and_then(|session| session.ptk).map(|ptk| ptk.tk)
+        };
+        let Some(tk) = tk else {
+            self.stats.no_key += 1;
+            return None;
+        };
+
+        let (fc0, fc1) = (frame[0], frame[1]);
+        let qos = fc0 & 0x80 != 0;
+        let four_address = fc1 & 0x03 == 0x03;
+        let qos_offset = if four_address { 30 } else { 24 };
+
+        let mut nonce = [0u8; 13];
+        nonce[0] = if qos { frame[qos_offset] & 0x0f } else { 0 };
+        nonce[1..7].copy_from_slice(&frame[10..16]);
+        nonce[7..].copy_from_slice(&[ccmp[7], ccmp[6], ccmp[5], ccmp[4], ccmp[1], ccmp[0]]);
+
+        // AAD: 屏蔽子类型低3位、重试/节能/更多数据位 (QoS帧还有Order位)，序列号置零
+        let mut aad = Vec::with_capacity(30);
+        aad.push(fc0 & 0x8f);
+        aad.push((fc1 & !(0x08 | 0x10 | 0x20 | if qos { 0x80 } else { 0 })) | FC_PROTECTED);
+        aad.extend_from_slice(&frame[4..22]);
+        aad.extend_from_slice(&[frame[22] & 0x0f, 0]);
+        if four_address {
+            aad.extend_from_slice(&frame[24..30]);
+        }
+        if qos {
+            aad.extend_from_slice(&[frame[qos_offset] & 0x0f, 0]);
+        }
+
+        match ccmp_decrypt(&tk, &nonce, &aad, &frame[header_len + CCMP_HEADER_LEN..]) {
+            Some(plaintext) => {
+                self.stats.decrypted += 1;
+                Some(plaintext)
+            }
+            None => {
+                self.stats.mic_errors += 1;
+                None
+            }
+        }
+    }
+}
+
+/// PTK = PRF-384(PMK, "Pairwise key expansion", min(AA,SPA) || max(AA,SPA) || min(ANonce,SNonce) || max(ANonce,SNonce))
+fn derive_ptk(pmk: &[u8; 32], aa: &Mac, spa: &Mac, anonce: &[u8; 32], snonce: &[u8; 32]) -> Ptk {
+    let mut data = Vec::with_capacity(76);
+    data.extend_from_slice(aa.min(spa));
+    data.extend_from_slice(aa.max(spa));
+    data.extend_from_slice(anonce.min(snonce));
+    data.extend_from_slice(anonce.max(snonce));
+    let ptk = prf(pmk, "Pairwise key expansion", &data, 48);
+    Ptk {
+        kck: ptk[..16].try_into().unwrap(),
+        kek: ptk[16..32].try_into().unwrap(),
+        tk: ptk[32..48].try_into().unwrap(),
+    }
+}
+
+/// 用KEK解包EAPOL-Key中加密的密钥数据
+fn unwrap_key_data(key: &EapolKey<'_>, kek: &[u8; 16]) -> Option<Vec<u8>> {
+    if key.key_info & KEY_INFO_ENCRYPTED_DATA == 0 {
+        return None;
+    }
+    aes_key_unwrap(kek, key.key_data)
+}
+
+/// 802.11帧的 (BSSID, 客户端) 地址，AP之间的四地址帧和IBSS帧返回None
+fn station_pair(frame: &[u8]) -> Option<(Mac, Mac)> {
+    let addr = |n: usize| -> Mac { frame[4 + n * 6..10 + n * 6].try_into().unwrap() };
+    match frame[1] & 0x03 {
+        // ToDS: 地址1为BSSID，地址2为发送的客户端
+        0x01 => Some((addr(0), addr(1))),
+        // FromDS: 地址1为接收的客户端，地址2为BSSID
+        0x02 => Some((addr(1), addr(0))),
+        _ => None,
+    }
+}
+
+fn format_mac(mac: &[u8]) -> String {
+    mac.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":")
+}
+
+/// 由口令计算PMK；64位十六进制数视为PSK本身 (与wpa_supplicant相同)
+fn derive_pmk(ssid: &str, psk: &str) -> Result<[u8; 32]> {
+    if ssid.is_empty() || ssid.len() > 32 {
+        anyhow::bail!(PcapError::InvalidArgument(format!("SSID的长度必须为1-32字节: {}", ssid)));
+    }
+    if psk.len() == 64 && psk.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let mut pmk = [0u8; 32];
+        for (i, byte) in pmk.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&psk[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        return Ok(pmk);
+    }
+    if !(8..=63).contains(&psk.len()) || !psk.bytes().all(|b| (0x20..0x7f).contains(&b)) {
+        anyhow::bail!(PcapError::InvalidArgument(
+            "口令必须为8-63个可打印ASCII字符，或64位十六进制的PSK".to_string()
+        ));
+    }
+    Ok(wpa_pmk(psk.as_bytes(), ssid.as_bytes()))
+}
+
+/// 用WPA2-PSK口令解密802.11抓包
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng，802.11或Radiotap链路类型)
+/// - `output_path`: 输出文件路径 (PCAP或pcapng)
+/// - `ssid`: 网络名称
+/// - `psk`: 口令 (8-63个字符) 或64位十六进制的PSK
+///
+/// # 说明
+/// 1. 从抓到的EAPOL四次握手推导每个客户端的PTK (需要消息1或3中的ANonce和消息2中的SNonce，
+///    并用消息2的MIC确认口令正确)，从消息3和组密钥握手中取出GTK解密广播和组播帧
+/// 2. 只支持CCMP (AES)；TKIP、WPA3-SAE、802.1X企业认证和受保护的管理帧不解密
+/// 3. 解密后的帧去掉CCMP头和MIC并清除受保护位，其他字段不变，之后可以直接使用IP层的功能或用 `decap` 转换为以太网帧；
+///    带FCS的帧重新计算FCS
+/// 4. 握手之前、没有密钥或MIC校验失败的帧原样输出
+pub fn pcap_decrypt_wifi(input_path: &str, output_path: &str, ssid: &str, psk: &str) -> Result<()> {
+    let pmk = derive_pmk(ssid, psk)?;
+    let mut pcap_reader = PacketReader::open(input_path)?;
+    let linktype: u32 = pcap_reader.header.datalink.into();
+    if linktype != LINKTYPE_IEEE802_11 && linktype != LINKTYPE_IEEE802_11_RADIOTAP {
+        anyhow::bail!(PcapError::InvalidArgument(format!(
+            "需要802.11或Radiotap输入，输入文件的链路层类型为 {}",
+            linktype_name(linktype)
+        )));
+    }
+    let out_file = create_output(output_path, compression())?;
+    let mut pcap_writer = PacketWriter::with_header(pcap_reader.header, out_file, output_path)
+        .map_err(|e| PcapError::WriteFailure(format!("创建PCAP写入器失败: {}", e)))?;
+    let mut digest = OutputDigest::default();
+
+    let mut decryptor = Decryptor { pmk, sessions: HashMap::new(), group_keys: HashMap::new(), stats: DecryptStats::default() };
+    let mut packet_count = 0u64;
+    let mut truncated = 0u64;
+    for mut packet in pcap_reader.by_ref() {
+        packet_count += 1;
+        if let Some(header) = parse_wlan_header(&packet.data, linktype) {
+            let end = packet.data.len() - header.fcs_len;
+            let frame = &packet.data[header.offset..end];
+            let payload_start = header.offset + header.header_len;
+            let pair = station_pair(frame);
+
+            if frame[1] & FC_PROTECTED == 0 {
+                if let Some((bssid, sta)) = pair
+                    && let Some(eapol) = eapol_payload(&packet.data[payload_start..end])
+                {
+                    decryptor.handle_eapol(bssid, sta, eapol);
+                }
+            } else {
+                decryptor.stats.protected += 1;
+                let plaintext = match pair {
+                    // 被截断的帧无法校验MIC
+                    Some(_) if packet.header.incl_len < packet.header.orig_len => {
+                        truncated += 1;
+                        None
+                    }
+                    Some((bssid, sta)) => decryptor.decrypt(frame, header.header_len, bssid, sta),
+                    None => {
+                        decryptor.stats.unsupported += 1;
+                        None
+                    }
+                };
+                if let (Some(plaintext), Some((bssid, sta))) = (plaintext, pair) {
+                    if let Some(eapol) = eapol_payload(&plaintext) {
+                        decryptor.handle_eapol(bssid, sta, eapol);
+                    }
+                    let mut data = packet.data[..payload_start].to_vec();
+                    data[header.offset + 1] &= !FC_PROTECTED;
+                    data.extend_from_slice(&plaintext);
+                    if header.fcs_len > 0 {
+                        let fcs = crc32(&data[header.offset..]);
+                        data.extend_from_slice(&fcs.to_le_bytes());
+                    }
+                    let removed = (CCMP_HEADER_LEN + CCMP_MIC_LEN) as u32;
+                    packet.header.incl_len = data.len() as u32;
+                    packet.header.orig_len -= removed;
+                    packet.data = data.into();
+                }
+            }
+        }
+
+        pcap_writer.write_packet(&packet)
+            .map_err(|e| PcapError::WriteFailure(format!("写入包#{}失败: {}", packet_count, e)))?;
+        digest.record_pcap(&packet);
+    }
+    pcap_reader.check_complete()?;
+    pcap_writer.into_inner().finish()?;
+    digest.verify(output_path)?;
+
+    let stats = &decryptor.stats;
+    if stats.protected > 0 && stats.handshakes == 0 && stats.wrong_key_handshakes == 0 {
+        warn!("没有抓到完整的EAPOL握手 (需要消息1或3中的ANonce和消息2中的SNonce)，无法解密");
+    }
+    if stats.no_key > 0 {
+        warn!("{} 个受保护的帧没有对应的密钥 (握手之前的帧或缺少握手的客户端)", stats.no_key);
+    }
+    if stats.mic_errors > 0 {
+        warn!("{} 个帧的MIC校验失败，保持加密", stats.mic_errors);
+    }
+    if stats.unsupported > 0 {
+        warn!("{} 个帧或握手不支持解密 (TKIP、四地址帧或IBSS)", stats.unsupported);
+    }
+    if truncated > 0 {
+        warn!("{} 个受保护的帧被截断，无法解密", truncated);
+    }
+    info!(
+        "成功解密: 包数={}, 受保护的帧={}, 解密的帧={}, 握手数={}, 组密钥数={}, 输出文件={}",
+        packet_count, stats.protected, stats.decrypted, stats.handshakes, stats.group_keys, output_path
+    );
+
+    Ok(())
+}
+
+/// LLC/SNAP封装的EAPOL帧
+fn eapol_payload(payload: &[u8]) -> Option<&[u8]> {
+    (payload.len() > 8 && payload[..6] == SNAP_HEADER && read_u16(payload, 6) == ETHERTYPE_EAPOL).then(|| &payload[8..])
+}
@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use anyhow::Result;
+use log::info;
+use seahash::hash_seeded;
+
+use crate::modules::pcap_error::PcapError;
+use crate::modules::pcap_flow_id::FlowIds;
+use crate::modules::pcap_packet_parser::{FlowKey, IP_PROTO_ICMP, IP_PROTO_ICMPV6, IP_PROTO_TCP, IP_PROTO_UDP, Transport, parse_packet};
+use crate::modules::pcap_pcapng::{CaptureReader, PcapngBlock};
+
+/// Zeek默认的不活动超时 (毫秒)
+const ZEEK_TCP_INACTIVITY_TIMEOUT_MS: u64 = 300_000;
+const ZEEK_UDP_INACTIVITY_TIMEOUT_MS: u64 = 60_000;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+/// conn.log的列 (Zeek的默认字段)
+const CONN_FIELDS: [(&str, &str); 22] = [
+    ("ts", "time"),
+    ("uid", "string"),
+    ("id.orig_h", "addr"),
+    ("id.orig_p", "port"),
+    ("id.resp_h", "addr"),
+    ("id.resp_p", "port"),
+    ("proto", "enum"),
+    ("service", "string"),
+    ("duration", "interval"),
+    ("orig_bytes", "count"),
+    ("resp_bytes", "count"),
+    ("conn_state", "string"),
+    ("local_orig", "bool"),
+    ("local_resp", "bool"),
+    ("missed_bytes", "count"),
+    ("history", "string"),
+    ("orig_pkts", "count"),
+    ("orig_ip_bytes", "count"),
+    ("resp_pkts", "count"),
+    ("resp_ip_bytes", "count"),
+    ("tunnel_parents", "set[string]"),
+    // 附加字段: 流ID，与其他命令的输出关联
+    ("flow_id", "string"),
+];
+
+/// 连接一端的统计
+#[derive(Debug, Default)]
+struct Endpoint {
+    packets: u64,
+    /// IP层字节数
+    ip_bytes: u64,
+    /// 传输层载荷字节数
+    payload_bytes: u64,
+    /// 出现过的TCP标志
+    flags: u8,
+}
+
+/// 一个双向连接，发起方为第一个包的发送方
+#[derive(Debug)]
+struct Connection {
+    /// 发起方到响应方的五元组 (ICMP的端口为0)
+    key: FlowKey,
+    /// 所属流的ID (同一五元组的多个连接相同)
+    flow_id: String,
+    /// ICMP的类型和代码 (conn.log中记在端口列)
+    icmp: Option<(u8, u8)>,
+    first_us: i64,
+    last_us: i64,
+    orig: Endpoint,
+    resp: Endpoint,
+    history: String,
+    /// 第一个RST是否由发起方发出
+    orig_reset_first: Option<bool>,
+}
+
+/// 把抓包中的连接导出为Zeek conn.log格式的TSV
+///
+/// # 参数
+/// - `input_path`: 输入文件路径 (PCAP或pcapng)
+/// - `output_path`: 输出文件路径
+/// - `idle_timeout_ms`: 连接空闲超过该时间后的包开始新连接，None时与Zeek相同 (TCP为5分钟，其他为1分钟)
+///
+/// # 功能
+/// 1. 双向的包归为一个连接，第一个包的发送方为发起方 (第一个包为SYN-ACK时反过来，历史中记为 `^`)
+/// 2. 按TCP标志推断 `conn_state` 和 `history`，载荷字节数按包的载荷长度求和 (重传的数据也计入)
+/// 3. 输出带 `#fields`/`#types` 头的TSV，可直接由 `zeek-cut` 和读取conn.log的工具处理
+pub fn pcap_export_conn_log(input_path: &str, output_path: &str, idle_timeout_ms: Option<u64>) -> Result<()> {
+    if idle_timeout_ms == Some(0) {
+        anyhow::bail!(PcapError::InvalidArgument("流超时必须大于0".to_string()));
+    }
+
+    let mut reader = CaptureReader::open(input_path)?;
+    let mut connections: Vec<Connection> = Vec::new();
+    // 发起方到响应方的五元组 -> 未结束的连接
+    let mut active: HashMap<FlowKey, usize> = HashMap::new();
+    let mut flow_ids = FlowIds::default();
+    let mut packet_count = 0u64;
+    let mut skipped = 0u64;
+    while let Some(block) = reader.next_block()? {
+        let PcapngBlock::Packet(packet) = block else {
+            continue;
+        };
+        packet_count += 1;
+        let Some(interface) = reader.interface(packet.interface_id) else {
+            anyhow::bail!(PcapError::InvalidFormat(format!("数据包引用了不存在的接口: {}", packet.interface_id)));
+        };
+        let time_us = (interface.timestamp_ns(packet.timestamp) / 1000) as i64;
+        let data = &packet.data;
+        let parsed = parse_packet(data, interface.linktype as u32);
+        let (Some(mut key), Some(ip)) = (parsed.flow_key(), parsed.ip.as_ref()) else {
+            skipped += 1;
+            continue;
+        };
+        let flow_id = flow_ids.assign(&key, time_us).to_string();
+        let ip_payload = ip.total_len.saturating_sub(ip.header_len);
+        let (flags, payload, icmp) = match parsed.transport {
+            Some(Transport::Tcp(tcp)) if tcp.offset + 14 <= data.len() => {
+                (data[tcp.offset + 13], ip_payload.saturating_sub(tcp.header_len), None)
+            }
+            Some(Transport::Udp(_)) => (0, ip_payload.saturating_sub(8), None),
+            Some(Transport::Icmp { offset }) if offset + 2 <= data.len() => {
+                // 请求和应答归为同一个连接
+                key.src_port = 0;
+                key.dst_port = 0;
+                (0, ip_payload.saturating_sub(8), Some((data[offset], data[offset + 1])))
+            }
+            _ => (0, ip_payload, None),
+        };
+
+        let timeout_ms = idle_timeout_ms.unwrap_or(if key.protocol == IP_PROTO_TCP {
+            ZEEK_TCP_INACTIVITY_TIMEOUT_MS
+        } else {
+            ZEEK_UDP_INACTIVITY_TIMEOUT_MS
+        });
+        let found = active.get(&key).or_else(|| active.get(&key.reversed())).copied();
+        let index = match found {
+            Some(index) if !connections[index].expired(time_us, timeout_ms, flags) => index,
+            found => {
+                if let Some(index) = found {
+                    active.remove(&connections[index].key);
+                }
+                let flipped = key.protocol == IP_PROTO_TCP && flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK;
+                let key = if flipped { key.reversed() } else { key };
+                connections.push(Connection {
+                    key,
+                    flow_id,
+                    icmp,
+                    first_us: time_us,
+                    last_us: time_us,
+                    orig: Endpoint::default(),
+                    resp: Endpoint::default(),
+                    history: if flipped { "^".to_string() } else { String::new() },
+                    orig_reset_first: None,
+                });
+                active.insert(key, connections.len() - 1);
+                connections.len() - 1
+            }
+        };
+
+        let connection = &mut connections[index];
+        let from_orig = connection.key.src == key.src && connection.key.src_port == key.src_port;
+        connection.first_us = connection.first_us.min(time_us);
+        connection.last_us = connection.last_us.max(time_us);
+        connection.record(from_orig, ip.total_len as u64, payload as u64, flags);
+    }
+    connections.sort_by_key(|connection| connection.first_us);
+
+    let out_file = File::create(Path::new(output_path))
+        .map_err(|e| PcapError::create_failed(output_path, e))?;
+    let mut writer = BufWriter::new(out_file);
+    write_conn_log(&mut writer, &connections)
+        .and_then(|()| writer.flush().map_err(Into::into))
+        .map_err(|e| PcapError::WriteFailure(format!("写入conn.log失败: {}: {}", output_path, e)))?;
+
+    info!(
+        "成功导出连接记录: 数据包数={}, 非IP包数={}, 连接数={}, 输出文件={}",
+        packet_count,
+        skipped,
+        connections.len(),
+        output_path
+    );
+    Ok(())
+}
+
+impl Connection {
+    /// 新的包是否应开始一个新连接: 空闲超时，或已关闭的TCP连接上出现新的SYN (端口复用)
+    fn expired(&self, time_us: i64, timeout_ms: u64, flags: u8) -> bool {
+        let idle_us = time_us.saturating_sub(self.last_us).max(0) as u64;
+        if idle_us > timeout_ms.saturating_mul(1000) {
+            return true;
+        }
+        let closed = (self.orig.flags | self.resp.flags) & (TCP_FIN | TCP_RST) != 0;
+        self.key.protocol == IP_PROTO_TCP && closed && flags & (TCP_SYN | TCP_ACK) == TCP_SYN
+    }
+
+    fn record(&mut self, from_orig: bool, ip_bytes: u64, payload: u64, flags: u8) {
+        let endpoint = if from_orig { &mut self.orig } else { &mut self.resp };
+        endpoint.packets += 1;
+        endpoint.ip_bytes += ip_bytes;
+        endpoint.payload_bytes += payload;
+        endpoint.flags |= flags;
+        if flags & TCP_RST != 0 && self.orig_reset_first.is_none() {
+            self.orig_reset_first = Some(from_orig);
+        }
+
+        // 每个事件在每个方向上只记录第一次，发起方为大写，响应方为小写
+        let mut events = Vec::new();
+        if self.key.protocol == IP_PROTO_TCP {
+            if flags & (TCP_SYN | TCP_ACK) == TCP_SYN {
+                events.push('S');
+            }
+            if flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK {
+                events.push('H');
+            }
+            if flags & (TCP_SYN | TCP_FIN | TCP_RST) == 0 && flags & TCP_ACK != 0 && payload == 0 {
+                events.push('A');
+            }
+        }
+        if payload > 0 {
+            events.push('D');
+        }
+        if self.key.protocol == IP_PROTO_TCP {
+            if flags & TCP_FIN != 0 {
+                events.push('F');
+            }
+            if flags & TCP_RST != 0 {
+                events.push('R');
+            }
+        }
+        for event in events {
+            let event = if from_orig { event } else { event.to_ascii_lowercase() };
+            if !self.history.contains(event) {
+                self.history.push(event);
+            }
+        }
+    }
+
+    fn proto(&self) -> &'static str {
+        match self.key.protocol {
+            IP_PROTO_TCP => "tcp",
+            IP_PROTO_UDP => "udp",
+            IP_PROTO_ICMP | IP_PROTO_ICMPV6 => "icmp",
+            _ => "unknown_transport",
+        }
+    }
+
+    /// Zeek的连接状态 (见Zeek文档中conn.log的 `conn_state`)
+    fn conn_state(&self) -> &'static str {
+        if self.key.protocol != IP_PROTO_TCP {
+            return if self.resp.packets > 0 { "SF" } else { "S0" };
+        }
+        let orig_syn = self.orig.flags & TCP_SYN != 0;
+        let resp_syn = self.resp.flags & TCP_SYN != 0;
+        let fin = |endpoint: &Endpoint| endpoint.flags & TCP_FIN != 0;
+        let reset_by_orig = self.orig_reset_first;
+        match (orig_syn, resp_syn) {
+            (true, true) => match reset_by_orig {
+                Some(true) => "RSTO",
+                Some(false) => "RSTR",
+                None => match (fin(&self.orig), fin(&self.resp)) {
+                    (true, true) => "SF",
+                    (true, false) => "S2",
+                    (false, true) => "S3",
+                    (false, false) => "S1",
+                },
+            },
+            (true, false) => match reset_by_orig {
+                Some(false) => "REJ",
+                Some(true) => "RSTOS0",
+                None if fin(&self.orig) => "SH",
+                None if self.resp.packets == 0 => "S0",
+                None => "OTH",
+            },
+            (false, true) => match reset_by_orig {
+                Some(false) => "RSTRH",
+                _ if fin(&self.resp) => "SHR",
+                _ => "OTH",
+            },
+            (false, false) => "OTH",
+        }
+    }
+
+    /// 连接的唯一标识: 与Zeek相同为 `C` 加base62字符，由五元组和开始时间确定
+    fn uid(&self, index: usize) -> String {
+        const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+        let mut text = format!("{:?}{}{}", self.key, self.first_us, index).into_bytes();
+        let mut uid = String::from("C");
+        for round in 0..2u64 {
+            let mut value = hash_seeded(&text, round, 0x7a65656b, 0x636f6e6e, 0);
+            for _ in 0..9 {
+                uid.push(DIGITS[(value % 62) as usize] as char);
+                value /= 62;
+            }
+            text.push(b'#');
+        }
+        uid.truncate(18);
+        uid
+    }
+}
+
+fn format_seconds(us: i64) -> String {
+    format!("{}.{:06}", us.div_euclid(1_000_000), us.rem_euclid(1_000_000))
+}
+
+fn write_conn_log(writer: &mut impl Write, connections: &[Connection]) -> Result<()> {
+    let now = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S");
+    writeln!(writer, "#separator \\x09")?;
+    writeln!(writer, "#set_separator\t,")?;
+    writeln!(writer, "#empty_field\t(empty)")?;
+    writeln!(writer, "#unset_field\t-")?;
+    writeln!(writer, "#path\tconn")?;
+    writeln!(writer, "#open\t{}", now)?;
+    let names: Vec<&str> = CONN_FIELDS.iter().map(|(name, _)| *name).collect();
+    let types: Vec<&str> = CONN_FIELDS.iter().map(|(_, kind)| *kind).collect();
+    writeln!(writer, "#fields\t{}", names.join("\t"))?;
+    writeln!(writer, "#types\t{}", types.join("\t"))?;
+
+    for (index, connection) in connections.iter().enumerate() {
+        let (orig_port, resp_port) = match connection.icmp {
+            Some((icmp_type, code)) => (icmp_type as u16, code as u16),
+            None => (connection.key.src_port, connection.key.dst_port),
+        };
+        let duration_us = connection.last_us - connection.first_us;
+        // 与Zeek相同，只有一个包 (时长为0) 时时长和载荷字节数未设置
+        let (duration, orig_bytes, resp_bytes) = if duration_us > 0 {
+            (
+                format_seconds(duration_us),
+                connection.orig.payload_bytes.to_string(),
+                connection.resp.payload_bytes.to_string(),
+            )
+        } else {
+            ("-".to_string(), "-".to_string(), "-".to_string())
+        };
+        let history = if connection.history.is_empty() { "-" } else { &connection.history };
+        let fields = [
+            format_seconds(connection.first_us),
+            connection.uid(index),
+            connection.key.src.to_string(),
+            orig_port.to_string(),
+            connection.key.dst.to_string(),
+            resp_port.to_string(),
+            connection.proto().to_string(),
+            "-".to_string(),
+            duration,
+            orig_bytes,
+            resp_bytes,
+            connection.conn_state().to_string(),
+            "-".to_string(),
+            "-".to_string(),
+            "0".to_string(),
+            history.to_string(),
+            connection.orig.packets.to_string(),
+            connection.orig.ip_bytes.to_string(),
+            connection.resp.packets.to_string(),
+            connection.resp.ip_bytes.to_string(),
+            "-".to_string(),
+            connection.flow_id.clone(),
+        ];
+        writeln!(writer, "{}", fields.join("\t"))?;
+    }
+    writeln!(writer, "#close\t{}", chrono::Local::now().format("%Y-%m-%d-%H-%M-%S"))?;
+    Ok(())
+}
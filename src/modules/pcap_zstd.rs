@@ -0,0 +1,611 @@
+use std::io::{self, Write};
+
+use crate::modules::pcap_lz77::{BitWriter, MatchFinder, MatchParams, Sequence};
+
+/// zstd帧的魔数
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// 每个块的最大解压大小
+const BLOCK_SIZE: usize = 128 * 1024;
+
+const BLOCK_RAW: u32 = 0;
+const BLOCK_COMPRESSED: u32 = 2;
+
+/// 字面量长度码的基数和额外位数
+const LITERAL_LENGTH_BASE: [u32; 36] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 20, 22, 24, 28, 32, 40, 48, 64, 128, 256, 512,
+    1024, 2048, 4096, 8192, 16384, 32768, 65536,
+];
+const LITERAL_LENGTH_EXTRA: [u32; 36] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 4, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+];
+
+/// 匹配长度码的基数和额外位数
+const MATCH_LENGTH_BASE: [u32; 53] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+    33, 34, 35, 37, 39, 41, 43, 47, 51, 59, 67, 83, 99, 131, 259, 515, 1027, 2051, 4099, 8195, 16387, 32771, 65539,
+];
+const MATCH_LENGTH_EXTRA: [u32; 53] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2,
+    3, 3, 4, 4, 5, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+];
+
+/// 预定义的FSE分布 (RFC 8878 3.1.1.3.2.2)，-1表示低概率符号
+const LITERAL_LENGTH_DISTRIBUTION: [i16; 36] = [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1, -1, -1, -1, -1,
+];
+const MATCH_LENGTH_DISTRIBUTION: [i16; 53] = [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+];
+const OFFSET_DISTRIBUTION: [i16; 29] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+];
+
+/// FSE编码表
+struct FseTable {
+    log: u32,
+    /// 按符号排列的下一个状态
+    states: Vec<u16>,
+    /// 每个符号的 (状态偏移, 输出位数的调整值)
+    symbols: Vec<(i32, u32)>,
+}
+
+impl FseTable {
+    /// 由归一化的分布构造 (符号的排布与解码器相同)
+    fn new(distribution: &[i16], log: u32) -> FseTable {
+        let size = 1usize << log;
+        let mask = size - 1;
+        let mut high = size - 1;
+        let mut table_symbols = vec![0usize; size];
+        let mut cumulative = vec![0usize; distribution.len() + 1];
+        for (symbol, &count) in distribution.iter().enumerate() {
+            if count == -1 {
+                cumulative[symbol + 1] = cumulative[symbol] + 1;
+                table_symbols[high] = symbol;
+                high -= 1;
+            } else {
+                cumulative[symbol + 1] = cumulative[symbol] + count as usize;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in distribution.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                table_symbols[position] = symbol;
+                position = (position + step) & mask;
+                while position > high {
+                    position = (position + step) & mask;
+                }
+            }
+        }
+
+        let mut states = vec![0u16; size];
+        for (state, &symbol) in table_symbols.iter().enumerate() {
+            states[cumulative[symbol]] = (size + state) as u16;
+            cumulative[symbol] += 1;
+        }
+
+        let mut total = 0i32;
+        let symbols = distribution
+            .iter()
+            .map(|&count| match count {
+                0 => (0, ((log + 1) << 16) - size as u32),
+                -1 | 1 => {
+                    total += 1;
+                    (total - 2, (log << 16) - size as u32)
+                }
+                count => {
+                    let max_bits_out = log - (count as u32 - 1).ilog2();
+                    let min_state_plus = (count as u32) << max_bits_out;
+                    total += count as i32;
+                    (total - 2 * count as i32, (max_bits_out << 16) - min_state_plus)
+                }
+            })
+            .collect();
+        FseTable { log, states, symbols }
+    }
+
+    /// 最后一个序列的符号作为初始状态
+    fn initial_state(&self, symbol: usize) -> u32 {
+        let (find_state, nb_bits) = self.symbols[symbol];
+        let bits_out = (nb_bits + (1 << 15)) >> 16;
+        let value = (bits_out << 16).wrapping_sub(nb_bits);
+        self.states[((value >> bits_out) as i32 + find_state) as usize] as u32
+    }
+
+    fn encode(&self, writer: &mut BitWriter, state: &mut u32, symbol: usize) {
+        let (find_state, nb_bits) = self.symbols[symbol];
+        let bits_out = (*state + nb_bits) >> 16;
+        writer.bits(*state, bits_out);
+        *state = self.states[((*state >> bits_out) as i32 + find_state) as usize] as u32;
+    }
+
+    fn flush(&self, writer: &mut BitWriter, state: u32) {
+        writer.bits(state, self.log);
+    }
+}
+
+/// 取值不超过 `value` 的最大基数的下标
+fn code_for(bases: &[u32], value: usize) -> usize {
+    bases.iter().rposition(|&base| base as usize <= value).unwrap()
+}
+
+/// 边写入边计算的XXH64 (种子为0)，zstd帧的内容校验和取其低32位
+struct Xxh64 {
+    accumulators: [u64; 4],
+    pending: Vec<u8>,
+    total_len: u64,
+}
+
+const PRIME64_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME64_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME64_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME64_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME64_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+fn xxh64_round(accumulator: u64, input: u64) -> u64 {
+    accumulator.wrapping_add(input.wrapping_mul(PRIME64_2)).rotate_left(31).wrapping_mul(PRIME64_1)
+}
+
+fn read_u64(data: &[u8]) -> u64 {
+    u64::from_le_bytes(data[..8].try_into().unwrap())
+}
+
+impl Xxh64 {
+    fn new() -> Xxh64 {
+        Xxh64 {
+            accumulators: [
+                PRIME64_1.wrapping_add(PRIME64_2),
+                PRIME64_2,
+                0,
+                0u64.wrapping_sub(PRIME64_1),
+            ],
+            pending: Vec::with_capacity(32),
+            total_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+        if !self.pending.is_empty() {
+            let take = (32 - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() < 32 {
+                return;
+            }
+            let stripe = std::mem::take(&mut self.pending);
+            self.stripe(&stripe);
+        }
+        let mut stripes = data.chunks_exact(32);
+        for stripe in stripes.by_ref() {
+            self.stripe(stripe);
+        }
+        self.pending.extend_from_slice(stripes.remainder());
+    }
+
+    fn stripe(&mut self, stripe: &[u8]) {
+        for (lane, accumulator) in self.accumulators.iter_mut().enumerate() {
+            *accumulator = xxh64_round(*accumulator, read_u64(&stripe[lane * 8..]));
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hash = if self.total_len >= 32 {
+            let [a, b, c, d] = self.accumulators;
+            let mut hash = a.rotate_left(1).wrapping_add(b.rotate_left(7)).wrapping_add(c.rotate_left(12)).wrapping_add(d.rotate_left(18));
+            for accumulator in self.accumulators {
+                hash = (hash ^ xxh64_round(0, accumulator)).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+            }
+            hash
+        } else {
+            PRIME64_5
+        };
+        hash = hash.wrapping_add(self.total_len);
+        let mut rest = &self.pending[..];
+        while rest.len() >= 8 {
+            hash = (hash ^ xxh64_round(0, read_u64(rest))).rotate_left(27).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            let value = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+            hash = (hash ^ value.wrapping_mul(PRIME64_1)).rotate_left(23).wrapping_mul(PRIME64_2).wrapping_add(PRIME64_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash = (hash ^ (byte as u64).wrapping_mul(PRIME64_5)).rotate_left(11).wrapping_mul(PRIME64_1);
+        }
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME64_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME64_3);
+        hash ^ (hash >> 32)
+    }
+}
+
+/// 边写入边压缩的zstd输出 (单个帧，RFC 8878)
+///
+/// 匹配和gzip一样用哈希链查找，级别 (1-19) 越高回溯窗口越大、查找越充分；
+/// 序列使用预定义的FSE分布，字面量不做熵编码。压缩后不比原始数据短的块按未压缩块写出。
+/// 帧带有内容校验和，写完后必须调用 [`ZstdEncoder::finish`] 写出最后的块和校验和。
+pub struct ZstdEncoder<W: Write> {
+    inner: W,
+    finder: MatchFinder,
+    /// 回溯窗口内的历史数据和尚未压缩的输入
+    buffer: Vec<u8>,
+    /// `buffer` 中已压缩的位置
+    processed: usize,
+    checksum: Xxh64,
+    tables: [FseTable; 3],
+}
+
+impl<W: Write> ZstdEncoder<W> {
+    /// 创建压缩器并写出帧头
+    pub fn new(mut inner: W, level: u32) -> io::Result<ZstdEncoder<W>> {
+        let level = level.clamp(1, 19);
+        let params = MatchParams {
+            window_log: (18 + level / 3).min(23),
+            min_len: 4,
+            max_len: 64 * 1024,
+            depth: 1 << (level / 2 + 1),
+            nice_len: (16 << (level / 4)).min(1024) as usize,
+            lazy: level >= 4,
+        };
+        // 帧头: 只有内容校验和标志，窗口大小为 2^window_log
+        inner.write_all(&ZSTD_MAGIC)?;
+        inner.write_all(&[0x04, ((params.window_log - 10) << 3) as u8])?;
+        Ok(ZstdEncoder {
+            inner,
+            finder: MatchFinder::new(params),
+            buffer: Vec::new(),
+            processed: 0,
+            checksum: Xxh64::new(),
+            tables: [
+                FseTable::new(&LITERAL_LENGTH_DISTRIBUTION, 6),
+                FseTable::new(&MATCH_LENGTH_DISTRIBUTION, 6),
+                FseTable::new(&OFFSET_DISTRIBUTION, 5),
+            ],
+        })
+    }
+
+    /// 压缩 `buffer[processed..end]` 为一个块并写出
+    fn compress_block(&mut self, end: usize, last: bool) -> io::Result<()> {
+        let data = &self.buffer[self.processed..end];
+        let (sequences, _) = self.finder.parse(&self.buffer, self.processed, end);
+        let compressed = self.encode_block(data, &sequences);
+        let (block_type, content) = match &compressed {
+            Some(compressed) if compressed.len() < data.len() => (BLOCK_COMPRESSED, &compressed[..]),
+            _ => (BLOCK_RAW, data),
+        };
+        let header = last as u32 | block_type << 1 | (content.len() as u32) << 3;
+        self.inner.write_all(&header.to_le_bytes()[..3])?;
+        self.inner.write_all(content)?;
+        self.processed = end;
+        self.processed -= self.finder.trim(&mut self.buffer, self.processed);
+        Ok(())
+    }
+
+    /// 按压缩块的格式编码: 未压缩的字面量段和使用预定义分布的序列段；没有匹配时返回None
+    fn encode_block(&self, data: &[u8], sequences: &[Sequence]) -> Option<Vec<u8>> {
+        if sequences.is_empty() {
+            return None;
+        }
+        let mut literals = Vec::with_capacity(data.len());
+        let mut pos = 0;
+        for sequence in sequences {
+            literals.extend_from_slice(&data[pos..pos + sequence.literal_len]);
+            pos += sequence.literal_len + sequence.match_len;
+        }
+        literals.extend_from_slice(&data[pos..]);
+
+        // 字面量段头: 类型为未压缩，按长度选择1、2或3字节的格式
+        let mut block = Vec::with_capacity(data.len());
+        let len = literals.len() as u32;
+        match len {
+            0..32 => block.push((len << 3) as u8),
+            32..4096 => block.extend_from_slice(&((len << 4) | 0b0100).to_le_bytes()[..2]),
+            _ => block.extend_from_slice(&((len << 4) | 0b1100).to_le_bytes()[..3]),
+        }
+        block.extend_from_slice(&literals);
+
+        let count = sequences.len();
+        match count {
+            0..128 => block.push(count as u8),
+            128..0x7f00 => block.extend_from_slice(&[(count >> 8) as u8 + 0x80, count as u8]),
+            _ => {
+                block.push(0xff);
+                block.extend_from_slice(&((count - 0x7f00) as u16).to_le_bytes());
+            }
+        }
+        // 三种码都使用预定义的分布
+        block.push(0);
+
+        // 序列从后向前编码，解码器从比特流的末尾开始读取
+        let codes: Vec<(usize, usize, usize)> = sequences
+            .iter()
+            .map(|sequence| {
+                (
+                    code_for(&LITERAL_LENGTH_BASE, sequence.literal_len),
+                    code_for(&MATCH_LENGTH_BASE, sequence.match_len),
+                    // 偏移值为距离 + 3 (1-3表示重复偏移)，码为其最高位
+                    (sequence.distance + 3).ilog2() as usize,
+                )
+            })
+            .collect();
+        let [literal_table, match_table, offset_table] = &self.tables;
+        let mut writer = BitWriter::default();
+        let write_extra = |writer: &mut BitWriter, sequence: &Sequence, (literal_code, match_code, offset_code): (usize, usize, usize)| {
+            writer.bits(sequence.literal_len as u32 - LITERAL_LENGTH_BASE[literal_code], LITERAL_LENGTH_EXTRA[literal_code]);
+            writer.bits(sequence.match_len as u32 - MATCH_LENGTH_BASE[match_code], MATCH_LENGTH_EXTRA[match_code]);
+            writer.bits((sequence.distance + 3) as u32 - (1 << offset_code), offset_code as u32);
+        };
+        let (literal_code, match_code, offset_code) = codes[count - 1];
+        let mut match_state = match_table.initial_state(match_code);
+        let mut offset_state = offset_table.initial_state(offset_code);
+        let mut literal_state = literal_table.initial_state(literal_code);
+        write_extra(&mut writer, &sequences[count - 1], codes[count - 1]);
+        for index in (0..count - 1).rev() {
+            let (literal_code, match_code, offset_code) = codes[index];
+            offset_table.encode(&mut writer, &mut offset_state, offset_code);
+            match_table.encode(&mut writer, &mut match_state, match_code);
+            literal_table.encode(&mut writer, &mut literal_state, literal_code);
+            write_extra(&mut writer, &sequences[index], codes[index]);
+        }
+        match_table.flush(&mut writer, match_state);
+        offset_table.flush(&mut writer, offset_state);
+        literal_table.flush(&mut writer, literal_state);
+        // 结束标记
+        writer.bits(1, 1);
+        writer.align();
+        block.extend_from_slice(&writer.take_bytes());
+        Some(block)
+    }
+
+    /// 写出剩余的数据和内容校验和
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.compress_block(self.buffer.len(), true)?;
+        self.inner.write_all(&(self.checksum.finish() as u32).to_le_bytes())?;
+        self.inner.flush()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write> Write for ZstdEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.checksum.update(buf);
+        self.buffer.extend_from_slice(buf);
+        while self.buffer.len() - self.processed >= BLOCK_SIZE {
+            self.compress_block(self.processed + BLOCK_SIZE, false)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// 只刷新已写出的完整块，未满一个块的数据在 [`ZstdEncoder::finish`] 时写出
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 逆序读取的比特流 (序列段)，从最后一个字节的结束标记之前开始
+    struct BackwardBits<'a> {
+        data: &'a [u8],
+        position: usize,
+    }
+
+    impl<'a> BackwardBits<'a> {
+        fn new(data: &'a [u8]) -> BackwardBits<'a> {
+            let last = *data.last().unwrap();
+            assert_ne!(last, 0, "缺少结束标记");
+            BackwardBits { data, position: data.len() * 8 - last.leading_zeros() as usize - 1 }
+        }
+
+        fn read(&mut self, count: u32) -> u32 {
+            self.position -= count as usize;
+            (0..count as usize).fold(0, |value, i| {
+                let bit = self.position + i;
+                value | (((self.data[bit / 8] >> (bit % 8)) & 1) as u32) << i
+            })
+        }
+    }
+
+    /// FSE解码表: 每个状态的 (符号, 读取位数, 基准值)
+    fn decode_table(distribution: &[i16], log: u32) -> Vec<(usize, u32, u32)> {
+        let size = 1usize << log;
+        let mut symbols = vec![0usize; size];
+        let mut high = size - 1;
+        let mut next: Vec<u32> = distribution.iter().map(|&count| count.max(1) as u32).collect();
+        for (symbol, &count) in distribution.iter().enumerate() {
+            if count == -1 {
+                symbols[high] = symbol;
+                high -= 1;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in distribution.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                symbols[position] = symbol;
+                position = (position + step) & (size - 1);
+                while position > high {
+                    position = (position + step) & (size - 1);
+                }
+            }
+        }
+        symbols
+            .into_iter()
+            .map(|symbol| {
+                let state = next[symbol];
+                next[symbol] += 1;
+                let bits = log - state.ilog2();
+                (symbol, bits, (state << bits) - size as u32)
+            })
+            .collect()
+    }
+
+    /// 只支持编码器使用的子集: 未压缩块，以及未压缩字面量和预定义分布的压缩块
+    fn decompress(frame: &[u8]) -> Vec<u8> {
+        assert_eq!(frame[..4], ZSTD_MAGIC);
+        assert_eq!(frame[4], 0x04, "帧头应只带内容校验和标志");
+        let tables = [
+            decode_table(&LITERAL_LENGTH_DISTRIBUTION, 6),
+            decode_table(&OFFSET_DISTRIBUTION, 5),
+            decode_table(&MATCH_LENGTH_DISTRIBUTION, 6),
+        ];
+        let mut output = Vec::new();
+        let mut pos = 6;
+        loop {
+            let header = u32::from_le_bytes([frame[pos], frame[pos + 1], frame[pos + 2], 0]);
+            let (last, block_type, size) = (header & 1 == 1, (header >> 1) & 3, (header >> 3) as usize);
+            let block = &frame[pos + 3..pos + 3 + size];
+            pos += 3 + size;
+            match block_type {
+                BLOCK_RAW => output.extend_from_slice(block),
+                BLOCK_COMPRESSED => decompress_block(block, &tables, &mut output),
+                other => panic!("不支持的块类型 {}", other),
+            }
+            if last {
+                break;
+            }
+        }
+        let mut checksum = Xxh64::new();
+        checksum.update(&output);
+        assert_eq!(frame[pos..], (checksum.finish() as u32).to_le_bytes(), "内容校验和不符");
+        output
+    }
+
+    fn decompress_block(block: &[u8], [literal_table, offset_table, match_table]: &[Vec<(usize, u32, u32)>; 3], output: &mut Vec<u8>) {
+        assert_eq!(block[0] & 3, 0, "字面量应为未压缩格式");
+        let (literal_len, header_len) = match (block[0] >> 2) & 3 {
+            1 => ((block[0] as usize >> 4) | (block[1] as usize) << 4, 2),
+            3 => ((block[0] as usize >> 4) | (block[1] as usize) << 4 | (block[2] as usize) << 12, 3),
+            _ => (block[0] as usize >> 3, 1),
+        };
+        let literals = &block[header_len..header_len + literal_len];
+        let mut pos = header_len + literal_len;
+        let count = match block[pos] {
+            0 => 0,
+            byte @ 1..128 => {
+                pos += 1;
+                byte as usize
+            }
+            255 => {
+                pos += 3;
+                block[pos - 2] as usize + ((block[pos - 1] as usize) << 8) + 0x7f00
+            }
+            byte => {
+                pos += 2;
+                ((byte as usize - 128) << 8) + block[pos - 1] as usize
+            }
+        };
+        assert_eq!(block[pos], 0, "应使用预定义分布");
+        let mut bits = BackwardBits::new(&block[pos + 1..]);
+        let mut literal_state = bits.read(6) as usize;
+        let mut offset_state = bits.read(5) as usize;
+        let mut match_state = bits.read(6) as usize;
+        let mut literal_pos = 0;
+        for index in 0..count {
+            let offset_code = offset_table[offset_state].0;
+            let match_code = match_table[match_state].0;
+            let literal_code = literal_table[literal_state].0;
+            let offset_value = (1 << offset_code) + bits.read(offset_code as u32) as usize;
+            let match_len = MATCH_LENGTH_BASE[match_code] as usize + bits.read(MATCH_LENGTH_EXTRA[match_code]) as usize;
+            let sequence_literals = LITERAL_LENGTH_BASE[literal_code] as usize + bits.read(LITERAL_LENGTH_EXTRA[literal_code]) as usize;
+            if index + 1 < count {
+                for (state, table) in [(&mut literal_state, literal_table), (&mut match_state, match_table), (&mut offset_state, offset_table)] {
+                    let (_, count, base) = table[*state];
+                    *state = (base + bits.read(count)) as usize;
+                }
+            }
+
+            output.extend_from_slice(&literals[literal_pos..literal_pos + sequence_literals]);
+            literal_pos += sequence_literals;
+            // 编码器不使用重复偏移
+            assert!(offset_value > 3);
+            let start = output.len() - (offset_value - 3);
+            for i in 0..match_len {
+                output.push(output[start + i]);
+            }
+        }
+        assert_eq!(bits.position, 0, "序列段有多余的比特");
+        output.extend_from_slice(&literals[literal_pos..]);
+    }
+
+    /// 可压缩的测试数据: 重复的类包头结构中夹杂伪随机字节
+    fn sample_data(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            data.extend_from_slice(b"\x45\x00\x00\x54\x12\x34\x40\x00\x40\x11");
+            data.extend_from_slice(&state.to_le_bytes()[..(state % 9) as usize]);
+        }
+        data.truncate(len);
+        data
+    }
+
+    fn compress(data: &[u8], level: u32) -> Vec<u8> {
+        let mut encoder = ZstdEncoder::new(Vec::new(), level).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap();
+        std::mem::take(encoder.get_mut())
+    }
+
+    #[test]
+    fn xxh64_matches_reference() {
+        for (input, expected) in [
+            (&b""[..], 0xef46_db37_51d8_e999u64),
+            (b"a", 0xd24e_c4f1_a98c_6e5b),
+            (b"abc", 0x44bc_2cf5_ad77_0999),
+        ] {
+            let mut hash = Xxh64::new();
+            hash.update(input);
+            assert_eq!(hash.finish(), expected);
+        }
+
+        // 分多次写入与一次写入相同
+        let data = sample_data(1000);
+        let mut whole = Xxh64::new();
+        whole.update(&data);
+        let mut pieces = Xxh64::new();
+        for chunk in data.chunks(7) {
+            pieces.update(chunk);
+        }
+        assert_eq!(whole.finish(), pieces.finish());
+    }
+
+    #[test]
+    fn round_trips_all_levels() {
+        // 跨越多个块，覆盖块边界处的匹配
+        let data = sample_data(2 * BLOCK_SIZE + 4321);
+        for level in [1, 3, 9, 19] {
+            let compressed = compress(&data, level);
+            assert!(compressed.len() < data.len(), "级别 {} 没有压缩", level);
+            assert_eq!(decompress(&compressed), data, "级别 {}", level);
+        }
+    }
+
+    #[test]
+    fn round_trips_short_and_incompressible_input() {
+        assert_eq!(decompress(&compress(b"", 3)), b"");
+        assert_eq!(decompress(&compress(b"abcabcabcabcabcabc", 3)), b"abcabcabcabcabcabc");
+
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..100_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        assert_eq!(decompress(&compress(&noise, 3)), noise);
+    }
+}